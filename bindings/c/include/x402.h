@@ -0,0 +1,121 @@
+#ifndef X402_H
+#define X402_H
+
+/* Generated by cbindgen from src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of an x402 call
+//
+// Values are stable; new codes are only ever added.
+typedef enum X402Status {
+  X402_STATUS_OK = 0,
+  // A required pointer argument was null
+  X402_STATUS_NULL_POINTER = 1,
+  // A string argument was not valid UTF-8
+  X402_STATUS_INVALID_UTF8 = 2,
+  // A header or JSON argument could not be decoded
+  X402_STATUS_DECODE = 3,
+  // The signature is invalid, or doesn't match the requirements
+  X402_STATUS_INVALID_SIGNATURE = 4,
+  // The payment has expired, or is valid for too long
+  X402_STATUS_EXPIRED = 5,
+  // The payment is for less than the required amount
+  X402_STATUS_INSUFFICIENT_AMOUNT = 6,
+  // Any other problem with the request or payment
+  X402_STATUS_INVALID = 7,
+  // Misconfiguration or a bug on the serving side
+  X402_STATUS_SERVER = 8,
+  // A temporary condition; retry later
+  X402_STATUS_TRANSIENT = 9,
+  // The library panicked; this is a bug
+  X402_STATUS_PANIC = 10,
+} X402Status;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Encode requirements (wire-format JSON) as an `X-Payment-Requirements`
+// header value
+//
+// # Safety
+// `requirements_json` must be a valid NUL-terminated string and
+// `out_header` valid for a pointer write.
+enum X402Status x402_encode_requirements(const char *requirements_json, char **out_header);
+
+// Decode an `X-Payment-Requirements` header value to wire-format JSON
+//
+// # Safety
+// `header` must be a valid NUL-terminated string and `out_json` valid for
+// a pointer write.
+enum X402Status x402_decode_requirements(const char *header, char **out_json);
+
+// Encode a payload (wire-format JSON) and its 65-byte signature as an
+// `X-Payment` header value
+//
+// # Safety
+// `payment_json` must be a valid NUL-terminated string, `signature` valid
+// for reads of `signature_len` bytes, and `out_header` valid for a pointer
+// write.
+enum X402Status x402_encode_payment(const char *payment_json,
+                                    const uint8_t *signature,
+                                    size_t signature_len,
+                                    char **out_header);
+
+// Decode an `X-Payment` header value to the signed payment's wire-format
+// JSON (`{"payment": {...}, "signature": [...]}`)
+//
+// # Safety
+// `header` must be a valid NUL-terminated string and `out_json` valid for
+// a pointer write.
+enum X402Status x402_decode_payment(const char *header, char **out_json);
+
+// Verify an `X-Payment` header value against requirements (wire-format
+// JSON), writing the payer's address on success
+//
+// # Safety
+// `header` and `requirements_json` must be valid NUL-terminated strings
+// and `out_payer` valid for a pointer write.
+enum X402Status x402_verify_payment(const char *header,
+                                    const char *requirements_json,
+                                    char **out_payer);
+
+// [`x402_verify_payment`] as of the unix time `now`
+//
+// # Safety
+// As for [`x402_verify_payment`].
+enum X402Status x402_verify_payment_at(const char *header,
+                                       const char *requirements_json,
+                                       uint64_t now,
+                                       char **out_payer);
+
+// Write the protocol surface compiled into this library (schemes,
+// networks, signature algorithms, wire formats and features) as JSON
+//
+// # Safety
+// `out_json` must be valid for a pointer write.
+enum X402Status x402_capabilities(char **out_json);
+
+// Message of the last failed call on this thread, or null if the last
+// call succeeded
+//
+// The string is owned by the library and valid until the next x402 call on
+// this thread; do not free it.
+const char *x402_last_error(void);
+
+// Free a string returned through an out-parameter
+//
+// # Safety
+// `s` must be null or a string returned by this library, not yet freed.
+void x402_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* X402_H */
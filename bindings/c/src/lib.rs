@@ -0,0 +1,338 @@
+//! C bindings for x402-core
+//!
+//! A small, stable `extern "C"` API over the protocol: encode and decode
+//! the `X-Payment-Requirements` and `X-Payment` headers, and verify
+//! payments. Structured values cross the boundary as JSON strings in the
+//! wire format.
+//!
+//! Every function returns an [`X402Status`] and writes its result through
+//! an out-parameter. Strings returned this way are owned by the caller and
+//! must be released with [`x402_string_free`]. When a call fails,
+//! [`x402_last_error`] describes why. `include/x402.h` is generated from
+//! this file by cbindgen.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use x402_core::{
+    capabilities, decode_payment_header, decode_requirements_header, encode_payment_header,
+    encode_requirements_header, verify_payment, verify_payment_at, ErrorCategory, PaymentPayload,
+    PaymentRequirements, SignedPayment, X402Error,
+};
+
+/// Result of an x402 call
+///
+/// Values are stable; new codes are only ever added.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X402Status {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// A header or JSON argument could not be decoded
+    Decode = 3,
+    /// The signature is invalid, or doesn't match the requirements
+    InvalidSignature = 4,
+    /// The payment has expired, or is valid for too long
+    Expired = 5,
+    /// The payment is for less than the required amount
+    InsufficientAmount = 6,
+    /// Any other problem with the request or payment
+    Invalid = 7,
+    /// Misconfiguration or a bug on the serving side
+    Server = 8,
+    /// A temporary condition; retry later
+    Transient = 9,
+    /// The library panicked; this is a bug
+    Panic = 10,
+}
+
+impl From<&X402Error> for X402Status {
+    fn from(e: &X402Error) -> Self {
+        use X402Error::*;
+        match e {
+            InvalidHeader(_) | Base64(_) | Json(_) | Cbor(_) | UnsupportedVersion(_) | EncodingError(_)
+            | LimitExceeded { .. } => X402Status::Decode,
+            InvalidSignature(_) | Ecdsa(_) | InvalidAddress(_) => X402Status::InvalidSignature,
+            PaymentExpired | ValidityTooLong { .. } => X402Status::Expired,
+            InsufficientAmount { .. } => X402Status::InsufficientAmount,
+            _ => match e.category() {
+                ErrorCategory::Server => X402Status::Server,
+                ErrorCategory::Transient => X402Status::Transient,
+                _ => X402Status::Invalid,
+            },
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Interior NULs can't cross into C; drop them rather than the message
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Failure of one call: its status and message
+struct Failure(X402Status, String);
+
+impl From<X402Error> for Failure {
+    fn from(e: X402Error) -> Self {
+        Failure(X402Status::from(&e), e.to_string())
+    }
+}
+
+/// Run `f`, recording any failure (or panic) for `x402_last_error`
+fn run(f: impl FnOnce() -> Result<(), Failure>) -> X402Status {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = None);
+            X402Status::Ok
+        }
+        Ok(Err(Failure(status, message))) => {
+            set_last_error(message);
+            status
+        }
+        Err(_) => {
+            set_last_error("x402 panicked".to_string());
+            X402Status::Panic
+        }
+    }
+}
+
+/// Borrow a C string argument
+///
+/// # Safety
+/// `s` must be null or a valid NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if s.is_null() {
+        return Err(Failure(X402Status::NullPointer, format!("{} is null", name)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| Failure(X402Status::InvalidUtf8, format!("{} is not UTF-8: {}", name, e)))
+}
+
+fn json_arg<T: serde::de::DeserializeOwned>(json: &str, name: &str) -> Result<T, Failure> {
+    serde_json::from_str(json).map_err(|e| Failure(X402Status::Decode, format!("invalid {} JSON: {}", name, e)))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, Failure> {
+    serde_json::to_string(value).map_err(|e| Failure(X402Status::Server, e.to_string()))
+}
+
+/// Hand `value` to the caller through `out`
+///
+/// # Safety
+/// `out` must be null or valid for a pointer write.
+unsafe fn write_out(out: *mut *mut c_char, value: String) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(Failure(X402Status::NullPointer, "output pointer is null".to_string()));
+    }
+    let value = CString::new(value).map_err(|e| Failure(X402Status::Server, e.to_string()))?;
+    *out = value.into_raw();
+    Ok(())
+}
+
+/// Encode requirements (wire-format JSON) as an `X-Payment-Requirements`
+/// header value
+///
+/// # Safety
+/// `requirements_json` must be a valid NUL-terminated string and
+/// `out_header` valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn x402_encode_requirements(
+    requirements_json: *const c_char,
+    out_header: *mut *mut c_char,
+) -> X402Status {
+    run(|| {
+        let requirements: PaymentRequirements = json_arg(str_arg(requirements_json, "requirements_json")?, "requirements")?;
+        write_out(out_header, encode_requirements_header(&requirements)?)
+    })
+}
+
+/// Decode an `X-Payment-Requirements` header value to wire-format JSON
+///
+/// # Safety
+/// `header` must be a valid NUL-terminated string and `out_json` valid for
+/// a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn x402_decode_requirements(header: *const c_char, out_json: *mut *mut c_char) -> X402Status {
+    run(|| {
+        let requirements = decode_requirements_header(str_arg(header, "header")?)?;
+        write_out(out_json, to_json(&requirements)?)
+    })
+}
+
+/// Encode a payload (wire-format JSON) and its 65-byte signature as an
+/// `X-Payment` header value
+///
+/// # Safety
+/// `payment_json` must be a valid NUL-terminated string, `signature` valid
+/// for reads of `signature_len` bytes, and `out_header` valid for a pointer
+/// write.
+#[no_mangle]
+pub unsafe extern "C" fn x402_encode_payment(
+    payment_json: *const c_char,
+    signature: *const u8,
+    signature_len: usize,
+    out_header: *mut *mut c_char,
+) -> X402Status {
+    run(|| {
+        let payment: PaymentPayload = json_arg(str_arg(payment_json, "payment_json")?, "payment")?;
+        if signature.is_null() {
+            return Err(Failure(X402Status::NullPointer, "signature is null".to_string()));
+        }
+        let signature = std::slice::from_raw_parts(signature, signature_len).to_vec();
+        write_out(out_header, encode_payment_header(&SignedPayment { payment, signature, signatures: Vec::new() })?)
+    })
+}
+
+/// Decode an `X-Payment` header value to the signed payment's wire-format
+/// JSON (`{"payment": {...}, "signature": [...]}`)
+///
+/// # Safety
+/// `header` must be a valid NUL-terminated string and `out_json` valid for
+/// a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn x402_decode_payment(header: *const c_char, out_json: *mut *mut c_char) -> X402Status {
+    run(|| {
+        let payment = decode_payment_header(str_arg(header, "header")?)?;
+        write_out(out_json, to_json(&payment)?)
+    })
+}
+
+/// Verify an `X-Payment` header value against requirements (wire-format
+/// JSON), writing the payer's address on success
+///
+/// # Safety
+/// `header` and `requirements_json` must be valid NUL-terminated strings
+/// and `out_payer` valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn x402_verify_payment(
+    header: *const c_char,
+    requirements_json: *const c_char,
+    out_payer: *mut *mut c_char,
+) -> X402Status {
+    run(|| {
+        let (payment, requirements) = verify_args(header, requirements_json)?;
+        write_out(out_payer, verify_payment(&payment, &requirements)?.to_checksum(None))
+    })
+}
+
+/// [`x402_verify_payment`] as of the unix time `now`
+///
+/// # Safety
+/// As for [`x402_verify_payment`].
+#[no_mangle]
+pub unsafe extern "C" fn x402_verify_payment_at(
+    header: *const c_char,
+    requirements_json: *const c_char,
+    now: u64,
+    out_payer: *mut *mut c_char,
+) -> X402Status {
+    run(|| {
+        let (payment, requirements) = verify_args(header, requirements_json)?;
+        write_out(out_payer, verify_payment_at(&payment, &requirements, now)?.to_checksum(None))
+    })
+}
+
+/// # Safety
+/// `header` and `requirements_json` must be null or valid NUL-terminated
+/// strings.
+unsafe fn verify_args(
+    header: *const c_char,
+    requirements_json: *const c_char,
+) -> Result<(SignedPayment, PaymentRequirements), Failure> {
+    let payment = decode_payment_header(str_arg(header, "header")?)?;
+    let requirements = json_arg(str_arg(requirements_json, "requirements_json")?, "requirements")?;
+    Ok((payment, requirements))
+}
+
+/// Write the protocol surface compiled into this library (schemes,
+/// networks, signature algorithms, wire formats and features) as JSON
+///
+/// # Safety
+/// `out_json` must be valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn x402_capabilities(out_json: *mut *mut c_char) -> X402Status {
+    run(|| write_out(out_json, to_json(&capabilities())?))
+}
+
+/// Message of the last failed call on this thread, or null if the last
+/// call succeeded
+///
+/// The string is owned by the library and valid until the next x402 call on
+/// this thread; do not free it.
+#[no_mangle]
+pub extern "C" fn x402_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Free a string returned through an out-parameter
+///
+/// # Safety
+/// `s` must be null or a string returned by this library, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn x402_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(s: *mut c_char) -> String {
+        let value = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { x402_string_free(s) };
+        value
+    }
+
+    #[test]
+    fn test_requirements_round_trip() {
+        let requirements = CString::new(
+            r#"{"amount":"1000","recipient":"0x1111111111111111111111111111111111111111","network":"base","resource":"/api"}"#,
+        )
+        .unwrap();
+        let mut header = ptr::null_mut();
+        assert_eq!(unsafe { x402_encode_requirements(requirements.as_ptr(), &mut header) }, X402Status::Ok);
+        assert!(x402_last_error().is_null());
+
+        let header = CString::new(take(header)).unwrap();
+        let mut json = ptr::null_mut();
+        assert_eq!(unsafe { x402_decode_requirements(header.as_ptr(), &mut json) }, X402Status::Ok);
+        assert!(take(json).contains(r#""amount":"1000""#));
+    }
+
+    #[test]
+    fn test_errors_set_status_and_message() {
+        let mut out = ptr::null_mut();
+        assert_eq!(unsafe { x402_decode_payment(ptr::null(), &mut out) }, X402Status::NullPointer);
+        assert!(out.is_null());
+
+        let garbage = CString::new("not a header").unwrap();
+        assert_eq!(unsafe { x402_decode_payment(garbage.as_ptr(), &mut out) }, X402Status::Decode);
+        assert!(!x402_last_error().is_null());
+
+        let requirements = CString::new("{}").unwrap();
+        let status = unsafe { x402_verify_payment(garbage.as_ptr(), requirements.as_ptr(), &mut out) };
+        assert_eq!(status, X402Status::Decode);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut json = ptr::null_mut();
+        assert_eq!(unsafe { x402_capabilities(&mut json) }, X402Status::Ok);
+        let parsed: x402_core::Capabilities = serde_json::from_str(&take(json)).unwrap();
+        assert_eq!(parsed, capabilities());
+        assert_eq!(unsafe { x402_capabilities(ptr::null_mut()) }, X402Status::NullPointer);
+    }
+}
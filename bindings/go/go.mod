@@ -0,0 +1,3 @@
+module github.com/girderdev/x402-sdk/bindings/go
+
+go 1.21
@@ -0,0 +1,139 @@
+package x402
+
+import (
+	"encoding/json"
+	"fmt"
+	"math/big"
+)
+
+// Network names in the wire format.
+const (
+	NetworkEthereum        = "ethereum"
+	NetworkSepolia         = "sepolia"
+	NetworkBase            = "base"
+	NetworkBaseSepolia     = "basesepolia"
+	NetworkArbitrum        = "arbitrum"
+	NetworkArbitrumSepolia = "arbitrumsepolia"
+	NetworkOptimism        = "optimism"
+	NetworkOptimismSepolia = "optimismsepolia"
+	NetworkPolygon         = "polygon"
+	NetworkPolygonAmoy     = "polygonamoy"
+	NetworkAvalanche       = "avalanche"
+	NetworkAvalancheFuji   = "avalanchefuji"
+	NetworkBNB             = "bnb"
+	NetworkBNBTestnet      = "bnbtestnet"
+	NetworkZkSync          = "zksync"
+	NetworkZkSyncSepolia   = "zksyncsepolia"
+	NetworkLinea           = "linea"
+	NetworkLineaSepolia    = "lineasepolia"
+	NetworkScroll          = "scroll"
+	NetworkScrollSepolia   = "scrollsepolia"
+)
+
+// Amount is a token amount in its smallest unit. It is encoded as a
+// decimal string, since JSON numbers lose precision above 2^53.
+type Amount struct {
+	big.Int
+}
+
+// NewAmount returns an Amount of v.
+func NewAmount(v uint64) Amount {
+	var a Amount
+	a.SetUint64(v)
+	return a
+}
+
+// MarshalJSON encodes the amount as a decimal string.
+func (a Amount) MarshalJSON() ([]byte, error) {
+	return json.Marshal(a.String())
+}
+
+// UnmarshalJSON accepts decimal strings and integer numbers.
+func (a *Amount) UnmarshalJSON(data []byte) error {
+	var s string
+	if err := json.Unmarshal(data, &s); err != nil {
+		s = string(data)
+	}
+	if _, ok := a.SetString(s, 10); !ok {
+		return fmt.Errorf("x402: invalid amount %s", data)
+	}
+	return nil
+}
+
+// Split is one output of a split payment.
+type Split struct {
+	Recipient string `json:"recipient"`
+	Share     Amount `json:"share"`
+}
+
+// PaymentRequirements are what a server asks for in a 402 response.
+type PaymentRequirements struct {
+	Amount            Amount          `json:"amount"`
+	Recipient         string          `json:"recipient"`
+	Network           string          `json:"network"`
+	Token             *string         `json:"token"`
+	Description       *string         `json:"description"`
+	ExpiresAt         *uint64         `json:"expiresAt"`
+	Resource          string          `json:"resource"`
+	Commitment        json.RawMessage `json:"commitment,omitempty"`
+	Scheme            string          `json:"scheme,omitempty"`
+	MimeType          *string         `json:"mimeType,omitempty"`
+	OutputSchema      json.RawMessage `json:"outputSchema,omitempty"`
+	MaxTimeoutSeconds *uint64         `json:"maxTimeoutSeconds,omitempty"`
+	Extra             map[string]any  `json:"extra,omitempty"`
+	Splits            []Split         `json:"splits,omitempty"`
+	PriceQuote        json.RawMessage `json:"priceQuote,omitempty"`
+}
+
+// PaymentPayload is the payment a client signs.
+type PaymentPayload struct {
+	Amount    Amount         `json:"amount"`
+	Recipient string         `json:"recipient"`
+	Payer     string         `json:"payer"`
+	ChainID   uint64         `json:"chainId"`
+	Token     *string        `json:"token"`
+	Resource  string         `json:"resource"`
+	Nonce     uint64         `json:"nonce"`
+	ExpiresAt uint64         `json:"expiresAt"`
+	Scheme    string         `json:"scheme,omitempty"`
+	Extra     map[string]any `json:"extra,omitempty"`
+	InvoiceID *string        `json:"invoiceId,omitempty"`
+	Splits    []Split        `json:"splits,omitempty"`
+}
+
+// SignedPayment is a payload and its 65-byte signature (r + s + v).
+type SignedPayment struct {
+	Payment   PaymentPayload
+	Signature []byte
+}
+
+// signedPaymentJSON is SignedPayment in the wire format, where the
+// signature is an array of byte values.
+type signedPaymentJSON struct {
+	Payment   PaymentPayload `json:"payment"`
+	Signature []uint16       `json:"signature"`
+}
+
+func (p *signedPaymentJSON) signedPayment() (*SignedPayment, error) {
+	signature := make([]byte, len(p.Signature))
+	for i, b := range p.Signature {
+		if b > 0xff {
+			return nil, fmt.Errorf("x402: invalid signature byte %d", b)
+		}
+		signature[i] = byte(b)
+	}
+	return &SignedPayment{Payment: p.Payment, Signature: signature}, nil
+}
+
+// Capabilities is the protocol surface an x402 SDK build supports, in the
+// JSON shape every SDK's capabilities output shares.
+type Capabilities struct {
+	SDKVersion          string   `json:"sdkVersion"`
+	X402Version         uint32   `json:"x402Version"`
+	HeaderFormatVersion uint32   `json:"headerFormatVersion"`
+	Schemes             []string `json:"schemes"`
+	Networks            []string `json:"networks"`
+	SignatureAlgorithms []string `json:"signatureAlgorithms"`
+	WireFormats         []string `json:"wireFormats"`
+	Features            []string `json:"features"`
+}
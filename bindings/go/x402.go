@@ -0,0 +1,216 @@
+// Package x402 is a Go wrapper over the x402 C API (bindings/c).
+//
+// It encodes and decodes the X-Payment-Requirements and X-Payment headers
+// and verifies payments with the Rust x402 core, which it links
+// statically. Build the C library first:
+//
+//	cargo build --release --manifest-path ../c/Cargo.toml
+package x402
+
+/*
+#cgo CFLAGS: -I${SRCDIR}/../c/include
+#cgo LDFLAGS: ${SRCDIR}/../c/target/release/libx402.a -ldl -lm -lpthread
+#cgo darwin LDFLAGS: -framework Security -framework CoreFoundation
+#include <stdlib.h>
+#include "x402.h"
+*/
+import "C"
+
+import (
+	"encoding/json"
+	"fmt"
+	"runtime"
+	"time"
+	"unsafe"
+)
+
+// Header names.
+const (
+	RequirementsHeader = "X-Payment-Requirements"
+	PaymentHeader      = "X-Payment"
+)
+
+// Status is the result code of a call into the C API.
+type Status int
+
+// Status codes, as in x402.h.
+const (
+	StatusOK                 Status = Status(C.X402_STATUS_OK)
+	StatusNullPointer        Status = Status(C.X402_STATUS_NULL_POINTER)
+	StatusInvalidUTF8        Status = Status(C.X402_STATUS_INVALID_UTF8)
+	StatusDecode             Status = Status(C.X402_STATUS_DECODE)
+	StatusInvalidSignature   Status = Status(C.X402_STATUS_INVALID_SIGNATURE)
+	StatusExpired            Status = Status(C.X402_STATUS_EXPIRED)
+	StatusInsufficientAmount Status = Status(C.X402_STATUS_INSUFFICIENT_AMOUNT)
+	StatusInvalid            Status = Status(C.X402_STATUS_INVALID)
+	StatusServer             Status = Status(C.X402_STATUS_SERVER)
+	StatusTransient          Status = Status(C.X402_STATUS_TRANSIENT)
+	StatusPanic              Status = Status(C.X402_STATUS_PANIC)
+)
+
+// Error is a failed x402 call.
+type Error struct {
+	Status  Status
+	Message string
+}
+
+func (e *Error) Error() string {
+	if e.Message == "" {
+		return fmt.Sprintf("x402: status %d", e.Status)
+	}
+	return "x402: " + e.Message
+}
+
+// Is reports whether target is the sentinel error for e's status, so
+// callers can write errors.Is(err, x402.ErrExpired).
+func (e *Error) Is(target error) bool {
+	t, ok := target.(*Error)
+	return ok && t.Message == "" && t.Status == e.Status
+}
+
+// Sentinel errors for errors.Is.
+var (
+	ErrDecode             = &Error{Status: StatusDecode}
+	ErrInvalidSignature   = &Error{Status: StatusInvalidSignature}
+	ErrExpired            = &Error{Status: StatusExpired}
+	ErrInsufficientAmount = &Error{Status: StatusInsufficientAmount}
+	ErrInvalid            = &Error{Status: StatusInvalid}
+	ErrTransient          = &Error{Status: StatusTransient}
+)
+
+// call runs f, which writes a string through its out-parameter, and
+// returns that string or the call's error.
+func call(f func(out **C.char) C.X402Status) (string, error) {
+	// The error message is thread-local; read it on the same thread
+	runtime.LockOSThread()
+	defer runtime.UnlockOSThread()
+
+	var out *C.char
+	if status := Status(f(&out)); status != StatusOK {
+		var message string
+		if msg := C.x402_last_error(); msg != nil {
+			message = C.GoString(msg)
+		}
+		return "", &Error{Status: status, Message: message}
+	}
+	defer C.x402_string_free(out)
+	return C.GoString(out), nil
+}
+
+// marshal encodes v as a JSON C string; free it with C.free.
+func marshal(v any) (*C.char, error) {
+	data, err := json.Marshal(v)
+	if err != nil {
+		return nil, err
+	}
+	return C.CString(string(data)), nil
+}
+
+// EncodeRequirements encodes requirements as an X-Payment-Requirements
+// header value.
+func EncodeRequirements(requirements *PaymentRequirements) (string, error) {
+	r, err := marshal(requirements)
+	if err != nil {
+		return "", err
+	}
+	defer C.free(unsafe.Pointer(r))
+	return call(func(out **C.char) C.X402Status {
+		return C.x402_encode_requirements(r, out)
+	})
+}
+
+// DecodeRequirements decodes an X-Payment-Requirements header value.
+func DecodeRequirements(header string) (*PaymentRequirements, error) {
+	h := C.CString(header)
+	defer C.free(unsafe.Pointer(h))
+	data, err := call(func(out **C.char) C.X402Status {
+		return C.x402_decode_requirements(h, out)
+	})
+	if err != nil {
+		return nil, err
+	}
+	var requirements PaymentRequirements
+	if err := json.Unmarshal([]byte(data), &requirements); err != nil {
+		return nil, err
+	}
+	return &requirements, nil
+}
+
+// EncodePayment encodes a signed payment as an X-Payment header value.
+func EncodePayment(payment *SignedPayment) (string, error) {
+	p, err := marshal(&payment.Payment)
+	if err != nil {
+		return "", err
+	}
+	defer C.free(unsafe.Pointer(p))
+	signature := C.CBytes(payment.Signature)
+	defer C.free(signature)
+	return call(func(out **C.char) C.X402Status {
+		return C.x402_encode_payment(p, (*C.uint8_t)(signature), C.size_t(len(payment.Signature)), out)
+	})
+}
+
+// DecodePayment decodes an X-Payment header value.
+func DecodePayment(header string) (*SignedPayment, error) {
+	h := C.CString(header)
+	defer C.free(unsafe.Pointer(h))
+	data, err := call(func(out **C.char) C.X402Status {
+		return C.x402_decode_payment(h, out)
+	})
+	if err != nil {
+		return nil, err
+	}
+	var payment signedPaymentJSON
+	if err := json.Unmarshal([]byte(data), &payment); err != nil {
+		return nil, err
+	}
+	return payment.signedPayment()
+}
+
+// VerifyPayment verifies an X-Payment header value against requirements
+// and returns the payer's address.
+func VerifyPayment(header string, requirements *PaymentRequirements) (string, error) {
+	return verify(header, requirements, func(h, r *C.char, out **C.char) C.X402Status {
+		return C.x402_verify_payment(h, r, out)
+	})
+}
+
+// VerifyPaymentAt is VerifyPayment as of now.
+func VerifyPaymentAt(header string, requirements *PaymentRequirements, now time.Time) (string, error) {
+	return verify(header, requirements, func(h, r *C.char, out **C.char) C.X402Status {
+		return C.x402_verify_payment_at(h, r, C.uint64_t(now.Unix()), out)
+	})
+}
+
+func verify(
+	header string,
+	requirements *PaymentRequirements,
+	f func(h, r *C.char, out **C.char) C.X402Status,
+) (string, error) {
+	r, err := marshal(requirements)
+	if err != nil {
+		return "", err
+	}
+	defer C.free(unsafe.Pointer(r))
+	h := C.CString(header)
+	defer C.free(unsafe.Pointer(h))
+	return call(func(out **C.char) C.X402Status {
+		return f(h, r, out)
+	})
+}
+
+// LibraryCapabilities returns the protocol surface compiled into the linked
+// x402 library, to compare with a peer SDK's at startup.
+func LibraryCapabilities() (*Capabilities, error) {
+	data, err := call(func(out **C.char) C.X402Status {
+		return C.x402_capabilities(out)
+	})
+	if err != nil {
+		return nil, err
+	}
+	var capabilities Capabilities
+	if err := json.Unmarshal([]byte(data), &capabilities); err != nil {
+		return nil, err
+	}
+	return &capabilities, nil
+}
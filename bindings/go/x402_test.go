@@ -0,0 +1,101 @@
+package x402
+
+import (
+	"encoding/json"
+	"errors"
+	"os"
+	"slices"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestRequirementsRoundTrip(t *testing.T) {
+	expiresAt := uint64(1700000300)
+	requirements := &PaymentRequirements{
+		Amount:    NewAmount(1000),
+		Recipient: "0x1111111111111111111111111111111111111111",
+		Network:   NetworkBase,
+		Resource:  "/api",
+		ExpiresAt: &expiresAt,
+		Extra:     map[string]any{"tier": "gold"},
+	}
+	header, err := EncodeRequirements(requirements)
+	if err != nil {
+		t.Fatal(err)
+	}
+	decoded, err := DecodeRequirements(header)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if decoded.Amount.Cmp(&requirements.Amount.Int) != 0 || decoded.Resource != "/api" || *decoded.ExpiresAt != expiresAt {
+		t.Fatalf("round trip changed requirements: %+v", decoded)
+	}
+	if decoded.Extra["tier"] != "gold" {
+		t.Fatalf("round trip lost extra: %+v", decoded.Extra)
+	}
+}
+
+func TestErrors(t *testing.T) {
+	_, err := DecodePayment("not a header")
+	if !errors.Is(err, ErrDecode) {
+		t.Fatalf("expected a decode error, got %v", err)
+	}
+	var x402Err *Error
+	if !errors.As(err, &x402Err) || x402Err.Message == "" {
+		t.Fatalf("expected a message, got %v", err)
+	}
+	if errors.Is(err, ErrExpired) {
+		t.Fatal("decode error matched ErrExpired")
+	}
+}
+
+// TestVectors verifies the cross-SDK vectors of spec/test-vectors.json.
+func TestVectors(t *testing.T) {
+	data, err := os.ReadFile("../../spec/test-vectors.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	var corpus struct {
+		Vectors []struct {
+			Name          string              `json:"name"`
+			Requirements  PaymentRequirements `json:"requirements"`
+			PaymentHeader string              `json:"paymentHeader"`
+			VerifyAt      int64               `json:"verifyAt"`
+			Expected      struct {
+				Result string `json:"result"`
+				Payer  string `json:"payer"`
+				Error  string `json:"error"`
+			} `json:"expected"`
+		} `json:"vectors"`
+	}
+	if err := json.Unmarshal(data, &corpus); err != nil {
+		t.Fatal(err)
+	}
+	errs := map[string]error{
+		"payment_expired":     ErrExpired,
+		"insufficient_amount": ErrInsufficientAmount,
+		"invalid_signature":   ErrInvalidSignature,
+		"unsupported_network": ErrInvalid,
+	}
+	for _, vector := range corpus.Vectors {
+		payer, err := VerifyPaymentAt(vector.PaymentHeader, &vector.Requirements, time.Unix(vector.VerifyAt, 0))
+		if vector.Expected.Result == "valid" {
+			if err != nil || !strings.EqualFold(payer, vector.Expected.Payer) {
+				t.Errorf("%s: got payer %q, error %v", vector.Name, payer, err)
+			}
+		} else if !errors.Is(err, errs[vector.Expected.Error]) {
+			t.Errorf("%s: expected %s, got %v", vector.Name, vector.Expected.Error, err)
+		}
+	}
+}
+
+func TestLibraryCapabilities(t *testing.T) {
+	capabilities, err := LibraryCapabilities()
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !slices.Contains(capabilities.Networks, NetworkBase) || !slices.Contains(capabilities.WireFormats, "json") {
+		t.Fatalf("capabilities missing base or json: %+v", capabilities)
+	}
+}
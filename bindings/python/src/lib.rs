@@ -5,53 +5,365 @@
 //! and core types to Python.
 
 use pyo3::prelude::*;
-use pyo3::exceptions::{PyValueError, PyRuntimeError};
+use pyo3::types::PyBytes;
+use pyo3::exceptions::{PyOverflowError, PyValueError, PyRuntimeError};
 use std::str::FromStr;
 
 use alloy_primitives::{Address, U256};
 
 use x402_core::{
-    PaymentRequirements, PaymentPayload, SignedPayment, Network,
+    Money, PaymentRequirements, PaymentPayload, SignedPayment, Network, Scheme, Extra, Split, Violation, check_canonical,
     encode_requirements_header, decode_requirements_header,
     encode_payment_header, decode_payment_header,
     verify_payment, X402Error,
 };
+use x402_core::capabilities as core_capabilities;
 
 /// Convert x402 Network to Python string
-fn network_to_py(network: &Network) -> &'static str {
-    match network {
+fn network_to_py(network: &Network) -> std::borrow::Cow<'static, str> {
+    std::borrow::Cow::Borrowed(match network {
         Network::Ethereum => "ethereum",
+        Network::Sepolia => "sepolia",
         Network::Base => "base",
         Network::BaseSepolia => "base_sepolia",
         Network::Arbitrum => "arbitrum",
+        Network::ArbitrumSepolia => "arbitrum_sepolia",
         Network::Optimism => "optimism",
+        Network::OptimismSepolia => "optimism_sepolia",
         Network::Polygon => "polygon",
-    }
+        Network::PolygonAmoy => "polygon_amoy",
+        Network::Avalanche => "avalanche",
+        Network::AvalancheFuji => "avalanche_fuji",
+        Network::Bnb => "bnb",
+        Network::BnbTestnet => "bnb_testnet",
+        Network::ZkSync => "zksync",
+        Network::ZkSyncSepolia => "zksync_sepolia",
+        Network::Linea => "linea",
+        Network::LineaSepolia => "linea_sepolia",
+        Network::Scroll => "scroll",
+        Network::ScrollSepolia => "scroll_sepolia",
+        Network::Custom(_) => return network.as_str(),
+    })
 }
 
 /// Convert Python string to x402 Network
 fn py_to_network(network: &str) -> PyResult<Network> {
     match network.to_lowercase().as_str() {
         "ethereum" => Ok(Network::Ethereum),
+        "sepolia" => Ok(Network::Sepolia),
         "base" => Ok(Network::Base),
         "base_sepolia" | "basesepolia" => Ok(Network::BaseSepolia),
         "arbitrum" => Ok(Network::Arbitrum),
+        "arbitrum_sepolia" | "arbitrumsepolia" => Ok(Network::ArbitrumSepolia),
         "optimism" => Ok(Network::Optimism),
+        "optimism_sepolia" | "optimismsepolia" => Ok(Network::OptimismSepolia),
         "polygon" => Ok(Network::Polygon),
+        "polygon_amoy" | "polygonamoy" => Ok(Network::PolygonAmoy),
+        "avalanche" => Ok(Network::Avalanche),
+        "avalanche_fuji" | "avalanchefuji" => Ok(Network::AvalancheFuji),
+        "bnb" => Ok(Network::Bnb),
+        "bnb_testnet" | "bnbtestnet" => Ok(Network::BnbTestnet),
+        "zksync" => Ok(Network::ZkSync),
+        "zksync_sepolia" | "zksyncsepolia" => Ok(Network::ZkSyncSepolia),
+        "linea" => Ok(Network::Linea),
+        "linea_sepolia" | "lineasepolia" => Ok(Network::LineaSepolia),
+        "scroll" => Ok(Network::Scroll),
+        "scroll_sepolia" | "scrollsepolia" => Ok(Network::ScrollSepolia),
         _ => Err(PyValueError::new_err(format!("Unknown network: {}", network))),
     }
 }
 
+/// Convert a `Network`, network name or chain id to x402 Network
+fn py_any_to_network(network: &Bound<'_, PyAny>) -> PyResult<Network> {
+    if let Ok(network) = network.downcast::<PyNetwork>() {
+        return Ok((*network.get()).into());
+    }
+    if let Ok(name) = network.extract::<String>() {
+        return py_to_network(&name);
+    }
+    let chain_id: u64 = network.extract()
+        .map_err(|_| PyValueError::new_err("network must be a Network, a network name or a chain id"))?;
+    Network::from_chain_id(chain_id)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown chain ID: {}", chain_id)))
+}
+
+/// Supported networks; members compare equal to their chain ids
+#[pyclass(name = "Network", eq, eq_int, hash, frozen)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum PyNetwork {
+    #[pyo3(name = "ETHEREUM")]
+    Ethereum = 1,
+    #[pyo3(name = "SEPOLIA")]
+    Sepolia = 11155111,
+    #[pyo3(name = "BASE")]
+    Base = 8453,
+    #[pyo3(name = "BASE_SEPOLIA")]
+    BaseSepolia = 84532,
+    #[pyo3(name = "ARBITRUM")]
+    Arbitrum = 42161,
+    #[pyo3(name = "ARBITRUM_SEPOLIA")]
+    ArbitrumSepolia = 421614,
+    #[pyo3(name = "OPTIMISM")]
+    Optimism = 10,
+    #[pyo3(name = "OPTIMISM_SEPOLIA")]
+    OptimismSepolia = 11155420,
+    #[pyo3(name = "POLYGON")]
+    Polygon = 137,
+    #[pyo3(name = "POLYGON_AMOY")]
+    PolygonAmoy = 80002,
+    #[pyo3(name = "AVALANCHE")]
+    Avalanche = 43114,
+    #[pyo3(name = "AVALANCHE_FUJI")]
+    AvalancheFuji = 43113,
+    #[pyo3(name = "BNB")]
+    Bnb = 56,
+    #[pyo3(name = "BNB_TESTNET")]
+    BnbTestnet = 97,
+    #[pyo3(name = "ZKSYNC")]
+    ZkSync = 324,
+    #[pyo3(name = "ZKSYNC_SEPOLIA")]
+    ZkSyncSepolia = 300,
+    #[pyo3(name = "LINEA")]
+    Linea = 59144,
+    #[pyo3(name = "LINEA_SEPOLIA")]
+    LineaSepolia = 59141,
+    #[pyo3(name = "SCROLL")]
+    Scroll = 534352,
+    #[pyo3(name = "SCROLL_SEPOLIA")]
+    ScrollSepolia = 534351,
+}
+
+impl From<PyNetwork> for Network {
+    fn from(network: PyNetwork) -> Self {
+        match network {
+            PyNetwork::Ethereum => Network::Ethereum,
+            PyNetwork::Sepolia => Network::Sepolia,
+            PyNetwork::Base => Network::Base,
+            PyNetwork::BaseSepolia => Network::BaseSepolia,
+            PyNetwork::Arbitrum => Network::Arbitrum,
+            PyNetwork::ArbitrumSepolia => Network::ArbitrumSepolia,
+            PyNetwork::Optimism => Network::Optimism,
+            PyNetwork::OptimismSepolia => Network::OptimismSepolia,
+            PyNetwork::Polygon => Network::Polygon,
+            PyNetwork::PolygonAmoy => Network::PolygonAmoy,
+            PyNetwork::Avalanche => Network::Avalanche,
+            PyNetwork::AvalancheFuji => Network::AvalancheFuji,
+            PyNetwork::Bnb => Network::Bnb,
+            PyNetwork::BnbTestnet => Network::BnbTestnet,
+            PyNetwork::ZkSync => Network::ZkSync,
+            PyNetwork::ZkSyncSepolia => Network::ZkSyncSepolia,
+            PyNetwork::Linea => Network::Linea,
+            PyNetwork::LineaSepolia => Network::LineaSepolia,
+            PyNetwork::Scroll => Network::Scroll,
+            PyNetwork::ScrollSepolia => Network::ScrollSepolia,
+        }
+    }
+}
+
+impl TryFrom<Network> for PyNetwork {
+    type Error = PyErr;
+
+    fn try_from(network: Network) -> PyResult<Self> {
+        Ok(match network {
+            Network::Ethereum => PyNetwork::Ethereum,
+            Network::Sepolia => PyNetwork::Sepolia,
+            Network::Base => PyNetwork::Base,
+            Network::BaseSepolia => PyNetwork::BaseSepolia,
+            Network::Arbitrum => PyNetwork::Arbitrum,
+            Network::ArbitrumSepolia => PyNetwork::ArbitrumSepolia,
+            Network::Optimism => PyNetwork::Optimism,
+            Network::OptimismSepolia => PyNetwork::OptimismSepolia,
+            Network::Polygon => PyNetwork::Polygon,
+            Network::PolygonAmoy => PyNetwork::PolygonAmoy,
+            Network::Avalanche => PyNetwork::Avalanche,
+            Network::AvalancheFuji => PyNetwork::AvalancheFuji,
+            Network::Bnb => PyNetwork::Bnb,
+            Network::BnbTestnet => PyNetwork::BnbTestnet,
+            Network::ZkSync => PyNetwork::ZkSync,
+            Network::ZkSyncSepolia => PyNetwork::ZkSyncSepolia,
+            Network::Linea => PyNetwork::Linea,
+            Network::LineaSepolia => PyNetwork::LineaSepolia,
+            Network::Scroll => PyNetwork::Scroll,
+            Network::ScrollSepolia => PyNetwork::ScrollSepolia,
+            Network::Custom(chain_id) => {
+                return Err(PyValueError::new_err(format!("chain {} has no Network member", chain_id)))
+            }
+        })
+    }
+}
+
+#[pymethods]
+impl PyNetwork {
+    /// Every supported network
+    #[classattr]
+    #[pyo3(name = "ALL")]
+    fn all() -> Vec<PyNetwork> {
+        Network::ALL.into_iter().filter_map(|network| PyNetwork::try_from(network).ok()).collect()
+    }
+
+    /// Look up a network by chain id; None if unsupported
+    #[staticmethod]
+    fn from_chain_id(chain_id: u64) -> Option<PyNetwork> {
+        Network::from_chain_id(chain_id).and_then(|network| PyNetwork::try_from(network).ok())
+    }
+
+    /// Convert a network name, chain id or Network to a Network
+    #[staticmethod]
+    fn parse(network: &Bound<'_, PyAny>) -> PyResult<PyNetwork> {
+        py_any_to_network(network).and_then(PyNetwork::try_from)
+    }
+
+    /// Whether a network name, chain id or Network is supported
+    #[staticmethod]
+    fn is_supported(network: &Bound<'_, PyAny>) -> bool {
+        py_any_to_network(network).is_ok()
+    }
+
+    #[getter]
+    fn chain_id(&self) -> u64 {
+        Network::from(*self).chain_id()
+    }
+
+    /// Whether this is a test network
+    #[getter]
+    fn is_testnet(&self) -> bool {
+        Network::from(*self).is_testnet()
+    }
+
+    /// Network name, e.g. `"base_sepolia"`
+    #[getter]
+    fn value(&self) -> String {
+        network_to_py(&Network::from(*self)).into_owned()
+    }
+
+    fn __str__(&self) -> String {
+        self.value()
+    }
+
+    /// Pickle as `Network.parse(chain_id)`
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(Bound<'py, PyAny>, (u64,))> {
+        Ok((py.get_type::<PyNetwork>().getattr("parse")?, (self.chain_id(),)))
+    }
+}
+
+/// Convert a JSON-serializable Python object to a JSON value
+fn py_to_json(value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let json: String = value.py().import("json")?.call_method1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(format!("Invalid JSON value: {}", e)))
+}
+
+/// Convert an optional Python dict to an `extra` metadata map
+fn py_to_extra(value: Option<&Bound<'_, PyAny>>) -> PyResult<Extra> {
+    match value.map(py_to_json).transpose()? {
+        None => Ok(Extra::new()),
+        Some(serde_json::Value::Object(map)) => {
+            let extra: Extra = map.into_iter().collect();
+            extra.values().try_for_each(check_canonical).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(extra)
+        }
+        Some(_) => Err(PyValueError::new_err("extra must be a dict")),
+    }
+}
+
+/// Convert `(recipient, share)` pairs to split outputs
+fn py_to_splits(splits: Option<Vec<(String, Bound<'_, PyAny>)>>) -> PyResult<Vec<Split>> {
+    splits.unwrap_or_default().into_iter().map(|(recipient, share)| {
+        let recipient = Address::from_str(&recipient)
+            .map_err(|e| PyValueError::new_err(format!("Invalid split recipient address: {}", e)))?;
+        Ok(Split { recipient, share: py_to_u256(&share)? })
+    }).collect()
+}
+
+/// Convert split outputs to `(recipient, share)` pairs
+fn splits_to_py(py: Python<'_>, splits: &[Split]) -> PyResult<Vec<(String, PyObject)>> {
+    splits.iter()
+        .map(|split| Ok((split.recipient.to_string(), u256_to_py(py, split.share)?)))
+        .collect()
+}
+
+/// Convert a Python int or decimal string to a U256, without truncation
+fn py_to_u256(value: &Bound<'_, PyAny>) -> PyResult<U256> {
+    if value.is_instance_of::<pyo3::types::PyBool>() {
+        return Err(PyValueError::new_err("Invalid amount: expected an int, got a bool"));
+    }
+    let digits: String = value.str()?.extract()?;
+    U256::from_str_radix(digits.trim(), 10)
+        .map_err(|e| PyValueError::new_err(format!("Invalid amount {}: {}", digits, e)))
+}
+
+/// Convert a U256 to a Python int
+fn u256_to_py(py: Python<'_>, value: U256) -> PyResult<PyObject> {
+    Ok(py.import("builtins")?.getattr("int")?.call1((value.to_string(),))?.unbind())
+}
+
+/// Convert a JSON value to the equivalent Python object
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (value.to_string(),))?.unbind())
+}
+
+/// Convert violations to dicts with `code`, `message` and any details
+fn violations_to_py(py: Python<'_>, violations: &[Violation]) -> PyResult<Vec<PyObject>> {
+    violations.iter().map(|violation| {
+        let mut value = serde_json::to_value(violation).unwrap_or_default();
+        value["message"] = violation.to_string().into();
+        json_to_py(py, &value)
+    }).collect()
+}
+
+/// Convert a serializable core value to a dict in its JSON wire form
+fn to_py_dict<T: serde::Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    json_to_py(py, &serde_json::to_value(value).map_err(|e| PyRuntimeError::new_err(e.to_string()))?)
+}
+
+/// Convert a dict in JSON wire form to a core value
+fn from_py_dict<T: serde::de::DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    serde_json::from_value(py_to_json(value)?).map_err(|e| PyValueError::new_err(format!("Invalid dict: {}", e)))
+}
+
+/// Serialize a core value to its JSON wire form
+fn to_json_string<T: serde::Serialize>(value: &T) -> PyResult<String> {
+    serde_json::to_string(value).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+/// Parse a core value from its JSON wire form
+fn from_json_string<T: serde::de::DeserializeOwned>(json: &str) -> PyResult<T> {
+    serde_json::from_str(json).map_err(|e| PyValueError::new_err(format!("Invalid JSON: {}", e)))
+}
+
+/// Canonical JSON of a value's wire format, so equal values compare and
+/// hash the same whatever the order of their `extra` keys
+fn canonical_text<T: serde::Serialize>(value: &T) -> PyResult<String> {
+    let value = serde_json::to_value(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(x402_core::canonical_json(&value))
+}
+
+/// Hash of `canonical_text`
+fn canonical_hash<T: serde::Serialize>(value: &T) -> PyResult<u64> {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    canonical_text(value)?.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
 /// Convert X402Error to PyErr
 fn x402_err_to_py(e: X402Error) -> PyErr {
     match e {
         X402Error::InvalidHeader(msg) => PyValueError::new_err(format!("Invalid header: {}", msg)),
         X402Error::InvalidSignature(msg) => PyValueError::new_err(format!("Invalid signature: {}", msg)),
         X402Error::PaymentExpired => PyValueError::new_err("Payment expired"),
+        X402Error::ValidityTooLong { .. } => PyValueError::new_err(e.to_string()),
         X402Error::InsufficientAmount { required, provided } => {
             PyValueError::new_err(format!("Insufficient amount: required {}, got {}", required, provided))
         },
         X402Error::UnsupportedNetwork(msg) => PyValueError::new_err(format!("Unsupported network: {}", msg)),
+        X402Error::UnsupportedScheme(msg) => PyValueError::new_err(format!("Unsupported scheme: {}", msg)),
+        X402Error::LimitExceeded { .. } => PyValueError::new_err(format!("Invalid header: {}", e)),
+        X402Error::InvalidAmount(_) | X402Error::Invalid(_) => PyValueError::new_err(e.to_string()),
+        X402Error::AmountOverflow { .. } => PyOverflowError::new_err(e.to_string()),
+        X402Error::Base64(_) | X402Error::Json(_) | X402Error::Cbor(_) | X402Error::UnsupportedVersion(_)
+        | X402Error::Ecdsa(_) => {
+            PyValueError::new_err(e.chain_message())
+        },
         _ => PyRuntimeError::new_err(format!("x402 error: {}", e)),
     }
 }
@@ -66,15 +378,22 @@ struct PyPaymentRequirements {
 #[pymethods]
 impl PyPaymentRequirements {
     #[new]
-    #[pyo3(signature = (amount, recipient, network, resource, token=None, description=None, expires_at=None))]
+    #[pyo3(signature = (amount, recipient, network, resource, token=None, description=None, expires_at=None, scheme="exact", mime_type=None, output_schema=None, max_timeout_seconds=None, extra=None, splits=None))]
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        amount: u64,
+        amount: &Bound<'_, PyAny>,
         recipient: String,
-        network: String,
+        network: &Bound<'_, PyAny>,
         resource: String,
         token: Option<String>,
         description: Option<String>,
         expires_at: Option<u64>,
+        scheme: &str,
+        mime_type: Option<String>,
+        output_schema: Option<&Bound<'_, PyAny>>,
+        max_timeout_seconds: Option<u64>,
+        extra: Option<&Bound<'_, PyAny>>,
+        splits: Option<Vec<(String, Bound<'_, PyAny>)>>,
     ) -> PyResult<Self> {
         let recipient_addr = Address::from_str(&recipient)
             .map_err(|e| PyValueError::new_err(format!("Invalid recipient address: {}", e)))?;
@@ -84,22 +403,33 @@ impl PyPaymentRequirements {
         }).transpose()
             .map_err(|e| PyValueError::new_err(format!("Invalid token address: {}", e)))?;
         
+        let output_schema = output_schema.map(py_to_json).transpose()?;
+        
         Ok(Self {
             inner: PaymentRequirements {
-                amount: U256::from(amount),
+                amount: py_to_u256(amount)?,
                 recipient: recipient_addr,
-                network: py_to_network(&network)?,
+                network: py_any_to_network(network)?,
                 token: token_addr,
                 description,
                 expires_at,
                 resource,
+                commitment: None,
+                scheme: scheme.parse::<Scheme>().map_err(x402_err_to_py)?,
+                mime_type,
+                output_schema,
+                max_timeout_seconds,
+                extra: py_to_extra(extra)?,
+                splits: py_to_splits(splits)?,
+                price_quote: None,
             }
         })
     }
     
+    /// Amount in the token's smallest unit, as an int of any size
     #[getter]
-    fn amount(&self) -> u64 {
-        self.inner.amount.try_into().unwrap_or(u64::MAX)
+    fn amount(&self, py: Python<'_>) -> PyResult<PyObject> {
+        u256_to_py(py, self.inner.amount)
     }
     
     #[getter]
@@ -108,8 +438,8 @@ impl PyPaymentRequirements {
     }
     
     #[getter]
-    fn network(&self) -> String {
-        network_to_py(&self.inner.network).to_string()
+    fn network(&self) -> PyResult<PyNetwork> {
+        self.inner.network.try_into()
     }
     
     #[getter]
@@ -131,6 +461,152 @@ impl PyPaymentRequirements {
     fn chain_id(&self) -> u64 {
         self.inner.network.chain_id()
     }
+    
+    #[getter]
+    fn scheme(&self) -> &'static str {
+        self.inner.scheme.as_str()
+    }
+    
+    #[getter]
+    fn mime_type(&self) -> Option<String> {
+        self.inner.mime_type.clone()
+    }
+    
+    /// Output schema as a Python object (dict), if advertised
+    #[getter]
+    fn output_schema(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        self.inner.output_schema.as_ref().map(|schema| json_to_py(py, schema)).transpose()
+    }
+    
+    #[getter]
+    fn max_timeout_seconds(&self) -> Option<u64> {
+        self.inner.max_timeout_seconds
+    }
+    
+    /// Application metadata as a dict
+    #[getter]
+    fn extra(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_py(py, &serde_json::to_value(&self.inner.extra).unwrap_or_default())
+    }
+    
+    /// Split outputs as `(recipient, share)` pairs
+    #[getter]
+    fn splits(&self, py: Python<'_>) -> PyResult<Vec<(String, PyObject)>> {
+        splits_to_py(py, &self.inner.splits)
+    }
+    
+    /// Expiry a client signing at unix time `now` should put on its payment
+    fn payment_expires_at(&self, now: u64) -> u64 {
+        self.inner.payment_expires_at(now)
+    }
+
+    /// Problems with these requirements as dicts, as of unix time `now`
+    /// (default: the current time)
+    #[pyo3(signature = (now=None))]
+    fn validate(&self, py: Python<'_>, now: Option<u64>) -> PyResult<Vec<PyObject>> {
+        let violations = now.map_or_else(|| self.inner.validate(), |now| self.inner.validate_at(now));
+        violations_to_py(py, &violations)
+    }
+
+    /// Requirements for `price`, a stablecoin `Money` amount, on `network`
+    #[staticmethod]
+    fn priced(price: &PyMoney, network: &Bound<'_, PyAny>, recipient: String, resource: String) -> PyResult<Self> {
+        let recipient = Address::from_str(&recipient)
+            .map_err(|e| PyValueError::new_err(format!("Invalid recipient address: {}", e)))?;
+        let inner = PaymentRequirements::priced(py_any_to_network(network)?, &price.inner, recipient, resource)
+            .map_err(x402_err_to_py)?;
+        Ok(Self { inner })
+    }
+
+    fn __eq__(&self, other: &PyPaymentRequirements) -> PyResult<bool> {
+        Ok(canonical_text(&self.inner)? == canonical_text(&other.inner)?)
+    }
+
+    fn __hash__(&self) -> PyResult<u64> {
+        canonical_hash(&self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        let r = &self.inner;
+        let mut repr = format!(
+            "PaymentRequirements(amount={}, recipient='{}', network='{}', resource={:?}",
+            r.amount, r.recipient, network_to_py(&r.network), r.resource
+        );
+        if let Some(token) = r.token {
+            repr.push_str(&format!(", token='{}'", token));
+        }
+        if !r.scheme.is_exact() {
+            repr.push_str(&format!(", scheme='{}'", r.scheme.as_str()));
+        }
+        if let Some(expires_at) = r.expires_at {
+            repr.push_str(&format!(", expires_at={}", expires_at));
+        }
+        repr.push(')');
+        repr
+    }
+
+    /// This value as a dict, in the JSON wire format
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py_dict(py, &self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_dict`
+    #[staticmethod]
+    fn from_dict(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self { inner: from_py_dict(value)? })
+    }
+
+    /// This value as a JSON string, in the wire format
+    fn to_json(&self) -> PyResult<String> {
+        to_json_string(&self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_json`
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self { inner: from_json_string(json)? })
+    }
+
+    fn __getstate__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+
+    fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+        self.inner = from_json_string(state)?;
+        Ok(())
+    }
+
+    /// Pickle as `from_json(to_json())`, since `__new__` needs arguments
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<(Bound<'py, PyAny>, (String,))> {
+        Ok((slf.get_type().getattr("from_json")?, (slf.borrow().to_json()?,)))
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        self.clone()
+    }
+}
+
+/// Python-style repr of a payload
+fn payload_repr(p: &PaymentPayload) -> String {
+    let mut repr = format!(
+        "PaymentPayload(amount={}, recipient='{}', payer='{}', chain_id={}, resource={:?}, nonce={}, expires_at={}",
+        p.amount, p.recipient, p.payer, p.chain_id, p.resource, p.nonce, p.expires_at
+    );
+    if let Some(token) = p.token {
+        repr.push_str(&format!(", token='{}'", token));
+    }
+    if !p.scheme.is_exact() {
+        repr.push_str(&format!(", scheme='{}'", p.scheme.as_str()));
+    }
+    if let Some(invoice_id) = &p.invoice_id {
+        repr.push_str(&format!(", invoice_id={:?}", invoice_id));
+    }
+    repr.push(')');
+    repr
 }
 
 /// Python wrapper for PaymentPayload
@@ -143,9 +619,10 @@ struct PyPaymentPayload {
 #[pymethods]
 impl PyPaymentPayload {
     #[new]
-    #[pyo3(signature = (amount, recipient, payer, chain_id, resource, nonce, expires_at, token=None))]
+    #[pyo3(signature = (amount, recipient, payer, chain_id, resource, nonce, expires_at, token=None, scheme="exact", extra=None, invoice_id=None, splits=None))]
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        amount: u64,
+        amount: &Bound<'_, PyAny>,
         recipient: String,
         payer: String,
         chain_id: u64,
@@ -153,6 +630,10 @@ impl PyPaymentPayload {
         nonce: u64,
         expires_at: u64,
         token: Option<String>,
+        scheme: &str,
+        extra: Option<&Bound<'_, PyAny>>,
+        invoice_id: Option<String>,
+        splits: Option<Vec<(String, Bound<'_, PyAny>)>>,
     ) -> PyResult<Self> {
         let recipient_addr = Address::from_str(&recipient)
             .map_err(|e| PyValueError::new_err(format!("Invalid recipient address: {}", e)))?;
@@ -167,7 +648,7 @@ impl PyPaymentPayload {
         
         Ok(Self {
             inner: PaymentPayload {
-                amount: U256::from(amount),
+                amount: py_to_u256(amount)?,
                 recipient: recipient_addr,
                 payer: payer_addr,
                 chain_id,
@@ -175,6 +656,10 @@ impl PyPaymentPayload {
                 resource,
                 nonce,
                 expires_at,
+                scheme: scheme.parse::<Scheme>().map_err(x402_err_to_py)?,
+                extra: py_to_extra(extra)?,
+                invoice_id,
+                splits: py_to_splits(splits)?,
             }
         })
     }
@@ -184,9 +669,10 @@ impl PyPaymentPayload {
         self.inner.message_hash().to_vec()
     }
     
+    /// Amount in the token's smallest unit, as an int of any size
     #[getter]
-    fn amount(&self) -> u64 {
-        self.inner.amount.try_into().unwrap_or(u64::MAX)
+    fn amount(&self, py: Python<'_>) -> PyResult<PyObject> {
+        u256_to_py(py, self.inner.amount)
     }
     
     #[getter]
@@ -218,6 +704,284 @@ impl PyPaymentPayload {
     fn expires_at(&self) -> u64 {
         self.inner.expires_at
     }
+    
+    #[getter]
+    fn scheme(&self) -> &'static str {
+        self.inner.scheme.as_str()
+    }
+    
+    /// Application metadata as a dict
+    #[getter]
+    fn extra(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_py(py, &serde_json::to_value(&self.inner.extra).unwrap_or_default())
+    }
+    
+    #[getter]
+    fn invoice_id(&self) -> Option<String> {
+        self.inner.invoice_id.clone()
+    }
+    
+    /// Split outputs as `(recipient, share)` pairs
+    #[getter]
+    fn splits(&self, py: Python<'_>) -> PyResult<Vec<(String, PyObject)>> {
+        splits_to_py(py, &self.inner.splits)
+    }
+
+    /// Problems with this payload as dicts, as of unix time `now`
+    /// (default: the current time)
+    #[pyo3(signature = (now=None))]
+    fn validate(&self, py: Python<'_>, now: Option<u64>) -> PyResult<Vec<PyObject>> {
+        let violations = now.map_or_else(|| self.inner.validate(), |now| self.inner.validate_at(now));
+        violations_to_py(py, &violations)
+    }
+
+    fn __eq__(&self, other: &PyPaymentPayload) -> PyResult<bool> {
+        Ok(canonical_text(&self.inner)? == canonical_text(&other.inner)?)
+    }
+
+    fn __hash__(&self) -> PyResult<u64> {
+        canonical_hash(&self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        payload_repr(&self.inner)
+    }
+
+    /// This value as a dict, in the JSON wire format
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py_dict(py, &self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_dict`
+    #[staticmethod]
+    fn from_dict(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self { inner: from_py_dict(value)? })
+    }
+
+    /// This value as a JSON string, in the wire format
+    fn to_json(&self) -> PyResult<String> {
+        to_json_string(&self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_json`
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self { inner: from_json_string(json)? })
+    }
+
+    fn __getstate__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+
+    fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+        self.inner = from_json_string(state)?;
+        Ok(())
+    }
+
+    /// Pickle as `from_json(to_json())`, since `__new__` needs arguments
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<(Bound<'py, PyAny>, (String,))> {
+        Ok((slf.get_type().getattr("from_json")?, (slf.borrow().to_json()?,)))
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        self.clone()
+    }
+}
+
+/// Python wrapper for SignedPayment
+#[pyclass(name = "SignedPayment")]
+#[derive(Clone)]
+struct PySignedPayment {
+    inner: SignedPayment,
+}
+
+#[pymethods]
+impl PySignedPayment {
+    #[new]
+    fn new(payment: &PyPaymentPayload, signature: Vec<u8>) -> Self {
+        Self { inner: SignedPayment { payment: payment.inner.clone(), signature, signatures: Vec::new() } }
+    }
+
+    #[getter]
+    fn payment(&self) -> PyPaymentPayload {
+        PyPaymentPayload { inner: self.inner.payment.clone() }
+    }
+
+    /// ECDSA signature (65 bytes: r + s + v)
+    #[getter]
+    fn signature<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.signature)
+    }
+
+    fn __eq__(&self, other: &PySignedPayment) -> PyResult<bool> {
+        Ok(canonical_text(&self.inner)? == canonical_text(&other.inner)?)
+    }
+
+    fn __hash__(&self) -> PyResult<u64> {
+        canonical_hash(&self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SignedPayment(payment={}, signature=0x{})",
+            payload_repr(&self.inner.payment),
+            alloy_primitives::hex::encode(&self.inner.signature)
+        )
+    }
+
+    /// This value as a dict, in the JSON wire format
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py_dict(py, &self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_dict`
+    #[staticmethod]
+    fn from_dict(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self { inner: from_py_dict(value)? })
+    }
+
+    /// This value as a JSON string, in the wire format
+    fn to_json(&self) -> PyResult<String> {
+        to_json_string(&self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_json`
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self { inner: from_json_string(json)? })
+    }
+
+    fn __getstate__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+
+    fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+        self.inner = from_json_string(state)?;
+        Ok(())
+    }
+
+    /// Pickle as `from_json(to_json())`, since `__new__` needs arguments
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<(Bound<'py, PyAny>, (String,))> {
+        Ok((slf.get_type().getattr("from_json")?, (slf.borrow().to_json()?,)))
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        self.clone()
+    }
+}
+
+/// Python wrapper for Money
+#[pyclass(name = "Money")]
+#[derive(Clone)]
+struct PyMoney {
+    inner: Money,
+}
+
+#[pymethods]
+impl PyMoney {
+    #[new]
+    #[pyo3(signature = (amount, decimals, symbol=None))]
+    fn new(amount: &Bound<'_, PyAny>, decimals: u8, symbol: Option<String>) -> PyResult<Self> {
+        Ok(Self { inner: Money::new(py_to_u256(amount)?, decimals, symbol) })
+    }
+
+    /// Parse `"<amount> <symbol>"` for a known stablecoin, e.g. `"1.25 USDC"`
+    #[staticmethod]
+    fn parse(value: &str) -> PyResult<Self> {
+        Ok(Self { inner: value.parse().map_err(x402_err_to_py)? })
+    }
+
+    /// Amount in the token's smallest unit
+    #[getter]
+    fn amount(&self, py: Python<'_>) -> PyResult<PyObject> {
+        u256_to_py(py, self.inner.amount)
+    }
+
+    #[getter]
+    fn decimals(&self) -> u8 {
+        self.inner.decimals
+    }
+
+    #[getter]
+    fn symbol(&self) -> Option<String> {
+        self.inner.symbol.clone()
+    }
+
+    fn __add__(&self, other: &PyMoney) -> PyResult<Self> {
+        Ok(Self { inner: self.inner.checked_add(&other.inner).map_err(x402_err_to_py)? })
+    }
+
+    fn __sub__(&self, other: &PyMoney) -> PyResult<Self> {
+        Ok(Self { inner: self.inner.checked_sub(&other.inner).map_err(x402_err_to_py)? })
+    }
+
+    fn __mul__(&self, factor: u64) -> PyResult<Self> {
+        Ok(Self { inner: self.inner.checked_mul(factor).map_err(x402_err_to_py)? })
+    }
+
+    fn __eq__(&self, other: &PyMoney) -> bool {
+        self.inner == other.inner
+    }
+
+    fn __str__(&self) -> String {
+        self.inner.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Money({:?})", self.inner.to_string())
+    }
+
+    /// This value as a dict, in the JSON wire format
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py_dict(py, &self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_dict`
+    #[staticmethod]
+    fn from_dict(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self { inner: from_py_dict(value)? })
+    }
+
+    /// This value as a JSON string, in the wire format
+    fn to_json(&self) -> PyResult<String> {
+        to_json_string(&self.inner)
+    }
+
+    /// Rebuild a value from the output of `to_json`
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self { inner: from_json_string(json)? })
+    }
+
+    fn __getstate__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+
+    fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+        self.inner = from_json_string(state)?;
+        Ok(())
+    }
+
+    /// Pickle as `from_json(to_json())`, since `__new__` needs arguments
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<(Bound<'py, PyAny>, (String,))> {
+        Ok((slf.get_type().getattr("from_json")?, (slf.borrow().to_json()?,)))
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        self.clone()
+    }
 }
 
 /// Encode payment requirements to a base64 header value
@@ -236,41 +1000,97 @@ fn decode_requirements(header: &str) -> PyResult<PyPaymentRequirements> {
 }
 
 /// Encode a signed payment to a base64 header value
+/// Takes a SignedPayment, or a PaymentPayload and its signature
 #[pyfunction]
-fn encode_payment(payload: &PyPaymentPayload, signature: Vec<u8>) -> PyResult<String> {
-    let signed = SignedPayment {
-        payment: payload.inner.clone(),
-        signature,
+#[pyo3(signature = (payment, signature=None))]
+fn encode_payment(payment: &Bound<'_, PyAny>, signature: Option<Vec<u8>>) -> PyResult<String> {
+    let signed = if let Ok(signed) = payment.downcast::<PySignedPayment>() {
+        signed.borrow().inner.clone()
+    } else {
+        let payload = payment.downcast::<PyPaymentPayload>()?.borrow().inner.clone();
+        let signature = signature.ok_or_else(|| PyValueError::new_err("signature is required with a PaymentPayload"))?;
+        SignedPayment { payment: payload, signature, signatures: Vec::new() }
     };
     encode_payment_header(&signed)
         .map_err(x402_err_to_py)
 }
 
 /// Decode a signed payment from a base64 header value
-/// Returns a tuple of (PaymentPayload, signature_bytes)
 #[pyfunction]
-fn decode_payment(header: &str) -> PyResult<(PyPaymentPayload, Vec<u8>)> {
-    let signed = decode_payment_header(header)
+fn decode_payment(header: &str) -> PyResult<PySignedPayment> {
+    let inner = decode_payment_header(header)
         .map_err(x402_err_to_py)?;
-    Ok((
-        PyPaymentPayload { inner: signed.payment },
-        signed.signature,
-    ))
+    Ok(PySignedPayment { inner })
 }
 
 /// Verify a signed payment against requirements
 /// Returns the verified payer address
+///
+/// The GIL is released while decoding and recovering the signature, so
+/// verifications on several threads run in parallel.
 #[pyfunction]
-fn verify_signed_payment(payment_header: &str, requirements: &PyPaymentRequirements) -> PyResult<String> {
-    let signed = decode_payment_header(payment_header)
-        .map_err(x402_err_to_py)?;
-    
-    let payer = verify_payment(&signed, &requirements.inner)
-        .map_err(x402_err_to_py)?;
+fn verify_signed_payment(py: Python<'_>, payment_header: &str, requirements: &PyPaymentRequirements) -> PyResult<String> {
+    let requirements = &requirements.inner;
+    let payer = py.allow_threads(|| {
+        let signed = decode_payment_header(payment_header)?;
+        verify_payment(&signed, requirements)
+    }).map_err(x402_err_to_py)?;
     
     Ok(format!("{:?}", payer))
 }
 
+/// Verify a signed payment on the running event loop's default executor
+/// Returns an awaitable resolving to the verified payer address
+#[pyfunction]
+fn verify_signed_payment_async<'py>(
+    py: Python<'py>,
+    payment_header: String,
+    requirements: Py<PyPaymentRequirements>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+    let verify = wrap_pyfunction!(verify_signed_payment, py)?;
+    event_loop.call_method1("run_in_executor", (py.None(), verify, payment_header, requirements))
+}
+
+/// Parse a 0x-prefixed or bare hex secp256k1 private key, wiping the
+/// decoded bytes afterwards (the key itself wipes on drop)
+#[cfg(feature = "signing")]
+fn py_to_signing_key(private_key_hex: &str) -> PyResult<k256::ecdsa::SigningKey> {
+    let hex = private_key_hex.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    let bytes = alloy_primitives::hex::decode(hex)
+        .map(zeroize::Zeroizing::new)
+        .map_err(|e| PyValueError::new_err(format!("Invalid private key: {}", e)))?;
+    if bytes.len() != 32 {
+        return Err(PyValueError::new_err(format!("Invalid private key: expected 32 bytes, got {}", bytes.len())));
+    }
+    k256::ecdsa::SigningKey::from_slice(&bytes).map_err(|e| PyValueError::new_err(format!("Invalid private key: {}", e)))
+}
+
+/// Sign a payment payload, returning the 65-byte signature (r + s + v)
+/// over `payload.message_hash()` that `verify_signed_payment` accepts
+#[cfg(feature = "signing")]
+#[pyfunction]
+fn sign_payment<'py>(py: Python<'py>, payload: &PyPaymentPayload, private_key_hex: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let key = py_to_signing_key(private_key_hex)?;
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(&payload.inner.message_hash())
+        .map_err(|e| PyRuntimeError::new_err(format!("Signing failed: {}", e)))?;
+    let mut bytes = signature.to_bytes().to_vec();
+    bytes.push(recovery_id.to_byte() + 27);
+    Ok(PyBytes::new(py, &bytes))
+}
+
+/// Get the checksummed address for a private key
+#[cfg(feature = "signing")]
+#[pyfunction]
+fn private_key_address(private_key_hex: &str) -> PyResult<String> {
+    let key = py_to_signing_key(private_key_hex)?;
+    let point = key.verifying_key().to_encoded_point(false);
+    let hash = alloy_primitives::keccak256(&point.as_bytes()[1..]);
+    Ok(Address::from_slice(&hash[12..]).to_checksum(None))
+}
+
 /// Get the chain ID for a network name
 #[pyfunction]
 fn get_chain_id(network: &str) -> PyResult<u64> {
@@ -286,12 +1106,23 @@ fn get_network_name(chain_id: u64) -> PyResult<String> {
         .ok_or_else(|| PyValueError::new_err(format!("Unknown chain ID: {}", chain_id)))
 }
 
+/// Protocol surface compiled into the native module, as a dict
+#[pyfunction]
+fn capabilities(py: Python<'_>) -> PyResult<PyObject> {
+    let value = serde_json::to_value(core_capabilities())
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    json_to_py(py, &value)
+}
+
 /// x402 native Python module
 #[pymodule]
 fn x402_native(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Types
+    m.add_class::<PyNetwork>()?;
     m.add_class::<PyPaymentRequirements>()?;
     m.add_class::<PyPaymentPayload>()?;
+    m.add_class::<PySignedPayment>()?;
+    m.add_class::<PyMoney>()?;
     
     // Protocol functions
     m.add_function(wrap_pyfunction!(encode_requirements, m)?)?;
@@ -301,10 +1132,18 @@ fn x402_native(m: &Bound<'_, PyModule>) -> PyResult<()> {
     
     // Verification
     m.add_function(wrap_pyfunction!(verify_signed_payment, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_signed_payment_async, m)?)?;
+    
+    // Signing
+    #[cfg(feature = "signing")]
+    m.add_function(wrap_pyfunction!(sign_payment, m)?)?;
+    #[cfg(feature = "signing")]
+    m.add_function(wrap_pyfunction!(private_key_address, m)?)?;
     
     // Network utilities
     m.add_function(wrap_pyfunction!(get_chain_id, m)?)?;
     m.add_function(wrap_pyfunction!(get_network_name, m)?)?;
+    m.add_function(wrap_pyfunction!(capabilities, m)?)?;
     
     // Constants
     m.add("X402_REQUIREMENTS_HEADER", x402_core::X402_REQUIREMENTS_HEADER)?;
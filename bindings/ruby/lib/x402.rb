@@ -0,0 +1,65 @@
+# frozen_string_literal: true
+
+require "json"
+
+# x402 Payment Protocol
+#
+# Native bindings to the Rust x402 core. Requirements and payments are
+# built from keyword arguments or wire-format JSON, and verified with
+# X402.verify_payment.
+module X402
+  REQUIREMENTS_HEADER = "X-Payment-Requirements"
+  PAYMENT_HEADER = "X-Payment"
+
+  # Base class of every x402 error
+  class Error < StandardError; end
+
+  # A header or JSON value could not be decoded
+  class DecodeError < Error; end
+
+  # The signature is invalid, or doesn't match the requirements
+  class InvalidSignature < Error; end
+
+  # The payment has expired, or is valid for longer than allowed
+  class PaymentExpired < Error; end
+
+  # The payment is for less than the required amount
+  class InsufficientAmount < Error; end
+
+  # Convert snake_case keyword arguments to the camelCase wire format.
+  # Amounts become decimal strings so large integers survive JSON.
+  def self.wire_json(attrs)
+    wire = attrs.to_h do |key, value|
+      key = key.to_s.gsub(/_([a-z])/) { Regexp.last_match(1).upcase }
+      value = value.to_s if key == "amount"
+      value = value.map { |recipient, share| { "recipient" => recipient, "share" => share.to_s } } if key == "splits"
+      [key, value]
+    end
+    JSON.generate(wire)
+  end
+end
+
+require "x402/x402_native"
+
+module X402
+  # Schemes, networks, signature algorithms, wire formats and features the
+  # native extension supports, as a Hash in the shape every SDK shares
+  def self.capabilities
+    JSON.parse(capabilities_json)
+  end
+
+  class PaymentRequirements
+    # Build requirements from keyword arguments, e.g.
+    # PaymentRequirements.build(amount: 1000, recipient: "0x...", network: "base", resource: "/api")
+    def self.build(**attrs)
+      from_json(X402.wire_json(attrs))
+    end
+  end
+
+  class PaymentPayload
+    # Build a payload from keyword arguments
+    def self.build(**attrs)
+      from_json(X402.wire_json(attrs))
+    end
+  end
+end
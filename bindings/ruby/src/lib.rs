@@ -0,0 +1,323 @@
+//! Ruby bindings for x402-core using magnus
+//!
+//! This crate builds the `x402/x402_native` extension loaded by the `x402`
+//! gem. It wraps the core types as `X402::PaymentRequirements`,
+//! `X402::PaymentPayload` and `X402::SignedPayment`, and exposes header
+//! encoding/decoding and verification. Values are built from wire-format
+//! JSON; `lib/x402.rb` adds keyword-argument constructors on top. Errors
+//! are raised as the `X402::Error` subclasses defined in `lib/x402.rb`.
+
+use magnus::{
+    function, method, prelude::*, typed_data::Obj, Error, ExceptionClass, RModule, RString, Ruby, TryConvert, Value,
+};
+
+use x402_core::{
+    capabilities, canonical_json, decode_payment_header, decode_requirements_header, encode_payment_header,
+    encode_requirements_header, verify_payment, verify_payment_at, Network, PaymentPayload, PaymentRequirements,
+    SignedPayment, X402Error,
+};
+
+/// Raise an x402 error as the matching `X402::Error` subclass
+fn x402_err_to_rb(ruby: &Ruby, e: X402Error) -> Error {
+    use X402Error::*;
+    let class = match &e {
+        InvalidHeader(_) | Base64(_) | Json(_) | Cbor(_) | UnsupportedVersion(_) | EncodingError(_)
+        | LimitExceeded { .. } => "DecodeError",
+        InvalidSignature(_) | Ecdsa(_) | InvalidAddress(_) => "InvalidSignature",
+        PaymentExpired | ValidityTooLong { .. } => "PaymentExpired",
+        InsufficientAmount { .. } => "InsufficientAmount",
+        _ => "Error",
+    };
+    let message = e.chain_message();
+    match ruby
+        .class_object()
+        .const_get::<_, RModule>("X402")
+        .and_then(|module| module.const_get::<_, ExceptionClass>(class))
+    {
+        Ok(class) => Error::new(class, message),
+        Err(_) => Error::new(ruby.exception_runtime_error(), message),
+    }
+}
+
+fn json_err(ruby: &Ruby, e: serde_json::Error) -> Error {
+    Error::new(ruby.exception_arg_error(), format!("Invalid JSON: {}", e))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(ruby: &Ruby, json: &str) -> Result<T, Error> {
+    serde_json::from_str(json).map_err(|e| json_err(ruby, e))
+}
+
+fn to_json<T: serde::Serialize>(ruby: &Ruby, value: &T) -> Result<String, Error> {
+    serde_json::to_string(value).map_err(|e| json_err(ruby, e))
+}
+
+/// Canonical JSON of a value, so equal values compare equal whatever the
+/// order of their `extra` keys
+fn canonical<T: serde::Serialize>(value: &T) -> Option<String> {
+    serde_json::to_value(value).ok().map(|value| canonical_json(&value))
+}
+
+/// A token amount as a Ruby Integer of any size
+fn amount_to_rb(ruby: &Ruby, amount: alloy_primitives::U256) -> Result<Value, Error> {
+    ruby.module_kernel().funcall("Integer", (amount.to_string(),))
+}
+
+/// Network name in the wire format, e.g. `"base"`
+fn network_name(network: Network) -> String {
+    serde_json::to_value(network)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Ruby wrapper for PaymentRequirements
+#[magnus::wrap(class = "X402::PaymentRequirements", free_immediately, size)]
+struct RbPaymentRequirements {
+    inner: PaymentRequirements,
+}
+
+impl RbPaymentRequirements {
+    fn from_json(ruby: &Ruby, json: String) -> Result<Self, Error> {
+        Ok(Self { inner: from_json(ruby, &json)? })
+    }
+
+    fn to_json(ruby: &Ruby, rb_self: &Self) -> Result<String, Error> {
+        to_json(ruby, &rb_self.inner)
+    }
+
+    fn from_header(ruby: &Ruby, header: String) -> Result<Self, Error> {
+        let inner = decode_requirements_header(header).map_err(|e| x402_err_to_rb(ruby, e))?;
+        Ok(Self { inner })
+    }
+
+    fn to_header(ruby: &Ruby, rb_self: &Self) -> Result<String, Error> {
+        encode_requirements_header(&rb_self.inner).map_err(|e| x402_err_to_rb(ruby, e))
+    }
+
+    fn amount(ruby: &Ruby, rb_self: &Self) -> Result<Value, Error> {
+        amount_to_rb(ruby, rb_self.inner.amount)
+    }
+
+    fn recipient(&self) -> String {
+        self.inner.recipient.to_checksum(None)
+    }
+
+    fn network(&self) -> String {
+        network_name(self.inner.network)
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.inner.network.chain_id()
+    }
+
+    fn resource(&self) -> String {
+        self.inner.resource.clone()
+    }
+
+    fn token(&self) -> Option<String> {
+        self.inner.token.map(|token| token.to_checksum(None))
+    }
+
+    fn description(&self) -> Option<String> {
+        self.inner.description.clone()
+    }
+
+    fn expires_at(&self) -> Option<u64> {
+        self.inner.expires_at
+    }
+
+    fn max_timeout_seconds(&self) -> Option<u64> {
+        self.inner.max_timeout_seconds
+    }
+
+    fn eq(&self, other: Value) -> bool {
+        Obj::<Self>::try_convert(other).is_ok_and(|other| canonical(&self.inner) == canonical(&other.inner))
+    }
+
+    fn inspect(&self) -> String {
+        format!(
+            "#<X402::PaymentRequirements amount={} recipient={} network={} resource={:?}>",
+            self.inner.amount,
+            self.recipient(),
+            self.network(),
+            self.inner.resource
+        )
+    }
+}
+
+/// Ruby wrapper for PaymentPayload
+#[magnus::wrap(class = "X402::PaymentPayload", free_immediately, size)]
+#[derive(Clone)]
+struct RbPaymentPayload {
+    inner: PaymentPayload,
+}
+
+impl RbPaymentPayload {
+    fn from_json(ruby: &Ruby, json: String) -> Result<Self, Error> {
+        Ok(Self { inner: from_json(ruby, &json)? })
+    }
+
+    fn to_json(ruby: &Ruby, rb_self: &Self) -> Result<String, Error> {
+        to_json(ruby, &rb_self.inner)
+    }
+
+    /// The 32-byte hash the payer signs, as a binary string
+    fn message_hash(ruby: &Ruby, rb_self: &Self) -> RString {
+        ruby.str_from_slice(&rb_self.inner.message_hash())
+    }
+
+    fn amount(ruby: &Ruby, rb_self: &Self) -> Result<Value, Error> {
+        amount_to_rb(ruby, rb_self.inner.amount)
+    }
+
+    fn recipient(&self) -> String {
+        self.inner.recipient.to_checksum(None)
+    }
+
+    fn payer(&self) -> String {
+        self.inner.payer.to_checksum(None)
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.inner.chain_id
+    }
+
+    fn resource(&self) -> String {
+        self.inner.resource.clone()
+    }
+
+    fn nonce(&self) -> u64 {
+        self.inner.nonce
+    }
+
+    fn expires_at(&self) -> u64 {
+        self.inner.expires_at
+    }
+
+    fn eq(&self, other: Value) -> bool {
+        Obj::<Self>::try_convert(other).is_ok_and(|other| canonical(&self.inner) == canonical(&other.inner))
+    }
+
+    fn inspect(&self) -> String {
+        format!(
+            "#<X402::PaymentPayload amount={} payer={} recipient={} chain_id={} nonce={} expires_at={}>",
+            self.inner.amount,
+            self.payer(),
+            self.recipient(),
+            self.inner.chain_id,
+            self.inner.nonce,
+            self.inner.expires_at
+        )
+    }
+}
+
+/// Ruby wrapper for SignedPayment
+#[magnus::wrap(class = "X402::SignedPayment", free_immediately, size)]
+struct RbSignedPayment {
+    inner: SignedPayment,
+}
+
+impl RbSignedPayment {
+    fn new(payment: &RbPaymentPayload, signature: RString) -> Self {
+        // SAFETY: the bytes are copied before any other Ruby code runs
+        let signature = unsafe { signature.as_slice() }.to_vec();
+        Self { inner: SignedPayment { payment: payment.inner.clone(), signature, signatures: Vec::new() } }
+    }
+
+    fn from_header(ruby: &Ruby, header: String) -> Result<Self, Error> {
+        let inner = decode_payment_header(header).map_err(|e| x402_err_to_rb(ruby, e))?;
+        Ok(Self { inner })
+    }
+
+    fn to_header(ruby: &Ruby, rb_self: &Self) -> Result<String, Error> {
+        encode_payment_header(&rb_self.inner).map_err(|e| x402_err_to_rb(ruby, e))
+    }
+
+    fn to_json(ruby: &Ruby, rb_self: &Self) -> Result<String, Error> {
+        to_json(ruby, &rb_self.inner)
+    }
+
+    fn payment(&self) -> RbPaymentPayload {
+        RbPaymentPayload { inner: self.inner.payment.clone() }
+    }
+
+    /// ECDSA signature (65 bytes: r + s + v), as a binary string
+    fn signature(ruby: &Ruby, rb_self: &Self) -> RString {
+        ruby.str_from_slice(&rb_self.inner.signature)
+    }
+}
+
+/// Verify an `X-Payment` header value against requirements, returning the
+/// payer's address
+fn rb_verify_payment(ruby: &Ruby, header: String, requirements: &RbPaymentRequirements) -> Result<String, Error> {
+    let payment = decode_payment_header(header).map_err(|e| x402_err_to_rb(ruby, e))?;
+    let payer = verify_payment(&payment, &requirements.inner).map_err(|e| x402_err_to_rb(ruby, e))?;
+    Ok(payer.to_checksum(None))
+}
+
+/// `verify_payment` as of the unix time `now`
+fn rb_verify_payment_at(
+    ruby: &Ruby,
+    header: String,
+    requirements: &RbPaymentRequirements,
+    now: u64,
+) -> Result<String, Error> {
+    let payment = decode_payment_header(header).map_err(|e| x402_err_to_rb(ruby, e))?;
+    let payer = verify_payment_at(&payment, &requirements.inner, now).map_err(|e| x402_err_to_rb(ruby, e))?;
+    Ok(payer.to_checksum(None))
+}
+
+/// Protocol surface compiled into the extension, as JSON
+fn rb_capabilities_json(ruby: &Ruby) -> Result<String, Error> {
+    to_json(ruby, &capabilities())
+}
+
+#[magnus::init]
+fn init(ruby: &Ruby) -> Result<(), Error> {
+    let module = ruby.define_module("X402")?;
+
+    let class = module.define_class("PaymentRequirements", ruby.class_object())?;
+    class.define_singleton_method("from_json", function!(RbPaymentRequirements::from_json, 1))?;
+    class.define_singleton_method("from_header", function!(RbPaymentRequirements::from_header, 1))?;
+    class.define_method("to_json", method!(RbPaymentRequirements::to_json, 0))?;
+    class.define_method("to_header", method!(RbPaymentRequirements::to_header, 0))?;
+    class.define_method("amount", method!(RbPaymentRequirements::amount, 0))?;
+    class.define_method("recipient", method!(RbPaymentRequirements::recipient, 0))?;
+    class.define_method("network", method!(RbPaymentRequirements::network, 0))?;
+    class.define_method("chain_id", method!(RbPaymentRequirements::chain_id, 0))?;
+    class.define_method("resource", method!(RbPaymentRequirements::resource, 0))?;
+    class.define_method("token", method!(RbPaymentRequirements::token, 0))?;
+    class.define_method("description", method!(RbPaymentRequirements::description, 0))?;
+    class.define_method("expires_at", method!(RbPaymentRequirements::expires_at, 0))?;
+    class.define_method("max_timeout_seconds", method!(RbPaymentRequirements::max_timeout_seconds, 0))?;
+    class.define_method("==", method!(RbPaymentRequirements::eq, 1))?;
+    class.define_method("inspect", method!(RbPaymentRequirements::inspect, 0))?;
+
+    let class = module.define_class("PaymentPayload", ruby.class_object())?;
+    class.define_singleton_method("from_json", function!(RbPaymentPayload::from_json, 1))?;
+    class.define_method("to_json", method!(RbPaymentPayload::to_json, 0))?;
+    class.define_method("message_hash", method!(RbPaymentPayload::message_hash, 0))?;
+    class.define_method("amount", method!(RbPaymentPayload::amount, 0))?;
+    class.define_method("recipient", method!(RbPaymentPayload::recipient, 0))?;
+    class.define_method("payer", method!(RbPaymentPayload::payer, 0))?;
+    class.define_method("chain_id", method!(RbPaymentPayload::chain_id, 0))?;
+    class.define_method("resource", method!(RbPaymentPayload::resource, 0))?;
+    class.define_method("nonce", method!(RbPaymentPayload::nonce, 0))?;
+    class.define_method("expires_at", method!(RbPaymentPayload::expires_at, 0))?;
+    class.define_method("==", method!(RbPaymentPayload::eq, 1))?;
+    class.define_method("inspect", method!(RbPaymentPayload::inspect, 0))?;
+
+    let class = module.define_class("SignedPayment", ruby.class_object())?;
+    class.define_singleton_method("new", function!(RbSignedPayment::new, 2))?;
+    class.define_singleton_method("from_header", function!(RbSignedPayment::from_header, 1))?;
+    class.define_method("to_header", method!(RbSignedPayment::to_header, 0))?;
+    class.define_method("to_json", method!(RbSignedPayment::to_json, 0))?;
+    class.define_method("payment", method!(RbSignedPayment::payment, 0))?;
+    class.define_method("signature", method!(RbSignedPayment::signature, 0))?;
+
+    module.define_module_function("verify_payment", function!(rb_verify_payment, 2))?;
+    module.define_module_function("verify_payment_at", function!(rb_verify_payment_at, 3))?;
+    module.define_module_function("capabilities_json", function!(rb_capabilities_json, 0))?;
+
+    Ok(())
+}
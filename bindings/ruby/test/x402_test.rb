@@ -0,0 +1,69 @@
+# frozen_string_literal: true
+
+require "json"
+require "minitest/autorun"
+require "x402"
+
+class X402Test < Minitest::Test
+  VECTORS = JSON.parse(File.read(File.expand_path("../../../spec/test-vectors.json", __dir__)))
+
+  ERRORS = {
+    "payment_expired" => X402::PaymentExpired,
+    "insufficient_amount" => X402::InsufficientAmount,
+    "invalid_signature" => X402::InvalidSignature,
+    "unsupported_network" => X402::Error,
+  }.freeze
+
+  def requirements
+    X402::PaymentRequirements.build(
+      amount: 2**80,
+      recipient: "0x1111111111111111111111111111111111111111",
+      network: "base",
+      resource: "/api",
+      expires_at: 1_700_000_300,
+    )
+  end
+
+  def test_requirements_round_trip
+    decoded = X402::PaymentRequirements.from_header(requirements.to_header)
+    assert_equal requirements, decoded
+    assert_equal 2**80, decoded.amount
+    assert_equal 8453, decoded.chain_id
+    assert_equal 1_700_000_300, decoded.expires_at
+  end
+
+  def test_errors
+    error = assert_raises(X402::DecodeError) { X402::SignedPayment.from_header("not a header") }
+    refute_empty error.message
+    assert_raises(ArgumentError) { X402::PaymentRequirements.from_json("{}") }
+  end
+
+  def test_vectors
+    VECTORS["vectors"].each do |vector|
+      requirements = X402::PaymentRequirements.from_json(JSON.generate(vector["requirements"]))
+      verify = -> { X402.verify_payment_at(vector["paymentHeader"], requirements, vector["verifyAt"]) }
+      expected = vector["expected"]
+      if expected["result"] == "valid"
+        assert_equal expected["payer"], verify.call.downcase, vector["name"]
+      else
+        error = assert_raises(X402::Error, vector["name"]) { verify.call }
+        assert_instance_of ERRORS.fetch(expected["error"]), error, vector["name"]
+      end
+    end
+  end
+
+  def test_capabilities
+    capabilities = X402.capabilities
+    assert_includes capabilities["networks"], "base"
+    assert_includes capabilities["wireFormats"], "json"
+  end
+
+  def test_signed_payment
+    vector = VECTORS["vectors"].first
+    signed = X402::SignedPayment.from_header(vector["paymentHeader"])
+    signature = [vector["signature"].delete_prefix("0x")].pack("H*")
+    assert_equal signature, signed.signature
+    assert_equal vector["messageHash"], "0x#{signed.payment.message_hash.unpack1("H*")}"
+    assert_equal signed.payment, X402::SignedPayment.new(signed.payment, signature).payment
+  end
+end
@@ -0,0 +1,92 @@
+//! WebAssembly bindings for x402-core using wasm-bindgen
+//!
+//! This crate lets browser dApps build x402 payments: decode the
+//! `X-Payment-Requirements` header of a 402 response, compute the payload's
+//! message hash for the wallet to sign, and encode the `X-Payment` header.
+//! Values cross the boundary as plain JavaScript objects in the JSON wire
+//! format (camelCase keys, amounts as decimal strings).
+
+use wasm_bindgen::prelude::*;
+
+use alloy_primitives::hex;
+use serde::Serialize;
+
+use x402_core::{
+    decode_requirements_header, encode_payment_header, encode_requirements_header, Nonce,
+    PaymentPayload, PaymentRequirements, SignedPayment, X402Error,
+};
+
+fn x402_err_to_js(e: X402Error) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// Convert a core value to a JavaScript object in its JSON wire form
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    // Maps as plain objects, so `extra` reads like any other field
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    value.serialize(&serializer).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Convert a JavaScript object in JSON wire form to a core value
+fn from_js<T: serde::de::DeserializeOwned>(value: JsValue, what: &str) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value).map_err(|e| JsError::new(&format!("Invalid {}: {}", what, e)))
+}
+
+/// Decode an `X-Payment-Requirements` header value into requirements
+#[wasm_bindgen(js_name = decodeRequirements)]
+pub fn decode_requirements(header: &str) -> Result<JsValue, JsError> {
+    to_js(&decode_requirements_header(header).map_err(x402_err_to_js)?)
+}
+
+/// Encode requirements as an `X-Payment-Requirements` header value
+#[wasm_bindgen(js_name = encodeRequirements)]
+pub fn encode_requirements(requirements: JsValue) -> Result<String, JsError> {
+    let requirements: PaymentRequirements = from_js(requirements, "requirements")?;
+    encode_requirements_header(&requirements).map_err(x402_err_to_js)
+}
+
+/// Build the payload paying `requirements` from `payer`, valid until
+/// `expiresAt` (unix seconds), with a random nonce
+#[wasm_bindgen(js_name = createPayment)]
+pub fn create_payment(requirements: JsValue, payer: &str, expires_at: u64) -> Result<JsValue, JsError> {
+    let requirements: PaymentRequirements = from_js(requirements, "requirements")?;
+    let payer = payer.parse().map_err(|e| JsError::new(&format!("Invalid payer address: {}", e)))?;
+    let payload = PaymentPayload::builder()
+        .requirements(&requirements)
+        .payer(payer)
+        .nonce(Nonce::random())
+        .expires_at(expires_at)
+        .build()
+        .map_err(x402_err_to_js)?;
+    to_js(&payload)
+}
+
+/// The 32-byte hash a wallet signs for `payload`, as 0x-prefixed hex
+///
+/// Verifiers recover the signer from this hash directly, so it must be
+/// signed as-is (`eth_sign`), not through `personal_sign`, which signs an
+/// EIP-191 prefixed message instead.
+#[wasm_bindgen(js_name = messageHash)]
+pub fn message_hash(payload: JsValue) -> Result<String, JsError> {
+    let payload: PaymentPayload = from_js(payload, "payment")?;
+    Ok(hex::encode_prefixed(payload.message_hash()))
+}
+
+/// Encode a payload and its 65-byte signature (0x-prefixed hex, r + s + v)
+/// as an `X-Payment` header value
+#[wasm_bindgen(js_name = encodePayment)]
+pub fn encode_payment(payload: JsValue, signature: &str) -> Result<String, JsError> {
+    let payment: PaymentPayload = from_js(payload, "payment")?;
+    let signature = hex::decode(signature).map_err(|e| JsError::new(&format!("Invalid signature: {}", e)))?;
+    if signature.len() != 65 {
+        return Err(JsError::new(&format!("Invalid signature: expected 65 bytes, got {}", signature.len())));
+    }
+    encode_payment_header(&SignedPayment { payment, signature, signatures: Vec::new() }).map_err(x402_err_to_js)
+}
+
+/// Protocol surface compiled into this module: schemes, networks, signature
+/// algorithms, wire formats and features
+#[wasm_bindgen]
+pub fn capabilities() -> Result<JsValue, JsError> {
+    to_js(&x402_core::capabilities())
+}
@@ -0,0 +1,92 @@
+//! Private keys and payment signing
+//!
+//! [`SigningKey`] wipes its scalar on drop; the raw bytes keys are parsed
+//! from or generated into are wiped here as well.
+
+use alloy_primitives::{hex, keccak256, Address};
+use k256::ecdsa::SigningKey;
+use x402_core::{PaymentPayload, Result, X402Error};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+// Keys must stay wiped on drop if k256 ever changes
+const _: fn() = || {
+    fn zeroize_on_drop<T: ZeroizeOnDrop>() {}
+    zeroize_on_drop::<SigningKey>();
+};
+
+/// Parse a hex private key, with or without `0x`
+pub fn parse_key(private_key_hex: &str) -> Result<SigningKey> {
+    let private_key_hex = private_key_hex.trim();
+    let bytes = hex::decode(private_key_hex.strip_prefix("0x").unwrap_or(private_key_hex))
+        .map(Zeroizing::new)
+        .map_err(|e| X402Error::InvalidConfig(format!("invalid private key: {}", e)))?;
+    if bytes.len() != 32 {
+        return Err(X402Error::InvalidConfig(format!(
+            "invalid private key: expected 32 bytes, got {}",
+            bytes.len()
+        )));
+    }
+    SigningKey::from_slice(&bytes).map_err(|e| X402Error::InvalidConfig(format!("invalid private key: {}", e)))
+}
+
+/// A random key from the OS CSPRNG
+pub fn generate_key() -> Result<SigningKey> {
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    loop {
+        getrandom::getrandom(&mut *bytes)
+            .map_err(|e| X402Error::InvalidConfig(format!("no system randomness: {}", e)))?;
+        // Fails only for zero or values above the curve order
+        if let Ok(key) = SigningKey::from_slice(&*bytes) {
+            return Ok(key);
+        }
+    }
+}
+
+/// `key` as 0x-prefixed hex, wiped when dropped
+pub fn key_hex(key: &SigningKey) -> Zeroizing<String> {
+    let mut bytes: [u8; 32] = key.to_bytes().into();
+    let hex = Zeroizing::new(hex::encode_prefixed(bytes));
+    bytes.zeroize();
+    hex
+}
+
+/// Address of `key`
+pub fn key_address(key: &SigningKey) -> Address {
+    let point = key.verifying_key().to_encoded_point(false);
+    Address::from_slice(&keccak256(&point.as_bytes()[1..])[12..])
+}
+
+/// Sign `payload`'s message hash, returning r ‖ s ‖ v with v = 27 or 28
+pub fn sign_payload(payload: &PaymentPayload, key: &SigningKey) -> Result<Vec<u8>> {
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&payload.message_hash())?;
+    let mut bytes = signature.to_bytes().to_vec();
+    bytes.push(recovery_id.to_byte() + 27);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x402_core::{recover_signer, Network, PaymentRequirements, SignedPayment};
+
+    #[test]
+    fn test_signature_recovers_to_key_address() {
+        let key = parse_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        let requirements = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/").unwrap();
+        let payment = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(key_address(&key))
+            .expires_at(2_000_000_000)
+            .build()
+            .unwrap();
+        let signature = sign_payload(&payment, &key).unwrap();
+        let payment = SignedPayment { payment, signature, signatures: Vec::new() };
+        assert_eq!(recover_signer(&payment).unwrap(), key_address(&key));
+        assert!(parse_key("0x1234").is_err());
+        assert_ne!(generate_key().unwrap().to_bytes(), generate_key().unwrap().to_bytes());
+        assert_eq!(
+            key_hex(&key).as_str(),
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! `x402`: inspect, check and produce x402 payment headers
+//!
+//! Decoded headers are printed as pretty JSON on stdout; errors go to
+//! stderr with a non-zero exit status, so the tool composes with `jq` and
+//! shell scripts.
+
+mod key;
+mod serve;
+
+use alloy_primitives::{hex, Address};
+use clap::{Parser, Subcommand};
+use k256::ecdsa::SigningKey;
+use serde::Serialize;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x402_core::{
+    capabilities, decode_payment_header, decode_requirements_header, encode_payment_header, encode_requirements_header,
+    verify_payment_at, Capabilities, Network, PaymentPayload, PaymentRequirements, Result, SignedPayment, Stablecoin,
+    X402Error,
+};
+
+#[derive(Parser)]
+#[command(name = "x402", version, about = "Inspect, verify and create x402 payment headers")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decode an X-Payment-Requirements header
+    DecodeRequirements {
+        /// Header value
+        header: String,
+    },
+    /// Decode an X-Payment header
+    DecodePayment {
+        /// Header value
+        header: String,
+    },
+    /// Verify a payment header against a requirements header
+    Verify {
+        #[arg(long)]
+        requirements: String,
+        #[arg(long)]
+        payment: String,
+        /// Verify as of this unix time instead of now
+        #[arg(long)]
+        now: Option<u64>,
+    },
+    /// Build a requirements header for a stablecoin price
+    EncodeRequirements {
+        /// Price as a decimal, e.g. 0.01
+        #[arg(long)]
+        amount: String,
+        #[arg(long)]
+        recipient: Address,
+        #[arg(long, default_value = "base")]
+        network: Network,
+        /// Stablecoin symbol
+        #[arg(long, default_value = "USDC")]
+        token: String,
+        #[arg(long, default_value = "/")]
+        resource: String,
+        #[arg(long)]
+        description: Option<String>,
+        /// Longest validity, in seconds, a payment may ask for
+        #[arg(long)]
+        max_timeout_seconds: Option<u64>,
+    },
+    /// Sign a payment for a requirements header and print the X-Payment header
+    Sign {
+        /// Hex private key of the payer
+        #[arg(long, env = "X402_PRIVATE_KEY", hide_env_values = true)]
+        key: String,
+        #[arg(long)]
+        requirements: String,
+        /// Seconds the payment stays valid (default: as long as the
+        /// requirements allow)
+        #[arg(long)]
+        expires_in: Option<u64>,
+    },
+    /// Sign a payment with a fresh throwaway key and print the X-Payment header
+    ///
+    /// The payer's address and key go to stderr, so the header can be
+    /// captured directly, e.g. `curl -H "X-Payment: $(x402 gen-test-payment ...)"`.
+    /// The payer holds no funds: use it against servers that only verify
+    /// signatures, or fund it on a testnet.
+    GenTestPayment {
+        #[arg(long)]
+        requirements: String,
+        /// Seconds the payment stays valid (default: as long as the
+        /// requirements allow)
+        #[arg(long)]
+        expires_in: Option<u64>,
+    },
+    /// Run a paywall reverse proxy in front of an existing HTTP service
+    Serve {
+        /// Price per request, e.g. 0.01USDC
+        #[arg(long)]
+        price: String,
+        #[arg(long)]
+        recipient: Address,
+        #[arg(long, default_value = "base")]
+        network: Network,
+        /// Upstream service, e.g. http://localhost:3000
+        #[arg(long)]
+        proxy: String,
+        #[arg(long, default_value = "127.0.0.1:4020")]
+        listen: String,
+        /// Longest validity, in seconds, a payment may ask for
+        #[arg(long, default_value_t = 300)]
+        max_timeout_seconds: u64,
+        /// Seconds to wait for the upstream's response headers
+        #[arg(long, default_value_t = 30)]
+        timeout_seconds: u64,
+        /// Requests handled at once; more are answered with a 503
+        #[arg(long, default_value_t = 256)]
+        max_concurrency: usize,
+        /// Route a path prefix, optionally on one host, to a tenant, as
+        /// [HOST]PREFIX=TENANT:RECIPIENT[:PRICE]; repeatable. With routes,
+        /// unrouted paths get a 404
+        #[arg(long = "route")]
+        routes: Vec<String>,
+    },
+    /// Print the schemes, networks, signature algorithms, wire formats and
+    /// features this build supports
+    Capabilities {
+        /// Instead, check that the capabilities in this JSON file (another
+        /// SDK's output) cover everything this build relies on
+        #[arg(long)]
+        peer: Option<std::path::PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("x402: {}", e.chain_message());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::DecodeRequirements { header } => print_json(&decode_requirements_header(header.trim())?),
+        Command::DecodePayment { header } => print_json(&DecodedPayment::new(decode_payment_header(header.trim())?)),
+        Command::Verify { requirements, payment, now } => {
+            let requirements = decode_requirements_header(requirements.trim())?;
+            let payment = decode_payment_header(payment.trim())?;
+            let payer = verify_payment_at(&payment, &requirements, now.unwrap_or_else(unix_now))?;
+            println!("valid payment from {}", payer);
+            Ok(())
+        }
+        Command::EncodeRequirements {
+            amount,
+            recipient,
+            network,
+            token,
+            resource,
+            description,
+            max_timeout_seconds,
+        } => {
+            let coin = Stablecoin::from_symbol(&token)
+                .ok_or_else(|| X402Error::InvalidConfig(format!("unknown stablecoin {:?}", token)))?;
+            let mut requirements = PaymentRequirements::stablecoin(network, coin, &amount, recipient, resource)?;
+            requirements.description = description;
+            requirements.max_timeout_seconds = max_timeout_seconds;
+            println!("{}", encode_requirements_header(&requirements)?);
+            Ok(())
+        }
+        Command::Sign { key, requirements, expires_in } => {
+            let key = zeroize::Zeroizing::new(key);
+            println!("{}", sign_header(&key::parse_key(&key)?, &requirements, expires_in)?);
+            Ok(())
+        }
+        Command::GenTestPayment { requirements, expires_in } => {
+            let key = key::generate_key()?;
+            let header = sign_header(&key, &requirements, expires_in)?;
+            eprintln!(
+                "x402: throwaway payer {} (key {})",
+                key::key_address(&key),
+                key::key_hex(&key).as_str()
+            );
+            println!("{}", header);
+            Ok(())
+        }
+        Command::Serve {
+            price,
+            recipient,
+            network,
+            proxy,
+            listen,
+            max_timeout_seconds,
+            timeout_seconds,
+            max_concurrency,
+            routes,
+        } => {
+            let config = serve::ProxyConfig {
+                price: serve::parse_price(&price)?,
+                recipient,
+                network,
+                upstream: serve::parse_upstream(&proxy)?,
+                max_timeout_seconds,
+                request_timeout: std::time::Duration::from_secs(timeout_seconds),
+                max_concurrency,
+            };
+            let mut proxy = serve::Proxy::bind(&listen, config.clone())?;
+            if !routes.is_empty() {
+                proxy = proxy.with_resolver(Arc::new(serve::parse_routes(&routes, &config)?));
+            }
+            eprintln!(
+                "x402 serve: charging {} per request on http://{}, forwarding to {}",
+                config.price,
+                proxy.local_addr()?,
+                config.upstream
+            );
+            proxy.serve()
+        }
+        Command::Capabilities { peer: None } => print_json(&capabilities()),
+        Command::Capabilities { peer: Some(path) } => {
+            let json = std::fs::read_to_string(&path)
+                .map_err(|e| X402Error::InvalidConfig(format!("can't read {}: {}", path.display(), e)))?;
+            let peer: Capabilities = serde_json::from_str(&json)
+                .map_err(|e| X402Error::InvalidConfig(format!("{} is not capabilities JSON: {}", path.display(), e)))?;
+            capabilities().compatible_with(&peer)?;
+            println!("compatible with {}", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// Sign a payment for the requirements header `requirements` with `key`
+fn sign_header(key: &SigningKey, requirements: &str, expires_in: Option<u64>) -> Result<String> {
+    let requirements = decode_requirements_header(requirements.trim())?;
+    let now = unix_now();
+    let expires_at = expires_in.map_or_else(|| requirements.payment_expires_at(now), |secs| now.saturating_add(secs));
+    let payment = PaymentPayload::builder()
+        .requirements(&requirements)
+        .payer(key::key_address(key))
+        .expires_at(expires_at)
+        .build()?;
+    let signature = key::sign_payload(&payment, key)?;
+    encode_payment_header(&SignedPayment { payment, signature, signatures: Vec::new() })
+}
+
+/// A decoded payment with its signature as hex rather than a byte array
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DecodedPayment {
+    payment: PaymentPayload,
+    signature: String,
+    message_hash: String,
+}
+
+impl DecodedPayment {
+    fn new(signed: SignedPayment) -> Self {
+        Self {
+            message_hash: hex::encode_prefixed(signed.payment.message_hash()),
+            signature: hex::encode_prefixed(&signed.signature),
+            payment: signed.payment,
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| X402Error::EncodingError(e.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
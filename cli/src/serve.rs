@@ -0,0 +1,521 @@
+//! `x402 serve`: a paywall reverse proxy in front of an existing service
+//!
+//! Requests without a valid `X-Payment` header get a 402 with requirements
+//! for the configured price, as a [`DefaultPaywall`] page for browsers that
+//! ask for HTML; paid requests are forwarded to the upstream
+//! with the header replaced by `X-Payment-Payer`. Payments are kept in a
+//! [`MemoryLedger`], so a payment header can only be spent once per run.
+//!
+//! With `--route`, one proxy serves several tenants: each request's host
+//! and path pick the recipient and price through a [`RouteTable`], the
+//! tenant named in [`X402_TENANT_HEADER`] choosing only among the routes
+//! they match, and paths no route covers get a 404. The upstream gets the
+//! resolved tenant in [`X402_TENANT_HEADER`], never the client's value.
+//!
+//! The proxy is a hyper HTTP/1.1 server behind a tower stack: at most
+//! [`ProxyConfig::max_concurrency`] requests are handled at once and the
+//! rest are shed with a 503, a request whose response headers take longer
+//! than [`ProxyConfig::request_timeout`] gets a 504, and a client has
+//! [`HEADER_READ_TIMEOUT`] to send its request headers.
+
+use alloy_primitives::Address;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{HeaderValue, ACCEPT, CONNECTION, CONTENT_TYPE, HOST};
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::{BoxError, ServiceBuilder};
+use x402_core::{
+    decode_payment_header, prefers_html, verify_payment, DefaultPaywall, LedgerEntry, MemoryLedger, Money, Network,
+    PaymentLedger, PaymentRequiredResponse, PaymentRequirements, PaywallRenderer, RecipientResolver, ResourceMatcher,
+    Result, RouteTable, TenantRoute, X402Error, X402_PAYMENT_HEADER, X402_REQUIREMENTS_HEADER, X402_TENANT_HEADER,
+};
+
+/// How long a client may take to send its request headers
+pub const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header carrying the verified payer to the upstream
+pub const PAYER_HEADER: &str = "X-Payment-Payer";
+
+type Body = BoxBody<Bytes, hyper::Error>;
+
+/// What the proxy charges and where it forwards paid requests
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub price: Money,
+    pub recipient: Address,
+    pub network: Network,
+    /// Upstream `host:port`
+    pub upstream: String,
+    /// Longest validity, in seconds, a payment may ask for
+    pub max_timeout_seconds: u64,
+    /// Longest wait for the upstream's response headers
+    pub request_timeout: Duration,
+    /// Requests handled at once; more are answered with a 503
+    pub max_concurrency: usize,
+}
+
+/// Parse a price such as `0.01USDC` or `0.01 USDC`
+pub fn parse_price(price: &str) -> Result<Money> {
+    let price = price.trim();
+    match price.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) if !price.contains(' ') => format!("{} {}", &price[..at], &price[at..]).parse(),
+        _ => price.parse(),
+    }
+}
+
+/// `host:port` of an `http://` upstream URL
+pub fn parse_upstream(url: &str) -> Result<String> {
+    let authority = url
+        .strip_prefix("http://")
+        .ok_or_else(|| X402Error::InvalidConfig(format!("upstream {:?} must be an http:// URL", url)))?
+        .trim_end_matches('/');
+    if authority.is_empty() || authority.contains('/') {
+        return Err(X402Error::InvalidConfig(format!("upstream {:?} must be http://host[:port]", url)));
+    }
+    Ok(if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) })
+}
+
+/// Parse `--route` values, `[HOST]PREFIX=TENANT:RECIPIENT[:PRICE]`, into a
+/// route table; routes without a price charge `config.price`, and routes
+/// without a host match any host
+pub fn parse_routes(routes: &[String], config: &ProxyConfig) -> Result<RouteTable> {
+    routes.iter().try_fold(RouteTable::new(), |table, spec| {
+        let invalid =
+            || X402Error::InvalidConfig(format!("route {:?} must be [HOST]PREFIX=TENANT:RECIPIENT[:PRICE]", spec));
+        let (location, target) = spec.split_once('=').ok_or_else(invalid)?;
+        let (host, prefix) = location.find('/').map(|at| location.split_at(at)).ok_or_else(invalid)?;
+        let mut parts = target.splitn(3, ':');
+        let (Some(tenant), Some(recipient)) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        if tenant.is_empty() {
+            return Err(invalid());
+        }
+        let recipient: Address = recipient.parse().map_err(|_| invalid())?;
+        let price = parts.next().map(parse_price).transpose()?.unwrap_or_else(|| config.price.clone());
+        let quoted = PaymentRequirements::priced(config.network, &price, recipient, prefix)?;
+        let template = PaymentRequirements::priced(config.network, &config.price, config.recipient, prefix)?;
+        if quoted.token != template.token {
+            return Err(X402Error::InvalidConfig(format!("route {:?} must be priced in {}", spec, config.price)));
+        }
+        let route = TenantRoute { tenant: tenant.to_string(), recipient, amount: quoted.amount };
+        let matcher = ResourceMatcher::Prefix(prefix.to_string());
+        Ok(if host.is_empty() { table.route(matcher, route) } else { table.route_host(host, matcher, route) })
+    })
+}
+
+/// The paywall proxy; see the [module docs](self)
+pub struct Proxy {
+    listener: TcpListener,
+    config: ProxyConfig,
+    ledger: MemoryLedger,
+    resolver: Option<Arc<dyn RecipientResolver>>,
+}
+
+impl Proxy {
+    pub fn bind(addr: &str, config: ProxyConfig) -> Result<Self> {
+        // Fail at startup, not on the first request, if the price is unusable
+        PaymentRequirements::priced(config.network, &config.price, config.recipient, "/")?;
+        if config.max_concurrency == 0 {
+            return Err(X402Error::InvalidConfig("max concurrency must be at least 1".to_string()));
+        }
+        let listener =
+            TcpListener::bind(addr).map_err(|e| X402Error::InvalidConfig(format!("cannot bind {}: {}", addr, e)))?;
+        Ok(Self { listener, config, ledger: MemoryLedger::new(), resolver: None })
+    }
+
+    /// Route requests to tenants with `resolver`, by host, path and the
+    /// tenant in [`X402_TENANT_HEADER`]; paths no tenant serves get a 404
+    pub fn with_resolver(mut self, resolver: Arc<dyn RecipientResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<std::net::SocketAddr> {
+        self.listener.local_addr().map_err(|e| X402Error::InvalidConfig(e.to_string()))
+    }
+
+    /// Serve connections until the listener fails
+    pub fn serve(self) -> Result<()> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| X402Error::InvalidConfig(format!("cannot start runtime: {}", e)))?;
+        runtime.block_on(self.run())
+    }
+
+    async fn run(self) -> Result<()> {
+        let listener = self
+            .listener
+            .try_clone()
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                tokio::net::TcpListener::from_std(listener)
+            })
+            .map_err(|e| X402Error::InvalidConfig(format!("cannot listen: {}", e)))?;
+        let (timeout, max_concurrency) = (self.config.request_timeout, self.config.max_concurrency);
+        let proxy = Arc::new(self);
+        let service = ServiceBuilder::new()
+            .map_result(answer_errors)
+            .load_shed()
+            .layer(GlobalConcurrencyLimitLayer::new(max_concurrency))
+            .timeout(timeout)
+            .service_fn(move |request| {
+                let proxy = Arc::clone(&proxy);
+                async move { Ok::<_, Infallible>(proxy.handle(request).await) }
+            });
+
+        loop {
+            let (stream, _) =
+                listener.accept().await.map_err(|e| X402Error::InvalidConfig(format!("accept failed: {}", e)))?;
+            let service = TowerToHyperService::new(service.clone());
+            tokio::spawn(async move {
+                let connection = hyper::server::conn::http1::Builder::new()
+                    .timer(TokioTimer::new())
+                    .header_read_timeout(HEADER_READ_TIMEOUT)
+                    .serve_connection(TokioIo::new(stream), service);
+                if let Err(e) = connection.await {
+                    eprintln!("x402 serve: connection error: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn handle(&self, request: Request<Incoming>) -> Response<Body> {
+        let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+        let host = header(HOST.as_str()).or_else(|| request.uri().authority().map(|authority| authority.as_str()));
+        let (route, requirements) = match self.requirements(request.uri().path(), host, header(X402_TENANT_HEADER)) {
+            Ok(Some(routed)) => routed,
+            Ok(None) => return text_response(StatusCode::NOT_FOUND, "no tenant serves this path"),
+            Err(e) => return text_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+        let html = request.headers().get(ACCEPT).and_then(|value| value.to_str().ok()).is_some_and(prefers_html);
+        let Some(header) = request.headers().get(X402_PAYMENT_HEADER) else {
+            return payment_required(&requirements, None, html);
+        };
+        match self.accept_payment(&String::from_utf8_lossy(header.as_bytes()), &requirements) {
+            Ok(payer) => match self.forward(request, payer, route).await {
+                Ok(response) => response.map(BodyExt::boxed),
+                Err(e) => text_response(StatusCode::BAD_GATEWAY, &format!("upstream unavailable: {}", e)),
+            },
+            Err(e) if e.status_code() == 402 => payment_required(&requirements, Some(e.to_string()), html),
+            Err(e) => text_response(status(e.status_code()), &e.to_string()),
+        }
+    }
+
+    /// The tenant route, if tenant routing is on, and requirements for
+    /// `path` on `host`; `None` if routing is on and no tenant serves it
+    fn requirements(
+        &self,
+        path: &str,
+        host: Option<&str>,
+        tenant: Option<&str>,
+    ) -> Result<Option<(Option<TenantRoute>, PaymentRequirements)>> {
+        let mut requirements =
+            PaymentRequirements::priced(self.config.network, &self.config.price, self.config.recipient, path)?;
+        requirements.max_timeout_seconds = Some(self.config.max_timeout_seconds);
+        let Some(resolver) = &self.resolver else {
+            return Ok(Some((None, requirements)));
+        };
+        Ok(resolver.requirements(&requirements, path, host, tenant)?.map(|(route, routed)| (Some(route), routed)))
+    }
+
+    fn accept_payment(&self, header: &str, requirements: &PaymentRequirements) -> Result<Address> {
+        let payment = decode_payment_header(header)?;
+        let payer = verify_payment(&payment, requirements)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        self.ledger.record(&LedgerEntry::new(&payment.payment, now))?;
+        Ok(payer)
+    }
+
+    /// Send the request (and its body) upstream over a fresh connection,
+    /// naming the tenant `route` pays, if any
+    async fn forward(
+        &self,
+        request: Request<Incoming>,
+        payer: Address,
+        route: Option<TenantRoute>,
+    ) -> std::result::Result<Response<Incoming>, BoxError> {
+        let stream = tokio::net::TcpStream::connect(&self.config.upstream).await?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+
+        let (mut parts, body) = request.into_parts();
+        parts.uri = parts.uri.path_and_query().map_or("/", |target| target.as_str()).parse()?;
+        for name in [CONNECTION.as_str(), X402_PAYMENT_HEADER, PAYER_HEADER, X402_TENANT_HEADER] {
+            parts.headers.remove(name);
+        }
+        parts.headers.insert(HOST, HeaderValue::from_str(&self.config.upstream)?);
+        parts.headers.insert(PAYER_HEADER, HeaderValue::from_str(&payer.to_string())?);
+        if let Some(route) = route {
+            parts.headers.insert(X402_TENANT_HEADER, HeaderValue::from_str(&route.tenant)?);
+        }
+        Ok(sender.send_request(Request::from_parts(parts, body)).await?)
+    }
+}
+
+/// Answer requests the tower stack refused, instead of dropping the connection
+fn answer_errors(
+    result: std::result::Result<Response<Body>, BoxError>,
+) -> std::result::Result<Response<Body>, BoxError> {
+    Ok(result.unwrap_or_else(|e| error_response(&e)))
+}
+
+fn error_response(error: &BoxError) -> Response<Body> {
+    if error.is::<tower::timeout::error::Elapsed>() {
+        text_response(StatusCode::GATEWAY_TIMEOUT, "upstream timed out")
+    } else if error.is::<tower::load_shed::error::Overloaded>() {
+        text_response(StatusCode::SERVICE_UNAVAILABLE, "too many requests in flight")
+    } else {
+        text_response(StatusCode::INTERNAL_SERVER_ERROR, &error.to_string())
+    }
+}
+
+fn status(code: u16) -> StatusCode {
+    StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// The 402 for `requirements`: a paywall page if `html`, JSON otherwise
+fn payment_required(requirements: &PaymentRequirements, error: Option<String>, html: bool) -> Response<Body> {
+    let mut response = PaymentRequiredResponse::new(requirements.clone());
+    response.error = error;
+    let body = if html {
+        Ok(("text/html; charset=utf-8", DefaultPaywall::new().render(requirements, response.error.as_deref())))
+    } else {
+        response.to_json_body().map(|body| ("application/json", body))
+    };
+    match (response.header_value(), body) {
+        (Ok(header), Ok((content_type, body))) => {
+            let mut response = body_response(StatusCode::PAYMENT_REQUIRED, content_type, body);
+            if let Ok(header) = HeaderValue::from_str(&header) {
+                response.headers_mut().insert(X402_REQUIREMENTS_HEADER, header);
+            }
+            response
+        }
+        (Err(e), _) | (_, Err(e)) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+fn text_response(status: StatusCode, body: &str) -> Response<Body> {
+    body_response(status, "text/plain", body.to_string())
+}
+
+fn body_response(status: StatusCode, content_type: &'static str, body: String) -> Response<Body> {
+    let mut response = Response::new(Full::new(Bytes::from(body)).map_err(|never| match never {}).boxed());
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::{key_address, parse_key, sign_payload};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use x402_core::{decode_requirements_header, encode_payment_header, PaymentPayload, SignedPayment};
+
+    fn config(upstream: String) -> ProxyConfig {
+        ProxyConfig {
+            price: parse_price("0.01USDC").unwrap(),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            upstream,
+            max_timeout_seconds: 300,
+            request_timeout: Duration::from_secs(5),
+            max_concurrency: 16,
+        }
+    }
+
+    fn start(proxy: Proxy) -> std::net::SocketAddr {
+        let addr = proxy.local_addr().unwrap();
+        std::thread::spawn(move || proxy.serve());
+        addr
+    }
+
+    /// Send a raw request (`head` without the blank line) and read the whole response
+    fn send(addr: std::net::SocketAddr, head: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{}Connection: close\r\nContent-Length: {}\r\n\r\n{}", head, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn requirements_of(response: &str) -> PaymentRequirements {
+        // hyper sends header names in lowercase
+        let header = response.lines().find_map(|l| l.strip_prefix("x-payment-requirements: ")).unwrap();
+        decode_requirements_header(header).unwrap()
+    }
+
+    /// `X-Payment` header line paying `requirements`
+    fn pay(requirements: &PaymentRequirements) -> String {
+        let key = parse_key(&"42".repeat(32)).unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let payment = PaymentPayload::builder()
+            .requirements(requirements)
+            .payer(key_address(&key))
+            .expires_at(requirements.payment_expires_at(now))
+            .build()
+            .unwrap();
+        let signature = sign_payload(&payment, &key).unwrap();
+        let payment = SignedPayment { payment, signature, signatures: Vec::new() };
+        format!("X-Payment: {}\r\n", encode_payment_header(&payment).unwrap())
+    }
+
+    /// Upstream answering each request with its method, target, payer,
+    /// tenant (if named) and body
+    fn echo_upstream() -> String {
+        let upstream = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = upstream.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in upstream.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut lines = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    lines.push(line.trim_end().to_string());
+                }
+                let header = |name: &str| {
+                    lines.iter().find_map(|l| {
+                        let (n, v) = l.split_once(':')?;
+                        n.eq_ignore_ascii_case(name).then(|| v.trim().to_string())
+                    })
+                };
+                let length: u64 = header("content-length").map_or(0, |l| l.parse().unwrap());
+                let mut body = String::new();
+                reader.by_ref().take(length).read_to_string(&mut body).unwrap();
+                let request_line: Vec<&str> = lines[0].split(' ').collect();
+                let payer = header(PAYER_HEADER).unwrap();
+                let tenant = header(X402_TENANT_HEADER).map(|tenant| format!(" tenant={}", tenant));
+                let tenant = tenant.unwrap_or_default();
+                let echo = format!("{} {} {}{} {}", request_line[0], request_line[1], payer, tenant, body);
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", echo.len(), echo)
+                    .unwrap();
+            }
+        });
+        parse_upstream(&format!("http://{}", addr)).unwrap()
+    }
+
+    #[test]
+    fn test_proxy_charges_then_forwards() {
+        let addr = start(Proxy::bind("127.0.0.1:0", config(echo_upstream())).unwrap());
+        let head = "POST /report?x=1 HTTP/1.1\r\nHost: paywall\r\n";
+
+        let response = send(addr, head, "ping");
+        assert!(response.starts_with("HTTP/1.1 402"), "{}", response);
+        let requirements = requirements_of(&response);
+        assert_eq!(requirements.resource, "/report");
+
+        // A client's X-Tenant never reaches the upstream
+        let paid = format!("{}X-Tenant: mallory\r\n{}", head, pay(&requirements));
+        let response = send(addr, &paid, "ping");
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        let payer = key_address(&parse_key(&"42".repeat(32)).unwrap());
+        assert!(response.ends_with(&format!("POST /report?x=1 {} ping", payer)), "{}", response);
+
+        let response = send(addr, &paid, "ping");
+        assert!(response.starts_with("HTTP/1.1 402"), "{}", response);
+        assert!(parse_upstream("https://example.com").is_err());
+
+        let browser = format!("{}Accept: text/html,*/*;q=0.8\r\n", head);
+        let response = send(addr, &browser, "");
+        assert!(response.starts_with("HTTP/1.1 402"), "{}", response);
+        assert!(response.contains("content-type: text/html; charset=utf-8"), "{}", response);
+        assert!(response.contains("ethereum:") && response.contains("0.01 USDC"), "{}", response);
+        assert_eq!(requirements_of(&response).resource, "/report");
+    }
+
+    #[test]
+    fn test_proxy_times_out_and_sheds_load() {
+        // Upstream that accepts connections and never answers
+        let upstream = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_addr = upstream.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let _held: Vec<_> = upstream.incoming().collect();
+        });
+        let config = ProxyConfig {
+            request_timeout: Duration::from_millis(500),
+            max_concurrency: 1,
+            ..config(upstream_addr)
+        };
+        assert!(Proxy::bind("127.0.0.1:0", ProxyConfig { max_concurrency: 0, ..config.clone() }).is_err());
+        let addr = start(Proxy::bind("127.0.0.1:0", config).unwrap());
+        let head = "GET /slow HTTP/1.1\r\nHost: paywall\r\n";
+        let requirements = requirements_of(&send(addr, head, ""));
+
+        let paid = format!("{}{}", head, pay(&requirements));
+        let slow = std::thread::spawn(move || send(addr, &paid, ""));
+        std::thread::sleep(Duration::from_millis(200));
+        let shed = send(addr, head, "");
+        assert!(shed.starts_with("HTTP/1.1 503"), "{}", shed);
+        let slow = slow.join().unwrap();
+        assert!(slow.starts_with("HTTP/1.1 504"), "{}", slow);
+        assert!(send(addr, head, "").starts_with("HTTP/1.1 402"));
+    }
+
+    #[test]
+    fn test_proxy_routes_tenants() {
+        let config = config("127.0.0.1:9".to_string());
+        let alice = Address::repeat_byte(0xa1);
+        let routes = [
+            format!("/alice=alice:{}:0.05USDC", alice),
+            format!("/shared=bob:{}", Address::repeat_byte(0xb0)),
+        ];
+        let table = parse_routes(&routes, &config).unwrap();
+        assert!(parse_routes(&["/x=bob".to_string()], &config).is_err());
+        assert!(parse_routes(&[format!("/x=bob:{}:0.05DAI", alice)], &config).is_err());
+
+        let addr = start(Proxy::bind("127.0.0.1:0", config).unwrap().with_resolver(Arc::new(table)));
+        let get = |path: &str, headers: &str| {
+            send(addr, &format!("GET {} HTTP/1.1\r\nHost: paywall\r\n{}", path, headers), "")
+        };
+
+        assert!(get("/report", "").starts_with("HTTP/1.1 404"));
+        assert!(get("/shared", "X-Tenant: alice\r\n").starts_with("HTTP/1.1 404"));
+        let response = get("/alice/data", "");
+        assert!(response.starts_with("HTTP/1.1 402"), "{}", response);
+        let requirements = requirements_of(&response);
+        assert_eq!((requirements.recipient, requirements.resource.as_str()), (alice, "/alice/data"));
+        assert_eq!(requirements.amount, alloy_primitives::U256::from(50_000));
+        assert_eq!(requirements.max_timeout_seconds, Some(300));
+    }
+
+    #[test]
+    fn test_proxy_routes_by_host() {
+        let config = config(echo_upstream());
+        let alice = Address::repeat_byte(0xa1);
+        let routes = [
+            format!("alice.example/=alice:{}:0.05USDC", alice),
+            format!("bob.example/=bob:{}:0.001USDC", Address::repeat_byte(0xb0)),
+        ];
+        let table = parse_routes(&routes, &config).unwrap();
+        assert!(parse_routes(&[format!("alice.example=alice:{}", alice)], &config).is_err());
+        let addr = start(Proxy::bind("127.0.0.1:0", config).unwrap().with_resolver(Arc::new(table)));
+        let get = |host: &str, headers: &str| {
+            send(addr, &format!("GET /data HTTP/1.1\r\nHost: {}\r\n{}", host, headers), "")
+        };
+
+        // Naming the cheaper tenant doesn't move alice's host onto bob's price
+        assert!(get("alice.example", "X-Tenant: bob\r\n").starts_with("HTTP/1.1 404"));
+        assert!(get("paywall", "").starts_with("HTTP/1.1 404"));
+        let requirements = requirements_of(&get("alice.example:4020", ""));
+        assert_eq!((requirements.recipient, requirements.amount), (alice, alloy_primitives::U256::from(50_000)));
+
+        let response = get("alice.example", &pay(&requirements));
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with(" tenant=alice "), "{}", response);
+    }
+}
@@ -0,0 +1,74 @@
+#!/usr/bin/env python3
+"""Export criterion results as a baseline JSON, and compare two of them.
+
+    python3 benches/baseline.py export main > baseline.json
+    python3 benches/baseline.py compare old.json new.json --threshold 10
+
+``export`` reads the ``target/criterion`` results saved under a baseline
+name (``cargo bench -- --save-baseline main``) and prints, per benchmark,
+the mean and median time in nanoseconds. ``compare`` prints the change of
+each benchmark's mean and exits with status 1 if any got slower by more
+than the threshold (percent).
+"""
+
+import argparse
+import json
+import sys
+from pathlib import Path
+
+
+def export(criterion_dir: Path, baseline: str) -> dict:
+    results = {}
+    for info in sorted(criterion_dir.glob(f"**/{baseline}/benchmark.json")):
+        full_id = json.loads(info.read_text())["full_id"]
+        estimates = json.loads((info.parent / "estimates.json").read_text())
+        results[full_id] = {
+            "mean_ns": estimates["mean"]["point_estimate"],
+            "median_ns": estimates["median"]["point_estimate"],
+        }
+    return {"baseline": baseline, "benchmarks": results}
+
+
+def compare(old: dict, new: dict, threshold: float) -> bool:
+    regressed = False
+    for name, result in sorted(new["benchmarks"].items()):
+        before = old["benchmarks"].get(name)
+        if before is None:
+            print(f"{name:45} {result['mean_ns']:14.1f} ns  (new)")
+            continue
+        change = (result["mean_ns"] / before["mean_ns"] - 1) * 100
+        flag = ""
+        if change > threshold:
+            flag = "  REGRESSED"
+            regressed = True
+        print(f"{name:45} {result['mean_ns']:14.1f} ns  {change:+7.1f}%{flag}")
+    return not regressed
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    commands = parser.add_subparsers(dest="command", required=True)
+    export_cmd = commands.add_parser("export", help="print a saved criterion baseline as JSON")
+    export_cmd.add_argument("baseline", help="name given to --save-baseline")
+    export_cmd.add_argument("--criterion-dir", type=Path, default=Path("target/criterion"))
+    compare_cmd = commands.add_parser("compare", help="compare two exported baselines")
+    compare_cmd.add_argument("old", type=Path)
+    compare_cmd.add_argument("new", type=Path)
+    compare_cmd.add_argument("--threshold", type=float, default=10.0, help="allowed slowdown in percent")
+    args = parser.parse_args()
+
+    if args.command == "export":
+        results = export(args.criterion_dir, args.baseline)
+        if not results["benchmarks"]:
+            print(f"no results saved as {args.baseline!r} in {args.criterion_dir}", file=sys.stderr)
+            return 1
+        json.dump(results, sys.stdout, indent=2, sort_keys=True)
+        print()
+        return 0
+    old = json.loads(args.old.read_text())
+    new = json.loads(args.new.read_text())
+    return 0 if compare(old, new, args.threshold) else 1
+
+
+if __name__ == "__main__":
+    sys.exit(main())
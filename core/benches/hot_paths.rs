@@ -0,0 +1,86 @@
+//! Benchmarks for the verification hot path: header codecs, message
+//! hashing and signature recovery
+//!
+//! See `benches/README.md` for saving and comparing baselines.
+
+use alloy_primitives::Address;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use x402_core::testing::TestSigner;
+use x402_core::{
+    decode_payment_header, decode_payment_header_borrowed, encode_payment_header, encode_payment_header_binary,
+    encode_payment_header_cbor, recover_signer, verify_payments_batch_at, Network, PaymentRequirements, Result,
+    SignedPayment, VerifyOptions,
+};
+
+const NOW: u64 = 1_700_000_000;
+
+fn requirements() -> PaymentRequirements {
+    PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/reports/2024/quarterly").unwrap()
+}
+
+fn signed(signer: &TestSigner) -> SignedPayment {
+    signer.pay(&requirements(), NOW).unwrap()
+}
+
+type Encoder = fn(&SignedPayment) -> Result<String>;
+
+const FORMATS: [(&str, Encoder); 3] = [
+    ("json", encode_payment_header),
+    ("cbor", encode_payment_header_cbor),
+    ("binary", encode_payment_header_binary),
+];
+
+fn header_codecs(c: &mut Criterion) {
+    let payment = signed(&TestSigner::default());
+
+    let mut group = c.benchmark_group("header/encode");
+    for (name, encode) in FORMATS {
+        group.bench_function(name, |b| b.iter(|| encode(black_box(&payment)).unwrap()));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("header/decode");
+    for (name, encode) in FORMATS {
+        let header = encode(&payment).unwrap();
+        group.bench_function(name, |b| b.iter(|| decode_payment_header(black_box(&header)).unwrap()));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("header/decode_borrowed");
+    for (name, encode) in FORMATS {
+        let header = encode(&payment).unwrap();
+        let mut buf = Vec::new();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                decode_payment_header_borrowed(black_box(header.as_bytes()), &mut buf).unwrap().payment.nonce
+            })
+        });
+    }
+    group.finish();
+}
+
+fn hashing_and_recovery(c: &mut Criterion) {
+    let payment = signed(&TestSigner::default());
+    c.bench_function("message_hash", |b| b.iter(|| black_box(&payment.payment).message_hash()));
+    c.bench_function("recover_signer", |b| b.iter(|| recover_signer(black_box(&payment)).unwrap()));
+}
+
+fn batch_verification(c: &mut Criterion) {
+    let requirements = requirements();
+    let signers: Vec<TestSigner> = (0..16).map(TestSigner::new).collect();
+    let options = VerifyOptions::default();
+
+    let mut group = c.benchmark_group("verify_payments_batch");
+    for size in [1usize, 64, 1024] {
+        let payments: Vec<SignedPayment> = (0..size).map(|i| signed(&signers[i % signers.len()])).collect();
+        let batch: Vec<_> = payments.iter().map(|payment| (payment, &requirements)).collect();
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &batch, |b, batch| {
+            b.iter(|| verify_payments_batch_at(black_box(batch), &options, NOW))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, header_codecs, hashing_and_recovery, batch_verification);
+criterion_main!(benches);
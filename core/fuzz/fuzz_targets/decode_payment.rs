@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use x402_core::{decode_payment_header, encode_payment_header};
+
+fuzz_target!(|data: &[u8]| {
+    // Anything that decodes must re-encode, and decode again to the same value
+    if let Ok(payment) = decode_payment_header(data) {
+        if let Ok(encoded) = encode_payment_header(&payment) {
+            let again = decode_payment_header(&encoded).expect("re-encoded payment decodes");
+            assert_eq!(encode_payment_header(&again).unwrap(), encoded);
+        }
+    }
+});
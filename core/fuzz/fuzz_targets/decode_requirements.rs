@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use x402_core::{decode_requirements_header, encode_requirements_header};
+
+fuzz_target!(|data: &[u8]| {
+    // Anything that decodes must re-encode, and decode again to the same value
+    if let Ok(requirements) = decode_requirements_header(data) {
+        if let Ok(encoded) = encode_requirements_header(&requirements) {
+            let again = decode_requirements_header(&encoded).expect("re-encoded requirements decode");
+            assert_eq!(encode_requirements_header(&again).unwrap(), encoded);
+        }
+    }
+});
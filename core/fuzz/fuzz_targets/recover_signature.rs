@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use x402_core::recover_address;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 32 {
+        return;
+    }
+    let (hash, signature) = data.split_at(32);
+    let _ = recover_address(hash.try_into().unwrap(), signature);
+});
@@ -0,0 +1,25 @@
+-- Verified x402 payments
+CREATE TABLE IF NOT EXISTS x402_payments (
+    id            BIGSERIAL     PRIMARY KEY,
+    chain_id      BIGINT        NOT NULL,
+    payer         TEXT          NOT NULL,
+    nonce         NUMERIC(20,0) NOT NULL,
+    recipient     TEXT          NOT NULL,
+    amount        NUMERIC(78,0) NOT NULL,
+    token         TEXT,
+    resource      TEXT          NOT NULL,
+    expires_at    BIGINT        NOT NULL,
+    recorded_at   BIGINT        NOT NULL,
+    settlement_tx TEXT
+);
+
+-- Replay protection: a (payer, nonce) pair can only be recorded once per chain.
+-- The leading (payer, nonce) columns also serve lookups by payer.
+CREATE UNIQUE INDEX IF NOT EXISTS x402_payments_payer_nonce
+    ON x402_payments (payer, nonce, chain_id);
+
+CREATE INDEX IF NOT EXISTS x402_payments_recorded_at
+    ON x402_payments (recorded_at);
+
+CREATE INDEX IF NOT EXISTS x402_payments_resource
+    ON x402_payments (resource, recorded_at);
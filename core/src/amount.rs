@@ -0,0 +1,139 @@
+//! Conversion between human decimal amounts and token units
+//!
+//! Token amounts travel as integers in the token's smallest unit; people
+//! write them as decimals. [`Amount`] converts between the two for a given
+//! number of token decimals. Parsing is exact by default: input with more
+//! fractional digits than the token has is rejected unless a [`Rounding`]
+//! mode says otherwise, and values that don't fit a `U256` fail with
+//! [`X402Error::AmountOverflow`].
+
+use crate::{Result, X402Error};
+use alloy_primitives::U256;
+use std::fmt;
+
+/// What to do with fractional digits a token can't represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Reject the amount
+    #[default]
+    Exact,
+    /// Round toward zero
+    Down,
+    /// Round away from zero
+    Up,
+}
+
+/// An amount in a token's smallest unit, with the token's decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Amount {
+    pub units: U256,
+    pub decimals: u8,
+}
+
+impl Amount {
+    pub fn new(units: U256, decimals: u8) -> Self {
+        Self { units, decimals }
+    }
+
+    /// Parse a human decimal string such as `"1.25"`, rejecting digits
+    /// beyond the token's precision
+    pub fn parse(amount: &str, decimals: u8) -> Result<Self> {
+        Self::parse_rounded(amount, decimals, Rounding::Exact)
+    }
+
+    /// Parse a human decimal string, rounding excess fractional digits
+    pub fn parse_rounded(amount: &str, decimals: u8, rounding: Rounding) -> Result<Self> {
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if (whole.is_empty() && fraction.is_empty()) || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(X402Error::InvalidAmount(format!("{:?} is not a decimal amount", amount)));
+        }
+        let precision = fraction.len().min(decimals as usize);
+        let (kept, dropped) = fraction.split_at(precision);
+        let inexact = dropped.chars().any(|c| c != '0');
+        if inexact && rounding == Rounding::Exact {
+            return Err(X402Error::InvalidAmount(format!(
+                "{} has more than {} decimal places",
+                amount, decimals
+            )));
+        }
+
+        let overflow = || X402Error::AmountOverflow { amount: amount.to_string(), decimals };
+        let digits = format!("{}{:0<width$}", whole, kept, width = decimals as usize);
+        let mut units = U256::from_str_radix(&digits, 10).map_err(|_| overflow())?;
+        if inexact && rounding == Rounding::Up {
+            units = units.checked_add(U256::from(1)).ok_or_else(overflow)?;
+        }
+        Ok(Self { units, decimals })
+    }
+
+    /// The same value with `decimals` decimals
+    pub fn rescale(&self, decimals: u8, rounding: Rounding) -> Result<Self> {
+        let overflow = || X402Error::AmountOverflow { amount: self.to_string(), decimals };
+        let factor = |digits: u8| U256::from(10).checked_pow(U256::from(digits)).ok_or_else(overflow);
+        let units = if decimals >= self.decimals {
+            self.units.checked_mul(factor(decimals - self.decimals)?).ok_or_else(overflow)?
+        } else {
+            let factor = factor(self.decimals - decimals)?;
+            let (quotient, remainder) = self.units.div_rem(factor);
+            match rounding {
+                _ if remainder.is_zero() => quotient,
+                Rounding::Exact => {
+                    return Err(X402Error::InvalidAmount(format!(
+                        "{} has more than {} decimal places",
+                        self, decimals
+                    )))
+                }
+                Rounding::Down => quotient,
+                Rounding::Up => quotient + U256::from(1),
+            }
+        };
+        Ok(Self { units, decimals })
+    }
+}
+
+/// Formats as a decimal without trailing fractional zeros, e.g. `1.25`
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = format!("{:0>width$}", self.units, width = self.decimals as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - self.decimals as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}", whole)
+        } else {
+            write!(f, "{}.{}", whole, fraction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let amount = Amount::parse("1.25", 6).unwrap();
+        assert_eq!(amount.units, U256::from(1_250_000));
+        assert_eq!(amount.to_string(), "1.25");
+        assert_eq!(Amount::new(U256::from(5), 6).to_string(), "0.000005");
+        assert_eq!(Amount::new(U256::from(7), 0).to_string(), "7");
+        assert_eq!(Amount::parse("2.500000000", 6).unwrap().units, U256::from(2_500_000));
+
+        assert!(matches!(Amount::parse("0.0000015", 6), Err(X402Error::InvalidAmount(_))));
+        assert_eq!(Amount::parse_rounded("0.0000015", 6, Rounding::Down).unwrap().units, U256::from(1));
+        assert_eq!(Amount::parse_rounded("0.0000015", 6, Rounding::Up).unwrap().units, U256::from(2));
+        assert!(matches!(Amount::parse("-1", 6), Err(X402Error::InvalidAmount(_))));
+        assert!(matches!(Amount::parse("1e6", 6), Err(X402Error::InvalidAmount(_))));
+        assert!(matches!(Amount::parse(&"9".repeat(80), 0), Err(X402Error::AmountOverflow { .. })));
+    }
+
+    #[test]
+    fn test_rescale() {
+        let usdc = Amount::parse("1.000001", 6).unwrap();
+        assert_eq!(usdc.rescale(18, Rounding::Exact).unwrap().to_string(), "1.000001");
+        assert!(usdc.rescale(2, Rounding::Exact).is_err());
+        assert_eq!(usdc.rescale(2, Rounding::Up).unwrap().to_string(), "1.01");
+        assert_eq!(usdc.rescale(2, Rounding::Down).unwrap().to_string(), "1");
+        assert!(matches!(Amount::new(U256::MAX, 0).rescale(1, Rounding::Exact), Err(X402Error::AmountOverflow { .. })));
+    }
+}
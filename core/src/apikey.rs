@@ -0,0 +1,339 @@
+//! API keys for hosted services, with rate limits and settlement quotas
+//!
+//! A public facilitator (see `facilitator_http`) serves anyone who can
+//! reach it, so it hands out API keys and meters each one. [`ApiKeyAuth`]
+//! checks a presented key against an [`ApiKeyStore`] and enforces its
+//! [`ApiKeyLimits`]: requests per minute and settlements per day, counted
+//! in fixed windows. Only the SHA-256 hash of a key is stored; the key
+//! itself is shown once, when [`ApiKeyAuth::create_key_at`] makes it.
+//!
+//! Keys are managed with an admin key, so the first one is seeded from the
+//! operator's configuration: generate a secret such as
+//! `x402_$(openssl rand -hex 32)`, set it as `X402_ADMIN_KEY` and call
+//! [`ApiKeyAuth::seed_admin_key_from_env`] at startup.
+//!
+//! [`MemoryApiKeyStore`] keeps keys and counters in process. Anything with
+//! an atomic increment (Redis `INCR` with `EXPIRE`, a SQL upsert) can back a
+//! store shared by several facilitator instances.
+
+use crate::{Result, X402Error};
+use alloy_primitives::{hex, B256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// HTTP header carrying an API key (`Authorization: Bearer` works too)
+pub const X402_API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Prefix of generated API keys, to tell them apart from other secrets
+pub const API_KEY_PREFIX: &str = "x402_";
+
+/// Environment variable holding the admin key [`ApiKeyAuth::seed_admin_key_from_env`] seeds
+pub const X402_ADMIN_KEY_ENV: &str = "X402_ADMIN_KEY";
+
+/// Shortest secret, after [`API_KEY_PREFIX`], accepted as a seeded admin key
+const MIN_SEEDED_SECRET_LEN: usize = 32;
+
+const MINUTE: u64 = 60;
+const DAY: u64 = 24 * 60 * 60;
+
+/// SHA-256 of an API key, as stored
+pub fn hash_api_key(key: &str) -> B256 {
+    B256::from_slice(&Sha256::digest(key.as_bytes()))
+}
+
+/// Usage limits of one API key; `None` = unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyLimits {
+    pub requests_per_minute: Option<u64>,
+    pub settlements_per_day: Option<u64>,
+}
+
+/// A stored API key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyRecord {
+    pub id: String,
+    /// Who the key was issued to
+    pub name: String,
+    /// [`hash_api_key`] of the key
+    pub key_hash: B256,
+    /// Whether the key may manage other keys
+    pub admin: bool,
+    pub limits: ApiKeyLimits,
+    /// Unix timestamp
+    pub created_at: u64,
+    pub revoked: bool,
+}
+
+/// Storage for API keys and their usage counters
+pub trait ApiKeyStore: Send + Sync {
+    fn insert(&self, record: ApiKeyRecord) -> Result<()>;
+
+    /// The key whose hash is `key_hash`, revoked or not
+    fn by_hash(&self, key_hash: B256) -> Result<Option<ApiKeyRecord>>;
+
+    /// Every key, oldest first
+    fn list(&self) -> Result<Vec<ApiKeyRecord>>;
+
+    /// Revoke the key `id`; false if there is none
+    fn revoke(&self, id: &str) -> Result<bool>;
+
+    /// Count one use of `counter` in the window starting at `window_start`
+    /// and return the window's count so far; earlier windows are forgotten
+    fn increment(&self, counter: &str, window_start: u64) -> Result<u64>;
+
+    /// Take back one [`increment`](Self::increment) of `counter` in the
+    /// window starting at `window_start`, if that window is still counted
+    fn decrement(&self, counter: &str, window_start: u64) -> Result<()>;
+}
+
+/// In-process [`ApiKeyStore`]
+#[derive(Debug, Default)]
+pub struct MemoryApiKeyStore {
+    keys: RwLock<HashMap<B256, ApiKeyRecord>>,
+    counters: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+impl MemoryApiKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ApiKeyStore for MemoryApiKeyStore {
+    fn insert(&self, record: ApiKeyRecord) -> Result<()> {
+        self.keys.write().unwrap().insert(record.key_hash, record);
+        Ok(())
+    }
+
+    fn by_hash(&self, key_hash: B256) -> Result<Option<ApiKeyRecord>> {
+        Ok(self.keys.read().unwrap().get(&key_hash).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<ApiKeyRecord>> {
+        let mut keys: Vec<ApiKeyRecord> = self.keys.read().unwrap().values().cloned().collect();
+        keys.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(keys)
+    }
+
+    fn revoke(&self, id: &str) -> Result<bool> {
+        let mut keys = self.keys.write().unwrap();
+        Ok(keys.values_mut().find(|record| record.id == id).map(|record| record.revoked = true).is_some())
+    }
+
+    fn increment(&self, counter: &str, window_start: u64) -> Result<u64> {
+        let mut counters = self.counters.lock().unwrap();
+        let (window, count) = counters.entry(counter.to_string()).or_insert((window_start, 0));
+        if *window != window_start {
+            *window = window_start;
+            *count = 0;
+        }
+        *count += 1;
+        Ok(*count)
+    }
+
+    fn decrement(&self, counter: &str, window_start: u64) -> Result<()> {
+        let mut counters = self.counters.lock().unwrap();
+        if let Some((window, count)) = counters.get_mut(counter) {
+            if *window == window_start {
+                *count = count.saturating_sub(1);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// API key checks over an [`ApiKeyStore`]; see the [module docs](self)
+#[derive(Clone)]
+pub struct ApiKeyAuth {
+    store: Arc<dyn ApiKeyStore>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(store: Arc<dyn ApiKeyStore>) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &Arc<dyn ApiKeyStore> {
+        &self.store
+    }
+
+    /// Issue a key, returning it (shown only now) and its record
+    pub fn create_key_at(
+        &self,
+        name: impl Into<String>,
+        limits: ApiKeyLimits,
+        admin: bool,
+        now: u64,
+    ) -> Result<(String, ApiKeyRecord)> {
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret)
+            .map_err(|e| X402Error::InvalidConfig(format!("no randomness for API keys: {}", e)))?;
+        let key = format!("{}{}", API_KEY_PREFIX, hex::encode(secret));
+        let record = self.insert_key_at(&key, name.into(), limits, admin, now)?;
+        Ok((key, record))
+    }
+
+    /// Make `key` an unlimited admin key named `name`, unless it is already
+    /// stored (revoked or not), returning its record
+    ///
+    /// For bootstrapping: `key` must be [`API_KEY_PREFIX`] followed by at
+    /// least 32 characters of secret.
+    pub fn seed_admin_key_at(&self, key: &str, name: impl Into<String>, now: u64) -> Result<ApiKeyRecord> {
+        let secret = key.strip_prefix(API_KEY_PREFIX).unwrap_or_default();
+        if secret.len() < MIN_SEEDED_SECRET_LEN {
+            return Err(X402Error::InvalidConfig(format!(
+                "admin key must be {:?} followed by at least {} characters",
+                API_KEY_PREFIX, MIN_SEEDED_SECRET_LEN
+            )));
+        }
+        if let Some(record) = self.store.by_hash(hash_api_key(key))? {
+            return Ok(record);
+        }
+        self.insert_key_at(key, name.into(), ApiKeyLimits::default(), true, now)
+    }
+
+    /// [`seed_admin_key_at`](Self::seed_admin_key_at) the key in
+    /// [`X402_ADMIN_KEY_ENV`], if it is set
+    pub fn seed_admin_key_from_env(&self) -> Result<Option<ApiKeyRecord>> {
+        let Ok(key) = std::env::var(X402_ADMIN_KEY_ENV) else {
+            return Ok(None);
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        self.seed_admin_key_at(key.trim(), X402_ADMIN_KEY_ENV, now).map(Some)
+    }
+
+    fn insert_key_at(
+        &self,
+        key: &str,
+        name: String,
+        limits: ApiKeyLimits,
+        admin: bool,
+        now: u64,
+    ) -> Result<ApiKeyRecord> {
+        let mut id = [0u8; 8];
+        getrandom::getrandom(&mut id)
+            .map_err(|e| X402Error::InvalidConfig(format!("no randomness for API keys: {}", e)))?;
+        let record = ApiKeyRecord {
+            id: hex::encode(id),
+            name,
+            key_hash: hash_api_key(key),
+            admin,
+            limits,
+            created_at: now,
+            revoked: false,
+        };
+        self.store.insert(record.clone())?;
+        Ok(record)
+    }
+
+    /// The record of an active `key`
+    pub fn authenticate(&self, key: &str) -> Result<ApiKeyRecord> {
+        match self.store.by_hash(hash_api_key(key))? {
+            Some(record) if !record.revoked => Ok(record),
+            Some(_) => Err(X402Error::InvalidApiKey("API key revoked".to_string())),
+            None => Err(X402Error::InvalidApiKey("unknown API key".to_string())),
+        }
+    }
+
+    /// Count a request made with `record`'s key at `now`, failing past its rate limit
+    pub fn check_request_at(&self, record: &ApiKeyRecord, now: u64) -> Result<()> {
+        let Some(limit) = record.limits.requests_per_minute else {
+            return Ok(());
+        };
+        let window_start = now - now % MINUTE;
+        if self.store.increment(&format!("{}/requests", record.id), window_start)? > limit {
+            return Err(X402Error::RateLimited { retry_after_seconds: window_start + MINUTE - now });
+        }
+        Ok(())
+    }
+
+    /// Run `settle` for `record`'s key at `now`, failing past its daily
+    /// settlement quota
+    ///
+    /// A settlement takes quota before it runs, so concurrent ones can't
+    /// overshoot, and gives it back if `settle` fails: only successful
+    /// settlements count.
+    pub fn meter_settlement_at<T>(
+        &self,
+        record: &ApiKeyRecord,
+        now: u64,
+        settle: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let Some(limit) = record.limits.settlements_per_day else {
+            return settle();
+        };
+        let counter = format!("{}/settlements", record.id);
+        let window_start = now - now % DAY;
+        if self.store.increment(&counter, window_start)? > limit {
+            self.store.decrement(&counter, window_start)?;
+            return Err(X402Error::QuotaExceeded(format!("{} settlements per day", limit)));
+        }
+        let result = settle();
+        if result.is_err() {
+            self.store.decrement(&counter, window_start)?;
+        }
+        result
+    }
+}
+
+impl std::fmt::Debug for ApiKeyAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeyAuth").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_and_limits() {
+        let auth = ApiKeyAuth::new(Arc::new(MemoryApiKeyStore::new()));
+        let limits = ApiKeyLimits { requests_per_minute: Some(2), settlements_per_day: Some(1) };
+        let (key, record) = auth.create_key_at("crawler", limits, false, 1_000).unwrap();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(auth.authenticate(&key).unwrap(), record);
+        assert!(matches!(auth.authenticate("x402_guess"), Err(X402Error::InvalidApiKey(_))));
+
+        // 1_000 is 40s into its minute
+        auth.check_request_at(&record, 1_000).unwrap();
+        auth.check_request_at(&record, 1_001).unwrap();
+        let error = auth.check_request_at(&record, 1_002).unwrap_err();
+        assert!(matches!(error, X402Error::RateLimited { retry_after_seconds: 18 }));
+        assert!(error.is_retryable());
+        auth.check_request_at(&record, 1_020).unwrap();
+
+        // Failed settlements give their quota back
+        let down = || Err::<(), _>(X402Error::Storage("down".to_string()));
+        let failed = auth.meter_settlement_at(&record, 1_000, down);
+        assert!(matches!(failed, Err(X402Error::Storage(_))));
+        auth.meter_settlement_at(&record, 1_000, || Ok(())).unwrap();
+        let over = auth.meter_settlement_at(&record, 2_000, || -> Result<()> { panic!("settled past the quota") });
+        assert!(matches!(over, Err(X402Error::QuotaExceeded(_))));
+        auth.meter_settlement_at(&record, 1_000 + DAY, || Ok(())).unwrap();
+
+        assert!(auth.store().revoke(&record.id).unwrap());
+        assert!(matches!(auth.authenticate(&key), Err(X402Error::InvalidApiKey(_))));
+        assert!(!auth.store().revoke("missing").unwrap());
+        assert_eq!(auth.store().list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_seed_admin_key() {
+        let auth = ApiKeyAuth::new(Arc::new(MemoryApiKeyStore::new()));
+        assert!(matches!(auth.seed_admin_key_at("x402_short", "operator", 1_000), Err(X402Error::InvalidConfig(_))));
+
+        let key = format!("{}{}", API_KEY_PREFIX, "ab".repeat(32));
+        let record = auth.seed_admin_key_at(&key, "operator", 1_000).unwrap();
+        assert!(record.admin);
+        assert_eq!(auth.authenticate(&key).unwrap(), record);
+        // Seeding again at the next startup keeps the stored key
+        assert_eq!(auth.seed_admin_key_at(&key, "operator", 2_000).unwrap(), record);
+        assert_eq!(auth.store().list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,312 @@
+//! ERC-20 approval management for settlement
+//!
+//! Settlement paths that pull tokens with `transferFrom` — directly or
+//! through Permit2 — need the owner to have approved the spender first.
+//! [`ApprovalManager`] checks the allowance through an [`AllowanceClient`]
+//! (an RPC client plus a signer for the owner accounts it controls),
+//! submits an `approve` when it falls short, and caches the allowance per
+//! (owner, token, spender) so settlements don't each cost an RPC call.
+//!
+//! Only the owner can approve, so this is a tool for the process holding
+//! the owner's key: a payer's agent or wallet service preparing to pay with
+//! Permit2, or a custodial facilitator settling from accounts it controls.
+//! A server settling payments signed by third-party payers can't approve on
+//! their behalf; [`SettlementBatcher`](crate::SettlementBatcher) doesn't
+//! call it, and such settlements fail until the payer has approved.
+//!
+//! EIP-3009 settlement needs no allowance and doesn't go through here.
+
+use crate::{Network, PaymentPayload, Result, X402Error};
+use alloy_primitives::{address, Address, B256, U256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Canonical Permit2 deployment, at the same address on every chain
+pub const PERMIT2_ADDRESS: Address = address!("000000000022D473030F116dDEE9F6B43aC78BA3");
+
+/// `approve(address,uint256)`
+const APPROVE_SELECTOR: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+
+/// Who settlement lets pull the tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApprovalSpender {
+    /// The Permit2 contract
+    Permit2,
+    /// A contract calling `transferFrom` itself, e.g. a batch settler
+    Direct(Address),
+}
+
+impl ApprovalSpender {
+    pub fn address(&self) -> Address {
+        match self {
+            Self::Permit2 => PERMIT2_ADDRESS,
+            Self::Direct(spender) => *spender,
+        }
+    }
+}
+
+/// An allowance: `owner` lets `spender` move its `token` on `network`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AllowanceKey {
+    pub network: Network,
+    pub owner: Address,
+    pub token: Address,
+    pub spender: Address,
+}
+
+impl AllowanceKey {
+    /// The allowance settling `payment` through `spender` draws on; `None`
+    /// for native-token payments
+    pub fn for_payment(payment: &PaymentPayload, spender: ApprovalSpender) -> Result<Option<Self>> {
+        let Some(token) = payment.token else {
+            return Ok(None);
+        };
+        let network = Network::from_chain_id(payment.chain_id)
+            .ok_or_else(|| X402Error::UnsupportedNetwork(format!("unknown chain {}", payment.chain_id)))?;
+        Ok(Some(Self { network, owner: payment.payer, token, spender: spender.address() }))
+    }
+}
+
+/// Calldata of `approve(spender, amount)`, to send to the token contract
+pub fn approve_calldata(spender: Address, amount: U256) -> Vec<u8> {
+    let mut data = Vec::with_capacity(68);
+    data.extend_from_slice(&APPROVE_SELECTOR);
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(spender.as_slice());
+    data.extend_from_slice(&amount.to_be_bytes::<32>());
+    data
+}
+
+/// Chain access for [`ApprovalManager`]
+pub trait AllowanceClient: Send + Sync {
+    /// Current allowance, e.g. from an `allowance(owner, spender)` call
+    fn allowance(&self, key: &AllowanceKey) -> Result<U256>;
+
+    /// Send `calldata` (from [`approve_calldata`]) to `key.token` from
+    /// `key.owner`, returning the transaction hash once it is included
+    ///
+    /// Fails if the client can't sign for the owner.
+    fn submit_approval(&self, key: &AllowanceKey, calldata: &[u8]) -> Result<B256>;
+}
+
+/// How much to approve when an allowance falls short
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApprovalAmount {
+    /// `U256::MAX`, which standard tokens never decrease: one approval per
+    /// (owner, token, spender)
+    #[default]
+    Unlimited,
+    /// Just the amount needed, approving again for every settlement
+    Exact,
+    /// A fixed amount, or the amount needed if that is more
+    Fixed(U256),
+}
+
+/// Checks, tops up and caches allowances for settlement
+///
+/// Calls for the same (owner, token, spender) are serialized, so
+/// concurrent settlements short of allowance send one approval between
+/// them.
+pub struct ApprovalManager<C: AllowanceClient> {
+    client: C,
+    amount: ApprovalAmount,
+    cache: Mutex<HashMap<AllowanceKey, U256>>,
+    /// Held while an allowance is read and topped up
+    in_flight: Mutex<HashMap<AllowanceKey, Arc<Mutex<()>>>>,
+}
+
+impl<C: AllowanceClient> ApprovalManager<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            amount: ApprovalAmount::default(),
+            cache: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_amount(mut self, amount: ApprovalAmount) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Make sure `key` allows at least `needed`, returning the approval
+    /// transaction if one had to be sent
+    ///
+    /// A cached allowance that covers `needed` is trusted without an RPC
+    /// call; otherwise the allowance is read from the chain first. A call
+    /// waiting on another for the same key rechecks the cache once that
+    /// one is done.
+    pub fn ensure(&self, key: &AllowanceKey, needed: U256) -> Result<Option<B256>> {
+        let covered = || self.cached(key).is_some_and(|allowance| allowance >= needed);
+        if covered() {
+            return Ok(None);
+        }
+        let lock = self.in_flight.lock().unwrap().entry(*key).or_default().clone();
+        let _guard = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if covered() {
+            return Ok(None);
+        }
+        let current = self.client.allowance(key)?;
+        if current >= needed {
+            self.cache.lock().unwrap().insert(*key, current);
+            return Ok(None);
+        }
+
+        let approve = match self.amount {
+            ApprovalAmount::Unlimited => U256::MAX,
+            ApprovalAmount::Exact => needed,
+            ApprovalAmount::Fixed(amount) => amount.max(needed),
+        };
+        // Drop the stale entry first, so a failed approval isn't trusted
+        self.cache.lock().unwrap().remove(key);
+        let transaction_hash = self.client.submit_approval(key, &approve_calldata(key.spender, approve))?;
+        self.cache.lock().unwrap().insert(*key, approve);
+        Ok(Some(transaction_hash))
+    }
+
+    /// [`ApprovalManager::ensure`] for settling `payment` through `spender`;
+    /// does nothing for native-token payments
+    pub fn ensure_for_payment(&self, payment: &PaymentPayload, spender: ApprovalSpender) -> Result<Option<B256>> {
+        match AllowanceKey::for_payment(payment, spender)? {
+            Some(key) => self.ensure(&key, payment.amount),
+            None => Ok(None),
+        }
+    }
+
+    /// Record that settlement moved `amount` under `key`
+    pub fn spent(&self, key: &AllowanceKey, amount: U256) {
+        if let Some(allowance) = self.cache.lock().unwrap().get_mut(key) {
+            if *allowance != U256::MAX {
+                *allowance = allowance.saturating_sub(amount);
+            }
+        }
+    }
+
+    /// Forget the cached allowance of `key`, e.g. after a settlement
+    /// reverted or the owner may have changed it elsewhere
+    pub fn invalidate(&self, key: &AllowanceKey) {
+        self.cache.lock().unwrap().remove(key);
+    }
+
+    /// The cached allowance of `key`, if any
+    pub fn cached(&self, key: &AllowanceKey) -> Option<U256> {
+        self.cache.lock().unwrap().get(key).copied()
+    }
+}
+
+impl<C: AllowanceClient> std::fmt::Debug for ApprovalManager<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApprovalManager")
+            .field("amount", &self.amount)
+            .field("cached", &self.cache.lock().unwrap().len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PaymentRequirements;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Token contract stand-in that applies approvals immediately
+    #[derive(Default)]
+    struct Chain {
+        allowances: Mutex<HashMap<AllowanceKey, U256>>,
+        reads: AtomicUsize,
+        approvals: AtomicUsize,
+    }
+
+    impl AllowanceClient for &Chain {
+        fn allowance(&self, key: &AllowanceKey) -> Result<U256> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(self.allowances.lock().unwrap().get(key).copied().unwrap_or_default())
+        }
+
+        fn submit_approval(&self, key: &AllowanceKey, calldata: &[u8]) -> Result<B256> {
+            assert_eq!(&calldata[..4], &APPROVE_SELECTOR);
+            assert_eq!(&calldata[16..36], key.spender.as_slice());
+            let amount = U256::from_be_slice(&calldata[36..68]);
+            self.allowances.lock().unwrap().insert(*key, amount);
+            Ok(B256::repeat_byte(self.approvals.fetch_add(1, Ordering::SeqCst) as u8 + 1))
+        }
+    }
+
+    fn payment(amount: &str) -> PaymentPayload {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, amount, Address::repeat_byte(0x11), "/reports").unwrap();
+        PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .expires_at(1_700_000_000)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_exact_approvals_tracked_in_cache() {
+        let chain = Chain::default();
+        let manager = ApprovalManager::new(&chain).with_amount(ApprovalAmount::Fixed(U256::from(1_500)));
+        let key = AllowanceKey::for_payment(&payment("0.001"), ApprovalSpender::Permit2).unwrap().unwrap();
+        assert_eq!(key.spender, PERMIT2_ADDRESS);
+
+        assert!(manager.ensure_for_payment(&payment("0.001"), ApprovalSpender::Permit2).unwrap().is_some());
+        assert_eq!(manager.cached(&key), Some(U256::from(1_500)));
+        let settle = |amount: u64| {
+            *chain.allowances.lock().unwrap().get_mut(&key).unwrap() -= U256::from(amount);
+            manager.spent(&key, U256::from(amount));
+        };
+        settle(1_000);
+
+        // 500 left in the cache is too little: re-read, then approve again
+        assert!(manager.ensure(&key, U256::from(1_000)).unwrap().is_some());
+        assert_eq!(chain.approvals.load(Ordering::SeqCst), 2);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 2);
+        settle(1_000);
+        assert_eq!(manager.ensure(&key, U256::from(500)).unwrap(), None);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 2);
+
+        let mut native = payment("0.001");
+        native.token = None;
+        assert_eq!(manager.ensure_for_payment(&native, ApprovalSpender::Permit2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_concurrent_ensures_approve_once() {
+        let chain = Chain::default();
+        let manager = ApprovalManager::new(&chain);
+        let key = AllowanceKey::for_payment(&payment("0.001"), ApprovalSpender::Permit2).unwrap().unwrap();
+
+        let approved: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|_| scope.spawn(|| manager.ensure(&key, U256::from(1_000)))).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap().unwrap().is_some() as usize).sum()
+        });
+        assert_eq!(approved, 1);
+        assert_eq!(chain.approvals.load(Ordering::SeqCst), 1);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_unlimited_approval_sent_once() {
+        let chain = Chain::default();
+        let spender = ApprovalSpender::Direct(Address::repeat_byte(0x44));
+        let key = AllowanceKey::for_payment(&payment("0.000001"), spender).unwrap().unwrap();
+        chain.allowances.lock().unwrap().insert(key, U256::from(10));
+        let manager = ApprovalManager::new(&chain);
+
+        assert_eq!(manager.ensure(&key, U256::from(10)).unwrap(), None);
+        assert_eq!(manager.cached(&key), Some(U256::from(10)));
+        assert!(manager.ensure(&key, U256::from(11)).unwrap().is_some());
+        for _ in 0..3 {
+            manager.spent(&key, U256::from(1_000_000));
+            assert_eq!(manager.ensure(&key, U256::from(1_000_000)).unwrap(), None);
+        }
+        assert_eq!(chain.approvals.load(Ordering::SeqCst), 1);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 2);
+
+        manager.invalidate(&key);
+        assert_eq!(manager.ensure(&key, U256::from(1)).unwrap(), None);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,312 @@
+//! Tamper-evident audit log of verification outcomes
+//!
+//! [`verify_audited`] records every verification, accepted or rejected,
+//! in an [`AuditLog`]. Each [`AuditRecord`] carries the SHA-256 of the
+//! payment header and the hash of the record before it, so editing,
+//! reordering or dropping a record breaks [`verify_audit_chain`].
+//! Record hashes are taken over the record's canonical JSON.
+//!
+//! Backends:
+//! - [`MemoryAuditLog`]
+//! - [`FileAuditLog`], one JSON record per line
+
+use crate::{canonical_json, Result, X402Error};
+#[cfg(feature = "verify")]
+use crate::{decode_payment_header, verify_payment_at, PaymentRequirements};
+use alloy_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The payment a header carried, when it could be decoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditedPayment {
+    pub payer: Address,
+    pub chain_id: u64,
+    pub nonce: u64,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+}
+
+/// Result of an audited verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The payment was accepted; `payer` is the recovered signer
+    Accepted { payer: Address },
+    /// The payment was rejected; `reason` is the [`X402Error::kind`]
+    Rejected { reason: String, message: String },
+}
+
+/// What happened in one verification, before it is chained into the log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    /// Verification time (unix timestamp)
+    pub timestamp: u64,
+    /// SHA-256 of the payment header as received
+    pub header_hash: B256,
+    /// Resource the requirements were for
+    pub resource: String,
+    pub payment: Option<AuditedPayment>,
+    pub outcome: AuditOutcome,
+}
+
+/// An [`AuditEvent`] chained to the record before it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// Position in the log, starting at 0
+    pub sequence: u64,
+    /// Hash of the previous record; zero for the first
+    pub prev_hash: B256,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    /// Hash of this record's canonical JSON, excluding this field
+    pub hash: B256,
+}
+
+impl AuditRecord {
+    /// Chain `event` after `prev` (`None` for the first record)
+    pub fn new(prev: Option<&AuditRecord>, event: AuditEvent) -> Self {
+        let mut record = Self {
+            sequence: prev.map_or(0, |p| p.sequence + 1),
+            prev_hash: prev.map_or(B256::ZERO, |p| p.hash),
+            event,
+            hash: B256::ZERO,
+        };
+        record.hash = record.compute_hash();
+        record
+    }
+
+    /// Hash of this record's contents, for comparison with `hash`
+    pub fn compute_hash(&self) -> B256 {
+        let mut value = serde_json::to_value(self).expect("audit records serialize to JSON");
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("hash");
+        }
+        B256::from_slice(&Sha256::digest(canonical_json(&value)))
+    }
+}
+
+/// Check that `records` form an unbroken chain from the first record
+pub fn verify_audit_chain<'a>(records: impl IntoIterator<Item = &'a AuditRecord>) -> Result<()> {
+    let mut prev: Option<&AuditRecord> = None;
+    for record in records {
+        let expected_sequence = prev.map_or(0, |p| p.sequence + 1);
+        if record.sequence != expected_sequence {
+            return Err(X402Error::Storage(format!(
+                "audit record {} found where {} was expected",
+                record.sequence, expected_sequence
+            )));
+        }
+        if record.prev_hash != prev.map_or(B256::ZERO, |p| p.hash) {
+            return Err(X402Error::Storage(format!("audit record {} does not follow its predecessor", record.sequence)));
+        }
+        if record.hash != record.compute_hash() {
+            return Err(X402Error::Storage(format!("audit record {} has been altered", record.sequence)));
+        }
+        prev = Some(record);
+    }
+    Ok(())
+}
+
+/// Append-only store of audit records
+pub trait AuditLog: Send + Sync {
+    /// Chain `event` after the last record and store it
+    fn append(&self, event: AuditEvent) -> Result<AuditRecord>;
+}
+
+/// Decode and verify a payment header, recording the outcome in `log`
+///
+/// If the outcome cannot be recorded the payment is not accepted: the
+/// log's error is returned instead.
+#[cfg(feature = "verify")]
+pub fn verify_audited<L: AuditLog + ?Sized>(
+    header: &str,
+    requirements: &PaymentRequirements,
+    log: &L,
+) -> Result<Address> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    verify_audited_at(header, requirements, now, log)
+}
+
+/// [`verify_audited`] as of the unix time `now`
+#[cfg(feature = "verify")]
+pub fn verify_audited_at<L: AuditLog + ?Sized>(
+    header: &str,
+    requirements: &PaymentRequirements,
+    now: u64,
+    log: &L,
+) -> Result<Address> {
+    let decoded = decode_payment_header(header);
+    let payment = decoded.as_ref().ok().map(|signed| AuditedPayment {
+        payer: signed.payment.payer,
+        chain_id: signed.payment.chain_id,
+        nonce: signed.payment.nonce,
+        amount: signed.payment.amount,
+    });
+    let result = decoded.and_then(|signed| verify_payment_at(&signed, requirements, now));
+    let outcome = match &result {
+        Ok(payer) => AuditOutcome::Accepted { payer: *payer },
+        Err(e) => AuditOutcome::Rejected { reason: e.kind().to_string(), message: e.chain_message() },
+    };
+    log.append(AuditEvent {
+        timestamp: now,
+        header_hash: B256::from_slice(&Sha256::digest(header.as_bytes())),
+        resource: requirements.resource.clone(),
+        payment,
+        outcome,
+    })?;
+    result
+}
+
+/// [`AuditLog`] kept in memory, for tests
+#[derive(Debug, Default)]
+pub struct MemoryAuditLog {
+    records: Mutex<Vec<AuditRecord>>,
+}
+
+impl MemoryAuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl AuditLog for MemoryAuditLog {
+    fn append(&self, event: AuditEvent) -> Result<AuditRecord> {
+        let mut records = self.records.lock().unwrap();
+        let record = AuditRecord::new(records.last(), event);
+        records.push(record.clone());
+        Ok(record)
+    }
+}
+
+/// [`AuditLog`] appending JSON lines to a file
+///
+/// Each record is flushed to disk before [`AuditLog::append`] returns.
+#[derive(Debug)]
+pub struct FileAuditLog {
+    path: PathBuf,
+    state: Mutex<(File, Option<AuditRecord>)>,
+}
+
+impl FileAuditLog {
+    /// Open or create the log at `path`, checking the existing chain
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let last = if path.exists() { Self::read(&path)?.pop() } else { None };
+        let file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| storage(&path, e))?;
+        Ok(Self { path, state: Mutex::new((file, last)) })
+    }
+
+    /// Read and check every record in the log at `path`
+    pub fn read(path: impl AsRef<Path>) -> Result<Vec<AuditRecord>> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| storage(path, e))?;
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| storage(path, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line).map_err(|e| storage(path, e))?);
+        }
+        verify_audit_chain(&records)?;
+        Ok(records)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AuditLog for FileAuditLog {
+    fn append(&self, event: AuditEvent) -> Result<AuditRecord> {
+        let mut state = self.state.lock().unwrap();
+        let (file, last) = &mut *state;
+        let record = AuditRecord::new(last.as_ref(), event);
+        let mut line = serde_json::to_string(&record).map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).and_then(|_| file.sync_data()).map_err(|e| storage(&self.path, e))?;
+        *last = Some(record.clone());
+        Ok(record)
+    }
+}
+
+fn storage(path: &Path, e: impl std::fmt::Display) -> X402Error {
+    X402Error::Storage(format!("audit log {}: {}", path.display(), e))
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::{encode_payment_header, Network, PaymentPayload, SignedPayment};
+
+    fn header(expires_at: u64) -> (String, PaymentRequirements) {
+        let requirements = PaymentRequirements::usdc(Network::Base, "0.001", Address::repeat_byte(0x11), "/api").unwrap();
+        let payment = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .expires_at(expires_at)
+            .build()
+            .unwrap();
+        let payment = SignedPayment { payment, signature: vec![0; 65], signatures: Vec::new() };
+        let header = encode_payment_header(&payment).unwrap();
+        (header, requirements)
+    }
+
+    #[test]
+    fn test_audit_chain_detects_tampering() {
+        let log = MemoryAuditLog::new();
+        let (header, requirements) = header(2_000);
+        assert!(verify_audited_at(&header, &requirements, 3_000, &log).is_err());
+        assert!(verify_audited_at("garbage", &requirements, 3_001, &log).is_err());
+
+        let records = log.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].event.header_hash, B256::from_slice(&Sha256::digest(header.as_bytes())));
+        assert_eq!(records[0].event.payment.as_ref().unwrap().payer, Address::repeat_byte(0x22));
+        assert!(matches!(&records[0].event.outcome, AuditOutcome::Rejected { reason, .. } if reason == "payment_expired"));
+        assert_eq!(records[1].event.payment, None);
+        assert_eq!(records[1].prev_hash, records[0].hash);
+        verify_audit_chain(&records).unwrap();
+
+        let mut altered = records.clone();
+        altered[0].event.outcome = AuditOutcome::Accepted { payer: Address::repeat_byte(0x22) };
+        assert!(verify_audit_chain(&altered).is_err());
+        assert!(verify_audit_chain(&records[1..]).is_err());
+    }
+
+    #[test]
+    fn test_file_audit_log_resumes_chain() {
+        let path = std::env::temp_dir().join(format!("x402-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (header, requirements) = header(2_000);
+
+        let log = FileAuditLog::open(&path).unwrap();
+        let _ = verify_audited_at(&header, &requirements, 1_000, &log);
+        drop(log);
+        let log = FileAuditLog::open(&path).unwrap();
+        let _ = verify_audited_at(&header, &requirements, 3_000, &log);
+
+        let records = FileAuditLog::read(&path).unwrap();
+        assert_eq!(records.iter().map(|r| r.sequence).collect::<Vec<_>>(), vec![0, 1]);
+
+        let text = std::fs::read_to_string(&path).unwrap().replace("payment_expired", "payment_expirex");
+        std::fs::write(&path, text).unwrap();
+        assert!(matches!(FileAuditLog::open(&path), Err(X402Error::Storage(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+//! Paid "hello world" API for trying out x402 end to end
+//!
+//! Configuration (environment):
+//! - `X402_DEMO_ADDR`: listen address (default `127.0.0.1:4020`)
+//! - `X402_DEMO_RECIPIENT`: address that gets paid
+//! - `X402_DEMO_PRICE`: price in base units (default 1000)
+
+use std::process::ExitCode;
+use x402_core::demo::{DemoConfig, DemoServer};
+
+fn main() -> ExitCode {
+    let mut config = DemoConfig::default();
+    if let Ok(recipient) = std::env::var("X402_DEMO_RECIPIENT") {
+        match recipient.parse() {
+            Ok(recipient) => config.recipient = recipient,
+            Err(e) => return fail(&format!("invalid X402_DEMO_RECIPIENT: {}", e)),
+        }
+    }
+    if let Ok(price) = std::env::var("X402_DEMO_PRICE") {
+        match price.parse() {
+            Ok(price) => config.price = price,
+            Err(e) => return fail(&format!("invalid X402_DEMO_PRICE: {}", e)),
+        }
+    }
+    let addr = std::env::var("X402_DEMO_ADDR").unwrap_or_else(|_| "127.0.0.1:4020".to_string());
+    let path = config.path.clone();
+
+    let server = match DemoServer::bind(&addr, config) {
+        Ok(server) => server,
+        Err(e) => return fail(&e.to_string()),
+    };
+    match server.local_addr() {
+        Ok(local) => eprintln!("x402-demo-server: paid endpoint at http://{}{}", local, path),
+        Err(e) => return fail(&e.to_string()),
+    }
+    match server.serve() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+fn fail(message: &str) -> ExitCode {
+    eprintln!("x402-demo-server: {}", message);
+    ExitCode::FAILURE
+}
@@ -0,0 +1,269 @@
+//! Compact fixed-layout binary encoding for signed payments
+//!
+//! Layout (all integers big-endian):
+//!
+//! | field        | size                         |
+//! |--------------|------------------------------|
+//! | version      | 1 ([`BINARY_FORMAT_V1`])     |
+//! | amount       | 32                           |
+//! | recipient    | 20                           |
+//! | payer        | 20                           |
+//! | chain id     | 8                            |
+//! | token flag   | 1 (0 = native, 1 = token)    |
+//! | token        | 20 if flag is 1              |
+//! | nonce        | 8                            |
+//! | expires at   | 8                            |
+//! | resource len | LEB128 varint                |
+//! | resource     | UTF-8 bytes                  |
+//! | signature    | 65                           |
+//!
+//! The encoding is deterministic: a payment has exactly one byte
+//! representation, and decoding never touches a JSON parser. Only
+//! [`Scheme::Exact`] payments without `extra` metadata, an invoice ID or
+//! split outputs have a binary form.
+
+use crate::{BorrowedPaymentPayload, BorrowedSignedPayment, DecodeLimits, Result, Scheme, SignedPayment, X402Error};
+use alloy_primitives::{Address, U256};
+use std::borrow::Cow;
+
+/// Version byte of the current binary layout
+///
+/// Doubles as the header format prefix, see [`crate::CBOR_FORMAT_PREFIX`].
+pub const BINARY_FORMAT_V1: u8 = 0x02;
+
+const SIGNATURE_LEN: usize = 65;
+
+/// Encode a signed payment in the binary layout
+pub fn encode_payment_binary(payment: &SignedPayment) -> Result<Vec<u8>> {
+    if payment.signature.len() != SIGNATURE_LEN {
+        return Err(X402Error::EncodingError(format!(
+            "signature must be {} bytes, got {}",
+            SIGNATURE_LEN,
+            payment.signature.len()
+        )));
+    }
+    if !payment.signatures.is_empty() {
+        return Err(X402Error::EncodingError("multisig payments have no binary encoding".to_string()));
+    }
+
+    let p = &payment.payment;
+    if !p.scheme.is_exact() {
+        return Err(X402Error::UnsupportedScheme(format!(
+            "{} payments have no binary encoding",
+            p.scheme.as_str()
+        )));
+    }
+    if !p.extra.is_empty() || p.invoice_id.is_some() || !p.splits.is_empty() {
+        return Err(X402Error::EncodingError(
+            "payments with extra metadata, an invoice ID or splits have no binary encoding".to_string(),
+        ));
+    }
+
+    let mut out = Vec::with_capacity(190 + p.resource.len());
+    out.push(BINARY_FORMAT_V1);
+    out.extend_from_slice(&p.amount.to_be_bytes::<32>());
+    out.extend_from_slice(p.recipient.as_slice());
+    out.extend_from_slice(p.payer.as_slice());
+    out.extend_from_slice(&p.chain_id.to_be_bytes());
+    match p.token {
+        Some(token) => {
+            out.push(1);
+            out.extend_from_slice(token.as_slice());
+        }
+        None => out.push(0),
+    }
+    out.extend_from_slice(&p.nonce.to_be_bytes());
+    out.extend_from_slice(&p.expires_at.to_be_bytes());
+    write_varint(&mut out, p.resource.len() as u64);
+    out.extend_from_slice(p.resource.as_bytes());
+    out.extend_from_slice(&payment.signature);
+    Ok(out)
+}
+
+/// Decode a signed payment from the binary layout
+pub fn decode_payment_binary(bytes: &[u8]) -> Result<SignedPayment> {
+    decode_payment_binary_with_limits(bytes, &DecodeLimits::default())
+}
+
+/// Decode a signed payment, rejecting resources longer than the limit
+/// before they are copied
+pub fn decode_payment_binary_with_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<SignedPayment> {
+    decode_payment_binary_borrowed(bytes, limits).map(BorrowedSignedPayment::into_owned)
+}
+
+/// Decode the binary layout, borrowing the resource and signature
+pub(crate) fn decode_payment_binary_borrowed<'a>(
+    bytes: &'a [u8],
+    limits: &DecodeLimits,
+) -> Result<BorrowedSignedPayment<'a>> {
+    let mut r = Reader { bytes };
+
+    match r.take(1)?[0] {
+        BINARY_FORMAT_V1 => {}
+        version => {
+            return Err(X402Error::InvalidHeader(format!(
+                "unsupported binary format version {}",
+                version
+            )))
+        }
+    }
+
+    let amount = U256::from_be_slice(r.take(32)?);
+    let recipient = Address::from_slice(r.take(20)?);
+    let payer = Address::from_slice(r.take(20)?);
+    let chain_id = r.u64()?;
+    let token = match r.take(1)?[0] {
+        0 => None,
+        1 => Some(Address::from_slice(r.take(20)?)),
+        flag => return Err(X402Error::InvalidHeader(format!("invalid token flag {}", flag))),
+    };
+    let nonce = r.u64()?;
+    let expires_at = r.u64()?;
+    let resource_len = usize::try_from(r.varint()?)
+        .map_err(|_| X402Error::InvalidHeader("resource length overflow".to_string()))?;
+    DecodeLimits::check("resource", limits.max_resource_len, resource_len)?;
+    let resource = std::str::from_utf8(r.take(resource_len)?)
+        .map_err(|e| X402Error::InvalidHeader(format!("invalid UTF-8 in resource: {}", e)))?;
+    let signature = r.take(SIGNATURE_LEN)?;
+
+    if !r.bytes.is_empty() {
+        return Err(X402Error::InvalidHeader(format!(
+            "{} trailing bytes after payment",
+            r.bytes.len()
+        )));
+    }
+
+    Ok(BorrowedSignedPayment {
+        payment: BorrowedPaymentPayload {
+            amount,
+            recipient,
+            payer,
+            chain_id,
+            token,
+            resource: Cow::Borrowed(resource),
+            nonce,
+            expires_at,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        },
+        signature: Cow::Borrowed(signature),
+        signatures: Vec::new(),
+    })
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(X402Error::InvalidHeader("truncated binary payment".to_string()));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// LEB128 varint in its one minimal encoding, so each payment has a
+    /// single binary form
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            if shift == 63 && byte > 1 {
+                return Err(X402Error::InvalidHeader("varint overflows u64".to_string()));
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                if byte == 0 && shift > 0 {
+                    return Err(X402Error::InvalidHeader("non-minimal varint".to_string()));
+                }
+                return Ok(value);
+            }
+        }
+        Err(X402Error::InvalidHeader("varint too long".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PaymentPayload;
+
+    fn payment(token: Option<Address>, resource: &str) -> SignedPayment {
+        SignedPayment {
+            payment: PaymentPayload {
+                amount: U256::MAX,
+                recipient: Address::repeat_byte(0x11),
+                payer: Address::repeat_byte(0x22),
+                chain_id: 8453,
+                token,
+                resource: resource.to_string(),
+                nonce: u64::MAX,
+                expires_at: 1700000000,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: vec![0xab; 65],
+            signatures: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        for p in [
+            payment(None, "/a"),
+            payment(Some(Address::repeat_byte(0x33)), &"x".repeat(300)),
+        ] {
+            let bytes = encode_payment_binary(&p).unwrap();
+            let decoded = decode_payment_binary(&bytes).unwrap();
+            assert_eq!(encode_payment_binary(&decoded).unwrap(), bytes);
+            assert_eq!(decoded.payment.resource, p.payment.resource);
+            assert_eq!(decoded.payment.token, p.payment.token);
+        }
+    }
+
+    #[test]
+    fn test_binary_rejects_malformed_input() {
+        let bytes = encode_payment_binary(&payment(None, "/a")).unwrap();
+        assert!(decode_payment_binary(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_payment_binary(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 0x7f;
+        assert!(decode_payment_binary(&wrong_version).is_err());
+    }
+
+    #[test]
+    fn test_varint_must_be_minimal() {
+        let read = |bytes: &[u8]| Reader { bytes }.varint();
+        for value in [0, 1, 127, 128, 300, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            assert_eq!(read(&bytes).unwrap(), value);
+        }
+        // 2 padded with a zero continuation, and u64::MAX with a bit past 64
+        assert!(matches!(read(&[0x82, 0x00]), Err(X402Error::InvalidHeader(_))));
+        assert!(matches!(read(&[0x80, 0x80, 0x00]), Err(X402Error::InvalidHeader(_))));
+        let mut overflow = vec![0xff; 9];
+        overflow.push(0x03);
+        assert!(matches!(read(&overflow), Err(X402Error::InvalidHeader(_))));
+    }
+}
@@ -0,0 +1,232 @@
+//! Zero-copy payment header decoding
+//!
+//! [`decode_payment_header`](crate::decode_payment_header) allocates the
+//! decoded bytes, the resource and the signature for every request.
+//! [`decode_payment_header_borrowed`] decodes into a buffer the caller
+//! reuses across requests and returns a [`BorrowedSignedPayment`] that
+//! points into it, so a gateway's hot path allocates nothing once the
+//! buffer has grown.
+//!
+//! Borrowing is best-effort per wire format: the binary format borrows
+//! both the resource and the signature; JSON borrows the resource unless
+//! it contains escapes, and allocates the signature (a number array on the
+//! wire); CBOR allocates both.
+
+use crate::binary::decode_payment_binary_borrowed;
+use crate::protocol::{decode_header_into, json_depth, parse_payload, Tracked};
+use crate::types::PaymentMessage;
+use crate::validate::check_invoice_id;
+use crate::{
+    DecodeLimits, Extra, PaymentPayload, Result, Scheme, SignedPayment, Split, UnknownFields, WireFormat, X402Error,
+};
+use alloy_primitives::{Address, U256};
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// [`PaymentPayload`] borrowing its strings from the decode buffer
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BorrowedPaymentPayload<'a> {
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    pub recipient: Address,
+    pub payer: Address,
+    pub chain_id: u64,
+    pub token: Option<Address>,
+    #[serde(borrow)]
+    pub resource: Cow<'a, str>,
+    pub nonce: u64,
+    pub expires_at: u64,
+    #[serde(default)]
+    pub scheme: Scheme,
+    #[serde(default, deserialize_with = "crate::canonical::deserialize_extra")]
+    pub extra: Extra,
+    #[serde(default, borrow)]
+    pub invoice_id: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub splits: Vec<Split>,
+}
+
+impl BorrowedPaymentPayload<'_> {
+    /// Same as [`PaymentPayload::message_hash`]
+    pub fn message_hash(&self) -> [u8; 32] {
+        PaymentMessage {
+            amount: &self.amount,
+            recipient: &self.recipient,
+            payer: &self.payer,
+            chain_id: self.chain_id,
+            resource: &self.resource,
+            nonce: self.nonce,
+            expires_at: self.expires_at,
+            scheme: self.scheme,
+            extra: &self.extra,
+            invoice_id: self.invoice_id.as_deref(),
+            splits: &self.splits,
+        }
+        .hash()
+    }
+
+    pub fn into_owned(self) -> PaymentPayload {
+        PaymentPayload {
+            amount: self.amount,
+            recipient: self.recipient,
+            payer: self.payer,
+            chain_id: self.chain_id,
+            token: self.token,
+            resource: self.resource.into_owned(),
+            nonce: self.nonce,
+            expires_at: self.expires_at,
+            scheme: self.scheme,
+            extra: self.extra,
+            invoice_id: self.invoice_id.map(Cow::into_owned),
+            splits: self.splits,
+        }
+    }
+}
+
+impl From<PaymentPayload> for BorrowedPaymentPayload<'static> {
+    fn from(payment: PaymentPayload) -> Self {
+        Self {
+            amount: payment.amount,
+            recipient: payment.recipient,
+            payer: payment.payer,
+            chain_id: payment.chain_id,
+            token: payment.token,
+            resource: Cow::Owned(payment.resource),
+            nonce: payment.nonce,
+            expires_at: payment.expires_at,
+            scheme: payment.scheme,
+            extra: payment.extra,
+            invoice_id: payment.invoice_id.map(Cow::Owned),
+            splits: payment.splits,
+        }
+    }
+}
+
+/// [`SignedPayment`] borrowing from the decode buffer
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BorrowedSignedPayment<'a> {
+    #[serde(borrow)]
+    pub payment: BorrowedPaymentPayload<'a>,
+    /// ECDSA signature (65 bytes: r + s + v); empty when `signatures` is used
+    pub signature: Cow<'a, [u8]>,
+    /// Multisig signatures, always allocated
+    #[serde(default)]
+    pub signatures: Vec<Vec<u8>>,
+}
+
+impl BorrowedSignedPayment<'_> {
+    pub fn into_owned(self) -> SignedPayment {
+        SignedPayment {
+            payment: self.payment.into_owned(),
+            signature: self.signature.into_owned(),
+            signatures: self.signatures,
+        }
+    }
+}
+
+impl From<SignedPayment> for BorrowedSignedPayment<'static> {
+    fn from(payment: SignedPayment) -> Self {
+        Self {
+            payment: payment.payment.into(),
+            signature: Cow::Owned(payment.signature),
+            signatures: payment.signatures,
+        }
+    }
+}
+
+/// Decode a signed payment into `buf`, borrowing from it where the wire
+/// format allows
+///
+/// `buf` is cleared first and keeps its capacity, so reusing one buffer
+/// per worker avoids reallocating it for every request. Clearing doesn't
+/// wipe the previous payment; call `buf.zeroize()` when that matters.
+pub fn decode_payment_header_borrowed<'a>(header: &[u8], buf: &'a mut Vec<u8>) -> Result<BorrowedSignedPayment<'a>> {
+    decode_payment_header_borrowed_with_limits(header, buf, &DecodeLimits::default())
+}
+
+/// [`decode_payment_header_borrowed`], enforcing custom [`DecodeLimits`]
+pub fn decode_payment_header_borrowed_with_limits<'a>(
+    header: &[u8],
+    buf: &'a mut Vec<u8>,
+    limits: &DecodeLimits,
+) -> Result<BorrowedSignedPayment<'a>> {
+    let (format, start) = decode_header_into(header, limits, buf)?;
+    let bytes = &buf[start..];
+    let payment = match format {
+        WireFormat::Binary => return decode_payment_binary_borrowed(bytes, limits),
+        WireFormat::Json => {
+            DecodeLimits::check("nesting depth", limits.max_depth, json_depth(bytes))?;
+            if limits.unknown_fields == UnknownFields::Reject {
+                let tracked: Tracked<BorrowedSignedPayment> = serde_json::from_slice(bytes).map_err(X402Error::Json)?;
+                limits.check_unknown(tracked)?
+            } else {
+                serde_json::from_slice(bytes).map_err(X402Error::Json)?
+            }
+        }
+        WireFormat::Cbor => parse_payload::<SignedPayment>(format, bytes, limits)?.into(),
+    };
+    DecodeLimits::check("resource", limits.max_resource_len, payment.payment.resource.len())?;
+    check_invoice_id(payment.payment.invoice_id.as_deref())?;
+    Ok(payment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_payment_header, encode_payment_header, encode_payment_header_binary, encode_payment_header_cbor,
+        Network, PaymentRequirements,
+    };
+
+    fn payment(resource: &str) -> SignedPayment {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), resource).unwrap();
+        let payment = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .expires_at(1_700_000_000)
+            .build()
+            .unwrap();
+        SignedPayment { payment, signature: vec![7; 65], signatures: Vec::new() }
+    }
+
+    #[test]
+    fn test_borrowed_decode_matches_owned() {
+        let mut buf = Vec::new();
+        let signed = payment("/reports/42");
+
+        let binary = encode_payment_header_binary(&signed).unwrap();
+        let borrowed = decode_payment_header_borrowed(binary.as_bytes(), &mut buf).unwrap();
+        assert!(matches!(borrowed.payment.resource, Cow::Borrowed("/reports/42")));
+        assert!(matches!(borrowed.signature, Cow::Borrowed(_)));
+        assert_eq!(borrowed.payment.message_hash(), signed.payment.message_hash());
+        let owned = borrowed.into_owned();
+        assert_eq!(owned.signature, signed.signature);
+        assert_eq!(owned.payment.message_hash(), decode_payment_header(&binary).unwrap().payment.message_hash());
+
+        let json = encode_payment_header(&signed).unwrap();
+        let capacity = buf.capacity();
+        let borrowed = decode_payment_header_borrowed(json.as_bytes(), &mut buf).unwrap();
+        assert!(matches!(borrowed.payment.resource, Cow::Borrowed("/reports/42")));
+        assert_eq!(borrowed.payment.message_hash(), signed.payment.message_hash());
+        assert!(buf.capacity() >= capacity);
+
+        let escaped = payment("/search?q=\"x\"");
+        let json = encode_payment_header(&escaped).unwrap();
+        let borrowed = decode_payment_header_borrowed(json.as_bytes(), &mut buf).unwrap();
+        assert!(matches!(borrowed.payment.resource, Cow::Owned(_)));
+        assert_eq!(borrowed.payment.resource, "/search?q=\"x\"");
+
+        let cbor = encode_payment_header_cbor(&signed).unwrap();
+        let borrowed = decode_payment_header_borrowed(cbor.as_bytes(), &mut buf).unwrap();
+        assert_eq!(borrowed.payment.message_hash(), signed.payment.message_hash());
+
+        let limits = DecodeLimits::default().with_max_resource_len(4);
+        let json = encode_payment_header(&signed).unwrap();
+        assert!(matches!(
+            decode_payment_header_borrowed_with_limits(json.as_bytes(), &mut buf, &limits),
+            Err(X402Error::LimitExceeded { what: "resource", .. })
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+//! Canonical JSON for signed messages
+//!
+//! Structured fields (currently `extra`) enter signed messages as canonical
+//! JSON, so every SDK hashes the same bytes:
+//!
+//! - object keys sorted by code point
+//! - no whitespace
+//! - strings escaped as in RFC 8259 (`"`, `\` and control characters only;
+//!   other characters are written as UTF-8)
+//! - numbers restricted to integers, written in plain decimal
+//!
+//! Non-integer numbers have no portable text form (`1e30`, `1.0` and
+//! `1e+30` all name the same float), so decoding rejects them and they
+//! never reach a verifier.
+
+use crate::{Extra, Result, X402Error};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Canonical text of a JSON value
+///
+/// Non-integer numbers, which [`check_canonical`] rejects, are written in
+/// serde_json's shortest form.
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Canonical text of an `extra` map
+pub fn canonical_extra(extra: &Extra) -> String {
+    let mut out = String::new();
+    write_object(&mut out, extra.iter());
+    out
+}
+
+/// Check that a value has a canonical form (no non-integer numbers)
+pub fn check_canonical(value: &Value) -> Result<()> {
+    match value {
+        Value::Number(n) if !n.is_i64() && !n.is_u64() => Err(X402Error::EncodingError(format!(
+            "non-integer number {} has no canonical form; send it as a string",
+            n
+        ))),
+        Value::Array(items) => items.iter().try_for_each(check_canonical),
+        Value::Object(map) => map.values().try_for_each(check_canonical),
+        _ => Ok(()),
+    }
+}
+
+/// Deserialize an `extra` map, rejecting values without a canonical form
+pub(crate) fn deserialize_extra<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Extra, D::Error> {
+    let extra = Extra::deserialize(deserializer)?;
+    for value in extra.values() {
+        check_canonical(value).map_err(serde::de::Error::custom)?;
+    }
+    Ok(extra)
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Object(map) => write_object(out, map.iter()),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        // serde_json already writes scalars compactly and escapes strings
+        // per RFC 8259
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+fn write_object<'a>(out: &mut String, entries: impl Iterator<Item = (&'a String, &'a Value)>) {
+    let mut entries: Vec<_> = entries.collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    out.push('{');
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&Value::from(key.as_str()).to_string());
+        out.push(':');
+        write_value(out, value);
+    }
+    out.push('}');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({"b": [1, -2, {"z": null, "a": true}], "a": "é\n\"", "c": u64::MAX});
+        assert_eq!(
+            canonical_json(&value),
+            format!(r#"{{"a":"é\n\"","b":[1,-2,{{"a":true,"z":null}}],"c":{}}}"#, u64::MAX)
+        );
+
+        assert!(check_canonical(&value).is_ok());
+        assert!(check_canonical(&json!({"price": [1.5]})).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Discovery of the protocol surface compiled into this build
+//!
+//! Deployments mixing languages (a Rust server, a Python client using the
+//! bindings, ...) can exchange [`capabilities()`] at startup and check
+//! [`Capabilities::compatible_with`] instead of finding out at the first
+//! payment that the other side lacks a scheme or wire format.
+
+use crate::{Network, Result, Scheme, WireFormat, X402Error, HEADER_FORMAT_VERSION, X402_VERSION};
+use serde::{Deserialize, Serialize};
+
+/// Recoverable secp256k1 ECDSA over a keccak256 message hash
+pub const SIGNATURE_SECP256K1: &str = "secp256k1-ecdsa-recoverable";
+
+/// BIP-340 Schnorr over secp256k1, including FROST group signatures
+pub const SIGNATURE_SCHNORR: &str = "secp256k1-schnorr-bip340";
+
+/// Protocol surface supported by a build of the SDK
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// SDK crate version
+    pub sdk_version: String,
+    /// Protocol version of 402 response bodies
+    pub x402_version: u32,
+    /// Highest header tag version understood
+    pub header_format_version: u32,
+    pub schemes: Vec<Scheme>,
+    pub networks: Vec<Network>,
+    pub signature_algorithms: Vec<String>,
+    pub wire_formats: Vec<WireFormat>,
+    /// Cargo features enabled in this build
+    pub features: Vec<String>,
+}
+
+/// Capabilities of this build
+pub fn capabilities() -> Capabilities {
+    let features = [
+        ("verify", cfg!(feature = "verify")),
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("postgres", cfg!(feature = "postgres")),
+        ("sled", cfg!(feature = "sled")),
+        ("demo-server", cfg!(feature = "demo-server")),
+        ("facilitator-http", cfg!(feature = "facilitator-http")),
+        ("tracing", cfg!(feature = "tracing")),
+        ("metrics", cfg!(feature = "metrics")),
+        ("testing", cfg!(feature = "testing")),
+        ("toml", cfg!(feature = "toml")),
+        ("graphql", cfg!(feature = "graphql")),
+        ("grpc", cfg!(feature = "grpc")),
+        ("parallel", cfg!(feature = "parallel")),
+    ];
+
+    Capabilities {
+        sdk_version: env!("CARGO_PKG_VERSION").to_string(),
+        x402_version: X402_VERSION,
+        header_format_version: HEADER_FORMAT_VERSION,
+        schemes: Scheme::ALL.to_vec(),
+        networks: Network::ALL.to_vec(),
+        signature_algorithms: vec![SIGNATURE_SECP256K1.to_string(), SIGNATURE_SCHNORR.to_string()],
+        wire_formats: WireFormat::ALL.to_vec(),
+        features: features.iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+    }
+}
+
+impl Capabilities {
+    /// Check that `other` supports everything this side relies on
+    ///
+    /// Protocol versions must match, and every scheme, network, signature
+    /// algorithm and wire format listed here must be supported by `other`.
+    /// Features and SDK versions are informational and not compared.
+    pub fn compatible_with(&self, other: &Capabilities) -> Result<()> {
+        let mut problems = Vec::new();
+        if self.x402_version != other.x402_version {
+            problems.push(format!("x402 version {} vs {}", self.x402_version, other.x402_version));
+        }
+        if other.header_format_version < self.header_format_version {
+            problems.push(format!(
+                "header format version {} vs {}",
+                self.header_format_version, other.header_format_version
+            ));
+        }
+        missing(&mut problems, "scheme", &self.schemes, &other.schemes, |s| s.as_str().to_string());
+        missing(&mut problems, "network", &self.networks, &other.networks, |n| format!("{:?}", n));
+        missing(&mut problems, "signature algorithm", &self.signature_algorithms, &other.signature_algorithms, Clone::clone);
+        missing(&mut problems, "wire format", &self.wire_formats, &other.wire_formats, |f| f.as_str().to_string());
+
+        if !problems.is_empty() {
+            return Err(X402Error::InvalidConfig(format!("incompatible peer: {}", problems.join("; "))));
+        }
+        Ok(())
+    }
+}
+
+fn missing<T: PartialEq>(problems: &mut Vec<String>, what: &str, ours: &[T], theirs: &[T], name: impl Fn(&T) -> String) {
+    for item in ours.iter().filter(|item| !theirs.contains(item)) {
+        problems.push(format!("{} {} unsupported", what, name(item)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_roundtrip_and_compatibility() {
+        let ours = capabilities();
+        assert!(ours.schemes.contains(&Scheme::Upto));
+        assert_eq!(ours.features.contains(&"sqlite".to_string()), cfg!(feature = "sqlite"));
+
+        let json = serde_json::to_string(&ours).unwrap();
+        assert!(json.contains("\"wireFormats\":[\"json\",\"cbor\",\"bin\"]"));
+        let theirs: Capabilities = serde_json::from_str(&json).unwrap();
+        assert!(ours.compatible_with(&theirs).is_ok());
+
+        let mut older = theirs;
+        older.schemes.retain(|s| *s == Scheme::Exact);
+        older.header_format_version = 1;
+        let err = ours.compatible_with(&older).unwrap_err().to_string();
+        assert!(err.contains("scheme upto unsupported") && err.contains("header format version"));
+        assert!(older.compatible_with(&ours).is_ok());
+    }
+}
@@ -0,0 +1,349 @@
+//! Runtime chain registry
+//!
+//! [`ChainRegistry`] describes chains — name, native token, block explorer
+//! and USDC contract — as data rather than code. [`ChainRegistry::builtin`]
+//! covers every [`Network`]; deployments overlay their own file with
+//! [`ChainRegistry::load`] to add chains, point at another explorer or
+//! change which chains count as testnets, without recompiling.
+//!
+//! Set [`VerifyOptions::chains`](crate::VerifyOptions) to restrict
+//! verification to the chains in a registry.
+//!
+//! [`ChainRegistry::install`] makes a registry's chains usable process-wide
+//! as [`Network::Custom`]: they parse from `eip155:<chain id>` or their
+//! registry name, serialize as `eip155:<chain id>`, and take their testnet
+//! flag and USDC contract from the registry. Until installed, a chain id
+//! outside the built-in networks is unsupported.
+
+use crate::{Network, Result, Stablecoin, X402Error};
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Chains installed with [`ChainRegistry::install`]
+static INSTALLED: RwLock<BTreeMap<u64, ChainInfo>> = RwLock::new(BTreeMap::new());
+
+/// The installed entry for `chain_id`, if any
+pub fn installed_chain(chain_id: u64) -> Option<ChainInfo> {
+    INSTALLED.read().unwrap().get(&chain_id).cloned()
+}
+
+/// The installed chain named `name` (case-insensitive), if any
+pub fn installed_chain_named(name: &str) -> Option<ChainInfo> {
+    INSTALLED.read().unwrap().values().find(|chain| chain.name.eq_ignore_ascii_case(name)).cloned()
+}
+
+/// What the registry knows about one chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    /// Human-readable name, e.g. "Base Sepolia"
+    pub name: String,
+    /// Symbol of the gas token, e.g. "ETH"
+    pub native_symbol: String,
+    /// Block explorer root, e.g. "https://basescan.org"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    /// USDC contract, if deployed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usdc: Option<Address>,
+    #[serde(default)]
+    pub testnet: bool,
+}
+
+impl ChainInfo {
+    /// Entry for a built-in network; for [`Network::Custom`], the installed
+    /// entry, or a bare placeholder if none is installed
+    pub fn builtin(network: Network) -> Self {
+        let (name, native_symbol, explorer_url) = match network {
+            Network::Ethereum => ("Ethereum", "ETH", "https://etherscan.io"),
+            Network::Sepolia => ("Sepolia", "ETH", "https://sepolia.etherscan.io"),
+            Network::Base => ("Base", "ETH", "https://basescan.org"),
+            Network::BaseSepolia => ("Base Sepolia", "ETH", "https://sepolia.basescan.org"),
+            Network::Arbitrum => ("Arbitrum One", "ETH", "https://arbiscan.io"),
+            Network::ArbitrumSepolia => ("Arbitrum Sepolia", "ETH", "https://sepolia.arbiscan.io"),
+            Network::Optimism => ("OP Mainnet", "ETH", "https://optimistic.etherscan.io"),
+            Network::OptimismSepolia => ("OP Sepolia", "ETH", "https://sepolia-optimism.etherscan.io"),
+            Network::Polygon => ("Polygon", "POL", "https://polygonscan.com"),
+            Network::PolygonAmoy => ("Polygon Amoy", "POL", "https://amoy.polygonscan.com"),
+            Network::Avalanche => ("Avalanche C-Chain", "AVAX", "https://snowtrace.io"),
+            Network::AvalancheFuji => ("Avalanche Fuji", "AVAX", "https://testnet.snowtrace.io"),
+            Network::Bnb => ("BNB Smart Chain", "BNB", "https://bscscan.com"),
+            Network::BnbTestnet => ("BNB Smart Chain Testnet", "tBNB", "https://testnet.bscscan.com"),
+            Network::ZkSync => ("zkSync Era", "ETH", "https://explorer.zksync.io"),
+            Network::ZkSyncSepolia => ("zkSync Era Sepolia", "ETH", "https://sepolia.explorer.zksync.io"),
+            Network::Linea => ("Linea", "ETH", "https://lineascan.build"),
+            Network::LineaSepolia => ("Linea Sepolia", "ETH", "https://sepolia.lineascan.build"),
+            Network::Scroll => ("Scroll", "ETH", "https://scrollscan.com"),
+            Network::ScrollSepolia => ("Scroll Sepolia", "ETH", "https://sepolia.scrollscan.com"),
+            Network::Custom(chain_id) => {
+                return installed_chain(chain_id).unwrap_or_else(|| Self {
+                    chain_id,
+                    name: format!("Chain {}", chain_id),
+                    native_symbol: "ETH".to_string(),
+                    explorer_url: None,
+                    usdc: None,
+                    testnet: false,
+                })
+            }
+        };
+        Self {
+            chain_id: network.chain_id(),
+            name: name.to_string(),
+            native_symbol: native_symbol.to_string(),
+            explorer_url: Some(explorer_url.to_string()),
+            usdc: Stablecoin::Usdc.address(network),
+            testnet: network.is_testnet(),
+        }
+    }
+
+    /// The built-in network with this chain id, if any
+    pub fn network(&self) -> Option<Network> {
+        Network::from_chain_id(self.chain_id)
+    }
+
+    /// Explorer page for the transaction `hash`
+    pub fn transaction_url(&self, hash: B256) -> Option<String> {
+        self.explorer_link("tx", &hash.to_string())
+    }
+
+    /// Explorer page for `address`
+    pub fn address_url(&self, address: Address) -> Option<String> {
+        self.explorer_link("address", &address.to_checksum(None))
+    }
+
+    fn explorer_link(&self, kind: &str, id: &str) -> Option<String> {
+        let root = self.explorer_url.as_deref()?.trim_end_matches('/');
+        Some(format!("{}/{}/{}", root, kind, id))
+    }
+}
+
+impl fmt::Display for ChainInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (chain {})", self.name, self.chain_id)
+    }
+}
+
+/// Chains by chain id
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainRegistry {
+    chains: BTreeMap<u64, ChainInfo>,
+}
+
+/// On-disk layout: a `chains` list (`[[chains]]` tables in TOML)
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChainFile {
+    chains: Vec<ChainInfo>,
+}
+
+impl ChainRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every built-in [`Network`]
+    pub fn builtin() -> Self {
+        Network::ALL.into_iter().fold(Self::new(), |registry, network| registry.with_chain(ChainInfo::builtin(network)))
+    }
+
+    /// Add `chain`, replacing any entry with the same chain id
+    pub fn with_chain(mut self, chain: ChainInfo) -> Self {
+        self.insert(chain);
+        self
+    }
+
+    /// Add `chain`, returning the entry it replaced
+    pub fn insert(&mut self, chain: ChainInfo) -> Option<ChainInfo> {
+        self.chains.insert(chain.chain_id, chain)
+    }
+
+    /// Add every chain in `other`, replacing entries with the same chain id
+    pub fn merge(mut self, other: ChainRegistry) -> Self {
+        self.chains.extend(other.chains);
+        self
+    }
+
+    pub fn get(&self, chain_id: u64) -> Option<&ChainInfo> {
+        self.chains.get(&chain_id)
+    }
+
+    pub fn contains(&self, chain_id: u64) -> bool {
+        self.chains.contains_key(&chain_id)
+    }
+
+    /// Look up a chain by decimal chain id, [`Network`] wire name or
+    /// registry name (case-insensitive)
+    pub fn resolve(&self, chain: &str) -> Result<&ChainInfo> {
+        let chain = chain.trim();
+        let by_id = chain
+            .parse::<u64>()
+            .ok()
+            .or_else(|| chain.parse::<Network>().ok().map(|network| network.chain_id()))
+            .and_then(|chain_id| self.get(chain_id));
+        by_id
+            .or_else(|| self.chains.values().find(|info| info.name.eq_ignore_ascii_case(chain)))
+            .ok_or_else(|| X402Error::UnsupportedNetwork(format!("{} is not in the chain registry", chain)))
+    }
+
+    /// Chains in chain id order
+    pub fn chains(&self) -> impl Iterator<Item = &ChainInfo> {
+        self.chains.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    /// Parse a JSON chain file: `{"chains": [{"chain_id": 1, ...}]}`
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: ChainFile =
+            serde_json::from_str(json).map_err(|e| X402Error::InvalidConfig(format!("chain file: {}", e)))?;
+        Self::from_file(file)
+    }
+
+    /// Parse a TOML chain file of `[[chains]]` tables (feature `toml`)
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let file: ChainFile =
+            toml::from_str(toml).map_err(|e| X402Error::InvalidConfig(format!("chain file: {}", e)))?;
+        Self::from_file(file)
+    }
+
+    /// Read a chain file, as TOML if its extension is `.toml` and JSON
+    /// otherwise
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| X402Error::InvalidConfig(format!("reading {}: {}", path.display(), e)))?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            #[cfg(feature = "toml")]
+            return Self::from_toml(&text);
+            #[cfg(not(feature = "toml"))]
+            return Err(X402Error::InvalidConfig(format!(
+                "{}: TOML chain files need the `toml` feature",
+                path.display()
+            )));
+        }
+        Self::from_json(&text)
+    }
+
+    /// Make these chains known process-wide, replacing installed entries
+    /// with the same chain id; chain ids outside the built-in networks then
+    /// become usable as [`Network::Custom`]
+    pub fn install(&self) {
+        INSTALLED.write().unwrap().extend(self.chains.iter().map(|(id, chain)| (*id, chain.clone())));
+    }
+
+    /// Serialize as a JSON chain file
+    pub fn to_json_pretty(&self) -> Result<String> {
+        let file = ChainFile { chains: self.chains.values().cloned().collect() };
+        serde_json::to_string_pretty(&file).map_err(|e| X402Error::EncodingError(e.to_string()))
+    }
+
+    fn from_file(file: ChainFile) -> Result<Self> {
+        let mut registry = Self::new();
+        for chain in file.chains {
+            if chain.name.trim().is_empty() {
+                return Err(X402Error::InvalidConfig(format!("chain {} has no name", chain.chain_id)));
+            }
+            let chain_id = chain.chain_id;
+            if registry.insert(chain).is_some() {
+                return Err(X402Error::InvalidConfig(format!("chain {} is listed twice", chain_id)));
+            }
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAINS_JSON: &str = r#"{
+        "chains": [
+            {
+                "chain_id": 8453,
+                "name": "Base",
+                "native_symbol": "ETH",
+                "explorer_url": "https://base.blockscout.com/"
+            },
+            {
+                "chain_id": 999999,
+                "name": "Example L3",
+                "native_symbol": "EX",
+                "usdc": "0x1111111111111111111111111111111111111111",
+                "testnet": true
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_builtin_registry_covers_every_network() {
+        let registry = ChainRegistry::builtin();
+        assert_eq!(registry.len(), Network::ALL.len());
+        for network in Network::ALL {
+            let chain = registry.get(network.chain_id()).unwrap();
+            assert_eq!((chain.network(), chain.testnet), (Some(network), network.is_testnet()));
+        }
+        assert_eq!(registry.resolve("basesepolia").unwrap().name, "Base Sepolia");
+        assert_eq!(registry.resolve("8453").unwrap().name, "Base");
+        assert_eq!(registry.resolve("op mainnet").unwrap().chain_id, 10);
+        assert!(matches!(registry.resolve("dogechain"), Err(X402Error::UnsupportedNetwork(_))));
+    }
+
+    #[test]
+    fn test_chain_file_overlays_builtin() {
+        let registry = ChainRegistry::builtin().merge(ChainRegistry::from_json(CHAINS_JSON).unwrap());
+        assert_eq!(registry.len(), Network::ALL.len() + 1);
+
+        let base = registry.resolve("base").unwrap();
+        assert_eq!(base.usdc, None);
+        assert_eq!(
+            base.transaction_url(B256::ZERO).unwrap(),
+            format!("https://base.blockscout.com/tx/{}", B256::ZERO)
+        );
+        let custom = registry.resolve("Example L3").unwrap();
+        assert_eq!((custom.chain_id, custom.network(), custom.testnet), (999999, None, true));
+        assert_eq!(custom.to_string(), "Example L3 (chain 999999)");
+        assert_eq!(custom.transaction_url(B256::ZERO), None);
+
+        let roundtrip = ChainRegistry::from_json(&registry.to_json_pretty().unwrap()).unwrap();
+        assert_eq!(roundtrip, registry);
+
+        let duplicate = r#"{"chains": [
+            {"chain_id": 1, "name": "A", "native_symbol": "ETH"},
+            {"chain_id": 1, "name": "B", "native_symbol": "ETH"}
+        ]}"#;
+        assert!(matches!(ChainRegistry::from_json(duplicate), Err(X402Error::InvalidConfig(_))));
+        let typo = r#"{"chains": [{"chain_id": 1, "name": "A", "native_symbol": "ETH", "explorer": "x"}]}"#;
+        assert!(matches!(ChainRegistry::from_json(typo), Err(X402Error::InvalidConfig(_))));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_chain_file() {
+        let toml = r#"
+            [[chains]]
+            chain_id = 999999
+            name = "Example L3"
+            native_symbol = "EX"
+            explorer_url = "https://explorer.example"
+        "#;
+        let registry = ChainRegistry::from_toml(toml).unwrap();
+        let chain = registry.get(999999).unwrap();
+        assert_eq!(
+            chain.address_url(Address::ZERO).unwrap(),
+            format!("https://explorer.example/address/{}", Address::ZERO)
+        );
+    }
+}
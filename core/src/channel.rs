@@ -0,0 +1,274 @@
+//! Unidirectional payment channels
+//!
+//! At very high request rates even verifying one signed payment per
+//! request adds up. A payer can instead lock a deposit on-chain, described
+//! by a [`ChannelOpen`], and then pay per request with off-chain
+//! [`ChannelVoucher`]s carrying a monotonically increasing cumulative
+//! amount. The server keeps only the latest voucher ([`PaymentChannel`]);
+//! on close it redeems that one voucher on-chain ([`ChannelSettlement`])
+//! and the rest of the deposit returns to the payer.
+//!
+//! Opening, and submitting the settlement, happen on-chain and are left to
+//! the caller. As with payments, signing is left to the payer's signer.
+
+use crate::{
+    decode_header, parse_payload, tagged, DecodeLimits, Result, WireFormat, X402Error,
+};
+#[cfg(feature = "verify")]
+use crate::recover_address;
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// HTTP header carrying a signed channel voucher
+pub const X402_VOUCHER_HEADER: &str = "X-Payment-Voucher";
+
+/// A channel opened by an on-chain deposit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelOpen {
+    pub payer: Address,
+    pub recipient: Address,
+    pub chain_id: u64,
+    /// Token contract (`None` = native token)
+    pub token: Option<Address>,
+    /// Amount locked in the channel
+    #[serde(with = "crate::serde_amount")]
+    pub deposit: U256,
+    /// Transaction hash of the deposit
+    pub deposit_tx: String,
+    /// Time after which the payer may reclaim the deposit (unix timestamp)
+    pub expires_at: u64,
+}
+
+impl ChannelOpen {
+    /// Identifier vouchers are bound to
+    pub fn channel_id(&self) -> B256 {
+        let message = format!(
+            "x402 Channel\nPayer: {}\nRecipient: {}\nChainId: {}\nToken: {}\nDeposit: {}\nDepositTx: {}\nExpires: {}",
+            self.payer,
+            self.recipient,
+            self.chain_id,
+            self.token.map(|t| t.to_string()).unwrap_or_default(),
+            self.deposit,
+            self.deposit_tx,
+            self.expires_at
+        );
+        keccak256(message.as_bytes())
+    }
+}
+
+/// Off-chain claim on a channel's deposit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelVoucher {
+    pub channel_id: B256,
+    /// Total paid over the channel so far, including earlier vouchers
+    #[serde(with = "crate::serde_amount")]
+    pub cumulative_amount: U256,
+}
+
+impl ChannelVoucher {
+    /// Create the message hash the payer signs
+    pub fn message_hash(&self) -> [u8; 32] {
+        let message = format!(
+            "x402 Channel Voucher\nChannel: {}\nCumulative: {}",
+            self.channel_id, self.cumulative_amount
+        );
+        *keccak256(message.as_bytes())
+    }
+}
+
+/// Voucher with the payer's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedVoucher {
+    pub voucher: ChannelVoucher,
+    /// ECDSA signature (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+impl SignedVoucher {
+    /// Encode as an `X-Payment-Voucher` header value
+    pub fn to_header(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    }
+
+    /// Decode an `X-Payment-Voucher` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let (format, bytes) = decode_header(header.as_ref(), &limits)?;
+        parse_payload(format, &bytes, &limits)
+    }
+}
+
+/// Server-side state of an open channel
+///
+/// Only the latest voucher is kept: it supersedes every earlier one.
+#[derive(Debug, Clone)]
+pub struct PaymentChannel {
+    open: ChannelOpen,
+    channel_id: B256,
+    latest: Option<SignedVoucher>,
+}
+
+impl PaymentChannel {
+    pub fn new(open: ChannelOpen) -> Result<Self> {
+        if open.deposit.is_zero() {
+            return Err(X402Error::InvalidConfig("channel deposit must be positive".to_string()));
+        }
+        let channel_id = open.channel_id();
+        Ok(Self { open, channel_id, latest: None })
+    }
+
+    pub fn open(&self) -> &ChannelOpen {
+        &self.open
+    }
+
+    pub fn channel_id(&self) -> B256 {
+        self.channel_id
+    }
+
+    /// Total claimed by the latest voucher
+    pub fn redeemed(&self) -> U256 {
+        self.latest.as_ref().map_or(U256::ZERO, |latest| latest.voucher.cumulative_amount)
+    }
+
+    /// Deposit not yet claimed
+    pub fn available(&self) -> U256 {
+        self.open.deposit - self.redeemed()
+    }
+
+    /// Accept a voucher paying at least `charge` more than the latest one,
+    /// as of the unix time `now`; returns the increment
+    #[cfg(feature = "verify")]
+    pub fn accept_voucher(&mut self, voucher: &SignedVoucher, charge: U256, now: u64) -> Result<U256> {
+        if now >= self.open.expires_at {
+            return Err(X402Error::PaymentExpired);
+        }
+        if voucher.voucher.channel_id != self.channel_id {
+            return Err(X402Error::InvalidVoucher(format!("voucher is not for channel {}", self.channel_id)));
+        }
+        let cumulative = voucher.voucher.cumulative_amount;
+        if cumulative > self.open.deposit {
+            return Err(X402Error::InvalidVoucher(format!(
+                "cumulative amount {} exceeds the deposit of {}",
+                cumulative, self.open.deposit
+            )));
+        }
+        let increment = cumulative.checked_sub(self.redeemed()).filter(|inc| !inc.is_zero()).ok_or_else(|| {
+            X402Error::InvalidVoucher(format!("cumulative amount {} does not exceed {}", cumulative, self.redeemed()))
+        })?;
+        if increment < charge {
+            return Err(X402Error::InvalidVoucher(format!("voucher pays {}, charge is {}", increment, charge)));
+        }
+
+        let signer = recover_address(&voucher.voucher.message_hash(), &voucher.signature)?;
+        if signer != self.open.payer {
+            return Err(X402Error::InvalidSignature(format!(
+                "voucher signed by {}, channel payer is {}",
+                signer, self.open.payer
+            )));
+        }
+
+        self.latest = Some(voucher.clone());
+        Ok(increment)
+    }
+
+    /// Close the channel, producing the settlement to submit on-chain
+    pub fn close(self) -> ChannelSettlement {
+        ChannelSettlement {
+            channel_id: self.channel_id,
+            to_recipient: self.redeemed(),
+            refund: self.available(),
+            voucher: self.latest,
+        }
+    }
+}
+
+/// Final split of a channel's deposit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelSettlement {
+    pub channel_id: B256,
+    #[serde(with = "crate::serde_amount")]
+    pub to_recipient: U256,
+    #[serde(with = "crate::serde_amount")]
+    pub refund: U256,
+    /// Voucher to redeem; `None` if nothing was paid
+    pub voucher: Option<SignedVoucher>,
+}
+
+/// Payer-side voucher issuance for an open channel
+#[derive(Debug, Clone)]
+pub struct ChannelPayer {
+    channel_id: B256,
+    deposit: U256,
+    cumulative: U256,
+}
+
+impl ChannelPayer {
+    pub fn new(open: &ChannelOpen) -> Self {
+        Self { channel_id: open.channel_id(), deposit: open.deposit, cumulative: U256::ZERO }
+    }
+
+    /// Total committed so far
+    pub fn cumulative(&self) -> U256 {
+        self.cumulative
+    }
+
+    /// Voucher paying `amount` more, to be signed and sent to the server
+    pub fn voucher_for(&mut self, amount: U256) -> Result<ChannelVoucher> {
+        let cumulative = self.cumulative.checked_add(amount).filter(|total| *total <= self.deposit).ok_or_else(|| {
+            X402Error::InvalidVoucher(format!("paying {} more would exceed the deposit of {}", amount, self.deposit))
+        })?;
+        self.cumulative = cumulative;
+        Ok(ChannelVoucher { channel_id: self.channel_id, cumulative_amount: cumulative })
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+
+    fn sign(signer: &TestSigner, voucher: ChannelVoucher) -> SignedVoucher {
+        let signature = signer.sign_hash(&voucher.message_hash()).unwrap();
+        SignedVoucher { voucher, signature }
+    }
+
+    #[test]
+    fn test_channel_lifecycle() {
+        let signer = TestSigner::new(0);
+        let open = ChannelOpen {
+            payer: signer.address(),
+            recipient: Address::repeat_byte(0x11),
+            chain_id: 8453,
+            token: None,
+            deposit: U256::from(100),
+            deposit_tx: "0xdeposit".to_string(),
+            expires_at: 2_000,
+        };
+        let mut channel = PaymentChannel::new(open.clone()).unwrap();
+        let mut payer = ChannelPayer::new(&open);
+
+        let first = sign(&signer, payer.voucher_for(U256::from(30)).unwrap());
+        let decoded = SignedVoucher::from_header(first.to_header().unwrap()).unwrap();
+        assert_eq!(channel.accept_voucher(&decoded, U256::from(30), 1_000).unwrap(), U256::from(30));
+        let second = sign(&signer, payer.voucher_for(U256::from(10)).unwrap());
+
+        // Replays, undercharging and late vouchers are refused
+        assert!(channel.accept_voucher(&first, U256::ZERO, 1_000).is_err());
+        assert!(channel.accept_voucher(&second, U256::from(11), 1_000).is_err());
+        assert!(matches!(channel.accept_voucher(&second, U256::from(10), 2_000), Err(X402Error::PaymentExpired)));
+        assert!(payer.voucher_for(U256::from(61)).is_err());
+
+        let forged = sign(&TestSigner::new(1), second.voucher.clone());
+        assert!(matches!(channel.accept_voucher(&forged, U256::from(10), 1_000), Err(X402Error::InvalidSignature(_))));
+
+        channel.accept_voucher(&second, U256::from(10), 1_000).unwrap();
+        let settlement = channel.close();
+        assert_eq!((settlement.to_recipient, settlement.refund), (U256::from(40), U256::from(60)));
+        assert_eq!(settlement.voucher, Some(second));
+    }
+}
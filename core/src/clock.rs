@@ -0,0 +1,157 @@
+//! Time sources and expiry helpers
+//!
+//! [`Clock`] abstracts "now" so code that stamps expiries can be tested
+//! with a [`FixedClock`]. [`PaymentRequirements::expiring_in`] and
+//! [`PaymentPayload::valid_for`] compute expiries from a [`Duration`], so
+//! callers don't add unix timestamps by hand. Timestamps and durations
+//! that only make sense as milliseconds are rejected.
+
+use crate::{PaymentPayload, PaymentPayloadBuilder, PaymentRequirements, Result, X402Error};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Unix timestamps at or above this (year 5138) are taken to be
+/// milliseconds
+pub const MAX_UNIX_SECONDS: u64 = 100_000_000_000;
+
+/// Longest validity the expiry helpers accept; longer durations are almost
+/// always milliseconds passed as seconds
+pub const MAX_VALIDITY: Duration = Duration::from_secs(366 * 24 * 60 * 60);
+
+/// Source of the current unix time, in seconds
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+}
+
+/// A clock that only moves when told to, for tests
+#[derive(Debug, Default)]
+pub struct FixedClock {
+    now: AtomicU64,
+}
+
+impl FixedClock {
+    pub fn new(now: u64) -> Self {
+        Self { now: AtomicU64::new(now) }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.fetch_add(by.as_secs(), Ordering::SeqCst);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Check that `timestamp` is in seconds, not milliseconds
+pub fn check_unix_seconds(timestamp: u64) -> Result<u64> {
+    if timestamp >= MAX_UNIX_SECONDS {
+        return Err(X402Error::InvalidConfig(format!(
+            "timestamp {} looks like milliseconds; expiries are unix seconds",
+            timestamp
+        )));
+    }
+    Ok(timestamp)
+}
+
+/// `clock`'s time plus `duration`, refusing implausibly long durations
+fn expiry<C: Clock + ?Sized>(duration: Duration, clock: &C) -> Result<u64> {
+    if duration > MAX_VALIDITY {
+        return Err(X402Error::InvalidConfig(format!(
+            "validity of {}s is over a year; was it meant as milliseconds?",
+            duration.as_secs()
+        )));
+    }
+    check_unix_seconds(check_unix_seconds(clock.now())? + duration.as_secs())
+}
+
+impl PaymentRequirements {
+    /// Expire these requirements `duration` from now
+    pub fn expiring_in(self, duration: Duration) -> Result<Self> {
+        self.expiring_in_with(duration, &SystemClock)
+    }
+
+    /// Expire these requirements `duration` after `clock`'s time
+    pub fn expiring_in_with<C: Clock + ?Sized>(mut self, duration: Duration, clock: &C) -> Result<Self> {
+        self.expires_at = Some(expiry(duration, clock)?);
+        Ok(self)
+    }
+}
+
+impl PaymentPayload {
+    /// Make this payload valid for `duration` from now
+    pub fn valid_for(self, duration: Duration) -> Result<Self> {
+        self.valid_for_with(duration, &SystemClock)
+    }
+
+    /// Make this payload valid for `duration` after `clock`'s time
+    pub fn valid_for_with<C: Clock + ?Sized>(mut self, duration: Duration, clock: &C) -> Result<Self> {
+        self.expires_at = expiry(duration, clock)?;
+        Ok(self)
+    }
+}
+
+impl PaymentPayloadBuilder {
+    /// Expire the payload `duration` after `clock`'s time
+    pub fn valid_for_with<C: Clock + ?Sized>(self, duration: Duration, clock: &C) -> Result<Self> {
+        Ok(self.expires_at(expiry(duration, clock)?))
+    }
+
+    /// Expire the payload `duration` from now
+    pub fn valid_for(self, duration: Duration) -> Result<Self> {
+        self.valid_for_with(duration, &SystemClock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Network;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn test_expiry_helpers() {
+        let clock = FixedClock::new(1_700_000_000);
+        let requirements = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api")
+            .unwrap()
+            .expiring_in_with(Duration::from_secs(300), &clock)
+            .unwrap();
+        assert_eq!(requirements.expires_at, Some(1_700_000_300));
+
+        clock.advance(Duration::from_secs(60));
+        let payload = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .valid_for_with(Duration::from_secs(120), &clock)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(payload.expires_at, 1_700_000_180);
+        assert_eq!(payload.valid_for_with(Duration::from_secs(10), &clock).unwrap().expires_at, 1_700_000_070);
+
+        let builder = PaymentPayload::builder().requirements(&requirements).payer(Address::repeat_byte(0x22));
+        assert!(matches!(builder.expires_at(1_700_000_300_000).build(), Err(X402Error::InvalidConfig(_))));
+        assert!(requirements.clone().expiring_in_with(Duration::from_secs(300_000_000), &clock).is_err());
+        clock.set(1_700_000_000_000);
+        assert!(requirements.expiring_in_with(Duration::from_secs(300), &clock).is_err());
+    }
+}
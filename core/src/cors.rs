@@ -0,0 +1,192 @@
+//! CORS for browser clients
+//!
+//! Browsers hide non-safelisted response headers from scripts unless the
+//! server lists them in `Access-Control-Expose-Headers`, and refuse to send
+//! custom request headers the server didn't allow in its preflight answer.
+//! Without both, a browser client sees a 402 it can't read
+//! `X-Payment-Requirements` from, and can't send `X-Payment`.
+//! [`CorsPolicy`] produces the headers for x402's request and response
+//! headers ([`X402_REQUEST_HEADERS`], [`X402_RESPONSE_HEADERS`]).
+
+use crate::{
+    Result, X402Error, X402_CREDIT_HEADER, X402_CREDIT_STATEMENT_HEADER, X402_LIGHTNING_HEADER, X402_OFFLINE_VOUCHER_HEADER,
+    X402_PAYMENT_HEADER, X402_PAYMENT_RESPONSE_HEADER, X402_REFUND_RECEIPT_HEADER, X402_REFUND_REQUEST_HEADER, X402_REQUIREMENTS_HEADER,
+    X402_TENANT_HEADER, X402_VOUCHER_HEADER,
+};
+
+/// Headers x402 clients send
+pub const X402_REQUEST_HEADERS: [&str; 7] = [
+    X402_PAYMENT_HEADER,
+    X402_LIGHTNING_HEADER,
+    X402_VOUCHER_HEADER,
+    X402_OFFLINE_VOUCHER_HEADER,
+    X402_CREDIT_HEADER,
+    X402_REFUND_REQUEST_HEADER,
+    X402_TENANT_HEADER,
+];
+
+/// Headers x402 servers answer with
+pub const X402_RESPONSE_HEADERS: [&str; 4] = [
+    X402_REQUIREMENTS_HEADER,
+    X402_PAYMENT_RESPONSE_HEADER,
+    X402_REFUND_RECEIPT_HEADER,
+    X402_CREDIT_STATEMENT_HEADER,
+];
+
+/// Which origins may make x402 requests from a browser
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsPolicy {
+    /// Allowed origins, e.g. `https://app.example`; `*` allows any
+    pub allowed_origins: Vec<String>,
+    /// Let requests carry cookies and HTTP auth; only with listed origins,
+    /// never `*` (see [`CorsPolicy::validate`])
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight answer, in seconds
+    pub max_age_seconds: u64,
+    /// Request headers to allow besides the x402 ones and `Content-Type`
+    pub extra_request_headers: Vec<String>,
+    /// Response headers to expose besides the x402 ones
+    pub extra_exposed_headers: Vec<String>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allow_credentials: false,
+            max_age_seconds: 600,
+            extra_request_headers: Vec::new(),
+            extra_exposed_headers: Vec::new(),
+        }
+    }
+}
+
+impl CorsPolicy {
+    /// Allow only `origins`
+    pub fn for_origins<S: Into<String>>(origins: impl IntoIterator<Item = S>) -> Self {
+        Self { allowed_origins: origins.into_iter().map(Into::into).collect(), ..Self::default() }
+    }
+
+    /// Let requests carry cookies and HTTP auth
+    ///
+    /// Fails with [`X402Error::InvalidConfig`] if any origin is allowed:
+    /// echoing every origin with credentials would let any site make
+    /// authenticated requests and read the answers.
+    pub fn with_credentials(mut self) -> Result<Self> {
+        self.allow_credentials = true;
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Fail with [`X402Error::InvalidConfig`] if credentials are allowed
+    /// together with `*`
+    pub fn validate(&self) -> Result<()> {
+        if self.allow_credentials && self.allows_any_origin() {
+            return Err(X402Error::InvalidConfig(
+                "CORS credentials can't be allowed for any origin (\"*\"); list the origins".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `origin` may make requests; `*` matches nothing when
+    /// credentials are allowed, so an invalid policy fails closed
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| (allowed == "*" && !self.allow_credentials) || allowed.eq_ignore_ascii_case(origin))
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed == "*")
+    }
+
+    /// Headers to add to an actual (non-preflight) response
+    ///
+    /// Empty if the request has no `Origin` or it is not allowed.
+    pub fn response_headers(&self, origin: Option<&str>) -> Vec<(&'static str, String)> {
+        let Some(origin) = origin.filter(|origin| self.allows_origin(origin)) else {
+            return Vec::new();
+        };
+        let mut headers = self.origin_headers(origin);
+        let exposed =
+            X402_RESPONSE_HEADERS.iter().copied().chain(self.extra_exposed_headers.iter().map(String::as_str));
+        headers.push(("Access-Control-Expose-Headers", join(exposed)));
+        headers
+    }
+
+    /// Headers answering a preflight (`OPTIONS`) request
+    ///
+    /// `None` if the origin is not allowed; the server should then answer
+    /// without CORS headers, which the browser treats as a refusal.
+    pub fn preflight_headers(&self, origin: Option<&str>) -> Option<Vec<(&'static str, String)>> {
+        let origin = origin.filter(|origin| self.allows_origin(origin))?;
+        let mut headers = self.origin_headers(origin);
+        headers.push(("Access-Control-Allow-Methods", "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS".to_string()));
+        let allowed = X402_REQUEST_HEADERS
+            .iter()
+            .copied()
+            .chain(["Content-Type"])
+            .chain(self.extra_request_headers.iter().map(String::as_str));
+        headers.push(("Access-Control-Allow-Headers", join(allowed)));
+        headers.push(("Access-Control-Max-Age", self.max_age_seconds.to_string()));
+        Some(headers)
+    }
+
+    fn origin_headers(&self, origin: &str) -> Vec<(&'static str, String)> {
+        let any = !self.allow_credentials && self.allows_any_origin();
+        let mut headers = vec![("Access-Control-Allow-Origin", if any { "*" } else { origin }.to_string())];
+        if !any {
+            headers.push(("Vary", "Origin".to_string()));
+        }
+        if self.allow_credentials {
+            headers.push(("Access-Control-Allow-Credentials", "true".to_string()));
+        }
+        headers
+    }
+}
+
+fn join<'a>(headers: impl Iterator<Item = &'a str>) -> String {
+    headers.collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get<'a>(headers: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+        headers.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_cors_headers() {
+        let any = CorsPolicy::default();
+        let headers = any.response_headers(Some("https://app.example"));
+        assert_eq!(get(&headers, "Access-Control-Allow-Origin"), Some("*"));
+        assert!(get(&headers, "Access-Control-Expose-Headers").unwrap().contains(X402_REQUIREMENTS_HEADER));
+        assert!(any.response_headers(None).is_empty());
+
+        let strict = CorsPolicy::for_origins(["https://app.example"]).with_credentials().unwrap();
+        let preflight = strict.preflight_headers(Some("https://app.example")).unwrap();
+        assert_eq!(get(&preflight, "Access-Control-Allow-Origin"), Some("https://app.example"));
+        assert_eq!(get(&preflight, "Vary"), Some("Origin"));
+        assert_eq!(get(&preflight, "Access-Control-Allow-Credentials"), Some("true"));
+        assert!(get(&preflight, "Access-Control-Allow-Headers").unwrap().starts_with("X-Payment, "));
+        assert!(strict.preflight_headers(Some("https://evil.example")).is_none());
+        assert!(strict.response_headers(Some("https://evil.example")).is_empty());
+    }
+
+    #[test]
+    fn test_credentials_never_with_any_origin() {
+        assert!(matches!(CorsPolicy::default().with_credentials(), Err(X402Error::InvalidConfig(_))));
+        let mixed = CorsPolicy::for_origins(["https://app.example", "*"]);
+        assert!(mixed.clone().with_credentials().is_err());
+
+        // Set directly, the wildcard stops matching instead of echoing
+        let invalid = CorsPolicy { allow_credentials: true, ..mixed };
+        assert!(invalid.validate().is_err());
+        assert!(invalid.response_headers(Some("https://evil.example")).is_empty());
+        let headers = invalid.response_headers(Some("https://app.example"));
+        assert_eq!(get(&headers, "Access-Control-Allow-Origin"), Some("https://app.example"));
+    }
+}
@@ -0,0 +1,412 @@
+//! Prepaid credit accounts
+//!
+//! A client making many small requests can pay once for a larger amount.
+//! The server verifies that deposit like any payment and answers with a
+//! [`CreditCredential`]: a [`CreditGrant`] signed by the server's key. The
+//! client then sends the credential with each request instead of a signed
+//! payment, and the server decrements the balance it keeps in a
+//! [`CreditAccount`]. Every so often (see [`StatementPolicy`]) the server
+//! hands out a signed [`BalanceStatement`], the client's proof of what it
+//! has left, checkable with [`verify_balance_statement`].
+//!
+//! The credential is a bearer token: whoever holds it can spend the
+//! balance, so it must only travel over TLS. As with refunds, this crate
+//! only produces the hashes to sign.
+
+use crate::{
+    decode_header, parse_payload, tagged, DecodeLimits, PaymentPayload, Result, WireFormat, X402Error,
+};
+#[cfg(feature = "verify")]
+use crate::recover_address;
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// HTTP header carrying a credit credential
+pub const X402_CREDIT_HEADER: &str = "X-Credit";
+
+/// HTTP header carrying a signed balance statement
+pub const X402_CREDIT_STATEMENT_HEADER: &str = "X-Credit-Statement";
+
+/// ID of the account `payer` holds with `recipient` for one token
+pub fn credit_account_id(payer: Address, recipient: Address, chain_id: u64, token: Option<Address>) -> B256 {
+    let mut preimage = Vec::with_capacity(80);
+    preimage.extend_from_slice(payer.as_slice());
+    preimage.extend_from_slice(recipient.as_slice());
+    preimage.extend_from_slice(&chain_id.to_be_bytes());
+    preimage.extend_from_slice(token.unwrap_or_default().as_slice());
+    keccak256(preimage)
+}
+
+/// Credit bought by one deposit payment, as signed by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditGrant {
+    pub account_id: B256,
+    pub payer: Address,
+    pub recipient: Address,
+    pub chain_id: u64,
+    /// Token contract (`None` = native token)
+    pub token: Option<Address>,
+    /// Nonce of the deposit payment
+    pub deposit_nonce: u64,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    /// When the credit was granted (unix timestamp)
+    pub issued_at: u64,
+}
+
+impl CreditGrant {
+    /// Credit for a verified deposit `payment`
+    pub fn for_payment(payment: &PaymentPayload, issued_at: u64) -> Result<Self> {
+        if payment.amount.is_zero() {
+            return Err(X402Error::InvalidCredit("deposit is empty".to_string()));
+        }
+        if !payment.splits.is_empty() {
+            return Err(X402Error::InvalidCredit("split payments can't buy credit".to_string()));
+        }
+        Ok(Self {
+            account_id: credit_account_id(payment.payer, payment.recipient, payment.chain_id, payment.token),
+            payer: payment.payer,
+            recipient: payment.recipient,
+            chain_id: payment.chain_id,
+            token: payment.token,
+            deposit_nonce: payment.nonce,
+            amount: payment.amount,
+            issued_at,
+        })
+    }
+
+    /// Create the message hash the server signs
+    pub fn message_hash(&self) -> [u8; 32] {
+        let message = format!(
+            "x402 Credit\nAccount: {}\nPayer: {}\nRecipient: {}\nChainId: {}\nToken: {}\nDepositNonce: {}\nAmount: {}\nIssued: {}",
+            self.account_id,
+            self.payer,
+            self.recipient,
+            self.chain_id,
+            self.token.map(|t| t.to_string()).unwrap_or_default(),
+            self.deposit_nonce,
+            self.amount,
+            self.issued_at
+        );
+        *keccak256(message.as_bytes())
+    }
+}
+
+/// Server-signed credit grant, presented by the client in place of payments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreditCredential {
+    pub grant: CreditGrant,
+    /// Address of the signing server key
+    pub server: Address,
+    /// ECDSA signature over [`CreditGrant::message_hash`] (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+impl CreditCredential {
+    /// Check the credential was signed by its server, returning the server address
+    ///
+    /// Servers must also check the returned address is their own key.
+    #[cfg(feature = "verify")]
+    pub fn verify(&self) -> Result<Address> {
+        let grant = &self.grant;
+        if grant.account_id != credit_account_id(grant.payer, grant.recipient, grant.chain_id, grant.token) {
+            return Err(X402Error::InvalidCredit("account ID does not match the grant".to_string()));
+        }
+        let signer = recover_address(&grant.message_hash(), &self.signature)?;
+        if signer != self.server {
+            return Err(X402Error::InvalidSignature(
+                "credit credential signature does not match server".to_string()
+            ));
+        }
+        Ok(signer)
+    }
+
+    /// Encode as an `X-Credit` header value
+    pub fn to_header(&self) -> Result<String> {
+        to_header(self)
+    }
+
+    /// Decode an `X-Credit` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        from_header(header.as_ref())
+    }
+}
+
+/// Balance of a credit account at one point, as signed by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceStatement {
+    pub account_id: B256,
+    #[serde(with = "crate::serde_amount")]
+    pub balance: U256,
+    /// Total charged since the account was opened
+    #[serde(with = "crate::serde_amount")]
+    pub spent: U256,
+    /// Number of charges since the account was opened
+    pub charges: u64,
+    /// Position of this statement; later statements supersede earlier ones
+    pub sequence: u64,
+    pub issued_at: u64,
+}
+
+impl BalanceStatement {
+    /// Create the message hash the server signs
+    pub fn message_hash(&self) -> [u8; 32] {
+        let message = format!(
+            "x402 Credit Statement\nAccount: {}\nBalance: {}\nSpent: {}\nCharges: {}\nSequence: {}\nIssued: {}",
+            self.account_id, self.balance, self.spent, self.charges, self.sequence, self.issued_at
+        );
+        *keccak256(message.as_bytes())
+    }
+}
+
+/// Server-signed balance statement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedStatement {
+    pub statement: BalanceStatement,
+    /// Address of the signing server key
+    pub server: Address,
+    /// ECDSA signature over [`BalanceStatement::message_hash`] (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+impl SignedStatement {
+    /// Encode as an `X-Credit-Statement` header value
+    pub fn to_header(&self) -> Result<String> {
+        to_header(self)
+    }
+
+    /// Decode an `X-Credit-Statement` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        from_header(header.as_ref())
+    }
+}
+
+/// Verify a statement is for `credential`'s account and was signed by the
+/// server that issued it
+#[cfg(feature = "verify")]
+pub fn verify_balance_statement(statement: &SignedStatement, credential: &CreditCredential) -> Result<()> {
+    if statement.statement.account_id != credential.grant.account_id {
+        return Err(X402Error::InvalidCredit("statement is for a different account".to_string()));
+    }
+    if statement.server != credential.server {
+        return Err(X402Error::InvalidCredit("statement is from a different server".to_string()));
+    }
+    let signer = recover_address(&statement.statement.message_hash(), &statement.signature)?;
+    if signer != statement.server {
+        return Err(X402Error::InvalidSignature(
+            "balance statement signature does not match server".to_string()
+        ));
+    }
+    Ok(())
+}
+
+/// When a [`CreditAccount`] is due a new statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementPolicy {
+    /// Issue a statement after this many charges
+    pub every_charges: u64,
+    /// Issue a statement once this long has passed since the last one, if
+    /// anything was charged
+    pub every_seconds: u64,
+}
+
+impl Default for StatementPolicy {
+    fn default() -> Self {
+        Self { every_charges: 100, every_seconds: 3600 }
+    }
+}
+
+/// Server-side state of a credit account
+///
+/// Serializable, so servers can persist it between charges.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditAccount {
+    account_id: B256,
+    payer: Address,
+    #[serde(with = "crate::serde_amount")]
+    balance: U256,
+    #[serde(with = "crate::serde_amount")]
+    spent: U256,
+    charges: u64,
+    deposits: BTreeSet<u64>,
+    statements: u64,
+    last_statement_charges: u64,
+    last_statement_at: u64,
+}
+
+impl CreditAccount {
+    /// Open an account holding the credit of `grant`
+    pub fn open(grant: &CreditGrant) -> Self {
+        Self {
+            account_id: grant.account_id,
+            payer: grant.payer,
+            balance: grant.amount,
+            spent: U256::ZERO,
+            charges: 0,
+            deposits: BTreeSet::from([grant.deposit_nonce]),
+            statements: 0,
+            last_statement_charges: 0,
+            last_statement_at: grant.issued_at,
+        }
+    }
+
+    pub fn account_id(&self) -> B256 {
+        self.account_id
+    }
+
+    pub fn payer(&self) -> Address {
+        self.payer
+    }
+
+    /// Credit left
+    pub fn balance(&self) -> U256 {
+        self.balance
+    }
+
+    /// Total charged so far
+    pub fn spent(&self) -> U256 {
+        self.spent
+    }
+
+    /// Add the credit of a further deposit, returning the new balance
+    ///
+    /// Fails with [`X402Error::AmountOverflow`], leaving the account as it
+    /// was, if the balance would exceed `U256::MAX`.
+    pub fn top_up(&mut self, grant: &CreditGrant) -> Result<U256> {
+        if grant.account_id != self.account_id {
+            return Err(X402Error::InvalidCredit(format!("grant is not for account {}", self.account_id)));
+        }
+        if self.deposits.contains(&grant.deposit_nonce) {
+            return Err(X402Error::DuplicatePayment(format!(
+                "account {} deposit {}",
+                self.account_id, grant.deposit_nonce
+            )));
+        }
+        self.balance = self.balance.checked_add(grant.amount).ok_or_else(|| X402Error::AmountOverflow {
+            amount: format!("{} + {}", self.balance, grant.amount),
+            decimals: 0,
+        })?;
+        self.deposits.insert(grant.deposit_nonce);
+        Ok(self.balance)
+    }
+
+    /// Deduct `amount` for one request, returning the remaining balance
+    pub fn charge(&mut self, amount: U256) -> Result<U256> {
+        if amount > self.balance {
+            return Err(X402Error::InsufficientCredit(format!(
+                "account {} has {}, charge is {}",
+                self.account_id, self.balance, amount
+            )));
+        }
+        self.balance -= amount;
+        self.spent += amount;
+        self.charges += 1;
+        Ok(self.balance)
+    }
+
+    /// Whether `policy` calls for a new statement at unix time `now`
+    pub fn statement_due(&self, policy: &StatementPolicy, now: u64) -> bool {
+        let charged = self.charges - self.last_statement_charges;
+        charged > 0
+            && (charged >= policy.every_charges || now.saturating_sub(self.last_statement_at) >= policy.every_seconds)
+    }
+
+    /// The next statement for the server to sign, as of unix time `now`
+    pub fn statement(&mut self, now: u64) -> BalanceStatement {
+        self.statements += 1;
+        self.last_statement_charges = self.charges;
+        self.last_statement_at = now;
+        BalanceStatement {
+            account_id: self.account_id,
+            balance: self.balance,
+            spent: self.spent,
+            charges: self.charges,
+            sequence: self.statements,
+            issued_at: now,
+        }
+    }
+}
+
+fn to_header<T: Serialize>(value: &T) -> Result<String> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+    Ok(tagged(WireFormat::Json, json.as_bytes()))
+}
+
+fn from_header<T: DeserializeOwned>(header: &[u8]) -> Result<T> {
+    let limits = DecodeLimits::default();
+    let (format, bytes) = decode_header(header, &limits)?;
+    parse_payload(format, &bytes, &limits)
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{verify_payment_at, Network, PaymentRequirements};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn deposit(payer: &TestSigner, price: &str) -> CreditGrant {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, price, Address::repeat_byte(0x11), "/credit").unwrap();
+        let payment = payer.pay(&requirements, NOW).unwrap();
+        verify_payment_at(&payment, &requirements, NOW).unwrap();
+        CreditGrant::for_payment(&payment.payment, NOW).unwrap()
+    }
+
+    #[test]
+    fn test_credit_account_flow() {
+        let (payer, server) = (TestSigner::new(1), TestSigner::new(9));
+        let grant = deposit(&payer, "1.00");
+        let credential = CreditCredential {
+            signature: server.sign_hash(&grant.message_hash()).unwrap(),
+            grant,
+            server: server.address(),
+        };
+        let credential = CreditCredential::from_header(credential.to_header().unwrap()).unwrap();
+        assert_eq!(credential.verify().unwrap(), server.address());
+
+        let mut account = CreditAccount::open(&credential.grant);
+        let policy = StatementPolicy { every_charges: 3, every_seconds: 60 };
+        assert!(!account.statement_due(&policy, NOW + 600));
+        assert_eq!(account.charge(U256::from(400_000)).unwrap(), U256::from(600_000));
+        assert!(!account.statement_due(&policy, NOW + 1));
+        assert!(account.statement_due(&policy, NOW + 60));
+        account.charge(U256::from(500_000)).unwrap();
+        assert!(matches!(account.charge(U256::from(200_000)), Err(X402Error::InsufficientCredit(_))));
+        account.charge(U256::from(100_000)).unwrap();
+        assert!(account.statement_due(&policy, NOW + 1));
+
+        let statement = account.statement(NOW + 1);
+        assert_eq!((statement.balance, statement.spent, statement.charges), (U256::ZERO, U256::from(1_000_000), 3));
+        assert!(!account.statement_due(&policy, NOW + 1));
+        let signed = SignedStatement {
+            signature: server.sign_hash(&statement.message_hash()).unwrap(),
+            statement,
+            server: server.address(),
+        };
+        verify_balance_statement(&SignedStatement::from_header(signed.to_header().unwrap()).unwrap(), &credential)
+            .unwrap();
+        let mut forged = signed.clone();
+        forged.statement.balance = U256::from(1);
+        assert!(verify_balance_statement(&forged, &credential).is_err());
+
+        let top_up = deposit(&payer, "0.50");
+        assert_eq!(account.top_up(&top_up).unwrap(), U256::from(500_000));
+        assert!(matches!(account.top_up(&top_up), Err(X402Error::DuplicatePayment(_))));
+        assert!(matches!(account.top_up(&deposit(&TestSigner::new(2), "1")), Err(X402Error::InvalidCredit(_))));
+        let huge = CreditGrant { amount: U256::MAX, deposit_nonce: top_up.deposit_nonce + 1, ..top_up };
+        assert!(matches!(account.top_up(&huge), Err(X402Error::AmountOverflow { .. })));
+        assert_eq!(account.balance(), U256::from(500_000));
+
+        let mut tampered = credential.clone();
+        tampered.grant.amount = U256::from(100_000_000);
+        assert!(tampered.verify().is_err());
+    }
+}
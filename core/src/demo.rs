@@ -0,0 +1,503 @@
+//! Runnable paid "hello world" API (feature `demo-server`)
+//!
+//! [`DemoServer`] wires the pieces of this crate into the smallest
+//! complete x402 server: it quotes requirements for a reverse-proxy aware
+//! resource URI, answers unpaid requests with a 402 (JSON, or a paywall
+//! page for browsers), verifies payments in soft-fail mode against a
+//! [`MockFacilitator`], and records them in a [`MemoryLedger`] so replays
+//! are refused. It speaks just enough HTTP/1.1 over `std::net` to need no
+//! extra dependencies, and is the end-to-end smoke test for the stack; the
+//! `x402-demo-server` binary runs it.
+//!
+//! With [`DemoServer::with_resolver`] it serves several tenants: each
+//! request's host and path pick the recipient and price, narrowed by the
+//! tenant named in [`X402_TENANT_HEADER`].
+
+use crate::events::report;
+use crate::{
+    decode_payment_header, prefers_html, CorsPolicy, DefaultPaywall, DeferredCheck, LedgerEntry, MemoryBlacklist,
+    MemoryLedger, Network, PaymentEvent, PaymentEvents, PaymentLedger, PaymentRequiredResponse, PaymentRequirements,
+    PaywallRenderer, RecipientResolver, RequestParts, ResourceUriBuilder, Result, Scheme, SignedPayment,
+    SoftFailVerifier, TenantLedgers, TenantRoute, VerificationMode, X402Error, X402_PAYMENT_HEADER,
+    X402_REQUIREMENTS_HEADER, X402_TENANT_HEADER,
+};
+use alloy_primitives::{Address, U256};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_REQUEST_HEAD: u64 = 16 * 1024;
+
+/// What the demo server charges for, and who gets paid
+#[derive(Debug, Clone)]
+pub struct DemoConfig {
+    pub recipient: Address,
+    pub network: Network,
+    pub price: U256,
+    /// Path of the paid endpoint
+    pub path: String,
+    /// How long quoted requirements stay valid, in seconds
+    pub quote_ttl: u64,
+    /// Networks whose `X-Forwarded-*` headers are trusted
+    pub trusted_proxies: Vec<String>,
+    /// Browser origins allowed to call the endpoint; `None` disables CORS
+    pub cors: Option<CorsPolicy>,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            recipient: Address::repeat_byte(0x42),
+            network: Network::BaseSepolia,
+            price: U256::from(1000),
+            path: "/hello".to_string(),
+            quote_ttl: 300,
+            trusted_proxies: vec!["127.0.0.1".to_string(), "::1".to_string()],
+            cors: Some(CorsPolicy::default()),
+        }
+    }
+}
+
+/// Facilitator stand-in that accepts every locally verified payment
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockFacilitator;
+
+impl DeferredCheck for MockFacilitator {
+    fn check(&self, _: &SignedPayment, _: &PaymentRequirements) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Single-threaded demo server; see the [module docs](self)
+pub struct DemoServer {
+    listener: TcpListener,
+    config: DemoConfig,
+    uris: ResourceUriBuilder,
+    verifier: SoftFailVerifier<MockFacilitator, MemoryBlacklist>,
+    ledger: Arc<MemoryLedger>,
+    paywall: Box<dyn PaywallRenderer>,
+    resolver: Option<Arc<dyn RecipientResolver>>,
+    tenant_ledgers: Option<Arc<TenantLedgers>>,
+    events: Option<Arc<dyn PaymentEvents>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl DemoServer {
+    pub fn bind(addr: impl ToSocketAddrs, config: DemoConfig) -> Result<Self> {
+        if let Some(cors) = &config.cors {
+            cors.validate()?;
+        }
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| X402Error::InvalidConfig(format!("cannot bind demo server: {}", e)))?;
+        let uris = ResourceUriBuilder::new().trust_proxies(&config.trusted_proxies)?;
+        Ok(Self {
+            listener,
+            config,
+            uris,
+            verifier: SoftFailVerifier::new(MockFacilitator, Arc::new(MemoryBlacklist::new())),
+            ledger: Arc::new(MemoryLedger::new()),
+            paywall: Box::new(DefaultPaywall::new()),
+            resolver: None,
+            tenant_ledgers: None,
+            events: None,
+        })
+    }
+
+    /// Render browser paywall pages with `paywall` instead of [`DefaultPaywall`]
+    pub fn with_paywall(mut self, paywall: impl PaywallRenderer + 'static) -> Self {
+        self.paywall = Box::new(paywall);
+        self
+    }
+
+    /// Route requests to tenants with `resolver`, by host, path and the
+    /// tenant in [`X402_TENANT_HEADER`]; the config's recipient and price become
+    /// defaults the routes override. Paths no tenant serves get a 404.
+    pub fn with_resolver(mut self, resolver: Arc<dyn RecipientResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Record each resolved tenant's payments in its own ledger, instead of
+    /// [`DemoServer::ledger`]
+    pub fn with_tenant_ledgers(mut self, ledgers: Arc<TenantLedgers>) -> Self {
+        self.tenant_ledgers = Some(ledgers);
+        self
+    }
+
+    /// Report accepted payments to `events`
+    pub fn with_events(mut self, events: Arc<dyn PaymentEvents>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(|e| X402Error::InvalidConfig(e.to_string()))
+    }
+
+    /// Payments accepted so far
+    pub fn ledger(&self) -> &Arc<MemoryLedger> {
+        &self.ledger
+    }
+
+    /// Serve connections one at a time until the listener fails
+    pub fn serve(&self) -> Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream.map_err(|e| X402Error::InvalidConfig(format!("accept failed: {}", e)))?;
+            if let Err(e) = self.handle_connection(stream) {
+                eprintln!("x402-demo-server: connection error: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let peer = stream.peer_addr().ok().map(|a| a.ip());
+        let response = match read_request(&stream)? {
+            Some(request) => self.respond_with_cors(&request, peer),
+            None => text_response(400, "malformed request"),
+        };
+        write_response(&mut stream, &response)
+    }
+
+    fn respond_with_cors(&self, request: &Request, peer: Option<std::net::IpAddr>) -> Response {
+        let origin = request.header("Origin");
+        let Some(cors) = &self.config.cors else {
+            return self.respond(request, peer);
+        };
+        if request.method == "OPTIONS" {
+            let mut response = text_response(204, "");
+            response.headers = cors.preflight_headers(origin).unwrap_or_default();
+            return response;
+        }
+        let mut response = self.respond(request, peer);
+        response.headers.extend(cors.response_headers(origin));
+        response
+    }
+
+    fn respond(&self, request: &Request, peer: Option<std::net::IpAddr>) -> Response {
+        let route = match &self.resolver {
+            Some(resolver) => match resolver.resolve(
+                &request.path,
+                request.header("Host"),
+                request.header(X402_TENANT_HEADER),
+            ) {
+                Ok(Some(route)) => Some(route),
+                Ok(None) => return text_response(404, "not found"),
+                Err(e) => return text_response(e.status_code(), &e.to_string()),
+            },
+            None if request.path != self.config.path => return text_response(404, "not found"),
+            None => None,
+        };
+
+        let headers: Vec<(&str, &str)> = request.headers.iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+        let parts = RequestParts {
+            scheme: "http",
+            host: request.header("Host").unwrap_or("localhost"),
+            path: &request.path,
+            peer,
+            headers: &headers,
+        };
+        let resource = self.uris.resource_uri(&parts);
+        let requirements = match &route {
+            Some(route) => route.apply(&self.requirements(String::new()), &resource),
+            None => self.requirements(resource),
+        };
+        let paywall = request.header("Accept").is_some_and(prefers_html).then_some(&*self.paywall);
+
+        let Some(header) = request.header(X402_PAYMENT_HEADER) else {
+            return payment_required(&requirements, None, paywall);
+        };
+
+        match self.accept_payment(header, &requirements, route.as_ref()) {
+            Ok(payer) => Response {
+                status: 200,
+                content_type: "application/json",
+                headers: Vec::new(),
+                body: serde_json::json!({ "message": "hello, world", "payer": payer }).to_string(),
+            },
+            Err(e) if e.status_code() == 402 => payment_required(&requirements, Some(e.to_string()), paywall),
+            Err(e) => text_response(e.status_code(), &e.to_string()),
+        }
+    }
+
+    fn requirements(&self, resource: String) -> PaymentRequirements {
+        PaymentRequirements {
+            amount: self.config.price,
+            recipient: self.config.recipient,
+            network: self.config.network,
+            token: None,
+            description: Some("Hello, world".to_string()),
+            expires_at: Some(now().saturating_add(self.config.quote_ttl)),
+            resource,
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: Some("application/json".to_string()),
+            output_schema: Some(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string" },
+                    "payer": { "type": "string" }
+                }
+            })),
+            max_timeout_seconds: Some(self.config.quote_ttl),
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        }
+    }
+
+    fn accept_payment(
+        &self,
+        header: &str,
+        requirements: &PaymentRequirements,
+        route: Option<&TenantRoute>,
+    ) -> Result<Address> {
+        let payment = decode_payment_header(header)?;
+        let payer = self.verifier.verify(&payment, requirements, VerificationMode::SoftFail)?;
+        let entry = LedgerEntry::new(&payment.payment, now());
+        match (&self.tenant_ledgers, route) {
+            (Some(ledgers), Some(route)) => ledgers.ledger(&route.tenant)?.record(&entry)?,
+            _ => self.ledger.record(&entry)?,
+        }
+        report(self.events.as_ref(), PaymentEvent::verified(&payment.payment));
+        Ok(payer)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn payment_required(
+    requirements: &PaymentRequirements,
+    error: Option<String>,
+    paywall: Option<&dyn PaywallRenderer>,
+) -> Response {
+    let mut response = PaymentRequiredResponse::new(requirements.clone());
+    response.error = error;
+    let header = match response.header_value() {
+        Ok(header) => header,
+        Err(e) => return text_response(500, &e.to_string()),
+    };
+
+    let (content_type, body) = match paywall {
+        Some(paywall) => ("text/html; charset=utf-8", paywall.render(requirements, response.error.as_deref())),
+        None => ("application/json", response.to_json_body().unwrap_or_default()),
+    };
+    Response {
+        status: 402,
+        content_type,
+        headers: vec![(X402_REQUIREMENTS_HEADER, header)],
+        body,
+    }
+}
+
+fn text_response(status: u16, body: &str) -> Response {
+    Response {
+        status,
+        content_type: "text/plain; charset=utf-8",
+        headers: Vec::new(),
+        body: body.to_string(),
+    }
+}
+
+/// Read a request line and headers; the body (if any) is ignored
+fn read_request(stream: &TcpStream) -> std::io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut request_line = line.split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Ok(None);
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(Some(Request { method, path, headers }))
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        402 => "Payment Required",
+        403 => "Forbidden",
+        404 => "Not Found",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{decode_requirements_header, encode_payment_header, PaymentPayload, ResourceMatcher, RouteTable};
+
+    fn get(addr: SocketAddr, headers: &[(&str, &str)]) -> (u16, String) {
+        send(addr, "GET", "/hello", headers)
+    }
+
+    fn send(addr: SocketAddr, method: &str, path: &str, headers: &[(&str, &str)]) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, addr);
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        (status, response)
+    }
+
+    fn header_value<'a>(response: &'a str, name: &str) -> &'a str {
+        response.lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix(": "))
+            .unwrap()
+    }
+
+    /// Payment header paying `requirements` with nonce `nonce`
+    fn pay(requirements: &PaymentRequirements, nonce: u64) -> String {
+        let signer = TestSigner::default();
+        let payload = PaymentPayload {
+            amount: requirements.amount,
+            recipient: requirements.recipient,
+            payer: signer.address(),
+            chain_id: requirements.network.chain_id(),
+            token: None,
+            resource: requirements.resource.clone(),
+            nonce,
+            expires_at: requirements.expires_at.unwrap(),
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        encode_payment_header(&signer.sign(payload).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_demo_server_end_to_end() {
+        let server = DemoServer::bind("127.0.0.1:0", DemoConfig::default()).unwrap();
+        let addr = server.local_addr().unwrap();
+        let ledger = Arc::clone(server.ledger());
+        std::thread::spawn(move || server.serve());
+
+        let (status, response) = get(addr, &[("Accept", "text/html,*/*;q=0.8")]);
+        assert_eq!(status, 402);
+        assert!(response.contains("text/html") && response.contains("<code>X-Payment</code>"));
+
+        let origin = ("Origin", "https://app.example");
+        let (status, response) = send(addr, "OPTIONS", "/hello", &[origin]);
+        assert_eq!(status, 204);
+        assert!(header_value(&response, "Access-Control-Allow-Headers").contains(X402_PAYMENT_HEADER));
+        let (_, response) = get(addr, &[origin]);
+        assert!(header_value(&response, "Access-Control-Expose-Headers").contains(X402_REQUIREMENTS_HEADER));
+
+        let (status, response) = get(addr, &[]);
+        assert_eq!(status, 402);
+        let requirements = decode_requirements_header(header_value(&response, X402_REQUIREMENTS_HEADER)).unwrap();
+        let header = pay(&requirements, 1);
+
+        let (status, response) = get(addr, &[(X402_PAYMENT_HEADER, &header)]);
+        assert_eq!(status, 200, "{}", response);
+        assert!(response.contains("hello, world"));
+        assert_eq!(ledger.len(), 1);
+
+        let (status, response) = get(addr, &[(X402_PAYMENT_HEADER, &header)]);
+        assert_eq!(status, 402);
+        assert!(response.contains("Duplicate payment"));
+    }
+
+    #[test]
+    fn test_demo_server_routes_tenants() {
+        let resolver = RouteTable::new()
+            .route(
+                ResourceMatcher::Prefix("/alice".to_string()),
+                TenantRoute { tenant: "alice".to_string(), recipient: Address::repeat_byte(0xa1), amount: U256::from(5) },
+            )
+            .route(
+                ResourceMatcher::Prefix("/shared".to_string()),
+                TenantRoute { tenant: "bob".to_string(), recipient: Address::repeat_byte(0xb0), amount: U256::from(7) },
+            );
+        let ledgers = Arc::new(TenantLedgers::new());
+        let server = DemoServer::bind("127.0.0.1:0", DemoConfig::default())
+            .unwrap()
+            .with_resolver(Arc::new(resolver))
+            .with_tenant_ledgers(Arc::clone(&ledgers));
+        let addr = server.local_addr().unwrap();
+        let ledger = Arc::clone(server.ledger());
+        std::thread::spawn(move || server.serve());
+
+        assert_eq!(get(addr, &[]).0, 404);
+        assert_eq!(send(addr, "GET", "/shared", &[(X402_TENANT_HEADER, "alice")]).0, 404);
+
+        let (status, response) = send(addr, "GET", "/alice/data", &[]);
+        assert_eq!(status, 402);
+        let requirements = decode_requirements_header(header_value(&response, X402_REQUIREMENTS_HEADER)).unwrap();
+        assert_eq!(requirements.recipient, Address::repeat_byte(0xa1));
+        assert_eq!(requirements.amount, U256::from(5));
+        assert!(requirements.resource.ends_with("/alice/data"));
+
+        let (status, response) = send(addr, "GET", "/alice/data", &[(X402_PAYMENT_HEADER, &pay(&requirements, 1))]);
+        assert_eq!(status, 200, "{}", response);
+        assert_eq!(ledgers.tenants(), ["alice"]);
+        assert_eq!(ledgers.ledger("alice").unwrap().query(&Default::default()).unwrap().len(), 1);
+        assert_eq!(ledger.len(), 0);
+    }
+}
@@ -0,0 +1,365 @@
+//! Out-of-band recipient attestation via DNS TXT records
+//!
+//! A server can publish the addresses it receives payments at in a TXT
+//! record on `_x402.<domain>`:
+//!
+//! ```text
+//! _x402.api.example.com. 300 IN TXT "x402-recipient=0x1234...abcd"
+//! ```
+//!
+//! Clients look the record up over DNS-over-HTTPS before paying, giving an
+//! independent check that the party asking for payment controls the API's
+//! domain. Lookups are cached for the record's TTL, for at most
+//! [`DEFAULT_DNS_CACHE_CAPACITY`] hosts at a time.
+
+use crate::{Result, X402Error};
+use alloy_primitives::Address;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Label prepended to the resource's host when looking up attestations
+pub const X402_TXT_LABEL: &str = "_x402";
+
+/// Key used inside the TXT record value
+pub const X402_TXT_RECIPIENT_KEY: &str = "x402-recipient";
+
+/// Default DoH endpoint (JSON API)
+pub const DEFAULT_DOH_ENDPOINT: &str = "https://cloudflare-dns.com/dns-query";
+
+/// Hosts a [`RecipientVerifier`] caches lookups for by default
+pub const DEFAULT_DNS_CACHE_CAPACITY: usize = 1024;
+
+/// A TXT record returned by a resolver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxtRecord {
+    /// Record text with surrounding quotes removed
+    pub value: String,
+    /// Time-to-live in seconds
+    pub ttl: u32,
+}
+
+/// Resolves TXT records for a DNS name
+pub trait TxtResolver: Send + Sync {
+    fn lookup_txt(&self, name: &str) -> impl Future<Output = Result<Vec<TxtRecord>>> + Send;
+}
+
+/// Performs the HTTPS GET for a DoH query
+pub trait DohHttpClient: Send + Sync {
+    /// GET `url` with `Accept: application/dns-json`, returning the body
+    fn get(&self, url: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+/// [`TxtResolver`] using the DoH JSON API (Cloudflare/Google style)
+pub struct DohResolver<C: DohHttpClient> {
+    client: C,
+    endpoint: String,
+}
+
+impl<C: DohHttpClient> DohResolver<C> {
+    pub fn new(client: C) -> Self {
+        Self::with_endpoint(client, DEFAULT_DOH_ENDPOINT)
+    }
+
+    pub fn with_endpoint(client: C, endpoint: impl Into<String>) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL")]
+    ttl: u32,
+    data: String,
+}
+
+/// Parse a DoH JSON response body into TXT records
+pub fn parse_doh_txt_response(body: &[u8]) -> Result<Vec<TxtRecord>> {
+    const TYPE_TXT: u16 = 16;
+    const NXDOMAIN: u32 = 3;
+
+    let response: DohResponse = serde_json::from_slice(body)
+        .map_err(|e| X402Error::DnsLookup(format!("invalid DoH response: {}", e)))?;
+
+    match response.status {
+        0 | NXDOMAIN => {}
+        status => return Err(X402Error::DnsLookup(format!("DNS error status {}", status))),
+    }
+
+    Ok(response.answer.into_iter()
+        .filter(|a| a.record_type == TYPE_TXT)
+        .map(|a| TxtRecord {
+            // Long TXT records arrive as several quoted strings
+            value: a.data.split('"').filter(|s| !s.trim().is_empty()).collect(),
+            ttl: a.ttl,
+        })
+        .collect())
+}
+
+/// Check that `name` is a DNS name: dot-separated labels of letters,
+/// digits, `-` and `_`, each 1 to 63 bytes, 253 bytes in all
+pub fn check_dns_name(name: &str) -> Result<()> {
+    let invalid = || X402Error::DnsLookup(format!("not a DNS name: {:?}", name));
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() > 253 {
+        return Err(invalid());
+    }
+    for label in name.split('.') {
+        let valid_chars = label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if label.is_empty() || label.len() > 63 || !valid_chars || label.starts_with('-') || label.ends_with('-') {
+            return Err(invalid());
+        }
+    }
+    Ok(())
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(b as char),
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+impl<C: DohHttpClient> TxtResolver for DohResolver<C> {
+    /// Query the endpoint for `name`, which must pass [`check_dns_name`]
+    async fn lookup_txt(&self, name: &str) -> Result<Vec<TxtRecord>> {
+        check_dns_name(name)?;
+        let url = format!("{}?name={}&type=TXT", self.endpoint, percent_encode(name));
+        let body = self.client.get(&url).await?;
+        parse_doh_txt_response(&body)
+    }
+}
+
+/// Extract the attested recipient from a TXT record value, if present
+pub fn parse_recipient_record(value: &str) -> Option<Address> {
+    value.split_whitespace()
+        .filter_map(|field| field.split_once('='))
+        .find(|(key, _)| *key == X402_TXT_RECIPIENT_KEY)
+        .and_then(|(_, addr)| Address::from_str(addr).ok())
+}
+
+/// Extract the host from an absolute resource URL
+pub fn resource_host(resource: &str) -> Option<&str> {
+    let (_, rest) = resource.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if port.chars().all(|c| c.is_ascii_digit()) => h,
+        _ => host,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+struct CacheEntry {
+    recipients: Vec<Address>,
+    expires: Instant,
+}
+
+/// Checks recipients against `_x402` TXT records, caching lookups
+///
+/// Once the cache holds its capacity of hosts, a new lookup evicts the
+/// expired entries, or failing that the one expiring soonest.
+pub struct RecipientVerifier<R: TxtResolver> {
+    resolver: R,
+    cache: Mutex<HashMap<String, CacheEntry>>,
+    max_ttl: Duration,
+    cache_capacity: usize,
+}
+
+impl<R: TxtResolver> RecipientVerifier<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            cache: Mutex::new(HashMap::new()),
+            max_ttl: Duration::from_secs(3600),
+            cache_capacity: DEFAULT_DNS_CACHE_CAPACITY,
+        }
+    }
+
+    /// Cache lookups for at most `capacity` hosts; 0 disables the cache
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity;
+        self
+    }
+
+    /// Cap how long a lookup is cached regardless of the record TTL
+    pub fn with_max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    /// Addresses attested for a host (possibly from cache)
+    pub async fn attested_recipients(&self, host: &str) -> Result<Vec<Address>> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        if let Some(entry) = self.cache.lock().unwrap().get(&host) {
+            if entry.expires > Instant::now() {
+                return Ok(entry.recipients.clone());
+            }
+        }
+
+        let records = self.resolver
+            .lookup_txt(&format!("{}.{}", X402_TXT_LABEL, host))
+            .await?;
+        let ttl = records.iter().map(|r| r.ttl).min().unwrap_or(0);
+        let recipients: Vec<Address> = records.iter()
+            .filter_map(|r| parse_recipient_record(&r.value))
+            .collect();
+
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if !cache.contains_key(&host) && cache.len() >= self.cache_capacity {
+            cache.retain(|_, entry| entry.expires > now);
+            while cache.len() >= self.cache_capacity {
+                let Some(soonest) = cache.iter().min_by_key(|(_, entry)| entry.expires).map(|(h, _)| h.clone()) else {
+                    break;
+                };
+                cache.remove(&soonest);
+            }
+        }
+        if self.cache_capacity > 0 {
+            cache.insert(host, CacheEntry {
+                recipients: recipients.clone(),
+                expires: now + Duration::from_secs(ttl.into()).min(self.max_ttl),
+            });
+        }
+
+        Ok(recipients)
+    }
+
+    /// Verify that `recipient` is attested by the domain serving `resource`
+    ///
+    /// `resource` must be an absolute URL.
+    pub async fn verify_recipient(&self, resource: &str, recipient: Address) -> Result<()> {
+        let host = resource_host(resource).ok_or_else(|| {
+            X402Error::DnsLookup(format!("resource is not an absolute URL: {}", resource))
+        })?;
+
+        if self.attested_recipients(host).await?.contains(&recipient) {
+            Ok(())
+        } else {
+            Err(X402Error::RecipientNotAttested(format!(
+                "{} is not listed in {}.{}",
+                recipient, X402_TXT_LABEL, host
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::task::{Context, Poll, Waker};
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = std::pin::pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+
+    const RECIPIENT: &str = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+
+    struct StaticDoh {
+        requests: AtomicU32,
+    }
+
+    impl DohHttpClient for StaticDoh {
+        async fn get(&self, url: &str) -> Result<Vec<u8>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            assert!(url.contains("?name=_x402.") && url.ends_with(".example.com&type=TXT"));
+            Ok(format!(
+                r#"{{"Status":0,"Answer":[{{"name":"_x402.api.example.com","type":16,"TTL":300,"data":"\"x402-recipient={}\""}}]}}"#,
+                RECIPIENT
+            ).into_bytes())
+        }
+    }
+
+    #[test]
+    fn test_verify_recipient_via_doh() {
+        let verifier = RecipientVerifier::new(DohResolver::new(StaticDoh {
+            requests: AtomicU32::new(0),
+        }));
+        let recipient = Address::from_str(RECIPIENT).unwrap();
+
+        block_on(verifier.verify_recipient("https://api.example.com:443/data", recipient)).unwrap();
+        block_on(verifier.verify_recipient("https://API.example.com/other", recipient)).unwrap();
+        assert_eq!(verifier.resolver.client.requests.load(Ordering::SeqCst), 1);
+
+        let result = block_on(verifier.verify_recipient("https://api.example.com/", Address::ZERO));
+        assert!(matches!(result, Err(X402Error::RecipientNotAttested(_))));
+        assert!(block_on(verifier.verify_recipient("/data", recipient)).is_err());
+    }
+
+    #[test]
+    fn test_lookups_are_validated_and_encoded() {
+        struct Recording(Mutex<Vec<String>>);
+
+        impl DohHttpClient for Recording {
+            async fn get(&self, url: &str) -> Result<Vec<u8>> {
+                self.0.lock().unwrap().push(url.to_string());
+                Ok(br#"{"Status":0,"Answer":[]}"#.to_vec())
+            }
+        }
+
+        let resolver = DohResolver::new(Recording(Mutex::new(Vec::new())));
+        for name in ["a.com&type=A", "a b.com", "a..com", "-a.com", "", &"a".repeat(64)] {
+            assert!(matches!(block_on(resolver.lookup_txt(name)), Err(X402Error::DnsLookup(_))), "{:?}", name);
+        }
+        assert!(check_dns_name("_x402.api.example.com.").is_ok());
+        assert_eq!(percent_encode("a&b=c d"), "a%26b%3Dc%20d");
+        assert!(block_on(resolver.lookup_txt("_x402.xn--bcher-kva.example")).unwrap().is_empty());
+        assert_eq!(
+            *resolver.client.0.lock().unwrap(),
+            [format!("{}?name=_x402.xn--bcher-kva.example&type=TXT", DEFAULT_DOH_ENDPOINT)]
+        );
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let verifier = RecipientVerifier::new(DohResolver::new(StaticDoh {
+            requests: AtomicU32::new(0),
+        }))
+        .with_cache_capacity(1);
+        let recipient = Address::from_str(RECIPIENT).unwrap();
+
+        block_on(verifier.verify_recipient("https://api.example.com/", recipient)).unwrap();
+        block_on(verifier.verify_recipient("https://other.example.com/", recipient)).unwrap();
+        assert_eq!(verifier.cache.lock().unwrap().len(), 1);
+        // The second host evicted the first, which is looked up again
+        block_on(verifier.verify_recipient("https://api.example.com/", recipient)).unwrap();
+        block_on(verifier.verify_recipient("https://api.example.com/", recipient)).unwrap();
+        assert_eq!(verifier.resolver.client.requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_resource_host() {
+        assert_eq!(resource_host("https://user@api.example.com:8443/x?y"), Some("api.example.com"));
+        assert_eq!(resource_host("/api/data"), None);
+    }
+}
@@ -0,0 +1,417 @@
+//! ERC-4337 smart account payers
+//!
+//! A payer may be a smart contract account rather than an EOA. Such an
+//! account signs in its own way, so its payment signature is checked by
+//! calling the account's ERC-1271 `isValidSignature` through an
+//! [`Erc1271Validator`] rather than by ECDSA recovery
+//! ([`verify_smart_account_payment`]).
+//!
+//! Settlement moves the funds with an EntryPoint v0.7 [`UserOperation`]
+//! built from the payment ([`UserOperation::for_payment`]), optionally
+//! sponsored by a [`Paymaster`]. The account signs
+//! [`UserOperation::hash`]; submitting the operation to a bundler is left to
+//! the caller.
+
+use crate::{PaymentPayload, Result, X402Error};
+use alloy_primitives::{address, keccak256, Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "verify")]
+use crate::verify::{check_terms, observe_verification};
+#[cfg(feature = "verify")]
+use crate::{recover_signer, PaymentRequirements, SignedPayment, VerifyOptions};
+
+/// The canonical EntryPoint v0.7 deployment
+pub const ENTRY_POINT_V07: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+/// What `isValidSignature` returns for a valid signature
+pub const ERC1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// `execute(address,uint256,bytes)`
+const EXECUTE_SELECTOR: [u8; 4] = [0xb6, 0x1d, 0x27, 0xf6];
+
+/// `transfer(address,uint256)`
+const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// Gas limits and fees of a user operation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UserOperationGas {
+    pub call_gas_limit: u128,
+    pub verification_gas_limit: u128,
+    pub pre_verification_gas: u128,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+/// A paymaster paying a user operation's gas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paymaster {
+    pub address: Address,
+    pub verification_gas_limit: u128,
+    pub post_op_gas_limit: u128,
+    /// Paymaster-specific data, e.g. its signature over the operation
+    pub data: Bytes,
+}
+
+/// An EntryPoint v0.7 user operation, in the form bundlers accept over
+/// JSON-RPC (`eth_sendUserOperation`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperation {
+    pub sender: Address,
+    pub nonce: U256,
+    /// Deploys the account on first use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Address>,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    pub factory_data: Bytes,
+    pub call_data: Bytes,
+    #[serde(with = "quantity")]
+    pub call_gas_limit: u128,
+    #[serde(with = "quantity")]
+    pub verification_gas_limit: u128,
+    #[serde(with = "quantity")]
+    pub pre_verification_gas: u128,
+    #[serde(with = "quantity")]
+    pub max_fee_per_gas: u128,
+    #[serde(with = "quantity")]
+    pub max_priority_fee_per_gas: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    #[serde(default, with = "quantity", skip_serializing_if = "is_zero")]
+    pub paymaster_verification_gas_limit: u128,
+    #[serde(default, with = "quantity", skip_serializing_if = "is_zero")]
+    pub paymaster_post_op_gas_limit: u128,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    pub paymaster_data: Bytes,
+    pub signature: Bytes,
+}
+
+impl UserOperation {
+    /// An unsigned operation settling `payment` from the payer's account,
+    /// using the account's account nonce `nonce`
+    ///
+    /// The call goes through `execute(address,uint256,bytes)`, as
+    /// implemented by the reference `SimpleAccount` and most accounts
+    /// derived from it: an ERC-20 `transfer` of `payment.token`, or a plain
+    /// value transfer when the payment has no token. Split payments need
+    /// a batch call and aren't supported.
+    pub fn for_payment(payment: &PaymentPayload, nonce: U256, gas: UserOperationGas) -> Result<Self> {
+        if !payment.splits.is_empty() {
+            return Err(X402Error::InvalidSplit("split payments can't be settled by a user operation".to_string()));
+        }
+        let call_data = match payment.token {
+            Some(token) => {
+                let mut transfer = TRANSFER_SELECTOR.to_vec();
+                transfer.extend_from_slice(&address_word(payment.recipient));
+                transfer.extend_from_slice(&payment.amount.to_be_bytes::<32>());
+                execute_call_data(token, U256::ZERO, &transfer)
+            }
+            None => execute_call_data(payment.recipient, payment.amount, &[]),
+        };
+        Ok(Self {
+            sender: payment.payer,
+            nonce,
+            factory: None,
+            factory_data: Bytes::new(),
+            call_data,
+            call_gas_limit: gas.call_gas_limit,
+            verification_gas_limit: gas.verification_gas_limit,
+            pre_verification_gas: gas.pre_verification_gas,
+            max_fee_per_gas: gas.max_fee_per_gas,
+            max_priority_fee_per_gas: gas.max_priority_fee_per_gas,
+            paymaster: None,
+            paymaster_verification_gas_limit: 0,
+            paymaster_post_op_gas_limit: 0,
+            paymaster_data: Bytes::new(),
+            signature: Bytes::new(),
+        })
+    }
+
+    /// Route gas through `paymaster`
+    pub fn with_paymaster(mut self, paymaster: Paymaster) -> Self {
+        self.paymaster = Some(paymaster.address);
+        self.paymaster_verification_gas_limit = paymaster.verification_gas_limit;
+        self.paymaster_post_op_gas_limit = paymaster.post_op_gas_limit;
+        self.paymaster_data = paymaster.data;
+        self
+    }
+
+    /// `initCode`: factory address followed by its data, or empty
+    pub fn init_code(&self) -> Bytes {
+        match self.factory {
+            Some(factory) => [factory.as_slice(), &self.factory_data].concat().into(),
+            None => Bytes::new(),
+        }
+    }
+
+    /// `paymasterAndData`: address, verification and post-op gas limits,
+    /// then the paymaster's data; empty without a paymaster
+    pub fn paymaster_and_data(&self) -> Bytes {
+        match self.paymaster {
+            Some(paymaster) => [
+                paymaster.as_slice(),
+                &self.paymaster_verification_gas_limit.to_be_bytes(),
+                &self.paymaster_post_op_gas_limit.to_be_bytes(),
+                &self.paymaster_data,
+            ]
+            .concat()
+            .into(),
+            None => Bytes::new(),
+        }
+    }
+
+    /// The hash the account signs: `EntryPoint.getUserOpHash`
+    pub fn hash(&self, entry_point: Address, chain_id: u64) -> B256 {
+        let packed = [
+            address_word(self.sender),
+            self.nonce.to_be_bytes::<32>(),
+            keccak256(self.init_code()).0,
+            keccak256(&self.call_data).0,
+            pack_u128s(self.verification_gas_limit, self.call_gas_limit),
+            U256::from(self.pre_verification_gas).to_be_bytes::<32>(),
+            pack_u128s(self.max_priority_fee_per_gas, self.max_fee_per_gas),
+            keccak256(self.paymaster_and_data()).0,
+        ]
+        .concat();
+        keccak256(
+            [keccak256(packed).0, address_word(entry_point), U256::from(chain_id).to_be_bytes::<32>()].concat(),
+        )
+    }
+}
+
+fn address_word(address: Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address.as_slice());
+    word
+}
+
+fn pack_u128s(high: u128, low: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[..16].copy_from_slice(&high.to_be_bytes());
+    word[16..].copy_from_slice(&low.to_be_bytes());
+    word
+}
+
+/// ABI-encoded `bytes` tail: length word, then the data zero-padded to a
+/// whole number of words
+fn bytes_tail(data: &[u8]) -> Vec<u8> {
+    let mut tail = U256::from(data.len()).to_be_bytes::<32>().to_vec();
+    tail.extend_from_slice(data);
+    tail.resize(32 + data.len().div_ceil(32) * 32, 0);
+    tail
+}
+
+fn execute_call_data(target: Address, value: U256, data: &[u8]) -> Bytes {
+    let mut call = EXECUTE_SELECTOR.to_vec();
+    call.extend_from_slice(&address_word(target));
+    call.extend_from_slice(&value.to_be_bytes::<32>());
+    call.extend_from_slice(&U256::from(96).to_be_bytes::<32>());
+    call.extend_from_slice(&bytes_tail(data));
+    call.into()
+}
+
+fn is_zero(value: &u128) -> bool {
+    *value == 0
+}
+
+fn is_empty(bytes: &Bytes) -> bool {
+    bytes.is_empty()
+}
+
+/// Calls ERC-1271 `isValidSignature` on smart contract accounts
+pub trait Erc1271Validator: Send + Sync {
+    /// Whether `account` on `chain_id` accepts `signature` over `hash`
+    ///
+    /// Implementations typically `eth_call` the account with
+    /// [`is_valid_signature_call_data`] and check the result with
+    /// [`is_erc1271_magic_value`].
+    fn is_valid_signature(&self, chain_id: u64, account: Address, hash: B256, signature: &[u8]) -> Result<bool>;
+}
+
+/// Call data for `isValidSignature(bytes32,bytes)`
+pub fn is_valid_signature_call_data(hash: B256, signature: &[u8]) -> Bytes {
+    let mut call = ERC1271_MAGIC_VALUE.to_vec();
+    call.extend_from_slice(hash.as_slice());
+    call.extend_from_slice(&U256::from(64).to_be_bytes::<32>());
+    call.extend_from_slice(&bytes_tail(signature));
+    call.into()
+}
+
+/// Whether `isValidSignature` returned the magic value
+pub fn is_erc1271_magic_value(return_data: &[u8]) -> bool {
+    return_data.len() == 32 && return_data[..4] == ERC1271_MAGIC_VALUE && return_data[4..].iter().all(|&b| b == 0)
+}
+
+/// Verify a payment whose payer may be a smart contract account
+///
+/// Makes every check [`verify_payment`](crate::verify_payment) makes, but
+/// accepts the signature if either it recovers to the payer (an EOA) or
+/// the payer's account accepts it through `validator`.
+#[cfg(feature = "verify")]
+pub fn verify_smart_account_payment<V: Erc1271Validator + ?Sized>(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    validator: &V,
+) -> Result<Address> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    verify_smart_account_payment_at(payment, requirements, options, validator, now)
+}
+
+/// [`verify_smart_account_payment`] as of the unix time `now`
+#[cfg(feature = "verify")]
+pub fn verify_smart_account_payment_at<V: Erc1271Validator + ?Sized>(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    validator: &V,
+    now: u64,
+) -> Result<Address> {
+    observe_verification(payment, requirements, || {
+        check_terms(payment, requirements, options, now)?;
+        let payer = payment.payment.payer;
+        if recover_signer(payment).ok() == Some(payer) {
+            return Ok(payer);
+        }
+        let hash = B256::from(payment.payment.message_hash());
+        if !validator.is_valid_signature(payment.payment.chain_id, payer, hash, &payment.signature)? {
+            return Err(X402Error::InvalidSignature("payer account rejected the signature".to_string()));
+        }
+        Ok(payer)
+    })
+}
+
+/// Hex quantities (`"0x1a"`), as used by Ethereum JSON-RPC
+mod quantity {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let digits = s.strip_prefix("0x").ok_or_else(|| D::Error::custom("quantity must start with 0x"))?;
+        u128::from_str_radix(digits, 16).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Network, Scheme};
+
+    fn payment(token: Option<Address>) -> PaymentPayload {
+        PaymentPayload {
+            amount: U256::from(10_000),
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0xaa),
+            chain_id: Network::Base.chain_id(),
+            token,
+            resource: "/api".to_string(),
+            nonce: 1 << 40,
+            expires_at: 2_000,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(keccak256("execute(address,uint256,bytes)")[..4], EXECUTE_SELECTOR);
+        assert_eq!(keccak256("transfer(address,uint256)")[..4], TRANSFER_SELECTOR);
+        assert_eq!(keccak256("isValidSignature(bytes32,bytes)")[..4], ERC1271_MAGIC_VALUE);
+    }
+
+    #[test]
+    fn test_user_operation_for_payment() {
+        let token = Address::repeat_byte(0x22);
+        let gas = UserOperationGas { call_gas_limit: 100_000, max_fee_per_gas: 1_000_000_000, ..Default::default() };
+        let op = UserOperation::for_payment(&payment(Some(token)), U256::from(7), gas).unwrap();
+        assert_eq!(op.sender, Address::repeat_byte(0xaa));
+        // selector, target, value, offset, length, 68-byte transfer padded to 96
+        assert_eq!(op.call_data.len(), 4 + 4 * 32 + 96);
+        assert_eq!(op.call_data[16..36], token[..]);
+        assert_eq!(op.call_data[4 + 4 * 32..][..4], TRANSFER_SELECTOR);
+
+        let native = UserOperation::for_payment(&payment(None), U256::from(7), gas).unwrap();
+        assert_eq!(native.call_data.len(), 4 + 4 * 32);
+        assert_eq!(U256::from_be_slice(&native.call_data[36..68]), U256::from(10_000));
+
+        let hash = op.hash(ENTRY_POINT_V07, 8453);
+        assert_ne!(hash, op.hash(ENTRY_POINT_V07, 84532));
+        let sponsored = op.clone().with_paymaster(Paymaster {
+            address: Address::repeat_byte(0x33),
+            verification_gas_limit: 50_000,
+            post_op_gas_limit: 20_000,
+            data: Bytes::from_static(b"sig"),
+        });
+        assert_eq!(sponsored.paymaster_and_data().len(), 20 + 16 + 16 + 3);
+        assert_ne!(sponsored.hash(ENTRY_POINT_V07, 8453), hash);
+
+        let json = serde_json::to_value(&sponsored).unwrap();
+        assert_eq!(json["callGasLimit"], "0x186a0");
+        assert_eq!(json["paymasterPostOpGasLimit"], "0x4e20");
+        assert!(serde_json::to_value(&op).unwrap().get("paymaster").is_none());
+        let back: UserOperation = serde_json::from_value(json).unwrap();
+        assert_eq!(back, sponsored);
+
+        let mut split = payment(Some(token));
+        split.splits = vec![crate::Split { recipient: Address::repeat_byte(0x11), share: U256::from(10_000) }];
+        assert!(matches!(UserOperation::for_payment(&split, U256::ZERO, gas), Err(X402Error::InvalidSplit(_))));
+    }
+
+    #[test]
+    fn test_erc1271_call_data() {
+        let call = is_valid_signature_call_data(B256::repeat_byte(0x01), &[0xff; 65]);
+        assert_eq!(call.len(), 4 + 32 + 32 + 32 + 96);
+        let mut magic = [0u8; 32];
+        magic[..4].copy_from_slice(&ERC1271_MAGIC_VALUE);
+        assert!(is_erc1271_magic_value(&magic));
+        assert!(!is_erc1271_magic_value(&[0u8; 32]));
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_smart_account_payment() {
+        use crate::testing::TestSigner;
+
+        struct Accounts(Address);
+        impl Erc1271Validator for Accounts {
+            fn is_valid_signature(&self, _: u64, account: Address, _: B256, signature: &[u8]) -> Result<bool> {
+                Ok(account == self.0 && signature == b"approved")
+            }
+        }
+
+        let now = 1_000;
+        let requirements = PaymentRequirements {
+            token: None,
+            ..PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api").unwrap()
+        };
+        let account = Address::repeat_byte(0xaa);
+        let validator = Accounts(account);
+        let options = VerifyOptions::default();
+        let smart = SignedPayment { payment: payment(None), signature: b"approved".to_vec(), signatures: Vec::new() };
+        assert_eq!(verify_smart_account_payment_at(&smart, &requirements, &options, &validator, now).unwrap(), account);
+
+        let rejected = SignedPayment { signature: b"forged".to_vec(), ..smart.clone() };
+        assert!(matches!(
+            verify_smart_account_payment_at(&rejected, &requirements, &options, &validator, now),
+            Err(X402Error::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            verify_smart_account_payment_at(&smart, &requirements, &options, &validator, 3_000),
+            Err(X402Error::PaymentExpired)
+        ));
+
+        let signer = TestSigner::default();
+        let eoa = signer.pay(&requirements, now).unwrap();
+        assert_eq!(verify_smart_account_payment_at(&eoa, &requirements, &options, &validator, now).unwrap(), signer.address());
+    }
+}
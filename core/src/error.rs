@@ -1,15 +1,40 @@
 //! Error types for x402-core
+//!
+//! [`X402Error`] is `#[non_exhaustive]`: match on [`X402Error::category`],
+//! [`X402Error::is_retryable`] or [`X402Error::status_code`] rather than on
+//! every variant, so new variants don't break downstream code. Errors
+//! caused by a lower-level library keep that error as their
+//! [`source`](std::error::Error::source).
 
 use thiserror::Error;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum X402Error {
     #[error("Invalid x402 header format: {0}")]
     InvalidHeader(String),
 
+    #[error("Invalid x402 header format: base64 decode failed")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("Invalid x402 header format: JSON parse failed")]
+    Json(#[source] serde_json::Error),
+
+    #[error("Invalid x402 header format: CBOR parse failed")]
+    Cbor(#[source] BoxError),
+
+    #[error("Unsupported header version: {0}")]
+    UnsupportedVersion(String),
+
     #[error("Invalid signature: {0}")]
     InvalidSignature(String),
 
+    #[cfg(feature = "verify")]
+    #[error("Invalid signature: malformed ECDSA signature")]
+    Ecdsa(#[from] k256::ecdsa::Error),
+
     #[error("Invalid address: {0}")]
     InvalidAddress(String),
 
@@ -19,11 +44,298 @@ pub enum X402Error {
     #[error("Payment expired")]
     PaymentExpired,
 
+    #[error("Payment valid for {requested_seconds}s, longer than the allowed {max_seconds}s")]
+    ValidityTooLong { max_seconds: u64, requested_seconds: u64 },
+
     #[error("Insufficient amount: required {required}, got {provided}")]
-    InsufficientAmount { required: u64, provided: u64 },
+    InsufficientAmount { required: alloy_primitives::U256, provided: alloy_primitives::U256 },
 
     #[error("Unsupported network: {0}")]
     UnsupportedNetwork(String),
+
+    #[error("Unsupported payment scheme: {0}")]
+    UnsupportedScheme(String),
+
+    #[error("Charge rejected: {0}")]
+    ChargeRejected(String),
+
+    #[error("Webhook delivery failed: {0}")]
+    WebhookDelivery(String),
+
+    #[error("DNS lookup failed: {0}")]
+    DnsLookup(String),
+
+    #[error("Recipient not attested: {0}")]
+    RecipientNotAttested(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Duplicate payment: {0}")]
+    DuplicatePayment(String),
+
+    #[error("Invalid quote: {0}")]
+    InvalidQuote(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Payer blacklisted: {0}")]
+    PayerBlacklisted(String),
+
+    #[error("Verifier overloaded: {0}")]
+    VerifierOverloaded(String),
+
+    #[error("Verification panicked: {0}")]
+    VerifierPanicked(String),
+
+    #[error("Stream {stream_id} needs payment {sequence} before more content")]
+    StreamCreditExhausted { stream_id: String, sequence: u64 },
+
+    #[error("Invalid stream payment: {0}")]
+    InvalidStreamPayment(String),
+
+    #[error("Invalid channel voucher: {0}")]
+    InvalidVoucher(String),
+
+    #[error("Invalid payment preimage: {0}")]
+    InvalidPreimage(String),
+
+    #[error("Invalid payment proof: {0}")]
+    InvalidProof(String),
+
+    #[error("Invalid refund: {0}")]
+    InvalidRefund(String),
+
+    #[error("Invalid subscription charge: {0}")]
+    InvalidSubscription(String),
+
+    #[error("Invalid payment split: {0}")]
+    InvalidSplit(String),
+
+    #[error("Invalid lottery ticket: {0}")]
+    InvalidTicket(String),
+
+    #[error("Invalid offline voucher: {0}")]
+    InvalidOfflineVoucher(String),
+
+    #[error("Invalid credit: {0}")]
+    InvalidCredit(String),
+
+    #[error("Insufficient credit: {0}")]
+    InsufficientCredit(String),
+
+    #[error("Exchange rate is {age_seconds}s old, older than the allowed {max_seconds}s")]
+    StaleExchangeRate { age_seconds: u64, max_seconds: u64 },
+
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+
+    #[error("Amount {amount} overflows with {decimals} decimals")]
+    AmountOverflow { amount: String, decimals: u8 },
+
+    #[error("Invalid payment data: {}", crate::validate::join(.0))]
+    Invalid(Vec<crate::Violation>),
+
+    #[error("{what} exceeds limit: {actual} > {limit}")]
+    LimitExceeded { what: &'static str, limit: usize, actual: usize },
+
+    #[error("Facilitator unavailable: {0}")]
+    FacilitatorUnavailable(String),
+
+    #[error("Facilitator answered {status} ({kind}): {message}")]
+    FacilitatorError { status: u16, kind: String, message: String },
+
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Rate limited; retry in {retry_after_seconds}s")]
+    RateLimited { retry_after_seconds: u64 },
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+}
+
+/// Who is at fault for an error, and whether retrying can help
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The request or payment is invalid; retrying it unchanged won't help
+    Client,
+    /// Misconfiguration or a bug on the serving side
+    Server,
+    /// A temporary condition (overload, network, storage); retry later
+    Transient,
+}
+
+impl X402Error {
+    pub fn category(&self) -> ErrorCategory {
+        use X402Error::*;
+        match self {
+            InvalidHeader(_) | Base64(_) | Json(_) | Cbor(_) | UnsupportedVersion(_) | InvalidSignature(_)
+            | InvalidAddress(_) | PaymentExpired | ValidityTooLong { .. } | InsufficientAmount { .. }
+            | UnsupportedNetwork(_) | UnsupportedScheme(_) | RecipientNotAttested(_) | DuplicatePayment(_)
+            | InvalidQuote(_) | PayerBlacklisted(_) | StreamCreditExhausted { .. } | InvalidStreamPayment(_)
+            | InvalidVoucher(_) | InvalidPreimage(_) | InvalidProof(_) | InvalidRefund(_) | InvalidSubscription(_)
+            | InvalidSplit(_) | InvalidTicket(_) | InvalidOfflineVoucher(_) | InvalidCredit(_)
+            | InsufficientCredit(_) | StaleExchangeRate { .. } | InvalidAmount(_)
+            | AmountOverflow { .. } | Invalid(_) | LimitExceeded { .. } | InvalidApiKey(_) | Forbidden(_)
+            | QuotaExceeded(_) => ErrorCategory::Client,
+            #[cfg(feature = "verify")]
+            Ecdsa(_) => ErrorCategory::Client,
+            EncodingError(_) | InvalidConfig(_) | ChargeRejected(_) | VerifierPanicked(_) => ErrorCategory::Server,
+            WebhookDelivery(_) | DnsLookup(_) | Storage(_) | VerifierOverloaded(_) | FacilitatorUnavailable(_)
+            | RateLimited { .. } => ErrorCategory::Transient,
+            // Only the facilitator's verdicts on the payment are the client's doing
+            FacilitatorError { status: 400 | 402, .. } => ErrorCategory::Client,
+            FacilitatorError { status: 429 | 502..=504, .. } => ErrorCategory::Transient,
+            FacilitatorError { .. } => ErrorCategory::Server,
+        }
+    }
+
+    /// Stable snake_case name of this error's variant, for logs and metric
+    /// labels
+    pub fn kind(&self) -> &'static str {
+        use X402Error::*;
+        match self {
+            InvalidHeader(_) => "invalid_header",
+            Base64(_) => "base64",
+            Json(_) => "json",
+            Cbor(_) => "cbor",
+            UnsupportedVersion(_) => "unsupported_version",
+            InvalidSignature(_) => "invalid_signature",
+            #[cfg(feature = "verify")]
+            Ecdsa(_) => "ecdsa",
+            InvalidAddress(_) => "invalid_address",
+            EncodingError(_) => "encoding_error",
+            PaymentExpired => "payment_expired",
+            ValidityTooLong { .. } => "validity_too_long",
+            InsufficientAmount { .. } => "insufficient_amount",
+            UnsupportedNetwork(_) => "unsupported_network",
+            UnsupportedScheme(_) => "unsupported_scheme",
+            ChargeRejected(_) => "charge_rejected",
+            WebhookDelivery(_) => "webhook_delivery",
+            DnsLookup(_) => "dns_lookup",
+            RecipientNotAttested(_) => "recipient_not_attested",
+            Storage(_) => "storage",
+            DuplicatePayment(_) => "duplicate_payment",
+            InvalidQuote(_) => "invalid_quote",
+            InvalidConfig(_) => "invalid_config",
+            PayerBlacklisted(_) => "payer_blacklisted",
+            VerifierOverloaded(_) => "verifier_overloaded",
+            VerifierPanicked(_) => "verifier_panicked",
+            StreamCreditExhausted { .. } => "stream_credit_exhausted",
+            InvalidStreamPayment(_) => "invalid_stream_payment",
+            InvalidVoucher(_) => "invalid_voucher",
+            InvalidPreimage(_) => "invalid_preimage",
+            InvalidProof(_) => "invalid_proof",
+            InvalidRefund(_) => "invalid_refund",
+            InvalidSubscription(_) => "invalid_subscription",
+            InvalidSplit(_) => "invalid_split",
+            InvalidTicket(_) => "invalid_ticket",
+            InvalidOfflineVoucher(_) => "invalid_offline_voucher",
+            InvalidCredit(_) => "invalid_credit",
+            InsufficientCredit(_) => "insufficient_credit",
+            StaleExchangeRate { .. } => "stale_exchange_rate",
+            InvalidAmount(_) => "invalid_amount",
+            AmountOverflow { .. } => "amount_overflow",
+            Invalid(_) => "invalid",
+            LimitExceeded { .. } => "limit_exceeded",
+            FacilitatorUnavailable(_) => "facilitator_unavailable",
+            FacilitatorError { .. } => "facilitator_error",
+            InvalidApiKey(_) => "invalid_api_key",
+            Forbidden(_) => "forbidden",
+            RateLimited { .. } => "rate_limited",
+            QuotaExceeded(_) => "quota_exceeded",
+        }
+    }
+
+    /// Whether the same operation may succeed if retried later
+    pub fn is_retryable(&self) -> bool {
+        self.category() == ErrorCategory::Transient
+    }
+
+    /// HTTP status a server should answer with for this error
+    ///
+    /// Payment problems map to 402 so the client receives fresh
+    /// requirements; malformed input to 400 (431 for oversized headers).
+    pub fn status_code(&self) -> u16 {
+        use X402Error::*;
+        match self {
+            InvalidHeader(_) | Base64(_) | Json(_) | Cbor(_) | UnsupportedVersion(_) | InvalidAddress(_)
+            | InvalidRefund(_) | InvalidAmount(_) | AmountOverflow { .. } | Invalid(_) => 400,
+            LimitExceeded { what: "header", .. } => 431,
+            LimitExceeded { .. } => 400,
+            InvalidSignature(_) | PaymentExpired | ValidityTooLong { .. } | InsufficientAmount { .. }
+            | UnsupportedNetwork(_) | UnsupportedScheme(_) | RecipientNotAttested(_) | DuplicatePayment(_)
+            | InvalidQuote(_) | StreamCreditExhausted { .. } | InvalidStreamPayment(_) | InvalidVoucher(_)
+            | InvalidPreimage(_) | InvalidProof(_) | InvalidSubscription(_) | InvalidSplit(_) | InvalidTicket(_)
+            | InvalidOfflineVoucher(_) | InvalidCredit(_) | InsufficientCredit(_) | StaleExchangeRate { .. } => 402,
+            #[cfg(feature = "verify")]
+            Ecdsa(_) => 402,
+            InvalidApiKey(_) => 401,
+            PayerBlacklisted(_) | Forbidden(_) => 403,
+            RateLimited { .. } | QuotaExceeded(_) => 429,
+            WebhookDelivery(_) => 502,
+            DnsLookup(_) | Storage(_) | VerifierOverloaded(_) | FacilitatorUnavailable(_) => 503,
+            EncodingError(_) | InvalidConfig(_) | ChargeRejected(_) | VerifierPanicked(_) => 500,
+            // The facilitator's verdict on the payment stands; refusing this
+            // server (auth, limits) or failing is a gateway problem
+            FacilitatorError { status: status @ (400 | 402), .. } => *status,
+            FacilitatorError { status: 429 | 503, .. } => 503,
+            FacilitatorError { .. } => 502,
+        }
+    }
+
+    /// This error's message followed by the messages of its sources
+    pub fn chain_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        message
+    }
 }
 
 pub type Result<T> = std::result::Result<T, X402Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_source_chaining_and_classification() {
+        let err = crate::decode_payment_header("not base64!").unwrap_err();
+        assert!(matches!(err, X402Error::Base64(_)));
+        assert!(err.source().is_some());
+        assert!(err.chain_message().starts_with("Invalid x402 header format: base64 decode failed: "));
+        assert_eq!(err.category(), ErrorCategory::Client);
+        assert_eq!(err.status_code(), 400);
+
+        let err = X402Error::VerifierOverloaded("queue full".to_string());
+        assert!(err.is_retryable());
+        assert_eq!(err.status_code(), 503);
+        assert_eq!(X402Error::PaymentExpired.status_code(), 402);
+
+        let facilitator = |status| X402Error::FacilitatorError { status, kind: String::new(), message: String::new() };
+        for (status, category, answered) in [
+            (402, ErrorCategory::Client, 402),
+            (400, ErrorCategory::Client, 400),
+            (401, ErrorCategory::Server, 502),
+            (403, ErrorCategory::Server, 502),
+            (404, ErrorCategory::Server, 502),
+            (429, ErrorCategory::Transient, 503),
+            (500, ErrorCategory::Server, 502),
+            (503, ErrorCategory::Transient, 503),
+        ] {
+            assert_eq!((facilitator(status).category(), facilitator(status).status_code()), (category, answered));
+        }
+    }
+}
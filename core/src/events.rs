@@ -0,0 +1,440 @@
+//! Payment event notifications (webhooks)
+//!
+//! Servers and facilitators report payment lifecycle events through a
+//! [`PaymentEvents`] sink: pass one to `FacilitatorServer::with_events`,
+//! `PaymentInterceptor::with_events`, `DemoServer::with_events` or, for
+//! GraphQL, the schema data. [`WebhookSink`] delivers them as signed JSON
+//! POSTs to configured endpoints on a background thread, retrying failed
+//! deliveries, so emitting never waits on an endpoint.
+//!
+//! The HTTP request itself is performed by a [`WebhookTransport`], so this
+//! crate stays free of any particular HTTP client.
+
+use crate::{PaymentPayload, Result, SettlementReceipt, X402Error};
+use alloy_primitives::{hex, Address, U256};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header carrying the webhook signature (`t=<unix>,v1=<hex hmac>`)
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Payment-Webhook-Signature";
+
+/// A payment lifecycle event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentEvent {
+    /// A payment passed verification
+    #[serde(rename_all = "camelCase")]
+    PaymentVerified {
+        payer: Address,
+        recipient: Address,
+        #[serde(with = "crate::serde_amount")]
+        amount: U256,
+        chain_id: u64,
+        resource: String,
+        nonce: u64,
+    },
+    /// A payment was settled on-chain
+    #[serde(rename_all = "camelCase")]
+    SettlementConfirmed {
+        payer: Address,
+        chain_id: u64,
+        nonce: u64,
+        transaction_hash: String,
+    },
+    /// Settlement of a verified payment failed
+    #[serde(rename_all = "camelCase")]
+    SettlementFailed {
+        payer: Address,
+        chain_id: u64,
+        nonce: u64,
+        reason: String,
+    },
+}
+
+impl PaymentEvent {
+    pub fn verified(payment: &PaymentPayload) -> Self {
+        PaymentEvent::PaymentVerified {
+            payer: payment.payer,
+            recipient: payment.recipient,
+            amount: payment.amount,
+            chain_id: payment.chain_id,
+            resource: payment.resource.clone(),
+            nonce: payment.nonce,
+        }
+    }
+
+    /// [`SettlementConfirmed`](Self::SettlementConfirmed) or
+    /// [`SettlementFailed`](Self::SettlementFailed), by the outcome of settling `payment`
+    pub fn settled(payment: &PaymentPayload, outcome: &Result<SettlementReceipt>) -> Self {
+        match outcome {
+            Ok(receipt) => PaymentEvent::SettlementConfirmed {
+                payer: receipt.payer,
+                chain_id: receipt.chain_id,
+                nonce: receipt.nonce,
+                transaction_hash: receipt.transaction_hash.to_string(),
+            },
+            Err(e) => PaymentEvent::SettlementFailed {
+                payer: payment.payer,
+                chain_id: payment.chain_id,
+                nonce: payment.nonce,
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Receives payment lifecycle events
+pub trait PaymentEvents: Send + Sync {
+    /// Handle an event
+    fn emit(&self, event: &PaymentEvent) -> Result<()>;
+}
+
+/// Emit `event` to `events`, if any, logging failures: a lost notification
+/// must not fail the payment it reports on
+#[cfg(any(feature = "facilitator-http", feature = "grpc", feature = "demo-server", feature = "graphql"))]
+pub(crate) fn report(events: Option<&Arc<dyn PaymentEvents>>, event: PaymentEvent) {
+    if let Some(events) = events {
+        if let Err(e) = events.emit(&event) {
+            eprintln!("x402 events: {}", e);
+        }
+    }
+}
+
+/// Performs the HTTP POST for a webhook delivery
+pub trait WebhookTransport: Send + Sync {
+    /// POST `body` to `url` with the given headers, returning the HTTP status
+    fn post(&self, url: &str, headers: &[(&str, String)], body: &[u8]) -> Result<u16>;
+}
+
+/// A configured webhook endpoint
+#[derive(Debug, Clone)]
+pub struct WebhookEndpoint {
+    /// URL to POST events to
+    pub url: String,
+    /// Shared secret used for HMAC-SHA256 signing
+    pub secret: Vec<u8>,
+}
+
+/// Retry behaviour for failed deliveries
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts per endpoint, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after each failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Events that may wait for delivery before new ones are refused
+pub const DEFAULT_WEBHOOK_QUEUE_CAPACITY: usize = 1024;
+
+/// Delivers events as HMAC-signed JSON webhooks
+///
+/// Deliveries, retries included, run on a background thread; [`emit`]
+/// only queues the event, failing if the queue is full. Deliveries that
+/// still fail after the last retry are logged and counted in
+/// [`failed_deliveries`](Self::failed_deliveries).
+///
+/// [`emit`]: PaymentEvents::emit
+pub struct WebhookSink<T: WebhookTransport + 'static> {
+    delivery: Arc<Delivery<T>>,
+    queue: Option<SyncSender<Vec<u8>>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct Delivery<T> {
+    transport: T,
+    endpoints: Vec<WebhookEndpoint>,
+    retry: Mutex<RetryPolicy>,
+    failures: AtomicU64,
+}
+
+impl<T: WebhookTransport + 'static> WebhookSink<T> {
+    /// Start the delivery thread, queueing up to [`DEFAULT_WEBHOOK_QUEUE_CAPACITY`] events
+    pub fn new(transport: T, endpoints: Vec<WebhookEndpoint>) -> Self {
+        Self::with_capacity(transport, endpoints, DEFAULT_WEBHOOK_QUEUE_CAPACITY)
+    }
+
+    /// Start the delivery thread, queueing up to `capacity` events
+    pub fn with_capacity(transport: T, endpoints: Vec<WebhookEndpoint>, capacity: usize) -> Self {
+        let delivery = Arc::new(Delivery {
+            transport,
+            endpoints,
+            retry: Mutex::new(RetryPolicy::default()),
+            failures: AtomicU64::new(0),
+        });
+        let (queue, bodies) = mpsc::sync_channel::<Vec<u8>>(capacity);
+        let worker = {
+            let delivery = Arc::clone(&delivery);
+            std::thread::Builder::new()
+                .name("x402-webhooks".to_string())
+                .spawn(move || {
+                    for body in bodies {
+                        for endpoint in &delivery.endpoints {
+                            if let Err(e) = delivery.deliver(endpoint, &body) {
+                                delivery.failures.fetch_add(1, Ordering::Relaxed);
+                                eprintln!("x402 webhooks: {}", e);
+                            }
+                        }
+                    }
+                })
+                .expect("failed to spawn webhook delivery thread")
+        };
+        Self { delivery, queue: Some(queue), worker: Some(worker) }
+    }
+
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        *self.delivery.retry.lock().unwrap() = retry;
+        self
+    }
+
+    /// Deliveries to an endpoint that failed after every retry
+    pub fn failed_deliveries(&self) -> u64 {
+        self.delivery.failures.load(Ordering::Relaxed)
+    }
+
+    /// Stop accepting events and wait for queued deliveries to finish
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        drop(self.queue.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<T: WebhookTransport + 'static> Drop for WebhookSink<T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<T: WebhookTransport> Delivery<T> {
+    fn deliver(&self, endpoint: &WebhookEndpoint, body: &[u8]) -> Result<()> {
+        let retry = *self.retry.lock().unwrap();
+        let mut backoff = retry.initial_backoff;
+        let mut last_error = String::new();
+
+        for attempt in 1..=retry.max_attempts.max(1) {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let headers = [
+                ("Content-Type", "application/json".to_string()),
+                (WEBHOOK_SIGNATURE_HEADER, signature_header(&endpoint.secret, timestamp, body)),
+            ];
+
+            match self.transport.post(&endpoint.url, &headers, body) {
+                Ok(status) if (200..300).contains(&status) => return Ok(()),
+                Ok(status) => last_error = format!("HTTP {}", status),
+                Err(e) => last_error = e.to_string(),
+            }
+
+            if attempt < retry.max_attempts {
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(retry.max_backoff);
+            }
+        }
+
+        Err(X402Error::WebhookDelivery(format!(
+            "{}: {}",
+            endpoint.url, last_error
+        )))
+    }
+}
+
+impl<T: WebhookTransport + 'static> PaymentEvents for WebhookSink<T> {
+    /// Queue the event for delivery to every endpoint
+    fn emit(&self, event: &PaymentEvent) -> Result<()> {
+        let body = serde_json::to_vec(event)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        let queue = self.queue.as_ref().expect("webhook sink is running");
+        queue.try_send(body).map_err(|e| match e {
+            TrySendError::Full(_) => X402Error::WebhookDelivery("webhook queue full; event dropped".to_string()),
+            TrySendError::Disconnected(_) => X402Error::WebhookDelivery("webhook delivery stopped".to_string()),
+        })
+    }
+}
+
+/// Compute the signature header value for a webhook body
+///
+/// The MAC covers `"<timestamp>.<body>"` so receivers can reject replays
+/// of old deliveries.
+pub fn signature_header(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let mac = webhook_mac(secret, timestamp, body).finalize().into_bytes();
+    format!("t={},v1={}", timestamp, hex::encode(mac))
+}
+
+/// Verify a received signature header against the raw request body
+pub fn verify_signature_header(secret: &[u8], header: &str, body: &[u8]) -> Result<u64> {
+    let mut timestamp = None;
+    let mut mac = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<u64>().ok(),
+            Some(("v1", value)) => mac = hex::decode(value).ok(),
+            _ => {}
+        }
+    }
+
+    let (timestamp, mac) = timestamp.zip(mac).ok_or_else(|| {
+        X402Error::InvalidSignature("malformed webhook signature header".to_string())
+    })?;
+
+    webhook_mac(secret, timestamp, body)
+        .verify_slice(&mac)
+        .map_err(|_| X402Error::InvalidSignature("webhook signature mismatch".to_string()))?;
+
+    Ok(timestamp)
+}
+
+fn webhook_mac(secret: &[u8], timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers with scripted statuses, counting calls and checking signatures
+    #[derive(Clone, Default)]
+    struct FlakyTransport {
+        statuses: Arc<Mutex<Vec<u16>>>,
+        calls: Arc<Mutex<u32>>,
+        bodies: Arc<Mutex<Vec<serde_json::Value>>>,
+    }
+
+    impl WebhookTransport for FlakyTransport {
+        fn post(&self, _url: &str, headers: &[(&str, String)], body: &[u8]) -> Result<u16> {
+            *self.calls.lock().unwrap() += 1;
+            let (_, signature) = headers.iter()
+                .find(|(name, _)| *name == WEBHOOK_SIGNATURE_HEADER)
+                .unwrap();
+            verify_signature_header(b"secret", signature, body).unwrap();
+            self.bodies.lock().unwrap().push(serde_json::from_slice(body).unwrap());
+            Ok(self.statuses.lock().unwrap().remove(0))
+        }
+    }
+
+    fn event() -> PaymentEvent {
+        PaymentEvent::SettlementFailed {
+            payer: Address::ZERO,
+            chain_id: 8453,
+            nonce: 7,
+            reason: "reverted".to_string(),
+        }
+    }
+
+    fn webhook_sink(statuses: Vec<u16>, max_attempts: u32) -> (WebhookSink<FlakyTransport>, FlakyTransport) {
+        let transport = FlakyTransport { statuses: Arc::new(Mutex::new(statuses)), ..Default::default() };
+        let endpoint = WebhookEndpoint {
+            url: "https://hooks.example.com/x402".to_string(),
+            secret: b"secret".to_vec(),
+        };
+        let sink = WebhookSink::new(transport.clone(), vec![endpoint]).with_retry_policy(RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        });
+        (sink, transport)
+    }
+
+    #[test]
+    fn test_webhook_retries_until_success() {
+        let (sink, transport) = webhook_sink(vec![500, 503, 200], 5);
+        sink.emit(&event()).unwrap();
+        sink.shutdown();
+        assert_eq!(*transport.calls.lock().unwrap(), 3);
+
+        let (sink, transport) = webhook_sink(vec![500, 500], 2);
+        sink.emit(&event()).unwrap();
+        while sink.failed_deliveries() == 0 {
+            std::thread::yield_now();
+        }
+        assert_eq!(*transport.calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_full_queue_refuses_events() {
+        /// Blocks every delivery until released
+        struct Stuck(Mutex<mpsc::Receiver<()>>);
+
+        impl WebhookTransport for Stuck {
+            fn post(&self, _: &str, _: &[(&str, String)], _: &[u8]) -> Result<u16> {
+                self.0.lock().unwrap().recv().map_err(|e| X402Error::WebhookDelivery(e.to_string()))?;
+                Ok(200)
+            }
+        }
+
+        let (release, released) = mpsc::channel();
+        let endpoint = WebhookEndpoint { url: "https://hooks.example.com/x402".to_string(), secret: Vec::new() };
+        let sink = WebhookSink::with_capacity(Stuck(Mutex::new(released)), vec![endpoint], 1);
+        // However many the worker has taken, at most two fit before the queue is full
+        let results: Vec<_> = (0..3).map(|_| sink.emit(&event())).collect();
+        assert!(matches!(results.last(), Some(Err(X402Error::WebhookDelivery(_)))));
+        for _ in 0..3 {
+            let _ = release.send(());
+        }
+        drop(release);
+        sink.shutdown();
+    }
+
+    #[test]
+    fn test_verified_event_amount_is_a_string() {
+        let (sink, transport) = webhook_sink(vec![200], 1);
+        let payment = PaymentPayload {
+            amount: U256::MAX,
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: None,
+            resource: "/premium".to_string(),
+            nonce: 1,
+            expires_at: 0,
+            scheme: crate::Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        sink.emit(&PaymentEvent::verified(&payment)).unwrap();
+        sink.shutdown();
+        let bodies = transport.bodies.lock().unwrap();
+        assert_eq!(bodies[0]["type"], "payment_verified");
+        assert_eq!(bodies[0]["amount"], U256::MAX.to_string());
+    }
+
+    #[test]
+    fn test_signature_header_rejects_tampered_body() {
+        let header = signature_header(b"secret", 1700000000, b"{}");
+        assert_eq!(verify_signature_header(b"secret", &header, b"{}").unwrap(), 1700000000);
+        assert!(verify_signature_header(b"secret", &header, b"{\"a\":1}").is_err());
+        assert!(verify_signature_header(b"other", &header, b"{}").is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Facilitator interface
+//!
+//! A facilitator verifies payments on a server's behalf and settles them
+//! on-chain. Servers written against [`Facilitator`] can swap a hosted
+//! facilitator for [`testing::MockFacilitator`](crate::testing) in tests.
+
+use crate::{decode_header, parse_payload, tagged, DecodeLimits, PaymentRequirements, Result, SignedPayment};
+use crate::{Network, Scheme, WireFormat, X402Error, X402_VERSION};
+use alloy_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// HTTP header carrying the settlement receipt of a paid request
+pub const X402_PAYMENT_RESPONSE_HEADER: &str = "X-Payment-Response";
+
+/// Proof that a payment was settled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementReceipt {
+    pub payer: Address,
+    pub chain_id: u64,
+    pub nonce: u64,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    pub transaction_hash: B256,
+}
+
+impl SettlementReceipt {
+    /// Encode as an `X-Payment-Response` header value
+    pub fn to_header(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    }
+
+    /// Decode an `X-Payment-Response` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let (format, bytes) = decode_header(header.as_ref(), &limits)?;
+        parse_payload(format, &bytes, &limits)
+    }
+}
+
+/// A (scheme, network, token) combination a facilitator verifies and settles
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportedPaymentKind {
+    pub x402_version: u32,
+    pub scheme: Scheme,
+    pub network: Network,
+    /// Token contract (`None` = native token)
+    pub token: Option<Address>,
+}
+
+impl SupportedPaymentKind {
+    pub fn new(scheme: Scheme, network: Network, token: Option<Address>) -> Self {
+        Self { x402_version: X402_VERSION, scheme, network, token }
+    }
+
+    /// Whether payments for `requirements` are of this kind
+    pub fn matches(&self, requirements: &PaymentRequirements) -> bool {
+        self.scheme == requirements.scheme && self.network == requirements.network && self.token == requirements.token
+    }
+}
+
+/// Body of a facilitator's `/supported` response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportedResponse {
+    pub kinds: Vec<SupportedPaymentKind>,
+}
+
+impl SupportedResponse {
+    /// Whether the facilitator can verify and settle payments for `requirements`
+    pub fn supports(&self, requirements: &PaymentRequirements) -> bool {
+        self.kinds.iter().any(|kind| kind.matches(requirements))
+    }
+}
+
+/// Verifies and settles payments
+pub trait Facilitator: Send + Sync {
+    /// Check a payment against requirements, returning the payer
+    fn verify(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<Address>;
+
+    /// Settle a verified payment on-chain
+    fn settle(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<SettlementReceipt>;
+
+    /// Payment kinds this facilitator handles; empty if it doesn't say
+    fn supported(&self) -> Result<Vec<SupportedPaymentKind>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_header_roundtrip() {
+        let receipt = SettlementReceipt {
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            nonce: 7,
+            amount: U256::from(10_000),
+            transaction_hash: B256::repeat_byte(0xab),
+        };
+        let header = receipt.to_header().unwrap();
+        assert!(header.starts_with("x402.v2.json."));
+        assert_eq!(SettlementReceipt::from_header(header).unwrap(), receipt);
+    }
+
+    #[test]
+    fn test_supported_kinds() {
+        let usdc = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/x").unwrap();
+        let response = SupportedResponse {
+            kinds: vec![
+                SupportedPaymentKind::new(Scheme::Exact, Network::Base, usdc.token),
+                SupportedPaymentKind::new(Scheme::Upto, Network::Base, None),
+            ],
+        };
+        assert!(response.supports(&usdc));
+        assert!(!response.supports(&PaymentRequirements { scheme: Scheme::Upto, ..usdc.clone() }));
+        assert!(!response.supports(&PaymentRequirements { network: Network::Ethereum, ..usdc }));
+
+        let json = serde_json::to_value(&response).unwrap();
+        let upto = serde_json::json!({"x402Version": 1, "scheme": "upto", "network": "base", "token": null});
+        assert_eq!(json["kinds"][1], upto);
+        assert_eq!(serde_json::from_value::<SupportedResponse>(json).unwrap(), response);
+    }
+}
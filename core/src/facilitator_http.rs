@@ -0,0 +1,683 @@
+//! Facilitator over HTTP (feature `facilitator-http`)
+//!
+//! [`FacilitatorServer`] exposes any [`Facilitator`] on three endpoints, and
+//! [`HttpFacilitator`] is the matching client, itself a [`Facilitator`]:
+//!
+//! - `GET /supported`: the [`SupportedPaymentKind`]s the facilitator
+//!   handles, as a [`SupportedResponse`]
+//! - `POST /verify`: a [`FacilitatorRequest`]; answers a [`VerifyResponse`]
+//! - `POST /settle`: a [`FacilitatorRequest`]; answers a [`SettlementReceipt`]
+//!
+//! Failures are answered with the error's [`X402Error::status_code`] and an
+//! [`ErrorBody`], which the client turns back into
+//! [`X402Error::FacilitatorError`].
+//!
+//! With [`FacilitatorServer::with_auth`], `/verify` and `/settle` require an
+//! API key (see [`crate::apikey`]) in `X-Api-Key` or `Authorization: Bearer`,
+//! metered by its rate limit and settlement quota (spent only by successful
+//! settlements), and admin keys manage keys on three more endpoints:
+//!
+//! - `GET /keys`: every [`ApiKeyRecord`]
+//! - `POST /keys`: a [`CreateApiKey`]; answers a [`CreatedApiKey`]
+//! - `DELETE /keys/{id}`: revoke a key
+//!
+//! Seed the first admin key with [`ApiKeyAuth::seed_admin_key_from_env`].
+//! With [`FacilitatorServer::with_events`], verified payments and settlement
+//! outcomes are reported as [`PaymentEvent`]s.
+//!
+//! `/supported` stays public. Like the demo server, both sides speak
+//! just enough HTTP/1.1 over `std::net` (one request per connection, no
+//! TLS) to need no extra dependencies; put a TLS-terminating proxy in front
+//! of a facilitator reachable beyond localhost. The server handles
+//! connections on a fixed pool of workers, each connection with a read and
+//! write timeout; when every worker is busy and as many connections wait,
+//! new ones are answered 503 straight away.
+
+use crate::events::report;
+use crate::{
+    decode_payment_header_with_limits, encode_payment_header, ApiKeyAuth, ApiKeyLimits, ApiKeyRecord, DecodeLimits,
+    Facilitator, PaymentEvent, PaymentEvents, PaymentRequirements, Result, SettlementReceipt, SupportedPaymentKind,
+    SupportedResponse, X402Error, X402_API_KEY_HEADER,
+};
+use alloy_primitives::Address;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Connections a [`FacilitatorServer`] handles at once by default
+pub const DEFAULT_FACILITATOR_WORKERS: usize = 32;
+
+/// Read and write timeout of a [`FacilitatorServer`]'s connections by default
+pub const DEFAULT_FACILITATOR_IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+const MAX_REQUEST_HEAD: u64 = 16 * 1024;
+const MAX_BODY: u64 = 64 * 1024;
+const MAX_RESPONSE: u64 = 1024 * 1024;
+
+/// Path of the supported payment kinds endpoint
+pub const FACILITATOR_SUPPORTED_PATH: &str = "/supported";
+
+/// Path of the verification endpoint
+pub const FACILITATOR_VERIFY_PATH: &str = "/verify";
+
+/// Path of the settlement endpoint
+pub const FACILITATOR_SETTLE_PATH: &str = "/settle";
+
+/// Path of the key management endpoints
+pub const FACILITATOR_KEYS_PATH: &str = "/keys";
+
+/// Body of `/verify` and `/settle` requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FacilitatorRequest {
+    /// The payment, as an `X-Payment` header value
+    pub payment_header: String,
+    pub payment_requirements: PaymentRequirements,
+}
+
+/// Body of a successful `/verify` response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyResponse {
+    pub payer: Address,
+}
+
+/// Body of a `POST /keys` request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKey {
+    pub name: String,
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub limits: ApiKeyLimits,
+}
+
+/// Body of a `POST /keys` response: the new key, shown only this once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    pub record: ApiKeyRecord,
+}
+
+/// Body of an error response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// [`X402Error::kind`] of the failure
+    pub error: String,
+    pub message: String,
+}
+
+/// HTTP server for a [`Facilitator`]; see the [module docs](self)
+pub struct FacilitatorServer {
+    listener: TcpListener,
+    facilitator: Arc<dyn Facilitator>,
+    limits: DecodeLimits,
+    auth: Option<ApiKeyAuth>,
+    events: Option<Arc<dyn PaymentEvents>>,
+    workers: usize,
+    io_timeout: Duration,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    fn api_key(&self) -> Option<&str> {
+        self.header(X402_API_KEY_HEADER)
+            .or_else(|| self.header("Authorization").and_then(|value| value.strip_prefix("Bearer ")))
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn status(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: String::new() }
+    }
+
+    fn json(value: &impl Serialize) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Self { status: 200, headers: Vec::new(), body },
+            Err(e) => Self::error(&X402Error::EncodingError(e.to_string())),
+        }
+    }
+
+    fn error(error: &X402Error) -> Self {
+        let body = ErrorBody { error: error.kind().to_string(), message: error.to_string() };
+        let mut headers = Vec::new();
+        if let X402Error::RateLimited { retry_after_seconds } = error {
+            headers.push(("Retry-After", retry_after_seconds.to_string()));
+        }
+        Self { status: error.status_code(), headers, body: serde_json::to_string(&body).unwrap_or_default() }
+    }
+}
+
+impl FacilitatorServer {
+    pub fn bind(addr: impl ToSocketAddrs, facilitator: Arc<dyn Facilitator>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| X402Error::InvalidConfig(format!("cannot bind facilitator server: {}", e)))?;
+        Ok(Self {
+            listener,
+            facilitator,
+            limits: DecodeLimits::default(),
+            auth: None,
+            events: None,
+            workers: DEFAULT_FACILITATOR_WORKERS,
+            io_timeout: DEFAULT_FACILITATOR_IO_TIMEOUT,
+        })
+    }
+
+    /// Handle up to `workers` connections at once (at least one)
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Drop connections that stall reading or writing for `timeout`
+    pub fn with_io_timeout(mut self, timeout: Duration) -> Self {
+        self.io_timeout = timeout;
+        self
+    }
+
+    /// Require API keys checked by `auth`, and serve the key management endpoints
+    pub fn with_auth(mut self, auth: ApiKeyAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Report verified payments and settlement outcomes to `events`
+    pub fn with_events(mut self, events: Arc<dyn PaymentEvents>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Decode payment headers with `limits`
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(|e| X402Error::InvalidConfig(e.to_string()))
+    }
+
+    /// Serve connections on the worker pool until the listener fails
+    pub fn serve(self) -> Result<()> {
+        let server = Arc::new(self);
+        // Accepted connections wait here for a worker; beyond that they're turned away
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(server.workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..server.workers {
+            let (server, receiver) = (Arc::clone(&server), Arc::clone(&receiver));
+            std::thread::spawn(move || loop {
+                let Ok(stream) = receiver.lock().unwrap().recv() else {
+                    return;
+                };
+                if let Err(e) = server.handle_connection(stream) {
+                    eprintln!("x402 facilitator: connection error: {}", e);
+                }
+            });
+        }
+        for stream in server.listener.incoming() {
+            let stream = stream.map_err(|e| X402Error::InvalidConfig(format!("accept failed: {}", e)))?;
+            if let Err(e) = stream
+                .set_read_timeout(Some(server.io_timeout))
+                .and_then(|_| stream.set_write_timeout(Some(server.io_timeout)))
+            {
+                eprintln!("x402 facilitator: connection error: {}", e);
+                continue;
+            }
+            match sender.try_send(stream) {
+                Ok(()) => {}
+                Err(TrySendError::Full(mut stream)) => {
+                    let busy = X402Error::VerifierOverloaded("too many connections".to_string());
+                    let _ = write_response(&mut stream, &Response::error(&busy));
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    return Err(X402Error::InvalidConfig("facilitator workers stopped".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let response = match read_request(&stream)? {
+            Some(request) => self.respond(&request),
+            None => Response::error(&X402Error::InvalidHeader("malformed request".to_string())),
+        };
+        write_response(&mut stream, &response)
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", FACILITATOR_SUPPORTED_PATH) => {
+                self.facilitator.supported().map(|kinds| Response::json(&SupportedResponse { kinds }))
+            }
+            ("POST", FACILITATOR_VERIFY_PATH) => self
+                .authorize(request, now)
+                .and_then(|_| self.verify(&request.body))
+                .map(|payer| Response::json(&payer)),
+            ("POST", FACILITATOR_SETTLE_PATH) => self
+                .authorize(request, now)
+                .and_then(|record| match (&self.auth, record) {
+                    (Some(auth), Some(record)) => auth.meter_settlement_at(&record, now, || self.settle(&request.body)),
+                    _ => self.settle(&request.body),
+                })
+                .map(|receipt| Response::json(&receipt)),
+            (_, FACILITATOR_SUPPORTED_PATH | FACILITATOR_VERIFY_PATH | FACILITATOR_SETTLE_PATH) => {
+                return Response::status(405)
+            }
+            (method, path) if self.auth.is_some() && is_keys_path(path) => self.manage_keys(method, path, request, now),
+            _ => return Response::status(404),
+        };
+        result.unwrap_or_else(|e| Response::error(&e))
+    }
+
+    /// Check the request's API key, if keys are required; its record, if so
+    fn authorize(&self, request: &Request, now: u64) -> Result<Option<ApiKeyRecord>> {
+        let Some(auth) = &self.auth else {
+            return Ok(None);
+        };
+        let key = request.api_key().ok_or_else(|| X402Error::InvalidApiKey("missing API key".to_string()))?;
+        let record = auth.authenticate(key)?;
+        auth.check_request_at(&record, now)?;
+        Ok(Some(record))
+    }
+
+    fn manage_keys(&self, method: &str, path: &str, request: &Request, now: u64) -> Result<Response> {
+        let Some(auth) = &self.auth else {
+            return Ok(Response::status(404));
+        };
+        if !self.authorize(request, now)?.is_some_and(|record| record.admin) {
+            return Err(X402Error::Forbidden("key management needs an admin key".to_string()));
+        }
+        let id = path.strip_prefix(FACILITATOR_KEYS_PATH).and_then(|rest| rest.strip_prefix('/'));
+        match (method, id) {
+            ("GET", None) => Ok(Response::json(&auth.store().list()?)),
+            ("POST", None) => {
+                let create: CreateApiKey = serde_json::from_slice(&request.body).map_err(X402Error::Json)?;
+                let (key, record) = auth.create_key_at(create.name, create.limits, create.admin, now)?;
+                Ok(Response::json(&CreatedApiKey { key, record }))
+            }
+            ("DELETE", Some(id)) if auth.store().revoke(id)? => Ok(Response::status(204)),
+            ("DELETE", Some(_)) => Ok(Response::status(404)),
+            _ => Ok(Response::status(405)),
+        }
+    }
+
+    fn verify(&self, body: &[u8]) -> Result<VerifyResponse> {
+        let request: FacilitatorRequest = serde_json::from_slice(body).map_err(X402Error::Json)?;
+        let payment = decode_payment_header_with_limits(&request.payment_header, &self.limits)?;
+        let payer = self.facilitator.verify(&payment, &request.payment_requirements)?;
+        report(self.events.as_ref(), PaymentEvent::verified(&payment.payment));
+        Ok(VerifyResponse { payer })
+    }
+
+    fn settle(&self, body: &[u8]) -> Result<SettlementReceipt> {
+        let request: FacilitatorRequest = serde_json::from_slice(body).map_err(X402Error::Json)?;
+        let payment = decode_payment_header_with_limits(&request.payment_header, &self.limits)?;
+        let outcome = self.facilitator.settle(&payment, &request.payment_requirements);
+        report(self.events.as_ref(), PaymentEvent::settled(&payment.payment, &outcome));
+        outcome
+    }
+}
+
+/// [`Facilitator`] reached over HTTP; see the [module docs](self)
+#[derive(Debug, Clone)]
+pub struct HttpFacilitator {
+    /// `host:port`
+    authority: String,
+    /// Path prefix of the endpoints, without a trailing `/`
+    base_path: String,
+    timeout: Duration,
+    api_key: Option<String>,
+}
+
+impl HttpFacilitator {
+    /// Client for the facilitator at `url`, e.g. `http://127.0.0.1:4021`
+    pub fn new(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| X402Error::InvalidConfig(format!("facilitator {:?} must be an http:// URL", url)))?;
+        let (authority, base_path) = rest.split_once('/').map_or((rest, ""), |(a, p)| (a, p));
+        if authority.is_empty() {
+            return Err(X402Error::InvalidConfig(format!("facilitator {:?} has no host", url)));
+        }
+        let authority = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+        let base_path = base_path.trim_end_matches('/');
+        let base_path = if base_path.is_empty() { String::new() } else { format!("/{}", base_path) };
+        Ok(Self { authority, base_path, timeout: Duration::from_secs(10), api_key: None })
+    }
+
+    /// Give up on calls taking longer than `timeout` (10 seconds by default)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Authenticate with `key`
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// The facilitator's supported payment kinds, as served
+    pub fn supported_response(&self) -> Result<SupportedResponse> {
+        self.call("GET", FACILITATOR_SUPPORTED_PATH, None)
+    }
+
+    /// Every API key (needs an admin key)
+    pub fn list_keys(&self) -> Result<Vec<ApiKeyRecord>> {
+        self.call("GET", FACILITATOR_KEYS_PATH, None)
+    }
+
+    /// Issue an API key (needs an admin key)
+    pub fn create_key(&self, request: &CreateApiKey) -> Result<CreatedApiKey> {
+        let body = serde_json::to_string(request).map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        self.call("POST", FACILITATOR_KEYS_PATH, Some(&body))
+    }
+
+    /// Revoke the API key `id` (needs an admin key)
+    pub fn revoke_key(&self, id: &str) -> Result<()> {
+        self.call::<serde::de::IgnoredAny>("DELETE", &format!("{}/{}", FACILITATOR_KEYS_PATH, id), None).map(|_| ())
+    }
+
+    fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        payment: &crate::SignedPayment,
+        requirements: &PaymentRequirements,
+    ) -> Result<T> {
+        let request = FacilitatorRequest {
+            payment_header: encode_payment_header(payment)?,
+            payment_requirements: requirements.clone(),
+        };
+        let body = serde_json::to_string(&request).map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        self.call("POST", path, Some(&body))
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, path: &str, body: Option<&str>) -> Result<T> {
+        let (status, response) = self
+            .exchange(method, path, body.unwrap_or_default())
+            .map_err(|e| X402Error::FacilitatorUnavailable(format!("{}: {}", self.authority, e)))?;
+        if status == 204 {
+            return serde_json::from_slice(b"null").map_err(X402Error::Json);
+        }
+        if status == 200 {
+            return serde_json::from_slice(&response).map_err(X402Error::Json);
+        }
+        let (kind, message) = match serde_json::from_slice::<ErrorBody>(&response) {
+            Ok(body) => (body.error, body.message),
+            Err(_) => ("http".to_string(), String::from_utf8_lossy(&response).into_owned()),
+        };
+        Err(X402Error::FacilitatorError { status, kind, message })
+    }
+
+    /// Send one request, returning the response status and body
+    fn exchange(&self, method: &str, path: &str, body: &str) -> std::io::Result<(u16, Vec<u8>)> {
+        let addr = self.authority.to_socket_addrs()?.next().ok_or(std::io::ErrorKind::AddrNotAvailable)?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let key = self.api_key.as_ref().map(|key| format!("{}: {}\r\n", X402_API_KEY_HEADER, key));
+        write!(
+            stream,
+            "{} {}{} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            method,
+            self.base_path,
+            path,
+            self.authority,
+            key.unwrap_or_default(),
+            body.len(),
+            body
+        )?;
+        let mut response = Vec::new();
+        stream.take(MAX_RESPONSE).read_to_end(&mut response)?;
+
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed HTTP response");
+        let head_end = response.windows(4).position(|w| w == b"\r\n\r\n").ok_or_else(invalid)?;
+        let status = std::str::from_utf8(&response[..head_end])
+            .ok()
+            .and_then(|head| head.split_whitespace().nth(1))
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok((status, response.split_off(head_end + 4)))
+    }
+}
+
+impl Facilitator for HttpFacilitator {
+    fn verify(&self, payment: &crate::SignedPayment, requirements: &PaymentRequirements) -> Result<Address> {
+        self.post::<VerifyResponse>(FACILITATOR_VERIFY_PATH, payment, requirements).map(|response| response.payer)
+    }
+
+    fn settle(&self, payment: &crate::SignedPayment, requirements: &PaymentRequirements) -> Result<SettlementReceipt> {
+        self.post(FACILITATOR_SETTLE_PATH, payment, requirements)
+    }
+
+    fn supported(&self) -> Result<Vec<SupportedPaymentKind>> {
+        self.supported_response().map(|response| response.kinds)
+    }
+}
+
+fn is_keys_path(path: &str) -> bool {
+    path == FACILITATOR_KEYS_PATH || path.starts_with("/keys/")
+}
+
+/// Read a request's head and its `Content-Length` body
+fn read_request(stream: &TcpStream) -> std::io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD + MAX_BODY));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut headers = Vec::new();
+    let mut length = 0u64;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim(), value.trim());
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.parse().unwrap_or(u64::MAX);
+            }
+            headers.push((name.to_string(), value.to_string()));
+        }
+    }
+    if length > MAX_BODY {
+        return Ok(None);
+    }
+    let mut body = Vec::with_capacity(length as usize);
+    reader.take(length).read_to_end(&mut body)?;
+    Ok(Some(Request { method, path, headers, body }))
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        402 => "Payment Required",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockFacilitator, RecordingEvents, TestSigner};
+    use crate::{MemoryApiKeyStore, Network, Scheme};
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn test_facilitator_over_http() {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/x").unwrap();
+        let kind = SupportedPaymentKind::new(Scheme::Exact, Network::Base, requirements.token);
+        let facilitator = MockFacilitator::new().with_supported(vec![kind.clone()]);
+        let events = Arc::new(RecordingEvents::new());
+        let server = FacilitatorServer::bind("127.0.0.1:0", Arc::new(facilitator)).unwrap().with_events(events.clone());
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.serve());
+
+        let client = HttpFacilitator::new(&format!("http://{}/", addr)).unwrap();
+        assert_eq!(client.supported().unwrap(), vec![kind]);
+        assert!(client.supported_response().unwrap().supports(&requirements));
+
+        let signer = TestSigner::new(1);
+        let payment = signer.pay(&requirements, now()).unwrap();
+        assert_eq!(client.verify(&payment, &requirements).unwrap(), signer.address());
+        let receipt = client.settle(&payment, &requirements).unwrap();
+        assert_eq!((receipt.payer, receipt.nonce), (signer.address(), payment.payment.nonce));
+        let confirmed = PaymentEvent::settled(&payment.payment, &Ok(receipt));
+        assert_eq!(events.events(), vec![PaymentEvent::verified(&payment.payment), confirmed]);
+
+        let amount = requirements.amount * alloy_primitives::U256::from(2);
+        let error = client.verify(&payment, &PaymentRequirements { amount, ..requirements }).unwrap_err();
+        let expected = "insufficient_amount";
+        assert!(matches!(&error, X402Error::FacilitatorError { status: 402, kind, .. } if kind == expected));
+        assert_eq!(error.status_code(), 402);
+
+        assert!(HttpFacilitator::new("https://example.com").is_err());
+        let unreachable = HttpFacilitator::new("http://127.0.0.1:1").unwrap();
+        assert!(unreachable.supported().unwrap_err().is_retryable());
+    }
+
+    #[test]
+    fn test_settlement_failures_are_reported() {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/x").unwrap();
+        let facilitator = MockFacilitator::new().fail_settle(|| X402Error::Storage("node down".to_string()));
+        let events = Arc::new(RecordingEvents::new());
+        let server = FacilitatorServer::bind("127.0.0.1:0", Arc::new(facilitator)).unwrap().with_events(events.clone());
+        let url = format!("http://{}", server.local_addr().unwrap());
+        std::thread::spawn(move || server.serve());
+
+        let payment = TestSigner::new(1).pay(&requirements, now()).unwrap();
+        assert!(HttpFacilitator::new(&url).unwrap().settle(&payment, &requirements).is_err());
+        let reported = events.events();
+        assert!(matches!(&reported[..], [PaymentEvent::SettlementFailed { reason, .. }] if reason.contains("node down")));
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/x").unwrap();
+        let auth = ApiKeyAuth::new(Arc::new(MemoryApiKeyStore::new()));
+        let (admin_key, _) = auth.create_key_at("operator", ApiKeyLimits::default(), true, now()).unwrap();
+        let server = FacilitatorServer::bind("127.0.0.1:0", Arc::new(MockFacilitator::new())).unwrap().with_auth(auth);
+        let url = format!("http://{}", server.local_addr().unwrap());
+        std::thread::spawn(move || server.serve());
+
+        let signer = TestSigner::new(1);
+        let payment = signer.pay(&requirements, now()).unwrap();
+        let anonymous = HttpFacilitator::new(&url).unwrap();
+        assert!(anonymous.supported().unwrap().is_empty());
+        let error = anonymous.verify(&payment, &requirements).unwrap_err();
+        assert!(matches!(&error, X402Error::FacilitatorError { status: 401, kind, .. } if kind == "invalid_api_key"));
+
+        let admin = HttpFacilitator::new(&url).unwrap().with_api_key(admin_key);
+        let limits = ApiKeyLimits { requests_per_minute: Some(100), settlements_per_day: Some(1) };
+        let created = admin.create_key(&CreateApiKey { name: "crawler".to_string(), admin: false, limits }).unwrap();
+        assert_eq!(created.record.limits, limits);
+        assert_eq!(admin.list_keys().unwrap().len(), 2);
+
+        let client = HttpFacilitator::new(&url).unwrap().with_api_key(created.key);
+        assert_eq!(client.verify(&payment, &requirements).unwrap(), signer.address());
+        client.settle(&payment, &requirements).unwrap();
+        let error = client.settle(&payment, &requirements).unwrap_err();
+        assert!(matches!(&error, X402Error::FacilitatorError { status: 429, kind, .. } if kind == "quota_exceeded"));
+        let error = client.list_keys().unwrap_err();
+        assert!(matches!(error, X402Error::FacilitatorError { status: 403, .. }));
+
+        admin.revoke_key(&created.record.id).unwrap();
+        assert!(matches!(client.verify(&payment, &requirements), Err(X402Error::FacilitatorError { status: 401, .. })));
+        assert!(matches!(admin.revoke_key("missing"), Err(X402Error::FacilitatorError { status: 404, .. })));
+    }
+
+    #[test]
+    fn test_connection_limits() {
+        let server = FacilitatorServer::bind("127.0.0.1:0", Arc::new(MockFacilitator::new()))
+            .unwrap()
+            .with_workers(1)
+            .with_io_timeout(Duration::from_secs(5));
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.serve());
+
+        // Idle connections hold the one worker and the one waiting slot, so
+        // one of three is turned away without waiting for the others
+        let idle: Vec<TcpStream> = (0..3).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        let refused = idle.iter().any(|mut stream| {
+            stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).is_ok() && response.starts_with("HTTP/1.1 503")
+        });
+        assert!(refused);
+        drop(idle);
+
+        // A stalled connection is dropped after the timeout
+        let server = FacilitatorServer::bind("127.0.0.1:0", Arc::new(MockFacilitator::new()))
+            .unwrap()
+            .with_io_timeout(Duration::from_millis(100));
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.serve());
+        let mut stalled = TcpStream::connect(addr).unwrap();
+        stalled.write_all(b"POST /verify HTTP/1.1\r\n").unwrap();
+        stalled.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let started = std::time::Instant::now();
+        let mut response = Vec::new();
+        assert_eq!(stalled.read_to_end(&mut response).unwrap(), 0);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,199 @@
+//! Reverse-proxy aware resource URIs
+//!
+//! Servers must derive the `resource` they quote from the URL the *client*
+//! used, which differs from what the application sees when it runs behind
+//! a reverse proxy on another port or under a path prefix. [`ResourceUriBuilder`]
+//! reconstructs it from `X-Forwarded-Proto`, `X-Forwarded-Host` and
+//! `X-Forwarded-Prefix`, honouring those headers only when the immediate
+//! peer is a trusted proxy.
+
+use crate::{Result, X402Error};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+pub const X_FORWARDED_PROTO: &str = "X-Forwarded-Proto";
+pub const X_FORWARDED_HOST: &str = "X-Forwarded-Host";
+pub const X_FORWARDED_PREFIX: &str = "X-Forwarded-Prefix";
+
+/// An IP network in CIDR notation (a bare address is a /32 or /128)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(net).into(), u32::from(*ip).into(), self.prefix_len, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(*ip), self.prefix_len, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, prefix_len: u8, bits: u8) -> bool {
+    if prefix_len == 0 {
+        return true;
+    }
+    let shift = u32::from(bits - prefix_len);
+    (net >> shift) == (ip >> shift)
+}
+
+impl FromStr for IpNet {
+    type Err = X402Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || X402Error::InvalidConfig(format!("invalid network: {}", s));
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len.parse::<u8>().map_err(|_| invalid())?)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr.trim()).map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max);
+        if prefix_len > max {
+            return Err(invalid());
+        }
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+/// The parts of an incoming request needed to build its resource URI
+#[derive(Debug, Clone, Copy)]
+pub struct RequestParts<'a> {
+    /// Scheme the server itself received the request on
+    pub scheme: &'a str,
+    /// `Host` header (may include a port)
+    pub host: &'a str,
+    /// Request path as seen by the application (without query string)
+    pub path: &'a str,
+    /// Address of the immediate peer, if known
+    pub peer: Option<IpAddr>,
+    /// Request headers; names are matched case-insensitively
+    pub headers: &'a [(&'a str, &'a str)],
+}
+
+impl RequestParts<'_> {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            // Chained proxies append; the first entry is the client-facing one
+            .and_then(|(_, v)| v.split(',').next())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    }
+}
+
+/// Builds canonical resource URIs for incoming requests
+#[derive(Debug, Clone, Default)]
+pub struct ResourceUriBuilder {
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl ResourceUriBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust forwarding headers from peers in these networks
+    pub fn trust_proxies<I, S>(mut self, networks: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for network in networks {
+            self.trusted_proxies.push(network.as_ref().parse()?);
+        }
+        Ok(self)
+    }
+
+    pub fn is_trusted(&self, peer: Option<IpAddr>) -> bool {
+        peer.is_some_and(|ip| self.trusted_proxies.iter().any(|net| net.contains(&ip)))
+    }
+
+    /// The absolute URI the client addressed, e.g. `https://api.example.com/v1/data`
+    ///
+    /// Default ports are omitted and the scheme and host are lowercased, so
+    /// the same resource always yields the same string.
+    pub fn resource_uri(&self, request: &RequestParts<'_>) -> String {
+        let trusted = self.is_trusted(request.peer);
+        let forwarded = |name| if trusted { request.header(name) } else { None };
+
+        let scheme = forwarded(X_FORWARDED_PROTO).unwrap_or(request.scheme).to_ascii_lowercase();
+        let host = forwarded(X_FORWARDED_HOST).unwrap_or(request.host).to_ascii_lowercase();
+        let host = strip_default_port(&host, &scheme);
+
+        let prefix = forwarded(X_FORWARDED_PREFIX).unwrap_or("").trim_end_matches('/');
+        let prefix = if prefix.is_empty() || prefix.starts_with('/') {
+            prefix.to_string()
+        } else {
+            format!("/{}", prefix)
+        };
+        let path = if request.path.starts_with('/') {
+            request.path.to_string()
+        } else {
+            format!("/{}", request.path)
+        };
+
+        format!("{}://{}{}{}", scheme, host, prefix, path)
+    }
+}
+
+fn strip_default_port<'a>(host: &'a str, scheme: &str) -> &'a str {
+    let default = match scheme {
+        "http" => ":80",
+        "https" => ":443",
+        _ => return host,
+    };
+    host.strip_suffix(default).unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(peer: &str, headers: &'a [(&'a str, &'a str)]) -> RequestParts<'a> {
+        RequestParts {
+            scheme: "http",
+            host: "app:8080",
+            path: "/data",
+            peer: Some(peer.parse().unwrap()),
+            headers,
+        }
+    }
+
+    #[test]
+    fn test_forwarded_headers_from_trusted_proxy() {
+        let builder = ResourceUriBuilder::new()
+            .trust_proxies(["10.0.0.0/8", "::1"])
+            .unwrap();
+        let headers = [
+            ("x-forwarded-proto", "https"),
+            ("X-Forwarded-Host", "API.example.com:443, internal"),
+            ("X-Forwarded-Prefix", "/billing/"),
+        ];
+
+        assert_eq!(
+            builder.resource_uri(&request("10.1.2.3", &headers)),
+            "https://api.example.com/billing/data"
+        );
+        assert_eq!(
+            builder.resource_uri(&request("203.0.113.9", &headers)),
+            "http://app:8080/data"
+        );
+    }
+
+    #[test]
+    fn test_ip_net_parsing() {
+        let net: IpNet = "192.168.0.0/16".parse().unwrap();
+        assert!(net.contains(&"192.168.44.1".parse().unwrap()));
+        assert!(!net.contains(&"192.169.0.1".parse().unwrap()));
+        assert!("0.0.0.0/0".parse::<IpNet>().unwrap().contains(&"8.8.8.8".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+    }
+}
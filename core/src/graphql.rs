@@ -0,0 +1,217 @@
+//! async-graphql guard for paid fields (feature `graphql`)
+//!
+//! Price individual queries and mutations with [`paid`]:
+//!
+//! ```ignore
+//! #[graphql(guard = "paid(report_requirements())")]
+//! async fn report(&self, ctx: &Context<'_>) -> Result<Report> {
+//!     let payer = paid_by(ctx);
+//!     ...
+//! }
+//! ```
+//!
+//! The HTTP layer passes the request's headers in as [`X402Request`]
+//! request data. The guard verifies the `X-Payment` header against the
+//! field's requirements and records the payer, which resolvers read with
+//! [`paid_by`]. Unpaid or rejected requests fail the field with a
+//! `PAYMENT_REQUIRED` error whose `paymentRequirements` extension holds an
+//! `X-Payment-Requirements` header value.
+//!
+//! A request carries one payment, so it pays for the requirements of one
+//! priced field: other fields with the same requirements (an aliased
+//! field, say) are covered too, fields priced differently fail with
+//! `PAYMENT_REQUIRED`. The schema data must hold an `Arc<dyn PaymentLedger>`;
+//! accepted payments are recorded there and replays are refused. An
+//! `Arc<dyn PaymentEvents>` in the schema data is told of each accepted
+//! payment.
+
+use crate::events::report;
+use crate::{
+    verify_payment, LedgerEntry, PaymentEvent, PaymentEvents, PaymentLedger, PaymentRequiredResponse,
+    PaymentRequirements, X402Error, X402_PAYMENT_HEADER,
+};
+use alloy_primitives::Address;
+use async_graphql::{Context, ErrorExtensions, Guard};
+use std::sync::{Arc, Mutex};
+
+/// x402 state of one GraphQL request, added as request data
+#[derive(Debug, Default)]
+pub struct X402Request {
+    payment: Option<String>,
+    /// Requirements the payment was accepted for, as JSON, and its payer
+    accepted: Mutex<Option<(String, Address)>>,
+}
+
+impl X402Request {
+    /// State for a request with the `X-Payment` header `payment`, if any
+    pub fn new(payment: Option<String>) -> Self {
+        Self { payment, accepted: Mutex::new(None) }
+    }
+
+    /// State for a request with `headers`
+    pub fn from_headers(headers: &http::HeaderMap) -> Self {
+        Self::new(headers.get(X402_PAYMENT_HEADER).and_then(|value| value.to_str().ok()).map(str::to_string))
+    }
+
+    /// The verified payer, once a guard accepted the payment
+    pub fn payer(&self) -> Option<Address> {
+        self.accepted.lock().unwrap().as_ref().map(|(_, payer)| *payer)
+    }
+}
+
+/// Guard requiring payment of `requirements`
+pub fn paid(requirements: PaymentRequirements) -> PaidGuard {
+    PaidGuard { requirements }
+}
+
+/// The payer verified for this request, if a paid field accepted a payment
+pub fn paid_by(ctx: &Context<'_>) -> Option<Address> {
+    ctx.data_opt::<X402Request>().and_then(X402Request::payer)
+}
+
+/// Guard built by [`paid`]
+#[derive(Debug, Clone)]
+pub struct PaidGuard {
+    requirements: PaymentRequirements,
+}
+
+impl PaidGuard {
+    fn accept(&self, ctx: &Context<'_>) -> crate::Result<Option<Address>> {
+        let Some(ledger) = ctx.data_opt::<Arc<dyn PaymentLedger>>() else {
+            return Err(X402Error::InvalidConfig("paid fields need a PaymentLedger in the schema data".to_string()));
+        };
+        let Some(request) = ctx.data_opt::<X402Request>() else {
+            return Ok(None);
+        };
+        let key = serde_json::to_string(&self.requirements).map_err(X402Error::Json)?;
+        // Held while verifying, so sibling fields resolved concurrently see one outcome
+        let mut accepted = request.accepted.lock().unwrap();
+        if let Some((paid_for, payer)) = accepted.as_ref() {
+            if *paid_for == key {
+                return Ok(Some(*payer));
+            }
+            return Err(X402Error::DuplicatePayment("the payment already paid for a differently priced field".to_string()));
+        }
+        let Some(header) = &request.payment else {
+            return Ok(None);
+        };
+        let payment = crate::decode_payment_header(header)?;
+        let payer = verify_payment(&payment, &self.requirements)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        ledger.record(&LedgerEntry::new(&payment.payment, now))?;
+        report(ctx.data_opt::<Arc<dyn PaymentEvents>>(), PaymentEvent::verified(&payment.payment));
+        *accepted = Some((key, payer));
+        Ok(Some(payer))
+    }
+
+    fn payment_required(&self, error: Option<String>) -> async_graphql::Error {
+        let mut response = PaymentRequiredResponse::new(self.requirements.clone());
+        response.error = error.clone();
+        let header = response.header_value();
+        let message = error.unwrap_or_else(|| "Payment required".to_string());
+        async_graphql::Error::new(message).extend_with(|_, extensions| {
+            extensions.set("code", "PAYMENT_REQUIRED");
+            if let Ok(header) = &header {
+                extensions.set("paymentRequirements", header.as_str());
+            }
+        })
+    }
+}
+
+impl Guard for PaidGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        match self.accept(ctx) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(self.payment_required(None)),
+            Err(e) if e.status_code() == 402 => Err(self.payment_required(Some(e.to_string()))),
+            Err(e) => Err(async_graphql::Error::new(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{decode_requirements_header, encode_payment_header, MemoryLedger, Network};
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Request, Schema};
+
+    fn requirements() -> PaymentRequirements {
+        PaymentRequirements::usdc(Network::Base, "0.05", Address::repeat_byte(0x11), "graphql:Query.report").unwrap()
+    }
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn free(&self) -> &str {
+            "free"
+        }
+
+        #[graphql(guard = "paid(requirements())")]
+        async fn report(&self, ctx: &Context<'_>) -> String {
+            paid_by(ctx).unwrap().to_string()
+        }
+
+        #[graphql(guard = "paid(forecast_requirements())")]
+        async fn forecast(&self) -> &str {
+            "sunny"
+        }
+    }
+
+    fn forecast_requirements() -> PaymentRequirements {
+        PaymentRequirements::usdc(Network::Base, "0.05", Address::repeat_byte(0x11), "graphql:Query.forecast").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_paid_field() {
+        let ledger: Arc<dyn PaymentLedger> = Arc::new(MemoryLedger::new());
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription).data(ledger).finish();
+        let run = |payment: Option<String>| {
+            let schema = schema.clone();
+            async move { schema.execute(Request::new("{ free report }").data(X402Request::new(payment))).await }
+        };
+
+        let unpaid = run(None).await;
+        let error = &unpaid.errors[0];
+        assert_eq!(error.message, "Payment required");
+        let extensions = serde_json::to_value(error.extensions.as_ref().unwrap()).unwrap();
+        assert_eq!(extensions["code"], "PAYMENT_REQUIRED");
+        let quoted = decode_requirements_header(extensions["paymentRequirements"].as_str().unwrap()).unwrap();
+        assert_eq!(quoted.amount, requirements().amount);
+
+        let signer = TestSigner::default();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let header = encode_payment_header(&signer.pay(&requirements(), now).unwrap()).unwrap();
+        let paid = run(Some(header.clone())).await;
+        assert!(paid.errors.is_empty(), "{:?}", paid.errors);
+        let data = paid.data.into_json().unwrap();
+        assert_eq!(data["report"], signer.address().to_string());
+        assert_eq!(data["free"], "free");
+
+        let replayed = run(Some(header)).await;
+        assert!(replayed.errors[0].message.contains("Duplicate payment"));
+
+        // One payment doesn't pay for two priced fields, aliased twice or not
+        let header = encode_payment_header(&signer.pay(&requirements(), now).unwrap()).unwrap();
+        let query = "{ a: report b: report forecast }";
+        let both = schema.execute(Request::new(query).data(X402Request::new(Some(header)))).await;
+        assert_eq!(both.errors.len(), 1, "{:?}", both.errors);
+        assert_eq!(both.errors[0].path[0], async_graphql::PathSegment::Field("forecast".to_string()));
+        let data = both.data.into_json().unwrap();
+        assert_eq!(data["a"], data["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_ledger_required() {
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription).finish();
+        let signer = TestSigner::default();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let header = encode_payment_header(&signer.pay(&requirements(), now).unwrap()).unwrap();
+        let response = schema.execute(Request::new("{ report }").data(X402Request::new(Some(header)))).await;
+        assert!(response.errors[0].message.contains("PaymentLedger"));
+    }
+}
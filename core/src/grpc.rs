@@ -0,0 +1,418 @@
+//! tonic interceptors carrying payments in gRPC metadata (feature `grpc`)
+//!
+//! gRPC has no 402, so payments travel in binary metadata instead of HTTP
+//! headers. Servers wrap a service with [`PaymentInterceptor`], which
+//! records accepted payments in a [`PaymentLedger`] to refuse replays:
+//!
+//! ```ignore
+//! let interceptor = PaymentInterceptor::new(requirements, ledger);
+//! Server::builder()
+//!     .layer(GrpcPathLayer)
+//!     .add_service(ReportServer::with_interceptor(service, interceptor))
+//! ```
+//!
+//! Calls without an acceptable payment in [`X402_PAYMENT_METADATA`] fail
+//! with `RESOURCE_EXHAUSTED` (or the code set with
+//! [`PaymentInterceptor::with_code`]), the requirements in
+//! [`X402_REQUIREMENTS_METADATA`]. Handlers read the payer with
+//! [`grpc_payer`].
+//!
+//! Marketplaces serving many tenants route each call with
+//! [`PaymentInterceptor::with_resolver`]: the call's authority and method
+//! (resource `grpc:/package.Service/Method`) pick the recipient and price,
+//! the tenant named in [`X402_TENANT_METADATA`] choosing only among the
+//! routes they select, and [`PaymentInterceptor::with_tenant_ledgers`]
+//! records each tenant's payments separately. tonic interceptors don't see
+//! the request URI, so routing needs [`GrpcPathLayer`] on the server.
+//!
+//! [`PaymentInterceptor::with_events`] reports accepted payments as
+//! [`PaymentEvent`]s.
+//!
+//! Clients read the requirements with [`requirements_from_status`], sign,
+//! and retry through a [`PaymentClientInterceptor`] holding the payment.
+//! Metadata values are the same strings as the HTTP headers; gRPC base64s
+//! `-bin` metadata on the wire.
+
+use crate::events::report;
+use crate::{
+    decode_payment_header, decode_requirements_header, encode_payment_header, verify_payment, LedgerEntry,
+    PaymentEvent, PaymentEvents, PaymentLedger, PaymentRequiredResponse, PaymentRequirements, RecipientResolver,
+    Result, SignedPayment, TenantLedgers, TenantRoute, X402Error,
+};
+use alloy_primitives::Address;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tonic::metadata::MetadataValue;
+use tonic::service::Interceptor;
+use tonic::{Code, Request, Status};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Binary metadata key carrying the payment (an `X-Payment` header value)
+pub const X402_PAYMENT_METADATA: &str = "x-payment-bin";
+
+/// Binary metadata key carrying the requirements (an
+/// `X-Payment-Requirements` header value) on a payment-required status
+pub const X402_REQUIREMENTS_METADATA: &str = "x-payment-requirements-bin";
+
+/// ASCII metadata key naming the tenant a call is for
+pub const X402_TENANT_METADATA: &str = "x-tenant";
+
+/// Payer of an accepted call, stored in the request extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrpcPayer(pub Address);
+
+/// The payer [`PaymentInterceptor`] accepted for `request`
+pub fn grpc_payer<T>(request: &Request<T>) -> Option<Address> {
+    request.extensions().get::<GrpcPayer>().map(|payer| payer.0)
+}
+
+/// Requirements in a payment-required `status`, if it carries any
+pub fn requirements_from_status(status: &Status) -> Option<Result<PaymentRequirements>> {
+    let value = status.metadata().get_bin(X402_REQUIREMENTS_METADATA)?;
+    Some(
+        value
+            .to_bytes()
+            .map_err(|e| X402Error::InvalidHeader(e.to_string()))
+            .and_then(decode_requirements_header),
+    )
+}
+
+/// The tenant route [`PaymentInterceptor`] resolved for `request`
+pub fn grpc_tenant<T>(request: &Request<T>) -> Option<&TenantRoute> {
+    request.extensions().get::<TenantRoute>()
+}
+
+/// Path of a call, `/package.Service/Method`, stored in the request
+/// extensions by [`GrpcPathLayer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcPath(pub String);
+
+/// Authority (`host[:port]`) a call was made to, stored in the request
+/// extensions by [`GrpcPathLayer`] when the URI has one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcAuthority(pub String);
+
+/// Server layer recording the path of each call as a [`GrpcPath`], and its
+/// authority as a [`GrpcAuthority`], for interceptors that need to know
+/// the method called
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcPathLayer;
+
+impl<S> Layer<S> for GrpcPathLayer {
+    type Service = GrpcPathService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcPathService { inner }
+    }
+}
+
+/// Service added by [`GrpcPathLayer`]
+#[derive(Debug, Clone)]
+pub struct GrpcPathService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcPathService<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> S::Future {
+        let path = GrpcPath(request.uri().path().to_string());
+        let authority = request.uri().authority().map(|authority| GrpcAuthority(authority.to_string()));
+        request.extensions_mut().insert(path);
+        if let Some(authority) = authority {
+            request.extensions_mut().insert(authority);
+        }
+        self.inner.call(request)
+    }
+}
+
+/// Server interceptor requiring payment of its requirements on every call
+#[derive(Clone)]
+pub struct PaymentInterceptor {
+    requirements: PaymentRequirements,
+    code: Code,
+    ledger: Arc<dyn PaymentLedger>,
+    resolver: Option<Arc<dyn RecipientResolver>>,
+    tenant_ledgers: Option<Arc<TenantLedgers>>,
+    events: Option<Arc<dyn PaymentEvents>>,
+}
+
+impl PaymentInterceptor {
+    /// Require `requirements` on every call, recording accepted payments in
+    /// `ledger` and refusing replays
+    pub fn new(requirements: PaymentRequirements, ledger: Arc<dyn PaymentLedger>) -> Self {
+        Self {
+            requirements,
+            code: Code::ResourceExhausted,
+            ledger,
+            resolver: None,
+            tenant_ledgers: None,
+            events: None,
+        }
+    }
+
+    /// Status code for calls without an acceptable payment
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Resolve the recipient and price of each call with `resolver`, from
+    /// the call's [`GrpcAuthority`], its [`GrpcPath`] (as resource
+    /// `grpc:<path>`) and the tenant in [`X402_TENANT_METADATA`]; the
+    /// requirements passed to
+    /// [`PaymentInterceptor::new`] supply everything else. Calls no tenant
+    /// serves fail with `NOT_FOUND`, and calls without a [`GrpcPath`] (no
+    /// [`GrpcPathLayer`]) with `INTERNAL`.
+    pub fn with_resolver(mut self, resolver: Arc<dyn RecipientResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Record each resolved tenant's payments in its own ledger, instead of
+    /// the one passed to [`PaymentInterceptor::new`]
+    pub fn with_tenant_ledgers(mut self, ledgers: Arc<TenantLedgers>) -> Self {
+        self.tenant_ledgers = Some(ledgers);
+        self
+    }
+
+    /// Report accepted payments to `events`
+    pub fn with_events(mut self, events: Arc<dyn PaymentEvents>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    fn route(&self, request: &Request<()>) -> Result<Option<(TenantRoute, PaymentRequirements)>> {
+        let Some(resolver) = &self.resolver else {
+            return Ok(None);
+        };
+        let path = request.extensions().get::<GrpcPath>().ok_or_else(|| {
+            X402Error::InvalidConfig("tenant routing needs GrpcPathLayer on the server".to_string())
+        })?;
+        let host = request.extensions().get::<GrpcAuthority>().map(|authority| authority.0.as_str());
+        let tenant = request.metadata().get(X402_TENANT_METADATA).and_then(|value| value.to_str().ok());
+        resolver.requirements(&self.requirements, &format!("grpc:{}", path.0), host, tenant)
+    }
+
+    fn accept(
+        &self,
+        request: &Request<()>,
+        requirements: &PaymentRequirements,
+        route: Option<&TenantRoute>,
+    ) -> Result<Option<Address>> {
+        let Some(value) = request.metadata().get_bin(X402_PAYMENT_METADATA) else {
+            return Ok(None);
+        };
+        let bytes = value.to_bytes().map_err(|e| X402Error::InvalidHeader(e.to_string()))?;
+        let payment = decode_payment_header(bytes)?;
+        let payer = verify_payment(&payment, requirements)?;
+        let ledger = match (&self.tenant_ledgers, route) {
+            (Some(ledgers), Some(route)) => ledgers.ledger(&route.tenant)?,
+            _ => self.ledger.clone(),
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        ledger.record(&LedgerEntry::new(&payment.payment, now))?;
+        report(self.events.as_ref(), PaymentEvent::verified(&payment.payment));
+        Ok(Some(payer))
+    }
+
+    fn payment_required(&self, requirements: &PaymentRequirements, error: Option<String>) -> Status {
+        let mut response = PaymentRequiredResponse::new(requirements.clone());
+        response.error = error.clone();
+        let mut status = Status::new(self.code, error.unwrap_or_else(|| "Payment required".to_string()));
+        if let Ok(header) = response.header_value() {
+            status
+                .metadata_mut()
+                .insert_bin(X402_REQUIREMENTS_METADATA, MetadataValue::from_bytes(header.as_bytes()));
+        }
+        status
+    }
+}
+
+impl Interceptor for PaymentInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> std::result::Result<Request<()>, Status> {
+        let (route, requirements) = match self.route(&request) {
+            Ok(Some((route, requirements))) => (Some(route), requirements),
+            Ok(None) if self.resolver.is_some() => return Err(Status::not_found("no tenant serves this call")),
+            Ok(None) => (None, self.requirements.clone()),
+            Err(e) => return Err(Status::internal(e.to_string())),
+        };
+        match self.accept(&request, &requirements, route.as_ref()) {
+            Ok(Some(payer)) => {
+                request.extensions_mut().insert(GrpcPayer(payer));
+                if let Some(route) = route {
+                    request.extensions_mut().insert(route);
+                }
+                Ok(request)
+            }
+            Ok(None) => Err(self.payment_required(&requirements, None)),
+            Err(e) if e.status_code() == 402 => Err(self.payment_required(&requirements, Some(e.to_string()))),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+}
+
+/// Client interceptor attaching a payment to the next call
+///
+/// Clones share the pending payment, so keep one with the client and call
+/// [`pay_with`](Self::pay_with) before retrying a payment-required call.
+#[derive(Debug, Clone, Default)]
+pub struct PaymentClientInterceptor {
+    pending: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl PaymentClientInterceptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach `payment` to the next call only
+    pub fn pay_with(&self, payment: &SignedPayment) -> Result<()> {
+        let header = encode_payment_header(payment)?;
+        *self.pending.lock().unwrap() = Some(header.into_bytes());
+        Ok(())
+    }
+}
+
+impl Interceptor for PaymentClientInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> std::result::Result<Request<()>, Status> {
+        if let Some(payment) = self.pending.lock().unwrap().take() {
+            request.metadata_mut().insert_bin(X402_PAYMENT_METADATA, MetadataValue::from_bytes(&payment));
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{RecordingEvents, TestSigner};
+    use crate::{MemoryLedger, Network, ResourceMatcher, RouteTable};
+    use alloy_primitives::U256;
+
+    #[test]
+    fn test_interceptors() {
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.05", Address::repeat_byte(0x11), "grpc:/reports.Reports/Get")
+                .unwrap();
+        let events = Arc::new(RecordingEvents::new());
+        let mut server =
+            PaymentInterceptor::new(requirements.clone(), Arc::new(MemoryLedger::new())).with_events(events.clone());
+        let mut client = PaymentClientInterceptor::new();
+
+        let unpaid = server.call(client.call(Request::new(())).unwrap()).unwrap_err();
+        assert_eq!(unpaid.code(), Code::ResourceExhausted);
+        assert_eq!(unpaid.message(), "Payment required");
+        assert_eq!(requirements_from_status(&unpaid).unwrap().unwrap().amount, requirements.amount);
+
+        let signer = TestSigner::default();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let payment = signer.pay(&requirements, now).unwrap();
+        client.pay_with(&payment).unwrap();
+        let request = client.call(Request::new(())).unwrap();
+        assert!(client.call(Request::new(())).unwrap().metadata().get_bin(X402_PAYMENT_METADATA).is_none());
+        let accepted = server.call(request).unwrap();
+        assert_eq!(grpc_payer(&accepted), Some(signer.address()));
+        assert_eq!(events.events(), vec![PaymentEvent::verified(&payment.payment)]);
+
+        client.pay_with(&payment).unwrap();
+        let mut strict = server.clone().with_code(Code::FailedPrecondition);
+        let replayed = strict.call(client.call(Request::new(())).unwrap()).unwrap_err();
+        assert_eq!(replayed.code(), Code::FailedPrecondition);
+        assert!(replayed.message().contains("Duplicate payment"));
+        assert!(requirements_from_status(&replayed).is_some());
+        assert!(requirements_from_status(&Status::internal("boom")).is_none());
+        assert_eq!(events.events().len(), 1);
+    }
+
+    #[test]
+    fn test_tenant_routing() {
+        let template = PaymentRequirements::usdc(Network::Base, "0.05", Address::ZERO, "grpc:/").unwrap();
+        let tenant = |name: &str, byte: u8| TenantRoute {
+            tenant: name.to_string(),
+            recipient: Address::repeat_byte(byte),
+            amount: U256::from(byte),
+        };
+        let table = RouteTable::new()
+            .route(ResourceMatcher::Prefix("grpc:/market.Data".to_string()), tenant("acme", 0xaa))
+            .route(ResourceMatcher::Prefix("grpc:/market.Data".to_string()), tenant("globex", 0xbb))
+            .route(ResourceMatcher::Prefix("grpc:/market.Quotes".to_string()), tenant("initech", 0xcc));
+        let ledgers = Arc::new(TenantLedgers::new());
+        let mut server = PaymentInterceptor::new(template, Arc::new(MemoryLedger::new()))
+            .with_resolver(Arc::new(table))
+            .with_tenant_ledgers(ledgers.clone());
+        let call = |path: &str, tenant: Option<&str>| {
+            let mut request = Request::new(());
+            request.extensions_mut().insert(GrpcPath(path.to_string()));
+            if let Some(tenant) = tenant {
+                request.metadata_mut().insert(X402_TENANT_METADATA, tenant.parse().unwrap());
+            }
+            request
+        };
+        let for_tenant = |name: &str| call("/market.Data/Get", Some(name));
+
+        // The method called picks the route
+        let quotes = requirements_from_status(&server.call(call("/market.Quotes/Get", None)).unwrap_err());
+        let quotes = quotes.unwrap().unwrap();
+        assert_eq!(quotes.recipient, Address::repeat_byte(0xcc));
+        assert_eq!(quotes.resource, "grpc:/market.Quotes/Get");
+        assert_eq!(server.call(call("/market.News/Get", None)).unwrap_err().code(), Code::NotFound);
+        assert_eq!(server.call(Request::new(())).unwrap_err().code(), Code::Internal);
+
+        let unpaid = server.call(for_tenant("globex")).unwrap_err();
+        let requirements = requirements_from_status(&unpaid).unwrap().unwrap();
+        assert_eq!((requirements.recipient, requirements.amount), (Address::repeat_byte(0xbb), U256::from(0xbb)));
+        assert_eq!(server.call(for_tenant("initech")).unwrap_err().code(), Code::NotFound);
+
+        let signer = TestSigner::default();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let payment = signer.pay(&requirements, now).unwrap();
+        let mut request = for_tenant("globex");
+        request.metadata_mut().insert_bin(
+            X402_PAYMENT_METADATA,
+            MetadataValue::from_bytes(encode_payment_header(&payment).unwrap().as_bytes()),
+        );
+        let accepted = server.call(request).unwrap();
+        assert_eq!(grpc_tenant(&accepted).map(|route| route.tenant.as_str()), Some("globex"));
+        assert_eq!(ledgers.tenants(), vec!["globex"]);
+        assert_eq!(ledgers.ledger("globex").unwrap().query(&Default::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_path_layer() {
+        struct Capture;
+
+        impl Service<http::Request<()>> for Capture {
+            type Response = (Option<GrpcPath>, Option<GrpcAuthority>);
+            type Error = std::convert::Infallible;
+            type Future = std::future::Ready<std::result::Result<Self::Response, Self::Error>>;
+
+            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, request: http::Request<()>) -> Self::Future {
+                let extensions = request.extensions();
+                std::future::ready(Ok((extensions.get().cloned(), extensions.get().cloned())))
+            }
+        }
+
+        let request = http::Request::builder().uri("http://localhost:50051/market.Data/Get").body(()).unwrap();
+        let (path, authority) = GrpcPathLayer.layer(Capture).call(request).into_inner().unwrap();
+        assert_eq!(path, Some(GrpcPath("/market.Data/Get".to_string())));
+        assert_eq!(authority, Some(GrpcAuthority("localhost:50051".to_string())));
+    }
+}
@@ -0,0 +1,153 @@
+//! Idempotent handling of retried purchases
+//!
+//! A client that times out waiting for a paid response retries, typically
+//! with a fresh payment. If both payments carry the same
+//! [`invoice_id`](crate::PaymentPayload::invoice_id), an
+//! [`IdempotencyStore`] lets the server recognise the retry and replay the
+//! response it already produced instead of charging and executing again.
+//!
+//! Invoice IDs are scoped to the payer, so one payer can't claim another's
+//! response by guessing its invoice ID.
+
+use crate::PaymentPayload;
+use alloy_primitives::Address;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// What to do with a payment, according to its invoice ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Idempotency<R> {
+    /// First attempt, or the payment has no invoice ID: charge and execute,
+    /// then call [`IdempotencyStore::complete`] (or
+    /// [`IdempotencyStore::abandon`] on failure)
+    Proceed,
+    /// Another attempt is still being handled; ask the client to retry later
+    InProgress,
+    /// Already handled: return this response without charging
+    Completed(R),
+}
+
+#[derive(Debug)]
+enum Slot<R> {
+    InProgress { started_at: u64 },
+    Completed { response: R, completed_at: u64 },
+}
+
+/// In-process map from invoice IDs to the responses already returned
+///
+/// Completed entries are kept for `ttl_seconds`, and in-progress entries
+/// are reclaimed after the same time in case their handler died.
+#[derive(Debug)]
+pub struct IdempotencyStore<R> {
+    ttl_seconds: u64,
+    slots: Mutex<HashMap<(Address, String), Slot<R>>>,
+}
+
+impl<R: Clone> IdempotencyStore<R> {
+    pub fn new(ttl_seconds: u64) -> Self {
+        Self { ttl_seconds, slots: Mutex::new(HashMap::new()) }
+    }
+
+    /// Look up `payment`'s invoice ID as of the unix time `now`, claiming
+    /// it if no attempt is on record
+    pub fn begin(&self, payment: &PaymentPayload, now: u64) -> Idempotency<R> {
+        let Some(invoice_id) = &payment.invoice_id else {
+            return Idempotency::Proceed;
+        };
+        let mut slots = self.slots.lock().unwrap();
+        let key = (payment.payer, invoice_id.clone());
+        match slots.get(&key) {
+            Some(Slot::Completed { response, completed_at }) if !self.expired(*completed_at, now) => {
+                Idempotency::Completed(response.clone())
+            }
+            Some(Slot::InProgress { started_at }) if !self.expired(*started_at, now) => Idempotency::InProgress,
+            _ => {
+                slots.insert(key, Slot::InProgress { started_at: now });
+                Idempotency::Proceed
+            }
+        }
+    }
+
+    /// Store the response to replay for retries of `payment`
+    pub fn complete(&self, payment: &PaymentPayload, response: R, now: u64) {
+        if let Some(invoice_id) = &payment.invoice_id {
+            let mut slots = self.slots.lock().unwrap();
+            slots.insert((payment.payer, invoice_id.clone()), Slot::Completed { response, completed_at: now });
+        }
+    }
+
+    /// Release `payment`'s invoice ID after a failed attempt, so a retry
+    /// can proceed
+    pub fn abandon(&self, payment: &PaymentPayload) {
+        if let Some(invoice_id) = &payment.invoice_id {
+            let mut slots = self.slots.lock().unwrap();
+            let key = (payment.payer, invoice_id.clone());
+            if matches!(slots.get(&key), Some(Slot::InProgress { .. })) {
+                slots.remove(&key);
+            }
+        }
+    }
+
+    /// Drop entries older than the TTL as of `now`
+    pub fn purge(&self, now: u64) {
+        let mut slots = self.slots.lock().unwrap();
+        slots.retain(|_, slot| match slot {
+            Slot::InProgress { started_at: at } | Slot::Completed { completed_at: at, .. } => !self.expired(*at, now),
+        });
+    }
+
+    fn expired(&self, at: u64, now: u64) -> bool {
+        now.saturating_sub(at) >= self.ttl_seconds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+    use alloy_primitives::U256;
+
+    fn payment(nonce: u64, invoice_id: Option<&str>) -> PaymentPayload {
+        PaymentPayload {
+            amount: U256::from(1000),
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: None,
+            resource: "/api/report".to_string(),
+            nonce,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: invoice_id.map(str::to_string),
+            splits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_retries_replay_response() {
+        let store = IdempotencyStore::new(60);
+        let first = payment(1, Some("inv-7"));
+        let retry = payment(2, Some("inv-7"));
+
+        assert_eq!(store.begin(&first, 100), Idempotency::Proceed);
+        assert_eq!(store.begin(&retry, 101), Idempotency::InProgress);
+        store.complete(&first, "report", 102);
+        assert_eq!(store.begin(&retry, 103), Idempotency::Completed("report"));
+
+        let other_payer = PaymentPayload { payer: Address::repeat_byte(0x33), ..retry.clone() };
+        assert_eq!(store.begin(&other_payer, 103), Idempotency::Proceed);
+        assert_eq!(store.begin(&payment(3, None), 103), Idempotency::Proceed);
+
+        store.abandon(&other_payer);
+        assert_eq!(store.begin(&other_payer, 104), Idempotency::Proceed);
+        assert_eq!(store.begin(&retry, 162), Idempotency::Proceed);
+    }
+
+    #[test]
+    fn test_invoice_id_is_signed() {
+        let plain = payment(1, None);
+        assert_ne!(plain.message_hash(), payment(1, Some("inv-7")).message_hash());
+        assert!(!serde_json::to_string(&plain).unwrap().contains("invoice_id"));
+    }
+}
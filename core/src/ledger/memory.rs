@@ -0,0 +1,103 @@
+//! In-process ledger backend
+
+use super::{LedgerEntry, LedgerQuery, PaymentLedger};
+use crate::{Result, X402Error};
+use alloy_primitives::Address;
+use std::sync::RwLock;
+
+/// [`PaymentLedger`] kept in memory, for tests, demos and single-process
+/// servers that don't need records to survive a restart
+#[derive(Debug, Default)]
+pub struct MemoryLedger {
+    entries: RwLock<Vec<LedgerEntry>>,
+}
+
+impl MemoryLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn same_payment(entry: &LedgerEntry, chain_id: u64, payer: Address, nonce: u64) -> bool {
+    entry.chain_id == chain_id && entry.payer == payer && entry.nonce == nonce
+}
+
+impl PaymentLedger for MemoryLedger {
+    fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        if entries.iter().any(|e| same_payment(e, entry.chain_id, entry.payer, entry.nonce)) {
+            return Err(X402Error::DuplicatePayment(format!(
+                "payer {} nonce {} on chain {}",
+                entry.payer, entry.nonce, entry.chain_id
+            )));
+        }
+        // Keep entries ordered by recorded_at, after equal timestamps
+        let at = entries.partition_point(|e| e.recorded_at <= entry.recorded_at);
+        entries.insert(at, entry.clone());
+        Ok(())
+    }
+
+    fn query(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        let entries = self.entries.read().unwrap();
+        Ok(entries.iter().filter(|e| query.matches(e)).cloned().collect())
+    }
+
+    fn mark_settled(&self, chain_id: u64, payer: Address, nonce: u64, tx_hash: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.iter_mut()
+            .find(|e| same_payment(e, chain_id, payer, nonce))
+            .ok_or_else(|| X402Error::Storage(format!(
+                "no recorded payment for payer {} nonce {} on chain {}",
+                payer, nonce, chain_id
+            )))?;
+        entry.settlement_tx = Some(tx_hash.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PaymentPayload, Scheme};
+    use alloy_primitives::U256;
+
+    fn entry(nonce: u64, recorded_at: u64) -> LedgerEntry {
+        let payload = PaymentPayload {
+            amount: U256::from(5),
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: None,
+            resource: "/a".to_string(),
+            nonce,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        LedgerEntry::new(&payload, recorded_at)
+    }
+
+    #[test]
+    fn test_memory_ledger() {
+        let ledger = MemoryLedger::new();
+        ledger.record(&entry(2, 200)).unwrap();
+        ledger.record(&entry(1, 100)).unwrap();
+        assert!(matches!(ledger.record(&entry(1, 300)), Err(X402Error::DuplicatePayment(_))));
+        assert_eq!(ledger.query(&LedgerQuery::default()).unwrap(), vec![entry(1, 100), entry(2, 200)]);
+
+        ledger.mark_settled(8453, Address::repeat_byte(0x22), 2, "0xabc").unwrap();
+        let unsettled = ledger.query(&LedgerQuery { settled: Some(false), ..Default::default() }).unwrap();
+        assert_eq!(unsettled, vec![entry(1, 100)]);
+        assert!(ledger.mark_settled(8453, Address::ZERO, 2, "0xabc").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Durable records of verified payments
+//!
+//! [`PaymentLedger`] is the storage interface servers use to record
+//! accepted payments, look them up for reconciliation, and track settlement.
+//! Backends:
+//! - [`MemoryLedger`]
+//! - [`SqliteLedger`] (feature `sqlite`)
+//! - [`PostgresLedger`] (feature `postgres`), implementing
+//!   [`AsyncPaymentLedger`]
+//! - [`SledStore`] (feature `sled`), which is also a
+//!   [`NonceStore`](crate::NonceStore)
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sled")]
+mod sled;
+
+pub use memory::MemoryLedger;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteLedger;
+#[cfg(feature = "postgres")]
+pub use postgres::{PostgresLedger, MIGRATOR as POSTGRES_MIGRATOR};
+#[cfg(feature = "sled")]
+pub use self::sled::SledStore;
+
+use crate::{PaymentPayload, Result};
+use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// A payment recorded in the ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    pub payer: Address,
+    pub recipient: Address,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    pub chain_id: u64,
+    pub token: Option<Address>,
+    pub resource: String,
+    pub nonce: u64,
+    pub expires_at: u64,
+    /// When the payment was recorded (unix timestamp)
+    pub recorded_at: u64,
+    /// Settlement transaction hash, once settled
+    pub settlement_tx: Option<String>,
+}
+
+impl LedgerEntry {
+    pub fn new(payment: &PaymentPayload, recorded_at: u64) -> Self {
+        Self {
+            payer: payment.payer,
+            recipient: payment.recipient,
+            amount: payment.amount,
+            chain_id: payment.chain_id,
+            token: payment.token,
+            resource: payment.resource.clone(),
+            nonce: payment.nonce,
+            expires_at: payment.expires_at,
+            recorded_at,
+            settlement_tx: None,
+        }
+    }
+}
+
+/// Filter for [`PaymentLedger::query`]; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct LedgerQuery {
+    pub payer: Option<Address>,
+    pub resource: Option<String>,
+    /// Inclusive lower bound on `recorded_at`
+    pub from: Option<u64>,
+    /// Exclusive upper bound on `recorded_at`
+    pub until: Option<u64>,
+    /// Only entries that have (`Some(true)`) or have not been settled
+    pub settled: Option<bool>,
+}
+
+impl LedgerQuery {
+    pub fn matches(&self, entry: &LedgerEntry) -> bool {
+        self.payer.is_none_or(|p| p == entry.payer)
+            && self.resource.as_ref().is_none_or(|r| *r == entry.resource)
+            && self.from.is_none_or(|t| entry.recorded_at >= t)
+            && self.until.is_none_or(|t| entry.recorded_at < t)
+            && self.settled.is_none_or(|s| s == entry.settlement_tx.is_some())
+    }
+}
+
+/// Storage for verified payments
+///
+/// A payment is identified by `(chain_id, payer, nonce)`; recording the
+/// same payment twice fails with [`X402Error::DuplicatePayment`].
+///
+/// [`X402Error::DuplicatePayment`]: crate::X402Error::DuplicatePayment
+pub trait PaymentLedger: Send + Sync {
+    /// Record a verified payment
+    fn record(&self, entry: &LedgerEntry) -> Result<()>;
+
+    /// Entries matching `query`, ordered by `recorded_at`
+    fn query(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>>;
+
+    /// Attach a settlement transaction to a recorded payment
+    fn mark_settled(&self, chain_id: u64, payer: Address, nonce: u64, tx_hash: &str) -> Result<()>;
+}
+
+/// Async counterpart of [`PaymentLedger`] for network-backed stores
+///
+/// Every [`PaymentLedger`] is also an `AsyncPaymentLedger`, so async
+/// servers can be written against this trait alone.
+pub trait AsyncPaymentLedger: Send + Sync {
+    fn record(&self, entry: &LedgerEntry) -> impl Future<Output = Result<()>> + Send;
+
+    fn query(&self, query: &LedgerQuery) -> impl Future<Output = Result<Vec<LedgerEntry>>> + Send;
+
+    fn mark_settled(
+        &self,
+        chain_id: u64,
+        payer: Address,
+        nonce: u64,
+        tx_hash: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<L: PaymentLedger> AsyncPaymentLedger for L {
+    async fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        PaymentLedger::record(self, entry)
+    }
+
+    async fn query(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        PaymentLedger::query(self, query)
+    }
+
+    async fn mark_settled(&self, chain_id: u64, payer: Address, nonce: u64, tx_hash: &str) -> Result<()> {
+        PaymentLedger::mark_settled(self, chain_id, payer, nonce, tx_hash)
+    }
+}
@@ -0,0 +1,210 @@
+//! PostgreSQL ledger backend (sqlx)
+
+use super::{AsyncPaymentLedger, LedgerEntry, LedgerQuery};
+use crate::{Result, X402Error};
+use alloy_primitives::{Address, U256};
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::Row;
+use std::str::FromStr;
+
+/// Embedded schema migrations (`core/migrations/postgres`)
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// [`AsyncPaymentLedger`] stored in PostgreSQL
+///
+/// Safe to share between server instances: the unique index on
+/// `(payer, nonce, chain_id)` makes concurrent inserts of the same payment
+/// fail with [`X402Error::DuplicatePayment`], so the ledger doubles as
+/// replay protection.
+#[derive(Clone)]
+pub struct PostgresLedger {
+    pool: PgPool,
+}
+
+impl PostgresLedger {
+    /// Connect to `url` and run pending migrations
+    pub async fn connect(url: &str) -> Result<Self> {
+        let pool = PgPool::connect(url).await.map_err(storage_err)?;
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing pool, running pending migrations
+    pub async fn from_pool(pool: PgPool) -> Result<Self> {
+        MIGRATOR.run(&pool).await
+            .map_err(|e| X402Error::Storage(format!("migration failed: {}", e)))?;
+        Ok(Self { pool })
+    }
+
+    /// Use an existing pool whose schema is managed elsewhere
+    pub fn from_pool_unmigrated(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Whether a payment with this identity has already been recorded
+    pub async fn contains(&self, chain_id: u64, payer: Address, nonce: u64) -> Result<bool> {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM x402_payments
+                WHERE payer = $1 AND nonce = $2::numeric AND chain_id = $3)",
+        )
+            .bind(address_to_sql(&payer))
+            .bind(nonce.to_string())
+            .bind(chain_id as i64)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(storage_err)
+    }
+}
+
+impl AsyncPaymentLedger for PostgresLedger {
+    async fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO x402_payments
+                (chain_id, payer, nonce, recipient, amount, token, resource, expires_at, recorded_at, settlement_tx)
+             VALUES ($1, $2, $3::numeric, $4, $5::numeric, $6, $7, $8, $9, $10)",
+        )
+            .bind(entry.chain_id as i64)
+            .bind(address_to_sql(&entry.payer))
+            .bind(entry.nonce.to_string())
+            .bind(address_to_sql(&entry.recipient))
+            .bind(entry.amount.to_string())
+            .bind(entry.token.as_ref().map(address_to_sql))
+            .bind(&entry.resource)
+            .bind(entry.expires_at as i64)
+            .bind(entry.recorded_at as i64)
+            .bind(&entry.settlement_tx)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e.as_database_error() {
+                Some(db) if db.is_unique_violation() => X402Error::DuplicatePayment(format!(
+                    "payer {} nonce {} on chain {}",
+                    entry.payer, entry.nonce, entry.chain_id
+                )),
+                _ => storage_err(e),
+            })?;
+        Ok(())
+    }
+
+    async fn query(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        let rows = sqlx::query(
+            "SELECT chain_id, payer, nonce::text, recipient, amount::text, token, resource,
+                    expires_at, recorded_at, settlement_tx
+             FROM x402_payments
+             WHERE ($1::text IS NULL OR payer = $1)
+               AND ($2::text IS NULL OR resource = $2)
+               AND ($3::bigint IS NULL OR recorded_at >= $3)
+               AND ($4::bigint IS NULL OR recorded_at < $4)
+               AND ($5::bool IS NULL OR (settlement_tx IS NOT NULL) = $5)
+             ORDER BY recorded_at, id",
+        )
+            .bind(query.payer.as_ref().map(address_to_sql))
+            .bind(&query.resource)
+            .bind(query.from.map(|t| t as i64))
+            .bind(query.until.map(|t| t as i64))
+            .bind(query.settled)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_err)?;
+
+        rows.iter().map(row_to_entry).collect()
+    }
+
+    async fn mark_settled(&self, chain_id: u64, payer: Address, nonce: u64, tx_hash: &str) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE x402_payments SET settlement_tx = $4
+             WHERE payer = $2 AND nonce = $3::numeric AND chain_id = $1",
+        )
+            .bind(chain_id as i64)
+            .bind(address_to_sql(&payer))
+            .bind(nonce.to_string())
+            .bind(tx_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_err)?;
+
+        if result.rows_affected() == 0 {
+            return Err(X402Error::Storage(format!(
+                "no recorded payment for payer {} nonce {} on chain {}",
+                payer, nonce, chain_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn row_to_entry(row: &PgRow) -> Result<LedgerEntry> {
+    let get_err = |e: sqlx::Error| X402Error::Storage(e.to_string());
+    let nonce: String = row.try_get(2).map_err(get_err)?;
+    let amount: String = row.try_get(4).map_err(get_err)?;
+    let token: Option<String> = row.try_get(5).map_err(get_err)?;
+
+    Ok(LedgerEntry {
+        chain_id: row.try_get::<i64, _>(0).map_err(get_err)? as u64,
+        payer: address_from_sql(&row.try_get::<String, _>(1).map_err(get_err)?)?,
+        nonce: nonce.parse()
+            .map_err(|_| X402Error::Storage(format!("invalid nonce: {}", nonce)))?,
+        recipient: address_from_sql(&row.try_get::<String, _>(3).map_err(get_err)?)?,
+        amount: U256::from_str(&amount).map_err(|e| X402Error::Storage(e.to_string()))?,
+        token: token.as_deref().map(address_from_sql).transpose()?,
+        resource: row.try_get(6).map_err(get_err)?,
+        expires_at: row.try_get::<i64, _>(7).map_err(get_err)? as u64,
+        recorded_at: row.try_get::<i64, _>(8).map_err(get_err)? as u64,
+        settlement_tx: row.try_get(9).map_err(get_err)?,
+    })
+}
+
+fn address_to_sql(address: &Address) -> String {
+    format!("{:#x}", address)
+}
+
+fn address_from_sql(value: &str) -> Result<Address> {
+    Address::from_str(value).map_err(|e| X402Error::Storage(format!("invalid address {}: {}", value, e)))
+}
+
+fn storage_err(e: sqlx::Error) -> X402Error {
+    X402Error::Storage(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PaymentPayload, Scheme};
+
+    /// Runs against `X402_TEST_POSTGRES_URL` when set; skipped otherwise
+    #[tokio::test]
+    async fn test_postgres_record_and_replay() {
+        let Ok(url) = std::env::var("X402_TEST_POSTGRES_URL") else {
+            return;
+        };
+        let ledger = PostgresLedger::connect(&url).await.unwrap();
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let payer = Address::left_padding_from(&nanos.to_be_bytes()[4..]);
+        let entry = LedgerEntry::new(&PaymentPayload {
+            amount: U256::MAX,
+            recipient: Address::repeat_byte(0x11),
+            payer,
+            chain_id: 8453,
+            token: None,
+            resource: "/api/data".to_string(),
+            nonce: u64::MAX,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        }, 1700000000);
+
+        ledger.record(&entry).await.unwrap();
+        assert!(ledger.contains(8453, payer, u64::MAX).await.unwrap());
+        assert!(matches!(ledger.record(&entry).await, Err(X402Error::DuplicatePayment(_))));
+
+        ledger.mark_settled(8453, payer, u64::MAX, "0xabc").await.unwrap();
+        let found = ledger.query(&LedgerQuery { payer: Some(payer), ..Default::default() }).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].amount, U256::MAX);
+        assert_eq!(found[0].settlement_tx.as_deref(), Some("0xabc"));
+    }
+}
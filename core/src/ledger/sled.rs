@@ -0,0 +1,253 @@
+//! sled ledger and nonce backend
+
+use super::{LedgerEntry, LedgerQuery, PaymentLedger};
+use crate::{NonceStore, Result, X402Error};
+use ::sled::transaction::{ConflictableTransactionError, TransactionError};
+use ::sled::Transactional;
+use alloy_primitives::Address;
+use std::ops::Bound;
+use std::path::Path;
+
+/// `chain_id ++ payer ++ nonce`, big-endian so keys sort numerically
+type PaymentKey = [u8; 36];
+
+fn payment_key(chain_id: u64, payer: Address, nonce: u64) -> PaymentKey {
+    let mut key = [0u8; 36];
+    key[..8].copy_from_slice(&chain_id.to_be_bytes());
+    key[8..28].copy_from_slice(payer.as_slice());
+    key[28..].copy_from_slice(&nonce.to_be_bytes());
+    key
+}
+
+/// [`PaymentLedger`] and [`NonceStore`] in an embedded sled database, for
+/// edge deployments that need records to survive a restart without
+/// running Redis or Postgres
+///
+/// Ledger entries are kept as JSON, with an index on `recorded_at` for
+/// ordered queries. sled flushes to disk in the background (every 500ms by
+/// default); call [`SledStore::flush`] where a write must be durable
+/// before answering.
+pub struct SledStore {
+    db: ::sled::Db,
+    payments: ::sled::Tree,
+    by_time: ::sled::Tree,
+    nonces: ::sled::Tree,
+}
+
+impl SledStore {
+    /// Open (or create) a database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_db(::sled::open(path).map_err(storage_err)?)
+    }
+
+    /// Create a store in a temporary database, deleted when dropped
+    pub fn temporary() -> Result<Self> {
+        Self::from_db(::sled::Config::new().temporary(true).open().map_err(storage_err)?)
+    }
+
+    /// Use an already open database, keeping the trees under `x402_`
+    pub fn from_db(db: ::sled::Db) -> Result<Self> {
+        Ok(Self {
+            payments: db.open_tree("x402_payments").map_err(storage_err)?,
+            by_time: db.open_tree("x402_payments_by_time").map_err(storage_err)?,
+            nonces: db.open_tree("x402_nonces").map_err(storage_err)?,
+            db,
+        })
+    }
+
+    /// Write everything recorded so far to disk
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush().map_err(storage_err)?;
+        Ok(())
+    }
+
+    /// Look up a single payment by its identity
+    pub fn get(&self, chain_id: u64, payer: Address, nonce: u64) -> Result<Option<LedgerEntry>> {
+        self.payments.get(payment_key(chain_id, payer, nonce)).map_err(storage_err)?.map(|v| decode(&v)).transpose()
+    }
+
+    /// Forget nonces of payments that expired before unix time `now`
+    pub fn purge_nonces(&self, now: u64) -> Result<()> {
+        for item in self.nonces.iter() {
+            let (key, expires_at) = item.map_err(storage_err)?;
+            if read_u64(&expires_at)? < now {
+                self.nonces.remove(key).map_err(storage_err)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PaymentLedger for SledStore {
+    fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        let key = payment_key(entry.chain_id, entry.payer, entry.nonce);
+        let value = serde_json::to_vec(entry).map_err(X402Error::Json)?;
+        let mut index = entry.recorded_at.to_be_bytes().to_vec();
+        index.extend_from_slice(&key);
+        // One transaction, so an entry is never left out of the index
+        let result = (&self.payments, &self.by_time).transaction(|(payments, by_time)| {
+            if payments.get(key)?.is_some() {
+                return Err(ConflictableTransactionError::Abort(()));
+            }
+            payments.insert(&key[..], value.as_slice())?;
+            by_time.insert(index.as_slice(), &[][..])?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => Ok(()),
+            Err(TransactionError::Abort(())) => Err(X402Error::DuplicatePayment(format!(
+                "payer {} nonce {} on chain {}",
+                entry.payer, entry.nonce, entry.chain_id
+            ))),
+            Err(TransactionError::Storage(e)) => Err(storage_err(e)),
+        }
+    }
+
+    fn query(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        let from = query.from.map_or(Bound::Unbounded, |t| Bound::Included(t.to_be_bytes().to_vec()));
+        let until = query.until.map_or(Bound::Unbounded, |t| Bound::Excluded(t.to_be_bytes().to_vec()));
+        let mut entries = Vec::new();
+        for item in self.by_time.range::<Vec<u8>, _>((from, until)) {
+            let (index, _) = item.map_err(storage_err)?;
+            let Some(value) = self.payments.get(&index[8..]).map_err(storage_err)? else {
+                continue;
+            };
+            let entry = decode(&value)?;
+            if query.matches(&entry) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn mark_settled(&self, chain_id: u64, payer: Address, nonce: u64, tx_hash: &str) -> Result<()> {
+        let key = payment_key(chain_id, payer, nonce);
+        loop {
+            let Some(current) = self.payments.get(key).map_err(storage_err)? else {
+                return Err(X402Error::Storage(format!(
+                    "no recorded payment for payer {} nonce {} on chain {}",
+                    payer, nonce, chain_id
+                )));
+            };
+            let mut entry = decode(&current)?;
+            entry.settlement_tx = Some(tx_hash.to_string());
+            let updated = serde_json::to_vec(&entry).map_err(X402Error::Json)?;
+            // Retry if another writer changed the entry in between
+            if self.payments.compare_and_swap(key, Some(current), Some(updated)).map_err(storage_err)?.is_ok() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl NonceStore for SledStore {
+    fn claim(&self, chain_id: u64, payer: Address, nonce: u64, expires_at: u64, _now: u64) -> Result<()> {
+        let swapped = self
+            .nonces
+            .compare_and_swap(payment_key(chain_id, payer, nonce), None::<&[u8]>, Some(&expires_at.to_be_bytes()))
+            .map_err(storage_err)?;
+        if swapped.is_err() {
+            return Err(X402Error::DuplicatePayment(format!(
+                "payer {} nonce {} on chain {}",
+                payer, nonce, chain_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for SledStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SledStore")
+            .field("payments", &self.payments.len())
+            .field("nonces", &self.nonces.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn decode(value: &[u8]) -> Result<LedgerEntry> {
+    serde_json::from_slice(value).map_err(|e| X402Error::Storage(format!("invalid ledger entry: {}", e)))
+}
+
+fn read_u64(value: &[u8]) -> Result<u64> {
+    let bytes = value.try_into().map_err(|_| X402Error::Storage("invalid nonce expiry".to_string()))?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn storage_err(e: ::sled::Error) -> X402Error {
+    X402Error::Storage(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PaymentPayload, Scheme};
+    use alloy_primitives::U256;
+
+    fn entry(nonce: u64, resource: &str, recorded_at: u64) -> LedgerEntry {
+        let payload = PaymentPayload {
+            amount: U256::MAX,
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: Some(Address::repeat_byte(0x33)),
+            resource: resource.to_string(),
+            nonce,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        LedgerEntry::new(&payload, recorded_at)
+    }
+
+    #[test]
+    fn test_record_query_and_settle() {
+        let store = SledStore::temporary().unwrap();
+        store.record(&entry(u64::MAX, "/a", 300)).unwrap();
+        store.record(&entry(1, "/a", 100)).unwrap();
+        store.record(&entry(2, "/b", 200)).unwrap();
+        assert!(matches!(store.record(&entry(1, "/other", 101)), Err(X402Error::DuplicatePayment(_))));
+
+        let all = store.query(&LedgerQuery::default()).unwrap();
+        assert_eq!(all, vec![entry(1, "/a", 100), entry(2, "/b", 200), entry(u64::MAX, "/a", 300)]);
+        let query = LedgerQuery { resource: Some("/a".to_string()), from: Some(150), ..Default::default() };
+        assert_eq!(store.query(&query).unwrap(), vec![entry(u64::MAX, "/a", 300)]);
+        let query = LedgerQuery { until: Some(200), ..Default::default() };
+        assert_eq!(store.query(&query).unwrap(), vec![entry(1, "/a", 100)]);
+
+        store.mark_settled(8453, Address::repeat_byte(0x22), 2, "0xabc").unwrap();
+        let settled = store.query(&LedgerQuery { settled: Some(true), ..Default::default() }).unwrap();
+        assert_eq!(settled.len(), 1);
+        assert_eq!(settled[0].settlement_tx.as_deref(), Some("0xabc"));
+        assert!(store.mark_settled(1, Address::ZERO, 2, "0xabc").is_err());
+        assert!(store.get(8453, Address::repeat_byte(0x22), 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_nonces_persist_across_reopen() {
+        let dir = tempfile::TempDir::new().unwrap();
+        {
+            let store = SledStore::open(dir.path()).unwrap();
+            store.claim(8453, Address::repeat_byte(0x22), 7, 1_000, 900).unwrap();
+            store.claim(8453, Address::repeat_byte(0x22), 8, 2_000, 900).unwrap();
+            store.record(&entry(7, "/a", 900)).unwrap();
+            store.flush().unwrap();
+        }
+        // sled's IO threads can hold the database file, and its lock, for a
+        // moment after the drop; block until they let go
+        let file = std::fs::File::open(dir.path().join("db")).unwrap();
+        fs2::FileExt::lock_exclusive(&file).unwrap();
+        drop(file);
+
+        let store = SledStore::open(dir.path()).unwrap();
+        let replay = store.claim(8453, Address::repeat_byte(0x22), 7, 1_000, 950);
+        assert!(matches!(replay, Err(X402Error::DuplicatePayment(_))));
+        assert_eq!(store.query(&LedgerQuery::default()).unwrap().len(), 1);
+
+        store.purge_nonces(1_500).unwrap();
+        store.claim(8453, Address::repeat_byte(0x22), 7, 1_000, 1_500).unwrap();
+        assert!(store.claim(8453, Address::repeat_byte(0x22), 8, 2_000, 1_500).is_err());
+    }
+}
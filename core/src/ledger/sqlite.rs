@@ -0,0 +1,262 @@
+//! SQLite ledger backend
+
+use super::{LedgerEntry, LedgerQuery, PaymentLedger};
+use crate::{Result, X402Error};
+use alloy_primitives::{Address, U256};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS x402_payments (
+    chain_id      INTEGER NOT NULL,
+    payer         TEXT    NOT NULL,
+    nonce         TEXT    NOT NULL,
+    recipient     TEXT    NOT NULL,
+    amount        TEXT    NOT NULL,
+    token         TEXT,
+    resource      TEXT    NOT NULL,
+    expires_at    INTEGER NOT NULL,
+    recorded_at   INTEGER NOT NULL,
+    settlement_tx TEXT,
+    PRIMARY KEY (chain_id, payer, nonce)
+);
+CREATE INDEX IF NOT EXISTS x402_payments_recorded_at ON x402_payments (recorded_at);
+CREATE INDEX IF NOT EXISTS x402_payments_resource ON x402_payments (resource, recorded_at);
+";
+
+/// [`PaymentLedger`] stored in a SQLite database
+///
+/// Addresses are stored as lowercase hex and amounts/nonces as decimal
+/// strings, since SQLite integers are limited to 64 signed bits.
+pub struct SqliteLedger {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteLedger {
+    /// Open (or create) a ledger database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path).map_err(storage_err)?)
+    }
+
+    /// Create a ledger in a private in-memory database
+    pub fn in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory().map_err(storage_err)?)
+    }
+
+    /// Use an existing connection, creating the schema if needed
+    pub fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA).map_err(storage_err)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+}
+
+impl PaymentLedger for SqliteLedger {
+    fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO x402_payments
+                (chain_id, payer, nonce, recipient, amount, token, resource, expires_at, recorded_at, settlement_tx)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                entry.chain_id as i64,
+                address_to_sql(&entry.payer),
+                entry.nonce.to_string(),
+                address_to_sql(&entry.recipient),
+                entry.amount.to_string(),
+                entry.token.as_ref().map(address_to_sql),
+                entry.resource,
+                entry.expires_at as i64,
+                entry.recorded_at as i64,
+                entry.settlement_tx,
+            ],
+        ).map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => X402Error::DuplicatePayment(format!(
+                "payer {} nonce {} on chain {}",
+                entry.payer, entry.nonce, entry.chain_id
+            )),
+            _ => storage_err(e),
+        })?;
+        Ok(())
+    }
+
+    fn query(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE (?1 IS NULL OR payer = ?1)
+               AND (?2 IS NULL OR resource = ?2)
+               AND (?3 IS NULL OR recorded_at >= ?3)
+               AND (?4 IS NULL OR recorded_at < ?4)
+               AND (?5 IS NULL OR (settlement_tx IS NOT NULL) = ?5)
+             ORDER BY recorded_at",
+            SELECT
+        )).map_err(storage_err)?;
+
+        let rows = stmt.query_map(
+            params![
+                query.payer.as_ref().map(address_to_sql),
+                query.resource,
+                query.from.map(|t| t as i64),
+                query.until.map(|t| t as i64),
+                query.settled,
+            ],
+            read_row,
+        ).map_err(storage_err)?;
+
+        rows.map(|row| row.map_err(storage_err)?.into_entry()).collect()
+    }
+
+    fn mark_settled(&self, chain_id: u64, payer: Address, nonce: u64, tx_hash: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE x402_payments SET settlement_tx = ?4
+             WHERE chain_id = ?1 AND payer = ?2 AND nonce = ?3",
+            params![chain_id as i64, address_to_sql(&payer), nonce.to_string(), tx_hash],
+        ).map_err(storage_err)?;
+
+        if updated == 0 {
+            return Err(X402Error::Storage(format!(
+                "no recorded payment for payer {} nonce {} on chain {}",
+                payer, nonce, chain_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl SqliteLedger {
+    /// Look up a single payment by its identity
+    pub fn get(&self, chain_id: u64, payer: Address, nonce: u64) -> Result<Option<LedgerEntry>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("{} WHERE chain_id = ?1 AND payer = ?2 AND nonce = ?3", SELECT),
+            params![chain_id as i64, address_to_sql(&payer), nonce.to_string()],
+            read_row,
+        )
+            .optional()
+            .map_err(storage_err)?
+            .map(Row::into_entry)
+            .transpose()
+    }
+}
+
+const SELECT: &str = "SELECT chain_id, payer, nonce, recipient, amount, token, resource, expires_at, recorded_at, settlement_tx
+             FROM x402_payments";
+
+/// Raw column values, converted after the statement borrow ends
+struct Row {
+    chain_id: i64,
+    payer: String,
+    nonce: String,
+    recipient: String,
+    amount: String,
+    token: Option<String>,
+    resource: String,
+    expires_at: i64,
+    recorded_at: i64,
+    settlement_tx: Option<String>,
+}
+
+fn read_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Row> {
+    Ok(Row {
+        chain_id: row.get(0)?,
+        payer: row.get(1)?,
+        nonce: row.get(2)?,
+        recipient: row.get(3)?,
+        amount: row.get(4)?,
+        token: row.get(5)?,
+        resource: row.get(6)?,
+        expires_at: row.get(7)?,
+        recorded_at: row.get(8)?,
+        settlement_tx: row.get(9)?,
+    })
+}
+
+impl Row {
+    fn into_entry(self) -> Result<LedgerEntry> {
+        Ok(LedgerEntry {
+            payer: address_from_sql(&self.payer)?,
+            recipient: address_from_sql(&self.recipient)?,
+            amount: U256::from_str(&self.amount).map_err(|e| X402Error::Storage(e.to_string()))?,
+            chain_id: self.chain_id as u64,
+            token: self.token.as_deref().map(address_from_sql).transpose()?,
+            resource: self.resource,
+            nonce: self.nonce.parse()
+                .map_err(|_| X402Error::Storage(format!("invalid nonce: {}", self.nonce)))?,
+            expires_at: self.expires_at as u64,
+            recorded_at: self.recorded_at as u64,
+            settlement_tx: self.settlement_tx,
+        })
+    }
+}
+
+fn address_to_sql(address: &Address) -> String {
+    format!("{:#x}", address)
+}
+
+fn address_from_sql(value: &str) -> Result<Address> {
+    Address::from_str(value).map_err(|e| X402Error::Storage(format!("invalid address {}: {}", value, e)))
+}
+
+fn storage_err(e: rusqlite::Error) -> X402Error {
+    X402Error::Storage(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PaymentPayload, Scheme};
+
+    fn entry(nonce: u64, resource: &str, recorded_at: u64) -> LedgerEntry {
+        let payload = PaymentPayload {
+            amount: U256::MAX,
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: Some(Address::repeat_byte(0x33)),
+            resource: resource.to_string(),
+            nonce,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        LedgerEntry::new(&payload, recorded_at)
+    }
+
+    #[test]
+    fn test_record_query_and_settle() {
+        let ledger = SqliteLedger::in_memory().unwrap();
+        ledger.record(&entry(1, "/a", 100)).unwrap();
+        ledger.record(&entry(2, "/b", 200)).unwrap();
+        ledger.record(&entry(u64::MAX, "/a", 300)).unwrap();
+
+        let all = ledger.query(&LedgerQuery::default()).unwrap();
+        assert_eq!(all, vec![entry(1, "/a", 100), entry(2, "/b", 200), entry(u64::MAX, "/a", 300)]);
+
+        let query = LedgerQuery {
+            resource: Some("/a".to_string()),
+            from: Some(150),
+            ..Default::default()
+        };
+        assert_eq!(ledger.query(&query).unwrap(), vec![entry(u64::MAX, "/a", 300)]);
+
+        ledger.mark_settled(8453, Address::repeat_byte(0x22), 2, "0xabc").unwrap();
+        let settled = LedgerQuery { settled: Some(true), ..Default::default() };
+        let settled = ledger.query(&settled).unwrap();
+        assert_eq!(settled.len(), 1);
+        assert_eq!(settled[0].settlement_tx.as_deref(), Some("0xabc"));
+        assert!(ledger.mark_settled(1, Address::ZERO, 2, "0xabc").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_payment_rejected() {
+        let ledger = SqliteLedger::in_memory().unwrap();
+        ledger.record(&entry(1, "/a", 100)).unwrap();
+        let result = ledger.record(&entry(1, "/other", 101));
+        assert!(matches!(result, Err(X402Error::DuplicatePayment(_))));
+        assert!(ledger.get(8453, Address::repeat_byte(0x22), 1).unwrap().is_some());
+    }
+}
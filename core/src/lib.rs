@@ -1,19 +1,192 @@
 //! x402-core: Core library for the x402 Payment Protocol
 //!
 //! This crate provides:
-//! - Payment types and structures
-//! - x402 header encoding/decoding
-//! - Signature verification
+//! - Payment types and structures (amounts as decimal strings on the wire)
+//! - x402 header encoding/decoding (JSON, CBOR and compact binary), with a
+//!   zero-copy decode path for high-throughput gateways, and strict or
+//!   forward-compatible handling of unknown fields
+//! - Signature verification (feature `verify`; without it the crate is
+//!   wire types and header codecs only, and does not depend on `k256`)
+//! - Payment event notifications (webhooks)
+//! - DNS TXT recipient attestation
+//! - Payment ledger storage (in memory, SQLite, Postgres or sled)
+//! - Nonce replay caches, unbounded or bounded with expiry-aligned TTL
+//! - Time-locked price quote commitments
+//! - Reverse-proxy aware resource URIs
+//! - Multi-tenant recipient routing, with a payment ledger per tenant
+//! - Soft-fail verification with deferred checks
+//! - Bounded verification worker pool, and batch verification spread over
+//!   a rayon pool (feature `parallel`)
+//! - Compatibility types for the reference x402 spec
+//! - HTML paywall pages for browsers, with wallet deep links
+//! - CORS headers that let browser clients send and read x402 headers
+//! - async-graphql guard for paid fields (feature `graphql`)
+//! - tonic interceptors carrying payments in gRPC metadata (feature `grpc`)
+//! - A runnable demo server (feature `demo-server`)
+//! - Capability discovery for the compiled-in protocol surface
+//! - Canonical JSON for structured fields in signed messages
+//! - Streaming micropayments for chunked and SSE responses
+//! - Unidirectional payment channels with off-chain vouchers
+//! - Lightning Network (BOLT11 invoice and preimage) payments
+//! - Privacy-preserving zero-knowledge payment proofs, verified by pluggable
+//!   proof-system backends
+//! - Server-signed refund receipts for failed paid requests
+//! - Recurring subscription authorizations
+//! - Invoice-ID idempotency for retried purchases
+//! - Fiat-denominated pricing with pluggable exchange rates
+//! - Registry of canonical stablecoin addresses per network
+//! - Checked conversion between decimal strings and token units
+//! - `Money` amounts that carry their decimals and symbol
+//! - `validate()` sanity checks on requirements and payloads
+//! - Resource URL normalization and pattern matching
+//! - CSPRNG-backed nonces and a payload builder that rejects guessable ones
+//! - Injectable clocks and `Duration`-based expiry helpers
+//! - `tracing` spans for encode, decode, verify and settle (feature `tracing`)
+//! - Verification and settlement metrics via the `metrics` facade (feature `metrics`)
+//! - Runtime chain registry, loaded from JSON or TOML (feature `toml`) files
+//! - ERC-4337 smart account payers: ERC-1271 signature checks and
+//!   user operations (optionally paymaster-sponsored) for settlement
+//! - Settlement cost estimation from pluggable gas and price data, and
+//!   batched settlement per network and token
+//! - ERC-20 approval management (Permit2 or direct `transferFrom`) with
+//!   cached allowances, for processes holding the owner's key
+//! - Probabilistic (`lottery`) micropayments with commit-reveal draws
+//! - Prepaid credit accounts with server-signed balance statements
+//! - BIP-340 Schnorr signatures, as aggregated by FROST threshold wallets
+//! - M-of-N multisig payers, with the signer set committed in the payment
+//! - Offline, payer-signed single-use payment vouchers
+//! - Hash-chained audit log of verification outcomes
+//! - Cross-SDK test vectors (`spec/test-vectors.json`)
+//! - `Facilitator` interface, with a mock facilitator and deterministic
+//!   test signer for integration tests (feature `testing`)
+//! - Facilitator HTTP server and client, with `/supported` payment kind
+//!   discovery (feature `facilitator-http`)
+//! - API keys with per-key rate limits and settlement quotas, in a
+//!   pluggable key store
 //!
 //! Note: This crate does NOT perform signing. Signing is delegated to
 //! external signers (KMS, hardware wallets, browser wallets, etc.)
 
+mod trace;
 pub mod types;
+pub mod serde_amount;
 pub mod protocol;
+pub mod binary;
+pub mod borrowed;
+#[cfg(feature = "verify")]
 pub mod verify;
 pub mod error;
+pub mod events;
+pub mod dns;
+pub mod ledger;
+pub mod replay;
+pub mod quote;
+pub mod forwarded;
+#[cfg(feature = "verify")]
+pub mod softfail;
+#[cfg(feature = "verify")]
+pub mod pool;
+pub mod spec;
+pub mod capabilities;
+pub mod canonical;
+pub mod stream;
+pub mod channel;
+pub mod lightning;
+pub mod zkproof;
+pub mod refund;
+pub mod subscription;
+pub mod idempotency;
+pub mod price;
+pub mod tokens;
+pub mod amount;
+pub mod money;
+pub mod validate;
+pub mod resource;
+pub mod tenant;
+pub mod nonce;
+pub mod clock;
+pub mod audit;
+#[cfg(feature = "verify")]
+pub mod testvectors;
+pub mod facilitator;
+pub mod apikey;
+pub mod chains;
+pub mod erc4337;
+pub mod multisig;
+pub mod schnorr;
+pub mod settlement;
+pub mod approval;
+pub mod lottery;
+pub mod credit;
+pub mod voucher;
+pub mod paywall;
+pub mod cors;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "demo-server")]
+pub mod demo;
+#[cfg(feature = "facilitator-http")]
+pub mod facilitator_http;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
+#[cfg(all(feature = "verify", any(test, feature = "testing")))]
+pub mod testing;
 
 pub use types::*;
 pub use protocol::*;
+pub use binary::*;
+pub use borrowed::*;
+#[cfg(feature = "verify")]
 pub use verify::*;
 pub use error::*;
+pub use events::*;
+pub use dns::*;
+pub use ledger::*;
+pub use replay::*;
+pub use quote::*;
+pub use forwarded::*;
+#[cfg(feature = "verify")]
+pub use softfail::*;
+#[cfg(feature = "verify")]
+pub use pool::*;
+pub use spec::*;
+pub use capabilities::*;
+pub use canonical::*;
+pub use stream::*;
+pub use channel::*;
+pub use lightning::*;
+pub use zkproof::*;
+pub use refund::*;
+pub use subscription::*;
+pub use idempotency::*;
+pub use price::*;
+pub use tokens::*;
+pub use amount::*;
+pub use money::*;
+pub use validate::*;
+pub use resource::*;
+pub use tenant::*;
+pub use nonce::*;
+pub use clock::*;
+pub use audit::*;
+#[cfg(feature = "verify")]
+pub use testvectors::*;
+pub use facilitator::*;
+pub use apikey::*;
+pub use chains::*;
+pub use erc4337::*;
+pub use multisig::*;
+pub use schnorr::*;
+pub use settlement::*;
+pub use approval::*;
+pub use lottery::*;
+pub use credit::*;
+pub use voucher::*;
+pub use paywall::*;
+pub use cors::*;
+#[cfg(feature = "graphql")]
+pub use graphql::*;
+#[cfg(feature = "grpc")]
+pub use grpc::*;
@@ -0,0 +1,282 @@
+//! Lightning Network (BOLT11) payments
+//!
+//! An alternative to the EVM schemes for Bitcoin-native clients, in the
+//! spirit of L402: the server advertises a BOLT11 invoice in
+//! [`LightningRequirements`] (see [`crate::PaymentRequiredResponse::lightning`]),
+//! the client pays it over Lightning and proves payment with the preimage
+//! the payee's node revealed on settlement ([`LightningPayment`]). The
+//! server checks the preimage against the invoice's payment hash with
+//! [`crate::verify_lightning_payment`].
+//!
+//! A preimage proves payment forever, so servers should record redeemed
+//! payment hashes (e.g. in a [`crate::PaymentLedger`]) to stop reuse.
+//! Invoice signatures aren't checked: servers only accept preimages for
+//! invoices they issued themselves.
+
+use crate::{decode_header, parse_payload, tagged, DecodeLimits, Result, WireFormat, X402Error};
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+/// HTTP header carrying a Lightning payment proof
+pub const X402_LIGHTNING_HEADER: &str = "X-Payment-Lightning";
+
+/// Invoice expiry when the invoice has no `x` field (BOLT11 default)
+pub const DEFAULT_INVOICE_EXPIRY_SECONDS: u64 = 3600;
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const SIGNATURE_WORDS: usize = 104;
+const TIMESTAMP_WORDS: usize = 7;
+const TAG_PAYMENT_HASH: u8 = 1;
+const TAG_EXPIRY: u8 = 6;
+
+/// Fields of a BOLT11 invoice needed to check a payment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bolt11Invoice {
+    /// Currency prefix (`bc`, `tb`, `bcrt`, `tbs`)
+    pub currency: String,
+    /// Amount in millisatoshis; `None` for any-amount invoices
+    pub amount_msat: Option<u64>,
+    pub payment_hash: B256,
+    /// Creation time (unix timestamp)
+    pub timestamp: u64,
+    pub expiry_seconds: u64,
+}
+
+impl Bolt11Invoice {
+    /// Parse a BOLT11 invoice string
+    pub fn parse(invoice: &str) -> Result<Self> {
+        let (hrp, words) = bech32_decode(invoice)?;
+        let hrp = hrp.strip_prefix("ln").ok_or_else(|| invalid("not a Lightning invoice"))?;
+        let currency = ["bcrt", "bc", "tbs", "tb"]
+            .into_iter()
+            .find(|prefix| hrp.starts_with(prefix))
+            .ok_or_else(|| invalid("unknown currency prefix"))?;
+        let amount_msat = parse_amount(&hrp[currency.len()..])?;
+
+        if words.len() < TIMESTAMP_WORDS + SIGNATURE_WORDS {
+            return Err(invalid("invoice too short"));
+        }
+        let (timestamp, fields) = words[..words.len() - SIGNATURE_WORDS].split_at(TIMESTAMP_WORDS);
+
+        let mut payment_hash = None;
+        let mut expiry_seconds = DEFAULT_INVOICE_EXPIRY_SECONDS;
+        let mut rest = fields;
+        while !rest.is_empty() {
+            if rest.len() < 3 {
+                return Err(invalid("truncated tagged field"));
+            }
+            let len = rest[1] as usize * 32 + rest[2] as usize;
+            let data = rest.get(3..3 + len).ok_or_else(|| invalid("truncated tagged field"))?;
+            match rest[0] {
+                // Fields of unexpected length must be skipped, per BOLT11
+                TAG_PAYMENT_HASH if len == 52 => {
+                    payment_hash = Some(B256::from_slice(&words_to_bytes(data)[..32]));
+                }
+                TAG_EXPIRY => expiry_seconds = words_to_u64(data)?,
+                _ => {}
+            }
+            rest = &rest[3 + len..];
+        }
+
+        Ok(Self {
+            currency: currency.to_string(),
+            amount_msat,
+            payment_hash: payment_hash.ok_or_else(|| invalid("invoice has no payment hash"))?,
+            timestamp: words_to_u64(timestamp)?,
+            expiry_seconds,
+        })
+    }
+
+    /// Time after which the invoice can no longer be paid (unix timestamp)
+    pub fn expires_at(&self) -> u64 {
+        self.timestamp.saturating_add(self.expiry_seconds)
+    }
+}
+
+/// Lightning payment option in a 402 response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightningRequirements {
+    /// BOLT11 invoice to pay
+    pub invoice: String,
+    /// Resource being paid for
+    pub resource: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Proof of a Lightning payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightningPayment {
+    /// The invoice that was paid
+    pub invoice: String,
+    /// Preimage of the invoice's payment hash
+    pub preimage: B256,
+}
+
+impl LightningPayment {
+    /// Encode as an `X-Payment-Lightning` header value
+    pub fn to_header(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    }
+
+    /// Decode an `X-Payment-Lightning` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let (format, bytes) = decode_header(header.as_ref(), &limits)?;
+        parse_payload(format, &bytes, &limits)
+    }
+}
+
+fn invalid(reason: &str) -> X402Error {
+    X402Error::InvalidHeader(format!("invalid BOLT11 invoice: {}", reason))
+}
+
+/// Human-readable amount: digits and an optional BTC multiplier
+fn parse_amount(amount: &str) -> Result<Option<u64>> {
+    if amount.is_empty() {
+        return Ok(None);
+    }
+    let (digits, multiplier) = match amount.as_bytes()[amount.len() - 1] {
+        b'0'..=b'9' => (amount, None),
+        unit => (&amount[..amount.len() - 1], Some(unit)),
+    };
+    let value: u64 = digits.parse().map_err(|_| invalid("malformed amount"))?;
+    // 1 BTC = 10^11 msat
+    let msat = match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some(b'm') => value.checked_mul(100_000_000),
+        Some(b'u') => value.checked_mul(100_000),
+        Some(b'n') => value.checked_mul(100),
+        Some(b'p') if value.is_multiple_of(10) => Some(value / 10),
+        _ => None,
+    };
+    msat.map(Some).ok_or_else(|| invalid("malformed amount"))
+}
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    values.fold(1, |chk, value| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x1ffffff) << 5) ^ value as u32;
+        (0..5).filter(|i| (top >> i) & 1 == 1).fold(chk, |chk, i| chk ^ GENERATOR[i])
+    })
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes().map(|b| b >> 5).chain([0]).chain(hrp.bytes().map(|b| b & 31))
+}
+
+/// Decode a bech32 string (without BIP-173's 90 character limit, which
+/// invoices exceed) into its HRP and data words
+fn bech32_decode(input: &str) -> Result<(String, Vec<u8>)> {
+    if input.chars().any(|c| c.is_ascii_uppercase()) && input.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err(invalid("mixed case"));
+    }
+    let input = input.to_ascii_lowercase();
+    let (hrp, data) = input.rsplit_once('1').ok_or_else(|| invalid("missing separator"))?;
+    if hrp.is_empty() || data.len() < 6 {
+        return Err(invalid("missing separator"));
+    }
+    let words = data
+        .bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|&d| d == c).map(|w| w as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| invalid("invalid character"))?;
+    if bech32_polymod(hrp_expand(hrp).chain(words.iter().copied())) != 1 {
+        return Err(invalid("bad checksum"));
+    }
+    Ok((hrp.to_string(), words[..words.len() - 6].to_vec()))
+}
+
+/// Pack 5-bit words into bytes, dropping trailing padding bits
+fn words_to_bytes(words: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(words.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0);
+    for &word in words {
+        acc = (acc << 5) | word as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    bytes
+}
+
+fn words_to_u64(words: &[u8]) -> Result<u64> {
+    if words.len() > 12 {
+        return Err(invalid("integer field too long"));
+    }
+    Ok(words.iter().fold(0, |acc, &word| (acc << 5) | word as u64))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn to_words(value: u64, count: usize) -> Vec<u8> {
+        (0..count).rev().map(|i| ((value >> (5 * i)) & 31) as u8).collect()
+    }
+
+    fn bytes_to_words(bytes: &[u8]) -> Vec<u8> {
+        let (mut words, mut acc, mut bits) = (Vec::new(), 0u32, 0);
+        for &byte in bytes {
+            acc = (acc << 8) | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                words.push(((acc >> bits) & 31) as u8);
+            }
+        }
+        if bits > 0 {
+            words.push(((acc << (5 - bits)) & 31) as u8);
+        }
+        words
+    }
+
+    /// Build an (unsigned) invoice for tests
+    pub(crate) fn invoice(hrp: &str, payment_hash: B256, timestamp: u64, expiry: u64) -> String {
+        let mut words = to_words(timestamp, TIMESTAMP_WORDS);
+        words.extend([TAG_PAYMENT_HASH, 1, 20]);
+        words.extend(bytes_to_words(payment_hash.as_slice()));
+        words.extend([TAG_EXPIRY, 0, 2]);
+        words.extend(to_words(expiry, 2));
+        words.extend([0; SIGNATURE_WORDS]);
+
+        let checksum = bech32_polymod(hrp_expand(hrp).chain(words.iter().copied()).chain([0; 6])) ^ 1;
+        words.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+        let data: String = words.iter().map(|&w| BECH32_CHARSET[w as usize] as char).collect();
+        format!("{}1{}", hrp, data)
+    }
+
+    #[test]
+    fn test_parse_bolt11() {
+        // Donation example from the BOLT11 specification
+        let spec = Bolt11Invoice::parse(
+            "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6\
+             twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9rn449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz\
+             25le42c4u4ecky03ylcqca784w",
+        ).unwrap();
+        assert_eq!(spec.payment_hash.to_string(), "0x0001020304050607080900010203040506070809000102030405060708090102");
+        assert_eq!((spec.amount_msat, spec.timestamp, spec.expires_at()), (None, 1_496_314_658, 1_496_318_258));
+
+        let hash = B256::repeat_byte(0xab);
+        let parsed = Bolt11Invoice::parse(&invoice("lnbc2500u", hash, 1_496_314_658, 60)).unwrap();
+        assert_eq!(parsed.currency, "bc");
+        assert_eq!(parsed.amount_msat, Some(250_000_000));
+        assert_eq!(parsed.payment_hash, hash);
+        assert_eq!(parsed.expires_at(), 1_496_314_718);
+
+        let any_amount = Bolt11Invoice::parse(&invoice("lntb", hash, 1, 60)).unwrap();
+        assert_eq!((any_amount.currency.as_str(), any_amount.amount_msat), ("tb", None));
+
+        let mut corrupted = invoice("lnbc1m", hash, 1, 60);
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(Bolt11Invoice::parse(&corrupted).is_err());
+        assert!(Bolt11Invoice::parse(&invoice("lnbc1x", hash, 1, 60)).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Probabilistic micropayments
+//!
+//! Instead of paying a sub-cent price on every request, a client signs a
+//! `lottery` ticket: a payment of `odds` times the price that the server
+//! settles only if the ticket wins, which happens with probability
+//! `1 / odds`. The expected cost per request is still the price, but only
+//! one request in `odds` pays settlement fees.
+//!
+//! The draw is commit-reveal. The server publishes the hash of a
+//! [`LotterySecret`] in the requirements' `extra` ([`LOTTERY_KEY`]), and a
+//! ticket's draw is the hash of that secret, the ticket's message hash and
+//! the `r` half of its signature. `s` is left out because anyone can flip
+//! it without invalidating the signature.
+//!
+//! The client signs without knowing the secret, so it can't aim for a
+//! losing ticket, and the commitment stops the server from swapping the
+//! secret after seeing a ticket. It doesn't stop the server from refusing
+//! the tickets it knows lost and asking for another: clients should treat
+//! a refused ticket as spent and stop paying a server that refuses often.
+//! Settle a ticket only after [`check_ticket_charge`] confirms it won.
+//!
+//! Once a secret is revealed, anyone can recompute draws with
+//! [`check_ticket`], so servers must switch to a fresh secret before
+//! revealing the old one.
+
+use crate::{Extra, PaymentRequirements, Result, Scheme, SignedPayment, X402Error};
+#[cfg(feature = "verify")]
+use crate::{verify::check_charge_with, verify_payment_at};
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// `extra` key carrying the [`LotteryTerms`]
+pub const LOTTERY_KEY: &str = "lottery";
+
+/// Odds and secret commitment of a lottery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LotteryTerms {
+    /// A ticket wins with probability `1 / odds`
+    pub odds: u64,
+    /// keccak256 of the server's secret
+    pub commitment: B256,
+}
+
+impl LotteryTerms {
+    /// Terms stored under [`LOTTERY_KEY`] in `extra`
+    pub fn from_extra(extra: &Extra) -> Result<Self> {
+        let value = extra
+            .get(LOTTERY_KEY)
+            .ok_or_else(|| X402Error::InvalidTicket("no lottery terms".to_string()))?;
+        let terms: Self = serde_json::from_value(value.clone())
+            .map_err(|e| X402Error::InvalidTicket(format!("malformed lottery terms: {}", e)))?;
+        if terms.odds == 0 {
+            return Err(X402Error::InvalidTicket("odds must be at least 1".to_string()));
+        }
+        Ok(terms)
+    }
+
+    /// Expected value of a ticket for `amount`
+    pub fn expected_value(&self, amount: U256) -> U256 {
+        amount / U256::from(self.odds)
+    }
+}
+
+/// Server-side lottery secret
+///
+/// Keep it private until every ticket drawn against it has been claimed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LotterySecret(B256);
+
+impl LotterySecret {
+    /// A fresh secret from the OS CSPRNG
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+        Self(B256::from(bytes))
+    }
+
+    pub fn from_bytes(bytes: B256) -> Self {
+        Self(bytes)
+    }
+
+    /// The hash published in [`LotteryTerms::commitment`]
+    pub fn commitment(&self) -> B256 {
+        keccak256(self.0)
+    }
+
+    /// The secret itself, for publishing once it is retired
+    pub fn reveal(&self) -> B256 {
+        self.0
+    }
+}
+
+impl std::fmt::Debug for LotterySecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LotterySecret").field(&self.commitment()).finish()
+    }
+}
+
+/// Turn `requirements` priced at the expected value into a lottery
+///
+/// Each ticket is for `odds` times the original amount and wins with
+/// probability `1 / odds`.
+pub fn lottery_requirements(
+    mut requirements: PaymentRequirements,
+    odds: u64,
+    secret: &LotterySecret,
+) -> Result<PaymentRequirements> {
+    if odds == 0 {
+        return Err(X402Error::InvalidConfig("lottery odds must be at least 1".to_string()));
+    }
+    if !requirements.splits.is_empty() {
+        return Err(X402Error::InvalidConfig("lottery tickets can't be split".to_string()));
+    }
+    requirements.amount = requirements.amount.checked_mul(U256::from(odds)).ok_or_else(|| {
+        X402Error::InvalidAmount(format!("{} times {} overflows", requirements.amount, odds))
+    })?;
+    let terms = LotteryTerms { odds, commitment: secret.commitment() };
+    let terms = serde_json::to_value(terms).map_err(X402Error::Json)?;
+    requirements.extra.insert(LOTTERY_KEY.to_string(), terms);
+    requirements.scheme = Scheme::Lottery;
+    Ok(requirements)
+}
+
+/// Random value of `ticket` under the revealed `secret`
+pub fn ticket_draw(ticket: &SignedPayment, secret: B256) -> Result<B256> {
+    let r = ticket
+        .signature
+        .get(..32)
+        .ok_or_else(|| X402Error::InvalidSignature(format!("signature is only {} bytes", ticket.signature.len())))?;
+    let mut preimage = Vec::with_capacity(96);
+    preimage.extend_from_slice(secret.as_slice());
+    preimage.extend_from_slice(&ticket.payment.message_hash());
+    preimage.extend_from_slice(r);
+    Ok(keccak256(preimage))
+}
+
+/// Whether `ticket` wins under the revealed `secret`
+///
+/// Fails if `secret` doesn't match the commitment in the ticket's terms.
+/// Does not check the ticket's signature.
+pub fn check_ticket(ticket: &SignedPayment, secret: B256) -> Result<bool> {
+    let terms = LotteryTerms::from_extra(&ticket.payment.extra)?;
+    if keccak256(secret) != terms.commitment {
+        return Err(X402Error::InvalidTicket("secret does not match the commitment".to_string()));
+    }
+    let draw = U256::from_be_bytes(ticket_draw(ticket, secret)?.0);
+    Ok(draw % U256::from(terms.odds) == U256::ZERO)
+}
+
+/// Result of drawing a verified ticket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LotteryOutcome {
+    pub payer: Address,
+    /// Whether to settle the ticket
+    pub won: bool,
+}
+
+/// Verify a lottery ticket against `requirements` and draw it with `secret`
+#[cfg(feature = "verify")]
+pub fn verify_ticket(
+    ticket: &SignedPayment,
+    requirements: &PaymentRequirements,
+    secret: &LotterySecret,
+) -> Result<LotteryOutcome> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    verify_ticket_at(ticket, requirements, secret, now)
+}
+
+/// [`verify_ticket`] at unix time `now`
+#[cfg(feature = "verify")]
+pub fn verify_ticket_at(
+    ticket: &SignedPayment,
+    requirements: &PaymentRequirements,
+    secret: &LotterySecret,
+    now: u64,
+) -> Result<LotteryOutcome> {
+    if requirements.scheme != Scheme::Lottery {
+        return Err(X402Error::UnsupportedScheme(format!("expected lottery, got {}", requirements.scheme.as_str())));
+    }
+    let payer = verify_payment_at(ticket, requirements, now)?;
+    let won = check_ticket(ticket, secret.reveal())?;
+    Ok(LotteryOutcome { payer, won })
+}
+
+/// Check the amount a server intends to settle for a verified ticket
+///
+/// Fails with [`X402Error::InvalidTicket`] unless `secret` matches the
+/// ticket's commitment and the ticket won; a winning ticket settles for
+/// its full amount.
+#[cfg(feature = "verify")]
+pub fn check_ticket_charge(ticket: &SignedPayment, charge: U256, secret: &LotterySecret) -> Result<()> {
+    if ticket.payment.scheme != Scheme::Lottery {
+        return Err(X402Error::UnsupportedScheme(format!("expected lottery, got {}", ticket.payment.scheme.as_str())));
+    }
+    check_charge_with(ticket, charge, Some(secret))
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{check_charge, Network};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn requirements(odds: u64, secret: &LotterySecret) -> PaymentRequirements {
+        let priced = PaymentRequirements::usdc(Network::Base, "0.001", Address::repeat_byte(0x11), "/api").unwrap();
+        lottery_requirements(priced, odds, secret).unwrap()
+    }
+
+    #[test]
+    fn test_lottery_win_rate() {
+        let secret = LotterySecret::from_bytes(B256::repeat_byte(0x5e));
+        let requirements = requirements(10, &secret);
+        assert_eq!(requirements.amount, U256::from(10_000u64));
+        let terms = LotteryTerms::from_extra(&requirements.extra).unwrap();
+        assert_eq!(terms.expected_value(requirements.amount), U256::from(1_000u64));
+
+        let signer = TestSigner::default();
+        let mut wins = 0;
+        for _ in 0..400 {
+            let ticket = signer.pay(&requirements, NOW).unwrap();
+            let outcome = verify_ticket_at(&ticket, &requirements, &secret, NOW).unwrap();
+            assert_eq!(outcome.payer, signer.address());
+            assert_eq!(outcome.won, check_ticket(&ticket, secret.reveal()).unwrap());
+            if outcome.won {
+                wins += 1;
+                check_ticket_charge(&ticket, requirements.amount, &secret).unwrap();
+            } else {
+                let lost = check_ticket_charge(&ticket, requirements.amount, &secret);
+                assert!(matches!(lost, Err(X402Error::InvalidTicket(_))));
+            }
+            assert!(matches!(check_charge(&ticket, requirements.amount), Err(X402Error::ChargeRejected(_))));
+        }
+        assert!((15..=70).contains(&wins), "{} wins in 400 tickets at 1 in 10", wins);
+    }
+
+    #[test]
+    fn test_lottery_terms_are_binding() {
+        let secret = LotterySecret::generate();
+        let requirements = requirements(100, &secret);
+        let signer = TestSigner::default();
+        let ticket = signer.pay(&requirements, NOW).unwrap();
+
+        let other = LotterySecret::generate();
+        assert!(matches!(check_ticket(&ticket, other.reveal()), Err(X402Error::InvalidTicket(_))));
+        let charge = check_ticket_charge(&ticket, requirements.amount, &other);
+        assert!(matches!(charge, Err(X402Error::InvalidTicket(_))));
+        let rotated = self::requirements(100, &other);
+        assert!(matches!(verify_payment_at(&ticket, &rotated, NOW), Err(X402Error::InvalidTicket(_))));
+        let longer_odds = self::requirements(1000, &secret);
+        assert!(verify_payment_at(&ticket, &longer_odds, NOW).is_err());
+
+        let mut flipped = ticket.clone();
+        flipped.signature[32] ^= 1;
+        assert_eq!(ticket_draw(&flipped, secret.reveal()).unwrap(), ticket_draw(&ticket, secret.reveal()).unwrap());
+
+        let plain = PaymentRequirements { scheme: Scheme::Exact, ..requirements.clone() };
+        assert!(matches!(verify_ticket_at(&ticket, &plain, &secret, NOW), Err(X402Error::UnsupportedScheme(_))));
+        assert!(lottery_requirements(requirements, 0, &secret).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Token amounts with their decimals and symbol
+//!
+//! [`Money`] keeps the decimals (and, optionally, the ticker) next to the
+//! integer amount, so prices can be printed, parsed and added without
+//! mixing up units. Arithmetic between amounts with different decimals or
+//! symbols is an error rather than a silent conversion.
+
+use crate::{Amount, Network, PaymentRequirements, Result, Stablecoin, X402Error};
+use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// An amount of a token
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    /// Amount in the token's smallest unit
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    pub decimals: u8,
+    /// Ticker, e.g. `USDC`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+}
+
+impl Money {
+    pub fn new(amount: U256, decimals: u8, symbol: Option<String>) -> Self {
+        Self { amount, decimals, symbol }
+    }
+
+    /// Parse a human decimal amount such as `"1.25"`
+    pub fn parse(amount: &str, decimals: u8, symbol: Option<String>) -> Result<Self> {
+        Ok(Self { amount: Amount::parse(amount, decimals)?.units, decimals, symbol })
+    }
+
+    pub fn as_amount(&self) -> Amount {
+        Amount::new(self.amount, self.decimals)
+    }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money> {
+        self.check_compatible(other)?;
+        let amount = self.amount.checked_add(other.amount).ok_or_else(|| self.overflow())?;
+        Ok(Money { amount, ..self.clone() })
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> Result<Money> {
+        self.check_compatible(other)?;
+        let amount = self.amount.checked_sub(other.amount).ok_or_else(|| {
+            X402Error::InvalidAmount(format!("{} is less than {}", self, other))
+        })?;
+        Ok(Money { amount, ..self.clone() })
+    }
+
+    pub fn checked_mul(&self, factor: u64) -> Result<Money> {
+        let amount = self.amount.checked_mul(U256::from(factor)).ok_or_else(|| self.overflow())?;
+        Ok(Money { amount, ..self.clone() })
+    }
+
+    fn check_compatible(&self, other: &Money) -> Result<()> {
+        if self.decimals != other.decimals {
+            return Err(X402Error::InvalidAmount(format!(
+                "cannot combine amounts with {} and {} decimals",
+                self.decimals, other.decimals
+            )));
+        }
+        if self.symbol != other.symbol {
+            return Err(X402Error::InvalidAmount(format!(
+                "cannot combine {} and {}",
+                self.symbol.as_deref().unwrap_or("untyped amount"),
+                other.symbol.as_deref().unwrap_or("untyped amount")
+            )));
+        }
+        Ok(())
+    }
+
+    fn overflow(&self) -> X402Error {
+        X402Error::AmountOverflow { amount: self.as_amount().to_string(), decimals: self.decimals }
+    }
+}
+
+/// Formats as `1.25 USDC`, or `1.25` without a symbol
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.symbol {
+            Some(symbol) => write!(f, "{} {}", self.as_amount(), symbol),
+            None => write!(f, "{}", self.as_amount()),
+        }
+    }
+}
+
+/// Parses `<amount> <symbol>` for symbols in the [`Stablecoin`] registry,
+/// which supplies the decimals
+impl FromStr for Money {
+    type Err = X402Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (amount, symbol) = s.trim().split_once(' ').ok_or_else(|| {
+            X402Error::InvalidAmount(format!("{:?} is not of the form \"<amount> <symbol>\"", s))
+        })?;
+        let coin = Stablecoin::from_symbol(symbol.trim())
+            .ok_or_else(|| X402Error::InvalidAmount(format!("unknown token symbol {:?}", symbol.trim())))?;
+        Money::parse(amount, coin.decimals(), Some(coin.symbol().to_string()))
+    }
+}
+
+impl Stablecoin {
+    /// `amount` (a human decimal string) of this token
+    pub fn money(&self, amount: &str) -> Result<Money> {
+        Money::parse(amount, self.decimals(), Some(self.symbol().to_string()))
+    }
+}
+
+impl PaymentRequirements {
+    /// Requirements for `price`, a [`Stablecoin`] amount, on `network`
+    pub fn priced(network: Network, price: &Money, recipient: Address, resource: impl Into<String>) -> Result<Self> {
+        let coin = price.symbol.as_deref().and_then(Stablecoin::from_symbol).ok_or_else(|| {
+            X402Error::InvalidAmount(format!("{} is not a known stablecoin amount", price))
+        })?;
+        if price.decimals != coin.decimals() {
+            return Err(X402Error::InvalidAmount(format!(
+                "{} has {} decimals, not {}",
+                coin.symbol(),
+                coin.decimals(),
+                price.decimals
+            )));
+        }
+        let mut requirements = Self::stablecoin(network, coin, "0", recipient, resource)?;
+        requirements.amount = price.amount;
+        Ok(requirements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money_arithmetic_and_format() {
+        let price: Money = "1.25 USDC".parse().unwrap();
+        assert_eq!(price.amount, U256::from(1_250_000));
+        assert_eq!(price.to_string(), "1.25 USDC");
+        assert_eq!(price.checked_mul(3).unwrap().to_string(), "3.75 USDC");
+        assert_eq!(price.checked_add(&Stablecoin::Usdc.money("0.75").unwrap()).unwrap().to_string(), "2 USDC");
+        assert!(price.checked_sub(&Stablecoin::Usdc.money("2").unwrap()).is_err());
+
+        let dai = Stablecoin::Dai.money("1").unwrap();
+        assert!(matches!(price.checked_add(&dai), Err(X402Error::InvalidAmount(_))));
+        let usdt = Stablecoin::Usdt.money("1").unwrap();
+        assert!(matches!(price.checked_add(&usdt), Err(X402Error::InvalidAmount(_))));
+        assert!("1.25 XYZ".parse::<Money>().is_err());
+        assert_eq!(Money::new(U256::from(5), 2, None).to_string(), "0.05");
+
+        let requirements = PaymentRequirements::priced(Network::Base, &price, Address::repeat_byte(0x11), "/").unwrap();
+        assert_eq!(requirements.amount, price.amount);
+        assert_eq!(requirements.token, Stablecoin::Usdc.address(Network::Base));
+    }
+}
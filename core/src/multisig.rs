@@ -0,0 +1,293 @@
+//! M-of-N multisig payers
+//!
+//! A treasury-controlled payer can require several keys to approve each
+//! payment. The payment commits to its [`MultisigPolicy`] (threshold and
+//! signer set) under [`MULTISIG_KEY`] in `extra`, so every signature covers
+//! it, and carries one 65-byte signature per signer in
+//! [`SignedPayment::signatures`](crate::SignedPayment::signatures), leaving
+//! `signature` empty.
+//!
+//! [`verify_payment_with_options`](crate::verify_payment_with_options)
+//! verifies such payments when [`VerifyOptions::multisig`] holds a
+//! [`MultisigRegistry`]: it requires `threshold` distinct signers from the
+//! set, and checks the set against the payer's registered policy, since the
+//! payload alone can't prove who controls the payer.
+//!
+//! The binary header format carries single signatures only; multisig
+//! payments use JSON or CBOR.
+
+use crate::{Extra, Result, X402Error};
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+#[cfg(feature = "verify")]
+use crate::verify::{check_terms, observe_verification};
+#[cfg(feature = "verify")]
+use crate::{recover_address, PaymentRequirements, SignedPayment, VerifyOptions};
+
+/// `extra` key carrying the [`MultisigPolicy`]
+pub const MULTISIG_KEY: &str = "multisig";
+
+/// Most signers a policy may have, bounding the recoveries per payment
+pub const MAX_MULTISIG_SIGNERS: usize = 16;
+
+/// `threshold` of `signers` must sign
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    pub threshold: usize,
+    /// Sorted, without duplicates
+    pub signers: Vec<Address>,
+}
+
+impl MultisigPolicy {
+    pub fn new(threshold: usize, mut signers: Vec<Address>) -> Result<Self> {
+        signers.sort();
+        signers.dedup();
+        if signers.is_empty() || signers.len() > MAX_MULTISIG_SIGNERS {
+            return Err(malformed(format!("needs 1 to {} signers, got {}", MAX_MULTISIG_SIGNERS, signers.len())));
+        }
+        if threshold == 0 || threshold > signers.len() {
+            return Err(malformed(format!("threshold {} out of range for {} signers", threshold, signers.len())));
+        }
+        Ok(Self { threshold, signers })
+    }
+
+    /// The policy stored under [`MULTISIG_KEY`] in `extra`, if any
+    pub fn from_extra(extra: &Extra) -> Result<Option<Self>> {
+        let Some(value) = extra.get(MULTISIG_KEY) else {
+            return Ok(None);
+        };
+        let policy: Self = serde_json::from_value(value.clone()).map_err(|e| malformed(e.to_string()))?;
+        Self::new(policy.threshold, policy.signers).map(Some)
+    }
+
+    /// Commit to this policy in a payment's (or requirements') `extra`
+    pub fn insert_into(&self, extra: &mut Extra) {
+        let value = serde_json::to_value(self).expect("policies serialize to JSON");
+        extra.insert(MULTISIG_KEY.to_string(), value);
+    }
+}
+
+fn malformed(reason: String) -> X402Error {
+    X402Error::InvalidSignature(format!("malformed multisig policy: {}", reason))
+}
+
+/// The multisig policies that control payers
+pub trait MultisigRegistry: Send + Sync {
+    /// Policy of `payer` on `chain_id`, or `None` if it isn't a known
+    /// multisig
+    ///
+    /// Implementations may read it from configuration or from the chain,
+    /// e.g. a Safe's `getOwners()` and `getThreshold()`.
+    fn policy(&self, chain_id: u64, payer: Address) -> Result<Option<MultisigPolicy>>;
+}
+
+/// In-process [`MultisigRegistry`]
+#[derive(Debug, Default)]
+pub struct MemoryMultisigRegistry {
+    policies: RwLock<HashMap<(u64, Address), MultisigPolicy>>,
+}
+
+impl MemoryMultisigRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, chain_id: u64, payer: Address, policy: MultisigPolicy) {
+        self.policies.write().unwrap().insert((chain_id, payer), policy);
+    }
+
+    pub fn remove(&self, chain_id: u64, payer: Address) -> Option<MultisigPolicy> {
+        self.policies.write().unwrap().remove(&(chain_id, payer))
+    }
+}
+
+impl MultisigRegistry for MemoryMultisigRegistry {
+    fn policy(&self, chain_id: u64, payer: Address) -> Result<Option<MultisigPolicy>> {
+        Ok(self.policies.read().unwrap().get(&(chain_id, payer)).cloned())
+    }
+}
+
+/// Verify a payment from an M-of-N multisig payer against `registry`
+///
+/// The same as [`verify_payment_with_options`](crate::verify_payment_with_options)
+/// with `registry` as [`VerifyOptions::multisig`], but fails for payments
+/// that commit to no multisig policy.
+#[cfg(feature = "verify")]
+pub fn verify_multisig_payment<R: MultisigRegistry + ?Sized>(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    registry: &R,
+) -> Result<Address> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    verify_multisig_payment_at(payment, requirements, options, registry, now)
+}
+
+/// [`verify_multisig_payment`] as of the unix time `now`
+#[cfg(feature = "verify")]
+pub fn verify_multisig_payment_at<R: MultisigRegistry + ?Sized>(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    registry: &R,
+    now: u64,
+) -> Result<Address> {
+    observe_verification(payment, requirements, || {
+        check_terms(payment, requirements, options, now)?;
+        check_multisig(payment, registry)
+    })
+}
+
+/// Check that `threshold` distinct signers of the payer's registered policy
+/// signed `payment`
+#[cfg(feature = "verify")]
+pub(crate) fn check_multisig<R: MultisigRegistry + ?Sized>(payment: &SignedPayment, registry: &R) -> Result<Address> {
+    let payer = payment.payment.payer;
+    let policy = MultisigPolicy::from_extra(&payment.payment.extra)?
+        .ok_or_else(|| X402Error::InvalidSignature("payment commits to no multisig policy".to_string()))?;
+    let registered = registry.policy(payment.payment.chain_id, payer)?.ok_or_else(|| {
+        X402Error::InvalidSignature(format!("payer {} has no registered multisig policy", payer))
+    })?;
+    if policy != registered {
+        return Err(X402Error::InvalidSignature("multisig policy does not match the payer's".to_string()));
+    }
+
+    if !payment.signature.is_empty() {
+        return Err(X402Error::InvalidSignature("multisig payments carry signatures, not signature".to_string()));
+    }
+    if payment.signatures.is_empty() || payment.signatures.len() > policy.signers.len() {
+        return Err(X402Error::InvalidSignature(format!(
+            "needs 1 to {} signatures, got {}",
+            policy.signers.len(),
+            payment.signatures.len()
+        )));
+    }
+    let hash = payment.payment.message_hash();
+    let mut approved = Vec::with_capacity(payment.signatures.len());
+    for signature in &payment.signatures {
+        let signer = recover_address(&hash, signature)?;
+        if !policy.signers.contains(&signer) {
+            return Err(X402Error::InvalidSignature(format!("{} is not a signer of {}", signer, payer)));
+        }
+        if approved.contains(&signer) {
+            return Err(X402Error::InvalidSignature(format!("{} signed more than once", signer)));
+        }
+        approved.push(signer);
+    }
+    if approved.len() < policy.threshold {
+        return Err(X402Error::InvalidSignature(format!(
+            "{} of {} required signatures",
+            approved.len(),
+            policy.threshold
+        )));
+    }
+    Ok(payer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_normalized_and_bounded() {
+        let (a, b) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let policy = MultisigPolicy::new(2, vec![b, a, b]).unwrap();
+        assert_eq!(policy.signers, vec![a, b]);
+        assert!(MultisigPolicy::new(3, vec![a, b]).is_err());
+        assert!(MultisigPolicy::new(0, vec![a]).is_err());
+        assert!(MultisigPolicy::new(1, (0..=16).map(Address::repeat_byte).collect()).is_err());
+
+        let mut extra = Extra::new();
+        assert_eq!(MultisigPolicy::from_extra(&extra).unwrap(), None);
+        policy.insert_into(&mut extra);
+        assert_eq!(MultisigPolicy::from_extra(&extra).unwrap(), Some(policy));
+        extra.insert(MULTISIG_KEY.to_string(), serde_json::json!({ "threshold": 5, "signers": [] }));
+        assert!(MultisigPolicy::from_extra(&extra).is_err());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_multisig_payment() {
+        use crate::testing::TestSigner;
+        use crate::Network;
+
+        let now = 1_000;
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/treasury").unwrap();
+        let options = VerifyOptions::default();
+        let signers: Vec<TestSigner> = (1..=3).map(TestSigner::new).collect();
+        let treasury = Address::repeat_byte(0xee);
+        let policy = MultisigPolicy::new(2, signers.iter().map(TestSigner::address).collect()).unwrap();
+        let registry = MemoryMultisigRegistry::new();
+        registry.insert(8453, treasury, policy.clone());
+
+        let mut payload = signers[0].pay(&requirements, now).unwrap().payment;
+        payload.payer = treasury;
+        policy.insert_into(&mut payload.extra);
+        let sign = |by: &[usize]| {
+            let signatures = by.iter().map(|&i| signers[i].sign(payload.clone()).unwrap().signature).collect();
+            SignedPayment { payment: payload.clone(), signature: Vec::new(), signatures }
+        };
+        let verify = |payment: &SignedPayment| verify_multisig_payment_at(payment, &requirements, &options, &registry, now);
+
+        assert_eq!(verify(&sign(&[2, 0])).unwrap(), treasury);
+        assert_eq!(verify(&sign(&[0, 1, 2])).unwrap(), treasury);
+        // A single key can't act for the treasury, alone or repeated
+        assert!(matches!(verify(&sign(&[1])), Err(X402Error::InvalidSignature(_))));
+        assert!(verify(&sign(&[1, 1])).is_err());
+        let mut packed = sign(&[0, 1]);
+        packed.signature = packed.signatures.concat();
+        assert!(verify(&packed).is_err());
+
+        // Outsiders don't count, and a self-declared signer set isn't trusted
+        let outsider = TestSigner::new(9);
+        let mut forged = sign(&[0]);
+        forged.signatures.push(outsider.sign(payload.clone()).unwrap().signature);
+        assert!(verify(&forged).is_err());
+        let mut rogue = payload.clone();
+        MultisigPolicy::new(1, vec![outsider.address()]).unwrap().insert_into(&mut rogue.extra);
+        assert!(verify(&outsider.sign(rogue).unwrap()).is_err());
+        registry.remove(8453, treasury);
+        assert!(verify(&sign(&[0, 1])).is_err());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_payment_dispatches_multisig() {
+        use crate::testing::TestSigner;
+        use crate::{verify_payment_with_options_at, Network};
+
+        let now = 1_000;
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/treasury").unwrap();
+        let signers: Vec<TestSigner> = (1..=2).map(TestSigner::new).collect();
+        let treasury = Address::repeat_byte(0xee);
+        let policy = MultisigPolicy::new(2, signers.iter().map(TestSigner::address).collect()).unwrap();
+        let registry = std::sync::Arc::new(MemoryMultisigRegistry::new());
+        registry.insert(8453, treasury, policy.clone());
+
+        let mut payload = signers[0].pay(&requirements, now).unwrap().payment;
+        payload.payer = treasury;
+        policy.insert_into(&mut payload.extra);
+        let signatures = signers.iter().map(|signer| signer.sign(payload.clone()).unwrap().signature).collect();
+        let payment = SignedPayment { payment: payload.clone(), signature: Vec::new(), signatures };
+
+        let without = VerifyOptions::default();
+        assert!(matches!(
+            verify_payment_with_options_at(&payment, &requirements, &without, now),
+            Err(X402Error::InvalidSignature(_))
+        ));
+        let options = VerifyOptions { multisig: Some(registry), ..Default::default() };
+        assert_eq!(verify_payment_with_options_at(&payment, &requirements, &options, now).unwrap(), treasury);
+
+        // One signer's single signature can't stand in for the policy
+        let single = signers[0].sign(payload).unwrap();
+        assert!(verify_payment_with_options_at(&single, &requirements, &options, now).is_err());
+    }
+}
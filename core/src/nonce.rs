@@ -0,0 +1,242 @@
+//! Payment nonces and the payload builder
+//!
+//! Replay protection keys payments on `(chain, payer, nonce)`, so nonces
+//! must not repeat or be guessable. [`Nonce::random`] draws 64 bits from
+//! the OS CSPRNG; [`Nonce::timestamped`] puts the unix time in the high 32
+//! bits and randomness in the low 32, so nonces also sort by creation time.
+//! [`PaymentPayload::builder`] uses a random nonce unless given one, and
+//! refuses small hand-picked values such as `1`.
+
+use crate::{check_unix_seconds, Extra, Network, PaymentPayload, PaymentRequirements, Result, Scheme, Split, X402Error};
+use alloy_primitives::{Address, U256};
+use std::fmt;
+
+/// A payment nonce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Nonce(pub u64);
+
+impl Nonce {
+    /// Smallest nonce [`PaymentPayload::builder`] accepts; everything below
+    /// is treated as hand-picked
+    pub const MIN_RECOMMENDED: u64 = 1 << 32;
+
+    /// 64 bits from the OS CSPRNG
+    pub fn random() -> Self {
+        loop {
+            let nonce = random_u64();
+            if nonce >= Self::MIN_RECOMMENDED {
+                return Self(nonce);
+            }
+        }
+    }
+
+    /// Current unix time in the high 32 bits, randomness in the low 32
+    pub fn timestamped() -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Self::timestamped_at(now)
+    }
+
+    /// [`Nonce::timestamped`] for the unix time `now`
+    pub fn timestamped_at(now: u64) -> Self {
+        Self((now.max(1) << 32) | (random_u64() & 0xffff_ffff))
+    }
+
+    /// Whether the nonce is small enough to have been picked by hand
+    pub fn is_guessable(&self) -> bool {
+        self.0 < Self::MIN_RECOMMENDED
+    }
+}
+
+fn random_u64() -> u64 {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    u64::from_le_bytes(bytes)
+}
+
+impl From<u64> for Nonce {
+    fn from(nonce: u64) -> Self {
+        Self(nonce)
+    }
+}
+
+impl From<Nonce> for u64 {
+    fn from(nonce: Nonce) -> Self {
+        nonce.0
+    }
+}
+
+impl fmt::Display for Nonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Builder for [`PaymentPayload`]; see [`PaymentPayload::builder`]
+#[derive(Debug, Clone, Default)]
+pub struct PaymentPayloadBuilder {
+    amount: Option<U256>,
+    recipient: Option<Address>,
+    payer: Option<Address>,
+    chain_id: Option<u64>,
+    token: Option<Address>,
+    resource: Option<String>,
+    nonce: Option<Nonce>,
+    expires_at: Option<u64>,
+    scheme: Scheme,
+    extra: Extra,
+    invoice_id: Option<String>,
+    splits: Vec<Split>,
+}
+
+impl PaymentPayload {
+    /// Start building a payload; the nonce defaults to [`Nonce::random`]
+    pub fn builder() -> PaymentPayloadBuilder {
+        PaymentPayloadBuilder::default()
+    }
+}
+
+impl PaymentPayloadBuilder {
+    /// Take amount, recipient, network, token, resource, scheme, extra and
+    /// splits from `requirements`
+    pub fn requirements(mut self, requirements: &PaymentRequirements) -> Self {
+        self.amount = Some(requirements.amount);
+        self.recipient = Some(requirements.recipient);
+        self.chain_id = Some(requirements.network.chain_id());
+        self.token = requirements.token;
+        self.resource = Some(requirements.resource.clone());
+        self.scheme = requirements.scheme;
+        self.extra = requirements.extra.clone();
+        self.splits = requirements.splits.clone();
+        self
+    }
+
+    pub fn amount(mut self, amount: U256) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn recipient(mut self, recipient: Address) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    pub fn payer(mut self, payer: Address) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
+    pub fn network(mut self, network: Network) -> Self {
+        self.chain_id = Some(network.chain_id());
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn token(mut self, token: Option<Address>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn resource(mut self, resource: impl Into<String>) -> Self {
+        self.resource = Some(resource.into());
+        self
+    }
+
+    /// Use `nonce`; [`build`](Self::build) rejects guessable values
+    pub fn nonce(mut self, nonce: impl Into<Nonce>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Expiry (unix timestamp)
+    pub fn expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    pub fn extra(mut self, extra: Extra) -> Self {
+        self.extra = extra;
+        self
+    }
+
+    pub fn invoice_id(mut self, invoice_id: impl Into<String>) -> Self {
+        self.invoice_id = Some(invoice_id.into());
+        self
+    }
+
+    pub fn splits(mut self, splits: Vec<Split>) -> Self {
+        self.splits = splits;
+        self
+    }
+
+    /// Fails if a required field is missing, the nonce is guessable or the
+    /// expiry is in milliseconds
+    pub fn build(self) -> Result<PaymentPayload> {
+        let missing = |field: &str| X402Error::InvalidConfig(format!("payment payload is missing {}", field));
+        let nonce = self.nonce.unwrap_or_else(Nonce::random);
+        if nonce.is_guessable() {
+            return Err(X402Error::InvalidConfig(format!(
+                "nonce {} is guessable and risks replays; use Nonce::random() or Nonce::timestamped()",
+                nonce
+            )));
+        }
+        Ok(PaymentPayload {
+            amount: self.amount.ok_or_else(|| missing("amount"))?,
+            recipient: self.recipient.ok_or_else(|| missing("recipient"))?,
+            payer: self.payer.ok_or_else(|| missing("payer"))?,
+            chain_id: self.chain_id.ok_or_else(|| missing("chain_id"))?,
+            token: self.token,
+            resource: self.resource.ok_or_else(|| missing("resource"))?,
+            nonce: nonce.0,
+            expires_at: check_unix_seconds(self.expires_at.ok_or_else(|| missing("expires_at"))?)?,
+            scheme: self.scheme,
+            extra: self.extra,
+            invoice_id: self.invoice_id,
+            splits: self.splits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonces() {
+        let (a, b) = (Nonce::random(), Nonce::random());
+        assert_ne!(a, b);
+        assert!(!a.is_guessable());
+        let timestamped = Nonce::timestamped_at(1_700_000_000);
+        assert_eq!(timestamped.0 >> 32, 1_700_000_000);
+        assert!(Nonce::timestamped_at(1_700_000_001) > timestamped);
+        assert!(Nonce(1).is_guessable());
+    }
+
+    #[test]
+    fn test_builder_enforces_nonce() {
+        let requirements = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api").unwrap();
+        let builder = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .expires_at(1_700_000_300);
+        let payload = builder.clone().build().unwrap();
+        assert_eq!(payload.amount, U256::from(10_000));
+        assert_eq!(payload.chain_id, 8453);
+        assert!(!Nonce(payload.nonce).is_guessable());
+
+        assert!(matches!(builder.clone().nonce(1).build(), Err(X402Error::InvalidConfig(_))));
+        assert_eq!(builder.clone().nonce(Nonce::timestamped()).build().unwrap().token, requirements.token);
+        assert!(PaymentPayload::builder().payer(Address::ZERO).build().is_err());
+    }
+}
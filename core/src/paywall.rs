@@ -0,0 +1,206 @@
+//! HTML paywall pages for browsers
+//!
+//! A browser opening a priced URL should get a page, not a bare 402 with
+//! a JSON body. [`prefers_html`] reads the `Accept` header to decide, and a
+//! [`PaywallRenderer`] turns the requirements into that page. The default,
+//! [`DefaultPaywall`], shows the price and network, links a wallet to the
+//! payment with a [`payment_uri`], and tells programmatic clients to retry
+//! with an `X-Payment` header. It can also poll a status URL and reload
+//! once it stops answering 402, for servers that also accept payments made
+//! on another device.
+//!
+//! A plain transfer from a wallet produces no `X-Payment` authorization, so
+//! the wallet link unlocks the resource only on servers that watch the
+//! chain for such transfers; pair it with [`DefaultPaywall::with_poll`]
+//! there.
+
+use crate::{ChainInfo, Money, PaymentRequirements, Stablecoin};
+use std::time::Duration;
+
+/// Turns payment requirements into an HTML paywall page
+pub trait PaywallRenderer: Send + Sync {
+    /// The page for `requirements`; `error` says why a submitted payment
+    /// was rejected, if one was
+    fn render(&self, requirements: &PaymentRequirements, error: Option<&str>) -> String;
+}
+
+/// Whether an `Accept` header ranks `text/html` above `application/json`
+///
+/// Ties go to JSON, so `*/*` from scripts and HTTP libraries keeps getting
+/// the machine-readable 402.
+pub fn prefers_html(accept: &str) -> bool {
+    let html = quality(accept, "text", "html");
+    html > 0.0 && html > quality(accept, "application", "json")
+}
+
+/// Quality the most specific matching media range in `accept` gives `kind/subtype`
+fn quality(accept: &str, kind: &str, subtype: &str) -> f32 {
+    let mut best: Option<(u8, f32)> = None;
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let Some((range_kind, range_subtype)) = params.next().and_then(|media| media.trim().split_once('/')) else {
+            continue;
+        };
+        let specificity = match (range_kind, range_subtype) {
+            (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => 2,
+            (k, "*") if k.eq_ignore_ascii_case(kind) => 1,
+            ("*", "*") => 0,
+            _ => continue,
+        };
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if best.is_none_or(|(current, _)| specificity > current) {
+            best = Some((specificity, q));
+        }
+    }
+    best.map_or(0.0, |(_, q)| q)
+}
+
+/// EIP-681 URI that opens a wallet on a transfer of the required amount
+///
+/// The transfer is an ordinary on-chain payment, not an x402 one: only a
+/// server that detects it on chain can grant access for it. `None` for
+/// split payments, which no single transfer can make.
+pub fn payment_uri(requirements: &PaymentRequirements) -> Option<String> {
+    if !requirements.splits.is_empty() {
+        return None;
+    }
+    let chain_id = requirements.network.chain_id();
+    Some(match requirements.token {
+        Some(token) => format!(
+            "ethereum:{}@{}/transfer?address={}&uint256={}",
+            token, chain_id, requirements.recipient, requirements.amount
+        ),
+        None => format!("ethereum:{}@{}?value={}", requirements.recipient, chain_id, requirements.amount),
+    })
+}
+
+/// Built-in paywall page
+#[derive(Debug, Clone, Default)]
+pub struct DefaultPaywall {
+    poll: Option<(String, Duration)>,
+}
+
+impl DefaultPaywall {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Poll `url` every `interval` and reload the page once it answers
+    /// anything but 402
+    pub fn with_poll(mut self, url: impl Into<String>, interval: Duration) -> Self {
+        self.poll = Some((url.into(), interval));
+        self
+    }
+}
+
+impl PaywallRenderer for DefaultPaywall {
+    fn render(&self, requirements: &PaymentRequirements, error: Option<&str>) -> String {
+        let chain = ChainInfo::builtin(requirements.network);
+        let mut page = format!(
+            "<!doctype html><html><head><meta charset=\"utf-8\">\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+             <title>Payment required</title></head><body><main>\
+             <h1>Payment required</h1>\
+             <p><code>{}</code> costs <strong>{}</strong> on {}, paid to <code>{}</code>.</p>",
+            escape_html(&requirements.resource),
+            escape_html(&price_label(requirements, &chain.native_symbol)),
+            escape_html(&chain.name),
+            requirements.recipient
+        );
+        if let Some(description) = &requirements.description {
+            page.push_str(&format!("<p>{}</p>", escape_html(description)));
+        }
+        if let Some(error) = error {
+            page.push_str(&format!("<p role=\"alert\">Payment rejected: {}</p>", escape_html(error)));
+        }
+        if let Some(uri) = payment_uri(requirements) {
+            page.push_str(&format!("<p><a href=\"{}\">Pay with a wallet</a></p>", escape_html(&uri)));
+        }
+        page.push_str("<p>Programmatic clients: retry with an <code>X-Payment</code> header.</p></main>");
+        if let Some((url, interval)) = &self.poll {
+            let url = serde_json::to_string(url).unwrap_or_default().replace("</", "<\\/");
+            page.push_str(&format!(
+                "<script>setInterval(async () => {{ try {{ \
+                 const response = await fetch({}, {{ headers: {{ Accept: \"application/json\" }} }}); \
+                 if (response.status !== 402) location.reload(); }} catch (e) {{}} }}, {});</script>",
+                url,
+                interval.as_millis()
+            ));
+        }
+        page.push_str("</body></html>");
+        page
+    }
+}
+
+/// `0.01 USDC` for registry stablecoins, native amounts in whole coins,
+/// and base units for anything else
+fn price_label(requirements: &PaymentRequirements, native_symbol: &str) -> String {
+    let money = match requirements.token {
+        Some(token) => Stablecoin::from_address(requirements.network, token)
+            .map(|coin| Money::new(requirements.amount, coin.decimals(), Some(coin.symbol().to_string()))),
+        None => Some(Money::new(requirements.amount, 18, Some(native_symbol.to_string()))),
+    };
+    money.map_or_else(|| format!("{} base units", requirements.amount), |money| money.to_string())
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Network;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn test_prefers_html() {
+        assert!(prefers_html("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"));
+        assert!(!prefers_html("*/*"));
+        assert!(!prefers_html("application/json"));
+        assert!(!prefers_html("application/json, text/html;q=0.5"));
+        assert!(!prefers_html("text/html;q=0, */*"));
+        assert!(prefers_html("text/*, application/json;q=0.2"));
+        assert!(!prefers_html(""));
+    }
+
+    #[test]
+    fn test_default_paywall() {
+        let mut requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/a?b=<c>").unwrap();
+        requirements.description = Some("<script>alert(1)</script>".to_string());
+        let uri = payment_uri(&requirements).unwrap();
+        assert!(uri.starts_with(&format!("ethereum:{}@8453/transfer?", requirements.token.unwrap())));
+        assert!(uri.ends_with("uint256=10000"));
+
+        let paywall = DefaultPaywall::new().with_poll("/status</script>", Duration::from_secs(2));
+        let page = paywall.render(&requirements, None);
+        assert!(page.contains("0.01 USDC"));
+        assert!(page.contains("/a?b=&lt;c&gt;"));
+        assert!(!page.contains("<script>alert"));
+        assert!(page.contains("\"/status<\\/script>\""));
+        assert!(page.contains(", 2000);"));
+        assert!(!page.contains("role=\"alert\""));
+        assert!(page.contains(&format!("<a href=\"{}\">", escape_html(&uri))));
+        assert!(page.contains("<code>X-Payment</code>"));
+        assert!(DefaultPaywall::new().render(&requirements, Some("expired")).contains("Payment rejected: expired"));
+
+        requirements.splits = vec![crate::Split { recipient: Address::repeat_byte(0x22), share: requirements.amount }];
+        assert!(payment_uri(&requirements).is_none());
+        assert!(!DefaultPaywall::new().render(&requirements, None).contains("ethereum:"));
+    }
+}
@@ -0,0 +1,360 @@
+//! Bounded worker pool for signature verification
+//!
+//! Recovering a secp256k1 signature takes tens of microseconds of CPU. Run
+//! inline on an async executor under burst load, it starves every other
+//! task. [`VerifierPool`] moves verification onto a fixed set of threads
+//! behind a bounded queue: submissions beyond the queue capacity fail fast
+//! with [`X402Error::VerifierOverloaded`] instead of piling up. A job
+//! that panics (e.g. in a [`MultisigRegistry`](crate::MultisigRegistry))
+//! fails with [`X402Error::VerifierPanicked`]; its worker carries on.
+//!
+//! The [`VerifyHandle`] returned for each job is a `Future`, so async
+//! servers can `.await` it without blocking, and it can also be waited on
+//! synchronously.
+
+use crate::{verify_payment_with_options, PaymentRequirements, Result, SignedPayment, VerifyOptions, X402Error};
+use alloy_primitives::Address;
+use std::any::Any;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+
+/// Point-in-time pool statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Jobs waiting for a worker
+    pub queue_depth: usize,
+    /// Maximum number of waiting jobs
+    pub queue_capacity: usize,
+    /// Jobs currently being verified
+    pub in_flight: usize,
+    /// Jobs finished since the pool started
+    pub completed: u64,
+    /// Submissions refused because the queue was full
+    pub rejected: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+#[derive(Default)]
+struct Slot {
+    result: Option<Result<Address>>,
+    waker: Option<Waker>,
+}
+
+type Shared = Arc<(Mutex<Slot>, Condvar)>;
+
+struct Job {
+    payment: SignedPayment,
+    requirements: PaymentRequirements,
+    slot: Shared,
+}
+
+/// Pending result of a submitted verification
+pub struct VerifyHandle {
+    slot: Shared,
+}
+
+impl VerifyHandle {
+    /// Block the current thread until the result is available
+    pub fn wait(self) -> Result<Address> {
+        let (lock, ready) = &*self.slot;
+        let mut slot = lock.lock().unwrap();
+        loop {
+            if let Some(result) = slot.result.take() {
+                return result;
+            }
+            slot = ready.wait(slot).unwrap();
+        }
+    }
+}
+
+impl Future for VerifyHandle {
+    type Output = Result<Address>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.0.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Fixed-size thread pool running [`verify_payment_with_options`]
+pub struct VerifierPool {
+    queue: Option<SyncSender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    counters: Arc<Counters>,
+    capacity: usize,
+}
+
+impl VerifierPool {
+    /// Start `threads` workers sharing a queue of `queue_capacity` jobs
+    pub fn new(threads: usize, queue_capacity: usize) -> Self {
+        Self::with_options(threads, queue_capacity, VerifyOptions::default())
+    }
+
+    /// [`VerifierPool::new`], verifying with `options`
+    pub fn with_options(threads: usize, queue_capacity: usize, options: VerifyOptions) -> Self {
+        let options = Arc::new(options);
+        let (queue, jobs) = mpsc::sync_channel::<Job>(queue_capacity);
+        let jobs = Arc::new(Mutex::new(jobs));
+        let counters = Arc::new(Counters::default());
+
+        let workers = (0..threads.max(1))
+            .map(|i| {
+                let jobs = Arc::clone(&jobs);
+                let counters = Arc::clone(&counters);
+                let options = Arc::clone(&options);
+                std::thread::Builder::new()
+                    .name(format!("x402-verify-{}", i))
+                    .spawn(move || worker_loop(&jobs, &counters, &options))
+                    .expect("failed to spawn verification worker")
+            })
+            .collect();
+
+        Self {
+            queue: Some(queue),
+            workers,
+            counters,
+            capacity: queue_capacity,
+        }
+    }
+
+    /// Queue a payment for verification
+    ///
+    /// Fails immediately with [`X402Error::VerifierOverloaded`] when the
+    /// queue is full.
+    pub fn submit(&self, payment: SignedPayment, requirements: PaymentRequirements) -> Result<VerifyHandle> {
+        let slot: Shared = Arc::default();
+        let job = Job {
+            payment,
+            requirements,
+            slot: Arc::clone(&slot),
+        };
+
+        let queue = self.queue.as_ref().expect("pool is running");
+        self.counters.queued.fetch_add(1, Ordering::SeqCst);
+        match queue.try_send(job) {
+            Ok(()) => Ok(VerifyHandle { slot }),
+            Err(e) => {
+                self.counters.queued.fetch_sub(1, Ordering::SeqCst);
+                self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(X402Error::VerifierOverloaded(match e {
+                    TrySendError::Full(_) => format!("queue full ({} jobs)", self.capacity),
+                    TrySendError::Disconnected(_) => "pool stopped".to_string(),
+                }))
+            }
+        }
+    }
+
+    /// Verify on the pool, waiting asynchronously for the result
+    pub async fn verify(&self, payment: SignedPayment, requirements: PaymentRequirements) -> Result<Address> {
+        self.submit(payment, requirements)?.await
+    }
+
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            queue_depth: self.counters.queued.load(Ordering::SeqCst),
+            queue_capacity: self.capacity,
+            in_flight: self.counters.in_flight.load(Ordering::SeqCst),
+            completed: self.counters.completed.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for VerifierPool {
+    /// Finish queued jobs, then stop the workers
+    fn drop(&mut self) {
+        drop(self.queue.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker_loop(jobs: &Mutex<Receiver<Job>>, counters: &Counters, options: &VerifyOptions) {
+    loop {
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        counters.queued.fetch_sub(1, Ordering::SeqCst);
+        counters.in_flight.fetch_add(1, Ordering::SeqCst);
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            verify_payment_with_options(&job.payment, &job.requirements, options)
+        }))
+        .unwrap_or_else(|panic| Err(X402Error::VerifierPanicked(panic_message(panic.as_ref()))));
+
+        counters.in_flight.fetch_sub(1, Ordering::SeqCst);
+        counters.completed.fetch_add(1, Ordering::Relaxed);
+
+        let (lock, ready) = &*job.slot;
+        let mut slot = lock.lock().unwrap();
+        slot.result = Some(result);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+        ready.notify_all();
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MultisigPolicy, MultisigRegistry, Network, PaymentPayload, Scheme};
+    use alloy_primitives::U256;
+
+    fn job(signature_len: usize) -> (SignedPayment, PaymentRequirements) {
+        let payment = SignedPayment {
+            payment: PaymentPayload {
+                amount: U256::from(100),
+                recipient: Address::ZERO,
+                payer: Address::ZERO,
+                chain_id: 8453,
+                token: None,
+                resource: "/test".to_string(),
+                nonce: 1,
+                expires_at: u64::MAX,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: vec![0u8; signature_len],
+            signatures: Vec::new(),
+        };
+        let requirements = PaymentRequirements {
+            amount: U256::from(100),
+            recipient: Address::ZERO,
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        (payment, requirements)
+    }
+
+    #[test]
+    fn test_pool_runs_jobs_and_reports_metrics() {
+        let pool = VerifierPool::new(2, 16);
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (payment, requirements) = job(64);
+                pool.submit(payment, requirements).unwrap()
+            })
+            .collect();
+
+        for handle in handles {
+            assert!(matches!(handle.wait(), Err(X402Error::InvalidSignature(_))));
+        }
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.completed, 8);
+        assert_eq!(metrics.queue_depth, 0);
+        assert_eq!(metrics.in_flight, 0);
+    }
+
+    /// A job whose verification consults `VerifyOptions::multisig`
+    fn multisig_job() -> (SignedPayment, PaymentRequirements) {
+        let (mut payment, requirements) = job(0);
+        MultisigPolicy::new(1, vec![Address::repeat_byte(0x55)]).unwrap().insert_into(&mut payment.payment.extra);
+        (payment, requirements)
+    }
+
+    /// Registry that blocks each lookup until released, then knows no policy
+    struct Gate {
+        entered: mpsc::SyncSender<()>,
+        release: Mutex<Receiver<()>>,
+    }
+
+    impl MultisigRegistry for Gate {
+        fn policy(&self, _: u64, _: Address) -> Result<Option<MultisigPolicy>> {
+            self.entered.send(()).unwrap();
+            self.release.lock().unwrap().recv().unwrap();
+            Ok(None)
+        }
+    }
+
+    struct Panics;
+
+    impl MultisigRegistry for Panics {
+        fn policy(&self, _: u64, _: Address) -> Result<Option<MultisigPolicy>> {
+            panic!("registry bug")
+        }
+    }
+
+    #[test]
+    fn test_pool_rejects_when_full() {
+        let (entered_tx, entered) = mpsc::sync_channel(2);
+        let (release, release_rx) = mpsc::sync_channel(2);
+        let gate = Gate { entered: entered_tx, release: Mutex::new(release_rx) };
+        let options = VerifyOptions { multisig: Some(Arc::new(gate)), ..Default::default() };
+        let pool = VerifierPool::with_options(1, 1, options);
+
+        // The only worker is busy with the first job and the queue holds the second
+        let (payment, requirements) = multisig_job();
+        let first = pool.submit(payment, requirements).unwrap();
+        entered.recv().unwrap();
+        let (payment, requirements) = multisig_job();
+        let second = pool.submit(payment, requirements).unwrap();
+
+        let (payment, requirements) = multisig_job();
+        assert!(matches!(pool.submit(payment, requirements), Err(X402Error::VerifierOverloaded(_))));
+        let metrics = pool.metrics();
+        assert_eq!((metrics.rejected, metrics.queue_depth, metrics.in_flight), (1, 1, 1));
+
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        assert!(matches!(first.wait(), Err(X402Error::InvalidSignature(_))));
+        assert!(matches!(second.wait(), Err(X402Error::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_pool_reports_panics_and_keeps_working() {
+        let options = VerifyOptions { multisig: Some(Arc::new(Panics)), ..Default::default() };
+        let pool = VerifierPool::with_options(1, 4, options);
+
+        let (payment, requirements) = multisig_job();
+        let result = pool.submit(payment, requirements).unwrap().wait();
+        assert!(matches!(result, Err(X402Error::VerifierPanicked(message)) if message == "registry bug"));
+
+        let (payment, requirements) = job(64);
+        assert!(matches!(pool.submit(payment, requirements).unwrap().wait(), Err(X402Error::InvalidSignature(_))));
+        assert_eq!(pool.metrics().completed, 2);
+    }
+}
@@ -0,0 +1,210 @@
+//! Fiat-denominated pricing
+//!
+//! Servers usually price in dollars, not token units. A [`FiatPrice`]
+//! ("1 cent USD") and the token to accept are converted to token units by a
+//! pluggable [`RateProvider`] when the requirements are built
+//! ([`price_requirements`]). The [`PriceQuote`] used travels in the
+//! requirements so clients can see the conversion, and verification
+//! rejects payments against quotes whose rate has gone stale
+//! ([`check_price_quote`]).
+
+use crate::{Network, PaymentRequirements, Result, X402Error};
+use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Fiat amounts are counted in millionths of the currency unit
+pub const FIAT_MICROS_PER_UNIT: u64 = 1_000_000;
+
+/// A price in a fiat currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FiatPrice {
+    /// ISO 4217 code, e.g. `USD`
+    pub currency: String,
+    /// Amount in millionths of the currency unit
+    pub micros: u64,
+}
+
+impl FiatPrice {
+    pub fn usd_cents(cents: u64) -> Self {
+        Self { currency: "USD".to_string(), micros: cents * (FIAT_MICROS_PER_UNIT / 100) }
+    }
+
+    pub fn usd_micros(micros: u64) -> Self {
+        Self { currency: "USD".to_string(), micros }
+    }
+}
+
+/// Price of a token in a fiat currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// Fiat micros per whole token
+    pub micros_per_token: u64,
+    /// Token decimals
+    pub decimals: u8,
+    /// When the rate was observed (unix timestamp)
+    pub as_of: u64,
+}
+
+impl ExchangeRate {
+    /// Token units (smallest unit) worth `micros` of the fiat currency,
+    /// rounded up so the server is never underpaid
+    pub fn to_token_units(&self, micros: u64) -> Result<U256> {
+        if self.micros_per_token == 0 {
+            return Err(X402Error::InvalidConfig(format!("zero exchange rate for {}", self.currency)));
+        }
+        let scale = U256::from(10).checked_pow(U256::from(self.decimals));
+        let numerator = scale.and_then(|scale| U256::from(micros).checked_mul(scale)).ok_or_else(|| {
+            X402Error::InvalidConfig(format!("{} token decimals overflow the amount", self.decimals))
+        })?;
+        Ok(numerator.div_ceil(U256::from(self.micros_per_token)))
+    }
+}
+
+/// Source of exchange rates, e.g. an oracle or price API client
+pub trait RateProvider: Send + Sync {
+    fn rate(&self, network: Network, token: Option<Address>, currency: &str) -> Result<ExchangeRate>;
+}
+
+/// [`RateProvider`] with fixed rates, for pegged stablecoins and tests
+///
+/// Rates are reported as observed at the time of the call.
+#[derive(Debug, Clone, Default)]
+pub struct FixedRateProvider {
+    rates: HashMap<(Network, Option<Address>, String), (u64, u8)>,
+}
+
+impl FixedRateProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Price `token` on `network` at `micros_per_token` of `currency`
+    pub fn with_rate(
+        mut self,
+        network: Network,
+        token: Option<Address>,
+        currency: impl Into<String>,
+        micros_per_token: u64,
+        decimals: u8,
+    ) -> Self {
+        self.rates.insert((network, token, currency.into()), (micros_per_token, decimals));
+        self
+    }
+}
+
+impl RateProvider for FixedRateProvider {
+    fn rate(&self, network: Network, token: Option<Address>, currency: &str) -> Result<ExchangeRate> {
+        let (micros_per_token, decimals) = *self.rates.get(&(network, token, currency.to_string())).ok_or_else(|| {
+            X402Error::InvalidConfig(format!("no {} rate for token {:?} on {:?}", currency, token, network))
+        })?;
+        let as_of = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Ok(ExchangeRate { currency: currency.to_string(), micros_per_token, decimals, as_of })
+    }
+}
+
+/// The fiat price and rate a requirements amount was computed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceQuote {
+    pub price: FiatPrice,
+    pub rate: ExchangeRate,
+    /// How long after `rate.as_of` payments against the quote are accepted
+    pub max_staleness_seconds: u64,
+}
+
+/// Set `requirements.amount` to `price` in its token, converted at the
+/// provider's current rate, and attach the quote
+pub fn price_requirements<P: RateProvider + ?Sized>(
+    mut requirements: PaymentRequirements,
+    price: &FiatPrice,
+    provider: &P,
+    max_staleness_seconds: u64,
+) -> Result<PaymentRequirements> {
+    let rate = provider.rate(requirements.network, requirements.token, &price.currency)?;
+    if rate.currency != price.currency {
+        return Err(X402Error::InvalidConfig(format!(
+            "provider returned a {} rate for a {} price",
+            rate.currency, price.currency
+        )));
+    }
+    requirements.amount = rate.to_token_units(price.micros)?;
+    requirements.price_quote = Some(PriceQuote { price: price.clone(), rate, max_staleness_seconds });
+    Ok(requirements)
+}
+
+/// Check the price quote on `requirements`, if any, as of the unix time
+/// `now`: the rate must be fresh and the amount must match the conversion
+pub fn check_price_quote(requirements: &PaymentRequirements, now: u64) -> Result<()> {
+    let Some(quote) = &requirements.price_quote else {
+        return Ok(());
+    };
+    let age_seconds = now.saturating_sub(quote.rate.as_of);
+    if age_seconds > quote.max_staleness_seconds {
+        return Err(X402Error::StaleExchangeRate { age_seconds, max_seconds: quote.max_staleness_seconds });
+    }
+    if quote.rate.to_token_units(quote.price.micros)? != requirements.amount {
+        return Err(X402Error::InvalidQuote("amount does not match the price quote".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    struct EthRate;
+
+    impl RateProvider for EthRate {
+        fn rate(&self, _: Network, _: Option<Address>, currency: &str) -> Result<ExchangeRate> {
+            // 1 ETH = $3000
+            Ok(ExchangeRate { currency: currency.to_string(), micros_per_token: 3_000_000_000, decimals: 18, as_of: 1_000 })
+        }
+    }
+
+    fn requirements(token: Option<Address>) -> PaymentRequirements {
+        PaymentRequirements {
+            amount: U256::ZERO,
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token,
+            description: None,
+            expires_at: None,
+            resource: "/api/data".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        }
+    }
+
+    #[test]
+    fn test_fiat_conversion() {
+        let priced = price_requirements(requirements(None), &FiatPrice::usd_cents(1), &EthRate, 30).unwrap();
+        // $0.01 / $3000 = 3.33e-6 ETH, rounded up
+        assert_eq!(priced.amount, U256::from(3_333_333_333_334u64));
+        check_price_quote(&priced, 1_030).unwrap();
+        assert!(matches!(check_price_quote(&priced, 1_031), Err(X402Error::StaleExchangeRate { age_seconds: 31, .. })));
+
+        let mut tampered = priced.clone();
+        tampered.amount -= U256::from(1);
+        assert!(matches!(check_price_quote(&tampered, 1_000), Err(X402Error::InvalidQuote(_))));
+
+        let usdc = Some(Address::repeat_byte(0x33));
+        let provider = FixedRateProvider::new().with_rate(Network::Base, usdc, "USD", FIAT_MICROS_PER_UNIT, 6);
+        let priced = price_requirements(requirements(usdc), &FiatPrice::usd_cents(1), &provider, 30).unwrap();
+        assert_eq!(priced.amount, U256::from(10_000));
+        assert!(price_requirements(requirements(None), &FiatPrice::usd_cents(1), &provider, 30).is_err());
+    }
+}
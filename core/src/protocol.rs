@@ -1,7 +1,18 @@
 //! x402 protocol header encoding/decoding
 
-use crate::{PaymentRequirements, SignedPayment, X402Error, Result};
-use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use crate::trace::traced;
+use crate::validate::ensure_encodable;
+use crate::{
+    decode_payment_binary_with_limits, encode_payment_binary, LightningRequirements, PaymentRequirements, SignedPayment, X402Error, ZkRequirements,
+    Result, BINARY_FORMAT_V1,
+};
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD as BASE64};
+use base64::engine::DecodePaddingMode;
+use base64::Engine as _;
+use http::{HeaderName, HeaderValue};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use zeroize::Zeroizing;
 
 /// Header name for payment requirements (server → client)
 pub const X402_REQUIREMENTS_HEADER: &str = "X-Payment-Requirements";
@@ -9,78 +20,605 @@ pub const X402_REQUIREMENTS_HEADER: &str = "X-Payment-Requirements";
 /// Header name for signed payment (client → server)
 pub const X402_PAYMENT_HEADER: &str = "X-Payment";
 
+/// [`X402_REQUIREMENTS_HEADER`] as an `http` header name
+pub const X402_REQUIREMENTS_HEADER_NAME: HeaderName = HeaderName::from_static("x-payment-requirements");
+
+/// [`X402_PAYMENT_HEADER`] as an `http` header name
+pub const X402_PAYMENT_HEADER_NAME: HeaderName = HeaderName::from_static("x-payment");
+
+/// Leading component of a tagged header value, e.g. `x402.v2.json.<base64>`
+///
+/// The tag names the header format version and the payload encoding, so
+/// decoders can dispatch without guessing and report unsupported versions
+/// precisely. Base64 never contains `.`, so tagged and untagged values
+/// can't be confused.
+pub const HEADER_TAG: &str = "x402";
+
+/// Header format version written by the encoders
+///
+/// Untagged values (plain base64, format sniffed from the first decoded
+/// byte) are version 1 and still accepted.
+pub const HEADER_FORMAT_VERSION: u32 = 2;
+
+/// First byte of a decoded untagged header value in the CBOR format
+///
+/// JSON header values always start with `{`, so a single leading byte is
+/// enough to tell the formats apart.
+pub const CBOR_FORMAT_PREFIX: u8 = 0x01;
+
+/// Payload encoding of a header value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    Json,
+    Cbor,
+    /// The fixed layout in [`crate::binary`]; payments only
+    #[serde(rename = "bin")]
+    Binary,
+}
+
+impl WireFormat {
+    pub const ALL: [WireFormat; 3] = [WireFormat::Json, WireFormat::Cbor, WireFormat::Binary];
+
+    /// Name used in header tags
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "json",
+            WireFormat::Cbor => "cbor",
+            WireFormat::Binary => "bin",
+        }
+    }
+}
+
+impl std::str::FromStr for WireFormat {
+    type Err = X402Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(WireFormat::Json),
+            "cbor" => Ok(WireFormat::Cbor),
+            "bin" => Ok(WireFormat::Binary),
+            other => Err(X402Error::InvalidHeader(format!("unknown wire format: {}", other))),
+        }
+    }
+}
+
+/// Bounds applied to attacker-controlled header values while decoding
+///
+/// The header length is checked before anything is allocated, which in
+/// turn bounds every later allocation. Start from [`DecodeLimits::default`]
+/// and adjust with the `with_*` methods; new limits may be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DecodeLimits {
+    /// Maximum length of the encoded (base64) header value
+    pub max_header_len: usize,
+    /// Maximum length of the `resource` field
+    pub max_resource_len: usize,
+    /// Maximum length of the `description` field
+    pub max_description_len: usize,
+    /// Maximum nesting depth of JSON/CBOR structures
+    pub max_depth: usize,
+    /// What to do with fields the decoder doesn't recognise
+    pub unknown_fields: UnknownFields,
+}
+
+/// Handling of unrecognised fields in JSON and CBOR header values
+///
+/// The binary format has a fixed layout and no room for unknown fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownFields {
+    /// Accept them, for forward compatibility with newer peers. Unknown
+    /// top-level fields of payment requirements move into `extra`, so they
+    /// survive re-encoding; elsewhere they are dropped. Payments can't
+    /// keep them: `extra` is signed, and moving fields into it would break
+    /// the payer's signature.
+    #[default]
+    Preserve,
+    /// Reject the header, for facilitators that must not act on content
+    /// they don't understand
+    Reject,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_header_len: 8 * 1024,
+            max_resource_len: 2048,
+            max_description_len: 1024,
+            max_depth: 16,
+            unknown_fields: UnknownFields::Preserve,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Set [`max_header_len`](Self::max_header_len)
+    pub fn with_max_header_len(mut self, max_header_len: usize) -> Self {
+        self.max_header_len = max_header_len;
+        self
+    }
+
+    /// Set [`max_resource_len`](Self::max_resource_len)
+    pub fn with_max_resource_len(mut self, max_resource_len: usize) -> Self {
+        self.max_resource_len = max_resource_len;
+        self
+    }
+
+    /// Set [`max_description_len`](Self::max_description_len)
+    pub fn with_max_description_len(mut self, max_description_len: usize) -> Self {
+        self.max_description_len = max_description_len;
+        self
+    }
+
+    /// Set [`max_depth`](Self::max_depth)
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set [`unknown_fields`](Self::unknown_fields)
+    pub fn with_unknown_fields(mut self, unknown_fields: UnknownFields) -> Self {
+        self.unknown_fields = unknown_fields;
+        self
+    }
+
+    pub(crate) fn check(what: &'static str, limit: usize, actual: usize) -> Result<()> {
+        if actual > limit {
+            return Err(X402Error::LimitExceeded { what, limit, actual });
+        }
+        Ok(())
+    }
+
+    /// [`UnknownFields::Reject`] the fields `tracked` ignored
+    pub(crate) fn check_unknown<T>(&self, tracked: Tracked<T>) -> Result<T> {
+        if self.unknown_fields == UnknownFields::Reject {
+            if let Some(path) = tracked.ignored.first() {
+                return Err(X402Error::InvalidHeader(format!("unknown field {}", path)));
+            }
+        }
+        Ok(tracked.value)
+    }
+}
+
+/// A decoded value and the paths of the fields it ignored
+pub(crate) struct Tracked<T> {
+    pub value: T,
+    pub ignored: Vec<String>,
+    /// Ignored fields of the top-level object
+    pub top_level: Vec<String>,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Tracked<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let (mut ignored, mut top_level) = (Vec::new(), Vec::new());
+        let value = serde_ignored::deserialize(deserializer, |path| {
+            if let serde_ignored::Path::Map { parent: serde_ignored::Path::Root, key } = &path {
+                top_level.push(key.clone());
+            }
+            ignored.push(path.to_string());
+        })?;
+        Ok(Self { value, ignored, top_level })
+    }
+}
+
 /// Encode payment requirements to header value
 /// 
 /// # Example
 /// ```
-/// use x402_core::{PaymentRequirements, encode_requirements_header, Network};
+/// use x402_core::{PaymentRequirements, encode_requirements_header, Network, Scheme};
 /// use alloy_primitives::{Address, U256};
 /// 
 /// let requirements = PaymentRequirements {
 ///     amount: U256::from(1000000),
-///     recipient: Address::ZERO,
+///     recipient: Address::repeat_byte(0x11),
 ///     network: Network::Base,
 ///     token: None,
 ///     description: Some("API access".to_string()),
 ///     expires_at: None,
 ///     resource: "/api/data".to_string(),
+///     commitment: None,
+///     scheme: Scheme::Exact,
+///     mime_type: None,
+///     output_schema: None,
+///     max_timeout_seconds: None,
+///     extra: Default::default(),
+///     splits: Vec::new(),
+///     price_quote: None,
 /// };
 /// 
 /// let header = encode_requirements_header(&requirements).unwrap();
 /// ```
 pub fn encode_requirements_header(requirements: &PaymentRequirements) -> Result<String> {
-    let json = serde_json::to_string(requirements)
-        .map_err(|e| X402Error::EncodingError(e.to_string()))?;
-    Ok(BASE64.encode(json.as_bytes()))
+    traced!("x402.encode_requirements", { network = ?requirements.network, amount = %requirements.amount, resource = %requirements.resource }, {
+        ensure_encodable(requirements.validate())?;
+        let json = serde_json::to_string(requirements)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    })
+}
+
+/// Encode payment requirements to an `http` header value
+pub fn encode_requirements_header_value(requirements: &PaymentRequirements) -> Result<HeaderValue> {
+    to_header_value(encode_requirements_header(requirements)?)
 }
 
 /// Decode payment requirements from header value
-pub fn decode_requirements_header(header: &str) -> Result<PaymentRequirements> {
-    let bytes = BASE64.decode(header)
-        .map_err(|e| X402Error::InvalidHeader(format!("base64 decode failed: {}", e)))?;
-    
-    let json = String::from_utf8(bytes)
-        .map_err(|e| X402Error::InvalidHeader(format!("invalid UTF-8: {}", e)))?;
-    
-    serde_json::from_str(&json)
-        .map_err(|e| X402Error::InvalidHeader(format!("JSON parse failed: {}", e)))
+///
+/// Accepts tagged and untagged values in the JSON or CBOR wire format.
+/// The value may be a `&str`, `String` or `http::HeaderValue`; surrounding
+/// whitespace is ignored.
+pub fn decode_requirements_header(header: impl AsRef<[u8]>) -> Result<PaymentRequirements> {
+    decode_requirements_header_with_limits(header, &DecodeLimits::default())
+}
+
+/// Decode payment requirements, enforcing custom [`DecodeLimits`]
+pub fn decode_requirements_header_with_limits(
+    header: impl AsRef<[u8]>,
+    limits: &DecodeLimits,
+) -> Result<PaymentRequirements> {
+    traced!("x402.decode_requirements", { header_len = header.as_ref().len() }, {
+        let (format, bytes) = decode_header(header.as_ref(), limits)?;
+        let tracked: Tracked<PaymentRequirements> = parse_payload_tracked(format, &bytes, limits)?;
+        let unknown = tracked.top_level.clone();
+        let mut requirements = limits.check_unknown(tracked)?;
+        if !unknown.is_empty() {
+            preserve_unknown_fields(format, &bytes, limits, &unknown, &mut requirements.extra)?;
+        }
+        DecodeLimits::check("resource", limits.max_resource_len, requirements.resource.len())?;
+        if let Some(description) = &requirements.description {
+            DecodeLimits::check("description", limits.max_description_len, description.len())?;
+        }
+        Ok(requirements)
+    })
 }
 
 /// Encode signed payment to header value
 pub fn encode_payment_header(payment: &SignedPayment) -> Result<String> {
-    let json = serde_json::to_string(payment)
-        .map_err(|e| X402Error::EncodingError(e.to_string()))?;
-    Ok(BASE64.encode(json.as_bytes()))
+    traced!("x402.encode_payment", { chain_id = payment.payment.chain_id, payer = %payment.payment.payer, amount = %payment.payment.amount, nonce = payment.payment.nonce }, {
+        ensure_encodable(payment.payment.validate())?;
+        let json = serde_json::to_string(payment)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    })
+}
+
+/// Encode signed payment to an `http` header value
+pub fn encode_payment_header_value(payment: &SignedPayment) -> Result<HeaderValue> {
+    to_header_value(encode_payment_header(payment)?)
 }
 
 /// Decode signed payment from header value
-pub fn decode_payment_header(header: &str) -> Result<SignedPayment> {
-    let bytes = BASE64.decode(header)
-        .map_err(|e| X402Error::InvalidHeader(format!("base64 decode failed: {}", e)))?;
-    
-    let json = String::from_utf8(bytes)
-        .map_err(|e| X402Error::InvalidHeader(format!("invalid UTF-8: {}", e)))?;
-    
-    serde_json::from_str(&json)
-        .map_err(|e| X402Error::InvalidHeader(format!("JSON parse failed: {}", e)))
+///
+/// Accepts the JSON, CBOR and binary wire formats, from the same value
+/// types as [`decode_requirements_header`].
+pub fn decode_payment_header(header: impl AsRef<[u8]>) -> Result<SignedPayment> {
+    decode_payment_header_with_limits(header, &DecodeLimits::default())
+}
+
+/// Decode signed payment, enforcing custom [`DecodeLimits`]
+pub fn decode_payment_header_with_limits(header: impl AsRef<[u8]>, limits: &DecodeLimits) -> Result<SignedPayment> {
+    traced!("x402.decode_payment", { header_len = header.as_ref().len() }, {
+        let (format, bytes) = decode_header(header.as_ref(), limits)?;
+        // Holds the signature and signed fields; wiped on drop
+        let bytes = Zeroizing::new(bytes);
+        if format == WireFormat::Binary {
+            return decode_payment_binary_with_limits(&bytes, limits);
+        }
+        let payment: SignedPayment = parse_payload(format, &bytes, limits)?;
+        DecodeLimits::check("resource", limits.max_resource_len, payment.payment.resource.len())?;
+        Ok(payment)
+    })
+}
+
+/// Encode signed payment to a compact binary header value
+///
+/// See [`crate::binary`] for the layout. Headers stay under 300 bytes for
+/// typical resource URLs.
+pub fn encode_payment_header_binary(payment: &SignedPayment) -> Result<String> {
+    ensure_encodable(payment.payment.validate())?;
+    Ok(tagged(WireFormat::Binary, &encode_payment_binary(payment)?))
+}
+
+/// Encode payment requirements to a CBOR header value
+///
+/// Produces smaller headers than [`encode_requirements_header`]; the
+/// regular decoders recognise the format automatically.
+pub fn encode_requirements_header_cbor(requirements: &PaymentRequirements) -> Result<String> {
+    ensure_encodable(requirements.validate())?;
+    encode_cbor(requirements)
+}
+
+/// Encode signed payment to a CBOR header value
+pub fn encode_payment_header_cbor(payment: &SignedPayment) -> Result<String> {
+    ensure_encodable(payment.payment.validate())?;
+    encode_cbor(payment)
+}
+
+fn encode_cbor<T: Serialize>(value: &T) -> Result<String> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes)
+        .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+    Ok(tagged(WireFormat::Cbor, &bytes))
+}
+
+pub(crate) fn tagged(format: WireFormat, payload: &[u8]) -> String {
+    format!(
+        "{}.v{}.{}.{}",
+        HEADER_TAG,
+        HEADER_FORMAT_VERSION,
+        format.as_str(),
+        BASE64.encode(payload)
+    )
+}
+
+fn to_header_value(encoded: String) -> Result<HeaderValue> {
+    HeaderValue::try_from(encoded).map_err(|e| X402Error::EncodingError(e.to_string()))
+}
+
+const LENIENT: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, LENIENT);
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT);
+
+/// Decode a header value in either base64 alphabet, padded or not,
+/// appending to `buf`
+///
+/// Encoding always uses standard padded base64, but some proxies and JS
+/// clients re-encode values URL-safe or strip the padding.
+fn decode_base64_into(header: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+    let engine = if header.iter().any(|b| matches!(b, b'-' | b'_')) {
+        &URL_SAFE_LENIENT
+    } else {
+        &STANDARD_LENIENT
+    };
+    Ok(engine.decode_vec(header, buf)?)
+}
+
+/// Split a header value into its wire format and decoded payload
+pub(crate) fn decode_header(header: &[u8], limits: &DecodeLimits) -> Result<(WireFormat, Vec<u8>)> {
+    let mut bytes = Vec::new();
+    let (format, start) = decode_header_into(header, limits, &mut bytes)?;
+    bytes.drain(..start);
+    Ok((format, bytes))
+}
+
+/// [`decode_header`] into `buf`, replacing its contents and keeping its
+/// capacity; returns the wire format and the offset of the payload in `buf`
+pub(crate) fn decode_header_into(
+    header: &[u8],
+    limits: &DecodeLimits,
+    buf: &mut Vec<u8>,
+) -> Result<(WireFormat, usize)> {
+    DecodeLimits::check("header", limits.max_header_len, header.len())?;
+    let header = header.trim_ascii();
+    buf.clear();
+
+    let Some(rest) = header.strip_prefix(HEADER_TAG.as_bytes()).and_then(|r| r.strip_prefix(b".")) else {
+        decode_base64_into(header, buf)?;
+        return Ok(match buf.first() {
+            Some(&CBOR_FORMAT_PREFIX) => (WireFormat::Cbor, 1),
+            Some(&BINARY_FORMAT_V1) => (WireFormat::Binary, 0),
+            _ => (WireFormat::Json, 0),
+        });
+    };
+
+    let mut parts = rest.splitn(3, |&b| b == b'.');
+    let (Some(version), Some(format), Some(payload)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(X402Error::InvalidHeader("incomplete header tag".to_string()));
+    };
+    let version = std::str::from_utf8(version).unwrap_or_default();
+    if version != format!("v{}", HEADER_FORMAT_VERSION) {
+        return Err(X402Error::UnsupportedVersion(version.to_string()));
+    }
+    let format = std::str::from_utf8(format).unwrap_or_default().parse()?;
+    decode_base64_into(payload, buf)?;
+    Ok((format, 0))
+}
+
+pub(crate) fn parse_payload<T: DeserializeOwned>(format: WireFormat, bytes: &[u8], limits: &DecodeLimits) -> Result<T> {
+    if limits.unknown_fields == UnknownFields::Reject {
+        return limits.check_unknown(parse_payload_tracked(format, bytes, limits)?);
+    }
+    parse_payload_untracked(format, bytes, limits)
+}
+
+/// [`parse_payload`], recording the fields the value ignored
+pub(crate) fn parse_payload_tracked<T: DeserializeOwned>(
+    format: WireFormat,
+    bytes: &[u8],
+    limits: &DecodeLimits,
+) -> Result<Tracked<T>> {
+    parse_payload_untracked(format, bytes, limits)
+}
+
+/// Move the top-level fields `unknown` into `extra`
+///
+/// Fields `extra` already has keep its value, and values without a
+/// canonical form (see [`crate::canonical`]) are dropped as before.
+fn preserve_unknown_fields(
+    format: WireFormat,
+    bytes: &[u8],
+    limits: &DecodeLimits,
+    unknown: &[String],
+    extra: &mut crate::Extra,
+) -> Result<()> {
+    let values: Vec<(String, serde_json::Value)> = match format {
+        WireFormat::Json => {
+            let mut object: std::collections::BTreeMap<String, serde_json::Value> =
+                parse_payload_untracked(format, bytes, limits)?;
+            unknown.iter().filter_map(|key| object.remove_entry(key)).collect()
+        }
+        WireFormat::Cbor => {
+            let mut object: std::collections::BTreeMap<String, ciborium::Value> =
+                parse_payload_untracked(format, bytes, limits)?;
+            unknown
+                .iter()
+                .filter_map(|key| object.remove_entry(key))
+                .filter_map(|(key, value)| Some((key, value.deserialized().ok()?)))
+                .collect()
+        }
+        WireFormat::Binary => Vec::new(),
+    };
+    for (key, value) in values {
+        if crate::check_canonical(&value).is_ok() {
+            extra.entry(key).or_insert(value);
+        }
+    }
+    Ok(())
+}
+
+fn parse_payload_untracked<T: DeserializeOwned>(format: WireFormat, bytes: &[u8], limits: &DecodeLimits) -> Result<T> {
+    match format {
+        WireFormat::Json => {
+            DecodeLimits::check("nesting depth", limits.max_depth, json_depth(bytes))?;
+            serde_json::from_slice(bytes).map_err(X402Error::Json)
+        }
+        WireFormat::Cbor => ciborium::de::from_reader_with_recursion_limit(bytes, limits.max_depth)
+            .map_err(|e| X402Error::Cbor(Box::new(e))),
+        WireFormat::Binary => Err(X402Error::InvalidHeader(
+            "binary format only carries payments".to_string(),
+        )),
+    }
+}
+
+/// Maximum nesting depth of a JSON document, ignoring brackets in strings
+pub(crate) fn json_depth(json: &[u8]) -> usize {
+    let (mut depth, mut max) = (0usize, 0usize);
+    let (mut in_string, mut escaped) = (false, false);
+    for &b in json {
+        match (in_string, b) {
+            (true, _) if escaped => escaped = false,
+            (true, b'\\') => escaped = true,
+            (true, b'"') => in_string = false,
+            (true, _) => {}
+            (false, b'"') => in_string = true,
+            (false, b'{' | b'[') => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            (false, b'}' | b']') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Protocol version reported in 402 response bodies
+pub const X402_VERSION: u32 = 1;
+
+/// A 402 Payment Required response
+///
+/// The reference x402 spec carries requirements in the JSON response body;
+/// this SDK also sends them in [`X402_REQUIREMENTS_HEADER`]. Servers should
+/// emit both, and clients accept either via [`decode_payment_required`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequiredResponse {
+    pub x402_version: u32,
+    /// Why payment is required (e.g. a rejected payment's error)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Acceptable ways to pay, in the server's order of preference
+    pub accepts: Vec<PaymentRequirements>,
+    /// Lightning invoice accepted instead of the options in `accepts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lightning: Option<LightningRequirements>,
+    /// Zero-knowledge payment proofs accepted instead of the options in
+    /// `accepts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zk: Option<ZkRequirements>,
+}
+
+impl PaymentRequiredResponse {
+    pub fn new(requirements: PaymentRequirements) -> Self {
+        Self {
+            x402_version: X402_VERSION,
+            error: None,
+            accepts: vec![requirements],
+            lightning: None,
+            zk: None,
+        }
+    }
+
+    /// Also offer payment over Lightning
+    pub fn with_lightning(mut self, lightning: LightningRequirements) -> Self {
+        self.lightning = Some(lightning);
+        self
+    }
+
+    /// Also accept zero-knowledge payment proofs
+    pub fn with_zk(mut self, zk: ZkRequirements) -> Self {
+        self.zk = Some(zk);
+        self
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    /// Value for [`X402_REQUIREMENTS_HEADER`] (the preferred requirements)
+    pub fn header_value(&self) -> Result<String> {
+        let preferred = self.accepts.first().ok_or_else(|| {
+            X402Error::EncodingError("payment required response has no requirements".to_string())
+        })?;
+        encode_requirements_header(preferred)
+    }
+
+    /// JSON response body
+    pub fn to_json_body(&self) -> Result<String> {
+        serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))
+    }
+
+    /// Parse a JSON response body
+    pub fn from_json_body(body: &[u8]) -> Result<Self> {
+        serde_json::from_slice(body).map_err(X402Error::Json)
+    }
+}
+
+/// Decode a 402 response from its requirements header and/or JSON body
+///
+/// The body is preferred since it can list several options; the header is
+/// used when the body is missing or not an x402 body (e.g. an HTML page).
+pub fn decode_payment_required(
+    header: Option<&str>,
+    body: Option<&[u8]>,
+) -> Result<PaymentRequiredResponse> {
+    let from_body = body.map(PaymentRequiredResponse::from_json_body);
+    match (from_body, header) {
+        (Some(Ok(response)), _) if !response.accepts.is_empty() => Ok(response),
+        (_, Some(header)) => Ok(PaymentRequiredResponse::new(decode_requirements_header(header)?)),
+        (Some(Err(e)), None) => Err(e),
+        _ => Err(X402Error::InvalidHeader("402 response carries no payment requirements".to_string())),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Network;
+    use crate::{Network, Scheme};
     use alloy_primitives::{Address, U256};
 
     #[test]
     fn test_requirements_roundtrip() {
         let requirements = PaymentRequirements {
             amount: U256::from(1000000),
-            recipient: Address::ZERO,
+            recipient: Address::repeat_byte(0x11),
             network: Network::Base,
             token: None,
             description: Some("Test payment".to_string()),
             expires_at: Some(1700000000),
             resource: "/api/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: Some("application/json".to_string()),
+            output_schema: Some(serde_json::json!({ "type": "object" })),
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
         };
 
         let encoded = encode_requirements_header(&requirements).unwrap();
@@ -89,5 +627,342 @@ mod tests {
         assert_eq!(decoded.amount, requirements.amount);
         assert_eq!(decoded.recipient, requirements.recipient);
         assert_eq!(decoded.resource, requirements.resource);
+        assert_eq!(decoded.mime_type, requirements.mime_type);
+        assert_eq!(decoded.output_schema, requirements.output_schema);
+
+        let cbor = decode_requirements_header(encode_requirements_header_cbor(&requirements).unwrap()).unwrap();
+        assert_eq!(cbor.output_schema, requirements.output_schema);
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert(X402_REQUIREMENTS_HEADER_NAME, encode_requirements_header_value(&requirements).unwrap());
+        let value = headers.get(X402_REQUIREMENTS_HEADER.to_ascii_lowercase()).unwrap();
+        assert_eq!(decode_requirements_header(value).unwrap().resource, requirements.resource);
+        assert!(decode_requirements_header(format!(" {}\t", encoded)).is_ok());
+    }
+
+    #[test]
+    fn test_tagged_header_values() {
+        let requirements = PaymentRequirements {
+            amount: U256::from(1000000),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/api/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        let json = encode_requirements_header(&requirements).unwrap();
+        let cbor = encode_requirements_header_cbor(&requirements).unwrap();
+        assert!(json.starts_with("x402.v2.json."));
+        assert!(cbor.starts_with("x402.v2.cbor."));
+        assert!(decode_requirements_header(&cbor).is_ok());
+
+        let untagged = BASE64.encode(serde_json::to_vec(&requirements).unwrap());
+        assert_eq!(decode_requirements_header(untagged).unwrap().resource, "/api/test");
+
+        let future = json.replacen("x402.v2.", "x402.v3.", 1);
+        assert!(matches!(
+            decode_requirements_header(future),
+            Err(X402Error::UnsupportedVersion(v)) if v == "v3"
+        ));
+        assert!(decode_requirements_header(json.replacen("json", "yaml", 1)).is_err());
+    }
+
+    #[test]
+    fn test_decode_accepts_url_safe_and_unpadded_base64() {
+        let requirements = PaymentRequirements {
+            amount: U256::from(1000000),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            description: Some("??>>~~".to_string()),
+            expires_at: None,
+            resource: "/api/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        let standard = encode_requirements_header(&requirements).unwrap();
+        assert!(standard.contains(['+', '/']) && standard.ends_with('='));
+
+        let url_safe = standard.replace('+', "-").replace('/', "_");
+        for header in [standard.trim_end_matches('='), &url_safe, url_safe.trim_end_matches('=')] {
+            assert_eq!(decode_requirements_header(header).unwrap().description, requirements.description);
+        }
+    }
+
+    #[test]
+    fn test_payment_required_body_or_header() {
+        let requirements = PaymentRequirements {
+            amount: U256::from(1000000),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/api/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        let response = PaymentRequiredResponse::new(requirements).with_error("payment required");
+        let header = response.header_value().unwrap();
+        let body = response.to_json_body().unwrap();
+        assert!(body.contains("\"x402Version\":1"));
+
+        let from_body = decode_payment_required(None, Some(body.as_bytes())).unwrap();
+        assert_eq!(from_body.error.as_deref(), Some("payment required"));
+
+        let from_header = decode_payment_required(Some(&header), Some(b"<html></html>")).unwrap();
+        assert_eq!(from_header.accepts[0].resource, "/api/test");
+
+        assert!(decode_payment_required(None, None).is_err());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let oversized = "A".repeat(DecodeLimits::default().max_header_len + 4);
+        assert!(matches!(
+            decode_payment_header(&oversized),
+            Err(X402Error::LimitExceeded { what: "header", .. })
+        ));
+
+        let nested = format!("{}{}", "[".repeat(64), "]".repeat(64));
+        assert!(matches!(
+            decode_requirements_header(BASE64.encode(nested)),
+            Err(X402Error::LimitExceeded { what: "nesting depth", .. })
+        ));
+
+        let requirements = PaymentRequirements {
+            amount: U256::from(1),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/".repeat(100),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        let header = encode_requirements_header(&requirements).unwrap();
+        let limits = DecodeLimits::default().with_max_resource_len(99);
+        assert!(decode_requirements_header_with_limits(&header, &limits).is_err());
+        assert!(decode_requirements_header(&header).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let strict = DecodeLimits::default().with_unknown_fields(UnknownFields::Reject);
+        let mut requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/reports").unwrap();
+        requirements.extra.insert("kept".to_string(), serde_json::json!("ours"));
+        let mut value = serde_json::to_value(&requirements).unwrap();
+        value["futureField"] = serde_json::json!({ "level": 2 });
+        value["kept"] = serde_json::json!("theirs");
+        value["ratio"] = serde_json::json!(0.5);
+
+        let json = tagged(WireFormat::Json, &serde_json::to_vec(&value).unwrap());
+        let mut cbor_value = ciborium::Value::serialized(&requirements).unwrap();
+        let map = cbor_value.as_map_mut().unwrap();
+        for key in ["futureField", "kept", "ratio"] {
+            map.push((key.into(), ciborium::Value::serialized(&value[key]).unwrap()));
+        }
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&cbor_value, &mut cbor).unwrap();
+        for header in [json, tagged(WireFormat::Cbor, &cbor)] {
+            let decoded = decode_requirements_header(&header).unwrap();
+            assert_eq!(decoded.extra["futureField"], serde_json::json!({ "level": 2 }));
+            assert_eq!(decoded.extra["kept"], "ours");
+            assert!(!decoded.extra.contains_key("ratio"));
+            let reencoded = decode_requirements_header(encode_requirements_header(&decoded).unwrap()).unwrap();
+            assert_eq!(reencoded.extra, decoded.extra);
+
+            assert!(matches!(
+                decode_requirements_header_with_limits(&header, &strict),
+                Err(X402Error::InvalidHeader(message)) if message.contains("futureField")
+            ));
+        }
+        let header = encode_requirements_header(&requirements).unwrap();
+        assert!(decode_requirements_header_with_limits(&header, &strict).is_ok());
+
+        // Payments ignore unknown fields, since `extra` is signed
+        let payment = SignedPayment {
+            payment: crate::PaymentPayload::builder()
+                .requirements(&requirements)
+                .payer(Address::repeat_byte(0x22))
+                .expires_at(1_700_000_000)
+                .build()
+                .unwrap(),
+            signature: vec![7; 65],
+            signatures: Vec::new(),
+        };
+        let mut value = serde_json::to_value(&payment).unwrap();
+        value["payment"]["futureField"] = serde_json::json!(1);
+        let header = tagged(WireFormat::Json, &serde_json::to_vec(&value).unwrap());
+        let decoded = decode_payment_header(&header).unwrap();
+        assert_eq!(decoded.payment.message_hash(), payment.payment.message_hash());
+        assert!(matches!(
+            decode_payment_header_with_limits(&header, &strict),
+            Err(X402Error::InvalidHeader(message)) if message == "unknown field payment.futureField"
+        ));
+        let mut buf = Vec::new();
+        assert!(crate::decode_payment_header_borrowed(header.as_bytes(), &mut buf).is_ok());
+        assert!(crate::decode_payment_header_borrowed_with_limits(header.as_bytes(), &mut buf, &strict).is_err());
+    }
+
+    #[test]
+    fn test_cbor_payment_roundtrip() {
+        let payment = SignedPayment {
+            payment: crate::PaymentPayload {
+                amount: U256::from(1000000),
+                recipient: Address::repeat_byte(0x11),
+                payer: Address::repeat_byte(0x22),
+                chain_id: 8453,
+                token: Some(Address::repeat_byte(0x33)),
+                resource: "https://api.example.com/data".to_string(),
+                nonce: 42,
+                expires_at: 1700000000,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: vec![0xab; 65],
+            signatures: Vec::new(),
+        };
+
+        let json = encode_payment_header(&payment).unwrap();
+        let cbor = encode_payment_header_cbor(&payment).unwrap();
+        assert!(cbor.len() < json.len());
+
+        let decoded = decode_payment_header(&cbor).unwrap();
+        assert_eq!(decoded.payment.token, payment.payment.token);
+        assert_eq!(decoded.payment.nonce, 42);
+        assert_eq!(decoded.signature, payment.signature);
+
+        let binary = encode_payment_header_binary(&payment).unwrap();
+        assert!(binary.len() < 300);
+        assert_eq!(decode_payment_header(&binary).unwrap().payment.resource, payment.payment.resource);
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn requirements() -> impl Strategy<Value = PaymentRequirements> {
+            (
+                1..u128::MAX,
+                any::<[u8; 20]>(),
+                0..Network::ALL.len(),
+                "/[a-z0-9/_-]{0,40}",
+                proptest::option::of("[ -~]{0,60}"),
+                proptest::option::of(1..3_600u64),
+            )
+                .prop_map(|(amount, recipient, network, resource, description, max_timeout_seconds)| {
+                    PaymentRequirements {
+                        amount: U256::from(amount),
+                        recipient: Address::from(recipient),
+                        network: Network::ALL[network],
+                        token: None,
+                        description,
+                        expires_at: None,
+                        resource,
+                        commitment: None,
+                        scheme: Scheme::Exact,
+                        mime_type: None,
+                        output_schema: None,
+                        max_timeout_seconds,
+                        extra: Default::default(),
+                        splits: Vec::new(),
+                        price_quote: None,
+                    }
+                })
+        }
+
+        fn payment() -> impl Strategy<Value = SignedPayment> {
+            (requirements(), any::<[u8; 20]>(), (1u64 << 32).., 1..4_000_000_000u64, any::<[u8; 65]>())
+                .prop_map(|(requirements, payer, nonce, expires_at, signature)| SignedPayment {
+                    payment: crate::PaymentPayload {
+                        amount: requirements.amount,
+                        recipient: requirements.recipient,
+                        payer: Address::from(payer),
+                        chain_id: requirements.network.chain_id(),
+                        token: requirements.token,
+                        resource: requirements.resource,
+                        nonce,
+                        expires_at,
+                        scheme: Scheme::Exact,
+                        extra: Default::default(),
+                        invoice_id: None,
+                        splits: Vec::new(),
+                    },
+                    signature: signature.to_vec(),
+                    signatures: Vec::new(),
+                })
+        }
+
+        proptest! {
+            #[test]
+            fn decoders_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+                let _ = decode_requirements_header(&bytes);
+                let _ = decode_payment_header(&bytes);
+                let tagged = format!("x402.v2.json.{}", BASE64.encode(&bytes));
+                let _ = decode_requirements_header(&tagged);
+                let _ = decode_payment_header(&tagged);
+                let _ = decode_payment_header(format!("x402.v2.bin.{}", BASE64.encode(&bytes)));
+                let _ = decode_payment_header(format!("x402.v2.cbor.{}", BASE64.encode(&bytes)));
+            }
+
+            #[test]
+            fn requirements_roundtrip(requirements in requirements()) {
+                for encoded in [
+                    encode_requirements_header(&requirements).unwrap(),
+                    encode_requirements_header_cbor(&requirements).unwrap(),
+                ] {
+                    let decoded = decode_requirements_header(&encoded).unwrap();
+                    prop_assert_eq!(
+                        encode_requirements_header(&decoded).unwrap(),
+                        encode_requirements_header(&requirements).unwrap()
+                    );
+                }
+            }
+
+            #[test]
+            fn payment_roundtrip(payment in payment()) {
+                let expected = encode_payment_header(&payment).unwrap();
+                for encoded in [
+                    expected.clone(),
+                    encode_payment_header_cbor(&payment).unwrap(),
+                    encode_payment_header_binary(&payment).unwrap(),
+                ] {
+                    let decoded = decode_payment_header(&encoded).unwrap();
+                    prop_assert_eq!(&encode_payment_header(&decoded).unwrap(), &expected);
+                }
+            }
+        }
     }
 }
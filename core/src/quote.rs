@@ -0,0 +1,237 @@
+//! Time-locked price quotes
+//!
+//! A server can attach a [`QuoteCommitment`] to its requirements: a
+//! signature over the quoted terms promising to accept any conforming
+//! payment until `expires_at`. If the server later rejects such a payment,
+//! the client can assemble a [`QuoteViolationProof`] that anyone can check
+//! with [`verify_quote_violation`].
+//!
+//! As with payments, this crate only produces the hash to sign
+//! ([`quote_hash`]); signing is left to the server's key management.
+
+use crate::{canonical_extra, splits_message, PaymentRequirements, Result, SignedPayment, X402Error};
+#[cfg(feature = "verify")]
+use crate::{recover_address, verify_payment_at};
+use alloy_primitives::{keccak256, Address};
+use serde::{Deserialize, Serialize};
+
+/// Server signature binding it to the quoted price until expiry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteCommitment {
+    /// Address of the committing server key
+    pub server: Address,
+    /// ECDSA signature over [`quote_hash`] (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+/// Hash of the quoted terms that a server signs to commit to a price
+///
+/// Covers every field payment verification depends on, so the terms in a
+/// [`QuoteViolationProof`] can't be altered without breaking the
+/// commitment. Fails if the requirements have no `expires_at`, since an
+/// open-ended commitment would bind the server forever.
+pub fn quote_hash(requirements: &PaymentRequirements) -> Result<[u8; 32]> {
+    let expires_at = requirements.expires_at.ok_or_else(|| {
+        X402Error::InvalidQuote("committed quotes require expires_at".to_string())
+    })?;
+
+    let mut message = format!(
+        "x402 Quote\nAmount: {}\nRecipient: {}\nChainId: {}\nToken: {}\nResource: {}\nExpires: {}\nScheme: {}",
+        requirements.amount,
+        requirements.recipient,
+        requirements.network.chain_id(),
+        requirements.token.map(|t| t.to_string()).unwrap_or_default(),
+        requirements.resource,
+        expires_at,
+        requirements.scheme.as_str()
+    );
+    if let Some(max_seconds) = requirements.max_timeout_seconds {
+        message.push_str(&format!("\nMaxTimeout: {}", max_seconds));
+    }
+    if !requirements.extra.is_empty() {
+        message.push_str(&format!("\nExtra: {}", canonical_extra(&requirements.extra)));
+    }
+    if !requirements.splits.is_empty() {
+        message.push_str(&format!("\nSplits: {}", splits_message(&requirements.splits)));
+    }
+    if let Some(quote) = &requirements.price_quote {
+        let quote = serde_json::to_string(quote).map_err(|e| X402Error::InvalidQuote(e.to_string()))?;
+        message.push_str(&format!("\nPriceQuote: {}", quote));
+    }
+
+    Ok(*keccak256(message.as_bytes()))
+}
+
+/// Verify the commitment attached to `requirements`, returning the server
+/// address it recovers to
+#[cfg(feature = "verify")]
+pub fn verify_quote_commitment(requirements: &PaymentRequirements) -> Result<Address> {
+    let commitment = requirements.commitment.as_ref().ok_or_else(|| {
+        X402Error::InvalidQuote("requirements carry no commitment".to_string())
+    })?;
+
+    let server = recover_address(&quote_hash(requirements)?, &commitment.signature)?;
+    if server != commitment.server {
+        return Err(X402Error::InvalidQuote(
+            "commitment signature does not match server".to_string()
+        ));
+    }
+
+    Ok(server)
+}
+
+/// Evidence that a server rejected a payment it had committed to accept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteViolationProof {
+    /// The committed requirements, as returned by the server
+    pub requirements: PaymentRequirements,
+    /// The payment the server rejected
+    pub payment: SignedPayment,
+    /// When the payment was submitted (unix timestamp)
+    pub submitted_at: u64,
+    /// The server's stated reason, if any
+    pub rejection_reason: Option<String>,
+}
+
+/// Check a violation proof, returning the server address at fault
+///
+/// A proof is valid when the commitment is genuine, the payment was
+/// submitted before the quote expired, and the payment satisfied the
+/// committed requirements at submission time. The submission time itself
+/// is asserted by the client; arbiters should corroborate it (for example
+/// against their own logs) before acting on the proof.
+#[cfg(feature = "verify")]
+pub fn verify_quote_violation(proof: &QuoteViolationProof) -> Result<Address> {
+    let server = verify_quote_commitment(&proof.requirements)?;
+
+    let expires_at = proof.requirements.expires_at.unwrap_or(0);
+    if proof.submitted_at > expires_at {
+        return Err(X402Error::InvalidQuote(format!(
+            "payment submitted at {} after quote expiry {}",
+            proof.submitted_at, expires_at
+        )));
+    }
+
+    if proof.payment.payment.resource != proof.requirements.resource {
+        return Err(X402Error::InvalidQuote("payment is for a different resource".to_string()));
+    }
+
+    verify_payment_at(&proof.payment, &proof.requirements, proof.submitted_at)
+        .map_err(|e| X402Error::InvalidQuote(format!("payment did not conform: {}", e)))?;
+
+    Ok(server)
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::{ExchangeRate, FiatPrice, Network, PaymentPayload, PriceQuote, Scheme, Split};
+    use crate::testing::TestSigner;
+    use alloy_primitives::U256;
+
+    fn requirements() -> PaymentRequirements {
+        PaymentRequirements {
+            amount: U256::from(1000),
+            recipient: Address::repeat_byte(0x33),
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: Some(1_700_000_600),
+            resource: "/api/data".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        }
+    }
+
+    fn commit(requirements: &mut PaymentRequirements, server: &TestSigner) {
+        requirements.commitment = Some(QuoteCommitment {
+            server: server.address(),
+            signature: server.sign_hash(&quote_hash(requirements).unwrap()).unwrap(),
+        });
+    }
+
+    #[test]
+    fn test_quote_violation_proof() {
+        let server = TestSigner::new(0);
+        let payer = TestSigner::new(1);
+
+        let mut requirements = requirements();
+        commit(&mut requirements, &server);
+        assert_eq!(verify_quote_commitment(&requirements).unwrap(), server.address());
+
+        let payload = PaymentPayload {
+            amount: U256::from(1000),
+            recipient: requirements.recipient,
+            payer: payer.address(),
+            chain_id: 8453,
+            token: None,
+            resource: "/api/data".to_string(),
+            nonce: 1,
+            expires_at: 1_700_000_300,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        let payment = payer.sign(payload).unwrap();
+        let mut proof = QuoteViolationProof {
+            requirements,
+            payment,
+            submitted_at: 1_700_000_100,
+            rejection_reason: Some("price changed".to_string()),
+        };
+        assert_eq!(verify_quote_violation(&proof).unwrap(), server.address());
+
+        // A raised price no longer matches the commitment signature
+        proof.requirements.amount = U256::from(2000);
+        assert!(verify_quote_violation(&proof).is_err());
+        proof.requirements.amount = U256::from(1000);
+
+        // Submitting after the quote expired proves nothing
+        proof.submitted_at = 1_700_000_601;
+        assert!(verify_quote_violation(&proof).is_err());
+    }
+
+    #[test]
+    fn test_commitment_covers_verified_terms() {
+        let server = TestSigner::new(0);
+        let mut committed = requirements();
+        committed.max_timeout_seconds = Some(60);
+        committed.price_quote = Some(PriceQuote {
+            price: FiatPrice::usd_cents(1),
+            rate: ExchangeRate { currency: "USD".to_string(), micros_per_token: 10, decimals: 0, as_of: 1_700_000_000 },
+            max_staleness_seconds: 300,
+        });
+        commit(&mut committed, &server);
+        verify_quote_commitment(&committed).unwrap();
+
+        let changes: [fn(&mut PaymentRequirements); 11] = [
+            |r| r.amount += U256::from(1),
+            |r| r.recipient = Address::repeat_byte(0x44),
+            |r| r.network = Network::Ethereum,
+            |r| r.token = Some(Address::repeat_byte(0x55)),
+            |r| r.resource = "/api/other".to_string(),
+            |r| r.expires_at = Some(1_700_000_601),
+            |r| r.scheme = Scheme::Upto,
+            |r| r.max_timeout_seconds = Some(3600),
+            |r| {
+                r.extra.insert("tier".to_string(), "gold".into());
+            },
+            |r| r.splits = vec![Split { recipient: Address::repeat_byte(0x66), share: U256::from(1000) }],
+            |r| r.price_quote.as_mut().unwrap().max_staleness_seconds = 86_400,
+        ];
+        for (i, change) in changes.iter().enumerate() {
+            let mut altered = committed.clone();
+            change(&mut altered);
+            assert!(verify_quote_commitment(&altered).is_err(), "change {} kept the commitment valid", i);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Refunds for failed paid requests
+//!
+//! When a paid request fails, the client sends a [`RefundRequest`] carrying
+//! the original signed payment, which proves it is the payer. The server
+//! answers with a [`RefundReceipt`]: a [`RefundAuthorization`] referencing
+//! the original payment's nonce, signed by the server's key. The receipt
+//! is the client's claim on the refund, checkable by anyone who knows the
+//! server's key with [`verify_refund_receipt`].
+//!
+//! Issuing the refund transfer itself is left to the server's settlement
+//! process. As with quotes, this crate only produces the hash to sign.
+
+use crate::{
+    decode_header, parse_payload, tagged, DecodeLimits, PaymentPayload, Result, SignedPayment, WireFormat, X402Error,
+};
+#[cfg(feature = "verify")]
+use crate::{recover_address, recover_signer};
+use alloy_primitives::{keccak256, Address, U256};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// HTTP header carrying a refund request
+pub const X402_REFUND_REQUEST_HEADER: &str = "X-Refund-Request";
+
+/// HTTP header carrying a refund receipt
+pub const X402_REFUND_RECEIPT_HEADER: &str = "X-Refund-Receipt";
+
+/// Client's claim for a refund of a payment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundRequest {
+    /// The payment to refund, as originally sent
+    pub payment: SignedPayment,
+    /// Amount to refund; `None` for the full payment
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_amount")]
+    pub amount: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl RefundRequest {
+    /// Check the request comes from the payer, returning the payer address
+    #[cfg(feature = "verify")]
+    pub fn verify(&self) -> Result<Address> {
+        let signer = recover_signer(&self.payment)?;
+        if signer != self.payment.payment.payer {
+            return Err(X402Error::InvalidSignature(
+                "recovered address does not match payer".to_string()
+            ));
+        }
+        Ok(signer)
+    }
+
+    /// Encode as an `X-Refund-Request` header value
+    pub fn to_header(&self) -> Result<String> {
+        to_header(self)
+    }
+
+    /// Decode an `X-Refund-Request` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        from_header(header.as_ref())
+    }
+}
+
+/// Terms of a refund, as signed by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundAuthorization {
+    /// Who gets the refund (the original payer)
+    pub payer: Address,
+    /// Recipient of the original payment
+    pub recipient: Address,
+    pub chain_id: u64,
+    /// Token contract (`None` = native token)
+    pub token: Option<Address>,
+    /// Nonce of the original payment
+    pub payment_nonce: u64,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    /// When the refund was authorized (unix timestamp)
+    pub issued_at: u64,
+}
+
+impl RefundAuthorization {
+    /// Refund `amount` of `payment`, failing if it exceeds what was paid
+    pub fn for_payment(payment: &PaymentPayload, amount: U256, issued_at: u64) -> Result<Self> {
+        if amount.is_zero() || amount > payment.amount {
+            return Err(X402Error::InvalidRefund(format!(
+                "cannot refund {} of a {} payment",
+                amount, payment.amount
+            )));
+        }
+        Ok(Self {
+            payer: payment.payer,
+            recipient: payment.recipient,
+            chain_id: payment.chain_id,
+            token: payment.token,
+            payment_nonce: payment.nonce,
+            amount,
+            issued_at,
+        })
+    }
+
+    /// Authorization answering `request`, after checking it came from the payer
+    #[cfg(feature = "verify")]
+    pub fn for_request(request: &RefundRequest, issued_at: u64) -> Result<Self> {
+        request.verify()?;
+        let payment = &request.payment.payment;
+        Self::for_payment(payment, request.amount.unwrap_or(payment.amount), issued_at)
+    }
+
+    /// Create the message hash the server signs
+    pub fn message_hash(&self) -> [u8; 32] {
+        let message = format!(
+            "x402 Refund\nPayer: {}\nRecipient: {}\nChainId: {}\nToken: {}\nPaymentNonce: {}\nAmount: {}\nIssued: {}",
+            self.payer,
+            self.recipient,
+            self.chain_id,
+            self.token.map(|t| t.to_string()).unwrap_or_default(),
+            self.payment_nonce,
+            self.amount,
+            self.issued_at
+        );
+        *keccak256(message.as_bytes())
+    }
+}
+
+/// Server-signed refund authorization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefundReceipt {
+    pub authorization: RefundAuthorization,
+    /// Address of the signing server key
+    pub server: Address,
+    /// ECDSA signature over [`RefundAuthorization::message_hash`] (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+impl RefundReceipt {
+    /// Encode as an `X-Refund-Receipt` header value
+    pub fn to_header(&self) -> Result<String> {
+        to_header(self)
+    }
+
+    /// Decode an `X-Refund-Receipt` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        from_header(header.as_ref())
+    }
+}
+
+/// Verify a refund receipt was signed by `server` and refunds `payment`
+///
+/// `server` is the refunding server's key, as the client knows it; the
+/// receipt's own `server` field is only a hint, since anyone can sign a
+/// receipt naming themselves.
+#[cfg(feature = "verify")]
+pub fn verify_refund_receipt(receipt: &RefundReceipt, payment: &PaymentPayload, server: Address) -> Result<()> {
+    let authorization = &receipt.authorization;
+    if authorization.payer != payment.payer
+        || authorization.recipient != payment.recipient
+        || authorization.chain_id != payment.chain_id
+        || authorization.token != payment.token
+        || authorization.payment_nonce != payment.nonce
+    {
+        return Err(X402Error::InvalidRefund("receipt refunds a different payment".to_string()));
+    }
+    let signer = recover_address(&authorization.message_hash(), &receipt.signature)?;
+    if signer != server || receipt.server != server {
+        return Err(X402Error::InvalidSignature(
+            "refund receipt signature does not match server".to_string()
+        ));
+    }
+    Ok(())
+}
+
+fn to_header<T: Serialize>(value: &T) -> Result<String> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+    Ok(tagged(WireFormat::Json, json.as_bytes()))
+}
+
+fn from_header<T: DeserializeOwned>(header: &[u8]) -> Result<T> {
+    let limits = DecodeLimits::default();
+    let (format, bytes) = decode_header(header, &limits)?;
+    parse_payload(format, &bytes, &limits)
+}
+
+mod optional_amount {
+    use alloy_primitives::U256;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(amount: &Option<U256>, serializer: S) -> Result<S::Ok, S::Error> {
+        match amount {
+            Some(amount) => crate::serde_amount::serialize(amount, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<U256>, D::Error> {
+        #[derive(Deserialize)]
+        struct Amount(#[serde(with = "crate::serde_amount")] U256);
+        Ok(Option::<Amount>::deserialize(deserializer)?.map(|Amount(amount)| amount))
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::Scheme;
+
+    #[test]
+    fn test_refund_flow() {
+        let payer = TestSigner::new(0);
+        let server_key = TestSigner::new(1);
+        let payment = PaymentPayload {
+            amount: U256::from(1000),
+            recipient: Address::repeat_byte(0x33),
+            payer: payer.address(),
+            chain_id: 8453,
+            token: None,
+            resource: "/api/data".to_string(),
+            nonce: 9,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        let request = RefundRequest {
+            payment: payer.sign(payment.clone()).unwrap(),
+            amount: Some(U256::from(400)),
+            reason: Some("upstream timeout".to_string()),
+        };
+
+        let request = RefundRequest::from_header(request.to_header().unwrap()).unwrap();
+        let authorization = RefundAuthorization::for_request(&request, 1_700_000_000).unwrap();
+        assert_eq!((authorization.payment_nonce, authorization.amount), (9, U256::from(400)));
+        let receipt = RefundReceipt {
+            server: server_key.address(),
+            signature: server_key.sign_hash(&authorization.message_hash()).unwrap(),
+            authorization,
+        };
+        let receipt = RefundReceipt::from_header(receipt.to_header().unwrap()).unwrap();
+        let server = server_key.address();
+        verify_refund_receipt(&receipt, &payment, server).unwrap();
+
+        let other = PaymentPayload { nonce: 10, ..payment.clone() };
+        assert!(matches!(verify_refund_receipt(&receipt, &other, server), Err(X402Error::InvalidRefund(_))));
+        let other_token = PaymentPayload { token: Some(Address::repeat_byte(0x55)), ..payment.clone() };
+        assert!(matches!(verify_refund_receipt(&receipt, &other_token, server), Err(X402Error::InvalidRefund(_))));
+
+        // A receipt signed by some other key, naming that key as the server
+        let forger = TestSigner::new(2);
+        let forged_receipt = RefundReceipt {
+            server: forger.address(),
+            signature: forger.sign_hash(&receipt.authorization.message_hash()).unwrap(),
+            authorization: receipt.authorization.clone(),
+        };
+        assert!(matches!(
+            verify_refund_receipt(&forged_receipt, &payment, server),
+            Err(X402Error::InvalidSignature(_))
+        ));
+        assert!(RefundAuthorization::for_payment(&payment, U256::from(1001), 0).is_err());
+
+        let mut forged = request.clone();
+        forged.payment.payment.payer = Address::repeat_byte(0x44);
+        assert!(RefundAuthorization::for_request(&forged, 0).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Replay protection for payment nonces
+//!
+//! A signed payment stays valid until it expires, so a server must remember
+//! every `(chain, payer, nonce)` it accepted until then. [`NonceStore`] is
+//! that memory. Backends:
+//! - [`MemoryNonceStore`]: unbounded until [`MemoryNonceStore::purge`]
+//! - [`BoundedNonceStore`]: bounded, dropping entries once their payment
+//!   expires; while it is full of live ones, every new payment fails
+//! - [`SledStore`](crate::SledStore) (feature `sled`): persistent, and
+//!   also a payment ledger
+//!
+//! Servers that already record payments in a [`PaymentLedger`](crate::PaymentLedger)
+//! get the same protection from its duplicate check.
+
+use crate::{PaymentPayload, Result, X402Error};
+use alloy_primitives::Address;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, RwLock};
+
+type NonceKey = (u64, Address, u64);
+
+fn duplicate((chain_id, payer, nonce): NonceKey) -> X402Error {
+    X402Error::DuplicatePayment(format!("payer {} nonce {} on chain {}", payer, nonce, chain_id))
+}
+
+/// Storage of the payment nonces already used
+pub trait NonceStore: Send + Sync {
+    /// Record the nonce as used at unix time `now`; fails with
+    /// [`X402Error::DuplicatePayment`] if it already was. `expires_at` is
+    /// when the payment, and so the record, stops mattering.
+    fn claim(&self, chain_id: u64, payer: Address, nonce: u64, expires_at: u64, now: u64) -> Result<()>;
+
+    /// [`NonceStore::claim`] the nonce of `payment`
+    fn claim_payment(&self, payment: &PaymentPayload, now: u64) -> Result<()> {
+        self.claim(payment.chain_id, payment.payer, payment.nonce, payment.expires_at, now)
+    }
+}
+
+/// In-process [`NonceStore`] that keeps every nonce until purged
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    used: RwLock<HashMap<NonceKey, u64>>,
+}
+
+impl MemoryNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.used.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget nonces of payments that expired before unix time `now`
+    pub fn purge(&self, now: u64) {
+        self.used.write().unwrap().retain(|_, expires_at| *expires_at >= now);
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn claim(&self, chain_id: u64, payer: Address, nonce: u64, expires_at: u64, _now: u64) -> Result<()> {
+        let key = (chain_id, payer, nonce);
+        let mut used = self.used.write().unwrap();
+        if used.insert(key, expires_at).is_some() {
+            return Err(duplicate(key));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Bounded {
+    /// Expiry of each nonce
+    entries: HashMap<NonceKey, u64>,
+    by_expiry: BTreeSet<(u64, NonceKey)>,
+    rejected_full: u64,
+}
+
+impl Bounded {
+    fn insert(&mut self, key: NonceKey, expires_at: u64) {
+        self.entries.insert(key, expires_at);
+        self.by_expiry.insert((expires_at, key));
+    }
+
+    fn purge(&mut self, now: u64) {
+        while let Some(&(expires_at, key)) = self.by_expiry.first() {
+            if expires_at >= now {
+                break;
+            }
+            self.by_expiry.pop_first();
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// [`NonceStore`] holding at most `capacity` nonces, for single-instance
+/// servers that need bounded memory without running Redis
+///
+/// Each claim first drops the nonces of expired payments, which can no
+/// longer be replayed. Live nonces are never evicted, since forgetting one
+/// would let its payment be replayed: once the store is full of them,
+/// every new payment fails with [`X402Error::VerifierOverloaded`] (a 503
+/// the client can retry) until one expires, and
+/// [`BoundedNonceStore::rejected_full`] counts the refusals. Size
+/// `capacity` above the number of payments accepted within the longest
+/// validity window.
+#[derive(Debug)]
+pub struct BoundedNonceStore {
+    capacity: usize,
+    nonces: Mutex<Bounded>,
+}
+
+impl BoundedNonceStore {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "BoundedNonceStore capacity must be positive");
+        Self { capacity, nonces: Mutex::new(Bounded::default()) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.nonces.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Payments refused because the store was full of live nonces
+    pub fn rejected_full(&self) -> u64 {
+        self.nonces.lock().unwrap().rejected_full
+    }
+
+    /// Forget nonces of payments that expired before unix time `now`
+    ///
+    /// [`NonceStore::claim`] does this itself; call it to release memory
+    /// between bursts.
+    pub fn purge(&self, now: u64) {
+        self.nonces.lock().unwrap().purge(now);
+    }
+}
+
+impl NonceStore for BoundedNonceStore {
+    fn claim(&self, chain_id: u64, payer: Address, nonce: u64, expires_at: u64, now: u64) -> Result<()> {
+        let key = (chain_id, payer, nonce);
+        let mut store = self.nonces.lock().unwrap();
+        store.purge(now);
+        if store.entries.contains_key(&key) {
+            return Err(duplicate(key));
+        }
+        if expires_at < now {
+            // Expired payments fail verification; nothing to remember
+            return Ok(());
+        }
+        if store.entries.len() >= self.capacity {
+            store.rejected_full += 1;
+            return Err(X402Error::VerifierOverloaded(format!(
+                "nonce store full with {} unexpired payments",
+                self.capacity
+            )));
+        }
+        store.insert(key, expires_at);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn payer(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    #[test]
+    fn test_memory_store_rejects_replays_until_purged() {
+        let store = MemoryNonceStore::new();
+        store.claim(8453, payer(1), 7, NOW + 60, NOW).unwrap();
+        store.claim(1, payer(1), 7, NOW + 60, NOW).unwrap();
+        assert!(matches!(store.claim(8453, payer(1), 7, NOW + 60, NOW), Err(X402Error::DuplicatePayment(_))));
+        store.purge(NOW + 61);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_bounded_store_expires_and_refuses_when_full() {
+        let store = BoundedNonceStore::new(2);
+        store.claim(8453, payer(1), 1, NOW + 10, NOW).unwrap();
+        store.claim(8453, payer(1), 2, NOW + 100, NOW).unwrap();
+        assert!(store.claim(8453, payer(1), 1, NOW + 10, NOW).is_err());
+
+        // Nonce 1 expired, making room
+        store.claim(8453, payer(2), 1, NOW + 100, NOW + 11).unwrap();
+        assert!(store.claim(8453, payer(1), 2, NOW + 100, NOW + 11).is_err());
+
+        // Full of live nonces: new payments are refused, old ones stay
+        let full = store.claim(8453, payer(3), 1, NOW + 100, NOW + 12).unwrap_err();
+        assert!(matches!(full, X402Error::VerifierOverloaded(_)));
+        assert!(full.is_retryable());
+        assert_eq!((store.len(), store.rejected_full()), (2, 1));
+        assert!(matches!(store.claim(8453, payer(1), 2, NOW + 100, NOW + 12), Err(X402Error::DuplicatePayment(_))));
+        assert!(matches!(store.claim(8453, payer(2), 1, NOW + 100, NOW + 12), Err(X402Error::DuplicatePayment(_))));
+
+        // Already-expired payments aren't stored
+        store.claim(8453, payer(4), 1, NOW, NOW + 12).unwrap();
+        assert_eq!(store.len(), 2);
+        store.purge(NOW + 101);
+        assert!(store.is_empty());
+        store.claim(8453, payer(3), 1, NOW + 200, NOW + 101).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+//! Resource URL normalization and matching
+//!
+//! Clients and servers rarely spell a resource identically: one sends
+//! `/api/data/`, the other quotes `/api/data`; one includes `:443`, the
+//! other doesn't. [`normalize_resource`] removes those differences, and
+//! [`ResourceMatcher`] compares resources exactly, by normalized URL, by
+//! path prefix, or by glob. Verification compares payment and requirements
+//! resources by normalized URL.
+
+/// Canonical form of a resource URL or path
+///
+/// Lowercases the scheme and host, drops default ports (80 for `http`,
+/// 443 for `https`), the fragment, and trailing slashes on the path. The
+/// query string is kept as is.
+pub fn normalize_resource(resource: &str) -> String {
+    let resource = resource.trim();
+    let resource = resource.split_once('#').map_or(resource, |(before, _)| before);
+    let (resource, query) = match resource.split_once('?') {
+        Some((resource, query)) => (resource, Some(query)),
+        None => (resource, None),
+    };
+
+    let (origin, path) = match resource.split_once("://") {
+        Some((scheme, rest)) => {
+            let scheme = scheme.to_ascii_lowercase();
+            let (authority, path) = rest.find('/').map_or((rest, ""), |i| rest.split_at(i));
+            let authority = authority.to_ascii_lowercase();
+            let default_port = match scheme.as_str() {
+                "http" => Some(":80"),
+                "https" => Some(":443"),
+                _ => None,
+            };
+            let authority = default_port
+                .and_then(|port| authority.strip_suffix(port))
+                .unwrap_or(&authority);
+            (format!("{}://{}", scheme, authority), path)
+        }
+        None => (String::new(), resource),
+    };
+
+    let mut path = path.trim_end_matches('/');
+    if path.is_empty() && origin.is_empty() && resource.starts_with('/') {
+        path = "/";
+    }
+    match query {
+        Some(query) => format!("{}{}?{}", origin, path, query),
+        None => format!("{}{}", origin, path),
+    }
+}
+
+/// Whether `resource` is the normalized `prefix` or under it, on a path
+/// segment boundary (`/api` matches `/api/data` but not `/apis`)
+pub(crate) fn prefix_matches(prefix: &str, resource: &str) -> bool {
+    let prefix = normalize_resource(prefix);
+    match normalize_resource(resource).strip_prefix(prefix.trim_end_matches('/')) {
+        Some(rest) => rest.is_empty() || rest.starts_with(['/', '?']),
+        None => false,
+    }
+}
+
+/// Which resources a payment or route applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceMatcher {
+    /// Byte-for-byte equal
+    Exact(String),
+    /// Equal after [`normalize_resource`]
+    Normalized(String),
+    /// The normalized prefix, or anything under it (`/api` matches
+    /// `/api/data` but not `/apis`)
+    Prefix(String),
+    /// Glob over normalized resources: `*` matches within a path segment,
+    /// `**` across segments
+    Glob(String),
+}
+
+impl ResourceMatcher {
+    pub fn matches(&self, resource: &str) -> bool {
+        match self {
+            ResourceMatcher::Exact(expected) => expected == resource,
+            ResourceMatcher::Normalized(expected) => normalize_resource(expected) == normalize_resource(resource),
+            ResourceMatcher::Prefix(prefix) => prefix_matches(prefix, resource),
+            ResourceMatcher::Glob(pattern) => {
+                glob_matches(normalize_resource(pattern).as_bytes(), normalize_resource(resource).as_bytes())
+            }
+        }
+    }
+}
+
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_matches(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_matches(rest, &text[i..]))
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_matches(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_resource() {
+        assert_eq!(normalize_resource("/api/data/"), "/api/data");
+        assert_eq!(normalize_resource("/"), "/");
+        assert_eq!(normalize_resource("HTTPS://API.Example.com:443/Data/?q=1#top"), "https://api.example.com/Data?q=1");
+        assert_eq!(normalize_resource("http://example.com:8080/"), "http://example.com:8080");
+        assert_eq!(normalize_resource("https://example.com"), normalize_resource("https://example.com/"));
+    }
+
+    #[test]
+    fn test_matchers() {
+        assert!(ResourceMatcher::Normalized("/api/data".to_string()).matches("/api/data/"));
+        assert!(!ResourceMatcher::Exact("/api/data".to_string()).matches("/api/data/"));
+
+        let prefix = ResourceMatcher::Prefix("https://example.com/api/".to_string());
+        assert!(prefix.matches("https://example.com:443/api"));
+        assert!(prefix.matches("https://example.com/api/data?page=2"));
+        assert!(!prefix.matches("https://example.com/apis"));
+
+        let glob = ResourceMatcher::Glob("/api/*/report".to_string());
+        assert!(glob.matches("/api/acme/report/"));
+        assert!(!glob.matches("/api/acme/eu/report"));
+        assert!(ResourceMatcher::Glob("/api/**".to_string()).matches("/api/acme/eu/report"));
+    }
+}
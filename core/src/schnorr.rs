@@ -0,0 +1,161 @@
+//! Schnorr (BIP-340) and FROST threshold signatures
+//!
+//! MPC wallets signing with FROST hold no single recoverable ECDSA key:
+//! the signers' shares aggregate into one BIP-340 Schnorr signature under a
+//! group key, indistinguishable from a single-signer Schnorr signature.
+//!
+//! A payment tags itself as Schnorr-signed with a [`SchnorrKey`] under
+//! [`SCHNORR_KEY`] in `extra`, carrying the x-only group key, and its
+//! signature is the 64-byte BIP-340 signature over
+//! [`PaymentPayload::message_hash`](crate::PaymentPayload::message_hash).
+//!
+//! No chain settles a transfer on a Schnorr signature, and the group key's
+//! Ethereum-style address ([`SchnorrKey::address`]) has no ECDSA key to
+//! authorize one. So the payer must be a smart contract account whose
+//! ERC-1271 `isValidSignature` checks the group signature:
+//! [`verify_smart_account_payment`](crate::verify_smart_account_payment)
+//! accepts such payments, while [`verify_payment`](crate::verify_payment)
+//! and the functions built on it reject them. [`SchnorrKey::verify`] checks
+//! the signature off-chain, e.g. inside an [`Erc1271Validator`](crate::Erc1271Validator)
+//! standing in for the account.
+//!
+//! The binary header format carries ECDSA signatures only; Schnorr-signed
+//! payments use JSON or CBOR.
+
+use crate::{Extra, Result, X402Error};
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "verify")]
+use alloy_primitives::{keccak256, Address};
+#[cfg(feature = "verify")]
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+
+/// `extra` key carrying the [`SchnorrKey`]
+pub const SCHNORR_KEY: &str = "schnorr";
+
+/// Length of a BIP-340 signature
+pub const SCHNORR_SIGNATURE_LEN: usize = 64;
+
+/// The x-only group key a Schnorr-signed payment is verified under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchnorrKey {
+    pub group_key: B256,
+}
+
+impl SchnorrKey {
+    pub fn new(group_key: B256) -> Self {
+        Self { group_key }
+    }
+
+    /// The key stored under [`SCHNORR_KEY`] in `extra`, if any
+    pub fn from_extra(extra: &Extra) -> Result<Option<Self>> {
+        extra
+            .get(SCHNORR_KEY)
+            .map(|value| {
+                serde_json::from_value(value.clone())
+                    .map_err(|e| X402Error::InvalidSignature(format!("malformed schnorr key: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// Tag a payment's `extra` as signed under this key
+    pub fn insert_into(&self, extra: &mut Extra) {
+        let value = serde_json::to_value(self).expect("keys serialize to JSON");
+        extra.insert(SCHNORR_KEY.to_string(), value);
+    }
+
+    /// Payer address of the group key: keccak256 of its even-y point, as
+    /// for an ECDSA public key
+    #[cfg(feature = "verify")]
+    pub fn address(&self) -> Result<Address> {
+        let key = self.verifying_key()?;
+        let point = key.as_affine().to_encoded_point(false);
+        Ok(Address::from_slice(&keccak256(&point.as_bytes()[1..])[12..]))
+    }
+
+    /// Check a BIP-340 signature over `message_hash`, returning
+    /// [`SchnorrKey::address`]
+    #[cfg(feature = "verify")]
+    pub fn verify(&self, message_hash: &[u8; 32], signature: &[u8]) -> Result<Address> {
+        if signature.len() != SCHNORR_SIGNATURE_LEN {
+            return Err(X402Error::InvalidSignature(format!(
+                "schnorr signature must be {} bytes, got {}",
+                SCHNORR_SIGNATURE_LEN,
+                signature.len()
+            )));
+        }
+        let invalid = |_| X402Error::InvalidSignature("schnorr signature does not verify".to_string());
+        let signature = k256::schnorr::Signature::try_from(signature).map_err(invalid)?;
+        self.verifying_key()?.verify_raw(message_hash, &signature).map_err(invalid)?;
+        self.address()
+    }
+
+    #[cfg(feature = "verify")]
+    fn verifying_key(&self) -> Result<k256::schnorr::VerifyingKey> {
+        k256::schnorr::VerifyingKey::from_bytes(self.group_key.as_slice())
+            .map_err(|_| X402Error::InvalidSignature("schnorr group key is not a curve point".to_string()))
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{
+        verify_payment_at, verify_smart_account_payment_at, Erc1271Validator, Network, PaymentPayload,
+        PaymentRequirements, SignedPayment, VerifyOptions,
+    };
+    use k256::schnorr::SigningKey;
+
+    #[test]
+    fn test_schnorr_signed_payment() {
+        let now = 1_000;
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/mpc").unwrap();
+        // A FROST group signature verifies exactly like a single-key one
+        let group = SigningKey::from_bytes(&[0x42; 32]).unwrap();
+        let key = SchnorrKey::new(B256::from_slice(&group.verifying_key().to_bytes()));
+        let account = Address::repeat_byte(0xac);
+
+        let mut payload = TestSigner::new(1).pay(&requirements, now).unwrap().payment;
+        payload.payer = account;
+        key.insert_into(&mut payload.extra);
+        assert_eq!(SchnorrKey::from_extra(&payload.extra).unwrap(), Some(key));
+        let signature = group.sign_raw(&payload.message_hash(), &[0; 32]).unwrap().to_bytes().to_vec();
+        let payment = SignedPayment { payment: payload.clone(), signature, signatures: Vec::new() };
+        assert_eq!(key.verify(&payload.message_hash(), &payment.signature).unwrap(), key.address().unwrap());
+
+        // Only a smart account checking the group signature can pay with it
+        let derived = SignedPayment {
+            payment: PaymentPayload { payer: key.address().unwrap(), ..payload.clone() },
+            ..payment.clone()
+        };
+        assert!(matches!(verify_payment_at(&derived, &requirements, now), Err(X402Error::InvalidSignature(_))));
+        let validator = GroupAccount { account, key };
+        let options = VerifyOptions::default();
+        assert_eq!(verify_smart_account_payment_at(&payment, &requirements, &options, &validator, now).unwrap(), account);
+
+        // The signature must cover the payload
+        let mut tampered = payment.clone();
+        tampered.payment.nonce += 1;
+        let result = verify_smart_account_payment_at(&tampered, &requirements, &options, &validator, now);
+        assert!(matches!(result, Err(X402Error::InvalidSignature(_))));
+        let mut off_curve = payment;
+        SchnorrKey::new(B256::repeat_byte(0xff)).insert_into(&mut off_curve.payment.extra);
+        assert!(SchnorrKey::from_extra(&off_curve.payment.extra).unwrap().unwrap().address().is_err());
+    }
+
+    /// A smart account that accepts signatures by its FROST group key
+    struct GroupAccount {
+        account: Address,
+        key: SchnorrKey,
+    }
+
+    impl Erc1271Validator for GroupAccount {
+        fn is_valid_signature(&self, _: u64, account: Address, hash: B256, signature: &[u8]) -> Result<bool> {
+            Ok(account == self.account && self.key.verify(&hash.0, signature).is_ok())
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Serde representation of token amounts
+//!
+//! Amounts are written as decimal strings (`"1000000"`): JavaScript and
+//! several Python JSON parsers silently round integers above 2^53, which
+//! corrupts large token amounts sent as JSON numbers. Decoding accepts
+//! decimal strings, `0x` hex strings (the previous wire format) and
+//! integer numbers.
+//!
+//! Use with `#[serde(with = "x402_core::serde_amount")]` on a `U256` field.
+
+use alloy_primitives::U256;
+use serde::de::{self, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+pub fn serialize<S: Serializer>(amount: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(amount)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    deserializer.deserialize_any(AmountVisitor)
+}
+
+struct AmountVisitor;
+
+impl Visitor<'_> for AmountVisitor {
+    type Value = U256;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a non-negative integer or integer string")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<U256, E> {
+        Ok(U256::from(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<U256, E> {
+        Ok(U256::from(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<U256, E> {
+        u64::try_from(v)
+            .map(U256::from)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<U256, E> {
+        // Floats can't carry a large amount exactly; refuse rather than round
+        Err(E::invalid_value(de::Unexpected::Float(v), &"an integer amount (use a string for large values)"))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<U256, E> {
+        let parsed = match v.strip_prefix("0x").or_else(|| v.strip_prefix("0X")) {
+            Some(hex) => U256::from_str_radix(hex, 16),
+            None => U256::from_str(v),
+        };
+        parsed.map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] U256);
+
+    #[test]
+    fn test_amount_wire_formats() {
+        assert_eq!(serde_json::to_string(&Wrapper(U256::MAX)).unwrap(), format!("\"{}\"", U256::MAX));
+
+        for (json, expected) in [
+            ("\"1000000\"", U256::from(1000000)),
+            ("1000000", U256::from(1000000)),
+            ("\"0xf4240\"", U256::from(1000000)),
+            (&format!("\"{}\"", U256::MAX), U256::MAX),
+        ] {
+            assert_eq!(serde_json::from_str::<Wrapper>(json).unwrap().0, expected);
+        }
+
+        assert!(serde_json::from_str::<Wrapper>("-1").is_err());
+        assert!(serde_json::from_str::<Wrapper>("1e30").is_err());
+        assert!(serde_json::from_str::<Wrapper>("\"12abc\"").is_err());
+    }
+}
@@ -0,0 +1,518 @@
+//! Settlement cost estimation and batching
+//!
+//! Settling a payment on-chain costs gas, which for sub-cent payments can
+//! exceed the payment itself. [`estimate_settlement_cost`] prices the
+//! settlement of a payment with fee data from a pluggable [`GasProvider`],
+//! in the native token and, where the provider can price it, in the
+//! payment's token, so facilitators can defer or batch payments that
+//! aren't worth settling alone ([`SettlementCost::worth_settling`]).
+//!
+//! [`SettlementBatcher`] collects verified payments per network and token
+//! and hands each group to a [`BatchSettler`] — a multicall or aggregator
+//! contract client — as one transaction, once the group is full or its
+//! oldest payment has waited long enough. Payments that keep failing to
+//! settle are set aside as [`DeadLetter`]s instead of being retried forever.
+
+use crate::{Network, PaymentPayload, Result, SettlementReceipt, SignedPayment, X402Error};
+use alloy_primitives::{Address, B256, U256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Typical gas of settling one ERC-20 output (an EIP-3009
+/// `transferWithAuthorization`)
+pub const ERC20_SETTLEMENT_GAS: u64 = 80_000;
+
+/// Gas of a plain native-token transfer
+pub const NATIVE_SETTLEMENT_GAS: u64 = 21_000;
+
+/// Wei per whole native token
+const WEI_PER_NATIVE: u128 = 1_000_000_000_000_000_000;
+
+/// Source of fee data, e.g. an RPC client or gas oracle
+pub trait GasProvider: Send + Sync {
+    /// Current fee per gas on `network`, in wei
+    ///
+    /// On rollups this should include the amortized L1 data fee.
+    fn fee_per_gas(&self, network: Network) -> Result<u128>;
+
+    /// Value of one whole native token in units of `token` (the native
+    /// token itself if `None`), or `None` if the provider can't price it
+    fn native_price(&self, network: Network, token: Option<Address>) -> Result<Option<U256>>;
+
+    /// Gas settling `payment` will use
+    fn settlement_gas(&self, payment: &PaymentPayload) -> Result<u64> {
+        Ok(default_settlement_gas(payment))
+    }
+}
+
+/// Gas of settling `payment` with one transfer per output
+pub fn default_settlement_gas(payment: &PaymentPayload) -> u64 {
+    let per_output = if payment.token.is_some() { ERC20_SETTLEMENT_GAS } else { NATIVE_SETTLEMENT_GAS };
+    per_output * payment.splits.len().max(1) as u64
+}
+
+/// Expected cost of settling a payment on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettlementCost {
+    pub network: Network,
+    pub gas: u64,
+    /// Fee per gas, in wei
+    pub fee_per_gas: u128,
+    /// `gas × fee_per_gas`, in wei
+    pub native_fee: U256,
+    /// `native_fee` in units of the payment's token, rounded up; `None` if
+    /// the provider can't price the token
+    pub token_fee: Option<U256>,
+}
+
+impl SettlementCost {
+    /// Whether the fee is at most `max_fee_bps` basis points of `amount`
+    /// (in the payment's token); `None` if the fee can't be priced in the
+    /// token
+    pub fn worth_settling(&self, amount: U256, max_fee_bps: u32) -> Option<bool> {
+        let fee = self.token_fee?;
+        let budget = amount.saturating_mul(U256::from(max_fee_bps));
+        Some(fee.saturating_mul(U256::from(10_000)) <= budget)
+    }
+}
+
+/// Estimate the cost of settling `payment` individually
+pub fn estimate_settlement_cost<P: GasProvider + ?Sized>(
+    payment: &PaymentPayload,
+    provider: &P,
+) -> Result<SettlementCost> {
+    let network = Network::from_chain_id(payment.chain_id)
+        .ok_or_else(|| X402Error::UnsupportedNetwork(format!("unknown chain {}", payment.chain_id)))?;
+    let gas = provider.settlement_gas(payment)?;
+    let fee_per_gas = provider.fee_per_gas(network)?;
+    let native_fee = U256::from(gas) * U256::from(fee_per_gas);
+    let token_fee = match payment.token {
+        None => Some(native_fee),
+        Some(token) => provider.native_price(network, Some(token))?.map(|units_per_native| {
+            native_fee.saturating_mul(units_per_native).div_ceil(U256::from(WEI_PER_NATIVE))
+        }),
+    };
+    Ok(SettlementCost { network, gas, fee_per_gas, native_fee, token_fee })
+}
+
+/// [`GasProvider`] with fixed fees and prices, for tests and chains with
+/// stable fees
+#[derive(Debug, Clone, Default)]
+pub struct FixedGasProvider {
+    fees: HashMap<Network, u128>,
+    prices: HashMap<(Network, Option<Address>), U256>,
+}
+
+impl FixedGasProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Charge `fee_per_gas` wei per gas on `network`
+    pub fn with_fee(mut self, network: Network, fee_per_gas: u128) -> Self {
+        self.fees.insert(network, fee_per_gas);
+        self
+    }
+
+    /// Value one whole native token of `network` at `units_per_native`
+    /// units of `token`
+    pub fn with_native_price(mut self, network: Network, token: Address, units_per_native: U256) -> Self {
+        self.prices.insert((network, Some(token)), units_per_native);
+        self
+    }
+}
+
+impl GasProvider for FixedGasProvider {
+    fn fee_per_gas(&self, network: Network) -> Result<u128> {
+        self.fees
+            .get(&network)
+            .copied()
+            .ok_or_else(|| X402Error::InvalidConfig(format!("no fee configured for {}", network.as_str())))
+    }
+
+    fn native_price(&self, network: Network, token: Option<Address>) -> Result<Option<U256>> {
+        if token.is_none() {
+            return Ok(Some(U256::from(WEI_PER_NATIVE)));
+        }
+        Ok(self.prices.get(&(network, token)).copied())
+    }
+}
+
+/// Payments on one network in one token, settled together
+#[derive(Debug, Clone)]
+pub struct SettlementBatch {
+    pub network: Network,
+    /// `None` for native-token payments
+    pub token: Option<Address>,
+    /// In the order they were added
+    pub payments: Vec<SignedPayment>,
+}
+
+impl SettlementBatch {
+    /// Sum of the payment amounts
+    pub fn total(&self) -> U256 {
+        self.payments.iter().fold(U256::ZERO, |total, p| total.saturating_add(p.payment.amount))
+    }
+}
+
+/// Settles a whole batch in one transaction, e.g. through Multicall3 or an
+/// aggregator contract
+pub trait BatchSettler: Send + Sync {
+    /// Submit `batch`, returning the transaction hash
+    fn settle_batch(&self, batch: &SettlementBatch) -> Result<B256>;
+}
+
+/// When a [`SettlementBatcher`] settles a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchPolicy {
+    /// Settle as soon as a group holds this many payments
+    pub max_payments: usize,
+    /// Settle a group once its oldest payment has waited this many seconds
+    pub max_delay_seconds: u64,
+    /// Settlement attempts per payment before it becomes a [`DeadLetter`]
+    pub max_attempts: u32,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self { max_payments: 100, max_delay_seconds: 60, max_attempts: 5 }
+    }
+}
+
+/// A batch that was settled, with a receipt per payment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettledBatch {
+    pub network: Network,
+    pub token: Option<Address>,
+    pub receipts: Vec<SettlementReceipt>,
+}
+
+/// A payment given up on after [`BatchPolicy::max_attempts`] failed settlements
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub payment: SignedPayment,
+    pub attempts: u32,
+    /// Error of the last attempt
+    pub error: String,
+}
+
+type BatchKey = (Network, Option<Address>);
+
+/// Payer, chain and nonce of a queued or settled payment
+type PaymentKey = (Address, u64, u64);
+
+struct Queued {
+    payment: SignedPayment,
+    /// Failed settlements so far
+    attempts: u32,
+}
+
+struct PendingBatch {
+    /// When the oldest payment was added (unix timestamp)
+    since: u64,
+    payments: Vec<Queued>,
+}
+
+fn payment_key(payment: &PaymentPayload) -> PaymentKey {
+    (payment.payer, payment.chain_id, payment.nonce)
+}
+
+/// Groups verified payments by network and token and settles each group
+/// in one transaction
+///
+/// Groups are settled by [`SettlementBatcher::add`] when full, and by
+/// [`SettlementBatcher::flush_due`] once old enough; call the latter from a
+/// timer. A failed settlement leaves its payments queued for the next
+/// flush, up to [`BatchPolicy::max_attempts`] tries; then they move to
+/// [`SettlementBatcher::take_dead_letters`].
+///
+/// A payment is refused while one with the same payer, chain and nonce is
+/// queued, settled or dead-lettered and hasn't expired.
+pub struct SettlementBatcher<S: BatchSettler> {
+    settler: S,
+    policy: BatchPolicy,
+    pending: Mutex<HashMap<BatchKey, PendingBatch>>,
+    /// Expiry of every payment seen, until it passes
+    seen: Mutex<HashMap<PaymentKey, u64>>,
+    dead_letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl<S: BatchSettler> SettlementBatcher<S> {
+    pub fn new(settler: S, policy: BatchPolicy) -> Self {
+        Self {
+            settler,
+            policy,
+            pending: Mutex::new(HashMap::new()),
+            seen: Mutex::new(HashMap::new()),
+            dead_letters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queue a verified payment, settling its group if that fills it
+    pub fn add(&self, payment: SignedPayment) -> Result<Option<SettledBatch>> {
+        self.add_at(payment, unix_now())
+    }
+
+    /// [`SettlementBatcher::add`] as of the unix time `now`
+    pub fn add_at(&self, payment: SignedPayment, now: u64) -> Result<Option<SettledBatch>> {
+        let network = Network::from_chain_id(payment.payment.chain_id)
+            .ok_or_else(|| X402Error::UnsupportedNetwork(format!("unknown chain {}", payment.payment.chain_id)))?;
+        let key = (network, payment.payment.token);
+        {
+            let mut seen = self.seen.lock().unwrap();
+            seen.retain(|_, expires_at| *expires_at >= now);
+            if seen.contains_key(&payment_key(&payment.payment)) {
+                return Err(X402Error::DuplicatePayment(format!(
+                    "nonce {} from {} is already queued or settled",
+                    payment.payment.nonce, payment.payment.payer
+                )));
+            }
+            seen.insert(payment_key(&payment.payment), payment.payment.expires_at);
+        }
+
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            let group = pending.entry(key).or_insert_with(|| PendingBatch { since: now, payments: Vec::new() });
+            group.payments.push(Queued { payment, attempts: 0 });
+            group.payments.len() >= self.policy.max_payments
+        };
+        if full {
+            return self.settle(key).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Settle every group whose oldest payment has waited
+    /// `max_delay_seconds`
+    pub fn flush_due(&self) -> Vec<Result<SettledBatch>> {
+        self.flush_due_at(unix_now())
+    }
+
+    /// [`SettlementBatcher::flush_due`] as of the unix time `now`
+    pub fn flush_due_at(&self, now: u64) -> Vec<Result<SettledBatch>> {
+        let due: Vec<BatchKey> = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, group)| now.saturating_sub(group.since) >= self.policy.max_delay_seconds)
+            .map(|(key, _)| *key)
+            .collect();
+        due.into_iter().map(|key| self.settle(key)).collect()
+    }
+
+    /// Settle every group now, e.g. on shutdown
+    pub fn flush_all(&self) -> Vec<Result<SettledBatch>> {
+        let keys: Vec<BatchKey> = self.pending.lock().unwrap().keys().copied().collect();
+        keys.into_iter().map(|key| self.settle(key)).collect()
+    }
+
+    /// Payments waiting to be settled
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().values().map(|group| group.payments.len()).sum()
+    }
+
+    /// Payments given up on since the last call, for an operator or a
+    /// slower settlement path to handle
+    pub fn take_dead_letters(&self) -> Vec<DeadLetter> {
+        std::mem::take(&mut *self.dead_letters.lock().unwrap())
+    }
+
+    fn settle(&self, key: BatchKey) -> Result<SettledBatch> {
+        let Some(group) = self.pending.lock().unwrap().remove(&key) else {
+            return Ok(SettledBatch { network: key.0, token: key.1, receipts: Vec::new() });
+        };
+        let (payments, attempts): (Vec<_>, Vec<_>) =
+            group.payments.into_iter().map(|queued| (queued.payment, queued.attempts + 1)).unzip();
+        let batch = SettlementBatch { network: key.0, token: key.1, payments };
+        let transaction_hash = match self.settler.settle_batch(&batch) {
+            Ok(hash) => hash,
+            Err(e) => {
+                let (retry, dead): (Vec<_>, Vec<_>) = batch
+                    .payments
+                    .into_iter()
+                    .zip(attempts)
+                    .map(|(payment, attempts)| Queued { payment, attempts })
+                    .partition(|queued| queued.attempts < self.policy.max_attempts);
+                self.dead_letters.lock().unwrap().extend(dead.into_iter().map(|queued| DeadLetter {
+                    payment: queued.payment,
+                    attempts: queued.attempts,
+                    error: e.to_string(),
+                }));
+                if !retry.is_empty() {
+                    // Requeue ahead of anything added meanwhile
+                    let mut pending = self.pending.lock().unwrap();
+                    let requeued = pending
+                        .entry(key)
+                        .or_insert_with(|| PendingBatch { since: group.since, payments: Vec::new() });
+                    requeued.since = requeued.since.min(group.since);
+                    requeued.payments.splice(0..0, retry);
+                }
+                return Err(e);
+            }
+        };
+
+        let receipts = batch
+            .payments
+            .iter()
+            .map(|p| SettlementReceipt {
+                payer: p.payment.payer,
+                chain_id: p.payment.chain_id,
+                nonce: p.payment.nonce,
+                amount: p.payment.amount,
+                transaction_hash,
+            })
+            .collect();
+        Ok(SettledBatch { network: key.0, token: key.1, receipts })
+    }
+}
+
+impl<S: BatchSettler> std::fmt::Debug for SettlementBatcher<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SettlementBatcher")
+            .field("policy", &self.policy)
+            .field("pending", &self.pending())
+            .finish_non_exhaustive()
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PaymentRequirements, Stablecoin};
+
+    #[test]
+    fn test_estimate_settlement_cost() {
+        let usdc = Stablecoin::Usdc.address(Network::Base).unwrap();
+        let mainnet_usdc = Stablecoin::Usdc.address(Network::Ethereum).unwrap();
+        let eth_in_usdc = U256::from(3_000_000_000u64); // 3000 USDC
+        let provider = FixedGasProvider::new()
+            .with_fee(Network::Base, 1_000_000) // 0.001 gwei
+            .with_fee(Network::Ethereum, 20_000_000_000) // 20 gwei
+            .with_native_price(Network::Base, usdc, eth_in_usdc)
+            .with_native_price(Network::Ethereum, mainnet_usdc, eth_in_usdc);
+
+        let requirements = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/").unwrap();
+        let payment = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .expires_at(2_000)
+            .build()
+            .unwrap();
+        let cost = estimate_settlement_cost(&payment, &provider).unwrap();
+        assert_eq!(cost.gas, ERC20_SETTLEMENT_GAS);
+        assert_eq!(cost.native_fee, U256::from(80_000_000_000u64));
+        assert_eq!(cost.token_fee, Some(U256::from(240))); // 2.4% of 0.01 USDC
+        assert_eq!(cost.worth_settling(payment.amount, 300), Some(true));
+        assert_eq!(cost.worth_settling(payment.amount, 200), Some(false));
+
+        let mainnet = PaymentPayload {
+            chain_id: Network::Ethereum.chain_id(),
+            token: Some(mainnet_usdc),
+            ..payment.clone()
+        };
+        let cost = estimate_settlement_cost(&mainnet, &provider).unwrap();
+        assert_eq!(cost.token_fee, Some(U256::from(4_800_000)));
+        assert_eq!(cost.worth_settling(mainnet.amount, 1_000), Some(false));
+
+        let native = PaymentPayload { token: None, ..payment.clone() };
+        let cost = estimate_settlement_cost(&native, &provider).unwrap();
+        assert_eq!((cost.gas, cost.token_fee), (NATIVE_SETTLEMENT_GAS, Some(cost.native_fee)));
+
+        let unpriced = PaymentPayload { token: Some(Address::repeat_byte(0x33)), ..payment.clone() };
+        assert_eq!(estimate_settlement_cost(&unpriced, &provider).unwrap().worth_settling(U256::MAX, 1), None);
+        let unknown = PaymentPayload { chain_id: Network::Polygon.chain_id(), ..payment };
+        assert!(matches!(estimate_settlement_cost(&unknown, &provider), Err(X402Error::InvalidConfig(_))));
+    }
+
+    struct RecordingSettler {
+        batches: Mutex<Vec<(usize, U256)>>,
+        fail: std::sync::atomic::AtomicBool,
+    }
+
+    impl BatchSettler for RecordingSettler {
+        fn settle_batch(&self, batch: &SettlementBatch) -> Result<B256> {
+            if self.fail.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(X402Error::Storage("bundler unavailable".to_string()));
+            }
+            let mut batches = self.batches.lock().unwrap();
+            batches.push((batch.payments.len(), batch.total()));
+            Ok(B256::with_last_byte(batches.len() as u8))
+        }
+    }
+
+    fn signed(network: Network, nonce: u64) -> SignedPayment {
+        let requirements = PaymentRequirements::usdc(network, "0.001", Address::repeat_byte(0x11), "/").unwrap();
+        let payment = PaymentPayload::builder()
+            .requirements(&requirements)
+            .payer(Address::repeat_byte(0x22))
+            .nonce(nonce)
+            .expires_at(2_000)
+            .build()
+            .unwrap();
+        SignedPayment { payment, signature: vec![0; 65], signatures: Vec::new() }
+    }
+
+    #[test]
+    fn test_batcher_groups_and_flushes() {
+        let settler = RecordingSettler { batches: Mutex::new(Vec::new()), fail: Default::default() };
+        let policy = BatchPolicy { max_payments: 3, max_delay_seconds: 60, max_attempts: 2 };
+        let batcher = SettlementBatcher::new(settler, policy);
+        let nonce = |i: u64| crate::Nonce::MIN_RECOMMENDED + i;
+
+        assert!(batcher.add_at(signed(Network::Base, nonce(0)), 1_000).unwrap().is_none());
+        assert!(batcher.add_at(signed(Network::Arbitrum, nonce(1)), 1_010).unwrap().is_none());
+        assert!(batcher.add_at(signed(Network::Base, nonce(2)), 1_020).unwrap().is_none());
+        assert!(matches!(
+            batcher.add_at(signed(Network::Base, nonce(2)), 1_020),
+            Err(X402Error::DuplicatePayment(_))
+        ));
+        let full = batcher.add_at(signed(Network::Base, nonce(3)), 1_030).unwrap().unwrap();
+        assert_eq!(full.network, Network::Base);
+        assert_eq!(full.receipts.iter().map(|r| r.nonce).collect::<Vec<_>>(), vec![nonce(0), nonce(2), nonce(3)]);
+        assert!(full.receipts.iter().all(|r| r.transaction_hash == B256::with_last_byte(1)));
+        assert_eq!(batcher.pending(), 1);
+
+        assert!(batcher.flush_due_at(1_060).is_empty());
+        batcher.settler.fail.store(true, std::sync::atomic::Ordering::SeqCst);
+        let failed = batcher.flush_due_at(1_070);
+        assert!(matches!(failed[..], [Err(X402Error::Storage(_))]));
+        assert_eq!(batcher.pending(), 1);
+
+        batcher.settler.fail.store(false, std::sync::atomic::Ordering::SeqCst);
+        let settled = batcher.flush_due_at(1_071);
+        assert_eq!(settled.len(), 1);
+        assert_eq!(settled[0].as_ref().unwrap().network, Network::Arbitrum);
+        assert_eq!(batcher.pending(), 0);
+        // A settled nonce stays taken until the payment expires; the same
+        // nonce on another chain is a different payment
+        let replayed = batcher.add_at(signed(Network::Base, nonce(0)), 1_100);
+        assert!(matches!(replayed, Err(X402Error::DuplicatePayment(_))));
+        assert!(batcher.add_at(signed(Network::Optimism, nonce(0)), 1_100).unwrap().is_none());
+        assert!(batcher.add_at(signed(Network::Base, nonce(0)), 2_001).unwrap().is_none());
+
+        let batches = batcher.settler.batches.lock().unwrap();
+        assert_eq!(batches[..], [(3, U256::from(3_000)), (1, U256::from(1_000))]);
+    }
+
+    #[test]
+    fn test_batcher_dead_letters() {
+        let settler = RecordingSettler { batches: Mutex::new(Vec::new()), fail: true.into() };
+        let policy = BatchPolicy { max_payments: 10, max_delay_seconds: 0, max_attempts: 2 };
+        let batcher = SettlementBatcher::new(settler, policy);
+        batcher.add_at(signed(Network::Base, crate::Nonce::MIN_RECOMMENDED), 1_000).unwrap();
+
+        assert!(matches!(batcher.flush_due_at(1_000)[..], [Err(X402Error::Storage(_))]));
+        assert_eq!((batcher.pending(), batcher.take_dead_letters().len()), (1, 0));
+        assert!(matches!(batcher.flush_due_at(1_001)[..], [Err(X402Error::Storage(_))]));
+        assert_eq!(batcher.pending(), 0);
+        let dead = batcher.take_dead_letters();
+        assert_eq!((dead.len(), dead[0].attempts), (1, 2));
+        assert!(dead[0].error.contains("bundler unavailable"));
+        assert!(batcher.flush_due_at(1_002).is_empty());
+    }
+}
@@ -0,0 +1,371 @@
+//! Soft-fail verification
+//!
+//! In [`VerificationMode::SoftFail`] only the cheap local checks (signature
+//! recovery, amount, recipient, expiry) run before the request is served.
+//! The expensive [`DeferredCheck`] — typically a facilitator or on-chain
+//! balance check — runs afterwards on a background thread, and a payer
+//! whose payment later fails it is added to the [`PayerBlacklist`] and
+//! refused from then on. A check that panics counts as failed, and the
+//! worker goes on with the next payment. Deferred checks wait in a bounded
+//! queue; when the worker falls that far behind, payments are checked
+//! strictly until it catches up.
+//!
+//! This trades strictness for latency and is meant for cheap resources;
+//! [`RouteModes`] selects the mode per route.
+
+use crate::resource::prefix_matches;
+use crate::{verify_payment, PaymentRequirements, Result, SignedPayment, X402Error};
+use alloy_primitives::Address;
+use std::collections::HashSet;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+/// How thoroughly a payment is checked before the response is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerificationMode {
+    /// All checks complete before the request is served
+    #[default]
+    Strict,
+    /// Local checks inline, deferred checks after the response
+    SoftFail,
+}
+
+/// Per-route verification modes, matched by longest path prefix on path
+/// segment boundaries (`/cheap` covers `/cheap/item` but not `/cheaper`)
+#[derive(Debug, Clone, Default)]
+pub struct RouteModes {
+    default: VerificationMode,
+    routes: Vec<(String, VerificationMode)>,
+}
+
+impl RouteModes {
+    pub fn new(default: VerificationMode) -> Self {
+        Self { default, routes: Vec::new() }
+    }
+
+    /// Use `mode` for paths under `prefix`
+    pub fn route(mut self, prefix: impl Into<String>, mode: VerificationMode) -> Self {
+        self.routes.push((prefix.into(), mode));
+        self
+    }
+
+    pub fn mode_for(&self, path: &str) -> VerificationMode {
+        self.routes.iter()
+            .filter(|(prefix, _)| prefix_matches(prefix, path))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, mode)| *mode)
+    }
+}
+
+/// A check too slow to run before every response
+pub trait DeferredCheck: Send + Sync + 'static {
+    fn check(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<()>;
+}
+
+/// Payers refused because an earlier payment failed a deferred check
+pub trait PayerBlacklist: Send + Sync + 'static {
+    fn is_blacklisted(&self, payer: &Address) -> bool;
+    fn blacklist(&self, payer: Address, reason: &str);
+}
+
+/// In-process [`PayerBlacklist`]
+#[derive(Debug, Default)]
+pub struct MemoryBlacklist {
+    payers: RwLock<HashSet<Address>>,
+}
+
+impl MemoryBlacklist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PayerBlacklist for MemoryBlacklist {
+    fn is_blacklisted(&self, payer: &Address) -> bool {
+        self.payers.read().unwrap().contains(payer)
+    }
+
+    fn blacklist(&self, payer: Address, _reason: &str) {
+        self.payers.write().unwrap().insert(payer);
+    }
+}
+
+type Job = (SignedPayment, PaymentRequirements);
+
+/// Deferred checks that may wait for the worker before payments are checked strictly
+pub const DEFAULT_DEFERRED_QUEUE_CAPACITY: usize = 1024;
+
+/// Verifier supporting both strict and soft-fail modes
+pub struct SoftFailVerifier<C: DeferredCheck, B: PayerBlacklist> {
+    check: Arc<C>,
+    blacklist: Arc<B>,
+    queue: Option<SyncSender<Job>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<C: DeferredCheck, B: PayerBlacklist> SoftFailVerifier<C, B> {
+    /// Start the background worker that runs deferred checks, queueing up
+    /// to [`DEFAULT_DEFERRED_QUEUE_CAPACITY`] of them
+    pub fn new(check: C, blacklist: Arc<B>) -> Self {
+        Self::with_capacity(check, blacklist, DEFAULT_DEFERRED_QUEUE_CAPACITY)
+    }
+
+    /// Start the background worker, queueing up to `capacity` deferred checks
+    pub fn with_capacity(check: C, blacklist: Arc<B>, capacity: usize) -> Self {
+        let check = Arc::new(check);
+        let (queue, jobs) = mpsc::sync_channel::<Job>(capacity);
+
+        let worker = {
+            let check = Arc::clone(&check);
+            let blacklist = Arc::clone(&blacklist);
+            std::thread::Builder::new()
+                .name("x402-deferred-verify".to_string())
+                .spawn(move || {
+                    for (payment, requirements) in jobs {
+                        let failure = match catch_unwind(AssertUnwindSafe(|| check.check(&payment, &requirements))) {
+                            Ok(result) => result.err().map(|e| e.to_string()),
+                            Err(_) => Some("deferred check panicked".to_string()),
+                        };
+                        if let Some(reason) = failure {
+                            blacklist.blacklist(payment.payment.payer, &reason);
+                        }
+                    }
+                })
+                .expect("failed to spawn deferred verification thread")
+        };
+
+        Self {
+            check,
+            blacklist,
+            queue: Some(queue),
+            worker: Some(worker),
+        }
+    }
+
+    /// Verify a payment, returning the payer address
+    ///
+    /// In soft-fail mode a successful result only means the local checks
+    /// passed; the deferred check is still pending. If the queue of deferred
+    /// checks is full, the check runs now, as in strict mode.
+    pub fn verify(
+        &self,
+        payment: &SignedPayment,
+        requirements: &PaymentRequirements,
+        mode: VerificationMode,
+    ) -> Result<Address> {
+        if self.blacklist.is_blacklisted(&payment.payment.payer) {
+            return Err(X402Error::PayerBlacklisted(payment.payment.payer.to_string()));
+        }
+
+        let payer = verify_payment(payment, requirements)?;
+
+        match mode {
+            VerificationMode::Strict => self.check.check(payment, requirements)?,
+            VerificationMode::SoftFail => {
+                let queue = self.queue.as_ref().expect("verifier is running");
+                match queue.try_send((payment.clone(), requirements.clone())) {
+                    Ok(()) => {}
+                    // The worker only exits once the queue is dropped, but
+                    // should it die, checking now is still correct
+                    Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => {
+                        self.check.check(payment, requirements)?
+                    }
+                }
+            }
+        }
+
+        Ok(payer)
+    }
+
+    /// Stop accepting work and wait for pending deferred checks to finish
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        drop(self.queue.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<C: DeferredCheck, B: PayerBlacklist> Drop for SoftFailVerifier<C, B> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{Network, PaymentPayload, Scheme};
+    use alloy_primitives::U256;
+
+    struct RejectAll;
+
+    impl DeferredCheck for RejectAll {
+        fn check(&self, _: &SignedPayment, _: &PaymentRequirements) -> Result<()> {
+            Err(X402Error::InsufficientAmount { required: U256::from(1), provided: U256::ZERO })
+        }
+    }
+
+    fn signed_payment() -> (SignedPayment, PaymentRequirements) {
+        let signer = TestSigner::default();
+        let payload = PaymentPayload {
+            amount: U256::from(100),
+            recipient: Address::repeat_byte(0x11),
+            payer: signer.address(),
+            chain_id: 8453,
+            token: None,
+            resource: "/cheap".to_string(),
+            nonce: 1,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        let payment = signer.sign(payload).unwrap();
+
+        let requirements = PaymentRequirements {
+            amount: U256::from(100),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/cheap".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        (payment, requirements)
+    }
+
+    #[test]
+    fn test_soft_fail_blacklists_after_deferred_failure() {
+        let (payment, requirements) = signed_payment();
+        let blacklist = Arc::new(MemoryBlacklist::new());
+        let verifier = SoftFailVerifier::new(RejectAll, Arc::clone(&blacklist));
+
+        assert!(verifier.verify(&payment, &requirements, VerificationMode::Strict).is_err());
+        assert!(verifier.verify(&payment, &requirements, VerificationMode::SoftFail).is_ok());
+
+        verifier.shutdown();
+        assert!(blacklist.is_blacklisted(&payment.payment.payer));
+
+        let verifier = SoftFailVerifier::new(RejectAll, blacklist);
+        let result = verifier.verify(&payment, &requirements, VerificationMode::SoftFail);
+        assert!(matches!(result, Err(X402Error::PayerBlacklisted(_))));
+    }
+
+    #[test]
+    fn test_panicking_check_blacklists_and_worker_survives() {
+        /// Panics on its first call, rejects after that
+        #[derive(Default)]
+        struct PanicsOnce(std::sync::atomic::AtomicBool);
+
+        impl DeferredCheck for PanicsOnce {
+            fn check(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<()> {
+                assert!(self.0.swap(true, std::sync::atomic::Ordering::SeqCst), "check bug");
+                RejectAll.check(payment, requirements)
+            }
+        }
+
+        /// Records reasons without refusing anyone
+        #[derive(Default)]
+        struct Reasons(std::sync::Mutex<Vec<String>>);
+
+        impl PayerBlacklist for Reasons {
+            fn is_blacklisted(&self, _: &Address) -> bool {
+                false
+            }
+
+            fn blacklist(&self, _: Address, reason: &str) {
+                self.0.lock().unwrap().push(reason.to_string());
+            }
+        }
+
+        let (payment, requirements) = signed_payment();
+        let reasons = Arc::new(Reasons::default());
+        let verifier = SoftFailVerifier::new(PanicsOnce::default(), Arc::clone(&reasons));
+        assert!(verifier.verify(&payment, &requirements, VerificationMode::SoftFail).is_ok());
+        assert!(verifier.verify(&payment, &requirements, VerificationMode::SoftFail).is_ok());
+        verifier.shutdown();
+
+        let reasons = reasons.0.lock().unwrap();
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[0], "deferred check panicked");
+        assert!(reasons[1].contains("Insufficient"));
+    }
+
+    #[test]
+    fn test_full_queue_checks_strictly() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::mpsc::{Receiver, Sender};
+        use std::sync::Mutex;
+
+        /// Holds the worker in its first check until released; rejects every later payment
+        struct Gate {
+            holding: AtomicBool,
+            started: Mutex<Sender<()>>,
+            release: Mutex<Receiver<()>>,
+        }
+
+        impl DeferredCheck for Gate {
+            fn check(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<()> {
+                if !self.holding.swap(true, Ordering::SeqCst) {
+                    self.started.lock().unwrap().send(()).unwrap();
+                    self.release.lock().unwrap().recv().unwrap();
+                    return Ok(());
+                }
+                RejectAll.check(payment, requirements)
+            }
+        }
+
+        let (payment, requirements) = signed_payment();
+        let (started, on_start) = mpsc::channel();
+        let (release, on_release) = mpsc::channel();
+        let gate = Gate {
+            holding: AtomicBool::new(false),
+            started: Mutex::new(started),
+            release: Mutex::new(on_release),
+        };
+        let blacklist = Arc::new(MemoryBlacklist::new());
+        let verifier = SoftFailVerifier::with_capacity(gate, Arc::clone(&blacklist), 1);
+
+        // The worker takes the first payment and holds it; the second fills the queue
+        verifier.verify(&payment, &requirements, VerificationMode::SoftFail).unwrap();
+        on_start.recv().unwrap();
+        verifier.verify(&payment, &requirements, VerificationMode::SoftFail).unwrap();
+        // The third is checked inline, and rejected before it is served
+        let result = verifier.verify(&payment, &requirements, VerificationMode::SoftFail);
+        assert!(matches!(result, Err(X402Error::InsufficientAmount { .. })));
+
+        release.send(()).unwrap();
+        verifier.shutdown();
+        assert!(blacklist.is_blacklisted(&payment.payment.payer));
+    }
+
+    #[test]
+    fn test_route_modes_longest_prefix() {
+        let modes = RouteModes::new(VerificationMode::Strict)
+            .route("/cheap", VerificationMode::SoftFail)
+            .route("/cheap/premium", VerificationMode::Strict);
+        assert_eq!(modes.mode_for("/cheap"), VerificationMode::SoftFail);
+        assert_eq!(modes.mode_for("/cheap/item"), VerificationMode::SoftFail);
+        assert_eq!(modes.mode_for("/cheap/premium/x"), VerificationMode::Strict);
+        assert_eq!(modes.mode_for("/cheaper"), VerificationMode::Strict);
+        assert_eq!(modes.mode_for("/cheap/premiumx"), VerificationMode::SoftFail);
+        assert_eq!(modes.mode_for("/other"), VerificationMode::Strict);
+    }
+}
@@ -0,0 +1,381 @@
+//! Compatibility with the reference x402 specification
+//!
+//! The official TypeScript SDK and facilitators speak the published x402
+//! schema (`x402Version`, `scheme`, `payTo`, `maxAmountRequired`, `asset`,
+//! ...). The `Spec*` types here mirror that schema field for field and
+//! convert to and from this crate's types, so servers and clients built on
+//! either SDK can talk to each other.
+//!
+//! The reference payload carries an
+//! EIP-3009 authorization; this crate maps its own payment fields onto it,
+//! but the signature keeps this SDK's message format and only verifies with
+//! [`crate::verify_payment`].
+
+use crate::{
+    decode_header, parse_payload, DecodeLimits, Extra, DEFAULT_MAX_TIMEOUT_SECONDS, Network, PaymentPayload, PaymentRequiredResponse,
+    PaymentRequirements, Result, SignedPayment, X402Error, X402_VERSION,
+};
+use alloy_primitives::{Address, Bytes, B256, U256};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Network identifier used by the reference spec; `eip155:<chain id>` for
+/// [`Network::Custom`] chains
+pub fn spec_network_name(network: Network) -> Cow<'static, str> {
+    Cow::Borrowed(match network {
+        Network::Ethereum => "ethereum",
+        Network::Sepolia => "sepolia",
+        Network::Base => "base",
+        Network::BaseSepolia => "base-sepolia",
+        Network::Arbitrum => "arbitrum",
+        Network::ArbitrumSepolia => "arbitrum-sepolia",
+        Network::Optimism => "optimism",
+        Network::OptimismSepolia => "optimism-sepolia",
+        Network::Polygon => "polygon",
+        Network::PolygonAmoy => "polygon-amoy",
+        Network::Avalanche => "avalanche",
+        Network::AvalancheFuji => "avalanche-fuji",
+        Network::Bnb => "bnb",
+        Network::BnbTestnet => "bnb-testnet",
+        Network::ZkSync => "zksync",
+        Network::ZkSyncSepolia => "zksync-sepolia",
+        Network::Linea => "linea",
+        Network::LineaSepolia => "linea-sepolia",
+        Network::Scroll => "scroll",
+        Network::ScrollSepolia => "scroll-sepolia",
+        Network::Custom(_) => return network.as_str(),
+    })
+}
+
+/// Parse a reference spec network identifier
+pub fn network_from_spec_name(name: &str) -> Result<Network> {
+    match name {
+        "ethereum" => Ok(Network::Ethereum),
+        "sepolia" => Ok(Network::Sepolia),
+        "base" => Ok(Network::Base),
+        "base-sepolia" => Ok(Network::BaseSepolia),
+        "arbitrum" => Ok(Network::Arbitrum),
+        "arbitrum-sepolia" => Ok(Network::ArbitrumSepolia),
+        "optimism" => Ok(Network::Optimism),
+        "optimism-sepolia" => Ok(Network::OptimismSepolia),
+        "polygon" => Ok(Network::Polygon),
+        "polygon-amoy" => Ok(Network::PolygonAmoy),
+        "avalanche" => Ok(Network::Avalanche),
+        "avalanche-fuji" => Ok(Network::AvalancheFuji),
+        "bnb" => Ok(Network::Bnb),
+        "bnb-testnet" => Ok(Network::BnbTestnet),
+        "zksync" => Ok(Network::ZkSync),
+        "zksync-sepolia" => Ok(Network::ZkSyncSepolia),
+        "linea" => Ok(Network::Linea),
+        "linea-sepolia" => Ok(Network::LineaSepolia),
+        "scroll" => Ok(Network::Scroll),
+        "scroll-sepolia" => Ok(Network::ScrollSepolia),
+        other if other.starts_with("eip155:") => other.parse(),
+        other => Err(X402Error::UnsupportedNetwork(other.to_string())),
+    }
+}
+
+/// `PaymentRequirements` as defined by the reference spec
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecPaymentRequirements {
+    pub scheme: String,
+    pub network: String,
+    #[serde(with = "crate::serde_amount")]
+    pub max_amount_required: U256,
+    pub resource: String,
+    pub description: String,
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    pub pay_to: Address,
+    pub max_timeout_seconds: u64,
+    /// Token contract; the zero address stands for the native token
+    pub asset: Address,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<serde_json::Value>,
+}
+
+impl From<&PaymentRequirements> for SpecPaymentRequirements {
+    fn from(requirements: &PaymentRequirements) -> Self {
+        Self {
+            scheme: requirements.scheme.as_str().to_string(),
+            network: spec_network_name(requirements.network).to_string(),
+            max_amount_required: requirements.amount,
+            resource: requirements.resource.clone(),
+            description: requirements.description.clone().unwrap_or_default(),
+            mime_type: requirements.mime_type.clone().unwrap_or_default(),
+            output_schema: requirements.output_schema.clone(),
+            pay_to: requirements.recipient,
+            max_timeout_seconds: requirements.max_timeout_seconds.unwrap_or(DEFAULT_MAX_TIMEOUT_SECONDS),
+            asset: requirements.token.unwrap_or(Address::ZERO),
+            extra: Some(&requirements.extra)
+                .filter(|extra| !extra.is_empty())
+                .map(|extra| serde_json::Value::Object(extra.clone().into_iter().collect())),
+        }
+    }
+}
+
+impl TryFrom<SpecPaymentRequirements> for PaymentRequirements {
+    type Error = X402Error;
+
+    fn try_from(spec: SpecPaymentRequirements) -> Result<Self> {
+        let scheme = spec.scheme.parse()?;
+        let extra = match spec.extra {
+            None | Some(serde_json::Value::Null) => Extra::new(),
+            Some(serde_json::Value::Object(map)) => map.into_iter().collect(),
+            Some(_) => return Err(X402Error::InvalidHeader("extra must be a JSON object".to_string())),
+        };
+        Ok(Self {
+            amount: spec.max_amount_required,
+            recipient: spec.pay_to,
+            network: network_from_spec_name(&spec.network)?,
+            token: Some(spec.asset).filter(|asset| !asset.is_zero()),
+            description: Some(spec.description).filter(|d| !d.is_empty()),
+            expires_at: None,
+            resource: spec.resource,
+            commitment: None,
+            scheme,
+            mime_type: Some(spec.mime_type).filter(|m| !m.is_empty()),
+            output_schema: spec.output_schema,
+            max_timeout_seconds: Some(spec.max_timeout_seconds),
+            extra,
+            splits: Vec::new(),
+            price_quote: None,
+        })
+    }
+}
+
+/// 402 response body as defined by the reference spec
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecPaymentRequiredResponse {
+    pub x402_version: u32,
+    #[serde(default)]
+    pub error: String,
+    pub accepts: Vec<SpecPaymentRequirements>,
+}
+
+impl From<&PaymentRequiredResponse> for SpecPaymentRequiredResponse {
+    fn from(response: &PaymentRequiredResponse) -> Self {
+        Self {
+            x402_version: response.x402_version,
+            error: response.error.clone().unwrap_or_default(),
+            accepts: response.accepts.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<SpecPaymentRequiredResponse> for PaymentRequiredResponse {
+    type Error = X402Error;
+
+    /// Options with unsupported schemes or networks are skipped; it is an
+    /// error only if none remain
+    fn try_from(spec: SpecPaymentRequiredResponse) -> Result<Self> {
+        let mut last_error = None;
+        let mut accepts = Vec::new();
+        for option in spec.accepts {
+            match PaymentRequirements::try_from(option) {
+                Ok(requirements) => accepts.push(requirements),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        if accepts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                X402Error::InvalidHeader("402 response carries no payment requirements".to_string())
+            }));
+        }
+        Ok(Self {
+            x402_version: spec.x402_version,
+            error: Some(spec.error).filter(|e| !e.is_empty()),
+            accepts,
+            lightning: None,
+            zk: None,
+        })
+    }
+}
+
+/// EIP-3009 style authorization inside a spec payment payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecAuthorization {
+    pub from: Address,
+    pub to: Address,
+    #[serde(with = "crate::serde_amount")]
+    pub value: U256,
+    /// Unix timestamp as a decimal string
+    pub valid_after: String,
+    /// Unix timestamp as a decimal string
+    pub valid_before: String,
+    pub nonce: B256,
+}
+
+/// Scheme-specific part of a spec payment payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecExactPayload {
+    pub signature: Bytes,
+    pub authorization: SpecAuthorization,
+}
+
+/// `X-PAYMENT` header payload as defined by the reference spec
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecPaymentPayload {
+    pub x402_version: u32,
+    pub scheme: String,
+    pub network: String,
+    pub payload: SpecExactPayload,
+}
+
+impl TryFrom<&SignedPayment> for SpecPaymentPayload {
+    type Error = X402Error;
+
+    fn try_from(signed: &SignedPayment) -> Result<Self> {
+        let payment = &signed.payment;
+        let network = Network::from_chain_id(payment.chain_id)
+            .ok_or_else(|| X402Error::UnsupportedNetwork(payment.chain_id.to_string()))?;
+        Ok(Self {
+            x402_version: X402_VERSION,
+            scheme: payment.scheme.as_str().to_string(),
+            network: spec_network_name(network).to_string(),
+            payload: SpecExactPayload {
+                signature: Bytes::copy_from_slice(&signed.signature),
+                authorization: SpecAuthorization {
+                    from: payment.payer,
+                    to: payment.recipient,
+                    value: payment.amount,
+                    valid_after: "0".to_string(),
+                    valid_before: payment.expires_at.to_string(),
+                    nonce: B256::from(U256::from(payment.nonce)),
+                },
+            },
+        })
+    }
+}
+
+impl SpecPaymentPayload {
+    /// Convert to a [`SignedPayment`] answering `requirements`
+    ///
+    /// The spec payload doesn't name the resource or the token, so they
+    /// come from the requirements the payment answers.
+    pub fn to_signed_payment(&self, requirements: &PaymentRequirements) -> Result<SignedPayment> {
+        let scheme = self.scheme.parse()?;
+        let network = network_from_spec_name(&self.network)?;
+        let authorization = &self.payload.authorization;
+        let nonce = u64::try_from(U256::from_be_bytes(authorization.nonce.0))
+            .map_err(|_| X402Error::InvalidHeader("nonce does not fit in 64 bits".to_string()))?;
+        let expires_at = authorization.valid_before.parse::<u64>()
+            .map_err(|_| X402Error::InvalidHeader(format!("invalid validBefore: {}", authorization.valid_before)))?;
+
+        Ok(SignedPayment {
+            payment: PaymentPayload {
+                amount: authorization.value,
+                recipient: authorization.to,
+                payer: authorization.from,
+                chain_id: network.chain_id(),
+                token: requirements.token,
+                resource: requirements.resource.clone(),
+                nonce,
+                expires_at,
+                scheme,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: self.payload.signature.to_vec(),
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Encode as an `X-PAYMENT` header value
+    pub fn to_header(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(BASE64.encode(json))
+    }
+
+    /// Decode an `X-PAYMENT` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let (format, bytes) = decode_header(header.as_ref(), &limits)?;
+        parse_payload(format, &bytes, &limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    fn requirements() -> PaymentRequirements {
+        PaymentRequirements {
+            amount: U256::from(10000),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::BaseSepolia,
+            token: Some(Address::repeat_byte(0x33)),
+            description: Some("Weather data".to_string()),
+            expires_at: None,
+            resource: "https://api.example.com/weather".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        }
+    }
+
+    #[test]
+    fn test_requirements_match_reference_schema() {
+        let response = PaymentRequiredResponse::new(requirements());
+        let spec = SpecPaymentRequiredResponse::from(&response);
+        let json: serde_json::Value = serde_json::to_value(&spec).unwrap();
+        let option = &json["accepts"][0];
+        assert_eq!(json["x402Version"], 1);
+        assert_eq!(option["scheme"], "exact");
+        assert_eq!(option["network"], "base-sepolia");
+        assert_eq!(option["maxAmountRequired"], "10000");
+        assert_eq!(option["payTo"], Address::repeat_byte(0x11).to_string());
+
+        let back = PaymentRequiredResponse::try_from(spec).unwrap();
+        assert_eq!(back.accepts[0].token, requirements().token);
+        assert_eq!(back.accepts[0].description, requirements().description);
+
+        let mut other = SpecPaymentRequirements::from(&requirements());
+        other.scheme = "stream".to_string();
+        assert!(PaymentRequirements::try_from(other).is_err());
+    }
+
+    #[test]
+    fn test_payment_payload_roundtrip() {
+        let signed = SignedPayment {
+            payment: PaymentPayload {
+                amount: U256::from(10000),
+                recipient: Address::repeat_byte(0x11),
+                payer: Address::repeat_byte(0x22),
+                chain_id: Network::BaseSepolia.chain_id(),
+                token: Some(Address::repeat_byte(0x33)),
+                resource: "https://api.example.com/weather".to_string(),
+                nonce: 7,
+                expires_at: 1700000000,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: vec![0xab; 65],
+            signatures: Vec::new(),
+        };
+
+        let header = SpecPaymentPayload::try_from(&signed).unwrap().to_header().unwrap();
+        let spec = SpecPaymentPayload::from_header(&header).unwrap();
+        assert_eq!(spec.payload.authorization.valid_before, "1700000000");
+
+        let back = spec.to_signed_payment(&requirements()).unwrap();
+        assert_eq!(back.payment.message_hash(), signed.payment.message_hash());
+        assert_eq!(back.signature, signed.signature);
+    }
+}
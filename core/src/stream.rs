@@ -0,0 +1,337 @@
+//! Streaming micropayments
+//!
+//! For chunked or SSE responses a server can charge as content flows
+//! instead of up front. It meters delivery with a [`StreamAccount`]; when
+//! the client's paid credit runs low the account emits a [`StreamDemand`],
+//! which the server sends in-band (for SSE, [`StreamDemand::to_sse_event`]).
+//!
+//! The client answers each demand with an ordinary signed payment whose
+//! `extra` names the stream and the demand's sequence number
+//! ([`STREAM_ID_KEY`], [`STREAM_SEQUENCE_KEY`]), so the binding is covered
+//! by the signature and a payment can't be replayed against another stream
+//! or demand. [`StreamTopUp`] builds those payments on the client side,
+//! within a spending budget; signing is left to the client's signer.
+
+#[cfg(feature = "verify")]
+use crate::{verify_payment_at, SignedPayment};
+use crate::{PaymentPayload, PaymentRequirements, Result, X402Error};
+use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+/// `extra` key carrying the stream ID
+pub const STREAM_ID_KEY: &str = "streamId";
+
+/// `extra` key carrying the demand sequence number
+pub const STREAM_SEQUENCE_KEY: &str = "streamSequence";
+
+/// SSE event name for in-band payment demands
+pub const STREAM_DEMAND_EVENT: &str = "x402-payment-required";
+
+/// What a stream is metered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamUnit {
+    Bytes,
+    Events,
+}
+
+/// Price of streamed content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamPricing {
+    pub unit: StreamUnit,
+    /// Units of content bought by one payment
+    pub units_per_payment: u64,
+    /// Price of one payment
+    #[serde(with = "crate::serde_amount")]
+    pub price: U256,
+}
+
+/// In-band demand for the next payment on a stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamDemand {
+    pub stream_id: String,
+    /// Sequence number of the payment demanded, starting at 0
+    pub sequence: u64,
+    pub unit: StreamUnit,
+    /// Units of content the payment buys
+    pub units: u64,
+    /// Terms of the payment, with the stream binding in `extra`
+    pub requirements: PaymentRequirements,
+}
+
+impl StreamDemand {
+    /// Encode as a server-sent event
+    pub fn to_sse_event(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(format!("event: {}\ndata: {}\n\n", STREAM_DEMAND_EVENT, json))
+    }
+
+    /// Parse the `data` of a demand event
+    pub fn from_sse_data(data: &str) -> Result<Self> {
+        serde_json::from_str(data).map_err(X402Error::Json)
+    }
+}
+
+/// Server-side accounting for one paid stream
+///
+/// Credit starts at zero: send [`demand`](Self::demand) when the stream
+/// opens, and again whenever [`record_delivery`](Self::record_delivery)
+/// fails with [`X402Error::StreamCreditExhausted`].
+#[derive(Debug, Clone)]
+pub struct StreamAccount {
+    stream_id: String,
+    requirements: PaymentRequirements,
+    pricing: StreamPricing,
+    low_water: u64,
+    payer: Option<Address>,
+    next_sequence: u64,
+    demanded: bool,
+    units_paid: u64,
+    units_delivered: u64,
+    total_paid: U256,
+}
+
+impl StreamAccount {
+    /// Account for `stream_id`, charging `pricing` under `requirements`
+    /// (the amount is replaced by the pricing's per-payment price)
+    pub fn new(stream_id: impl Into<String>, requirements: PaymentRequirements, pricing: StreamPricing) -> Result<Self> {
+        if pricing.units_per_payment == 0 {
+            return Err(X402Error::InvalidConfig("stream units_per_payment must be positive".to_string()));
+        }
+        Ok(Self {
+            stream_id: stream_id.into(),
+            requirements,
+            pricing,
+            low_water: 0,
+            payer: None,
+            next_sequence: 0,
+            demanded: false,
+            units_paid: 0,
+            units_delivered: 0,
+            total_paid: U256::ZERO,
+        })
+    }
+
+    /// Demand the next payment once remaining credit drops to `units`,
+    /// so a prompt client is never stalled
+    pub fn with_low_water(mut self, units: u64) -> Self {
+        self.low_water = units;
+        self
+    }
+
+    pub fn stream_id(&self) -> &str {
+        &self.stream_id
+    }
+
+    /// Payer bound by the first accepted payment
+    pub fn payer(&self) -> Option<Address> {
+        self.payer
+    }
+
+    /// Units paid for but not yet delivered
+    pub fn remaining(&self) -> u64 {
+        self.units_paid - self.units_delivered
+    }
+
+    pub fn units_delivered(&self) -> u64 {
+        self.units_delivered
+    }
+
+    pub fn total_paid(&self) -> U256 {
+        self.total_paid
+    }
+
+    /// Demand for the next payment
+    pub fn demand(&self) -> StreamDemand {
+        let mut requirements = self.requirements.clone();
+        requirements.amount = self.pricing.price;
+        requirements.extra.insert(STREAM_ID_KEY.to_string(), self.stream_id.clone().into());
+        requirements.extra.insert(STREAM_SEQUENCE_KEY.to_string(), self.next_sequence.into());
+        StreamDemand {
+            stream_id: self.stream_id.clone(),
+            sequence: self.next_sequence,
+            unit: self.pricing.unit,
+            units: self.pricing.units_per_payment,
+            requirements,
+        }
+    }
+
+    /// Record `units` of content about to be sent
+    ///
+    /// Fails without recording anything if the paid credit doesn't cover
+    /// them. Returns a demand, once per payment, when the remaining credit
+    /// reaches the low-water mark.
+    pub fn record_delivery(&mut self, units: u64) -> Result<Option<StreamDemand>> {
+        if units > self.remaining() {
+            return Err(X402Error::StreamCreditExhausted {
+                stream_id: self.stream_id.clone(),
+                sequence: self.next_sequence,
+            });
+        }
+        self.units_delivered += units;
+        if self.remaining() <= self.low_water && !self.demanded {
+            self.demanded = true;
+            return Ok(Some(self.demand()));
+        }
+        Ok(None)
+    }
+
+    /// Verify a payment answering the current demand and add its credit,
+    /// returning the payer
+    #[cfg(feature = "verify")]
+    pub fn apply_payment(&mut self, payment: &SignedPayment, now: u64) -> Result<Address> {
+        let extra = &payment.payment.extra;
+        if extra.get(STREAM_ID_KEY).and_then(|id| id.as_str()) != Some(self.stream_id.as_str()) {
+            return Err(X402Error::InvalidStreamPayment(format!("payment is not for stream {}", self.stream_id)));
+        }
+        let sequence = extra.get(STREAM_SEQUENCE_KEY).and_then(|seq| seq.as_u64()).ok_or_else(|| {
+            X402Error::InvalidStreamPayment("payment carries no stream sequence".to_string())
+        })?;
+        if sequence < self.next_sequence {
+            return Err(X402Error::DuplicatePayment(format!("stream {} payment {}", self.stream_id, sequence)));
+        }
+        if sequence > self.next_sequence {
+            return Err(X402Error::InvalidStreamPayment(format!(
+                "expected payment {}, got {}",
+                self.next_sequence, sequence
+            )));
+        }
+
+        let payer = verify_payment_at(payment, &self.demand().requirements, now)?;
+        if self.payer.is_some_and(|bound| bound != payer) {
+            return Err(X402Error::InvalidStreamPayment(format!(
+                "stream {} is paid for by another payer",
+                self.stream_id
+            )));
+        }
+
+        self.payer = Some(payer);
+        self.next_sequence += 1;
+        self.demanded = false;
+        self.units_paid += self.pricing.units_per_payment;
+        self.total_paid += payment.payment.amount;
+        Ok(payer)
+    }
+}
+
+/// Client-side automatic top-up for paid streams
+///
+/// Turns demands into payments to sign, refusing once the budget would be
+/// exceeded or a demand repeats.
+#[derive(Debug, Clone)]
+pub struct StreamTopUp {
+    payer: Address,
+    budget: U256,
+    spent: U256,
+    next_nonce: u64,
+    paid: Vec<(String, u64)>,
+}
+
+impl StreamTopUp {
+    /// Top-up for `payer`, spending at most `budget`, with nonces counting
+    /// up from `first_nonce`
+    pub fn new(payer: Address, budget: U256, first_nonce: u64) -> Self {
+        Self { payer, budget, spent: U256::ZERO, next_nonce: first_nonce, paid: Vec::new() }
+    }
+
+    pub fn spent(&self) -> U256 {
+        self.spent
+    }
+
+    /// Payment answering `demand`, to be signed and sent to the server
+    pub fn payment_for(&mut self, demand: &StreamDemand, now: u64) -> Result<PaymentPayload> {
+        let requirements = &demand.requirements;
+        let bound_id = requirements.extra.get(STREAM_ID_KEY).and_then(|id| id.as_str());
+        let bound_sequence = requirements.extra.get(STREAM_SEQUENCE_KEY).and_then(|seq| seq.as_u64());
+        if bound_id != Some(demand.stream_id.as_str()) || bound_sequence != Some(demand.sequence) {
+            return Err(X402Error::InvalidStreamPayment("demand requirements don't name its stream".to_string()));
+        }
+        let key = (demand.stream_id.clone(), demand.sequence);
+        if self.paid.contains(&key) {
+            return Err(X402Error::DuplicatePayment(format!("stream {} payment {}", key.0, key.1)));
+        }
+        let spent = self.spent.saturating_add(requirements.amount);
+        if spent > self.budget {
+            return Err(X402Error::InvalidStreamPayment(format!(
+                "stream budget of {} exhausted ({} spent)",
+                self.budget, self.spent
+            )));
+        }
+
+        let payment = PaymentPayload {
+            amount: requirements.amount,
+            recipient: requirements.recipient,
+            payer: self.payer,
+            chain_id: requirements.network.chain_id(),
+            token: requirements.token,
+            resource: requirements.resource.clone(),
+            nonce: self.next_nonce,
+            expires_at: requirements.payment_expires_at(now),
+            scheme: requirements.scheme,
+            extra: requirements.extra.clone(),
+            invoice_id: None,
+            splits: requirements.splits.clone(),
+        };
+        self.next_nonce += 1;
+        self.spent = spent;
+        self.paid.push(key);
+        Ok(payment)
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::{Network, Scheme};
+
+    #[test]
+    fn test_stream_payments() {
+        let signer = TestSigner::default();
+        let requirements = PaymentRequirements {
+            amount: U256::ZERO,
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/events".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: Some("text/event-stream".to_string()),
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        let pricing = StreamPricing { unit: StreamUnit::Events, units_per_payment: 10, price: U256::from(5) };
+        let mut account = StreamAccount::new("s-1", requirements, pricing).unwrap().with_low_water(2);
+        let mut top_up = StreamTopUp::new(signer.address(), U256::from(10), 1);
+        let now = 1_700_000_000;
+
+        assert!(matches!(account.record_delivery(1), Err(X402Error::StreamCreditExhausted { sequence: 0, .. })));
+        let event = account.demand().to_sse_event().unwrap();
+        let data = event.lines().find_map(|line| line.strip_prefix("data: ")).unwrap();
+        let first = signer.sign(top_up.payment_for(&StreamDemand::from_sse_data(data).unwrap(), now).unwrap()).unwrap();
+        assert_eq!(account.apply_payment(&first, now).unwrap(), signer.address());
+        assert!(matches!(account.apply_payment(&first, now), Err(X402Error::DuplicatePayment(_))));
+
+        assert!(account.record_delivery(7).unwrap().is_none());
+        let demand = account.record_delivery(1).unwrap().expect("low water reached");
+        assert!(account.record_delivery(1).unwrap().is_none());
+        let second = signer.sign(top_up.payment_for(&demand, now).unwrap()).unwrap();
+        account.apply_payment(&second, now).unwrap();
+        assert_eq!((account.remaining(), account.total_paid()), (11, U256::from(10)));
+
+        // Budget spent; a payment for another stream is refused as well
+        assert!(top_up.payment_for(&account.demand(), now).is_err());
+        let mut other = second.payment.clone();
+        other.extra.insert(STREAM_ID_KEY.to_string(), "s-2".into());
+        let other = signer.sign(other).unwrap();
+        assert!(matches!(account.apply_payment(&other, now), Err(X402Error::InvalidStreamPayment(_))));
+    }
+}
@@ -0,0 +1,218 @@
+//! Recurring subscription payments
+//!
+//! For monthly-billed API plans a payer signs one
+//! [`SubscriptionAuthorization`]: an amount per period, the period length,
+//! a start and optional end, and an optional cap on the number of charges.
+//! The server then charges at most once per period with
+//! [`charge_subscription`], tracking consumed periods through a
+//! [`SubscriptionScheduler`].
+//!
+//! Periods are not charged retroactively: a period in which no charge was
+//! made is simply skipped.
+
+#[cfg(feature = "verify")]
+use crate::recover_address;
+use crate::{Result, X402Error};
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+
+/// Terms of a recurring payment, as signed by the payer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionAuthorization {
+    pub payer: Address,
+    pub recipient: Address,
+    pub chain_id: u64,
+    /// Token contract (`None` = native token)
+    pub token: Option<Address>,
+    /// Plan or resource subscribed to
+    pub resource: String,
+    #[serde(with = "crate::serde_amount")]
+    pub amount_per_period: U256,
+    pub period_seconds: u64,
+    /// Start of the first period (unix timestamp)
+    pub starts_at: u64,
+    /// End of the subscription (unix timestamp); `None` until cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<u64>,
+    /// Most periods that may be charged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_charges: Option<u64>,
+    /// Nonce distinguishing subscriptions with the same terms
+    pub nonce: u64,
+}
+
+impl SubscriptionAuthorization {
+    /// Create the message hash the payer signs
+    pub fn message_hash(&self) -> [u8; 32] {
+        let message = format!(
+            "x402 Subscription\nPayer: {}\nRecipient: {}\nChainId: {}\nToken: {}\nResource: {}\n\
+             AmountPerPeriod: {}\nPeriod: {}\nStarts: {}\nEnds: {}\nMaxCharges: {}\nNonce: {}",
+            self.payer,
+            self.recipient,
+            self.chain_id,
+            self.token.map(|t| t.to_string()).unwrap_or_default(),
+            self.resource,
+            self.amount_per_period,
+            self.period_seconds,
+            self.starts_at,
+            self.ends_at.map(|t| t.to_string()).unwrap_or_default(),
+            self.max_charges.map(|n| n.to_string()).unwrap_or_default(),
+            self.nonce
+        );
+        *keccak256(message.as_bytes())
+    }
+
+    /// Identifier the scheduler tracks the subscription under
+    pub fn subscription_id(&self) -> B256 {
+        B256::from(self.message_hash())
+    }
+
+    /// Index of the period containing `now`, if the subscription is active
+    pub fn period_at(&self, now: u64) -> Option<u64> {
+        if self.period_seconds == 0 || now < self.starts_at || self.ends_at.is_some_and(|end| now >= end) {
+            return None;
+        }
+        Some((now - self.starts_at) / self.period_seconds)
+    }
+}
+
+/// Subscription authorization with the payer's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedSubscription {
+    pub authorization: SubscriptionAuthorization,
+    /// ECDSA signature (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+impl SignedSubscription {
+    /// Check the signature, returning the payer address
+    #[cfg(feature = "verify")]
+    pub fn verify(&self) -> Result<Address> {
+        let signer = recover_address(&self.authorization.message_hash(), &self.signature)?;
+        if signer != self.authorization.payer {
+            return Err(X402Error::InvalidSignature(
+                "recovered address does not match payer".to_string()
+            ));
+        }
+        Ok(signer)
+    }
+}
+
+/// A charge made for one subscription period
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionCharge {
+    pub subscription_id: B256,
+    pub period: u64,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+}
+
+/// Storage of the periods charged per subscription
+pub trait SubscriptionScheduler: Send + Sync {
+    /// Number of periods charged so far
+    fn charges(&self, subscription_id: &B256) -> Result<u64>;
+    /// Record a charge for `period`; fails with
+    /// [`X402Error::DuplicatePayment`] if it was already charged
+    fn record_charge(&self, subscription_id: &B256, period: u64) -> Result<()>;
+}
+
+/// In-process [`SubscriptionScheduler`]
+#[derive(Debug, Default)]
+pub struct MemorySubscriptionScheduler {
+    periods: RwLock<HashMap<B256, BTreeSet<u64>>>,
+}
+
+impl MemorySubscriptionScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SubscriptionScheduler for MemorySubscriptionScheduler {
+    fn charges(&self, subscription_id: &B256) -> Result<u64> {
+        let periods = self.periods.read().unwrap();
+        Ok(periods.get(subscription_id).map_or(0, |charged| charged.len() as u64))
+    }
+
+    fn record_charge(&self, subscription_id: &B256, period: u64) -> Result<()> {
+        let mut periods = self.periods.write().unwrap();
+        if !periods.entry(*subscription_id).or_default().insert(period) {
+            return Err(X402Error::DuplicatePayment(format!(
+                "subscription {} period {}",
+                subscription_id, period
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Charge the period containing `now`, as of the unix time `now`
+///
+/// Fails if the signature is invalid, the subscription isn't active, the
+/// charge cap is reached, or the period was already charged.
+#[cfg(feature = "verify")]
+pub fn charge_subscription<S: SubscriptionScheduler + ?Sized>(
+    subscription: &SignedSubscription,
+    scheduler: &S,
+    now: u64,
+) -> Result<SubscriptionCharge> {
+    subscription.verify()?;
+    let authorization = &subscription.authorization;
+    let subscription_id = authorization.subscription_id();
+
+    let period = authorization.period_at(now).ok_or_else(|| {
+        X402Error::InvalidSubscription(format!("subscription {} is not active", subscription_id))
+    })?;
+    if let Some(max_charges) = authorization.max_charges {
+        if scheduler.charges(&subscription_id)? >= max_charges {
+            return Err(X402Error::InvalidSubscription(format!(
+                "subscription {} reached its {} charges",
+                subscription_id, max_charges
+            )));
+        }
+    }
+    scheduler.record_charge(&subscription_id, period)?;
+
+    Ok(SubscriptionCharge { subscription_id, period, amount: authorization.amount_per_period })
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+
+    #[test]
+    fn test_subscription_periods() {
+        let signer = TestSigner::default();
+        let authorization = SubscriptionAuthorization {
+            payer: signer.address(),
+            recipient: Address::repeat_byte(0x11),
+            chain_id: 8453,
+            token: None,
+            resource: "plan:pro".to_string(),
+            amount_per_period: U256::from(500),
+            period_seconds: 100,
+            starts_at: 1_000,
+            ends_at: Some(2_000),
+            max_charges: Some(2),
+            nonce: 1,
+        };
+        let signature = signer.sign_hash(&authorization.message_hash()).unwrap();
+        let subscription = SignedSubscription { authorization, signature };
+        let scheduler = MemorySubscriptionScheduler::new();
+
+        assert!(matches!(charge_subscription(&subscription, &scheduler, 999), Err(X402Error::InvalidSubscription(_))));
+        assert_eq!(charge_subscription(&subscription, &scheduler, 1_050).unwrap().period, 0);
+        assert!(matches!(charge_subscription(&subscription, &scheduler, 1_099), Err(X402Error::DuplicatePayment(_))));
+        assert_eq!(charge_subscription(&subscription, &scheduler, 1_350).unwrap().period, 3);
+        assert!(matches!(charge_subscription(&subscription, &scheduler, 1_450), Err(X402Error::InvalidSubscription(_))));
+
+        let mut tampered = subscription.clone();
+        tampered.authorization.amount_per_period = U256::from(5_000);
+        assert!(charge_subscription(&tampered, &MemorySubscriptionScheduler::new(), 1_050).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Verification and settlement metrics (feature `metrics`)
+//!
+//! Counters and histograms are emitted through the [`metrics`] facade, so
+//! they go to whichever recorder the application installs, e.g.
+//! `metrics-exporter-prometheus`. Nothing is recorded until one is.
+//! Amounts are in the token's smallest unit, saturating at `u64::MAX`.
+
+use crate::{Network, PaymentRequirements, Result, SignedPayment};
+use alloy_primitives::{Address, U256};
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Payments that passed verification, by `network` and `scheme`
+pub const PAYMENTS_VERIFIED: &str = "x402_payments_verified_total";
+/// Payments that failed verification, by `network` and `reason`
+pub const PAYMENTS_REJECTED: &str = "x402_payments_rejected_total";
+/// Time spent verifying a payment, by `network` and `outcome`
+pub const VERIFY_DURATION: &str = "x402_verify_duration_seconds";
+/// Settlement checks, by `network` and `outcome`
+pub const SETTLEMENTS: &str = "x402_settlements_total";
+/// Amount authorized by verified payments, by `network`
+pub const VERIFIED_AMOUNT: &str = "x402_verified_amount_total";
+/// Amount charged by accepted settlements, by `network`
+pub const SETTLED_AMOUNT: &str = "x402_settled_amount_total";
+
+/// Register units and help text for this crate's metrics with the
+/// installed recorder
+pub fn describe_metrics() {
+    describe_counter!(PAYMENTS_VERIFIED, Unit::Count, "Payments that passed verification");
+    describe_counter!(PAYMENTS_REJECTED, Unit::Count, "Payments that failed verification");
+    describe_histogram!(VERIFY_DURATION, Unit::Seconds, "Time spent verifying a payment");
+    describe_counter!(SETTLEMENTS, Unit::Count, "Settlement checks");
+    describe_counter!(VERIFIED_AMOUNT, "Amount authorized by verified payments, in token units");
+    describe_counter!(SETTLED_AMOUNT, "Amount charged by accepted settlements, in token units");
+}
+
+fn network_label(chain_id: u64) -> Cow<'static, str> {
+    Network::from_chain_id(chain_id).map_or(Cow::Borrowed("unknown"), |network| network.as_str())
+}
+
+fn saturating_u64(amount: U256) -> u64 {
+    amount.try_into().unwrap_or(u64::MAX)
+}
+
+pub(crate) fn record_verification(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    result: &Result<Address>,
+    elapsed: Duration,
+) {
+    let network = requirements.network.as_str();
+    match result {
+        Ok(_) => {
+            counter!(PAYMENTS_VERIFIED, "network" => network.clone(), "scheme" => payment.payment.scheme.as_str())
+                .increment(1);
+            counter!(VERIFIED_AMOUNT, "network" => network.clone()).increment(saturating_u64(payment.payment.amount));
+        }
+        Err(e) => counter!(PAYMENTS_REJECTED, "network" => network.clone(), "reason" => e.kind()).increment(1),
+    }
+    let outcome = if result.is_ok() { "verified" } else { "rejected" };
+    histogram!(VERIFY_DURATION, "network" => network, "outcome" => outcome).record(elapsed.as_secs_f64());
+}
+
+pub(crate) fn record_settlement(payment: &SignedPayment, charge: U256, result: &Result<()>) {
+    let network = network_label(payment.payment.chain_id);
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    counter!(SETTLEMENTS, "network" => network.clone(), "outcome" => outcome).increment(1);
+    if result.is_ok() {
+        counter!(SETTLED_AMOUNT, "network" => network).increment(saturating_u64(charge));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_charge, verify_payment_at, PaymentPayload};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[test]
+    fn test_verification_and_settlement_metrics() {
+        let requirements = PaymentRequirements::usdc(Network::Base, "0.001", Address::repeat_byte(0x11), "/api").unwrap();
+        let payment = SignedPayment {
+            payment: PaymentPayload::builder()
+                .requirements(&requirements)
+                .payer(Address::repeat_byte(0x22))
+                .expires_at(2_000)
+                .build()
+                .unwrap(),
+            signature: vec![0; 65],
+            signatures: Vec::new(),
+        };
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            assert!(verify_payment_at(&payment, &requirements, 1_000).is_err());
+            assert!(verify_payment_at(&payment, &requirements, 3_000).is_err());
+            check_charge(&payment, U256::from(1000)).unwrap();
+            assert!(check_charge(&payment, U256::from(10)).is_err());
+        });
+
+        let values: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels: Vec<_> = key.key().labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+                (key.key().name().to_string(), labels.join(","), value)
+            })
+            .collect();
+        let metric = |name: &str, labels: &str| {
+            values.iter().find(|(n, l, _)| n == name && l == labels).map(|(_, _, value)| value)
+        };
+
+        assert_eq!(metric(PAYMENTS_REJECTED, "network=base,reason=payment_expired"), Some(&DebugValue::Counter(1)));
+        assert_eq!(metric(PAYMENTS_VERIFIED, "network=base,scheme=exact"), None);
+        assert!(matches!(
+            metric(VERIFY_DURATION, "network=base,outcome=rejected"),
+            Some(DebugValue::Histogram(samples)) if samples.len() == 2
+        ));
+        assert_eq!(metric(SETTLEMENTS, "network=base,outcome=success"), Some(&DebugValue::Counter(1)));
+        assert_eq!(metric(SETTLEMENTS, "network=base,outcome=failure"), Some(&DebugValue::Counter(1)));
+        assert_eq!(metric(SETTLED_AMOUNT, "network=base"), Some(&DebugValue::Counter(1000)));
+    }
+}
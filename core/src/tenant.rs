@@ -0,0 +1,252 @@
+//! Multi-tenant recipient routing
+//!
+//! API marketplaces serve many sellers from one server, each paid at their
+//! own address and price. A [`RecipientResolver`] maps each request's
+//! host and resource to a [`TenantRoute`] at request time; [`RouteTable`]
+//! is the static implementation.
+//!
+//! The tenant a caller names in [`X402_TENANT_HEADER`] is forgeable, so it
+//! only chooses among the routes the host and resource already select:
+//! it can't make a request pay, or be served as, a tenant the operator
+//! didn't route that host and resource to. Servers report the resolved
+//! tenant, never the header, to whatever serves the request.
+//! [`TenantLedgers`] keeps a separate [`PaymentLedger`] per tenant, so each
+//! seller's payments can be reported and settled on their own.
+
+use crate::{MemoryLedger, PaymentLedger, PaymentRequirements, ResourceMatcher, Result};
+use alloy_primitives::{Address, U256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Header naming the tenant an HTTP request is for
+pub const X402_TENANT_HEADER: &str = "X-Tenant";
+
+/// Who gets paid for a request, and how much
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantRoute {
+    pub tenant: String,
+    pub recipient: Address,
+    /// Price in the token's smallest unit
+    pub amount: U256,
+}
+
+impl TenantRoute {
+    /// `template` paying this tenant for `resource`
+    pub fn apply(&self, template: &PaymentRequirements, resource: &str) -> PaymentRequirements {
+        PaymentRequirements {
+            recipient: self.recipient,
+            amount: self.amount,
+            resource: resource.to_string(),
+            ..template.clone()
+        }
+    }
+}
+
+/// Runtime mapping of requests to the tenant that gets paid
+pub trait RecipientResolver: Send + Sync {
+    /// Route for `resource` requested from `host`, or `None` if no tenant
+    /// serves it. `tenant` is the tenant the caller named, if any; it may
+    /// only narrow the routes `host` and `resource` select.
+    fn resolve(&self, resource: &str, host: Option<&str>, tenant: Option<&str>) -> Result<Option<TenantRoute>>;
+
+    /// [`RecipientResolver::resolve`], applied to `template`
+    fn requirements(
+        &self,
+        template: &PaymentRequirements,
+        resource: &str,
+        host: Option<&str>,
+        tenant: Option<&str>,
+    ) -> Result<Option<(TenantRoute, PaymentRequirements)>> {
+        Ok(self.resolve(resource, host, tenant)?.map(|route| {
+            let requirements = route.apply(template, resource);
+            (route, requirements)
+        }))
+    }
+}
+
+/// [`RecipientResolver`] over a fixed list of routes; the first matching
+/// route wins
+///
+/// Routes added with [`RouteTable::route_host`] only match requests for
+/// their host (compared without case or port); routes added with
+/// [`RouteTable::route`] match any host.
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    routes: Vec<(Option<String>, ResourceMatcher, TenantRoute)>,
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route resources matching `matcher`, on any host, to `route`
+    pub fn route(mut self, matcher: ResourceMatcher, route: TenantRoute) -> Self {
+        self.routes.push((None, matcher, route));
+        self
+    }
+
+    /// Route resources matching `matcher` on `host` to `route`
+    pub fn route_host(mut self, host: impl Into<String>, matcher: ResourceMatcher, route: TenantRoute) -> Self {
+        self.routes.push((Some(host.into()), matcher, route));
+        self
+    }
+}
+
+impl RecipientResolver for RouteTable {
+    fn resolve(&self, resource: &str, host: Option<&str>, tenant: Option<&str>) -> Result<Option<TenantRoute>> {
+        let host = host.map(without_port);
+        let serves_host = |route_host: &Option<String>| match (route_host, host) {
+            (None, _) => true,
+            (Some(route_host), Some(host)) => host.eq_ignore_ascii_case(route_host),
+            (Some(_), None) => false,
+        };
+        Ok(self
+            .routes
+            .iter()
+            .find(|(route_host, matcher, route)| {
+                serves_host(route_host)
+                    && matcher.matches(resource)
+                    && tenant.is_none_or(|tenant| route.tenant == tenant)
+            })
+            .map(|(_, _, route)| route.clone()))
+    }
+}
+
+/// `host` from a `Host` header value, without its port
+fn without_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && !port.contains(']') && port.bytes().all(|b| b.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    }
+}
+
+type LedgerFactory = dyn Fn(&str) -> Result<Arc<dyn PaymentLedger>> + Send + Sync;
+
+/// One [`PaymentLedger`] per tenant, opened on first use
+pub struct TenantLedgers {
+    ledgers: RwLock<HashMap<String, Arc<dyn PaymentLedger>>>,
+    open: Box<LedgerFactory>,
+}
+
+impl TenantLedgers {
+    /// In-memory ledgers
+    pub fn new() -> Self {
+        Self::with_factory(|_| Ok(Arc::new(MemoryLedger::new())))
+    }
+
+    /// Ledgers opened by `open`, e.g. a SQLite file per tenant
+    pub fn with_factory(
+        open: impl Fn(&str) -> Result<Arc<dyn PaymentLedger>> + Send + Sync + 'static,
+    ) -> Self {
+        Self { ledgers: RwLock::new(HashMap::new()), open: Box::new(open) }
+    }
+
+    /// Use `ledger` for `tenant`
+    pub fn insert(&self, tenant: impl Into<String>, ledger: Arc<dyn PaymentLedger>) {
+        self.ledgers.write().unwrap().insert(tenant.into(), ledger);
+    }
+
+    /// The ledger of `tenant`, opening it if needed
+    pub fn ledger(&self, tenant: &str) -> Result<Arc<dyn PaymentLedger>> {
+        if let Some(ledger) = self.ledgers.read().unwrap().get(tenant) {
+            return Ok(ledger.clone());
+        }
+        let mut ledgers = self.ledgers.write().unwrap();
+        if let Some(ledger) = ledgers.get(tenant) {
+            return Ok(ledger.clone());
+        }
+        let ledger = (self.open)(tenant)?;
+        ledgers.insert(tenant.to_string(), ledger.clone());
+        Ok(ledger)
+    }
+
+    /// Tenants with an open ledger
+    pub fn tenants(&self) -> Vec<String> {
+        let mut tenants: Vec<String> = self.ledgers.read().unwrap().keys().cloned().collect();
+        tenants.sort();
+        tenants
+    }
+}
+
+impl Default for TenantLedgers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for TenantLedgers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantLedgers").field("tenants", &self.tenants()).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LedgerEntry, LedgerQuery, Network, PaymentPayload, Scheme};
+
+    fn route(tenant: &str, byte: u8, amount: u64) -> TenantRoute {
+        TenantRoute { tenant: tenant.to_string(), recipient: Address::repeat_byte(byte), amount: U256::from(amount) }
+    }
+
+    #[test]
+    fn test_route_table_and_ledgers() {
+        let table = RouteTable::new()
+            .route(ResourceMatcher::Prefix("/weather".to_string()), route("weather", 0xaa, 100))
+            .route(ResourceMatcher::Glob("/maps/**".to_string()), route("maps", 0xbb, 250))
+            .route(ResourceMatcher::Prefix("/".to_string()), route("fallback", 0xcc, 1));
+        let template = PaymentRequirements::usdc(Network::Base, "0.01", Address::ZERO, "/").unwrap();
+
+        let (weather, requirements) = table.requirements(&template, "/weather/today", None, None).unwrap().unwrap();
+        assert_eq!(weather.tenant, "weather");
+        assert_eq!((requirements.recipient, requirements.amount), (Address::repeat_byte(0xaa), U256::from(100)));
+        assert_eq!((requirements.resource.as_str(), requirements.network), ("/weather/today", Network::Base));
+        assert_eq!(table.resolve("/maps/eu/tiles", None, None).unwrap().unwrap().tenant, "maps");
+        assert_eq!(table.resolve("/maps/eu", None, Some("fallback")).unwrap().unwrap().tenant, "fallback");
+        assert_eq!(table.resolve("/weather", None, Some("maps")).unwrap(), None);
+
+        let ledgers = TenantLedgers::new();
+        let payment = PaymentPayload {
+            amount: requirements.amount,
+            recipient: requirements.recipient,
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: requirements.token,
+            resource: requirements.resource.clone(),
+            nonce: 1,
+            expires_at: u64::MAX,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        ledgers.ledger("weather").unwrap().record(&LedgerEntry::new(&payment, 1)).unwrap();
+        ledgers.ledger("maps").unwrap().record(&LedgerEntry::new(&payment, 1)).unwrap();
+        assert_eq!(ledgers.ledger("weather").unwrap().query(&LedgerQuery::default()).unwrap().len(), 1);
+        assert_eq!(ledgers.tenants(), vec!["maps", "weather"]);
+    }
+
+    #[test]
+    fn test_tenant_header_only_narrows_host_routes() {
+        let table = RouteTable::new()
+            .route_host("alice.example", ResourceMatcher::Prefix("/".to_string()), route("alice", 0xa1, 100))
+            .route_host("bob.example", ResourceMatcher::Prefix("/".to_string()), route("bob", 0xb0, 1))
+            .route(ResourceMatcher::Prefix("/public".to_string()), route("public", 0xcc, 5));
+        let tenant = |host: Option<&str>, resource: &str, named: Option<&str>| {
+            table.resolve(resource, host, named).unwrap().map(|route| route.tenant)
+        };
+
+        assert_eq!(tenant(Some("Alice.Example:8443"), "/data", None).as_deref(), Some("alice"));
+        assert_eq!(tenant(Some("alice.example"), "/data", Some("alice")).as_deref(), Some("alice"));
+        // Naming a cheaper tenant the host doesn't route to finds nothing
+        assert_eq!(tenant(Some("alice.example"), "/data", Some("bob")), None);
+        assert_eq!(tenant(None, "/data", None), None);
+        assert_eq!(tenant(Some("mallory.example"), "/public/x", None).as_deref(), Some("public"));
+        assert_eq!(tenant(Some("alice.example"), "/public/x", Some("public")).as_deref(), Some("public"));
+        assert_eq!(without_port("[::1]:80"), "[::1]");
+        assert_eq!(without_port("[::1]"), "[::1]");
+    }
+}
@@ -0,0 +1,300 @@
+//! Test doubles for integration tests (feature `testing`)
+//!
+//! [`MockFacilitator`] stands in for a hosted facilitator, with scripted
+//! verify and settle results and optional latency. [`TestSigner`] signs
+//! payments with fixed, publicly known keys and sequential nonces, so runs
+//! are reproducible. Neither touches a chain. [`RecordingEvents`] keeps the
+//! [`PaymentEvent`]s a component reports.
+
+use crate::{
+    encode_payment_header, verify_payment, DeferredCheck, Facilitator, Nonce, PaymentEvent, PaymentEvents,
+    PaymentPayload, PaymentRequirements, Result, SettlementReceipt, SignedPayment, SupportedPaymentKind, X402Error,
+};
+use alloy_primitives::{keccak256, Address, B256};
+use k256::ecdsa::SigningKey;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+type VerifyFn = Box<dyn Fn(&SignedPayment, &PaymentRequirements) -> Result<Address> + Send + Sync>;
+type SettleFn = Box<dyn Fn(&SignedPayment) -> Result<SettlementReceipt> + Send + Sync>;
+
+/// Scriptable [`Facilitator`]
+///
+/// By default it verifies payments locally with [`verify_payment`] and
+/// settles every payment with a transaction hash derived from the
+/// payment's message hash.
+pub struct MockFacilitator {
+    verify: VerifyFn,
+    settle: SettleFn,
+    latency: Duration,
+    supported: Vec<SupportedPaymentKind>,
+    verified: Mutex<Vec<SignedPayment>>,
+    settled: Mutex<Vec<SettlementReceipt>>,
+}
+
+impl Default for MockFacilitator {
+    fn default() -> Self {
+        Self {
+            verify: Box::new(verify_payment),
+            settle: Box::new(|payment| Ok(mock_receipt(payment))),
+            latency: Duration::ZERO,
+            supported: Vec::new(),
+            verified: Mutex::new(Vec::new()),
+            settled: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl MockFacilitator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept every payment without checking it
+    pub fn accept_all(mut self) -> Self {
+        self.verify = Box::new(|payment, _| Ok(payment.payment.payer));
+        self
+    }
+
+    /// Reject every verification with the error `error` builds
+    pub fn fail_verify(mut self, error: impl Fn() -> X402Error + Send + Sync + 'static) -> Self {
+        self.verify = Box::new(move |_, _| Err(error()));
+        self
+    }
+
+    /// Decide verification results with `verify`
+    pub fn verify_with(
+        mut self,
+        verify: impl Fn(&SignedPayment, &PaymentRequirements) -> Result<Address> + Send + Sync + 'static,
+    ) -> Self {
+        self.verify = Box::new(verify);
+        self
+    }
+
+    /// Fail every settlement with the error `error` builds
+    pub fn fail_settle(mut self, error: impl Fn() -> X402Error + Send + Sync + 'static) -> Self {
+        self.settle = Box::new(move |_| Err(error()));
+        self
+    }
+
+    /// Sleep for `latency` before answering each call
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Report `kinds` from [`Facilitator::supported`]
+    pub fn with_supported(mut self, kinds: Vec<SupportedPaymentKind>) -> Self {
+        self.supported = kinds;
+        self
+    }
+
+    /// Payments passed to [`Facilitator::verify`], in call order
+    pub fn verified(&self) -> Vec<SignedPayment> {
+        self.verified.lock().unwrap().clone()
+    }
+
+    /// Successful settlements, in call order
+    pub fn settled(&self) -> Vec<SettlementReceipt> {
+        self.settled.lock().unwrap().clone()
+    }
+
+    fn delay(&self) {
+        if !self.latency.is_zero() {
+            std::thread::sleep(self.latency);
+        }
+    }
+}
+
+impl std::fmt::Debug for MockFacilitator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockFacilitator").field("latency", &self.latency).finish_non_exhaustive()
+    }
+}
+
+fn mock_receipt(payment: &SignedPayment) -> SettlementReceipt {
+    SettlementReceipt {
+        payer: payment.payment.payer,
+        chain_id: payment.payment.chain_id,
+        nonce: payment.payment.nonce,
+        amount: payment.payment.amount,
+        transaction_hash: keccak256(payment.payment.message_hash()),
+    }
+}
+
+impl Facilitator for MockFacilitator {
+    fn verify(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<Address> {
+        self.delay();
+        self.verified.lock().unwrap().push(payment.clone());
+        (self.verify)(payment, requirements)
+    }
+
+    fn settle(&self, payment: &SignedPayment, _: &PaymentRequirements) -> Result<SettlementReceipt> {
+        self.delay();
+        let receipt = (self.settle)(payment)?;
+        self.settled.lock().unwrap().push(receipt.clone());
+        Ok(receipt)
+    }
+
+    fn supported(&self) -> Result<Vec<SupportedPaymentKind>> {
+        Ok(self.supported.clone())
+    }
+}
+
+/// Lets a [`MockFacilitator`] drive a [`SoftFailVerifier`](crate::SoftFailVerifier)
+impl DeferredCheck for MockFacilitator {
+    fn check(&self, payment: &SignedPayment, requirements: &PaymentRequirements) -> Result<()> {
+        Facilitator::verify(self, payment, requirements).map(|_| ())
+    }
+}
+
+/// [`PaymentEvents`] sink keeping every event it receives
+#[derive(Debug, Default)]
+pub struct RecordingEvents {
+    events: Mutex<Vec<PaymentEvent>>,
+}
+
+impl RecordingEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events received, in order
+    pub fn events(&self) -> Vec<PaymentEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl PaymentEvents for RecordingEvents {
+    fn emit(&self, event: &PaymentEvent) -> Result<()> {
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+/// Signs payments with a fixed test key
+///
+/// Keys are public; never fund their addresses. Nonces count up from
+/// [`Nonce::MIN_RECOMMENDED`], so the same sequence of calls produces the
+/// same payments.
+#[derive(Debug)]
+pub struct TestSigner {
+    key: SigningKey,
+    next_nonce: AtomicU64,
+}
+
+impl TestSigner {
+    /// Signer number `index`; different indexes have different keys
+    pub fn new(index: u8) -> Self {
+        let mut bytes = [0x7e; 32];
+        bytes[31] = index;
+        Self {
+            key: SigningKey::from_slice(&bytes).expect("constant test keys are valid"),
+            next_nonce: AtomicU64::new(Nonce::MIN_RECOMMENDED),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        Address::from_slice(&keccak256(&point.as_bytes()[1..])[12..])
+    }
+
+    pub fn private_key(&self) -> B256 {
+        B256::from_slice(&self.key.to_bytes())
+    }
+
+    /// Recoverable signature (65 bytes: r + s + v) over a message hash,
+    /// e.g. of a credit grant or voucher terms
+    pub fn sign_hash(&self, hash: &[u8; 32]) -> Result<Vec<u8>> {
+        let (signature, recovery_id) = self.key.sign_prehash_recoverable(hash)?;
+        let mut signature = signature.to_bytes().to_vec();
+        signature.push(recovery_id.to_byte() + 27);
+        Ok(signature)
+    }
+
+    /// Sign `payload` as is, payer included
+    pub fn sign(&self, payload: PaymentPayload) -> Result<SignedPayment> {
+        let signature = self.sign_hash(&payload.message_hash())?;
+        Ok(SignedPayment { payment: payload, signature, signatures: Vec::new() })
+    }
+
+    /// A payment satisfying `requirements` at unix time `now`
+    pub fn pay(&self, requirements: &PaymentRequirements, now: u64) -> Result<SignedPayment> {
+        let payload = PaymentPayload::builder()
+            .requirements(requirements)
+            .payer(self.address())
+            .nonce(self.next_nonce.fetch_add(1, Ordering::SeqCst))
+            .expires_at(requirements.payment_expires_at(now))
+            .build()?;
+        self.sign(payload)
+    }
+
+    /// [`TestSigner::pay`], encoded as an `X-Payment` header value
+    pub fn payment_header(&self, requirements: &PaymentRequirements, now: u64) -> Result<String> {
+        encode_payment_header(&self.pay(requirements, now)?)
+    }
+}
+
+impl Default for TestSigner {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_payment_header, verify_payment_at, MemoryBlacklist, Network, SoftFailVerifier, VerificationMode};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    fn requirements() -> PaymentRequirements {
+        PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api").unwrap()
+    }
+
+    #[test]
+    fn test_signer_is_deterministic() {
+        let now = 1_700_000_000;
+        let (a, b) = (TestSigner::new(1), TestSigner::new(1));
+        assert_eq!(a.address(), b.address());
+        assert_ne!(a.address(), TestSigner::new(2).address());
+        assert_eq!(a.payment_header(&requirements(), now).unwrap(), b.payment_header(&requirements(), now).unwrap());
+
+        let payment = decode_payment_header(a.payment_header(&requirements(), now).unwrap()).unwrap();
+        assert_eq!(payment.payment.nonce, Nonce::MIN_RECOMMENDED + 1);
+        assert_eq!(verify_payment_at(&payment, &requirements(), now).unwrap(), a.address());
+    }
+
+    #[test]
+    fn test_mock_facilitator_scripts_results() {
+        let signer = TestSigner::default();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let payment = signer.pay(&requirements(), now).unwrap();
+
+        let facilitator = MockFacilitator::new();
+        assert_eq!(facilitator.verify(&payment, &requirements()).unwrap(), signer.address());
+        let receipt = facilitator.settle(&payment, &requirements()).unwrap();
+        assert_eq!((receipt.payer, receipt.amount), (signer.address(), requirements().amount));
+        assert_eq!(facilitator.settled(), vec![receipt]);
+
+        let failing = MockFacilitator::new()
+            .fail_verify(|| X402Error::InvalidSignature("scripted".to_string()))
+            .fail_settle(|| X402Error::Storage("chain unavailable".to_string()))
+            .with_latency(Duration::from_millis(20));
+        let started = Instant::now();
+        assert!(matches!(failing.verify(&payment, &requirements()), Err(X402Error::InvalidSignature(_))));
+        assert!(matches!(failing.settle(&payment, &requirements()), Err(X402Error::Storage(_))));
+        assert!(started.elapsed() >= Duration::from_millis(40));
+        assert_eq!(failing.verified().len(), 1);
+        assert!(failing.settled().is_empty());
+
+        let lenient = MockFacilitator::new().accept_all();
+        let unsigned = SignedPayment { signature: vec![0; 65], ..payment };
+        assert_eq!(lenient.verify(&unsigned, &requirements()).unwrap(), signer.address());
+
+        let verifier = SoftFailVerifier::new(MockFacilitator::new(), Arc::new(MemoryBlacklist::new()));
+        let payment = signer.pay(&requirements(), now).unwrap();
+        assert_eq!(verifier.verify(&payment, &requirements(), VerificationMode::Strict).unwrap(), signer.address());
+    }
+}
@@ -0,0 +1,304 @@
+//! Cross-SDK test vectors
+//!
+//! [`generate_test_vectors`] builds a deterministic corpus of requirements,
+//! payloads, message hashes, signatures and encoded headers, together with
+//! the verification outcome each should produce, plus headers every
+//! decoder must reject. Other implementations load the JSON form (checked
+//! in as `spec/test-vectors.json`) and compare byte-for-byte;
+//! [`check_test_vectors`] runs the same checks against this crate.
+//!
+//! The private keys in the corpus are public test keys. Never fund them.
+
+use crate::{
+    decode_payment_header, decode_requirements_header, encode_payment_header, encode_requirements_header,
+    verify_payment_at, Extra, Network, PaymentPayload, PaymentRequirements, Result, Scheme, SignedPayment, Split,
+    X402Error,
+};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use k256::ecdsa::SigningKey;
+use serde::{Deserialize, Serialize};
+
+/// Version of the corpus layout
+pub const TEST_VECTORS_VERSION: u32 = 1;
+
+/// Unix time every vector is verified at
+pub const TEST_VECTORS_NOW: u64 = 1_700_000_000;
+
+/// The full corpus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestVectors {
+    pub version: u32,
+    pub vectors: Vec<TestVector>,
+    pub malformed_headers: Vec<MalformedHeader>,
+}
+
+/// A signed payment and the outcome of verifying it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestVector {
+    pub name: String,
+    pub description: String,
+    pub requirements: PaymentRequirements,
+    /// `requirements` encoded with [`encode_requirements_header`]
+    pub requirements_header: String,
+    pub payload: PaymentPayload,
+    pub message_hash: B256,
+    /// Test key the payload was signed with
+    pub private_key: B256,
+    /// r ‖ s ‖ v, v = 27 or 28, over `message_hash` without any prefix
+    pub signature: Bytes,
+    /// `payload` and `signature` encoded with [`encode_payment_header`]
+    pub payment_header: String,
+    /// Unix time to verify at
+    pub verify_at: u64,
+    pub expected: Expected,
+}
+
+/// What verifying a vector must produce
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Expected {
+    Valid { payer: Address },
+    /// `error` is the [`X402Error::kind`] of the rejection
+    Invalid { error: String },
+}
+
+/// A header decoding must reject
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MalformedHeader {
+    pub name: String,
+    pub header: String,
+    /// [`X402Error::kind`] of the decoding error
+    pub error: String,
+}
+
+impl TestVectors {
+    pub fn to_json_pretty(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| X402Error::EncodingError(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(X402Error::Json)
+    }
+}
+
+fn test_key(byte: u8) -> SigningKey {
+    SigningKey::from_slice(&[byte; 32]).expect("constant test keys are valid")
+}
+
+fn key_address(key: &SigningKey) -> Address {
+    let point = key.verifying_key().to_encoded_point(false);
+    Address::from_slice(&keccak256(&point.as_bytes()[1..])[12..])
+}
+
+fn sign(payload: &PaymentPayload, key: &SigningKey) -> Result<Vec<u8>> {
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&payload.message_hash())?;
+    let mut bytes = signature.to_bytes().to_vec();
+    bytes.push(recovery_id.to_byte() + 27);
+    Ok(bytes)
+}
+
+fn base_requirements() -> Result<PaymentRequirements> {
+    let mut requirements = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api/weather")?;
+    requirements.description = Some("Weather report".to_string());
+    requirements.max_timeout_seconds = Some(300);
+    Ok(requirements)
+}
+
+fn payload_for(requirements: &PaymentRequirements, payer: &SigningKey) -> PaymentPayload {
+    PaymentPayload {
+        amount: requirements.amount,
+        recipient: requirements.recipient,
+        payer: key_address(payer),
+        chain_id: requirements.network.chain_id(),
+        token: requirements.token,
+        resource: requirements.resource.clone(),
+        nonce: 0x5eed_0000_0000_0001,
+        expires_at: TEST_VECTORS_NOW + 120,
+        scheme: requirements.scheme,
+        extra: requirements.extra.clone(),
+        invoice_id: None,
+        splits: requirements.splits.clone(),
+    }
+}
+
+fn vector(
+    name: &str,
+    description: &str,
+    requirements: PaymentRequirements,
+    payload: PaymentPayload,
+    key: &SigningKey,
+) -> Result<TestVector> {
+    let signature = sign(&payload, key)?;
+    let signed = SignedPayment { payment: payload, signature, signatures: Vec::new() };
+    let expected = match verify_payment_at(&signed, &requirements, TEST_VECTORS_NOW) {
+        Ok(payer) => Expected::Valid { payer },
+        Err(e) => Expected::Invalid { error: e.kind().to_string() },
+    };
+    Ok(TestVector {
+        name: name.to_string(),
+        description: description.to_string(),
+        requirements_header: encode_requirements_header(&requirements)?,
+        requirements,
+        message_hash: B256::from(signed.payment.message_hash()),
+        private_key: B256::from_slice(&key.to_bytes()),
+        signature: Bytes::from(signed.signature.clone()),
+        payment_header: encode_payment_header(&signed)?,
+        payload: signed.payment,
+        verify_at: TEST_VECTORS_NOW,
+        expected,
+    })
+}
+
+/// Build the corpus; the output is the same on every run
+pub fn generate_test_vectors() -> Result<TestVectors> {
+    let payer = test_key(0x01);
+    let other = test_key(0x02);
+    let requirements = base_requirements()?;
+    let payload = payload_for(&requirements, &payer);
+
+    let upto = PaymentRequirements { scheme: Scheme::Upto, ..requirements.clone() };
+    let extra: Extra = serde_json::from_value(serde_json::json!({ "order": "A-17", "units": 3 }))
+        .map_err(X402Error::Json)?;
+    let with_extra = PaymentRequirements { extra, ..requirements.clone() };
+    let split = PaymentRequirements {
+        splits: vec![
+            Split { recipient: Address::repeat_byte(0x11), share: U256::from(7_000) },
+            Split { recipient: Address::repeat_byte(0x33), share: U256::from(3_000) },
+        ],
+        ..requirements.clone()
+    };
+
+    let vectors = vec![
+        vector("exact", "Exact USDC payment on Base", requirements.clone(), payload.clone(), &payer)?,
+        vector("upto", "Upto scheme adds a Scheme line to the message", upto.clone(), payload_for(&upto, &payer), &payer)?,
+        vector(
+            "extra_and_invoice",
+            "Extra is hashed as canonical JSON; the invoice ID gets its own line",
+            with_extra.clone(),
+            PaymentPayload { invoice_id: Some("inv-42".to_string()), ..payload_for(&with_extra, &payer) },
+            &payer,
+        )?,
+        vector("splits", "Split outputs summing to the amount", split.clone(), payload_for(&split, &payer), &payer)?,
+        vector(
+            "expired",
+            "Expired before the verification time",
+            requirements.clone(),
+            PaymentPayload { expires_at: TEST_VECTORS_NOW - 1, ..payload.clone() },
+            &payer,
+        )?,
+        vector(
+            "insufficient_amount",
+            "Pays less than required",
+            requirements.clone(),
+            PaymentPayload { amount: requirements.amount - U256::from(1), ..payload.clone() },
+            &payer,
+        )?,
+        vector(
+            "wrong_recipient",
+            "Pays someone other than the advertised recipient",
+            requirements.clone(),
+            PaymentPayload { recipient: Address::repeat_byte(0x22), ..payload.clone() },
+            &payer,
+        )?,
+        vector(
+            "wrong_chain",
+            "Signed for Ethereum mainnet against Base requirements",
+            requirements.clone(),
+            PaymentPayload { chain_id: Network::Ethereum.chain_id(), ..payload.clone() },
+            &payer,
+        )?,
+        vector("wrong_signer", "Signed by a key other than the payer's", requirements.clone(), payload, &other)?,
+    ];
+
+    let malformed = [
+        ("not_base64", "x402.v2.json.!!!"),
+        ("not_json", "x402.v2.json.bm90IGpzb24="),
+        ("missing_fields", "x402.v2.json.eyJhbW91bnQiOiIxIn0="),
+        ("fractional_amount", "x402.v2.json.eyJwYXltZW50Ijp7ImFtb3VudCI6IjEuNSJ9fQ=="),
+    ];
+    let malformed_headers = malformed
+        .iter()
+        .map(|(name, header)| MalformedHeader {
+            name: name.to_string(),
+            header: header.to_string(),
+            error: decode_payment_header(header).err().map_or_else(String::new, |e| e.kind().to_string()),
+        })
+        .collect();
+
+    Ok(TestVectors { version: TEST_VECTORS_VERSION, vectors, malformed_headers })
+}
+
+/// Check every vector against this crate, returning the first mismatch
+pub fn check_test_vectors(corpus: &TestVectors) -> Result<()> {
+    let mismatch = |name: &str, what: &str| Err(X402Error::InvalidConfig(format!("test vector {}: {}", name, what)));
+    for v in &corpus.vectors {
+        if encode_requirements_header(&v.requirements)? != v.requirements_header {
+            return mismatch(&v.name, "requirements header differs");
+        }
+        let decoded = decode_requirements_header(&v.requirements_header)?;
+        if encode_requirements_header(&decoded)? != v.requirements_header {
+            return mismatch(&v.name, "requirements header decodes differently");
+        }
+        if B256::from(v.payload.message_hash()) != v.message_hash {
+            return mismatch(&v.name, "message hash differs");
+        }
+        let key = SigningKey::from_slice(v.private_key.as_slice())?;
+        if sign(&v.payload, &key)? != v.signature.as_ref() {
+            return mismatch(&v.name, "signature differs");
+        }
+        let signed =
+            SignedPayment { payment: v.payload.clone(), signature: v.signature.to_vec(), signatures: Vec::new() };
+        if encode_payment_header(&signed)? != v.payment_header {
+            return mismatch(&v.name, "payment header differs");
+        }
+        let decoded = decode_payment_header(&v.payment_header)?;
+        let outcome = match verify_payment_at(&decoded, &v.requirements, v.verify_at) {
+            Ok(payer) => Expected::Valid { payer },
+            Err(e) => Expected::Invalid { error: e.kind().to_string() },
+        };
+        if outcome != v.expected {
+            return mismatch(&v.name, &format!("expected {:?}, got {:?}", v.expected, outcome));
+        }
+    }
+    for m in &corpus.malformed_headers {
+        match decode_payment_header(&m.header) {
+            Ok(_) => return mismatch(&m.name, "malformed header decoded"),
+            Err(e) if e.kind() != m.error => {
+                return mismatch(&m.name, &format!("expected {} error, got {}", m.error, e.kind()));
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../spec/test-vectors.json");
+
+    /// Set `X402_UPDATE_TEST_VECTORS=1` to rewrite the checked-in corpus
+    #[test]
+    fn test_checked_in_corpus_matches() {
+        let generated = generate_test_vectors().unwrap();
+        check_test_vectors(&generated).unwrap();
+        assert!(matches!(&generated.vectors[0].expected, Expected::Valid { .. }));
+        assert!(generated.malformed_headers.iter().all(|m| !m.error.is_empty()));
+
+        if std::env::var_os("X402_UPDATE_TEST_VECTORS").is_some() {
+            std::fs::write(CORPUS_PATH, generated.to_json_pretty().unwrap() + "\n").unwrap();
+        }
+        let json = std::fs::read_to_string(CORPUS_PATH).unwrap();
+        assert_eq!(json, generated.to_json_pretty().unwrap() + "\n", "spec/test-vectors.json is stale");
+        let checked_in = TestVectors::from_json(&json).unwrap();
+        check_test_vectors(&checked_in).unwrap();
+
+        let mut tampered = checked_in;
+        tampered.vectors[0].payload.amount += U256::from(1);
+        assert!(check_test_vectors(&tampered).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Registry of well-known stablecoins
+//!
+//! Canonical USDC, USDT and DAI contracts per supported [`Network`], so
+//! integrators don't hard-code (and mistype) token addresses. Networks
+//! without a canonical deployment of a token have no entry, nor do
+//! deployments whose decimals differ from the token's usual ones (USDC and
+//! USDT on BNB Chain use 18).
+
+use crate::{Amount, Network, PaymentRequirements, Result, Scheme, X402Error};
+use alloy_primitives::{address, Address};
+
+/// Stablecoins known to the registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stablecoin {
+    Usdc,
+    Usdt,
+    Dai,
+}
+
+impl Stablecoin {
+    pub const ALL: [Stablecoin; 3] = [Stablecoin::Usdc, Stablecoin::Usdt, Stablecoin::Dai];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Stablecoin::Usdc => "USDC",
+            Stablecoin::Usdt => "USDT",
+            Stablecoin::Dai => "DAI",
+        }
+    }
+
+    pub fn decimals(&self) -> u8 {
+        match self {
+            Stablecoin::Usdc | Stablecoin::Usdt => 6,
+            Stablecoin::Dai => 18,
+        }
+    }
+
+    /// Contract address on `network`, if the token is deployed there
+    pub fn address(&self, network: Network) -> Option<Address> {
+        let address = match (self, network) {
+            (Stablecoin::Usdc, Network::Custom(chain_id)) => return crate::installed_chain(chain_id)?.usdc,
+            (Stablecoin::Usdc, Network::Ethereum) => address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            (Stablecoin::Usdc, Network::Base) => address!("833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
+            (Stablecoin::Usdc, Network::BaseSepolia) => address!("036CbD53842c5426634e7929541eC2318f3dCF7e"),
+            (Stablecoin::Usdc, Network::Sepolia) => address!("1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"),
+            (Stablecoin::Usdc, Network::ArbitrumSepolia) => address!("75faf114eafb1BDbe2F0316DF893fd58CE46AA4d"),
+            (Stablecoin::Usdc, Network::OptimismSepolia) => address!("5fd84259d66Cd46123540766Be93DFE6D43130D7"),
+            (Stablecoin::Usdc, Network::PolygonAmoy) => address!("41E94Eb019C0762f9Bfcf9Fb1E58725BfB0e7582"),
+            (Stablecoin::Usdc, Network::AvalancheFuji) => address!("5425890298aed601595a70AB815c96711a31Bc65"),
+            (Stablecoin::Usdc, Network::Arbitrum) => address!("af88d065e77c8cC2239327C5EDb3A432268e5831"),
+            (Stablecoin::Usdc, Network::Optimism) => address!("0b2C639c533813f4Aa9D7837cAf62653d097Ff85"),
+            (Stablecoin::Usdc, Network::Polygon) => address!("3c499c542cEF5E3811e1192ce70d8cC03d5c3359"),
+            (Stablecoin::Usdc, Network::Avalanche) => address!("B97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E"),
+            (Stablecoin::Usdc, Network::ZkSync) => address!("1d17CBcF0D6D143135aE902365D2E5e2A16538D4"),
+            (Stablecoin::Usdc, Network::Linea) => address!("176211869cA2b568f2A7D4EE941E073a821EE1ff"),
+            (Stablecoin::Usdc, Network::Scroll) => address!("06eFdBFf2a14a7c8E15944D1F4A48F9F95F663A4"),
+            (Stablecoin::Usdt, Network::Ethereum) => address!("dAC17F958D2ee523a2206206994597C13D831ec7"),
+            (Stablecoin::Usdt, Network::Arbitrum) => address!("Fd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9"),
+            (Stablecoin::Usdt, Network::Optimism) => address!("94b008aA00579c1307B0EF2c499aD98a8ce58e58"),
+            (Stablecoin::Usdt, Network::Polygon) => address!("c2132D05D31c914a87C6611C10748AEb04B58e8F"),
+            (Stablecoin::Dai, Network::Ethereum) => address!("6B175474E89094C44Da98b954EedeAC495271d0F"),
+            (Stablecoin::Dai, Network::Base) => address!("50c5725949A6F0c72E6C4a641F24049A917DB0Cb"),
+            (Stablecoin::Dai, Network::Arbitrum) => address!("DA10009cBd5D07dd0CeCc66161FC93D7c9000da1"),
+            (Stablecoin::Dai, Network::Optimism) => address!("DA10009cBd5D07dd0CeCc66161FC93D7c9000da1"),
+            (Stablecoin::Dai, Network::Polygon) => address!("8f3Cf7ad23Cd3CaDbD9735AFf958023239c6A063"),
+            _ => return None,
+        };
+        Some(address)
+    }
+
+    /// The token at `address` on `network`, if it's a known stablecoin
+    pub fn from_address(network: Network, address: Address) -> Option<Self> {
+        Self::ALL.into_iter().find(|coin| coin.address(network) == Some(address))
+    }
+
+    /// The token with ticker `symbol` (case-insensitive)
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|coin| coin.symbol().eq_ignore_ascii_case(symbol))
+    }
+}
+
+impl PaymentRequirements {
+    /// Requirements for `amount` (a human decimal string like `"0.01"`) of
+    /// `coin` on `network`
+    pub fn stablecoin(
+        network: Network,
+        coin: Stablecoin,
+        amount: &str,
+        recipient: Address,
+        resource: impl Into<String>,
+    ) -> Result<Self> {
+        let token = coin.address(network).ok_or_else(|| {
+            X402Error::UnsupportedNetwork(format!("{} is not deployed on {:?}", coin.symbol(), network))
+        })?;
+        Ok(Self {
+            amount: Amount::parse(amount, coin.decimals())?.units,
+            recipient,
+            network,
+            token: Some(token),
+            description: None,
+            expires_at: None,
+            resource: resource.into(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        })
+    }
+
+    /// Requirements for `amount` USDC on `network`
+    pub fn usdc(network: Network, amount: &str, recipient: Address, resource: impl Into<String>) -> Result<Self> {
+        Self::stablecoin(network, Stablecoin::Usdc, amount, recipient, resource)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+
+    #[test]
+    fn test_stablecoin_requirements() {
+        let recipient = Address::repeat_byte(0x11);
+        let requirements = PaymentRequirements::usdc(Network::Base, "1.25", recipient, "/api/data").unwrap();
+        assert_eq!(requirements.amount, U256::from(1_250_000));
+        assert_eq!(requirements.token, Stablecoin::Usdc.address(Network::Base));
+        assert_eq!(Stablecoin::from_address(Network::Base, requirements.token.unwrap()), Some(Stablecoin::Usdc));
+
+        let dai = PaymentRequirements::stablecoin(Network::Ethereum, Stablecoin::Dai, ".5", recipient, "/").unwrap();
+        assert_eq!(dai.amount, U256::from(5u64) * U256::from(10u64).pow(U256::from(17)));
+
+        assert!(PaymentRequirements::usdc(Network::Base, "0.0000001", recipient, "/").is_err());
+        assert!(PaymentRequirements::usdc(Network::Base, "1,5", recipient, "/").is_err());
+        assert!(matches!(
+            PaymentRequirements::stablecoin(Network::BaseSepolia, Stablecoin::Dai, "1", recipient, "/"),
+            Err(X402Error::UnsupportedNetwork(_))
+        ));
+    }
+}
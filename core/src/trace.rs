@@ -0,0 +1,41 @@
+//! Tracing instrumentation (feature `tracing`)
+//!
+//! Encoding, decoding, verification and settlement checks run inside a
+//! span carrying the payment's network, payer and amount, and log their
+//! outcome and duration when they finish. Without the feature, the
+//! [`traced!`] wrapper compiles down to the wrapped expression.
+
+/// Evaluate `$body` inside a span named `$name` with the given fields, then
+/// log its outcome and timing
+///
+/// `$body` runs in a closure returning [`crate::Result`], so `?` inside it
+/// still reaches the outcome log.
+macro_rules! traced {
+    ($name:literal, { $($fields:tt)* }, $body:expr) => {{
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($name, $($fields)*).entered();
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        #[allow(clippy::redundant_closure_call)]
+        let result: $crate::Result<_> = (|| $body)();
+        #[cfg(feature = "tracing")]
+        $crate::trace::finish(&result, started);
+        result
+    }};
+}
+pub(crate) use traced;
+
+/// Log the outcome of a traced operation in the current span
+#[cfg(feature = "tracing")]
+pub(crate) fn finish<T>(result: &crate::Result<T>, started: std::time::Instant) {
+    let elapsed_us = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+    match result {
+        Ok(_) => tracing::info!(elapsed_us, outcome = "ok"),
+        Err(e) => tracing::warn!(
+            elapsed_us,
+            outcome = "rejected",
+            category = ?e.category(),
+            error = %e.chain_message(),
+        ),
+    }
+}
@@ -1,56 +1,315 @@
 //! Core types for x402 payments
 
-use alloy_primitives::{Address, U256};
+use crate::{canonical_extra, PriceQuote, QuoteCommitment, X402Error};
+use alloy_primitives::{Address, Keccak256, U256};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Application metadata bound into a payment or quote (order IDs, plan
+/// names, ...)
+pub type Extra = BTreeMap<String, serde_json::Value>;
 
 /// Supported blockchain networks
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+///
+/// On the wire a network is its lowercase name ([`Network::as_str`]), or
+/// `eip155:<chain id>` for [`Network::Custom`] chains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Network {
     /// Ethereum Mainnet
     Ethereum,
+    /// Ethereum Sepolia Testnet
+    Sepolia,
     /// Base Mainnet
     Base,
     /// Base Sepolia Testnet
     BaseSepolia,
     /// Arbitrum One
     Arbitrum,
+    /// Arbitrum Sepolia Testnet
+    ArbitrumSepolia,
     /// Optimism
     Optimism,
+    /// Optimism Sepolia Testnet
+    OptimismSepolia,
     /// Polygon
     Polygon,
+    /// Polygon Amoy Testnet
+    PolygonAmoy,
+    /// Avalanche C-Chain
+    Avalanche,
+    /// Avalanche Fuji Testnet
+    AvalancheFuji,
+    /// BNB Smart Chain
+    Bnb,
+    /// BNB Smart Chain Testnet
+    BnbTestnet,
+    /// zkSync Era
+    ZkSync,
+    /// zkSync Era Sepolia Testnet
+    ZkSyncSepolia,
+    /// Linea
+    Linea,
+    /// Linea Sepolia Testnet
+    LineaSepolia,
+    /// Scroll
+    Scroll,
+    /// Scroll Sepolia Testnet
+    ScrollSepolia,
+    /// Any other chain, by chain id, described by a
+    /// [`ChainRegistry`](crate::ChainRegistry) installed with
+    /// [`ChainRegistry::install`](crate::ChainRegistry::install)
+    Custom(u64),
 }
 
 impl Network {
+    pub const ALL: [Network; 20] = [
+        Network::Ethereum,
+        Network::Sepolia,
+        Network::Base,
+        Network::BaseSepolia,
+        Network::Arbitrum,
+        Network::ArbitrumSepolia,
+        Network::Optimism,
+        Network::OptimismSepolia,
+        Network::Polygon,
+        Network::PolygonAmoy,
+        Network::Avalanche,
+        Network::AvalancheFuji,
+        Network::Bnb,
+        Network::BnbTestnet,
+        Network::ZkSync,
+        Network::ZkSyncSepolia,
+        Network::Linea,
+        Network::LineaSepolia,
+        Network::Scroll,
+        Network::ScrollSepolia,
+    ];
+
     pub fn chain_id(&self) -> u64 {
         match self {
             Network::Ethereum => 1,
+            Network::Sepolia => 11155111,
             Network::Base => 8453,
             Network::BaseSepolia => 84532,
             Network::Arbitrum => 42161,
+            Network::ArbitrumSepolia => 421614,
             Network::Optimism => 10,
+            Network::OptimismSepolia => 11155420,
             Network::Polygon => 137,
+            Network::PolygonAmoy => 80002,
+            Network::Avalanche => 43114,
+            Network::AvalancheFuji => 43113,
+            Network::Bnb => 56,
+            Network::BnbTestnet => 97,
+            Network::ZkSync => 324,
+            Network::ZkSyncSepolia => 300,
+            Network::Linea => 59144,
+            Network::LineaSepolia => 59141,
+            Network::Scroll => 534352,
+            Network::ScrollSepolia => 534351,
+            Network::Custom(chain_id) => *chain_id,
+        }
+    }
+
+    /// Whether this is a test network, whose tokens have no value
+    ///
+    /// For [`Network::Custom`] chains, the `testnet` flag of the installed
+    /// registry entry.
+    pub fn is_testnet(&self) -> bool {
+        if let Network::Custom(chain_id) = self {
+            return crate::installed_chain(*chain_id).is_some_and(|chain| chain.testnet);
         }
+        matches!(
+            self,
+            Network::Sepolia
+                | Network::BaseSepolia
+                | Network::ArbitrumSepolia
+                | Network::OptimismSepolia
+                | Network::PolygonAmoy
+                | Network::AvalancheFuji
+                | Network::BnbTestnet
+                | Network::ZkSyncSepolia
+                | Network::LineaSepolia
+                | Network::ScrollSepolia
+        )
     }
 
+    /// Name as used on the wire: lowercase for built-in networks,
+    /// `eip155:<chain id>` for [`Network::Custom`]
+    pub fn as_str(&self) -> Cow<'static, str> {
+        Cow::Borrowed(match self {
+            Network::Ethereum => "ethereum",
+            Network::Sepolia => "sepolia",
+            Network::Base => "base",
+            Network::BaseSepolia => "basesepolia",
+            Network::Arbitrum => "arbitrum",
+            Network::ArbitrumSepolia => "arbitrumsepolia",
+            Network::Optimism => "optimism",
+            Network::OptimismSepolia => "optimismsepolia",
+            Network::Polygon => "polygon",
+            Network::PolygonAmoy => "polygonamoy",
+            Network::Avalanche => "avalanche",
+            Network::AvalancheFuji => "avalanchefuji",
+            Network::Bnb => "bnb",
+            Network::BnbTestnet => "bnbtestnet",
+            Network::ZkSync => "zksync",
+            Network::ZkSyncSepolia => "zksyncsepolia",
+            Network::Linea => "linea",
+            Network::LineaSepolia => "lineasepolia",
+            Network::Scroll => "scroll",
+            Network::ScrollSepolia => "scrollsepolia",
+            Network::Custom(chain_id) => return Cow::Owned(format!("{}{}", EIP155_PREFIX, chain_id)),
+        })
+    }
+
+    /// The built-in network with this chain id, or a [`Network::Custom`]
+    /// one if an installed [`ChainRegistry`](crate::ChainRegistry) lists it
     pub fn from_chain_id(chain_id: u64) -> Option<Self> {
         match chain_id {
             1 => Some(Network::Ethereum),
+            11155111 => Some(Network::Sepolia),
             8453 => Some(Network::Base),
             84532 => Some(Network::BaseSepolia),
             42161 => Some(Network::Arbitrum),
+            421614 => Some(Network::ArbitrumSepolia),
             10 => Some(Network::Optimism),
+            11155420 => Some(Network::OptimismSepolia),
             137 => Some(Network::Polygon),
-            _ => None,
+            80002 => Some(Network::PolygonAmoy),
+            43114 => Some(Network::Avalanche),
+            43113 => Some(Network::AvalancheFuji),
+            56 => Some(Network::Bnb),
+            97 => Some(Network::BnbTestnet),
+            324 => Some(Network::ZkSync),
+            300 => Some(Network::ZkSyncSepolia),
+            59144 => Some(Network::Linea),
+            59141 => Some(Network::LineaSepolia),
+            534352 => Some(Network::Scroll),
+            534351 => Some(Network::ScrollSepolia),
+            _ => crate::installed_chain(chain_id).map(|_| Network::Custom(chain_id)),
         }
     }
 }
 
+const EIP155_PREFIX: &str = "eip155:";
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_str())
+    }
+}
+
+/// Parses the names [`Network::as_str`] returns, ignoring case, and the
+/// names of installed [`ChainRegistry`](crate::ChainRegistry) chains.
+/// `eip155:<chain id>` only parses for built-in or installed chains.
+impl std::str::FromStr for Network {
+    type Err = X402Error;
+
+    fn from_str(s: &str) -> Result<Self, X402Error> {
+        let builtin = Network::ALL.into_iter().find(|network| network.as_str().eq_ignore_ascii_case(s));
+        let by_id = || {
+            let (prefix, chain_id) = s.split_at_checked(EIP155_PREFIX.len())?;
+            if !prefix.eq_ignore_ascii_case(EIP155_PREFIX) {
+                return None;
+            }
+            Network::from_chain_id(chain_id.parse().ok()?)
+        };
+        builtin
+            .or_else(by_id)
+            .or_else(|| crate::installed_chain_named(s).and_then(|chain| Network::from_chain_id(chain.chain_id)))
+            .ok_or_else(|| X402Error::UnsupportedNetwork(s.to_string()))
+    }
+}
+
+impl Serialize for Network {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Network {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = Cow::<str>::deserialize(deserializer)?;
+        name.parse().map_err(|_| serde::de::Error::custom(format!("unsupported network {:?}", name)))
+    }
+}
+
+/// How the payment amount relates to the price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    /// The payment covers the full required amount
+    #[default]
+    Exact,
+    /// The payment authorizes charges up to its amount; the server charges
+    /// actual usage, at most the advertised amount
+    Upto,
+    /// A lottery ticket: the payment wins its full amount with the odds in
+    /// the requirements' `extra`, and is otherwise never settled
+    Lottery,
+}
+
+impl Scheme {
+    pub const ALL: [Scheme; 3] = [Scheme::Exact, Scheme::Upto, Scheme::Lottery];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::Exact => "exact",
+            Scheme::Upto => "upto",
+            Scheme::Lottery => "lottery",
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        *self == Scheme::Exact
+    }
+}
+
+impl std::str::FromStr for Scheme {
+    type Err = X402Error;
+
+    fn from_str(s: &str) -> Result<Self, X402Error> {
+        match s {
+            "exact" => Ok(Scheme::Exact),
+            "upto" => Ok(Scheme::Upto),
+            "lottery" => Ok(Scheme::Lottery),
+            other => Err(X402Error::UnsupportedScheme(other.to_string())),
+        }
+    }
+}
+
+/// One output of a split payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Split {
+    pub recipient: Address,
+    /// Amount paid to this recipient, in the smallest unit
+    #[serde(with = "crate::serde_amount")]
+    pub share: U256,
+}
+
+/// Total of a list of split outputs, `None` on overflow
+pub fn splits_total(splits: &[Split]) -> Option<U256> {
+    splits.iter().try_fold(U256::ZERO, |total, split| total.checked_add(split.share))
+}
+
+/// Text of split outputs in signed messages
+pub(crate) fn splits_message(splits: &[Split]) -> String {
+    splits
+        .iter()
+        .map(|split| format!("{}={}", split.recipient, split.share))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// Payment requirements returned in 402 response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentRequirements {
     /// Amount in smallest unit (wei, satoshi, etc.)
+    #[serde(with = "crate::serde_amount")]
     pub amount: U256,
     /// Recipient address
     pub recipient: Address,
@@ -64,6 +323,51 @@ pub struct PaymentRequirements {
     pub expires_at: Option<u64>,
     /// Unique resource identifier
     pub resource: String,
+    /// Server commitment to honour this quote until `expires_at`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<QuoteCommitment>,
+    /// Payment scheme the server accepts
+    #[serde(default, skip_serializing_if = "Scheme::is_exact")]
+    pub scheme: Scheme,
+    /// Media type of the paid resource, e.g. `application/json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// JSON Schema of the paid response, for machine clients deciding
+    /// whether to buy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    /// Longest validity window, in seconds from signing, the server
+    /// accepts on a payment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_timeout_seconds: Option<u64>,
+    /// Application metadata, covered by the quote commitment
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "crate::canonical::deserialize_extra"
+    )]
+    pub extra: Extra,
+    /// Revenue split: when present, the outputs the payment is divided
+    /// into, summing to `amount`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splits: Vec<Split>,
+    /// Fiat price and exchange rate `amount` was converted from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_quote: Option<PriceQuote>,
+}
+
+/// Validity window clients use when the server doesn't advertise one
+pub const DEFAULT_MAX_TIMEOUT_SECONDS: u64 = 60;
+
+impl PaymentRequirements {
+    /// The `expires_at` a client signing at `now` should use
+    ///
+    /// The quote's own expiry, capped at `now` plus the advertised (or
+    /// default) maximum validity window.
+    pub fn payment_expires_at(&self, now: u64) -> u64 {
+        let latest = now.saturating_add(self.max_timeout_seconds.unwrap_or(DEFAULT_MAX_TIMEOUT_SECONDS));
+        self.expires_at.map_or(latest, |expires_at| expires_at.min(latest))
+    }
 }
 
 /// Signed payment submitted by client
@@ -72,8 +376,12 @@ pub struct PaymentRequirements {
 pub struct SignedPayment {
     /// Payment details
     pub payment: PaymentPayload,
-    /// ECDSA signature (65 bytes: r + s + v)
+    /// ECDSA signature (65 bytes: r + s + v); empty when `signatures` is used
     pub signature: Vec<u8>,
+    /// Signatures of a multisig payer's signers, 65 bytes each (see
+    /// [`crate::multisig`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<Vec<u8>>,
 }
 
 /// Payment payload to be signed
@@ -81,6 +389,7 @@ pub struct SignedPayment {
 #[serde(rename_all = "camelCase")]
 pub struct PaymentPayload {
     /// Amount in smallest unit
+    #[serde(with = "crate::serde_amount")]
     pub amount: U256,
     /// Recipient address
     pub recipient: Address,
@@ -96,15 +405,79 @@ pub struct PaymentPayload {
     pub nonce: u64,
     /// Expiry timestamp
     pub expires_at: u64,
+    /// Payment scheme; `upto` payments authorize a maximum charge
+    #[serde(default, skip_serializing_if = "Scheme::is_exact")]
+    pub scheme: Scheme,
+    /// Application metadata, covered by the signature
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "crate::canonical::deserialize_extra"
+    )]
+    pub extra: Extra,
+    /// Idempotency key: retries of one purchase reuse it, so the server
+    /// can answer them without charging twice; no control characters
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "crate::validate::deserialize_invoice_id"
+    )]
+    pub invoice_id: Option<String>,
+    /// Split outputs committed to by the signature, summing to `amount`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splits: Vec<Split>,
 }
 
 impl PaymentPayload {
     /// Create the message hash to be signed (EIP-712 style)
     pub fn message_hash(&self) -> [u8; 32] {
-        use alloy_primitives::keccak256;
-        
-        // Simplified hashing - in production, use full EIP-712 typed data
-        let message = format!(
+        PaymentMessage {
+            amount: &self.amount,
+            recipient: &self.recipient,
+            payer: &self.payer,
+            chain_id: self.chain_id,
+            resource: &self.resource,
+            nonce: self.nonce,
+            expires_at: self.expires_at,
+            scheme: self.scheme,
+            extra: &self.extra,
+            invoice_id: self.invoice_id.as_deref(),
+            splits: &self.splits,
+        }
+        .hash()
+    }
+}
+
+/// Signed fields of a payment, shared by owned and borrowed payloads
+pub(crate) struct PaymentMessage<'a> {
+    pub amount: &'a U256,
+    pub recipient: &'a Address,
+    pub payer: &'a Address,
+    pub chain_id: u64,
+    pub resource: &'a str,
+    pub nonce: u64,
+    pub expires_at: u64,
+    pub scheme: Scheme,
+    pub extra: &'a Extra,
+    pub invoice_id: Option<&'a str>,
+    pub splits: &'a [Split],
+}
+
+impl PaymentMessage<'_> {
+    pub fn hash(&self) -> [u8; 32] {
+        // Simplified hashing - in production, use full EIP-712 typed data.
+        // This is one keccak per call; when EIP-712 replaces it, cache the
+        // domain separator per chain id and the struct type hash (OnceLock)
+        // so verification doesn't pay four keccaks per payment.
+        let mut hasher = HashWriter(Keccak256::new());
+        // Writing into a hasher can't fail
+        let _ = self.write_to(&mut hasher);
+        *hasher.0.finalize()
+    }
+
+    fn write_to(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            out,
             "x402 Payment\nAmount: {}\nRecipient: {}\nPayer: {}\nChainId: {}\nResource: {}\nNonce: {}\nExpires: {}",
             self.amount,
             self.recipient,
@@ -113,20 +486,92 @@ impl PaymentPayload {
             self.resource,
             self.nonce,
             self.expires_at
-        );
-        
-        *keccak256(message.as_bytes())
+        )?;
+        // Defaults keep the original message so existing signers still verify
+        if !self.scheme.is_exact() {
+            write!(out, "\nScheme: {}", self.scheme.as_str())?;
+        }
+        if !self.extra.is_empty() {
+            write!(out, "\nExtra: {}", Zeroizing::new(canonical_extra(self.extra)).as_str())?;
+        }
+        if let Some(invoice_id) = self.invoice_id {
+            write!(out, "\nInvoice: {}", invoice_id)?;
+        }
+        if !self.splits.is_empty() {
+            write!(out, "\nSplits: {}", Zeroizing::new(splits_message(self.splits)).as_str())?;
+        }
+        Ok(())
+    }
+}
+
+/// Feeds formatted text straight into the hash, so the signed message
+/// never sits in a buffer that outlives the call (or is left behind,
+/// unwiped, when a growing `String` reallocates)
+struct HashWriter(Keccak256);
+
+impl fmt::Write for HashWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.update(s.as_bytes());
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{network_from_spec_name, spec_network_name};
 
     #[test]
     fn test_network_chain_id() {
         assert_eq!(Network::Base.chain_id(), 8453);
         assert_eq!(Network::from_chain_id(8453), Some(Network::Base));
+        assert_eq!(Network::ZkSync.chain_id(), 324);
+        assert!(Network::PolygonAmoy.is_testnet() && !Network::Polygon.is_testnet());
+        assert_eq!(Network::ALL.iter().filter(|n| n.is_testnet()).count(), 10);
+
+        for network in Network::ALL {
+            assert_eq!(Network::from_chain_id(network.chain_id()), Some(network));
+            assert_eq!(network.as_str().parse::<Network>().unwrap(), network);
+            assert_eq!(serde_json::to_value(network).unwrap(), *network.as_str());
+            assert_eq!(network_from_spec_name(&spec_network_name(network)).unwrap(), network);
+        }
+    }
+
+    #[test]
+    fn test_custom_network_needs_installed_chain() {
+        let chain_id = 4_200_042;
+        let wire = format!("\"eip155:{}\"", chain_id);
+        assert_eq!(Network::from_chain_id(chain_id), None);
+        assert!(serde_json::from_str::<Network>(&wire).is_err());
+        assert_eq!(Network::from_chain_id(1), Some(Network::Ethereum));
+        assert_eq!("EIP155:8453".parse::<Network>().unwrap(), Network::Base);
+
+        let usdc = Address::repeat_byte(0x11);
+        crate::ChainRegistry::new()
+            .with_chain(crate::ChainInfo {
+                chain_id,
+                name: "Custom Testnet".to_string(),
+                native_symbol: "CT".to_string(),
+                explorer_url: None,
+                usdc: Some(usdc),
+                testnet: true,
+            })
+            .install();
+
+        let network = Network::Custom(chain_id);
+        assert_eq!(Network::from_chain_id(chain_id), Some(network));
+        assert_eq!(serde_json::to_string(&network).unwrap(), wire);
+        assert_eq!(serde_json::from_str::<Network>(&wire).unwrap(), network);
+        assert_eq!("custom testnet".parse::<Network>().unwrap(), network);
+        assert_eq!(network_from_spec_name(&spec_network_name(network)).unwrap(), network);
+        assert!(network.is_testnet());
+        assert_eq!(crate::Stablecoin::Usdc.address(network), Some(usdc));
+
+        let requirements = PaymentRequirements::stablecoin(network, crate::Stablecoin::Usdc, "1.5", usdc, "/custom")
+            .unwrap();
+        assert_eq!((requirements.token, requirements.amount), (Some(usdc), U256::from(1_500_000)));
+        let json = serde_json::to_string(&requirements).unwrap();
+        assert_eq!(serde_json::from_str::<PaymentRequirements>(&json).unwrap().network, network);
     }
 
     #[test]
@@ -140,9 +585,46 @@ mod tests {
             resource: "https://api.example.com/data".to_string(),
             nonce: 1,
             expires_at: 1700000000,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
         };
         
         let hash = payload.message_hash();
         assert_eq!(hash.len(), 32);
     }
+
+    #[test]
+    fn test_extra_bound_into_hash() {
+        let mut payload = PaymentPayload {
+            amount: U256::from(1000000),
+            recipient: Address::ZERO,
+            payer: Address::ZERO,
+            chain_id: 8453,
+            token: None,
+            resource: "https://api.example.com/data".to_string(),
+            nonce: 1,
+            expires_at: 1700000000,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        let plain = payload.message_hash();
+        assert!(!serde_json::to_string(&payload).unwrap().contains("extra"));
+
+        payload.extra.insert("plan".to_string(), "pro".into());
+        payload.extra.insert("orderId".to_string(), serde_json::json!({"id": 42, "ref": "A"}));
+        let json = serde_json::to_string(&payload).unwrap();
+        let back: PaymentPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.extra, payload.extra);
+        assert_ne!(back.message_hash(), plain);
+
+        let reordered: PaymentPayload = serde_json::from_str(
+            &json.replace(r#""orderId":{"id":42,"ref":"A"},"plan":"pro""#, r#""plan":"pro","orderId":{"ref":"A","id":42}"#),
+        ).unwrap();
+        assert_eq!(reordered.message_hash(), payload.message_hash());
+        assert!(serde_json::from_str::<PaymentPayload>(&json.replace("42", "42.0")).is_err());
+    }
 }
@@ -0,0 +1,232 @@
+//! Sanity checks on requirements and payloads
+//!
+//! [`PaymentRequirements::validate`] and [`PaymentPayload::validate`]
+//! catch values that can never lead to a successful payment (a zero
+//! amount, a zero-address recipient, an empty resource, an expiry in the
+//! past, an unknown chain, an invoice ID with control characters). They
+//! report every problem at once, as a list of [`Violation`]s. The header
+//! encoders refuse values with violations other than expiry.
+
+use crate::{Network, PaymentPayload, PaymentRequirements, Result, X402Error};
+use alloy_primitives::Address;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+/// A problem found by `validate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum Violation {
+    ZeroAmount,
+    ZeroRecipient,
+    EmptyResource,
+    Expired { expires_at: u64, now: u64 },
+    UnknownChainId { chain_id: u64 },
+    /// The invoice ID has a line break or other control character, which
+    /// could forge lines of the signed message
+    InvalidInvoiceId,
+}
+
+impl Violation {
+    /// Stable identifier, e.g. `zero_amount`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Violation::ZeroAmount => "zero_amount",
+            Violation::ZeroRecipient => "zero_recipient",
+            Violation::EmptyResource => "empty_resource",
+            Violation::Expired { .. } => "expired",
+            Violation::UnknownChainId { .. } => "unknown_chain_id",
+            Violation::InvalidInvoiceId => "invalid_invoice_id",
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::ZeroAmount => write!(f, "amount is zero"),
+            Violation::ZeroRecipient => write!(f, "recipient is the zero address"),
+            Violation::EmptyResource => write!(f, "resource is empty"),
+            Violation::Expired { expires_at, now } => write!(f, "expired at {}, before {}", expires_at, now),
+            Violation::UnknownChainId { chain_id } => write!(f, "unknown chain id {}", chain_id),
+            Violation::InvalidInvoiceId => write!(f, "invoice ID contains control characters"),
+        }
+    }
+}
+
+fn common_violations(amount_is_zero: bool, recipient: Address, resource: &str) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if amount_is_zero {
+        violations.push(Violation::ZeroAmount);
+    }
+    if recipient.is_zero() {
+        violations.push(Violation::ZeroRecipient);
+    }
+    if resource.is_empty() {
+        violations.push(Violation::EmptyResource);
+    }
+    violations
+}
+
+/// Whether `invoice_id` can go into the signed message as one line
+pub(crate) fn invoice_id_is_valid(invoice_id: &str) -> bool {
+    !invoice_id.chars().any(char::is_control)
+}
+
+/// Fail unless `invoice_id` passes [`invoice_id_is_valid`]
+pub(crate) fn check_invoice_id(invoice_id: Option<&str>) -> Result<()> {
+    match invoice_id {
+        Some(invoice_id) if !invoice_id_is_valid(invoice_id) => Err(X402Error::Invalid(vec![Violation::InvalidInvoiceId])),
+        _ => Ok(()),
+    }
+}
+
+/// Deserialize an invoice ID, rejecting control characters
+pub(crate) fn deserialize_invoice_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    let invoice_id = Option::<String>::deserialize(deserializer)?;
+    if invoice_id.as_deref().is_some_and(|invoice_id| !invoice_id_is_valid(invoice_id)) {
+        return Err(serde::de::Error::custom(Violation::InvalidInvoiceId));
+    }
+    Ok(invoice_id)
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+impl PaymentRequirements {
+    /// Problems with these requirements as of the current time
+    pub fn validate(&self) -> Vec<Violation> {
+        self.validate_at(now())
+    }
+
+    /// Problems with these requirements as of the unix time `now`
+    pub fn validate_at(&self, now: u64) -> Vec<Violation> {
+        let mut violations = common_violations(self.amount.is_zero(), self.recipient, &self.resource);
+        if let Some(expires_at) = self.expires_at.filter(|expires_at| *expires_at <= now) {
+            violations.push(Violation::Expired { expires_at, now });
+        }
+        violations
+    }
+}
+
+impl PaymentPayload {
+    /// Problems with this payload as of the current time
+    pub fn validate(&self) -> Vec<Violation> {
+        self.validate_at(now())
+    }
+
+    /// Problems with this payload as of the unix time `now`
+    pub fn validate_at(&self, now: u64) -> Vec<Violation> {
+        let mut violations = common_violations(self.amount.is_zero(), self.recipient, &self.resource);
+        if self.expires_at <= now {
+            violations.push(Violation::Expired { expires_at: self.expires_at, now });
+        }
+        if Network::from_chain_id(self.chain_id).is_none() {
+            violations.push(Violation::UnknownChainId { chain_id: self.chain_id });
+        }
+        if self.invoice_id.as_deref().is_some_and(|invoice_id| !invoice_id_is_valid(invoice_id)) {
+            violations.push(Violation::InvalidInvoiceId);
+        }
+        violations
+    }
+}
+
+pub(crate) fn join(violations: &[Violation]) -> String {
+    violations.iter().map(Violation::to_string).collect::<Vec<_>>().join(", ")
+}
+
+/// Fail with the violations that make a value unfit to encode
+///
+/// Expiry is left to the verifier, so values can be encoded ahead of time.
+pub(crate) fn ensure_encodable(mut violations: Vec<Violation>) -> Result<()> {
+    violations.retain(|violation| !matches!(violation, Violation::Expired { .. }));
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(X402Error::Invalid(violations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+    use alloy_primitives::U256;
+    use base64::Engine as _;
+
+    #[test]
+    fn test_violations_are_collected() {
+        let payload = PaymentPayload {
+            amount: U256::ZERO,
+            recipient: Address::ZERO,
+            payer: Address::repeat_byte(0x22),
+            chain_id: 999_999,
+            token: None,
+            resource: String::new(),
+            nonce: 1,
+            expires_at: 100,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: None,
+            splits: Vec::new(),
+        };
+        let violations = payload.validate_at(100);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::ZeroAmount,
+                Violation::ZeroRecipient,
+                Violation::EmptyResource,
+                Violation::Expired { expires_at: 100, now: 100 },
+                Violation::UnknownChainId { chain_id: 999_999 },
+            ]
+        );
+        assert_eq!(serde_json::to_value(&violations[4]).unwrap()["code"], "unknown_chain_id");
+
+        let valid = PaymentPayload {
+            amount: U256::from(1),
+            recipient: Address::repeat_byte(0x11),
+            chain_id: 8453,
+            resource: "/api".to_string(),
+            expires_at: 101,
+            ..payload.clone()
+        };
+        assert!(valid.validate_at(100).is_empty());
+        let err = ensure_encodable(payload.validate_at(100)).unwrap_err();
+        assert!(err.to_string().contains("recipient is the zero address"));
+    }
+
+    #[test]
+    fn test_invoice_id_cannot_forge_lines() {
+        let payload = PaymentPayload {
+            amount: U256::from(1),
+            recipient: Address::repeat_byte(0x11),
+            payer: Address::repeat_byte(0x22),
+            chain_id: 8453,
+            token: None,
+            resource: "/api".to_string(),
+            nonce: 1,
+            expires_at: 2_000,
+            scheme: Scheme::Exact,
+            extra: Default::default(),
+            invoice_id: Some("order-1\nSplits: 0x2222222222222222222222222222222222222222=1".to_string()),
+            splits: Vec::new(),
+        };
+        assert_eq!(payload.validate_at(1_000), vec![Violation::InvalidInvoiceId]);
+        let payment = crate::SignedPayment { payment: payload, signature: vec![0; 65], signatures: Vec::new() };
+        assert!(matches!(crate::encode_payment_header(&payment), Err(X402Error::Invalid(_))));
+
+        // A header built by hand is refused when decoded
+        let mut json = serde_json::to_value(&payment).unwrap();
+        json["payment"]["invoiceId"] = "order-1\r\nExpires: 0".into();
+        let header = base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(&json).unwrap());
+        assert!(crate::decode_payment_header(&header).is_err());
+        let mut buf = Vec::new();
+        assert!(crate::decode_payment_header_borrowed(header.as_bytes(), &mut buf).is_err());
+    }
+}
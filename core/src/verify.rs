@@ -1,41 +1,272 @@
 //! Signature verification for x402 payments
 
-use crate::{SignedPayment, PaymentRequirements, X402Error, Result};
-use alloy_primitives::Address;
+use crate::multisig::check_multisig;
+use crate::trace::traced;
+use crate::{
+    check_price_quote, check_ticket, splits_total, MultisigPolicy, MultisigRegistry, ChainRegistry, LotterySecret, LotteryTerms, ResourceMatcher, SchnorrKey, Bolt11Invoice, LightningPayment, LightningRequirements, SignedPayment, PaymentRequirements, Scheme,
+    X402Error, Result, ZkPaymentProof, ZkProofVerifier, ZkRequirements, resource_hash,
+};
+use alloy_primitives::{Address, B256, U256};
 use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
 
 /// Verify a signed payment against requirements
 /// 
 /// Checks:
-/// 1. Signature is valid and recovers to payer address
-/// 2. Amount meets requirements
-/// 3. Recipient matches
-/// 4. Payment not expired
+/// 1. Signature is valid and recovers to payer address (or, for a
+///    [`SchnorrKey`]-tagged payment, verifies under a key of that address)
+/// 2. Amount meets requirements (for `upto`, the authorized maximum
+///    covers the advertised amount)
+/// 3. Recipient and resource (after normalization) match
+/// 4. Payment not expired, and not valid for longer than the requirements'
+///    `max_timeout_seconds`
 /// 5. Network matches
+/// 6. Scheme matches
+/// 7. Split outputs, if any, match and sum to the amount
+/// 8. A fiat price quote, if any, is fresh and matches the amount
 pub fn verify_payment(
     payment: &SignedPayment,
     requirements: &PaymentRequirements,
 ) -> Result<Address> {
-    // Check expiry
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_secs();
+
+    verify_payment_at(payment, requirements, now)
+}
+
+/// Verify a signed payment against requirements as of the unix time `now`
+pub fn verify_payment_at(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    now: u64,
+) -> Result<Address> {
+    verify_payment_with_options_at(payment, requirements, &VerifyOptions::default(), now)
+}
+
+/// Policy checks on top of those [`verify_payment`] always makes
+#[derive(Clone, Default)]
+pub struct VerifyOptions {
+    /// Reject payments on test networks. Production servers should set
+    /// this, so a misconfigured deployment can't accept worthless tokens.
+    pub reject_testnets: bool,
+    /// Accept only chains in this registry, whose `testnet` flags then
+    /// decide what [`VerifyOptions::reject_testnets`] rejects
+    pub chains: Option<Arc<ChainRegistry>>,
+    /// Policies of multisig payers; without it, payments committing to a
+    /// [`MultisigPolicy`](crate::MultisigPolicy) are rejected
+    pub multisig: Option<Arc<dyn MultisigRegistry>>,
+}
+
+impl std::fmt::Debug for VerifyOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerifyOptions")
+            .field("reject_testnets", &self.reject_testnets)
+            .field("chains", &self.chains)
+            .field("multisig", &self.multisig.as_ref().map(|_| "MultisigRegistry"))
+            .finish()
+    }
+}
+
+/// The multisig registry compares by identity
+impl PartialEq for VerifyOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.reject_testnets == other.reject_testnets
+            && self.chains == other.chains
+            && match (&self.multisig, &other.multisig) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+impl Eq for VerifyOptions {}
+
+/// [`verify_payment`], also enforcing `options`
+pub fn verify_payment_with_options(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+) -> Result<Address> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    verify_payment_with_options_at(payment, requirements, options, now)
+}
+
+/// [`verify_payment_with_options`] as of the unix time `now`
+pub fn verify_payment_with_options_at(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    now: u64,
+) -> Result<Address> {
+    observe_verification(payment, requirements, || check_payment(payment, requirements, options, now))
+}
+
+/// Verify many payments, each against its own requirements
+///
+/// Returns one result per payment, in order; a failing payment doesn't
+/// stop the others from being checked.
+///
+/// With the `parallel` feature, signature recovery is spread across
+/// rayon's global pool; call this inside `ThreadPool::install` to use a
+/// dedicated one instead.
+pub fn verify_payments_batch(
+    batch: &[(&SignedPayment, &PaymentRequirements)],
+    options: &VerifyOptions,
+) -> Vec<Result<Address>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    verify_payments_batch_at(batch, options, now)
+}
+
+/// [`verify_payments_batch`] as of the unix time `now`
+pub fn verify_payments_batch_at(
+    batch: &[(&SignedPayment, &PaymentRequirements)],
+    options: &VerifyOptions,
+    now: u64,
+) -> Vec<Result<Address>> {
+    #[cfg(feature = "parallel")]
+    use rayon::prelude::*;
+
+    #[cfg(feature = "parallel")]
+    let batch = batch.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let batch = batch.iter();
+    batch
+        .map(|(payment, requirements)| verify_payment_with_options_at(payment, requirements, options, now))
+        .collect()
+}
+
+/// Run `check` inside the `x402.verify` span and record its outcome
+#[cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(unused_variables))]
+pub(crate) fn observe_verification(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    check: impl FnOnce() -> Result<Address>,
+) -> Result<Address> {
+    #[cfg(feature = "metrics")]
+    let started = std::time::Instant::now();
+    let result = traced!(
+        "x402.verify",
+        {
+            network = ?requirements.network,
+            chain_id = payment.payment.chain_id,
+            payer = %payment.payment.payer,
+            amount = %payment.payment.amount,
+            nonce = payment.payment.nonce,
+            resource = %payment.payment.resource,
+        },
+        check()
+    );
+    #[cfg(feature = "metrics")]
+    crate::telemetry::record_verification(payment, requirements, &result, started.elapsed());
+    result
+}
+
+fn check_payment(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    now: u64,
+) -> Result<Address> {
+    check_terms(payment, requirements, options, now)?;
+
+    // A group key's address holds no key that could authorize the transfer
+    if SchnorrKey::from_extra(&payment.payment.extra)?.is_some() {
+        return Err(X402Error::InvalidSignature(
+            "schnorr-signed payments must come from a smart account; see verify_smart_account_payment".to_string(),
+        ));
+    }
+
+    if MultisigPolicy::from_extra(&payment.payment.extra)?.is_some() {
+        let registry = options.multisig.as_deref().ok_or_else(|| {
+            X402Error::InvalidSignature("multisig payments need a MultisigRegistry in VerifyOptions::multisig".to_string())
+        })?;
+        return check_multisig(payment, registry);
+    }
+    if !payment.signatures.is_empty() {
+        return Err(X402Error::InvalidSignature("signatures is only for multisig payments".to_string()));
+    }
+
+    // Verify signature and recover payer address
+    let recovered_address = recover_signer(payment)?;
     
+    if recovered_address != payment.payment.payer {
+        return Err(X402Error::InvalidSignature(
+            "recovered address does not match payer".to_string()
+        ));
+    }
+
+    Ok(recovered_address)
+}
+
+/// Every check [`verify_payment`] makes except the signature
+pub(crate) fn check_terms(
+    payment: &SignedPayment,
+    requirements: &PaymentRequirements,
+    options: &VerifyOptions,
+    now: u64,
+) -> Result<()> {
+    let testnet = match &options.chains {
+        Some(chains) => {
+            let chain = chains.get(requirements.network.chain_id()).ok_or_else(|| {
+                X402Error::UnsupportedNetwork(format!("{} is not in the chain registry", requirements.network.as_str()))
+            })?;
+            chain.testnet
+        }
+        None => requirements.network.is_testnet(),
+    };
+    if options.reject_testnets && testnet {
+        return Err(X402Error::UnsupportedNetwork(format!(
+            "{} is a testnet",
+            requirements.network.as_str()
+        )));
+    }
+
+    // Check expiry
     if payment.payment.expires_at < now {
         return Err(X402Error::PaymentExpired);
     }
+    if let Some(max_seconds) = requirements.max_timeout_seconds {
+        let requested_seconds = payment.payment.expires_at - now;
+        if requested_seconds > max_seconds {
+            return Err(X402Error::ValidityTooLong { max_seconds, requested_seconds });
+        }
+    }
 
-    // Check amount (convert U256 to u64 for comparison - simplified)
-    let required_amount: u64 = requirements.amount.try_into()
-        .unwrap_or(u64::MAX);
-    let provided_amount: u64 = payment.payment.amount.try_into()
-        .unwrap_or(0);
-    
-    if provided_amount < required_amount {
+    check_price_quote(requirements, now)?;
+    crate::validate::check_invoice_id(payment.payment.invoice_id.as_deref())?;
+
+    // Check scheme
+    if payment.payment.scheme != requirements.scheme {
+        return Err(X402Error::UnsupportedScheme(format!(
+            "expected {}, got {}",
+            requirements.scheme.as_str(),
+            payment.payment.scheme.as_str()
+        )));
+    }
+
+    // Lottery tickets must carry the advertised odds and commitment
+    if requirements.scheme == Scheme::Lottery
+        && LotteryTerms::from_extra(&payment.payment.extra)? != LotteryTerms::from_extra(&requirements.extra)?
+    {
+        return Err(X402Error::InvalidTicket("ticket terms differ from the requirements".to_string()));
+    }
+
+    // Check amount
+    if payment.payment.amount < requirements.amount {
         return Err(X402Error::InsufficientAmount {
-            required: required_amount,
-            provided: provided_amount,
+            required: requirements.amount,
+            provided: payment.payment.amount,
         });
     }
 
@@ -46,6 +277,35 @@ pub fn verify_payment(
         ));
     }
 
+    // Check token; None is the native coin
+    if payment.payment.token != requirements.token {
+        return Err(X402Error::InvalidSignature(format!(
+            "token mismatch: payment is in {}, not {}",
+            asset_name(payment.payment.token.as_ref()),
+            asset_name(requirements.token.as_ref())
+        )));
+    }
+
+    // Check resource, ignoring trailing slashes and default ports
+    if !ResourceMatcher::Normalized(requirements.resource.clone()).matches(&payment.payment.resource) {
+        return Err(X402Error::InvalidSignature(format!(
+            "resource mismatch: payment is for {}, not {}",
+            payment.payment.resource, requirements.resource
+        )));
+    }
+
+    // Check split outputs: the signed payment must commit to exactly the
+    // advertised split, which must account for the whole amount
+    if payment.payment.splits != requirements.splits {
+        return Err(X402Error::InvalidSplit("payment splits differ from the requirements".to_string()));
+    }
+    if !payment.payment.splits.is_empty() && splits_total(&payment.payment.splits) != Some(payment.payment.amount) {
+        return Err(X402Error::InvalidSplit(format!(
+            "split shares do not sum to the payment amount of {}",
+            payment.payment.amount
+        )));
+    }
+
     // Check network
     if payment.payment.chain_id != requirements.network.chain_id() {
         return Err(X402Error::UnsupportedNetwork(
@@ -55,31 +315,164 @@ pub fn verify_payment(
         ));
     }
 
-    // Verify signature and recover payer address
-    let recovered_address = recover_signer(payment)?;
-    
-    if recovered_address != payment.payment.payer {
-        return Err(X402Error::InvalidSignature(
-            "recovered address does not match payer".to_string()
+    Ok(())
+}
+
+fn asset_name(token: Option<&Address>) -> String {
+    token.map_or_else(|| "the native coin".to_string(), Address::to_string)
+}
+
+/// Check the amount a server intends to settle for a verified payment
+///
+/// `exact` payments settle for their full amount; `upto` payments for
+/// any metered charge up to the authorized amount. `lottery` tickets are
+/// refused here: their draw needs the server's secret, so charge them with
+/// [`check_ticket_charge`](crate::check_ticket_charge).
+pub fn check_charge(payment: &SignedPayment, charge: U256) -> Result<()> {
+    check_charge_with(payment, charge, None)
+}
+
+/// [`check_charge`], drawing `lottery` tickets with `secret`
+pub(crate) fn check_charge_with(payment: &SignedPayment, charge: U256, secret: Option<&LotterySecret>) -> Result<()> {
+    let authorized = payment.payment.amount;
+    let result = traced!("x402.settle", { payer = %payment.payment.payer, charge = %charge, authorized = %authorized }, {
+        let allowed = match payment.payment.scheme {
+            Scheme::Exact => charge == authorized,
+            Scheme::Upto => charge <= authorized,
+            Scheme::Lottery => {
+                let secret = secret.ok_or_else(|| {
+                    X402Error::ChargeRejected("lottery tickets are charged with check_ticket_charge".to_string())
+                })?;
+                if !check_ticket(payment, secret.reveal())? {
+                    return Err(X402Error::InvalidTicket("ticket lost the draw".to_string()));
+                }
+                charge == authorized
+            }
+        };
+        if !allowed {
+            return Err(X402Error::ChargeRejected(format!(
+                "cannot charge {} on {} payment of {}",
+                charge,
+                payment.payment.scheme.as_str(),
+                authorized
+            )));
+        }
+        Ok(())
+    });
+    #[cfg(feature = "metrics")]
+    crate::telemetry::record_settlement(payment, charge, &result);
+    result
+}
+
+/// Verify a Lightning payment proof against requirements
+///
+/// Checks the proof is for the advertised invoice, that the invoice hadn't
+/// expired, and that the preimage hashes to the invoice's payment hash.
+/// Returns the payment hash, which the caller should record to stop reuse.
+pub fn verify_lightning_payment(
+    payment: &LightningPayment,
+    requirements: &LightningRequirements,
+) -> Result<B256> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    verify_lightning_payment_at(payment, requirements, now)
+}
+
+/// Verify a Lightning payment proof as of the unix time `now`
+pub fn verify_lightning_payment_at(
+    payment: &LightningPayment,
+    requirements: &LightningRequirements,
+    now: u64,
+) -> Result<B256> {
+    if payment.invoice != requirements.invoice {
+        return Err(X402Error::InvalidPreimage("proof is for a different invoice".to_string()));
+    }
+    let invoice = Bolt11Invoice::parse(&payment.invoice)?;
+    if invoice.expires_at() < now {
+        return Err(X402Error::PaymentExpired);
+    }
+
+    let hash = B256::from_slice(&Sha256::digest(payment.preimage));
+    if hash != invoice.payment_hash {
+        return Err(X402Error::InvalidPreimage(
+            "preimage does not match the payment hash".to_string()
         ));
     }
 
-    Ok(recovered_address)
+    Ok(hash)
+}
+
+/// Verify a zero-knowledge payment proof against requirements
+///
+/// Checks the proof uses a system the server offers and `verifier`
+/// implements, that its statement pays at least the required amount to the
+/// required recipient, token, chain and resource, that it hasn't expired,
+/// and finally the proof itself. Returns the nullifier, which the caller
+/// should record to stop reuse.
+pub fn verify_zk_payment<V: ZkProofVerifier + ?Sized>(
+    proof: &ZkPaymentProof,
+    requirements: &ZkRequirements,
+    verifier: &V,
+) -> Result<B256> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    verify_zk_payment_at(proof, requirements, verifier, now)
+}
+
+/// Verify a zero-knowledge payment proof as of the unix time `now`
+pub fn verify_zk_payment_at<V: ZkProofVerifier + ?Sized>(
+    proof: &ZkPaymentProof,
+    requirements: &ZkRequirements,
+    verifier: &V,
+    now: u64,
+) -> Result<B256> {
+    if !requirements.proof_systems.contains(&proof.proof_system) || proof.proof_system != verifier.proof_system() {
+        return Err(X402Error::InvalidProof(format!("unsupported proof system {}", proof.proof_system)));
+    }
+    let statement = &proof.statement;
+    if statement.recipient != requirements.recipient
+        || statement.chain_id != requirements.network.chain_id()
+        || statement.token != requirements.token
+        || statement.resource_hash != resource_hash(&requirements.resource)
+    {
+        return Err(X402Error::InvalidProof("statement differs from the requirements".to_string()));
+    }
+    if statement.min_amount < requirements.amount {
+        return Err(X402Error::InsufficientAmount { required: requirements.amount, provided: statement.min_amount });
+    }
+    if statement.expires_at < now {
+        return Err(X402Error::PaymentExpired);
+    }
+
+    if !verifier.verify(statement, &proof.proof)? {
+        return Err(X402Error::InvalidProof("proof does not verify".to_string()));
+    }
+
+    Ok(statement.nullifier)
 }
 
 /// Recover the signer address from a signed payment
 pub fn recover_signer(payment: &SignedPayment) -> Result<Address> {
-    if payment.signature.len() != 65 {
+    recover_address(&payment.payment.message_hash(), &payment.signature)
+}
+
+/// Recover the address that produced a 65-byte signature over `message_hash`
+pub fn recover_address(message_hash: &[u8; 32], signature: &[u8]) -> Result<Address> {
+    if signature.len() != 65 {
         return Err(X402Error::InvalidSignature(
-            format!("signature must be 65 bytes, got {}", payment.signature.len())
+            format!("signature must be 65 bytes, got {}", signature.len())
         ));
     }
 
-    let message_hash = payment.payment.message_hash();
-    
     // Parse signature components
-    let r_s = &payment.signature[..64];
-    let v = payment.signature[64];
+    let r_s = &signature[..64];
+    let v = signature[64];
     
     // Recovery ID: v is either 27/28 (legacy) or 0/1
     let recovery_id = if v >= 27 {
@@ -88,11 +481,8 @@ pub fn recover_signer(payment: &SignedPayment) -> Result<Address> {
         RecoveryId::try_from(v)
     }.map_err(|_| X402Error::InvalidSignature("invalid recovery id".to_string()))?;
 
-    let signature = Signature::from_slice(r_s)
-        .map_err(|e| X402Error::InvalidSignature(e.to_string()))?;
-
-    let verifying_key = VerifyingKey::recover_from_prehash(&message_hash, &signature, recovery_id)
-        .map_err(|e| X402Error::InvalidSignature(e.to_string()))?;
+    let signature = Signature::from_slice(r_s)?;
+    let verifying_key = VerifyingKey::recover_from_prehash(message_hash, &signature, recovery_id)?;
 
     // Convert public key to Ethereum address
     let public_key_bytes = verifying_key.to_encoded_point(false);
@@ -109,7 +499,6 @@ mod tests {
     #[test]
     fn test_invalid_signature_length() {
         use crate::{PaymentPayload, SignedPayment};
-        use alloy_primitives::U256;
 
         let payment = SignedPayment {
             payment: PaymentPayload {
@@ -121,11 +510,387 @@ mod tests {
                 resource: "/test".to_string(),
                 nonce: 1,
                 expires_at: u64::MAX,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
             },
             signature: vec![0u8; 64], // Wrong length
+            signatures: Vec::new(),
         };
 
         let result = recover_signer(&payment);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_scheme_rules() {
+        use crate::{Network, PaymentPayload};
+
+        let mut payment = SignedPayment {
+            payment: PaymentPayload {
+                amount: U256::from(1000),
+                recipient: Address::ZERO,
+                payer: Address::ZERO,
+                chain_id: 8453,
+                token: None,
+                resource: "/test".to_string(),
+                nonce: 1,
+                expires_at: u64::MAX,
+                scheme: Scheme::Upto,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: vec![0u8; 65],
+            signatures: Vec::new(),
+        };
+        let requirements = PaymentRequirements {
+            amount: U256::from(1000),
+            recipient: Address::ZERO,
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        assert!(matches!(
+            verify_payment(&payment, &requirements),
+            Err(X402Error::UnsupportedScheme(_))
+        ));
+
+        assert!(check_charge(&payment, U256::from(250)).is_ok());
+        assert!(check_charge(&payment, U256::from(1001)).is_err());
+        payment.payment.scheme = Scheme::Exact;
+        assert!(check_charge(&payment, U256::from(250)).is_err());
+        assert!(check_charge(&payment, U256::from(1000)).is_ok());
+    }
+
+    #[test]
+    fn test_reject_testnets() {
+        use crate::{testing::TestSigner, ChainInfo, Network};
+
+        let now = 1_700_000_000;
+        let signer = TestSigner::default();
+        let strict = VerifyOptions { reject_testnets: true, ..Default::default() };
+        let mainnet = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api").unwrap();
+        let testnet = PaymentRequirements::usdc(Network::BaseSepolia, "0.01", Address::repeat_byte(0x11), "/api").unwrap();
+
+        let payment = signer.pay(&mainnet, now).unwrap();
+        assert_eq!(verify_payment_with_options_at(&payment, &mainnet, &strict, now).unwrap(), signer.address());
+        let payment = signer.pay(&testnet, now).unwrap();
+        assert_eq!(verify_payment_at(&payment, &testnet, now).unwrap(), signer.address());
+        assert!(matches!(
+            verify_payment_with_options_at(&payment, &testnet, &strict, now),
+            Err(X402Error::UnsupportedNetwork(_))
+        ));
+
+        let mut chains = ChainRegistry::new().with_chain(ChainInfo::builtin(Network::BaseSepolia));
+        let payment = signer.pay(&testnet, now).unwrap();
+        let listed = VerifyOptions { chains: Some(Arc::new(chains.clone())), ..strict.clone() };
+        assert!(verify_payment_with_options_at(&payment, &testnet, &listed, now).is_err());
+        chains.insert(ChainInfo { testnet: false, ..ChainInfo::builtin(Network::BaseSepolia) });
+        let relabelled = VerifyOptions { chains: Some(Arc::new(chains)), ..strict.clone() };
+        assert_eq!(verify_payment_with_options_at(&payment, &testnet, &relabelled, now).unwrap(), signer.address());
+        let payment = signer.pay(&mainnet, now).unwrap();
+        assert!(matches!(
+            verify_payment_with_options_at(&payment, &mainnet, &relabelled, now),
+            Err(X402Error::UnsupportedNetwork(_))
+        ));
+    }
+
+    #[test]
+    fn test_amounts_above_u64() {
+        use crate::{testing::TestSigner, Network};
+
+        let now = 1_700_000_000;
+        let signer = TestSigner::default();
+        let mut requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api").unwrap();
+        requirements.amount = U256::from(u64::MAX) + U256::from(1000);
+        let payment = signer.pay(&requirements, now).unwrap();
+        assert_eq!(verify_payment_at(&payment, &requirements, now).unwrap(), signer.address());
+
+        let mut underpaid = requirements.clone();
+        underpaid.amount -= U256::from(1);
+        let payment = signer.pay(&underpaid, now).unwrap();
+        match verify_payment_at(&payment, &requirements, now) {
+            Err(X402Error::InsufficientAmount { required, provided }) => {
+                assert_eq!((required, provided), (requirements.amount, underpaid.amount));
+            }
+            other => panic!("expected InsufficientAmount, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_must_match() {
+        use crate::{testing::TestSigner, Network};
+
+        let now = 1_700_000_000;
+        let signer = TestSigner::default();
+        let requirements =
+            PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/api").unwrap();
+        let mut other_token = requirements.clone();
+        other_token.token = Some(Address::repeat_byte(0x22));
+        let mut native = requirements.clone();
+        native.token = None;
+
+        for paid in [&other_token, &native] {
+            let payment = signer.pay(paid, now).unwrap();
+            match verify_payment_at(&payment, &requirements, now) {
+                Err(X402Error::InvalidSignature(message)) => assert!(message.starts_with("token mismatch")),
+                other => panic!("expected a token mismatch, got {:?}", other),
+            }
+        }
+        let payment = signer.pay(&requirements, now).unwrap();
+        assert!(matches!(
+            verify_payment_at(&payment, &native, now),
+            Err(X402Error::InvalidSignature(message)) if message.ends_with("not the native coin")
+        ));
+        assert_eq!(verify_payment_at(&payment, &requirements, now).unwrap(), signer.address());
+    }
+
+    #[test]
+    fn test_verify_payments_batch() {
+        use crate::{testing::TestSigner, Network};
+
+        let now = 1_700_000_000;
+        let (alice, bob) = (TestSigner::new(1), TestSigner::new(2));
+        let reports = PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/reports").unwrap();
+        let search = PaymentRequirements::usdc(Network::Base, "0.02", Address::repeat_byte(0x11), "/search").unwrap();
+        let paid = alice.pay(&reports, now).unwrap();
+        let mut forged = bob.pay(&search, now).unwrap();
+        forged.payment.payer = alice.address();
+        let underpaid = bob.pay(&reports, now).unwrap();
+
+        let results = verify_payments_batch_at(
+            &[(&paid, &reports), (&forged, &search), (&underpaid, &search), (&bob.pay(&search, now).unwrap(), &search)],
+            &VerifyOptions::default(),
+            now,
+        );
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap(), &alice.address());
+        assert!(matches!(results[1], Err(X402Error::InvalidSignature(_))));
+        assert!(results[2].is_err());
+        assert_eq!(results[3].as_ref().unwrap(), &bob.address());
+        assert!(verify_payments_batch_at(&[], &VerifyOptions::default(), now).is_empty());
+    }
+
+    #[test]
+    fn test_validity_window_limited_by_max_timeout() {
+        use crate::{Network, PaymentPayload};
+
+        let requirements = PaymentRequirements {
+            amount: U256::from(1000),
+            recipient: Address::ZERO,
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: Some(60),
+            extra: Default::default(),
+            splits: Vec::new(),
+            price_quote: None,
+        };
+        let payment = SignedPayment {
+            payment: PaymentPayload {
+                amount: U256::from(1000),
+                recipient: Address::ZERO,
+                payer: Address::ZERO,
+                chain_id: 8453,
+                token: None,
+                resource: "/test".to_string(),
+                nonce: 1,
+                expires_at: 1_000 + 7 * 24 * 3600,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: Vec::new(),
+            },
+            signature: vec![0u8; 65],
+            signatures: Vec::new(),
+        };
+
+        assert!(matches!(
+            verify_payment_at(&payment, &requirements, 1_000),
+            Err(X402Error::ValidityTooLong { max_seconds: 60, .. })
+        ));
+        assert_eq!(requirements.payment_expires_at(1_000), 1_060);
+    }
+
+    #[test]
+    fn test_lightning_preimage() {
+        let preimage = B256::repeat_byte(0x07);
+        let payment_hash = B256::from_slice(&Sha256::digest(preimage));
+        let invoice = crate::lightning::tests::invoice("lnbc10u", payment_hash, 1_000, 600);
+        let requirements = LightningRequirements {
+            invoice: invoice.clone(),
+            resource: "/test".to_string(),
+            description: None,
+        };
+
+        let payment = LightningPayment::from_header(LightningPayment { invoice, preimage }.to_header().unwrap()).unwrap();
+        assert_eq!(verify_lightning_payment_at(&payment, &requirements, 1_500).unwrap(), payment_hash);
+        assert!(matches!(verify_lightning_payment_at(&payment, &requirements, 1_601), Err(X402Error::PaymentExpired)));
+
+        let wrong = LightningPayment { preimage: B256::repeat_byte(0x08), ..payment };
+        assert!(matches!(verify_lightning_payment_at(&wrong, &requirements, 1_500), Err(X402Error::InvalidPreimage(_))));
+    }
+
+    #[test]
+    fn test_zk_payment_proof() {
+        use crate::{Network, ZkPaymentStatement};
+        use alloy_primitives::Bytes;
+
+        // Stands in for a real backend: accepts proofs that echo the nullifier
+        struct EchoVerifier;
+        impl ZkProofVerifier for EchoVerifier {
+            fn proof_system(&self) -> &str {
+                "echo"
+            }
+            fn verify(&self, statement: &ZkPaymentStatement, proof: &[u8]) -> Result<bool> {
+                Ok(proof == statement.nullifier.as_slice())
+            }
+        }
+
+        let requirements = ZkRequirements {
+            proof_systems: vec!["echo".to_string()],
+            amount: U256::from(10_000),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: Some(Address::repeat_byte(0x22)),
+            resource: "/private".to_string(),
+        };
+        let nullifier = B256::repeat_byte(0x05);
+        let statement = ZkPaymentStatement::for_requirements(&requirements, nullifier, 2_000);
+        let proof = ZkPaymentProof {
+            proof_system: "echo".to_string(),
+            statement: statement.clone(),
+            proof: Bytes::copy_from_slice(nullifier.as_slice()),
+        };
+        assert_eq!(verify_zk_payment_at(&proof, &requirements, &EchoVerifier, 1_000).unwrap(), nullifier);
+        assert!(matches!(
+            verify_zk_payment_at(&proof, &requirements, &EchoVerifier, 2_001),
+            Err(X402Error::PaymentExpired)
+        ));
+
+        let forged = ZkPaymentProof { proof: Bytes::from_static(b"forged"), ..proof.clone() };
+        assert!(matches!(
+            verify_zk_payment_at(&forged, &requirements, &EchoVerifier, 1_000),
+            Err(X402Error::InvalidProof(_))
+        ));
+        let other_resource = ZkPaymentProof {
+            statement: ZkPaymentStatement { resource_hash: resource_hash("/other"), ..statement.clone() },
+            ..proof.clone()
+        };
+        assert!(matches!(
+            verify_zk_payment_at(&other_resource, &requirements, &EchoVerifier, 1_000),
+            Err(X402Error::InvalidProof(_))
+        ));
+        let underpaid = ZkPaymentProof {
+            statement: ZkPaymentStatement { min_amount: U256::from(9_999), ..statement },
+            ..proof.clone()
+        };
+        assert!(matches!(
+            verify_zk_payment_at(&underpaid, &requirements, &EchoVerifier, 1_000),
+            Err(X402Error::InsufficientAmount { .. })
+        ));
+        let unoffered = ZkPaymentProof { proof_system: "plonk".to_string(), ..proof };
+        assert!(verify_zk_payment_at(&unoffered, &requirements, &EchoVerifier, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_split_outputs() {
+        use crate::{Network, PaymentPayload, Split};
+
+        let splits = vec![
+            Split { recipient: Address::repeat_byte(0x11), share: U256::from(700) },
+            Split { recipient: Address::repeat_byte(0x22), share: U256::from(300) },
+        ];
+        let requirements = PaymentRequirements {
+            amount: U256::from(1000),
+            recipient: Address::ZERO,
+            network: Network::Base,
+            token: None,
+            description: None,
+            expires_at: None,
+            resource: "/test".to_string(),
+            commitment: None,
+            scheme: Scheme::Exact,
+            mime_type: None,
+            output_schema: None,
+            max_timeout_seconds: None,
+            extra: Default::default(),
+            splits: splits.clone(),
+            price_quote: None,
+        };
+        let mut payment = SignedPayment {
+            payment: PaymentPayload {
+                amount: U256::from(1000),
+                recipient: Address::ZERO,
+                payer: Address::ZERO,
+                chain_id: 8453,
+                token: None,
+                resource: "/test".to_string(),
+                nonce: 1,
+                expires_at: u64::MAX,
+                scheme: Scheme::Exact,
+                extra: Default::default(),
+                invoice_id: None,
+                splits: splits[..1].to_vec(),
+            },
+            signature: vec![0u8; 65],
+            signatures: Vec::new(),
+        };
+        let unsplit_hash = PaymentPayload { splits: Vec::new(), ..payment.payment.clone() }.message_hash();
+        assert_ne!(payment.payment.message_hash(), unsplit_hash);
+        assert!(matches!(verify_payment_at(&payment, &requirements, 0), Err(X402Error::InvalidSplit(_))));
+
+        // Matching splits get as far as the signature check
+        payment.payment.splits = splits;
+        assert!(!matches!(verify_payment_at(&payment, &requirements, 0), Err(X402Error::InvalidSplit(_))));
+        payment.payment.amount = U256::from(2000);
+        assert!(matches!(verify_payment_at(&payment, &requirements, 0), Err(X402Error::InvalidSplit(_))));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn signature_parser_never_panics(
+                hash in any::<[u8; 32]>(),
+                signature in proptest::collection::vec(any::<u8>(), 0..80),
+            ) {
+                let _ = recover_address(&hash, &signature);
+            }
+
+            #[test]
+            fn recovers_signing_key(secret in any::<[u8; 32]>(), hash in any::<[u8; 32]>(), legacy_v in any::<bool>()) {
+                let Ok(key) = k256::ecdsa::SigningKey::from_slice(&secret) else {
+                    return Ok(());
+                };
+                let (signature, recovery_id) = key.sign_prehash_recoverable(&hash).unwrap();
+                let mut bytes = signature.to_bytes().to_vec();
+                bytes.push(recovery_id.to_byte() + if legacy_v { 27 } else { 0 });
+                let point = key.verifying_key().to_encoded_point(false);
+                let address = Address::from_slice(&alloy_primitives::keccak256(&point.as_bytes()[1..])[12..]);
+                prop_assert_eq!(recover_address(&hash, &bytes).unwrap(), address);
+            }
+        }
+    }
 }
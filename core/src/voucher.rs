@@ -0,0 +1,250 @@
+//! Offline payment vouchers
+//!
+//! A batch job that will make paid requests long after its payer is gone
+//! can carry [`PaymentVoucher`]s minted in advance. Each voucher is signed
+//! by the payer over [`VoucherTerms`]: an amount, a resource scope, an
+//! expiry and a random single-use ID. It is a bearer token: the server
+//! checks the payer's signature and redeems it with [`redeem_voucher`],
+//! tracking spent IDs through [`VoucherRedemptions`], without any further
+//! contact with the payer.
+//!
+//! Anyone holding a voucher can spend it, so keep scopes and expiries
+//! tight. Settling the redeemed amount is left to the server's settlement
+//! process.
+
+use crate::{decode_header, parse_payload, tagged, DecodeLimits, Result, WireFormat, X402Error};
+#[cfg(feature = "verify")]
+use crate::{recover_address, PaymentRequirements, ResourceMatcher};
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// HTTP header carrying an offline payment voucher
+pub const X402_OFFLINE_VOUCHER_HEADER: &str = "X-Offline-Voucher";
+
+/// A fresh voucher ID from the OS CSPRNG
+pub fn random_voucher_id() -> B256 {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    B256::from(bytes)
+}
+
+/// Terms of a voucher, as signed by the payer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherTerms {
+    /// Single-use ID; see [`random_voucher_id`]
+    pub id: B256,
+    pub payer: Address,
+    pub recipient: Address,
+    pub chain_id: u64,
+    /// Token contract (`None` = native token)
+    pub token: Option<Address>,
+    /// Most the voucher pays for one request
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    /// Resources the voucher may pay for, as a [`ResourceMatcher::Glob`]
+    /// pattern
+    pub scope: String,
+    /// Last unix time the voucher can be redeemed
+    pub expires_at: u64,
+}
+
+impl VoucherTerms {
+    /// Create the message hash the payer signs
+    pub fn message_hash(&self) -> [u8; 32] {
+        let message = format!(
+            "x402 Voucher\nId: {}\nPayer: {}\nRecipient: {}\nChainId: {}\nToken: {}\nAmount: {}\nScope: {}\nExpires: {}",
+            self.id,
+            self.payer,
+            self.recipient,
+            self.chain_id,
+            self.token.map(|t| t.to_string()).unwrap_or_default(),
+            self.amount,
+            self.scope,
+            self.expires_at
+        );
+        *keccak256(message.as_bytes())
+    }
+}
+
+/// Payer-signed voucher terms
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentVoucher {
+    pub terms: VoucherTerms,
+    /// ECDSA signature over [`VoucherTerms::message_hash`] (65 bytes: r + s + v)
+    pub signature: Vec<u8>,
+}
+
+impl PaymentVoucher {
+    /// Check the voucher can pay for `requirements` at unix time `now`,
+    /// returning the payer
+    ///
+    /// Does not check whether the voucher was already redeemed.
+    #[cfg(feature = "verify")]
+    pub fn verify_at(&self, requirements: &PaymentRequirements, now: u64) -> Result<Address> {
+        let terms = &self.terms;
+        if terms.expires_at < now {
+            return Err(X402Error::PaymentExpired);
+        }
+        if terms.recipient != requirements.recipient
+            || terms.chain_id != requirements.network.chain_id()
+            || terms.token != requirements.token
+        {
+            return Err(X402Error::InvalidOfflineVoucher(
+                "voucher pays a different recipient, network or token".to_string()
+            ));
+        }
+        if !ResourceMatcher::Glob(terms.scope.clone()).matches(&requirements.resource) {
+            return Err(X402Error::InvalidOfflineVoucher(format!(
+                "{} is outside the voucher scope {}",
+                requirements.resource, terms.scope
+            )));
+        }
+        if terms.amount < requirements.amount {
+            return Err(X402Error::InvalidOfflineVoucher(format!(
+                "voucher pays up to {}, price is {}",
+                terms.amount, requirements.amount
+            )));
+        }
+
+        let signer = recover_address(&terms.message_hash(), &self.signature)?;
+        if signer != terms.payer {
+            return Err(X402Error::InvalidSignature(
+                "recovered address does not match payer".to_string()
+            ));
+        }
+        Ok(signer)
+    }
+
+    /// Encode as an `X-Offline-Voucher` header value
+    pub fn to_header(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    }
+
+    /// Decode an `X-Offline-Voucher` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let (format, bytes) = decode_header(header.as_ref(), &limits)?;
+        parse_payload(format, &bytes, &limits)
+    }
+}
+
+/// A redeemed voucher, to settle for the price charged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherRedemption {
+    pub voucher_id: B256,
+    pub payer: Address,
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+}
+
+/// Storage of redeemed voucher IDs
+pub trait VoucherRedemptions: Send + Sync {
+    /// Record `id` as redeemed; fails with [`X402Error::DuplicatePayment`]
+    /// if it already was. `expires_at` is when the record may be dropped.
+    fn redeem(&self, id: &B256, expires_at: u64) -> Result<()>;
+}
+
+/// In-process [`VoucherRedemptions`]
+#[derive(Debug, Default)]
+pub struct MemoryVoucherRedemptions {
+    redeemed: RwLock<HashMap<B256, u64>>,
+}
+
+impl MemoryVoucherRedemptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget vouchers that expired before unix time `now`; they can't be
+    /// redeemed again anyway
+    pub fn purge(&self, now: u64) {
+        self.redeemed.write().unwrap().retain(|_, expires_at| *expires_at >= now);
+    }
+}
+
+impl VoucherRedemptions for MemoryVoucherRedemptions {
+    fn redeem(&self, id: &B256, expires_at: u64) -> Result<()> {
+        let mut redeemed = self.redeemed.write().unwrap();
+        if redeemed.insert(*id, expires_at).is_some() {
+            return Err(X402Error::DuplicatePayment(format!("voucher {}", id)));
+        }
+        Ok(())
+    }
+}
+
+/// Redeem `voucher` for `requirements` at unix time `now`
+///
+/// Fails if the voucher doesn't cover the requirements, its signature is
+/// invalid, or it was already redeemed.
+#[cfg(feature = "verify")]
+pub fn redeem_voucher<R: VoucherRedemptions + ?Sized>(
+    voucher: &PaymentVoucher,
+    requirements: &PaymentRequirements,
+    redemptions: &R,
+    now: u64,
+) -> Result<VoucherRedemption> {
+    let payer = voucher.verify_at(requirements, now)?;
+    redemptions.redeem(&voucher.terms.id, voucher.terms.expires_at)?;
+    Ok(VoucherRedemption { voucher_id: voucher.terms.id, payer, amount: requirements.amount })
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestSigner;
+    use crate::Network;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn requirements() -> PaymentRequirements {
+        PaymentRequirements::usdc(Network::Base, "0.01", Address::repeat_byte(0x11), "/jobs/42/run").unwrap()
+    }
+
+    fn mint(payer: &TestSigner, amount: u64, scope: &str) -> PaymentVoucher {
+        let terms = VoucherTerms {
+            id: random_voucher_id(),
+            payer: payer.address(),
+            recipient: Address::repeat_byte(0x11),
+            chain_id: Network::Base.chain_id(),
+            token: requirements().token,
+            amount: U256::from(amount),
+            scope: scope.to_string(),
+            expires_at: NOW + 86_400,
+        };
+        let signature = payer.sign_hash(&terms.message_hash()).unwrap();
+        PaymentVoucher { terms, signature }
+    }
+
+    #[test]
+    fn test_voucher_redeemed_once() {
+        let payer = TestSigner::default();
+        let requirements = requirements();
+        let redemptions = MemoryVoucherRedemptions::new();
+
+        let voucher = PaymentVoucher::from_header(mint(&payer, 20_000, "/jobs/**").to_header().unwrap()).unwrap();
+        let redemption = redeem_voucher(&voucher, &requirements, &redemptions, NOW).unwrap();
+        assert_eq!((redemption.payer, redemption.amount), (payer.address(), U256::from(10_000)));
+        assert!(matches!(
+            redeem_voucher(&voucher, &requirements, &redemptions, NOW),
+            Err(X402Error::DuplicatePayment(_))
+        ));
+        redemptions.purge(NOW + 86_401);
+        assert!(matches!(
+            redeem_voucher(&voucher, &requirements, &redemptions, NOW + 86_401),
+            Err(X402Error::PaymentExpired)
+        ));
+
+        let check = |voucher: &PaymentVoucher| redeem_voucher(voucher, &requirements, &redemptions, NOW);
+        assert!(matches!(check(&mint(&payer, 20_000, "/admin/**")), Err(X402Error::InvalidOfflineVoucher(_))));
+        assert!(matches!(check(&mint(&payer, 5_000, "/jobs/**")), Err(X402Error::InvalidOfflineVoucher(_))));
+        let mut tampered = mint(&payer, 20_000, "/jobs/*/run");
+        tampered.terms.amount = U256::from(1_000_000);
+        assert!(matches!(check(&tampered), Err(X402Error::InvalidSignature(_))));
+    }
+}
@@ -0,0 +1,145 @@
+//! Privacy-preserving payment proofs
+//!
+//! An alternative to signed payments for clients that don't want to reveal
+//! their address: the client pays through a shielded pool (or any system
+//! with private notes) and sends a zero-knowledge proof of the statement
+//! "I paid at least `min_amount` of `token` to `recipient` for the resource
+//! hashing to `resource_hash`" ([`ZkPaymentStatement`]). The server
+//! advertises [`ZkRequirements`] (see [`crate::PaymentRequiredResponse::zk`])
+//! and checks the proof with [`crate::verify_zk_payment`].
+//!
+//! Proof systems plug in as [`ZkProofVerifier`] backends; this crate checks
+//! the statement against the requirements and leaves the proof itself to
+//! the backend. The statement's `nullifier` is the only handle on the
+//! payment: the circuit must bind it to the spent note, and servers should
+//! record redeemed nullifiers (e.g. in a [`crate::PaymentLedger`]) to stop
+//! a proof being reused.
+
+use crate::{decode_header, parse_payload, tagged, DecodeLimits, Network, Result, WireFormat, X402Error};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// HTTP header carrying a payment proof
+pub const X402_PROOF_HEADER: &str = "X-Payment-Proof";
+
+/// Zero-knowledge payment option in a 402 response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZkRequirements {
+    /// Proof systems the server can verify, e.g. `groth16-bn254`
+    pub proof_systems: Vec<String>,
+    /// Least amount the proof must show was paid
+    #[serde(with = "crate::serde_amount")]
+    pub amount: U256,
+    pub recipient: Address,
+    pub network: Network,
+    /// Token address (None = native token)
+    pub token: Option<Address>,
+    /// Resource being paid for
+    pub resource: String,
+}
+
+/// Public inputs of a payment proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZkPaymentStatement {
+    #[serde(with = "crate::serde_amount")]
+    pub min_amount: U256,
+    pub recipient: Address,
+    pub chain_id: u64,
+    pub token: Option<Address>,
+    /// [`resource_hash`] of the resource paid for
+    pub resource_hash: B256,
+    /// Unique per payment, without revealing the payer
+    pub nullifier: B256,
+    /// Time after which the proof is no longer accepted (unix timestamp)
+    pub expires_at: u64,
+}
+
+impl ZkPaymentStatement {
+    /// The statement a proof for `requirements` must prove
+    pub fn for_requirements(requirements: &ZkRequirements, nullifier: B256, expires_at: u64) -> Self {
+        Self {
+            min_amount: requirements.amount,
+            recipient: requirements.recipient,
+            chain_id: requirements.network.chain_id(),
+            token: requirements.token,
+            resource_hash: resource_hash(&requirements.resource),
+            nullifier,
+            expires_at,
+        }
+    }
+}
+
+/// keccak256 of a resource identifier, as committed in a proof
+pub fn resource_hash(resource: &str) -> B256 {
+    keccak256(resource.as_bytes())
+}
+
+/// A zero-knowledge proof of payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZkPaymentProof {
+    /// Proof system the proof is for, matching [`ZkProofVerifier::proof_system`]
+    pub proof_system: String,
+    pub statement: ZkPaymentStatement,
+    pub proof: Bytes,
+}
+
+impl ZkPaymentProof {
+    /// Encode as an `X-Payment-Proof` header value
+    pub fn to_header(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| X402Error::EncodingError(e.to_string()))?;
+        Ok(tagged(WireFormat::Json, json.as_bytes()))
+    }
+
+    /// Decode an `X-Payment-Proof` header value
+    pub fn from_header(header: impl AsRef<[u8]>) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let (format, bytes) = decode_header(header.as_ref(), &limits)?;
+        parse_payload(format, &bytes, &limits)
+    }
+}
+
+/// Verification backend for one proof system
+pub trait ZkProofVerifier: Send + Sync {
+    /// Name of the proof system, as listed in [`ZkRequirements::proof_systems`]
+    fn proof_system(&self) -> &str;
+
+    /// Whether `proof` proves `statement`
+    ///
+    /// Return `Ok(false)` for a proof that doesn't verify and `Err` only
+    /// if verification itself couldn't run.
+    fn verify(&self, statement: &ZkPaymentStatement, proof: &[u8]) -> Result<bool>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_header_roundtrip() {
+        let requirements = ZkRequirements {
+            proof_systems: vec!["groth16-bn254".to_string()],
+            amount: U256::from(10_000),
+            recipient: Address::repeat_byte(0x11),
+            network: Network::Base,
+            token: None,
+            resource: "/private".to_string(),
+        };
+        let statement = ZkPaymentStatement::for_requirements(&requirements, B256::repeat_byte(0x01), 2_000);
+        assert_eq!(statement.chain_id, 8453);
+        assert_eq!(statement.resource_hash, resource_hash("/private"));
+
+        let proof = ZkPaymentProof {
+            proof_system: "groth16-bn254".to_string(),
+            statement,
+            proof: Bytes::from_static(&[0xab; 8]),
+        };
+        let header = proof.to_header().unwrap();
+        assert_eq!(ZkPaymentProof::from_header(&header).unwrap(), proof);
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["statement"]["minAmount"], "10000");
+    }
+}
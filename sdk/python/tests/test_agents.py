@@ -0,0 +1,99 @@
+"""Tests for the function-calling tool."""
+
+import json
+
+import httpx
+import pytest
+from unittest.mock import AsyncMock
+
+from x402.agents import TOOL_NAME, PaidHTTPTool
+from x402.budget import Budget
+from x402.protocol import (
+    X402_PAYMENT_HEADER,
+    X402_REQUIREMENTS_HEADER,
+    decode_payment_header,
+    encode_requirements_header,
+)
+from x402.types import Network, PaymentRequirements
+
+PAYER = "0x2222222222222222222222222222222222222222"
+BASE_ETH = (8453, None)
+
+
+@pytest.fixture
+def mock_signer():
+    signer = AsyncMock()
+    signer.get_address.return_value = PAYER
+    signer.sign_payment.return_value = b"\x00" * 65
+    return signer
+
+
+def paid_api(price=1000):
+    """Mock API charging ``price`` for /premium; records paid amounts."""
+    paid = []
+    requirements = PaymentRequirements(
+        amount=price,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=Network.BASE,
+        resource="/premium",
+    )
+
+    def handler(request):
+        if request.url.path != "/premium":
+            return httpx.Response(200, text="free")
+        header = request.headers.get(X402_PAYMENT_HEADER)
+        if header is None:
+            return httpx.Response(
+                402,
+                headers={X402_REQUIREMENTS_HEADER: encode_requirements_header(requirements)},
+            )
+        paid.append(decode_payment_header(header).payment.amount)
+        return httpx.Response(200, json={"data": "premium"})
+
+    return httpx.MockTransport(handler), paid
+
+
+async def test_pays_within_budget(mock_signer):
+    transport, paid = paid_api(price=1000)
+    tool = PaidHTTPTool(mock_signer, budget=Budget(total=1500, tokens=[BASE_ETH]), transport=transport)
+
+    result = json.loads(await tool.ainvoke(json.dumps({"url": "https://api.example.com/premium"})))
+    assert result["status"] == 200
+    assert result["paid"] == "1000"
+    assert result["budgetRemaining"] == "500"
+    assert json.loads(result["body"]) == {"data": "premium"}
+    assert paid == [1000]
+
+    # The session budget no longer covers the price
+    result = json.loads(await tool.arun("https://api.example.com/premium"))
+    assert "budget" in result["error"]
+    assert paid == [1000]
+    assert tool.budget.spent == 1000
+
+
+async def test_free_resource_and_bad_arguments(mock_signer):
+    transport, paid = paid_api()
+    tool = PaidHTTPTool(mock_signer, budget=Budget(total=0, tokens=[BASE_ETH]), transport=transport)
+
+    result = json.loads(await tool.ainvoke({"url": "https://api.example.com/free"}))
+    assert (result["status"], result["paid"], result["body"]) == (200, None, "free")
+    assert "error" in json.loads(await tool.ainvoke("{not json"))
+    assert "error" in json.loads(await tool.ainvoke({"method": "GET"}))
+    mock_signer.sign_payment.assert_not_called()
+
+
+def test_sync_invoke(mock_signer):
+    transport, paid = paid_api(price=250)
+    tool = PaidHTTPTool(mock_signer, budget=Budget(per_payment=300, tokens=[BASE_ETH]), transport=transport)
+
+    result = json.loads(tool.invoke({"url": "https://api.example.com/premium"}))
+    assert result["paid"] == "250"
+    assert json.loads(tool.run("https://api.example.com/premium"))["status"] == 200
+    assert paid == [250, 250]
+
+
+def test_openai_schema(mock_signer):
+    schema = PaidHTTPTool(mock_signer).openai_schema()
+    assert schema["type"] == "function"
+    assert schema["function"]["name"] == TOOL_NAME
+    assert schema["function"]["parameters"]["required"] == ["url"]
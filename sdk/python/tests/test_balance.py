@@ -0,0 +1,104 @@
+"""Tests for pre-payment balance checks."""
+
+import json
+
+import httpx
+import pytest
+from unittest.mock import AsyncMock
+
+from x402.balance import PERMIT2_ADDRESS, RpcBalanceCheck, WouldBounce
+from x402.client import payment_header_for
+from x402.protocol import decode_payment_header
+from x402.types import Network, PaymentRequirements
+
+PAYER = "0x2222222222222222222222222222222222222222"
+USDC = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+
+
+def requirements(network=Network.BASE, token=USDC, amount=1000):
+    return PaymentRequirements(
+        amount=amount,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=network,
+        token=token,
+        resource="/premium",
+    )
+
+
+def node(native=0, token=0, allowance=0, eip3009=True):
+    """Mock JSON-RPC node; records the calls it answers."""
+    calls = []
+    
+    def handler(request):
+        rpc = json.loads(request.content)
+        calls.append(rpc["method"])
+        if rpc["method"] == "eth_getBalance":
+            return httpx.Response(200, json={"jsonrpc": "2.0", "id": rpc["id"], "result": hex(native)})
+        data = rpc["params"][0]["data"]
+        if data.startswith("0x70a08231"):
+            result = hex(token)
+        elif data.startswith("0xdd62ed3e"):
+            assert data.endswith(PERMIT2_ADDRESS.lower()[2:])
+            result = hex(allowance)
+        elif eip3009:
+            result = "0x" + "00" * 32
+        else:
+            return httpx.Response(200, json={"jsonrpc": "2.0", "id": rpc["id"], "error": {"message": "reverted"}})
+        return httpx.Response(200, json={"jsonrpc": "2.0", "id": rpc["id"], "result": result})
+    
+    return httpx.MockTransport(handler), calls
+
+
+@pytest.mark.asyncio
+async def test_token_balance_and_eip3009():
+    transport, calls = node(token=1000)
+    check = RpcBalanceCheck({Network.BASE: "https://rpc.example"}, transport=transport)
+    assert await check(requirements(), PAYER)
+    assert calls == ["eth_call", "eth_call"]
+    
+    transport, _ = node(token=999)
+    with pytest.raises(WouldBounce, match="token balance 999"):
+        await RpcBalanceCheck({8453: "https://rpc.example"}, transport=transport).check(requirements(), PAYER)
+    
+    transport, _ = node(token=1000, eip3009=False)
+    with pytest.raises(WouldBounce, match="EIP-3009"):
+        await RpcBalanceCheck({8453: "https://rpc.example"}, transport=transport).check(requirements(), PAYER)
+
+
+@pytest.mark.asyncio
+async def test_native_and_permit2():
+    transport, _ = node(native=10**18)
+    check = RpcBalanceCheck({8453: "https://rpc.example"}, transport=transport)
+    assert await check(requirements(token=None, amount=10**18), PAYER)
+    with pytest.raises(WouldBounce, match="native balance"):
+        await check.check(requirements(token=None, amount=10**18 + 1), PAYER)
+    
+    transport, _ = node(token=1000, allowance=500)
+    permit2 = RpcBalanceCheck({8453: "https://rpc.example"}, settlement="permit2", transport=transport)
+    with pytest.raises(WouldBounce, match="Permit2 allowance 500"):
+        await permit2.check(requirements(), PAYER)
+    
+    # Networks without an RPC URL aren't checked
+    assert await permit2(requirements(network=Network.POLYGON), PAYER)
+
+
+@pytest.mark.asyncio
+async def test_client_fails_fast_or_falls_back():
+    signer = AsyncMock()
+    signer.get_address.return_value = PAYER
+    signer.sign_payment.return_value = b"\x00" * 65
+    body = json.dumps({"accepts": [
+        requirements(Network.BASE).model_dump(),
+        requirements(Network.POLYGON).model_dump(),
+    ]}).encode()
+    
+    transport, _ = node(token=0)
+    broke = RpcBalanceCheck({Network.BASE: "https://base.example", Network.POLYGON: "https://polygon.example"},
+                            transport=transport)
+    with pytest.raises(WouldBounce):
+        await payment_header_for({}, signer, 1, body=body, balance_check=broke)
+    signer.sign_payment.assert_not_called()
+    
+    only_base_broke = RpcBalanceCheck({Network.BASE: "https://base.example"}, transport=transport)
+    header = await payment_header_for({}, signer, 1, body=body, balance_check=only_base_broke)
+    assert decode_payment_header(header).payment.chain_id == Network.POLYGON.chain_id
@@ -1,12 +1,16 @@
 """Tests for x402 client."""
 
+import json
+
 import pytest
 from unittest.mock import AsyncMock, MagicMock, patch
 import httpx
 
-from x402.client import X402Client
+from x402.client import X402Client, payment_header_for, sign_payment_header
 from x402.types import Network, PaymentRequirements
-from x402.protocol import encode_requirements_header, X402_REQUIREMENTS_HEADER
+from x402.protocol import decode_payment_header, encode_requirements_header, X402_REQUIREMENTS_HEADER
+
+from tests.test_types import load_test_vectors
 
 
 @pytest.fixture
@@ -98,7 +102,7 @@ class TestClientPaymentFlow:
         """Test 402 with amount exceeding max_amount doesn't pay."""
         requirements = PaymentRequirements(
             amount=10000,  # Exceeds max_amount
-            recipient="0x0000000000000000000000000000000000000000",
+            recipient="0x1111111111111111111111111111111111111111",
             network=Network.BASE,
             resource="/api/data",
         )
@@ -115,3 +119,72 @@ class TestClientPaymentFlow:
                 
                 assert response.status_code == 402
                 mock_signer.sign_payment.assert_not_called()
+
+
+class TestNetworkFallback:
+    """Tests for choosing among several advertised options."""
+    
+    @staticmethod
+    def body():
+        accepts = [
+            PaymentRequirements(
+                amount=1000,
+                recipient="0x1111111111111111111111111111111111111111",
+                network=network,
+                resource="/api/data",
+            ).model_dump()
+            for network in (Network.BASE, Network.POLYGON)
+        ]
+        return json.dumps({"x402Version": 1, "accepts": accepts}).encode()
+    
+    @staticmethod
+    def chain_id(header):
+        return decode_payment_header(header).payment.chain_id
+    
+    @pytest.mark.asyncio
+    async def test_preference_order(self, mock_signer):
+        header = await payment_header_for({}, mock_signer, 1, body=self.body())
+        assert self.chain_id(header) == Network.BASE.chain_id
+        
+        preferred = [Network.POLYGON, "base"]
+        header = await payment_header_for({}, mock_signer, 1, body=self.body(), networks=preferred)
+        assert self.chain_id(header) == Network.POLYGON.chain_id
+        
+        assert await payment_header_for({}, mock_signer, 1, body=self.body(), networks=[Network.ARBITRUM]) is None
+    
+    @pytest.mark.asyncio
+    async def test_falls_back_on_balance_check(self, mock_signer):
+        async def only_polygon(requirements, payer):
+            return requirements.network == Network.POLYGON.value
+        
+        header = await payment_header_for({}, mock_signer, 1, body=self.body(), balance_check=only_polygon)
+        assert self.chain_id(header) == Network.POLYGON.chain_id
+    
+    @pytest.mark.asyncio
+    async def test_falls_back_on_signer_error(self, mock_signer):
+        mock_signer.sign_payment.side_effect = [RuntimeError("no key for chain"), b"\x00" * 65]
+        header = await payment_header_for({}, mock_signer, 1, body=self.body())
+        assert self.chain_id(header) == Network.POLYGON.chain_id
+        
+        mock_signer.sign_payment.side_effect = RuntimeError("signer offline")
+        with pytest.raises(RuntimeError):
+            await payment_header_for({}, mock_signer, 1, body=self.body())
+
+
+@pytest.mark.asyncio
+async def test_sign_payment_header_matches_test_vectors(mock_signer):
+    """A payment signed from the vector's requirements has the Rust core's message."""
+    for vector in load_test_vectors():
+        if vector["expected"]["result"] != "valid":
+            continue
+        payload = vector["payload"]
+        await sign_payment_header(
+            PaymentRequirements(**vector["requirements"]),
+            mock_signer,
+            payload["payer"],
+            payload["nonce"],
+            expires_at=payload["expires_at"],
+            invoice_id=payload.get("invoice_id"),
+        )
+        signed = mock_signer.sign_payment.call_args.args[0]
+        assert "0x" + signed.message_hash().hex() == vector["message_hash"], vector["name"]
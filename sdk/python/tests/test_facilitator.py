@@ -0,0 +1,41 @@
+"""Tests for facilitator discovery."""
+
+import httpx
+
+from x402.facilitator import FacilitatorClient
+from x402.types import Network, PaymentRequirements, SupportedPaymentKind
+
+USDC = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+
+
+def facilitator_api():
+    def handler(request):
+        assert request.url.path == "/facilitator/supported"
+        assert request.headers["X-Api-Key"] == "x402_test"
+        return httpx.Response(
+            200,
+            json={
+                "kinds": [
+                    {"x402Version": 1, "scheme": "exact", "network": "base", "token": USDC.lower()},
+                    {"x402Version": 1, "scheme": "upto", "network": "basesepolia", "token": None},
+                ]
+            },
+        )
+
+    return httpx.MockTransport(handler)
+
+
+async def test_supported():
+    async with FacilitatorClient(
+        "https://f.example.com/facilitator/", api_key="x402_test", transport=facilitator_api()
+    ) as client:
+        kinds = await client.supported()
+        assert kinds[0] == SupportedPaymentKind(scheme="exact", network="base", token=USDC.lower())
+
+        requirements = PaymentRequirements(
+            amount=10_000, recipient="0x" + "11" * 20, network=Network.BASE, token=USDC, resource="/x"
+        )
+        assert await client.supports(requirements)
+        assert not await client.supports(requirements.model_copy(update={"scheme": "upto"}))
+        native = requirements.model_copy(update={"network": Network.BASE_SEPOLIA, "token": None, "scheme": "upto"})
+        assert await client.supports(native)
@@ -0,0 +1,201 @@
+"""Tests for the FastAPI integration."""
+
+import pytest
+
+pytest.importorskip("fastapi")
+
+from fastapi import Depends, FastAPI
+from fastapi.testclient import TestClient
+
+import x402.fastapi
+from x402.cors import CorsPolicy
+from x402.fastapi import RequirePayment, X402Middleware, add_x402_cors, get_payer, get_tenant
+from x402.protocol import decode_payment_header, decode_requirements_header, encode_payment_header
+from x402.quota import FreeTier
+from x402.tenant import RouteTable, TenantRoute
+from x402.types import Network, PaymentPayload, PaymentRequirements, SignedPayment
+from x402.verify import InvalidSignatureError
+
+PAYER = "0x2222222222222222222222222222222222222222"
+
+
+def paid(nonce: int = 1) -> str:
+    """An encoded payment from PAYER; the fake verifier accepts any that decodes."""
+    return encode_payment_header(SignedPayment(
+        payment=PaymentPayload(
+            amount=1000,
+            recipient="0x1111111111111111111111111111111111111111",
+            payer=PAYER,
+            chain_id=8453,
+            resource="/premium",
+            nonce=nonce,
+            expires_at=4_000_000_000,
+        ),
+        signature=b"\x01" * 65,
+    ))
+
+
+@pytest.fixture
+def requirements():
+    return PaymentRequirements(
+        amount=1000,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=Network.BASE,
+        resource="/premium",
+    )
+
+
+@pytest.fixture
+def fake_verify(monkeypatch):
+    """Accept any payment header that decodes."""
+    async def verify(header, requirements, current_time=None):
+        try:
+            return decode_payment_header(header).payment.payer
+        except ValueError:
+            raise InvalidSignatureError("bad signature")
+    monkeypatch.setattr(x402.fastapi, "verify_payment_async", verify)
+
+
+def test_dependency_requires_payment(requirements, fake_verify):
+    app = FastAPI()
+    
+    @app.get("/premium")
+    async def premium(payer: str = Depends(RequirePayment(requirements))):
+        return {"payer": payer}
+    
+    client = TestClient(app)
+    
+    response = client.get("/premium")
+    assert response.status_code == 402
+    quoted = decode_requirements_header(response.headers["X-Payment-Requirements"])
+    assert quoted.amount == 1000
+    assert "error" not in response.json()["detail"]
+    
+    response = client.get("/premium", headers={"X-Payment": "forged"})
+    assert response.status_code == 402
+    assert response.json()["detail"]["error"] == "bad signature"
+    
+    response = client.get("/premium", headers={"X-Payment": paid()})
+    assert response.status_code == 200
+    assert response.json() == {"payer": PAYER}
+
+
+def test_middleware_gates_priced_paths(requirements, fake_verify):
+    app = FastAPI()
+    app.add_middleware(
+        X402Middleware,
+        pricing=lambda request: requirements if request.url.path == "/premium" else None,
+    )
+    
+    @app.get("/premium")
+    async def premium(payer=Depends(get_payer)):
+        return {"payer": payer}
+    
+    @app.get("/free")
+    async def free(payer=Depends(get_payer)):
+        return {"payer": payer}
+    
+    client = TestClient(app)
+    
+    assert client.get("/free").json() == {"payer": None}
+    response = client.get("/premium")
+    assert response.status_code == 402
+    assert response.json()["accepts"][0]["amount"] == "1000"
+    assert client.get("/premium", headers={"X-Payment": paid()}).json() == {"payer": PAYER}
+    
+    page = client.get("/premium", headers={"Accept": "text/html,*/*;q=0.8"})
+    assert page.status_code == 402
+    assert page.headers["content-type"].startswith("text/html")
+    assert "X-Payment-Requirements" in page.headers
+    assert "ethereum:0x1111111111111111111111111111111111111111@8453?value=1000" in page.text
+
+
+def test_replayed_payment_is_refused(requirements, fake_verify):
+    app = FastAPI()
+    app.add_middleware(X402Middleware, pricing=lambda request: requirements if request.url.path == "/gated" else None)
+    
+    @app.get("/gated")
+    async def gated(payer=Depends(get_payer)):
+        return {"payer": payer}
+    
+    @app.get("/premium")
+    async def premium(payer: str = Depends(RequirePayment(requirements))):
+        return {"payer": payer}
+    
+    client = TestClient(app)
+    for path in ("/gated", "/premium"):
+        header = paid(nonce=7)
+        assert client.get(path, headers={"X-Payment": header}).status_code == 200
+        replay = client.get(path, headers={"X-Payment": header})
+        assert replay.status_code == 402, path
+        assert "payment nonce already used" in replay.text
+        assert client.get(path, headers={"X-Payment": paid(nonce=8)}).status_code == 200
+
+
+def test_middleware_routes_tenants(requirements, fake_verify):
+    alice = TenantRoute("alice", "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1", 5000)
+    app = FastAPI()
+    app.add_middleware(
+        X402Middleware,
+        pricing=lambda request: requirements,
+        resolver=RouteTable([("/premium", alice)]),
+    )
+    
+    @app.get("/premium")
+    async def premium(tenant=Depends(get_tenant)):
+        return {"tenant": tenant}
+    
+    client = TestClient(app)
+    
+    assert client.get("/other").status_code == 404
+    assert client.get("/premium", headers={"X-Tenant": "bob"}).status_code == 404
+    response = client.get("/premium")
+    assert response.status_code == 402
+    quoted = decode_requirements_header(response.headers["X-Payment-Requirements"])
+    assert (quoted.recipient, quoted.amount) == (alice.recipient, 5000)
+    assert client.get("/premium", headers={"X-Payment": paid(), "X-Tenant": "alice"}).json() == {"tenant": "alice"}
+
+
+def test_middleware_free_tier_by_address(requirements, fake_verify):
+    app = FastAPI()
+    app.add_middleware(
+        X402Middleware,
+        pricing=lambda request: requirements,
+        free_tier=FreeTier(limit=1, by="address"),
+    )
+    
+    @app.get("/premium")
+    async def premium(payer=Depends(get_payer)):
+        return {"payer": payer}
+    
+    client = TestClient(app)
+    address = {"X-Payer-Address": PAYER}
+    
+    assert client.get("/premium").status_code == 402
+    assert client.get("/premium", headers=address).json() == {"payer": None}
+    assert client.get("/premium", headers=address).status_code == 402
+    assert client.get("/premium", headers={**address, "X-Payment": paid()}).json() == {"payer": PAYER}
+
+
+def test_cors_exposes_requirements(requirements, fake_verify):
+    app = FastAPI()
+    app.add_middleware(X402Middleware, pricing=lambda request: requirements)
+    add_x402_cors(app, CorsPolicy(allow_origins=["https://app.example"]))
+    
+    @app.get("/premium")
+    async def premium(payer=Depends(get_payer)):
+        return {"payer": payer}
+    
+    client = TestClient(app)
+    origin = {"Origin": "https://app.example"}
+    
+    preflight = client.options(
+        "/premium",
+        headers={**origin, "Access-Control-Request-Method": "GET", "Access-Control-Request-Headers": "X-Payment"},
+    )
+    assert preflight.status_code == 200
+    assert "x-payment" in preflight.headers["access-control-allow-headers"].lower()
+    
+    response = client.get("/premium", headers=origin)
+    assert response.status_code == 402
+    assert "X-Payment-Requirements" in response.headers["access-control-expose-headers"]
@@ -0,0 +1,101 @@
+"""Tests for the httpx and requests payment hooks."""
+
+import pytest
+from unittest.mock import AsyncMock
+import httpx
+
+from x402.httpx import X402Auth
+from x402.types import Network, PaymentRequirements
+from x402.protocol import (
+    encode_requirements_header,
+    decode_payment_header,
+    X402_PAYMENT_HEADER,
+    X402_REQUIREMENTS_HEADER,
+)
+
+PAYER = "0x2222222222222222222222222222222222222222"
+
+
+@pytest.fixture
+def mock_signer():
+    signer = AsyncMock()
+    signer.get_address.return_value = PAYER
+    signer.sign_payment.return_value = b"\x01" * 65
+    return signer
+
+
+@pytest.fixture
+def requirements_header():
+    return encode_requirements_header(PaymentRequirements(
+        amount=1000,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=Network.BASE,
+        resource="/premium",
+    ))
+
+
+def paywall(requirements_header):
+    """Mock server: 402 until a payment header arrives, then echo the payer."""
+    def handler(request):
+        payment = request.headers.get(X402_PAYMENT_HEADER)
+        if payment is None:
+            return httpx.Response(402, headers={X402_REQUIREMENTS_HEADER: requirements_header})
+        return httpx.Response(200, json={"payer": decode_payment_header(payment).payment.payer})
+    return handler
+
+
+def test_httpx_sync_auth_pays(mock_signer, requirements_header):
+    transport = httpx.MockTransport(paywall(requirements_header))
+    with httpx.Client(transport=transport, auth=X402Auth(mock_signer)) as client:
+        response = client.get("https://api.example.com/premium")
+    assert response.status_code == 200
+    assert response.json() == {"payer": PAYER}
+
+
+def test_httpx_auth_leaves_paid_responses_streamed(mock_signer, requirements_header):
+    def handler(request):
+        if request.headers.get(X402_PAYMENT_HEADER) is None:
+            return httpx.Response(402, headers={X402_REQUIREMENTS_HEADER: requirements_header})
+        return httpx.Response(200, content=iter([b"chunk-1", b"chunk-2"]))
+    
+    transport = httpx.MockTransport(handler)
+    with httpx.Client(transport=transport, auth=X402Auth(mock_signer)) as client:
+        with client.stream("GET", "https://api.example.com/premium") as response:
+            assert response.status_code == 200
+            assert not response.is_stream_consumed
+            assert list(response.iter_bytes()) == [b"chunk-1", b"chunk-2"]
+
+
+@pytest.mark.asyncio
+async def test_httpx_async_auth_respects_max_amount(mock_signer, requirements_header):
+    transport = httpx.MockTransport(paywall(requirements_header))
+    async with httpx.AsyncClient(transport=transport, auth=X402Auth(mock_signer)) as client:
+        assert (await client.get("https://api.example.com/premium")).status_code == 200
+    
+    auth = X402Auth(mock_signer, max_amount=999)
+    async with httpx.AsyncClient(transport=transport, auth=auth) as client:
+        assert (await client.get("https://api.example.com/premium")).status_code == 402
+
+
+def test_requests_adapter_pays(mock_signer, requirements_header, monkeypatch):
+    requests = pytest.importorskip("requests")
+    from requests.adapters import HTTPAdapter
+    from x402.requests import X402Adapter
+    
+    handler = paywall(requirements_header)
+    
+    def send(self, request, **kwargs):
+        reply = handler(httpx.Request(request.method, request.url, headers=dict(request.headers)))
+        response = requests.Response()
+        response.status_code = reply.status_code
+        response.headers.update(reply.headers)
+        response._content = reply.content
+        return response
+    
+    monkeypatch.setattr(HTTPAdapter, "send", send)
+    session = requests.Session()
+    session.mount("https://", X402Adapter(mock_signer))
+    
+    response = session.get("https://api.example.com/premium")
+    assert response.status_code == 200
+    assert response.json() == {"payer": PAYER}
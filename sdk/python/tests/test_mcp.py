@@ -0,0 +1,158 @@
+"""Tests for the MCP server."""
+
+import json
+
+import httpx
+import pytest
+from unittest.mock import AsyncMock
+
+from x402.budget import Budget, BudgetExceeded
+from x402.mcp import TOOLS, X402McpServer
+from x402.protocol import (
+    X402_PAYMENT_HEADER,
+    X402_REQUIREMENTS_HEADER,
+    decode_payment_header,
+    encode_requirements_header,
+)
+from x402.types import Network, PaymentRequirements
+
+PAYER = "0x2222222222222222222222222222222222222222"
+USDC = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+BASE_ETH = (8453, None)
+
+
+@pytest.fixture
+def mock_signer():
+    signer = AsyncMock()
+    signer.get_address.return_value = PAYER
+    signer.sign_payment.return_value = b"\x00" * 65
+    return signer
+
+
+def paid_api(price=1000):
+    """Mock API charging ``price`` for /premium; records paid amounts."""
+    paid = []
+    requirements = PaymentRequirements(
+        amount=price,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=Network.BASE,
+        resource="/premium",
+    )
+
+    def handler(request):
+        if request.url.path != "/premium":
+            return httpx.Response(200, text="free")
+        header = request.headers.get(X402_PAYMENT_HEADER)
+        if header is None:
+            return httpx.Response(
+                402,
+                headers={X402_REQUIREMENTS_HEADER: encode_requirements_header(requirements)},
+            )
+        paid.append(decode_payment_header(header).payment.amount)
+        return httpx.Response(200, json={"data": "premium"})
+
+    return httpx.MockTransport(handler), paid
+
+
+async def call(server, name, arguments, request_id=1):
+    params = {"name": name, "arguments": arguments}
+    reply = await server.handle({"jsonrpc": "2.0", "id": request_id, "method": "tools/call", "params": params})
+    result = reply["result"]
+    text = result["content"][0]["text"]
+    return result["isError"], text if result["isError"] else json.loads(text)
+
+
+def test_budget_limits():
+    budget = Budget(total=2500, per_payment=1000, tokens=[(8453, USDC)])
+    assert budget.max_payment() == 1000
+    budget.charge(1000, 8453, USDC)
+    budget.charge(1000, 8453, USDC.lower())
+    assert (budget.remaining(), budget.max_payment()) == (500, 500)
+    with pytest.raises(BudgetExceeded):
+        budget.charge(501, 8453, USDC)
+    # Amounts in other tokens aren't comparable, so they are refused
+    with pytest.raises(BudgetExceeded):
+        budget.charge(1, 8453, None)
+    with pytest.raises(BudgetExceeded):
+        budget.charge(1, 1, USDC)
+    assert Budget().max_payment() is None
+    with pytest.raises(ValueError):
+        Budget(total=2500)
+
+
+async def test_protocol_messages(mock_signer):
+    server = X402McpServer(mock_signer)
+    init = await server.handle({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}})
+    assert init["result"]["capabilities"] == {"tools": {}}
+    assert await server.handle({"jsonrpc": "2.0", "method": "notifications/initialized"}) is None
+    listed = await server.handle({"jsonrpc": "2.0", "id": 2, "method": "tools/list"})
+    assert [tool["name"] for tool in listed["result"]["tools"]] == [tool["name"] for tool in TOOLS]
+    unknown = await server.handle({"jsonrpc": "2.0", "id": 3, "method": "resources/list"})
+    assert unknown["error"]["code"] == -32601
+    await server.close()
+
+
+async def test_fetch_within_budget(mock_signer):
+    transport, paid = paid_api(price=1000)
+    server = X402McpServer(mock_signer, budget=Budget(total=1500, tokens=[BASE_ETH]), transport=transport)
+
+    is_error, quote = await call(server, "quote_price", {"url": "https://api.example/premium"})
+    assert not is_error
+    assert quote["options"][0]["amount"] == "1000" and quote["options"][0]["withinBudget"]
+
+    is_error, fetched = await call(server, "fetch_paid_resource", {"url": "https://api.example/premium"})
+    assert not is_error
+    assert (fetched["status"], fetched["paid"]) == (200, "1000")
+    assert json.loads(fetched["body"]) == {"data": "premium"}
+    assert paid == [1000]
+
+    # 500 left: the next payment is refused before signing
+    is_error, message = await call(server, "fetch_paid_resource", {"url": "https://api.example/premium"})
+    assert is_error and "budget" in message
+    assert paid == [1000]
+
+    _, free = await call(server, "fetch_paid_resource", {"url": "https://api.example/free"})
+    assert (free["status"], free["paid"]) == (200, None)
+
+    _, report = await call(server, "get_spend_report", {})
+    assert (report["spent"], report["remaining"]) == ("1000", "500")
+    assert [payment["amount"] for payment in report["payments"]] == ["1000"]
+    await server.close()
+
+
+async def test_budget_refuses_other_tokens(mock_signer):
+    # The API charges in ETH; amounts of a USDC budget say nothing about ETH
+    transport, paid = paid_api(price=1000)
+    budget = Budget(total=1_000_000, tokens=[(8453, USDC)])
+    server = X402McpServer(mock_signer, budget=budget, transport=transport)
+
+    _, quote = await call(server, "quote_price", {"url": "https://api.example/premium"})
+    assert not quote["options"][0]["withinBudget"]
+    is_error, message = await call(server, "fetch_paid_resource", {"url": "https://api.example/premium"})
+    assert is_error and "token" in message
+    assert paid == [] and budget.spent == 0
+    await server.close()
+
+
+async def test_payment_not_forwarded_across_origins(mock_signer):
+    transport, paid = paid_api(price=1000)
+    seen = {}
+
+    def handler(request):
+        if request.url.path == "/moved":
+            return httpx.Response(302, headers={"Location": "https://api.example/premium"})
+        if request.url.host == "cdn.example":
+            seen[request.url.path] = request.headers.get(X402_PAYMENT_HEADER)
+            return httpx.Response(200, text="elsewhere")
+        if request.url.path == "/premium" and X402_PAYMENT_HEADER in request.headers:
+            paid.append(decode_payment_header(request.headers[X402_PAYMENT_HEADER]).payment.amount)
+            return httpx.Response(302, headers={"Location": "https://cdn.example/premium"})
+        return transport.handler(request)
+
+    budget = Budget(total=5000, tokens=[BASE_ETH])
+    server = X402McpServer(mock_signer, budget=budget, transport=httpx.MockTransport(handler))
+    _, fetched = await call(server, "fetch_paid_resource", {"url": "https://api.example/moved"})
+    assert (fetched["status"], fetched["body"]) == (200, "elsewhere")
+    assert paid == [1000]
+    assert seen == {"/premium": None}
+    await server.close()
@@ -0,0 +1,47 @@
+"""Tests for the browser paywall page."""
+
+from x402.paywall import payment_uri, prefers_html, render_paywall
+from x402.types import Network, PaymentRequirements, Split
+
+USDC = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+
+RECIPIENT = "0x1111111111111111111111111111111111111111"
+
+REQUIREMENTS = PaymentRequirements(
+    amount=10000,
+    recipient=RECIPIENT,
+    network=Network.BASE,
+    token=USDC,
+    resource="/a?b=<c>",
+    description="<script>alert(1)</script>",
+)
+
+
+def test_prefers_html():
+    assert prefers_html("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+    assert not prefers_html("*/*")
+    assert not prefers_html("application/json")
+    assert not prefers_html("application/json, text/html;q=0.5")
+    assert not prefers_html("text/html;q=0, */*")
+    assert prefers_html("text/*, application/json;q=0.2")
+    assert not prefers_html("")
+    assert not prefers_html(None)
+
+
+def test_payment_uri():
+    transfer = f"ethereum:{USDC}@8453/transfer?address={RECIPIENT}&uint256=10000"
+    assert payment_uri(REQUIREMENTS) == transfer
+    native = REQUIREMENTS.model_copy(update={"token": None})
+    assert payment_uri(native) == f"ethereum:{RECIPIENT}@8453?value=10000"
+    split = REQUIREMENTS.model_copy(update={"splits": [Split(recipient=USDC, share=10000)]})
+    assert payment_uri(split) is None
+
+
+def test_render_paywall():
+    page = render_paywall(REQUIREMENTS)
+    assert "/a?b=&lt;c&gt;" in page
+    assert "<script>alert" not in page
+    assert 'role="alert"' not in page
+    assert f'<a href="ethereum:{USDC}@8453/transfer?address={RECIPIENT}&amp;uint256=10000"' in page
+    assert "<code>X-Payment</code>" in page
+    assert "Payment rejected: expired" in render_paywall(REQUIREMENTS, "expired")
@@ -0,0 +1,136 @@
+"""Tests for bulk prepayment."""
+
+import httpx
+import pytest
+from unittest.mock import AsyncMock
+
+from x402.prepay import X402_CREDIT_HEADER, BulkPayer, presign_batch
+from x402.protocol import (
+    X402_PAYMENT_HEADER,
+    X402_REQUIREMENTS_HEADER,
+    decode_payment_header,
+    encode_requirements_header,
+)
+from x402.types import Network, PaymentRequirements
+
+PAYER = "0x2222222222222222222222222222222222222222"
+RECIPIENT = "0x1111111111111111111111111111111111111111"
+
+
+@pytest.fixture
+def mock_signer():
+    signer = AsyncMock()
+    signer.get_address.return_value = PAYER
+    signer.sign_payment.return_value = b"\x00" * 65
+    return signer
+
+
+def pages_api(price=100, credit=0):
+    """Mock site charging ``price`` (or ``price(path)``) per /pages/ page, or
+    accepting ``credit`` requests with the credential sold at /credit;
+    records what paid each request."""
+    requests = []
+    state = {"credit": credit}
+
+    def handler(request):
+        path = request.url.raw_path.decode()
+        amount = price(path) if callable(price) else price
+        requirements = PaymentRequirements(amount=amount, recipient=RECIPIENT, network=Network.BASE, resource=path)
+        if request.headers.get(X402_CREDIT_HEADER) == "credential" and state["credit"] > 0:
+            state["credit"] -= 1
+            requests.append((path, "credit"))
+            return httpx.Response(200, text=path)
+        header = request.headers.get(X402_PAYMENT_HEADER)
+        if header is None:
+            return httpx.Response(
+                402,
+                headers={X402_REQUIREMENTS_HEADER: encode_requirements_header(requirements)},
+            )
+        payment = decode_payment_header(header).payment
+        assert payment.resource == path
+        if payment.amount < amount:
+            return httpx.Response(402, headers={X402_REQUIREMENTS_HEADER: encode_requirements_header(requirements)})
+        requests.append((path, payment.nonce))
+        if path == "/credit":
+            return httpx.Response(200, headers={X402_CREDIT_HEADER: "credential"})
+        return httpx.Response(200, text=path)
+
+    return httpx.MockTransport(handler), requests
+
+
+async def test_presign_batch(mock_signer):
+    requirements = PaymentRequirements(amount=100, recipient=RECIPIENT, network=Network.BASE, resource="/pages/0")
+    urls = [f"https://example.com/pages/{i}?v=1" for i in range(3)]
+
+    headers = await presign_batch(requirements, urls + urls[:1], mock_signer, 7, expires_at=2_000_000_000)
+    payments = [decode_payment_header(headers[url]).payment for url in urls]
+    assert [payment.resource for payment in payments] == [f"/pages/{i}?v=1" for i in range(3)]
+    assert [payment.nonce for payment in payments] == [7, 8, 9]
+    assert {payment.expires_at for payment in payments} == {2_000_000_000}
+    assert mock_signer.sign_payment.call_count == 3
+
+
+async def test_prepaid_crawl(mock_signer):
+    transport, requests = pages_api()
+    urls = [f"https://example.com/pages/{i}" for i in range(5)]
+    async with BulkPayer(mock_signer, max_amount=100, transport=transport) as payer:
+        assert await payer.prepay(urls) == 5
+        assert payer.prepaid == 5
+        # Only the quote went unpaid; every page is paid on its first request
+        responses = {url: response async for url, response in payer.crawl(urls)}
+        assert {response.status_code for response in responses.values()} == {200}
+        assert payer.prepaid == 0
+        assert sorted(path for path, _ in requests) == sorted(f"/pages/{i}" for i in range(5))
+
+        # Not prepaid: paid the usual way
+        assert (await payer.get("https://example.com/pages/9")).status_code == 200
+        assert requests[-1][0] == "/pages/9"
+
+
+async def test_prepay_quotes_each_url(mock_signer):
+    transport, requests = pages_api(price=lambda path: 300 if path.endswith("/2") else 100)
+    urls = [f"https://example.com/pages/{i}" for i in range(4)]
+    async with BulkPayer(mock_signer, transport=transport) as payer:
+        assert await payer.prepay(urls) == 4
+        amounts = {url: decode_payment_header(header).payment.amount for url, header in payer._prepaid.items()}
+        assert amounts == {url: 300 if url.endswith("/2") else 100 for url in urls}
+        async for _, response in payer.crawl(urls):
+            assert response.status_code == 200
+    # Each page was paid once, by its prepayment
+    assert len(requests) == 4
+
+
+async def test_prepay_over_limit(mock_signer):
+    transport, _ = pages_api(price=500)
+    async with BulkPayer(mock_signer, max_amount=100, transport=transport) as payer:
+        with pytest.raises(ValueError):
+            await payer.prepay(["https://example.com/pages/1"])
+    mock_signer.sign_payment.assert_not_called()
+
+
+async def test_session_credential(mock_signer):
+    transport, requests = pages_api(credit=2)
+    async with BulkPayer(mock_signer, transport=transport) as payer:
+        credential = await payer.buy_session("https://example.com/pages/", "https://example.com/credit")
+        assert credential == "credential"
+        for i in range(3):
+            response = await payer.get(f"https://example.com/pages/{i}")
+            assert response.status_code == 200
+    # Two pages on credit, then the credit ran out and the third was paid
+    assert [paid for _, paid in requests[1:3]] == ["credit", "credit"]
+    assert requests[3][0] == "/pages/2" and requests[3][1] != "credit"
+
+
+async def test_session_prefix_matches_origin_and_segments(mock_signer):
+    transport, _ = pages_api(credit=10)
+    async with BulkPayer(mock_signer, transport=transport) as payer:
+        await payer.buy_session("https://example.com/pages", "https://example.com/credit")
+        assert payer._session_for("https://example.com/pages") == "credential"
+        assert payer._session_for("https://example.com/pages/1?v=2") == "credential"
+        for url in [
+            "https://example.com/pages-archive/1",
+            "http://example.com/pages/1",
+            "https://example.com:8443/pages/1",
+            "https://example.com.evil.test/pages/1",
+        ]:
+            assert payer._session_for(url) is None, url
@@ -12,7 +12,7 @@ def test_requirements_roundtrip():
     """Test encoding and decoding requirements."""
     requirements = PaymentRequirements(
         amount=1000000,
-        recipient="0x0000000000000000000000000000000000000000",
+        recipient="0x1111111111111111111111111111111111111111",
         network=Network.BASE,
         resource="/api/test",
         description="Test payment",
@@ -41,3 +41,17 @@ def test_invalid_header():
         # Valid base64 but not JSON
         import base64
         decode_requirements_header(base64.b64encode(b"not json").decode())
+
+
+def test_large_amount_roundtrip():
+    """Test 18-decimal amounts beyond 64 bits survive encoding."""
+    requirements = PaymentRequirements(
+        amount=25 * 10**18,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=Network.ETHEREUM,
+        resource="/api/test",
+    )
+    
+    decoded = decode_requirements_header(encode_requirements_header(requirements))
+    
+    assert decoded.amount == 25 * 10**18
@@ -0,0 +1,49 @@
+"""Tests for free-tier quotas."""
+
+import pytest
+
+from x402.quota import FreeTier, MemoryQuotaStore
+
+ADDRESS = "0x2222222222222222222222222222222222222222"
+
+
+def test_memory_store_windows():
+    now = [1000.0]
+    store = MemoryQuotaStore(clock=lambda: now[0])
+    assert [store.increment("a", 60) for _ in range(3)] == [1, 2, 3]
+    assert store.increment("b", 60) == 1
+    now[0] += 60
+    assert store.increment("a", 60) == 1
+    assert store.increment("c", None) == 1
+    now[0] += 10**9
+    assert store.increment("c", None) == 2
+
+
+def test_memory_store_prunes_expired_windows():
+    now = [1000.0]
+    store = MemoryQuotaStore(clock=lambda: now[0])
+    store.increment("a", 60)
+    store.increment("b", 120)
+    store.increment("c", None)
+    now[0] += 60
+    store.increment("d", 60)
+    assert set(store._counts) == {"b", "c", "d"}
+    now[0] += 60
+    store.increment("d", 60)
+    assert set(store._counts) == {"c", "d"}
+    assert [key for _, key, _ in store._ends] == ["d"]
+
+
+def test_free_tier_by_address():
+    free_tier = FreeTier(limit=1, by="address")
+    assert not free_tier.allow("203.0.113.7")
+    assert not free_tier.allow("203.0.113.7", "not-an-address")
+    assert free_tier.allow("203.0.113.7", ADDRESS.upper().replace("0X", "0x"))
+    assert not free_tier.allow("198.51.100.1", ADDRESS)
+
+
+def test_free_tier_rejects_bad_config():
+    with pytest.raises(ValueError):
+        FreeTier(limit=1, by="cookie")
+    with pytest.raises(ValueError):
+        FreeTier(limit=-1)
@@ -0,0 +1,104 @@
+"""Tests for client-side settlement receipt storage."""
+
+import base64
+import json
+
+import httpx
+import pytest
+from unittest.mock import AsyncMock
+
+from x402.httpx import X402Auth
+from x402.protocol import (
+    decode_payment_header,
+    decode_payment_response_header,
+    encode_payment_header,
+    encode_requirements_header,
+    X402_PAYMENT_HEADER,
+    X402_PAYMENT_RESPONSE_HEADER,
+    X402_REQUIREMENTS_HEADER,
+)
+from x402.receipts import SqliteReceiptStore, StoredReceipt, record_receipt
+from x402.types import Network, PaymentPayload, PaymentRequirements, SettlementReceipt, SignedPayment
+
+PAYER = "0x2222222222222222222222222222222222222222"
+RECIPIENT = "0x1111111111111111111111111111111111111111"
+TX = "0x" + "ab" * 32
+
+
+def response_header(payer: str, nonce: int) -> str:
+    """X-Payment-Response value as the Rust core writes it."""
+    receipt = {"payer": payer, "chainId": 8453, "nonce": nonce, "amount": "1000", "transactionHash": TX}
+    return "x402.v2.json." + base64.b64encode(json.dumps(receipt).encode()).decode()
+
+
+def stored(resource: str, recipient: str, paid_at: int, nonce: int) -> StoredReceipt:
+    receipt = SettlementReceipt(payer=PAYER, chain_id=8453, nonce=nonce, amount=1000, transaction_hash=TX)
+    return StoredReceipt(receipt, resource, recipient, None, f"https://api.example.com{resource}", paid_at)
+
+
+def test_decode_payment_response_header():
+    receipt = decode_payment_response_header(response_header(PAYER, 7))
+    assert (receipt.payer, receipt.chain_id, receipt.nonce, receipt.amount) == (PAYER, 8453, 7, 1000)
+    assert receipt.model_dump(by_alias=True)["transactionHash"] == TX
+    with pytest.raises(ValueError):
+        decode_payment_response_header("not base64 json")
+
+
+def test_sqlite_store_queries():
+    store = SqliteReceiptStore(":memory:")
+    store.save(stored("/a", RECIPIENT, 100, 1))
+    store.save(stored("/b", RECIPIENT, 200, 2))
+    store.save(stored("/a", "0x3333333333333333333333333333333333333333", 300, 3))
+    store.save(stored("/a", RECIPIENT, 100, 1))
+
+    assert [s.receipt.nonce for s in store.query()] == [1, 2, 3]
+    assert [s.receipt.nonce for s in store.query(resource="/a")] == [1, 3]
+    assert [s.receipt.nonce for s in store.query(recipient=RECIPIENT.upper().replace("0X", "0x"))] == [1, 2]
+    assert [s.receipt.nonce for s in store.query(since=150, until=250)] == [2]
+    assert store.query(resource="/a", since=150)[0].recipient.endswith("3333")
+    assert store.query()[0] == stored("/a", RECIPIENT, 100, 1)
+
+
+@pytest.mark.asyncio
+async def test_auth_records_receipts():
+    signer = AsyncMock()
+    signer.get_address.return_value = PAYER
+    signer.sign_payment.return_value = b"\x01" * 65
+    requirements = encode_requirements_header(PaymentRequirements(
+        amount=1000, recipient=RECIPIENT, network=Network.BASE, resource="/premium",
+    ))
+
+    def handler(request):
+        payment = request.headers.get(X402_PAYMENT_HEADER)
+        if payment is None:
+            return httpx.Response(402, headers={X402_REQUIREMENTS_HEADER: requirements})
+        nonce = decode_payment_header(payment).payment.nonce
+        return httpx.Response(200, headers={X402_PAYMENT_RESPONSE_HEADER: response_header(PAYER, nonce)})
+
+    store = SqliteReceiptStore(":memory:")
+    auth = X402Auth(signer, receipts=store)
+    async with httpx.AsyncClient(transport=httpx.MockTransport(handler), auth=auth) as client:
+        assert (await client.get("https://api.example.com/premium?q=1")).status_code == 200
+
+    [receipt] = store.query(recipient=RECIPIENT)
+    assert (receipt.resource, receipt.url) == ("/premium", "https://api.example.com/premium?q=1")
+    assert receipt.receipt.transaction_hash == TX
+
+
+def test_record_receipt_rejects_mismatch():
+    store = SqliteReceiptStore(":memory:")
+    header = encode_payment_header(SignedPayment(
+        payment=PaymentPayload(
+            amount=1000, recipient=RECIPIENT, payer=PAYER, chain_id=8453,
+            resource="/premium", nonce=5, expires_at=2_000_000_000,
+        ),
+        signature=b"\x01" * 65,
+    ))
+    assert record_receipt(store, header, {}, "https://api.example.com/premium") is None
+    other_payer = {X402_PAYMENT_RESPONSE_HEADER: response_header(RECIPIENT, 5)}
+    other_nonce = {X402_PAYMENT_RESPONSE_HEADER: response_header(PAYER, 6)}
+    assert record_receipt(store, header, other_payer, "https://api.example.com/premium") is None
+    assert record_receipt(store, header, other_nonce, "https://api.example.com/premium") is None
+    matching = {X402_PAYMENT_RESPONSE_HEADER: response_header(PAYER, 5)}
+    assert record_receipt(store, header, matching, "https://api.example.com/premium", clock=lambda: 42).paid_at == 42
+    assert len(store.query()) == 1
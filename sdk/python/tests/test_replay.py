@@ -0,0 +1,20 @@
+"""Tests for replay protection."""
+
+from x402.replay import MemoryNonceStore
+
+PAYER = "0xabababababababababababababababababababab"
+
+
+def test_memory_nonce_store_claims_once_until_expiry():
+    now = [1_000]
+    store = MemoryNonceStore(clock=lambda: now[0])
+    
+    assert store.claim(8453, PAYER, 1, expires_at=1_100)
+    assert not store.claim(8453, "0x" + PAYER[2:].upper(), 1, expires_at=1_100)
+    assert store.claim(1, PAYER, 1, expires_at=1_100)
+    assert store.claim(8453, PAYER, 2, expires_at=1_200)
+    
+    # Expired payments fail verification anyway, so their nonces are dropped
+    now[0] = 1_150
+    assert store.claim(8453, PAYER, 3, expires_at=1_300)
+    assert len(store) == 2
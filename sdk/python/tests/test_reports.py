@@ -0,0 +1,71 @@
+"""Tests for spend report exports."""
+
+import calendar
+import csv
+import io
+import json
+
+from x402.receipts import SqliteReceiptStore, StoredReceipt
+from x402.reports import REPORT_COLUMNS, SpendReport
+from x402.types import SettlementReceipt
+
+PAYER = "0x2222222222222222222222222222222222222222"
+RECIPIENT = "0x1111111111111111111111111111111111111111"
+USDC = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+MAY = calendar.timegm((2024, 5, 1, 0, 0, 0))
+
+
+def save(store, nonce, paid_at, amount, token=USDC, resource="/premium"):
+    receipt = SettlementReceipt(
+        payer=PAYER, chain_id=8453, nonce=nonce, amount=amount, transaction_hash="0x" + f"{nonce:064x}"
+    )
+    store.save(StoredReceipt(receipt, resource, RECIPIENT, token, f"https://api.example.com{resource}", paid_at))
+
+
+def test_monthly_report_exports():
+    store = SqliteReceiptStore(":memory:")
+    save(store, 1, MAY - 1, 5)
+    save(store, 2, MAY + 3600, 10_000, resource="/a,\"quoted\"")
+    save(store, 3, MAY + 86400, 2**70)
+    save(store, 4, MAY + 2 * 86400, 7, token=None)
+    save(store, 5, calendar.timegm((2024, 6, 1, 0, 0, 0)), 1)
+
+    report = SpendReport.for_month(store, 2024, 5)
+    assert [stored.receipt.nonce for stored in report.payments] == [2, 3, 4]
+    assert report.totals() == {(8453, ""): 7, (8453, USDC): 10_000 + 2**70}
+
+    rows = list(csv.DictReader(io.StringIO(report.export_csv())))
+    assert tuple(rows[0]) == REPORT_COLUMNS
+    assert rows[0]["timestamp"] == "2024-05-01T01:00:00Z"
+    assert rows[0]["resource"] == "/a,\"quoted\""
+    assert rows[1]["amount"] == str(2**70)
+    assert rows[2]["token"] == ""
+
+    exported = json.loads(report.export_json())
+    assert exported["payments"] == rows
+    assert exported["totals"] == [
+        {"chain_id": "8453", "token": "", "amount": "7"},
+        {"chain_id": "8453", "token": USDC, "amount": str(10_000 + 2**70)},
+    ]
+
+
+def test_empty_report():
+    report = SpendReport([])
+    assert report.export_csv() == ",".join(REPORT_COLUMNS) + "\n"
+    assert json.loads(report.export_json()) == {"payments": [], "totals": []}
+
+
+def test_csv_escapes_formulas():
+    receipt = SettlementReceipt(payer=PAYER, chain_id=8453, nonce=1, amount=1, transaction_hash="0x" + "00" * 32)
+    payments = [
+        StoredReceipt(receipt, resource, RECIPIENT, USDC, url, MAY)
+        for resource, url in [("=1+1", "@SUM(A1)"), ("+cmd", "-2"), ("/ok", "https://api.example.com/ok")]
+    ]
+    report = SpendReport(payments)
+    rows = list(csv.DictReader(io.StringIO(report.export_csv())))
+    assert [(row["resource"], row["url"]) for row in rows] == [
+        ("'=1+1", "'@SUM(A1)"),
+        ("'+cmd", "'-2"),
+        ("/ok", "https://api.example.com/ok"),
+    ]
+    assert json.loads(report.export_json())["payments"][0]["resource"] == "=1+1"
@@ -4,7 +4,7 @@ import pytest
 import os
 from unittest.mock import patch, MagicMock
 
-from x402.signer import Signer, LocalSigner
+from x402.signer import Signer, LocalSigner, NativeSigner
 from x402.signer.base import BaseSigner
 from x402.types import PaymentPayload
 
@@ -117,6 +117,47 @@ class TestLocalSigner:
             assert signer is not None
 
 
+@pytest.mark.skipif(not NativeSigner.is_available(), reason="x402-native built without signing")
+class TestNativeSigner:
+    """Tests for NativeSigner."""
+    
+    @pytest.mark.asyncio
+    async def test_signature_verifies_natively(self):
+        """Test native signatures pass native verification."""
+        from x402_native import PaymentRequirements as NativePaymentRequirements, verify_signed_payment
+        from x402.protocol import encode_payment_header
+        from x402.types import SignedPayment
+        
+        signer = NativeSigner("0x" + "42" * 32)
+        payload = PaymentPayload(
+            amount=1000000,
+            recipient="0x1111111111111111111111111111111111111111",
+            payer=await signer.get_address(),
+            chain_id=8453,
+            token=None,
+            resource="/api/test",
+            nonce=2**40,
+            expires_at=4000000000,
+        )
+        
+        signature = await signer.sign_payment(payload)
+        assert len(signature) == 65
+        
+        requirements = NativePaymentRequirements(
+            amount=1000000,
+            recipient=payload.recipient,
+            network="base",
+            resource="/api/test",
+        )
+        header = encode_payment_header(SignedPayment(payment=payload, signature=signature))
+        assert verify_signed_payment(header, requirements).lower() == payload.payer.lower()
+    
+    def test_invalid_key_raises(self):
+        """Test malformed keys are rejected."""
+        with pytest.raises(ValueError):
+            NativeSigner("0x1234")
+
+
 class TestAWSKMSSigner:
     """Tests for AWS KMS signer."""
     
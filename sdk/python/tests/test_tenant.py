@@ -0,0 +1,43 @@
+"""Tests for multi-tenant recipient routing."""
+
+from x402.tenant import RouteTable, TenantRoute, tenant_requirements
+from x402.types import Network, PaymentRequirements
+
+ALICE = TenantRoute("alice", "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1", 5000)
+BOB = TenantRoute("bob", "0xb0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0", 7000)
+
+
+def test_route_table_matches_prefixes_on_segments():
+    routes = RouteTable([("/alice/", ALICE)]).route("/shared", BOB)
+    assert routes.resolve("/alice", None, None) == ALICE
+    assert routes.resolve("/alice/data", None, None) == ALICE
+    assert routes.resolve("/alicex", None, None) is None
+    assert routes.resolve("/shared/x", None, "bob") == BOB
+    assert routes.resolve("/shared/x", None, "alice") is None
+
+
+def test_tenant_header_only_narrows_host_routes():
+    routes = RouteTable().route("/", ALICE, host="alice.example")
+    routes.route("/", BOB, host="bob.example")
+    assert routes.resolve("/data", "Alice.Example:8443", None) == ALICE
+    assert routes.resolve("/data", "alice.example", "alice") == ALICE
+    # Naming another tenant doesn't move alice's host onto bob's price
+    assert routes.resolve("/data", "alice.example", "bob") is None
+    assert routes.resolve("/data", "mallory.example", None) is None
+    assert routes.resolve("/data", None, None) is None
+
+
+def test_tenant_requirements_override_recipient_and_amount():
+    template = PaymentRequirements(
+        amount=1000,
+        recipient="0x1111111111111111111111111111111111111111",
+        network=Network.BASE,
+        resource="https://api.example/alice/data",
+        description="Data",
+    )
+    routes = RouteTable([("/alice", ALICE)])
+    route, requirements = tenant_requirements(routes, template, "/alice/data", None, None)
+    assert route == ALICE
+    assert (requirements.recipient, requirements.amount) == (ALICE.recipient, 5000)
+    assert (requirements.resource, requirements.description) == (template.resource, "Data")
+    assert tenant_requirements(routes, template, "/bob", None, None) is None
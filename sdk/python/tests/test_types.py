@@ -1,8 +1,30 @@
 """Tests for x402 types."""
 
+import json
+import re
+from pathlib import Path
+
 import pytest
 from x402.types import Network, PaymentRequirements, PaymentPayload
 
+TEST_VECTORS = Path(__file__).resolve().parents[3] / "spec" / "test-vectors.json"
+
+
+def load_test_vectors() -> list[dict]:
+    """Vectors of ``spec/test-vectors.json``, keys in snake case (``extra`` as is)."""
+
+    def snake(value):
+        if isinstance(value, dict):
+            return {
+                re.sub(r"(?<!^)([A-Z])", r"_\1", key).lower(): item if key == "extra" else snake(item)
+                for key, item in value.items()
+            }
+        if isinstance(value, list):
+            return [snake(item) for item in value]
+        return value
+
+    return snake(json.loads(TEST_VECTORS.read_text())["vectors"])
+
 
 def test_network_chain_id():
     """Test network chain ID mapping."""
@@ -18,6 +40,14 @@ def test_network_from_chain_id():
     assert Network.from_chain_id(999999) is None
 
 
+def test_network_is_testnet():
+    """Test testnet detection."""
+    assert Network.BASE_SEPOLIA.is_testnet
+    assert Network.POLYGON_AMOY.is_testnet
+    assert not Network.POLYGON.is_testnet
+    assert sum(network.is_testnet for network in Network) == 10
+
+
 def test_payment_requirements():
     """Test payment requirements serialization."""
     requirements = PaymentRequirements(
@@ -70,3 +100,88 @@ def test_payment_payload_message_hash():
     )
     hash3 = payload2.message_hash()
     assert hash1 != hash3
+
+
+def test_invoice_id_rejects_line_breaks():
+    fields = dict(
+        amount=1000000,
+        recipient="0x0000000000000000000000000000000000000000",
+        payer="0x0000000000000000000000000000000000000001",
+        chain_id=8453,
+        resource="/api/test",
+        nonce=1,
+        expires_at=1700000000,
+    )
+    assert PaymentPayload(**fields, invoice_id="order-1").invoice_id == "order-1"
+    with pytest.raises(ValueError):
+        PaymentPayload(**fields, invoice_id="order-1\nExpires: 0")
+
+
+def test_native_types_compare_and_hash_by_content():
+    native = pytest.importorskip("x402_native")
+    
+    def payload(**overrides):
+        fields = dict(
+            amount=1000000,
+            recipient="0x1111111111111111111111111111111111111111",
+            payer="0x2222222222222222222222222222222222222222",
+            chain_id=8453,
+            resource="/api/test",
+            nonce=2**40,
+            expires_at=1700000000,
+        )
+        fields.update(overrides)
+        return native.PaymentPayload(**fields)
+    
+    a = payload(extra={"x": 1, "y": 2})
+    b = payload(extra={"y": 2, "x": 1})
+    assert a == b
+    assert hash(a) == hash(b)
+    assert a != payload(amount=1)
+    assert len({a, b, payload(amount=1)}) == 2
+    assert "amount=1000000" in repr(a)
+    
+    signed = native.SignedPayment(a, b"\x01" * 65)
+    assert signed == native.SignedPayment(b, b"\x01" * 65)
+    assert {signed: "ok"}[native.SignedPayment(b, b"\x01" * 65)] == "ok"
+    assert repr(signed).startswith("SignedPayment(payment=PaymentPayload(")
+
+
+def test_native_types_pickle_and_copy():
+    native = pytest.importorskip("x402_native")
+    import copy
+    import pickle
+    
+    requirements = native.PaymentRequirements(
+        amount=2**200,
+        recipient="0x1111111111111111111111111111111111111111",
+        network="base",
+        resource="/api/test",
+        extra={"tier": "gold"},
+    )
+    payload = native.PaymentPayload(
+        amount=2**200,
+        recipient="0x1111111111111111111111111111111111111111",
+        payer="0x2222222222222222222222222222222222222222",
+        chain_id=8453,
+        resource="/api/test",
+        nonce=2**40,
+        expires_at=1700000000,
+    )
+    signed = native.SignedPayment(payload, b"\x01" * 65)
+    
+    for value in (requirements, payload, signed, native.Money.parse("1.25 USDC"), native.Network.BASE):
+        assert pickle.loads(pickle.dumps(value)) == value
+        assert copy.copy(value) == value
+        assert copy.deepcopy(value) == value
+
+
+def test_message_hash_matches_test_vectors():
+    """The signed message is byte-for-byte the Rust core's."""
+    for vector in load_test_vectors():
+        payload = PaymentPayload(**vector["payload"])
+        assert "0x" + payload.message_hash().hex() == vector["message_hash"], vector["name"]
+
+        # Address case doesn't change the message
+        upper = payload.model_copy(update={"recipient": "0x" + payload.recipient[2:].upper()})
+        assert upper.message_hash() == payload.message_hash()
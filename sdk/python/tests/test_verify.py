@@ -3,9 +3,11 @@
 import pytest
 import time
 
-from x402.types import Network, PaymentRequirements, PaymentPayload, SignedPayment
+from x402.types import Network, PaymentRequirements, PaymentPayload, SignedPayment, Split
 from x402.verify import (
+    _normalize_resource,
     verify_payment,
+    verify_payment_async,
     PaymentExpiredError,
     InsufficientAmountError,
     InvalidSignatureError,
@@ -13,6 +15,8 @@ from x402.verify import (
 from x402.protocol import encode_payment_header
 from x402.signer import LocalSigner
 
+RECIPIENT = "0x1111111111111111111111111111111111111111"
+
 
 class TestVerifyPayment:
     """Tests for payment verification."""
@@ -22,7 +26,7 @@ class TestVerifyPayment:
         """Standard payment requirements."""
         return PaymentRequirements(
             amount=1000000,
-            recipient="0x0000000000000000000000000000000000000000",
+            recipient="0x1111111111111111111111111111111111111111",
             network=Network.BASE,
             resource="/api/test",
         )
@@ -80,7 +84,7 @@ class TestVerifyPayment:
         
         payload = PaymentPayload(
             amount=1000000,
-            recipient="0x1111111111111111111111111111111111111111",  # Wrong
+            recipient="0x2222222222222222222222222222222222222222",  # Wrong
             payer=await signer.get_address(),
             chain_id=8453,
             token=None,
@@ -118,6 +122,67 @@ class TestVerifyPayment:
         
         with pytest.raises(InvalidSignatureError, match="[Cc]hain"):
             verify_payment(header, requirements)
+    
+    @pytest.mark.asyncio
+    @pytest.mark.parametrize("field,value,error", [
+        ("token", "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "[Tt]oken"),
+        ("resource", "/api/other", "[Rr]esource"),
+        ("scheme", "upto", "[Ss]cheme"),
+        ("splits", [Split(recipient=RECIPIENT, share=1000000)], "[Ss]plit"),
+    ])
+    async def test_mismatched_terms_raise(self, requirements, field, value, error):
+        """Test a payment for other terms than the requirements is refused."""
+        signer = LocalSigner.generate()
+        
+        payload = PaymentPayload(
+            amount=1000000,
+            recipient=requirements.recipient,
+            payer=await signer.get_address(),
+            chain_id=8453,
+            token=None,
+            resource="/api/test/",
+            nonce=1,
+            expires_at=int(time.time()) + 3600,
+        )
+        
+        signature = await signer.sign_payment(payload)
+        header = encode_payment_header(SignedPayment(payment=payload, signature=signature))
+        assert verify_payment(header, requirements).lower() == payload.payer.lower()
+        
+        payload = payload.model_copy(update={field: value})
+        signature = await signer.sign_payment(payload)
+        header = encode_payment_header(SignedPayment(payment=payload, signature=signature))
+        with pytest.raises(Exception, match=error):
+            verify_payment(header, requirements)
+    
+    def test_normalize_resource(self):
+        """Test resources compare as the Rust core compares them."""
+        assert _normalize_resource("HTTPS://Example.com:443/api/#top") == "https://example.com/api"
+        assert _normalize_resource("http://a.com:8080/b/?q=1/") == "http://a.com:8080/b?q=1/"
+        assert _normalize_resource("/") == "/"
+        assert _normalize_resource("/api//") == "/api"
+    
+    @pytest.mark.asyncio
+    async def test_verify_payment_async(self, requirements):
+        """Test async verification returns the payer without blocking."""
+        signer = LocalSigner.generate()
+        
+        payload = PaymentPayload(
+            amount=1000000,
+            recipient=requirements.recipient,
+            payer=await signer.get_address(),
+            chain_id=8453,
+            token=None,
+            resource="/api/test",
+            nonce=2**40,
+            expires_at=int(time.time()) + 3600,
+        )
+        
+        signature = await signer.sign_payment(payload)
+        header = encode_payment_header(SignedPayment(payment=payload, signature=signature))
+        
+        payer = await verify_payment_async(header, requirements)
+        assert payer.lower() == payload.payer.lower()
 
 
 class TestVerificationErrors:
@@ -0,0 +1,189 @@
+"""Tests for the WSGI middleware."""
+
+import json
+
+import pytest
+
+import x402.wsgi
+from x402.wsgi import TENANT_ENVIRON_KEY, X402WSGIMiddleware, get_payer
+from x402.cors import CorsPolicy
+from x402.quota import FreeTier
+from x402.protocol import decode_payment_header, decode_requirements_header, encode_payment_header
+from x402.replay import MemoryNonceStore
+from x402.tenant import RouteTable, TenantRoute
+from x402.types import Network, PaymentPayload, PaymentRequirements, SignedPayment
+from x402.verify import InvalidSignatureError
+
+PAYER = "0x2222222222222222222222222222222222222222"
+
+REQUIREMENTS = PaymentRequirements(
+    amount=1000,
+    recipient="0x1111111111111111111111111111111111111111",
+    network=Network.BASE,
+    resource="/premium",
+)
+
+
+def paid(nonce: int = 1) -> str:
+    """An encoded payment from PAYER; the fake verifier accepts any that decodes."""
+    return encode_payment_header(SignedPayment(
+        payment=PaymentPayload(
+            amount=1000,
+            recipient="0x1111111111111111111111111111111111111111",
+            payer=PAYER,
+            chain_id=8453,
+            resource="/premium",
+            nonce=nonce,
+            expires_at=4_000_000_000,
+        ),
+        signature=b"\x01" * 65,
+    ))
+
+
+def app(environ, start_response):
+    start_response("200 OK", [("Content-Type", "application/json")])
+    return [json.dumps({"payer": get_payer(environ)}).encode()]
+
+
+def call(middleware, path, payment=None, ip="203.0.113.7", method="GET", **extra):
+    """Run one request, returning (status, headers, json body)."""
+    environ = {"REQUEST_METHOD": method, "PATH_INFO": path, "REMOTE_ADDR": ip, **extra}
+    if payment is not None:
+        environ["HTTP_X_PAYMENT"] = payment
+    captured = {}
+    
+    def start_response(status, headers):
+        captured["status"] = status
+        captured["headers"] = dict(headers)
+    
+    body = b"".join(middleware(environ, start_response))
+    return captured["status"], captured["headers"], json.loads(body) if body else None
+
+
+@pytest.fixture
+def middleware(monkeypatch):
+    def verify(header, requirements, current_time=None):
+        try:
+            return decode_payment_header(header).payment.payer
+        except ValueError:
+            raise InvalidSignatureError("bad signature")
+    monkeypatch.setattr(x402.wsgi, "verify_payment", verify)
+    return X402WSGIMiddleware(app, lambda path: REQUIREMENTS if path == "/premium" else None)
+
+
+def test_free_paths_pass_through(middleware):
+    assert call(middleware, "/free") == ("200 OK", {"Content-Type": "application/json"}, {"payer": None})
+
+
+def test_priced_paths_require_payment(middleware):
+    status, headers, body = call(middleware, "/premium")
+    assert status.startswith("402")
+    assert decode_requirements_header(headers["X-Payment-Requirements"]).amount == 1000
+    assert "error" not in body
+    
+    status, _, body = call(middleware, "/premium", payment="forged")
+    assert status.startswith("402")
+    assert body["error"] == "bad signature"
+    
+    status, _, body = call(middleware, "/premium", payment=paid())
+    assert status == "200 OK"
+    assert body == {"payer": PAYER}
+
+
+def test_browsers_get_a_paywall_page(middleware):
+    captured = {}
+    
+    def start_response(status, headers):
+        captured["status"] = status
+        captured["headers"] = dict(headers)
+    
+    environ = {
+        "REQUEST_METHOD": "GET",
+        "PATH_INFO": "/premium",
+        "HTTP_ACCEPT": "text/html,application/xhtml+xml,*/*;q=0.8",
+        "HTTP_X_PAYMENT": "forged",
+    }
+    page = b"".join(middleware(environ, start_response)).decode()
+    assert captured["status"].startswith("402")
+    assert captured["headers"]["Content-Type"] == "text/html; charset=utf-8"
+    assert "X-Payment-Requirements" in captured["headers"]
+    assert "Payment rejected: bad signature" in page
+    assert "ethereum:0x1111111111111111111111111111111111111111@8453?value=1000" in page
+    
+    assert call(middleware, "/premium", HTTP_ACCEPT="*/*")[2]["accepts"][0]["amount"] == "1000"
+
+
+def test_replayed_payment_is_refused(middleware):
+    header = paid(nonce=7)
+    assert call(middleware, "/premium", payment=header)[0] == "200 OK"
+    status, _, body = call(middleware, "/premium", payment=header)
+    assert status.startswith("402")
+    assert body["error"] == "payment nonce already used"
+    assert call(middleware, "/premium", payment=paid(nonce=8))[0] == "200 OK"
+    
+    # Middlewares sharing a store share what they have seen
+    nonces = MemoryNonceStore()
+    first = X402WSGIMiddleware(app, lambda path: REQUIREMENTS, nonces=nonces)
+    second = X402WSGIMiddleware(app, lambda path: REQUIREMENTS, nonces=nonces)
+    assert call(first, "/premium", payment=header)[0] == "200 OK"
+    assert call(second, "/premium", payment=header)[0].startswith("402")
+
+
+def test_resolver_routes_tenants(middleware):
+    alice = TenantRoute("alice", "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1", 5000)
+    routes = RouteTable([("/premium", alice)])
+    
+    def tenant_app(environ, start_response):
+        start_response("200 OK", [("Content-Type", "application/json")])
+        return [json.dumps({"tenant": environ.get(TENANT_ENVIRON_KEY)}).encode()]
+    
+    tenants = X402WSGIMiddleware(tenant_app, lambda path: None if path == "/free" else REQUIREMENTS, resolver=routes)
+    assert call(tenants, "/free")[2] == {"tenant": None}
+    assert call(tenants, "/other")[0].startswith("404")
+    assert call(tenants, "/premium", HTTP_X_TENANT="bob")[0].startswith("404")
+    
+    status, headers, _ = call(tenants, "/premium/report")
+    assert status.startswith("402")
+    quoted = decode_requirements_header(headers["X-Payment-Requirements"])
+    assert (quoted.recipient, quoted.amount) == (alice.recipient, 5000)
+    assert call(tenants, "/premium", payment=paid(), HTTP_X_TENANT="alice")[2] == {"tenant": "alice"}
+
+
+def test_free_tier_precedes_payment(middleware):
+    free = X402WSGIMiddleware(app, lambda path: REQUIREMENTS, free_tier=FreeTier(limit=2))
+    assert call(free, "/premium")[0] == "200 OK"
+    assert call(free, "/premium", payment=paid())[2] == {"payer": PAYER}
+    assert call(free, "/premium")[0] == "200 OK"
+    assert call(free, "/premium")[0].startswith("402")
+    assert call(free, "/premium", ip="198.51.100.1")[0] == "200 OK"
+
+
+def test_cors_exposes_x402_headers(middleware):
+    cors = X402WSGIMiddleware(
+        app, lambda path: REQUIREMENTS, cors=CorsPolicy(allow_origins=["https://app.example"])
+    )
+    origin = {"HTTP_ORIGIN": "https://app.example"}
+    preflight = {"HTTP_ACCESS_CONTROL_REQUEST_METHOD": "GET", **origin}
+    
+    status, headers, _ = call(cors, "/premium", method="OPTIONS", **preflight)
+    assert status == "204 No Content"
+    assert "X-Payment" in headers["Access-Control-Allow-Headers"]
+    assert headers["Access-Control-Allow-Origin"] == "https://app.example"
+    
+    status, headers, _ = call(cors, "/premium", **origin)
+    assert status.startswith("402")
+    assert headers["Access-Control-Expose-Headers"] == "X-Payment-Requirements, X-Payment-Response"
+    
+    _, headers, _ = call(cors, "/premium", HTTP_ORIGIN="https://evil.example")
+    assert "Access-Control-Allow-Origin" not in headers
+
+
+def test_cors_credentials_need_listed_origins():
+    with pytest.raises(ValueError):
+        CorsPolicy(allow_credentials=True)
+    with pytest.raises(ValueError):
+        CorsPolicy(allow_origins=["https://app.example", "*"], allow_credentials=True)
+    
+    policy = CorsPolicy(allow_origins=["https://app.example"], allow_credentials=True)
+    assert ("Access-Control-Allow-Credentials", "true") in policy.response_headers("https://app.example")
+    assert policy.response_headers("https://evil.example") == []
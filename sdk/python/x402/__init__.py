@@ -12,16 +12,24 @@ from x402.types import (
     PaymentRequirements,
     PaymentPayload,
     SignedPayment,
+    SettlementReceipt,
+    SupportedPaymentKind,
+    Split,
 )
 from x402.client import X402Client
-from x402.verify import verify_payment
+from x402.facilitator import FacilitatorClient
+from x402.balance import RpcBalanceCheck, WouldBounce
+from x402.receipts import ReceiptStore, SqliteReceiptStore, StoredReceipt
+from x402.reports import SpendReport
+from x402.verify import verify_payment, verify_payment_async
 from x402.protocol import (
     encode_requirements_header,
     decode_requirements_header,
     encode_payment_header,
     decode_payment_header,
+    decode_payment_response_header,
 )
-from x402.signer import Signer, LocalSigner, AWSKMSSigner
+from x402.signer import AsyncSigner, Signer, LocalSigner, NativeSigner, AWSKMSSigner
 
 __version__ = "0.1.0"
 
@@ -31,17 +39,31 @@ __all__ = [
     "PaymentRequirements",
     "PaymentPayload",
     "SignedPayment",
+    "SettlementReceipt",
+    "SupportedPaymentKind",
+    "Split",
     # Client
     "X402Client",
+    "FacilitatorClient",
+    "RpcBalanceCheck",
+    "WouldBounce",
+    "ReceiptStore",
+    "SqliteReceiptStore",
+    "StoredReceipt",
+    "SpendReport",
     # Signers
+    "AsyncSigner",
     "Signer",
     "LocalSigner",
+    "NativeSigner",
     "AWSKMSSigner",
     # Verification
     "verify_payment",
+    "verify_payment_async",
     # Protocol
     "encode_requirements_header",
     "decode_requirements_header",
     "encode_payment_header",
     "decode_payment_header",
+    "decode_payment_response_header",
 ]
@@ -0,0 +1,176 @@
+"""Agent-toolkit helpers: paid HTTP as a function-calling tool.
+
+``PaidHTTPTool`` fetches URLs for an LLM agent, paying 402 responses
+within a per-session ``Budget`` (one budget per tool instance). It plugs
+into:
+
+- OpenAI function calling: pass ``tool.openai_schema()`` in ``tools`` and
+  answer each tool call with ``tool.invoke(call.function.arguments)`` (or
+  ``await tool.ainvoke(...)``)
+- LangChain: ``tool.as_langchain_tool()`` is a ``StructuredTool`` with
+  both sync and async implementations (requires ``langchain-core``)
+
+By default it signs with ``NativeSigner`` from ``X402_PRIVATE_KEY``, i.e.
+with the native bindings. Results are JSON strings for the model to read;
+refused payments and HTTP failures come back as ``{"error": ...}`` rather
+than exceptions, so one expensive URL doesn't end the agent's run.
+
+The sync methods run the async ones with ``asyncio.run``, so they must not
+be called from inside a running event loop.
+"""
+
+import asyncio
+import json
+import time
+from typing import Any, Dict, Optional, Sequence, Union
+
+import httpx
+
+from x402.budget import Budget, BudgetExceeded, pay_within_budget
+from x402.client import BalanceCheck
+from x402.protocol import X402_PAYMENT_HEADER
+from x402.receipts import ReceiptStore, record_receipt
+from x402.signer.base import Signer
+from x402.types import Network
+
+# Longest response body returned to the model, in characters
+MAX_BODY_CHARS = 16 * 1024
+
+TOOL_NAME = "paid_http_request"
+
+TOOL_DESCRIPTION = (
+    "Fetch a URL over HTTP. Paid APIs answering 402 Payment Required are paid "
+    "automatically within the session budget. Returns JSON with the status, the "
+    "amount paid (in the token's smallest unit) and the response body."
+)
+
+TOOL_PARAMETERS: Dict[str, Any] = {
+    "type": "object",
+    "properties": {
+        "url": {"type": "string", "description": "URL to fetch"},
+        "method": {"type": "string", "enum": ["GET", "POST", "PUT", "DELETE"], "default": "GET"},
+        "body": {"type": "string", "description": "Request body, for POST and PUT"},
+    },
+    "required": ["url"],
+}
+
+
+class PaidHTTPTool:
+    """Function-calling tool that pays for HTTP requests within a budget.
+
+    Args:
+        signer: Signer to pay with; ``NativeSigner.from_env()`` by default
+        budget: Spending limits for the session; unlimited by default
+        receipts: Where to keep settlement receipts of paid requests
+        networks: Networks to pay on, most preferred first. None = any
+        balance_check: Skips options the payer can't cover
+        headers: Headers sent with every request
+        timeout: HTTP timeout in seconds
+        transport: httpx transport, e.g. a ``MockTransport`` in tests
+    """
+
+    name = TOOL_NAME
+    description = TOOL_DESCRIPTION
+
+    def __init__(
+        self,
+        signer: Optional[Signer] = None,
+        *,
+        budget: Optional[Budget] = None,
+        receipts: Optional[ReceiptStore] = None,
+        networks: Optional[Sequence[Union[Network, str]]] = None,
+        balance_check: Optional[BalanceCheck] = None,
+        headers: Optional[Dict[str, str]] = None,
+        timeout: float = 30.0,
+        transport: Optional[httpx.AsyncBaseTransport] = None,
+    ):
+        if signer is None:
+            from x402.signer import NativeSigner
+
+            signer = NativeSigner.from_env()
+        self.budget = budget or Budget()
+        self._signer = signer
+        self._receipts = receipts
+        self._networks = networks
+        self._balance_check = balance_check
+        self._headers = dict(headers or {})
+        self._timeout = timeout
+        self._transport = transport
+        self._nonce = int(time.time() * 1000)
+
+    async def arun(self, url: str, method: str = "GET", body: Optional[str] = None) -> str:
+        """Fetch ``url``, paying within the budget; a JSON result string."""
+        try:
+            result = await self._fetch(url, method.upper(), body)
+        except (BudgetExceeded, httpx.HTTPError, ValueError) as e:
+            result = {"error": str(e)}
+        return json.dumps(result)
+
+    def run(self, url: str, method: str = "GET", body: Optional[str] = None) -> str:
+        """Sync ``arun``."""
+        return asyncio.run(self.arun(url, method, body))
+
+    async def ainvoke(self, arguments: Union[str, Dict[str, Any]]) -> str:
+        """Answer a tool call whose arguments are a JSON string or dict."""
+        if isinstance(arguments, str):
+            try:
+                arguments = json.loads(arguments)
+            except json.JSONDecodeError as e:
+                return json.dumps({"error": f"arguments are not JSON: {e}"})
+        if not isinstance(arguments, dict) or not isinstance(arguments.get("url"), str):
+            return json.dumps({"error": "a url argument is required"})
+        return await self.arun(arguments["url"], arguments.get("method") or "GET", arguments.get("body"))
+
+    def invoke(self, arguments: Union[str, Dict[str, Any]]) -> str:
+        """Sync ``ainvoke``."""
+        return asyncio.run(self.ainvoke(arguments))
+
+    def openai_schema(self) -> Dict[str, Any]:
+        """Tool definition for OpenAI chat completions' ``tools``."""
+        return {
+            "type": "function",
+            "function": {"name": self.name, "description": self.description, "parameters": TOOL_PARAMETERS},
+        }
+
+    def as_langchain_tool(self) -> Any:
+        """This tool as a LangChain ``StructuredTool``."""
+        from langchain_core.tools import StructuredTool
+
+        return StructuredTool.from_function(
+            func=self.run,
+            coroutine=self.arun,
+            name=self.name,
+            description=self.description,
+        )
+
+    async def _fetch(self, url: str, method: str, body: Optional[str]) -> Dict[str, Any]:
+        headers = dict(self._headers)
+        # A client per call, so the sync methods' event loops don't share one
+        async with httpx.AsyncClient(timeout=self._timeout, transport=self._transport) as client:
+            response = await client.request(method, url, headers=headers, content=body)
+            paid: Optional[int] = None
+            if response.status_code == 402:
+                self._nonce += 1
+                payment_header, payment = await pay_within_budget(
+                    response,
+                    self.budget,
+                    self._signer,
+                    self._nonce,
+                    networks=self._networks,
+                    balance_check=self._balance_check,
+                )
+                paid = payment.amount
+                headers[X402_PAYMENT_HEADER] = payment_header
+                response = await client.request(method, url, headers=headers, content=body)
+                if self._receipts is not None:
+                    record_receipt(self._receipts, payment_header, response.headers, str(response.url))
+
+        text = response.text
+        remaining = self.budget.remaining()
+        return {
+            "status": response.status_code,
+            "paid": None if paid is None else str(paid),
+            "budgetRemaining": None if remaining is None else str(remaining),
+            "body": text[:MAX_BODY_CHARS],
+            "truncated": len(text) > MAX_BODY_CHARS,
+        }
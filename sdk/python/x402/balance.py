@@ -0,0 +1,153 @@
+"""Pre-payment balance checks.
+
+A payment the payer can't cover is accepted by the server and then fails
+at settlement. ``RpcBalanceCheck`` asks an RPC node first, so the client
+fails fast with ``WouldBounce`` (or falls back to another advertised
+option) instead::
+
+    check = RpcBalanceCheck({8453: "https://mainnet.base.org"})
+    client = X402Client(signer, balance_check=check)
+
+It checks the payer's native or token balance and the preconditions of
+the settlement method: that the token implements EIP-3009
+``transferWithAuthorization``, or, for Permit2, that the payer has approved
+the Permit2 contract for at least the amount.
+"""
+
+from typing import Any, Mapping, Optional, Union
+
+import httpx
+
+from x402.types import Network, PaymentRequirements
+
+# Canonical Permit2 deployment, at the same address on every chain
+PERMIT2_ADDRESS = "0x000000000022D473030F116dDEE9F6B43aC78BA3"
+
+_BALANCE_OF = "0x70a08231"  # balanceOf(address)
+_ALLOWANCE = "0xdd62ed3e"  # allowance(address,address)
+_AUTHORIZATION_STATE = "0xe94a0102"  # authorizationState(address,bytes32)
+
+
+class WouldBounce(Exception):
+    """The payer can't cover a payment, so settlement would fail.
+
+    Attributes:
+        requirements: The requirements that can't be paid
+        payer: The payer address
+        reason: What is missing
+    """
+
+    def __init__(self, requirements: PaymentRequirements, payer: str, reason: str):
+        super().__init__(f"payment of {requirements.amount} to {requirements.recipient} would bounce: {reason}")
+        self.requirements = requirements
+        self.payer = payer
+        self.reason = reason
+
+
+class RpcBalanceCheck:
+    """Balance check against Ethereum JSON-RPC nodes.
+
+    Use as the ``balance_check`` of a client; it raises ``WouldBounce``
+    for options the payer can't cover.
+
+    Args:
+        rpc_urls: RPC endpoint per network (``Network`` or chain ID);
+            options on other networks are not checked
+        settlement: ``"eip3009"`` (the default, as settled by the Rust
+            core), ``"permit2"``, or None to check balances only
+        timeout: RPC timeout in seconds
+        transport: httpx transport, e.g. for tests
+    """
+
+    def __init__(
+        self,
+        rpc_urls: Mapping[Union[Network, int], str],
+        *,
+        settlement: Optional[str] = "eip3009",
+        timeout: float = 10.0,
+        transport: Optional[httpx.AsyncBaseTransport] = None,
+    ):
+        if settlement not in ("eip3009", "permit2", None):
+            raise ValueError(f"settlement must be 'eip3009', 'permit2' or None, not {settlement!r}")
+        self._rpc_urls = {
+            network.chain_id if isinstance(network, Network) else int(network): url
+            for network, url in rpc_urls.items()
+        }
+        self._settlement = settlement
+        self._timeout = timeout
+        self._transport = transport
+
+    async def __call__(self, requirements: PaymentRequirements, payer: str) -> bool:
+        await self.check(requirements, payer)
+        return True
+
+    async def check(self, requirements: PaymentRequirements, payer: str) -> None:
+        """Raise ``WouldBounce`` if ``payer`` can't cover ``requirements``.
+
+        Raises:
+            WouldBounce: If a balance, allowance or token capability is missing
+            httpx.HTTPError: If the RPC node can't be reached
+        """
+        url = self._rpc_urls.get(Network(requirements.network).chain_id)
+        if url is None:
+            return
+        async with httpx.AsyncClient(timeout=self._timeout, transport=self._transport) as client:
+            rpc = _Rpc(client, url)
+            if requirements.token is None:
+                balance = _quantity(await rpc.call("eth_getBalance", [payer, "latest"]))
+                if balance < requirements.amount:
+                    raise WouldBounce(requirements, payer, f"native balance {balance} is below {requirements.amount}")
+                return
+
+            token = requirements.token
+            balance = _quantity(await rpc.eth_call(token, _BALANCE_OF + _word(payer)))
+            if balance < requirements.amount:
+                raise WouldBounce(requirements, payer, f"token balance {balance} is below {requirements.amount}")
+            if self._settlement == "permit2":
+                allowance = _quantity(await rpc.eth_call(token, _ALLOWANCE + _word(payer) + _word(PERMIT2_ADDRESS)))
+                if allowance < requirements.amount:
+                    raise WouldBounce(
+                        requirements, payer, f"Permit2 allowance {allowance} is below {requirements.amount}"
+                    )
+            elif self._settlement == "eip3009":
+                try:
+                    await rpc.eth_call(token, _AUTHORIZATION_STATE + _word(payer) + _word("0x0"))
+                except _RpcError:
+                    raise WouldBounce(requirements, payer, "token does not implement EIP-3009") from None
+
+
+class _RpcError(Exception):
+    """JSON-RPC error response (e.g. a reverted ``eth_call``)."""
+
+
+class _Rpc:
+    def __init__(self, client: httpx.AsyncClient, url: str):
+        self._client = client
+        self._url = url
+        self._id = 0
+
+    async def call(self, method: str, params: list) -> Any:
+        self._id += 1
+        response = await self._client.post(
+            self._url, json={"jsonrpc": "2.0", "id": self._id, "method": method, "params": params}
+        )
+        response.raise_for_status()
+        reply = response.json()
+        if "error" in reply:
+            raise _RpcError(reply["error"].get("message", "RPC error"))
+        return reply.get("result")
+
+    async def eth_call(self, to: str, data: str) -> Any:
+        return await self.call("eth_call", [{"to": to, "data": data}, "latest"])
+
+
+def _word(value: str) -> str:
+    """An address or hex quantity as a 32-byte ABI word, without ``0x``."""
+    return value.lower().removeprefix("0x").rjust(64, "0")
+
+
+def _quantity(value: Any) -> int:
+    """Parse a hex quantity or ABI word; empty return data counts as zero."""
+    if not isinstance(value, str) or value in ("0x", ""):
+        return 0
+    return int(value, 16)
@@ -0,0 +1,140 @@
+"""Spending limits for autonomous clients.
+
+Agents pay without a human approving each payment (see ``x402.mcp`` and
+``x402.agents``), so their spending is capped by a ``Budget``: a limit per
+payment and a total for the session. ``pay_within_budget`` answers a 402
+under one.
+
+Amounts are in the token's smallest unit, so a budget with limits names
+the tokens they are counted in, e.g. USDC on Base and on Ethereum. Options
+in any other token are never paid: raw amounts of tokens with different
+decimals or prices can't be compared.
+"""
+
+from typing import FrozenSet, Iterable, Optional, Sequence, Tuple, Union
+
+import httpx
+
+from x402.client import BalanceCheck, payment_asset, payment_header_for
+from x402.protocol import decode_payment_header, decode_payment_options
+from x402.signer.base import Signer
+from x402.types import Network, PaymentPayload
+
+
+class BudgetExceeded(Exception):
+    """A payment would exceed the budget."""
+
+
+class Budget:
+    """Spending limits for an agent session.
+
+    Args:
+        total: Most to spend over the session. None = no limit
+        per_payment: Most to spend on one payment. None = no limit
+        tokens: ``(chain_id, token)`` pairs the limits are counted in, token
+            None for the native coin. Required with a limit; list only
+            tokens worth the same per smallest unit. None = any token
+
+    Raises:
+        ValueError: If a limit is set without ``tokens``
+    """
+
+    def __init__(
+        self,
+        total: Optional[int] = None,
+        per_payment: Optional[int] = None,
+        tokens: Optional[Iterable[Tuple[int, Optional[str]]]] = None,
+    ):
+        if tokens is None and (total is not None or per_payment is not None):
+            raise ValueError(
+                "a budget with limits needs tokens: amounts in different tokens can't be compared"
+            )
+        self.total = total
+        self.per_payment = per_payment
+        self.tokens: Optional[FrozenSet[Tuple[int, Optional[str]]]] = (
+            None if tokens is None else frozenset(_asset(*asset) for asset in tokens)
+        )
+        self.spent = 0
+
+    def covers(self, chain_id: int, token: Optional[str]) -> bool:
+        """Whether payments in ``token`` on ``chain_id`` count against this budget."""
+        return self.tokens is None or _asset(chain_id, token) in self.tokens
+
+    def remaining(self) -> Optional[int]:
+        """What is left of ``total``, or None without one."""
+        return None if self.total is None else max(self.total - self.spent, 0)
+
+    def max_payment(self) -> Optional[int]:
+        """Largest payment allowed now, or None for any."""
+        limits = [limit for limit in (self.per_payment, self.remaining()) if limit is not None]
+        return min(limits) if limits else None
+
+    def charge(self, amount: int, chain_id: int, token: Optional[str]) -> None:
+        """Count ``amount`` of ``token`` on ``chain_id`` as spent.
+
+        Raises:
+            BudgetExceeded: If the payment is over the limits or in a token
+                the budget doesn't cover
+        """
+        if not self.covers(chain_id, token):
+            raise BudgetExceeded(
+                f"budget doesn't cover token {token or 'native'} on chain {chain_id}"
+            )
+        limit = self.max_payment()
+        if limit is not None and amount > limit:
+            raise BudgetExceeded(f"payment of {amount} exceeds the budget's limit of {limit}")
+        self.spent += amount
+
+
+async def pay_within_budget(
+    response: httpx.Response,
+    budget: Budget,
+    signer: Signer,
+    nonce: int,
+    *,
+    networks: Optional[Sequence[Union[Network, str]]] = None,
+    balance_check: Optional[BalanceCheck] = None,
+) -> Tuple[str, PaymentPayload]:
+    """Sign a payment for a 402 ``response`` and charge it to ``budget``.
+
+    The payment is charged before it is sent: a signed authorization can be
+    settled whatever the paid response turns out to be.
+
+    Returns:
+        The ``X-Payment`` header value and the payment it carries
+
+    Raises:
+        BudgetExceeded: If no option the response offers fits the budget
+    """
+    limit = budget.max_payment()
+    payment_header = await payment_header_for(
+        response.headers,
+        signer,
+        nonce,
+        max_amount=limit,
+        body=response.content,
+        networks=networks,
+        balance_check=balance_check,
+        tokens=budget.tokens,
+    )
+    if payment_header is None:
+        options = [
+            option
+            for option in decode_payment_options(response.headers, response.content)
+            if budget.covers(*payment_asset(option))
+        ]
+        if not options:
+            raise BudgetExceeded(
+                f"not paying {response.url}: no option is in a token the budget covers"
+            )
+        cheapest = min(option.amount for option in options)
+        raise BudgetExceeded(
+            f"not paying {response.url}: cheapest option costs {cheapest}, budget allows {limit}"
+        )
+    payment = decode_payment_header(payment_header).payment
+    budget.charge(payment.amount, payment.chain_id, payment.token)
+    return payment_header, payment
+
+
+def _asset(chain_id: int, token: Optional[str]) -> Tuple[int, Optional[str]]:
+    return chain_id, token.lower() if token else None
@@ -1,17 +1,19 @@
 """x402 HTTP client with automatic payment handling."""
 
 import time
-from typing import Any, Optional, Dict, Union
+from typing import (
+    Any, Awaitable, Callable, Collection, Dict, List, Mapping, Optional, Sequence, Tuple, Union,
+)
 
 import httpx
 
 from x402.types import PaymentRequirements, PaymentPayload, SignedPayment, Network
 from x402.protocol import (
-    X402_REQUIREMENTS_HEADER,
     X402_PAYMENT_HEADER,
-    decode_requirements_header,
+    decode_payment_options,
     encode_payment_header,
 )
+from x402.receipts import ReceiptStore, record_receipt
 from x402.signer.base import Signer
 
 
@@ -40,6 +42,9 @@ class X402Client:
         auto_pay: bool = True,
         timeout: float = 30.0,
         base_url: Optional[str] = None,
+        receipts: Optional[ReceiptStore] = None,
+        networks: Optional[Sequence[Union[Network, str]]] = None,
+        balance_check: Optional["BalanceCheck"] = None,
     ):
         """Initialize x402 client.
         
@@ -49,10 +54,17 @@ class X402Client:
             auto_pay: Whether to automatically pay 402 responses
             timeout: Request timeout in seconds
             base_url: Optional base URL for all requests
+            receipts: Where to keep settlement receipts of paid requests
+            networks: Networks to pay on, most preferred first; see
+                ``payment_header_for``. None = any, in server order
+            balance_check: Skips options the payer can't cover
         """
         self._signer = signer
         self._max_amount = max_amount
         self._auto_pay = auto_pay
+        self._receipts = receipts
+        self._networks = networks
+        self._balance_check = balance_check
         self._nonce = int(time.time() * 1000)  # Simple incrementing nonce
         
         self._client = httpx.AsyncClient(
@@ -91,6 +103,8 @@ class X402Client:
             
         Raises:
             httpx.HTTPError: On HTTP errors (after payment attempt if 402)
+            WouldBounce: If the balance check found no option the payer
+                can cover
         """
         headers = dict(headers or {})
         
@@ -105,6 +119,8 @@ class X402Client:
                 # Retry with payment
                 headers[X402_PAYMENT_HEADER] = payment_header
                 response = await self._client.request(method, url, headers=headers, **kwargs)
+                if self._receipts is not None:
+                    record_receipt(self._receipts, payment_header, response.headers, str(response.url))
         
         return response
     
@@ -134,46 +150,15 @@ class X402Client:
         Returns:
             Encoded payment header, or None if payment was rejected
         """
-        # Get payment requirements from header
-        requirements_header = response.headers.get(X402_REQUIREMENTS_HEADER)
-        if not requirements_header:
-            return None
-        
-        try:
-            requirements = decode_requirements_header(requirements_header)
-        except ValueError:
-            return None
-        
-        # Check max amount
-        if self._max_amount is not None and requirements.amount > self._max_amount:
-            return None
-        
-        # Create payment payload
-        payer_address = await self._signer.get_address()
-        
-        # Get chain ID from network
-        if isinstance(requirements.network, Network):
-            chain_id = requirements.network.chain_id
-        else:
-            chain_id = _get_chain_id(requirements.network)
-        
-        payload = PaymentPayload(
-            amount=requirements.amount,
-            recipient=requirements.recipient,
-            payer=payer_address,
-            chain_id=chain_id,
-            token=requirements.token,
-            resource=requirements.resource,
-            nonce=self._get_nonce(),
-            expires_at=requirements.expires_at or (int(time.time()) + 300),  # 5 min default
+        return await payment_header_for(
+            response.headers,
+            self._signer,
+            self._get_nonce(),
+            max_amount=self._max_amount,
+            body=response.content,
+            networks=self._networks,
+            balance_check=self._balance_check,
         )
-        
-        # Sign the payment
-        signature = await self._signer.sign_payment(payload)
-        
-        signed_payment = SignedPayment(payment=payload, signature=signature)
-        
-        return encode_payment_header(signed_payment)
     
     def _get_nonce(self) -> int:
         """Get next nonce value."""
@@ -181,14 +166,181 @@ class X402Client:
         return self._nonce
 
 
-def _get_chain_id(network: str) -> int:
-    """Get chain ID from network string."""
+# Checks whether the payer can cover ``requirements`` before signing
+BalanceCheck = Callable[[PaymentRequirements, str], Awaitable[bool]]
+
+
+async def payment_header_for(
+    headers: Mapping[str, str],
+    signer: Signer,
+    nonce: int,
+    *,
+    max_amount: Optional[int] = None,
+    body: Optional[Union[bytes, str]] = None,
+    networks: Optional[Sequence[Union[Network, str]]] = None,
+    balance_check: Optional[BalanceCheck] = None,
+    tokens: Optional[Collection[Tuple[int, Optional[str]]]] = None,
+) -> Optional[str]:
+    """Sign a payment for the requirements in a 402 response.
+    
+    Shared by X402Client and the httpx/requests hooks.
+    
+    When the response offers several options, they are tried in the order
+    of ``networks`` (or the server's order), falling back to the next one
+    when the balance check rejects an option (returns False or raises,
+    e.g. ``WouldBounce``) or the signer fails on it.
+    
+    Args:
+        headers: Headers of the 402 response
+        signer: Signer to pay with
+        nonce: Nonce for the payment
+        max_amount: Maximum amount to pay. None = no limit
+        body: Body of the 402 response, which may list several options
+        networks: Networks to pay on, most preferred first; options on
+            other networks are never paid. None = any, in server order
+        balance_check: Called with each option and the payer address;
+            options it returns False for or raises on are skipped. See
+            ``x402.balance.RpcBalanceCheck``
+        tokens: ``(chain_id, token)`` pairs to pay in, token None for the
+            native coin and addresses lowercase; options in other tokens
+            are never paid. None = any
+        
+    Returns:
+        Encoded payment header, or None if there is nothing acceptable to pay
+        
+    Raises:
+        Exception: The last balance check or signer error, if every option
+            tried failed with one (``WouldBounce`` when none could be covered)
+    """
+    options = [
+        requirements
+        for requirements in by_preference(decode_payment_options(headers, body), networks)
+        # Check max amount
+        if max_amount is None or requirements.amount <= max_amount
+        if tokens is None or payment_asset(requirements) in tokens
+    ]
+    if not options:
+        return None
+    
+    payer_address = await signer.get_address()
+    error: Optional[Exception] = None
+    for requirements in options:
+        try:
+            if balance_check is not None and not await balance_check(requirements, payer_address):
+                continue
+            return await sign_payment_header(requirements, signer, payer_address, nonce)
+        except Exception as e:
+            error = e
+    if error is not None:
+        raise error
+    return None
+
+
+def payment_asset(requirements: PaymentRequirements) -> Tuple[int, Optional[str]]:
+    """``(chain_id, token)`` an option is paid in; token lowercase, None for native."""
+    token = requirements.token.lower() if requirements.token else None
+    return _get_chain_id(requirements.network), token
+
+
+def by_preference(
+    options: List[PaymentRequirements],
+    networks: Optional[Sequence[Union[Network, str]]],
+) -> List[PaymentRequirements]:
+    """``options`` on ``networks``, in that order; stable within a network.
+
+    All of ``options`` in server order if ``networks`` is None.
+    """
+    if networks is None:
+        return options
+    rank = {_network_name(network): i for i, network in reversed(list(enumerate(networks)))}
+    preferred = [option for option in options if _network_name(option.network) in rank]
+    return sorted(preferred, key=lambda option: rank[_network_name(option.network)])
+
+
+def _network_name(network: Union[Network, str]) -> str:
+    return network.value if isinstance(network, Network) else str(network)
+
+
+async def sign_payment_header(
+    requirements: PaymentRequirements,
+    signer: Signer,
+    payer_address: str,
+    nonce: int,
+    expires_at: Optional[int] = None,
+    invoice_id: Optional[str] = None,
+) -> str:
+    """Sign a payment of ``requirements`` and encode it as a header value.
+
+    Unlike ``payment_header_for``, pays ``requirements`` as given: no limit,
+    balance or network checks.
+
+    Args:
+        requirements: What to pay
+        signer: Signer to pay with
+        payer_address: Address of ``signer``
+        nonce: Nonce of the payment
+        expires_at: Validity other than the default 5 minutes (unix
+            timestamp); the server's limits still apply
+        invoice_id: Idempotency key shared by retries of one purchase
+    """
+    chain_id = _get_chain_id(requirements.network)
+    
+    # Keep the authorization no longer-lived than the server allows
+    now = int(time.time())
+    if expires_at is None:
+        expires_at = requirements.expires_at or (now + 300)  # 5 min default
+    elif requirements.expires_at is not None:
+        expires_at = min(expires_at, requirements.expires_at)
+    if requirements.max_timeout_seconds is not None:
+        expires_at = min(expires_at, now + requirements.max_timeout_seconds)
+    
+    payload = PaymentPayload(
+        amount=requirements.amount,
+        recipient=requirements.recipient,
+        payer=payer_address,
+        chain_id=chain_id,
+        token=requirements.token,
+        resource=requirements.resource,
+        nonce=nonce,
+        expires_at=expires_at,
+        scheme=requirements.scheme,
+        extra=dict(requirements.extra),
+        splits=list(requirements.splits),
+        invoice_id=invoice_id,
+    )
+    
+    # Sign the payment
+    signature = await signer.sign_payment(payload)
+    
+    signed_payment = SignedPayment(payment=payload, signature=signature)
+    
+    return encode_payment_header(signed_payment)
+
+
+def _get_chain_id(network: Union[Network, str]) -> int:
+    """Get chain ID from network; 0 if unknown."""
+    if isinstance(network, Network):
+        return network.chain_id
     chain_ids = {
         "ethereum": 1,
+        "sepolia": 11155111,
         "base": 8453,
         "base_sepolia": 84532,
         "arbitrum": 42161,
+        "arbitrum_sepolia": 421614,
         "optimism": 10,
+        "optimism_sepolia": 11155420,
         "polygon": 137,
+        "polygon_amoy": 80002,
+        "avalanche": 43114,
+        "avalanche_fuji": 43113,
+        "bnb": 56,
+        "bnb_testnet": 97,
+        "zksync": 324,
+        "zksync_sepolia": 300,
+        "linea": 59144,
+        "linea_sepolia": 59141,
+        "scroll": 534352,
+        "scroll_sepolia": 534351,
     }
     return chain_ids.get(network, 0)
@@ -0,0 +1,99 @@
+"""CORS settings for browser clients.
+
+Browsers hide response headers from scripts unless the server lists them
+in ``Access-Control-Expose-Headers``, and won't send custom request headers
+the server didn't allow in its preflight answer. Without both, a browser
+client can't read ``X-Payment-Requirements`` from a 402 or send
+``X-Payment``.
+
+``X402WSGIMiddleware`` takes a ``CorsPolicy``. FastAPI apps use
+``add_x402_cors`` from ``x402.fastapi``, which configures Starlette's
+``CORSMiddleware`` with the headers below.
+"""
+
+from typing import Iterable, List, Optional, Sequence, Tuple
+
+from x402.protocol import X402_PAYMENT_HEADER, X402_PAYMENT_RESPONSE_HEADER, X402_REQUIREMENTS_HEADER
+from x402.quota import X402_PAYER_ADDRESS_HEADER
+from x402.tenant import X402_TENANT_HEADER
+
+# Headers x402 clients send
+X402_REQUEST_HEADERS = (X402_PAYMENT_HEADER, X402_PAYER_ADDRESS_HEADER, X402_TENANT_HEADER)
+
+# Headers x402 servers answer with
+X402_RESPONSE_HEADERS = (X402_REQUIREMENTS_HEADER, X402_PAYMENT_RESPONSE_HEADER)
+
+_METHODS = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS"
+
+
+class CorsPolicy:
+    """Which origins may make x402 requests from a browser.
+
+    Args:
+        allow_origins: Allowed origins, e.g. ``https://app.example``;
+            ``"*"`` allows any
+        allow_credentials: Let requests carry cookies and HTTP auth; only
+            with listed origins, never ``"*"``
+        max_age: How long browsers may cache a preflight answer, in seconds
+        allow_headers: Request headers to allow besides the x402 ones and
+            ``Content-Type``
+        expose_headers: Response headers to expose besides the x402 ones
+
+    Raises:
+        ValueError: If credentials are allowed for any origin, which would
+            let every site make authenticated requests and read the answers
+    """
+
+    def __init__(
+        self,
+        allow_origins: Sequence[str] = ("*",),
+        allow_credentials: bool = False,
+        max_age: int = 600,
+        allow_headers: Iterable[str] = (),
+        expose_headers: Iterable[str] = (),
+    ):
+        if allow_credentials and "*" in allow_origins:
+            raise ValueError('CORS credentials can\'t be allowed for any origin ("*"); list the origins')
+        self.allow_origins = list(allow_origins)
+        self.allow_credentials = allow_credentials
+        self.max_age = max_age
+        self.allow_headers = [*X402_REQUEST_HEADERS, "Content-Type", *allow_headers]
+        self.expose_headers = [*X402_RESPONSE_HEADERS, *expose_headers]
+
+    def allows_origin(self, origin: str) -> bool:
+        return any(allowed == "*" or allowed.lower() == origin.lower() for allowed in self.allow_origins)
+
+    def response_headers(self, origin: Optional[str]) -> List[Tuple[str, str]]:
+        """Headers to add to an actual (non-preflight) response.
+
+        Empty if the request has no ``Origin`` or it is not allowed.
+        """
+        if not origin or not self.allows_origin(origin):
+            return []
+        return [
+            *self._origin_headers(origin),
+            ("Access-Control-Expose-Headers", ", ".join(self.expose_headers)),
+        ]
+
+    def preflight_headers(self, origin: Optional[str]) -> Optional[List[Tuple[str, str]]]:
+        """Headers answering a preflight (``OPTIONS``) request.
+
+        None if the origin is not allowed; answering without CORS headers
+        makes the browser refuse the request.
+        """
+        if not origin or not self.allows_origin(origin):
+            return None
+        return [
+            *self._origin_headers(origin),
+            ("Access-Control-Allow-Methods", _METHODS),
+            ("Access-Control-Allow-Headers", ", ".join(self.allow_headers)),
+            ("Access-Control-Max-Age", str(self.max_age)),
+        ]
+
+    def _origin_headers(self, origin: str) -> List[Tuple[str, str]]:
+        if "*" in self.allow_origins:
+            return [("Access-Control-Allow-Origin", "*")]
+        headers = [("Access-Control-Allow-Origin", origin), ("Vary", "Origin")]
+        if self.allow_credentials:
+            headers.append(("Access-Control-Allow-Credentials", "true"))
+        return headers
@@ -0,0 +1,65 @@
+"""Facilitator discovery.
+
+A facilitator verifies and settles payments for servers. Its ``/supported``
+endpoint lists the (scheme, network, token) combinations it handles, so a
+server can check a facilitator covers its prices before relying on it.
+
+Public facilitators may require an API key, sent in ``X-Api-Key``.
+"""
+
+from typing import Any, List, Optional
+
+import httpx
+
+from x402.types import PaymentRequirements, SupportedPaymentKind
+
+# Header carrying a facilitator API key, as in the Rust core
+X402_API_KEY_HEADER = "X-Api-Key"
+
+
+class FacilitatorClient:
+    """Client for a facilitator's HTTP API.
+
+    Args:
+        url: Base URL of the facilitator, e.g. ``https://facilitator.example.com``
+        api_key: API key to authenticate with, if the facilitator requires one
+        timeout: Request timeout in seconds
+        transport: httpx transport, e.g. a ``MockTransport`` in tests
+    """
+
+    def __init__(
+        self,
+        url: str,
+        *,
+        api_key: Optional[str] = None,
+        timeout: float = 10.0,
+        transport: Optional[httpx.AsyncBaseTransport] = None,
+    ):
+        headers = {X402_API_KEY_HEADER: api_key} if api_key else {}
+        self._client = httpx.AsyncClient(
+            base_url=url.rstrip("/"), headers=headers, timeout=timeout, transport=transport
+        )
+
+    async def __aenter__(self) -> "FacilitatorClient":
+        return self
+
+    async def __aexit__(self, *args: Any) -> None:
+        await self.close()
+
+    async def close(self) -> None:
+        """Close the HTTP client."""
+        await self._client.aclose()
+
+    async def supported(self) -> List[SupportedPaymentKind]:
+        """The payment kinds the facilitator verifies and settles.
+
+        Raises:
+            httpx.HTTPStatusError: If the facilitator answers with an error
+        """
+        response = await self._client.get("/supported")
+        response.raise_for_status()
+        return [SupportedPaymentKind.model_validate(kind) for kind in response.json().get("kinds", [])]
+
+    async def supports(self, requirements: PaymentRequirements) -> bool:
+        """Whether the facilitator can verify and settle payments for ``requirements``."""
+        return any(kind.matches(requirements) for kind in await self.supported())
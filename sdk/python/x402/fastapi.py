@@ -0,0 +1,232 @@
+"""FastAPI/Starlette integration.
+
+Two ways to charge for endpoints, both verifying with the native Rust
+implementation when it is installed:
+
+- ``RequirePayment``: a dependency for individual routes that returns the
+  verified payer address::
+
+      paid = RequirePayment(requirements)
+
+      @app.get("/premium")
+      async def premium(payer: str = Depends(paid)):
+          ...
+
+- ``X402Middleware``: gates every request its ``pricing`` callable returns
+  requirements for; handlers read the payer with ``get_payer``. Pass it a
+  ``FreeTier`` to let clients make a few unpaid requests first, and a
+  ``RecipientResolver`` to pay a different tenant per path (see
+  ``x402.tenant``).
+
+Both refuse a payment whose nonce they have already accepted; see
+``x402.replay``. The default ``MemoryNonceStore`` covers one process.
+
+Browser clients on other origins also need ``add_x402_cors``.
+
+Unpaid or rejected requests get a 402 carrying the encoded requirements.
+``X402Middleware`` answers browsers that ask for HTML with a paywall page
+(see ``x402.paywall``); ``RequirePayment`` always answers with JSON, as
+FastAPI renders its ``HTTPException``.
+Requires ``pip install x402[fastapi]``.
+"""
+
+import inspect
+from typing import Awaitable, Callable, Optional, Union
+
+from fastapi import FastAPI, HTTPException, Request
+from starlette.middleware.cors import CORSMiddleware
+from starlette.middleware.base import BaseHTTPMiddleware, RequestResponseEndpoint
+from starlette.responses import HTMLResponse, JSONResponse, Response
+from starlette.types import ASGIApp
+
+from x402.types import PaymentRequirements
+from x402.cors import CorsPolicy
+from x402.paywall import prefers_html, render_paywall
+from x402.protocol import X402_PAYMENT_HEADER, decode_payment_header
+from x402.quota import X402_PAYER_ADDRESS_HEADER, FreeTier
+from x402.replay import MemoryNonceStore, NonceStore, claim_payment
+from x402.server import payment_required_body, payment_required_headers
+from x402.tenant import X402_TENANT_HEADER, RecipientResolver, tenant_requirements
+from x402.verify import X402VerificationError, verify_payment_async
+
+# Requirements for a request, or None if it is free
+Pricing = Callable[[Request], Optional[PaymentRequirements]]
+
+
+class PaymentRequired(Exception):
+    """A request lacks an acceptable payment."""
+    
+    def __init__(self, requirements: PaymentRequirements, error: Optional[str] = None):
+        self.requirements = requirements
+        self.error = error
+        super().__init__(error or "Payment required")
+    
+    def response(self, html: bool = False) -> Response:
+        """The 402 response to send: a paywall page if ``html``, JSON otherwise."""
+        if html:
+            return HTMLResponse(
+                render_paywall(self.requirements, self.error),
+                status_code=402,
+                headers=payment_required_headers(self.requirements),
+            )
+        return JSONResponse(
+            payment_required_body(self.requirements, self.error),
+            status_code=402,
+            headers=payment_required_headers(self.requirements),
+        )
+
+
+async def _verify(request: Request, requirements: PaymentRequirements, nonces: NonceStore) -> str:
+    """Verify the request's payment, claim its nonce and record the payer on
+    ``request.state``.
+    
+    Raises:
+        PaymentRequired: If the payment is missing, rejected or replayed
+    """
+    header = request.headers.get(X402_PAYMENT_HEADER)
+    if not header:
+        raise PaymentRequired(requirements)
+    try:
+        payer = await verify_payment_async(header, requirements)
+    except X402VerificationError as e:
+        raise PaymentRequired(requirements, str(e))
+    if not claim_payment(nonces, decode_payment_header(header).payment):
+        raise PaymentRequired(requirements, "payment nonce already used")
+    request.state.x402_payer = payer
+    return payer
+
+
+class RequirePayment:
+    """Route dependency that requires payment and returns the payer.
+    
+    Args:
+        requirements: Fixed requirements, or a callable computing them from
+            the request (it may be async)
+        nonces: Nonces already accepted; a fresh ``MemoryNonceStore`` by
+            default
+    """
+    
+    def __init__(
+        self,
+        requirements: Union[
+            PaymentRequirements,
+            Callable[[Request], Union[PaymentRequirements, Awaitable[PaymentRequirements]]],
+        ],
+        nonces: Optional[NonceStore] = None,
+    ):
+        self._requirements = requirements
+        self._nonces = nonces if nonces is not None else MemoryNonceStore()
+    
+    async def requirements_for(self, request: Request) -> PaymentRequirements:
+        """The requirements ``request`` has to meet."""
+        if isinstance(self._requirements, PaymentRequirements):
+            return self._requirements
+        requirements = self._requirements(request)
+        if inspect.isawaitable(requirements):
+            requirements = await requirements
+        return requirements
+    
+    async def __call__(self, request: Request) -> str:
+        requirements = await self.requirements_for(request)
+        try:
+            return await _verify(request, requirements, self._nonces)
+        except PaymentRequired as e:
+            raise HTTPException(
+                status_code=402,
+                detail=payment_required_body(e.requirements, e.error),
+                headers=payment_required_headers(e.requirements),
+            )
+
+
+class X402Middleware(BaseHTTPMiddleware):
+    """Middleware that requires payment for the requests ``pricing`` prices.
+    
+    Example:
+        def pricing(request):
+            if request.url.path.startswith("/premium"):
+                return PaymentRequirements(...)
+            return None
+        
+        app.add_middleware(X402Middleware, pricing=pricing)
+    
+    Args:
+        pricing: Returns the requirements for a request, or None
+        free_tier: Unpaid requests to allow per client before charging
+        nonces: Nonces already accepted; a fresh ``MemoryNonceStore`` by
+            default
+        resolver: Picks the tenant paid for each priced request, by host,
+            path and ``X-Tenant``; priced paths no tenant serves get a 404
+    """
+    
+    def __init__(
+        self,
+        app: ASGIApp,
+        pricing: Pricing,
+        free_tier: Optional[FreeTier] = None,
+        nonces: Optional[NonceStore] = None,
+        resolver: Optional[RecipientResolver] = None,
+    ):
+        super().__init__(app)
+        self._pricing = pricing
+        self._free_tier = free_tier
+        self._nonces = nonces if nonces is not None else MemoryNonceStore()
+        self._resolver = resolver
+    
+    async def dispatch(self, request: Request, call_next: RequestResponseEndpoint) -> Response:
+        requirements = self._pricing(request)
+        if requirements is None:
+            return await call_next(request)
+        if self._resolver is not None:
+            routed = tenant_requirements(
+                self._resolver,
+                requirements,
+                request.url.path,
+                request.headers.get("host"),
+                request.headers.get(X402_TENANT_HEADER),
+            )
+            if routed is None:
+                return JSONResponse({"error": "no tenant serves this path"}, status_code=404)
+            route, requirements = routed
+            request.state.x402_tenant = route.tenant
+        if self._free_tier is not None and not request.headers.get(X402_PAYMENT_HEADER):
+            ip = request.client.host if request.client else None
+            if self._free_tier.allow(ip, request.headers.get(X402_PAYER_ADDRESS_HEADER)):
+                return await call_next(request)
+        try:
+            await _verify(request, requirements, self._nonces)
+        except PaymentRequired as e:
+            return e.response(html=prefers_html(request.headers.get("accept")))
+        return await call_next(request)
+
+
+def get_payer(request: Request) -> Optional[str]:
+    """The payer verified for ``request``, if it was paid for.
+    
+    Usable as a dependency: ``payer: Optional[str] = Depends(get_payer)``.
+    """
+    return getattr(request.state, "x402_payer", None)
+
+
+def get_tenant(request: Request) -> Optional[str]:
+    """The tenant ``X402Middleware`` routed ``request`` to, if any."""
+    return getattr(request.state, "x402_tenant", None)
+
+
+def add_x402_cors(app: FastAPI, policy: Optional[CorsPolicy] = None) -> None:
+    """Add Starlette's ``CORSMiddleware``, allowing and exposing the x402 headers.
+    
+    Add it after ``X402Middleware`` so it also wraps 402 responses.
+    
+    Args:
+        policy: Origins and extra headers; any origin by default
+    """
+    policy = policy or CorsPolicy()
+    app.add_middleware(
+        CORSMiddleware,
+        allow_origins=policy.allow_origins,
+        allow_credentials=policy.allow_credentials,
+        allow_methods=["*"],
+        allow_headers=policy.allow_headers,
+        expose_headers=policy.expose_headers,
+        max_age=policy.max_age,
+    )
@@ -0,0 +1,94 @@
+"""httpx integration.
+
+``X402Auth`` pays for 402 responses on any ``httpx.Client`` or
+``httpx.AsyncClient``, the same way ``X402Client`` does::
+
+    async with httpx.AsyncClient(auth=X402Auth(signer, max_amount=1_000_000)) as client:
+        response = await client.get("https://api.example.com/premium")
+
+Sync clients work too, as long as they aren't used from inside a running
+event loop (the signer is async and is run with ``asyncio.run``).
+"""
+
+import asyncio
+import time
+from typing import AsyncGenerator, Generator, Optional, Sequence, Union
+
+import httpx
+
+from x402.client import BalanceCheck, payment_header_for
+from x402.protocol import X402_PAYMENT_HEADER
+from x402.receipts import ReceiptStore, record_receipt
+from x402.signer.base import Signer
+from x402.types import Network
+
+
+class X402Auth(httpx.Auth):
+    """httpx auth that answers 402 responses with a signed payment.
+    
+    Args:
+        signer: Signer to pay with
+        max_amount: Maximum amount to pay per request. None = no limit
+        receipts: Where to keep settlement receipts of paid requests
+        networks: Networks to pay on, most preferred first. None = any
+        balance_check: Skips options the payer can't cover
+    """
+    
+    # Buffer request bodies so paid retries can resend them
+    requires_request_body = True
+    
+    def __init__(
+        self,
+        signer: Signer,
+        *,
+        max_amount: Optional[int] = None,
+        receipts: Optional[ReceiptStore] = None,
+        networks: Optional[Sequence[Union[Network, str]]] = None,
+        balance_check: Optional[BalanceCheck] = None,
+    ):
+        self._signer = signer
+        self._max_amount = max_amount
+        self._receipts = receipts
+        self._networks = networks
+        self._balance_check = balance_check
+        self._nonce = int(time.time() * 1000)
+    
+    def sync_auth_flow(self, request: httpx.Request) -> Generator[httpx.Request, httpx.Response, None]:
+        response = yield request
+        if response.status_code != 402:
+            return
+        # Only 402 bodies are read (they may offer several ways to pay);
+        # other responses stay streamed
+        response.read()
+        payment_header = asyncio.run(self._payment_header(response))
+        if payment_header:
+            request.headers[X402_PAYMENT_HEADER] = payment_header
+            response = yield request
+            self._record(payment_header, request, response)
+    
+    async def async_auth_flow(self, request: httpx.Request) -> AsyncGenerator[httpx.Request, httpx.Response]:
+        response = yield request
+        if response.status_code != 402:
+            return
+        await response.aread()
+        payment_header = await self._payment_header(response)
+        if payment_header:
+            request.headers[X402_PAYMENT_HEADER] = payment_header
+            response = yield request
+            self._record(payment_header, request, response)
+    
+    def _record(self, payment_header: str, request: httpx.Request, response: httpx.Response) -> None:
+        if self._receipts is not None:
+            record_receipt(self._receipts, payment_header, response.headers, str(request.url))
+    
+    async def _payment_header(self, response: httpx.Response) -> Optional[str]:
+        self._nonce += 1
+        return await payment_header_for(
+            response.headers,
+            self._signer,
+            self._nonce,
+            max_amount=self._max_amount,
+            body=response.content,
+            networks=self._networks,
+            balance_check=self._balance_check,
+        )
@@ -0,0 +1,360 @@
+"""MCP (Model Context Protocol) server exposing x402 payments as tools.
+
+LLM agents get three tools:
+
+- ``fetch_paid_resource``: fetch a URL, paying its 402 if the budget allows
+- ``quote_price``: what a URL costs, without paying
+- ``get_spend_report``: what the session spent, and the receipts kept
+
+Every payment goes through a ``Budget``, a per-payment cap and a session
+total, so an agent can never spend more than its operator allowed. Run the
+server over stdio::
+
+    X402_PRIVATE_KEY=0x... X402_BUDGET=1000000 x402-mcp
+
+or embed ``X402McpServer`` with your own signer, budget and receipt store.
+The environment variables ``X402_BUDGET_TOKENS`` (comma-separated
+``<chain id>:<token address>`` pairs the limits are counted in, ``native``
+for the chain's coin; required with a limit), ``X402_MAX_PAYMENT``
+(per-payment cap), ``X402_NETWORKS`` (comma-separated, most preferred
+first) and ``X402_RECEIPTS`` (SQLite receipt database) configure the rest::
+
+    X402_BUDGET_TOKENS=8453:0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913 X402_BUDGET=1000000 ...
+
+Amounts are in the token's smallest unit; see ``x402.budget``.
+"""
+
+import asyncio
+import json
+import os
+import sys
+import time
+from typing import Any, Dict, List, Optional, Sequence, Tuple, Union
+
+import httpx
+
+from x402.budget import Budget, BudgetExceeded, pay_within_budget
+from x402.client import BalanceCheck, payment_asset
+from x402.protocol import X402_PAYMENT_HEADER, decode_payment_options
+from x402.receipts import ReceiptStore, SqliteReceiptStore, record_receipt
+from x402.reports import SpendReport
+from x402.signer.base import Signer
+from x402.types import Network
+
+# MCP revision implemented; newer clients negotiate down to it
+MCP_PROTOCOL_VERSION = "2024-11-05"
+
+# Longest response body returned to the agent, in characters
+MAX_BODY_CHARS = 64 * 1024
+
+TOOLS: List[Dict[str, Any]] = [
+    {
+        "name": "fetch_paid_resource",
+        "description": (
+            "Fetch a URL over HTTP. If the server answers 402 Payment Required, pay it "
+            "(within the budget) and retry. Returns the status, amount paid and body."
+        ),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "url": {"type": "string", "description": "URL to fetch"},
+                "method": {"type": "string", "description": "HTTP method", "default": "GET"},
+                "headers": {"type": "object", "additionalProperties": {"type": "string"}},
+                "body": {"type": "string", "description": "Request body"},
+            },
+            "required": ["url"],
+        },
+    },
+    {
+        "name": "quote_price",
+        "description": (
+            "What fetching a URL costs, without paying. Lists every payment option the server offers."
+        ),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "url": {"type": "string", "description": "URL to price"},
+                "method": {"type": "string", "description": "HTTP method", "default": "GET"},
+            },
+            "required": ["url"],
+        },
+    },
+    {
+        "name": "get_spend_report",
+        "description": "Amounts spent this session, the remaining budget, and the settlement receipts kept.",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "since": {"type": "integer", "description": "Earliest receipt (unix time)"},
+                "until": {"type": "integer", "description": "Latest receipt (unix time)"},
+            },
+        },
+    },
+]
+
+
+class X402McpServer:
+    """MCP server paying for the agent's requests.
+
+    Args:
+        signer: Signer to pay with
+        budget: Spending limits; unlimited by default
+        receipts: Where to keep settlement receipts, also read by
+            ``get_spend_report``
+        networks: Networks to pay on, most preferred first. None = any
+        balance_check: Skips options the payer can't cover
+        timeout: HTTP timeout in seconds
+        transport: httpx transport, e.g. a ``MockTransport`` in tests
+    """
+
+    def __init__(
+        self,
+        signer: Signer,
+        *,
+        budget: Optional[Budget] = None,
+        receipts: Optional[ReceiptStore] = None,
+        networks: Optional[Sequence[Union[Network, str]]] = None,
+        balance_check: Optional[BalanceCheck] = None,
+        timeout: float = 30.0,
+        transport: Optional[httpx.AsyncBaseTransport] = None,
+    ):
+        self.budget = budget or Budget()
+        self._signer = signer
+        self._receipts = receipts
+        self._networks = networks
+        self._balance_check = balance_check
+        self._client = httpx.AsyncClient(timeout=timeout, transport=transport)
+        self._nonce = int(time.time() * 1000)
+        self._payments: List[Dict[str, Any]] = []
+
+    async def close(self) -> None:
+        """Close the HTTP client."""
+        await self._client.aclose()
+
+    async def handle(self, message: Dict[str, Any]) -> Optional[Dict[str, Any]]:
+        """Answer one JSON-RPC message; None for notifications."""
+        if "id" not in message:
+            return None
+        request_id = message["id"]
+        method = message.get("method")
+        params = message.get("params") or {}
+        if method == "initialize":
+            result: Dict[str, Any] = {
+                "protocolVersion": MCP_PROTOCOL_VERSION,
+                "capabilities": {"tools": {}},
+                "serverInfo": {"name": "x402", "version": _version()},
+            }
+        elif method == "ping":
+            result = {}
+        elif method == "tools/list":
+            result = {"tools": TOOLS}
+        elif method == "tools/call":
+            name = params.get("name")
+            if name not in {tool["name"] for tool in TOOLS}:
+                return _error(request_id, -32602, f"Unknown tool: {name}")
+            result = await self.call_tool(name, params.get("arguments") or {})
+        else:
+            return _error(request_id, -32601, f"Method not found: {method}")
+        return {"jsonrpc": "2.0", "id": request_id, "result": result}
+
+    async def call_tool(self, name: str, arguments: Dict[str, Any]) -> Dict[str, Any]:
+        """Run a tool, returning its MCP ``CallToolResult``."""
+        try:
+            if name == "fetch_paid_resource":
+                output = await self.fetch_paid_resource(**arguments)
+            elif name == "quote_price":
+                output = await self.quote_price(**arguments)
+            else:
+                output = self.get_spend_report(**arguments)
+        except (BudgetExceeded, httpx.HTTPError, TypeError, ValueError) as e:
+            return {"content": [{"type": "text", "text": str(e)}], "isError": True}
+        return {"content": [{"type": "text", "text": json.dumps(output, indent=2)}], "isError": False}
+
+    async def fetch_paid_resource(
+        self,
+        url: str,
+        method: str = "GET",
+        headers: Optional[Dict[str, str]] = None,
+        body: Optional[str] = None,
+    ) -> Dict[str, Any]:
+        """Fetch ``url``, paying a 402 within the budget."""
+        headers = dict(headers or {})
+        response = await self._request(method, url, headers=headers, content=body)
+        paid: Optional[int] = None
+        if response.status_code == 402:
+            self._nonce += 1
+            payment_header, payment = await pay_within_budget(
+                response,
+                self.budget,
+                self._signer,
+                self._nonce,
+                networks=self._networks,
+                balance_check=self._balance_check,
+            )
+            paid = payment.amount
+            self._payments.append(
+                {
+                    "url": url,
+                    "amount": str(payment.amount),
+                    "recipient": payment.recipient,
+                    "chainId": payment.chain_id,
+                    "token": payment.token,
+                    "paidAt": int(time.time()),
+                }
+            )
+            headers[X402_PAYMENT_HEADER] = payment_header
+            response = await self._request(method, url, headers=headers, content=body)
+            if self._receipts is not None:
+                record_receipt(self._receipts, payment_header, response.headers, str(response.url))
+
+        text = response.text
+        return {
+            "status": response.status_code,
+            "paid": None if paid is None else str(paid),
+            "contentType": response.headers.get("content-type"),
+            "body": text[:MAX_BODY_CHARS],
+            "truncated": len(text) > MAX_BODY_CHARS,
+        }
+
+    async def quote_price(self, url: str, method: str = "GET") -> Dict[str, Any]:
+        """The payment options ``url`` offers, without paying."""
+        response = await self._request(method, url)
+        if response.status_code != 402:
+            return {"status": response.status_code, "free": True, "options": []}
+        options = decode_payment_options(response.headers, response.content)
+        limit = self.budget.max_payment()
+        return {
+            "status": 402,
+            "free": False,
+            "options": [
+                {
+                    **option.model_dump(mode="json"),
+                    "amount": str(option.amount),
+                    "withinBudget": (
+                        self.budget.covers(*payment_asset(option))
+                        and (limit is None or option.amount <= limit)
+                    ),
+                }
+                for option in options
+            ],
+        }
+
+    async def _request(self, method: str, url: str, **kwargs: Any) -> httpx.Response:
+        """Send a request, following redirects without leaking the payment.
+
+        httpx drops ``Authorization`` when a redirect leaves the origin but
+        keeps other headers, so ``X-Payment`` is removed here: a signed
+        payment is only for the server that asked for it.
+        """
+        response = await self._client.request(method, url, **kwargs)
+        for _ in range(self._client.max_redirects):
+            redirect = response.next_request
+            if redirect is None:
+                return response
+            if not _same_origin(response.request.url, redirect.url):
+                redirect.headers.pop(X402_PAYMENT_HEADER, None)
+            await response.aclose()
+            response = await self._client.send(redirect)
+        if response.next_request is None:
+            return response
+        raise httpx.TooManyRedirects("Exceeded maximum allowed redirects.", request=response.next_request)
+
+    def get_spend_report(self, since: Optional[int] = None, until: Optional[int] = None) -> Dict[str, Any]:
+        """Session spending, and the receipts kept between ``since`` and ``until``."""
+        remaining = self.budget.remaining()
+        report: Dict[str, Any] = {
+            "spent": str(self.budget.spent),
+            "remaining": None if remaining is None else str(remaining),
+            "perPaymentLimit": None if self.budget.per_payment is None else str(self.budget.per_payment),
+            "payments": [
+                payment
+                for payment in self._payments
+                if (since is None or payment["paidAt"] >= since)
+                and (until is None or payment["paidAt"] <= until)
+            ],
+        }
+        if self._receipts is not None:
+            spend = SpendReport.from_store(self._receipts, since=since, until=until)
+            report["receipts"] = json.loads(spend.export_json())
+        return report
+
+    async def serve_stdio(self) -> None:
+        """Serve newline-delimited JSON-RPC on stdin/stdout until EOF."""
+        loop = asyncio.get_running_loop()
+        while True:
+            line = await loop.run_in_executor(None, sys.stdin.readline)
+            if not line:
+                break
+            if not line.strip():
+                continue
+            try:
+                message = json.loads(line)
+            except json.JSONDecodeError as e:
+                reply: Optional[Dict[str, Any]] = _error(None, -32700, f"Parse error: {e}")
+            else:
+                reply = await self.handle(message)
+            if reply is not None:
+                sys.stdout.write(json.dumps(reply) + "\n")
+                sys.stdout.flush()
+
+
+def _same_origin(a: httpx.URL, b: httpx.URL) -> bool:
+    return (a.scheme, a.host, a.port) == (b.scheme, b.host, b.port)
+
+
+def _error(request_id: Any, code: int, message: str) -> Dict[str, Any]:
+    return {"jsonrpc": "2.0", "id": request_id, "error": {"code": code, "message": message}}
+
+
+def _version() -> str:
+    from x402 import __version__
+
+    return __version__
+
+
+def _env_int(name: str) -> Optional[int]:
+    value = os.environ.get(name)
+    return int(value) if value else None
+
+
+def _env_tokens(name: str) -> Optional[List[Tuple[int, Optional[str]]]]:
+    value = os.environ.get(name)
+    if not value:
+        return None
+    tokens: List[Tuple[int, Optional[str]]] = []
+    for pair in value.split(","):
+        chain_id, _, token = pair.strip().partition(":")
+        if not token:
+            raise ValueError(f"{name}: expected <chain id>:<token address or native>, got {pair!r}")
+        tokens.append((int(chain_id), None if token == "native" else token))
+    return tokens
+
+
+def main() -> None:
+    """Entry point of ``x402-mcp``: serve over stdio, configured from the environment."""
+    from x402.signer import LocalSigner
+
+    networks = os.environ.get("X402_NETWORKS")
+    receipts_path = os.environ.get("X402_RECEIPTS")
+    server = X402McpServer(
+        LocalSigner.from_env("X402_PRIVATE_KEY"),
+        budget=Budget(
+            total=_env_int("X402_BUDGET"),
+            per_payment=_env_int("X402_MAX_PAYMENT"),
+            tokens=_env_tokens("X402_BUDGET_TOKENS"),
+        ),
+        receipts=SqliteReceiptStore(receipts_path) if receipts_path else None,
+        networks=[network.strip() for network in networks.split(",")] if networks else None,
+    )
+
+    async def serve() -> None:
+        try:
+            await server.serve_stdio()
+        finally:
+            await server.close()
+
+    asyncio.run(serve())
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,106 @@
+"""HTML paywall pages for browsers, matching the Rust core's ``DefaultPaywall``.
+
+A browser opening a priced URL should get a page, not a bare 402 with a
+JSON body. ``X402Middleware`` and ``X402WSGIMiddleware`` answer requests
+whose ``Accept`` header ranks ``text/html`` above ``application/json``
+(see ``prefers_html``) with ``render_paywall``'s page, still carrying the
+``X-Payment-Requirements`` header.
+
+The page links a wallet to the payment with an EIP-681 ``payment_uri``.
+A plain transfer from a wallet produces no ``X-Payment`` authorization, so
+the link unlocks the resource only on servers that watch the chain for
+such transfers.
+"""
+
+import html
+from typing import Optional, Tuple
+
+from eth_utils import to_checksum_address
+
+from x402.types import Network, PaymentRequirements
+
+
+def prefers_html(accept: Optional[str]) -> bool:
+    """Whether an ``Accept`` header ranks ``text/html`` above ``application/json``.
+
+    Ties go to JSON, so ``*/*`` from scripts and HTTP libraries keeps getting
+    the machine-readable 402.
+    """
+    if not accept:
+        return False
+    quality = _quality(accept, "text", "html")
+    return quality > 0 and quality > _quality(accept, "application", "json")
+
+
+def _quality(accept: str, kind: str, subtype: str) -> float:
+    """Quality the most specific matching media range in ``accept`` gives ``kind/subtype``."""
+    best: Optional[Tuple[int, float]] = None
+    for media_range in accept.split(","):
+        media, *params = media_range.split(";")
+        range_kind, slash, range_subtype = media.strip().lower().partition("/")
+        if not slash:
+            continue
+        if (range_kind, range_subtype) == (kind, subtype):
+            specificity = 2
+        elif (range_kind, range_subtype) == (kind, "*"):
+            specificity = 1
+        elif (range_kind, range_subtype) == ("*", "*"):
+            specificity = 0
+        else:
+            continue
+        quality = 1.0
+        for param in params:
+            name, _, value = param.strip().partition("=")
+            if name == "q":
+                try:
+                    quality = float(value)
+                    break
+                except ValueError:
+                    continue
+        if best is None or specificity > best[0]:
+            best = (specificity, quality)
+    return best[1] if best is not None else 0.0
+
+
+def payment_uri(requirements: PaymentRequirements) -> Optional[str]:
+    """EIP-681 URI that opens a wallet on a transfer of the required amount.
+
+    None for split payments, which no single transfer can make.
+    """
+    if requirements.splits:
+        return None
+    chain_id = Network(requirements.network).chain_id
+    recipient = to_checksum_address(requirements.recipient)
+    if requirements.token is None:
+        return f"ethereum:{recipient}@{chain_id}?value={requirements.amount}"
+    token = to_checksum_address(requirements.token)
+    return f"ethereum:{token}@{chain_id}/transfer?address={recipient}&uint256={requirements.amount}"
+
+
+def render_paywall(requirements: PaymentRequirements, error: Optional[str] = None) -> str:
+    """The paywall page for ``requirements``.
+
+    Args:
+        requirements: What the client has to pay
+        error: Why a submitted payment was rejected, if one was
+    """
+    network = Network(requirements.network)
+    asset = html.escape(requirements.token) if requirements.token else "the native coin"
+    page = (
+        '<!doctype html><html><head><meta charset="utf-8">'
+        '<meta name="viewport" content="width=device-width, initial-scale=1">'
+        "<title>Payment required</title></head><body><main>"
+        "<h1>Payment required</h1>"
+        f"<p><code>{html.escape(requirements.resource)}</code> costs "
+        f"<strong>{requirements.amount} base units</strong> of {asset} on {network.value}, "
+        f"paid to <code>{html.escape(requirements.recipient)}</code>.</p>"
+    )
+    if requirements.description:
+        page += f"<p>{html.escape(requirements.description)}</p>"
+    if error is not None:
+        page += f'<p role="alert">Payment rejected: {html.escape(error)}</p>'
+    uri = payment_uri(requirements)
+    if uri is not None:
+        page += f'<p><a href="{html.escape(uri)}">Pay with a wallet</a></p>'
+    page += "<p>Programmatic clients: retry with an <code>X-Payment</code> header.</p></main>"
+    return page + "</body></html>"
@@ -0,0 +1,324 @@
+"""Bulk prepayment for crawlers.
+
+Paying per request costs a round trip per page: the 402, then the paid
+retry. A crawler fetching millions of pages can skip it two ways:
+
+- Pre-signed batches: ``prepay`` quotes every URL of the batch
+  (``BulkPayer.quote``) and signs a payment for each up front, pages with
+  the same price in one batch. Each page is then fetched with its payment
+  already attached.
+- Session credentials: ``buy_session`` pays one deposit and keeps the
+  ``X-Credit`` credential the server answers with (see the Rust core's
+  ``credit`` module), sending it with every request under a URL prefix (same
+  scheme, host and port; the path on segment boundaries) until the credit
+  runs out.
+
+Either way, a page that still answers 402 (a different price, spent
+credit) is paid the usual way, so a stale prepayment never loses a page.
+
+Example:
+    async with BulkPayer(signer, max_amount=10_000) as payer:
+        await payer.prepay(urls)
+        async for url, response in payer.crawl(urls):
+            ...
+"""
+
+import asyncio
+import time
+from typing import Any, AsyncIterator, Callable, Dict, Iterable, List, Optional, Sequence, Tuple, Union
+
+import httpx
+
+from x402.client import BalanceCheck, by_preference, payment_header_for, sign_payment_header
+from x402.protocol import X402_PAYMENT_HEADER, decode_payment_options
+from x402.receipts import ReceiptStore, record_receipt
+from x402.signer.base import Signer
+from x402.types import Network, PaymentRequirements
+
+# Header carrying a credit credential, as in the Rust core
+X402_CREDIT_HEADER = "X-Credit"
+
+# Validity of pre-signed payments when the caller doesn't choose one
+DEFAULT_PREPAY_TTL = 3600
+
+
+def resource_path(url: str) -> str:
+    """The resource a URL is paid as: its path and query."""
+    return httpx.URL(url).raw_path.decode("ascii")
+
+
+async def presign_batch(
+    requirements: PaymentRequirements,
+    urls: Iterable[str],
+    signer: Signer,
+    first_nonce: int,
+    *,
+    expires_at: Optional[int] = None,
+    resource_for: Callable[[str], str] = resource_path,
+) -> Dict[str, str]:
+    """Sign a payment of ``requirements`` for each of ``urls``.
+
+    Args:
+        requirements: Price template, e.g. one URL's 402; its ``resource``
+            is replaced by ``resource_for(url)``
+        urls: URLs to pay for; duplicates are paid once
+        signer: Signer to pay with
+        first_nonce: Nonce of the first payment; the rest follow it
+        expires_at: End of the payments' validity (unix timestamp),
+            capped by the server's ``max_timeout_seconds``
+        resource_for: Resource of a URL, as the server names it
+
+    Returns:
+        Payment header value per URL
+    """
+    urls = list(dict.fromkeys(urls))
+    payer_address = await signer.get_address()
+    headers = await asyncio.gather(
+        *(
+            sign_payment_header(
+                requirements.model_copy(update={"resource": resource_for(url)}),
+                signer,
+                payer_address,
+                first_nonce + i,
+                expires_at,
+            )
+            for i, url in enumerate(urls)
+        )
+    )
+    return dict(zip(urls, headers))
+
+
+def _under_prefix(url: str, prefix: str) -> bool:
+    """Whether ``url`` has the origin of ``prefix`` and a path under its path."""
+    target, base = httpx.URL(url), httpx.URL(prefix)
+    if (target.scheme, target.host, target.port) != (base.scheme, base.host, base.port):
+        return False
+    path = base.path.rstrip("/")
+    return target.path == path or target.path.startswith(path + "/")
+
+
+class BulkPayer:
+    """HTTP client for crawlers, paying ahead of its requests.
+
+    Args:
+        signer: Signer to pay with
+        max_amount: Most to pay for one page (or session deposit). None = no limit
+        networks: Networks to pay on, most preferred first. None = any
+        balance_check: Skips options the payer can't cover
+        receipts: Where to keep settlement receipts of paid requests
+        concurrency: Most requests ``crawl`` keeps in flight
+        timeout: HTTP timeout in seconds
+        transport: httpx transport, e.g. a ``MockTransport`` in tests
+    """
+
+    def __init__(
+        self,
+        signer: Signer,
+        *,
+        max_amount: Optional[int] = None,
+        networks: Optional[Sequence[Union[Network, str]]] = None,
+        balance_check: Optional[BalanceCheck] = None,
+        receipts: Optional[ReceiptStore] = None,
+        concurrency: int = 16,
+        timeout: float = 30.0,
+        transport: Optional[httpx.AsyncBaseTransport] = None,
+    ):
+        self._signer = signer
+        self._max_amount = max_amount
+        self._networks = networks
+        self._balance_check = balance_check
+        self._receipts = receipts
+        self._concurrency = concurrency
+        self._client = httpx.AsyncClient(timeout=timeout, transport=transport)
+        self._nonce = int(time.time() * 1000)
+        self._prepaid: Dict[str, str] = {}
+        self._sessions: Dict[str, str] = {}
+
+    async def __aenter__(self) -> "BulkPayer":
+        return self
+
+    async def __aexit__(self, *args: Any) -> None:
+        await self.close()
+
+    async def close(self) -> None:
+        """Close the HTTP client."""
+        await self._client.aclose()
+
+    @property
+    def prepaid(self) -> int:
+        """Pre-signed payments not yet used."""
+        return len(self._prepaid)
+
+    async def quote(self, url: str) -> Optional[PaymentRequirements]:
+        """The option this client would pay ``url`` with; None if it's free.
+
+        Raises:
+            ValueError: If ``url`` offers nothing this client may pay
+        """
+        response = await self._client.get(url)
+        if response.status_code != 402:
+            return None
+        options = [
+            option
+            for option in by_preference(decode_payment_options(response.headers, response.content), self._networks)
+            if self._max_amount is None or option.amount <= self._max_amount
+        ]
+        payer_address = await self._signer.get_address()
+        for option in options:
+            if self._balance_check is None or await self._balance_check(option, payer_address):
+                return option
+        raise ValueError(f"{url} offers no payment option within limits")
+
+    async def prepay(
+        self,
+        urls: Iterable[str],
+        requirements: Optional[PaymentRequirements] = None,
+        *,
+        ttl: int = DEFAULT_PREPAY_TTL,
+        resource_for: Callable[[str], str] = resource_path,
+    ) -> int:
+        """Pre-sign payments for ``urls``, used by later requests to them.
+
+        Args:
+            urls: Pages to pay for
+            requirements: Price of every page; if None, each URL is quoted
+                and paid at its own price
+            ttl: Validity of the payments in seconds
+            resource_for: Resource of a URL, as the server names it
+
+        Returns:
+            Number of payments signed (free pages aren't counted)
+        """
+        urls = [url for url in dict.fromkeys(urls) if url not in self._prepaid]
+        if requirements is not None:
+            if self._max_amount is not None and requirements.amount > self._max_amount:
+                raise ValueError(f"price {requirements.amount} exceeds the limit of {self._max_amount}")
+            groups = [(requirements, urls)] if urls else []
+        else:
+            groups = await self._quote_all(urls)
+        signed = 0
+        for price, group in groups:
+            first_nonce = self._nonce + 1
+            self._nonce += len(group)
+            headers = await presign_batch(
+                price,
+                group,
+                self._signer,
+                first_nonce,
+                expires_at=int(time.time()) + ttl,
+                resource_for=resource_for,
+            )
+            self._prepaid.update(headers)
+            signed += len(headers)
+        return signed
+
+    async def _quote_all(self, urls: List[str]) -> List[Tuple[PaymentRequirements, List[str]]]:
+        """Quote ``urls`` concurrently, grouping the paid ones by price."""
+        semaphore = asyncio.Semaphore(self._concurrency)
+
+        async def quote(url: str) -> Optional[PaymentRequirements]:
+            async with semaphore:
+                return await self.quote(url)
+
+        quotes = await asyncio.gather(*(quote(url) for url in urls))
+        groups: Dict[str, Tuple[PaymentRequirements, List[str]]] = {}
+        for url, option in zip(urls, quotes):
+            if option is not None:
+                # Everything but the resource, which each payment sets itself
+                price = option.model_dump_json(exclude={"resource"})
+                groups.setdefault(price, (option, []))[1].append(url)
+        return list(groups.values())
+
+    async def buy_session(self, prefix: str, deposit_url: Optional[str] = None, method: str = "POST") -> str:
+        """Buy a credit credential and use it for every URL under ``prefix``.
+
+        Args:
+            prefix: URL prefix the credential is sent to, e.g.
+                ``https://api.example.com/pages/``
+            deposit_url: URL selling the credit; ``prefix`` if None
+            method: HTTP method of the deposit request
+
+        Returns:
+            The credential, as sent in the ``X-Credit`` header
+
+        Raises:
+            ValueError: If the deposit wasn't paid or the server sent no credential
+        """
+        url = deposit_url or prefix
+        response = await self._send(method, url, {})
+        credential = response.headers.get(X402_CREDIT_HEADER)
+        if not credential:
+            raise ValueError(f"{url} sold no credit (status {response.status_code})")
+        self._sessions[prefix] = credential
+        return credential
+
+    async def request(
+        self,
+        method: str,
+        url: str,
+        *,
+        headers: Optional[Dict[str, str]] = None,
+        **kwargs: Any,
+    ) -> httpx.Response:
+        """Request ``url`` with its prepayment, paying a 402 if there's none."""
+        headers = dict(headers or {})
+        credential = self._session_for(url)
+        if credential is not None:
+            headers[X402_CREDIT_HEADER] = credential
+        elif url in self._prepaid:
+            headers[X402_PAYMENT_HEADER] = self._prepaid.pop(url)
+        return await self._send(method, url, headers, **kwargs)
+
+    async def get(self, url: str, **kwargs: Any) -> httpx.Response:
+        """Make a GET request."""
+        return await self.request("GET", url, **kwargs)
+
+    async def crawl(self, urls: Iterable[str]) -> AsyncIterator[Tuple[str, httpx.Response]]:
+        """GET ``urls`` concurrently, yielding each response as it arrives."""
+        semaphore = asyncio.Semaphore(self._concurrency)
+
+        async def fetch(url: str) -> Tuple[str, httpx.Response]:
+            async with semaphore:
+                return url, await self.get(url)
+
+        tasks: List["asyncio.Task[Tuple[str, httpx.Response]]"] = [
+            asyncio.ensure_future(fetch(url)) for url in urls
+        ]
+        try:
+            for task in asyncio.as_completed(tasks):
+                yield await task
+        finally:
+            for task in tasks:
+                task.cancel()
+
+    def _session_for(self, url: str) -> Optional[str]:
+        matches = [prefix for prefix in self._sessions if _under_prefix(url, prefix)]
+        return self._sessions[max(matches, key=len)] if matches else None
+
+    async def _send(self, method: str, url: str, headers: Dict[str, str], **kwargs: Any) -> httpx.Response:
+        """Send a request, paying a 402 the usual way."""
+        response = await self._client.request(method, url, headers=headers, **kwargs)
+        payment_header = headers.get(X402_PAYMENT_HEADER)
+        if response.status_code == 402:
+            self._nonce += 1
+            payment_header = await payment_header_for(
+                response.headers,
+                self._signer,
+                self._nonce,
+                max_amount=self._max_amount,
+                body=response.content,
+                networks=self._networks,
+                balance_check=self._balance_check,
+            )
+            credential = headers.pop(X402_CREDIT_HEADER, None)
+            if credential is not None:
+                # The credit ran out; pay per request from now on
+                self._sessions = {prefix: c for prefix, c in self._sessions.items() if c != credential}
+            if payment_header is None:
+                return response
+            headers = dict(headers)
+            headers[X402_PAYMENT_HEADER] = payment_header
+            response = await self._client.request(method, url, headers=headers, **kwargs)
+        if payment_header is not None and self._receipts is not None:
+            record_receipt(self._receipts, payment_header, response.headers, str(response.url))
+        return response
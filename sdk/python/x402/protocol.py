@@ -6,9 +6,9 @@ falling back to pure Python for compatibility.
 
 import base64
 import json
-from typing import Any, Tuple
+from typing import Any, List, Mapping, Optional, Tuple, Union
 
-from x402.types import PaymentRequirements, SignedPayment, PaymentPayload
+from x402.types import PaymentRequirements, SignedPayment, PaymentPayload, SettlementReceipt, Split
 
 # Try to import native Rust bindings
 try:
@@ -28,6 +28,41 @@ except ImportError:
 # Header names
 X402_REQUIREMENTS_HEADER = "X-Payment-Requirements"
 X402_PAYMENT_HEADER = "X-Payment"
+X402_PAYMENT_RESPONSE_HEADER = "X-Payment-Response"
+
+# Tag prefix of JSON header values written by the Rust core
+_JSON_HEADER_TAG = "x402.v2.json."
+
+
+def _decode_header_json(header: str) -> Any:
+    """Decode a tagged or untagged base64 JSON header value."""
+    header = header.strip()
+    if header.startswith("x402."):
+        if not header.startswith(_JSON_HEADER_TAG):
+            raise ValueError(f"unsupported header format: {header.split('.', 3)[:3]}")
+        header = header[len(_JSON_HEADER_TAG):]
+    return json.loads(base64.b64decode(header))
+
+
+def to_native_payload(payload: PaymentPayload) -> "NativePaymentPayload":
+    """Convert a payment payload to its native counterpart.
+    
+    Requires the native bindings.
+    """
+    return NativePaymentPayload(
+        amount=payload.amount,
+        recipient=payload.recipient,
+        payer=payload.payer,
+        chain_id=payload.chain_id,
+        resource=payload.resource,
+        nonce=payload.nonce,
+        expires_at=payload.expires_at,
+        token=payload.token,
+        scheme=payload.scheme,
+        extra=payload.extra,
+        invoice_id=payload.invoice_id,
+        splits=[(split.recipient, split.share) for split in payload.splits],
+    )
 
 
 def encode_requirements_header(requirements: PaymentRequirements) -> str:
@@ -51,6 +86,12 @@ def encode_requirements_header(requirements: PaymentRequirements) -> str:
             token=requirements.token,
             description=requirements.description,
             expires_at=requirements.expires_at,
+            scheme=requirements.scheme,
+            mime_type=requirements.mime_type,
+            output_schema=requirements.output_schema,
+            max_timeout_seconds=requirements.max_timeout_seconds,
+            extra=requirements.extra,
+            splits=[(split.recipient, split.share) for split in requirements.splits],
         )
         return _native_encode_requirements(native_req)
     
@@ -79,17 +120,22 @@ def decode_requirements_header(header: str) -> PaymentRequirements:
         return PaymentRequirements(
             amount=native_req.amount,
             recipient=native_req.recipient,
-            network=native_req.network,
+            network=native_req.network.value,
             token=None,  # TODO: handle token
             description=native_req.description,
             expires_at=native_req.expires_at,
             resource=native_req.resource,
+            scheme=native_req.scheme,
+            mime_type=native_req.mime_type,
+            output_schema=native_req.output_schema,
+            max_timeout_seconds=native_req.max_timeout_seconds,
+            extra=native_req.extra,
+            splits=[Split(recipient=r, share=share) for r, share in native_req.splits],
         )
     
     # Fallback: pure Python
     try:
-        json_bytes = base64.b64decode(header)
-        data = json.loads(json_bytes)
+        data = _decode_header_json(header)
         return PaymentRequirements(**data)
     except Exception as e:
         raise ValueError(f"Invalid X-Payment-Requirements header: {e}")
@@ -108,17 +154,7 @@ def encode_payment_header(payment: SignedPayment) -> str:
     """
     if _USE_NATIVE:
         # Use Rust implementation
-        native_payload = NativePaymentPayload(
-            amount=payment.payment.amount,
-            recipient=payment.payment.recipient,
-            payer=payment.payment.payer,
-            chain_id=payment.payment.chain_id,
-            resource=payment.payment.resource,
-            nonce=payment.payment.nonce,
-            expires_at=payment.payment.expires_at,
-            token=payment.payment.token,
-        )
-        return _native_encode_payment(native_payload, payment.signature)
+        return _native_encode_payment(to_native_payload(payment.payment), payment.signature)
     
     # Fallback: pure Python
     data = {
@@ -145,7 +181,8 @@ def decode_payment_header(header: str) -> SignedPayment:
     """
     if _USE_NATIVE:
         # Use Rust implementation
-        native_payload, signature = _native_decode_payment(header)
+        native_signed = _native_decode_payment(header)
+        native_payload, signature = native_signed.payment, native_signed.signature
         payload = PaymentPayload(
             amount=native_payload.amount,
             recipient=native_payload.recipient,
@@ -155,13 +192,16 @@ def decode_payment_header(header: str) -> SignedPayment:
             resource=native_payload.resource,
             nonce=native_payload.nonce,
             expires_at=native_payload.expires_at,
+            scheme=native_payload.scheme,
+            extra=native_payload.extra,
+            invoice_id=native_payload.invoice_id,
+            splits=[Split(recipient=r, share=share) for r, share in native_payload.splits],
         )
         return SignedPayment(payment=payload, signature=bytes(signature))
     
     # Fallback: pure Python
     try:
-        json_bytes = base64.b64decode(header)
-        data = json.loads(json_bytes)
+        data = _decode_header_json(header)
         
         payment = PaymentPayload(**data["payment"])
         signature = bytes.fromhex(data["signature"])
@@ -169,3 +209,63 @@ def decode_payment_header(header: str) -> SignedPayment:
         return SignedPayment(payment=payment, signature=signature)
     except Exception as e:
         raise ValueError(f"Invalid X-Payment header: {e}")
+
+
+def decode_payment_response_header(header: str) -> SettlementReceipt:
+    """Decode a settlement receipt from an ``X-Payment-Response`` header value.
+    
+    Args:
+        header: Base64-encoded header value
+        
+    Returns:
+        Decoded SettlementReceipt
+        
+    Raises:
+        ValueError: If header is invalid
+    """
+    try:
+        return SettlementReceipt.model_validate(_decode_header_json(header))
+    except Exception as e:
+        raise ValueError(f"Invalid X-Payment-Response header: {e}")
+
+
+def decode_payment_options(
+    headers: Mapping[str, str],
+    body: Optional[Union[bytes, str]] = None,
+) -> List[PaymentRequirements]:
+    """Decode the ways a 402 response accepts payment.
+    
+    The JSON body's ``accepts`` list is preferred since it can offer several
+    options (e.g. one per network); the requirements header is used when the
+    body is missing or not an x402 body. Options that fail to parse are
+    skipped.
+    
+    Args:
+        headers: Headers of the 402 response
+        body: Body of the 402 response, if read
+        
+    Returns:
+        Acceptable requirements in the server's order of preference; empty
+        if the response carries none
+    """
+    options = []
+    if isinstance(body, (bytes, str)) and body:
+        try:
+            accepts = json.loads(body).get("accepts", [])
+        except (ValueError, AttributeError):
+            accepts = []
+        for option in accepts if isinstance(accepts, list) else []:
+            try:
+                options.append(PaymentRequirements.model_validate(option))
+            except ValueError:
+                continue
+    if options:
+        return options
+    
+    header = headers.get(X402_REQUIREMENTS_HEADER)
+    if not header:
+        return []
+    try:
+        return [decode_requirements_header(header)]
+    except ValueError:
+        return []
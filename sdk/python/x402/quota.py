@@ -0,0 +1,125 @@
+"""Free-tier quotas: a few free requests per client before the 402.
+
+Pass a ``FreeTier`` to ``X402Middleware`` or ``X402WSGIMiddleware``::
+
+    free_tier = FreeTier(limit=10)                          # 10 per IP, ever
+    free_tier = FreeTier(limit=100, by="address", window=86400)  # daily
+
+Unpaid requests to priced paths pass through while the client has quota
+left; after that they get the usual 402. Requests carrying a payment are
+verified as usual and don't use up quota.
+
+Clients are identified by IP address or by the address they send in
+``X-Payer-Address``. The latter is self-reported, so anyone can claim a
+fresh one: use it for trials where that abuse is acceptable.
+
+Counts live in a ``QuotaStore``; ``MemoryQuotaStore`` keeps them in
+process, and anything with an atomic increment (Redis ``INCR`` with
+``EXPIRE``, a SQL upsert) can back a shared one.
+"""
+
+import heapq
+import re
+import threading
+import time
+from typing import Callable, Dict, List, Optional, Protocol, Tuple
+
+# Header in which clients identify themselves for ``by="address"``
+X402_PAYER_ADDRESS_HEADER = "X-Payer-Address"
+
+_ADDRESS = re.compile(r"^0x[0-9a-fA-F]{40}$")
+
+
+class QuotaStore(Protocol):
+    """Counts requests per client."""
+
+    def increment(self, key: str, window: Optional[int]) -> int:
+        """Count one request for ``key`` and return the count so far.
+
+        Args:
+            key: The client
+            window: Seconds after the first counted request at which the
+                count resets, or None to never reset
+        """
+        ...
+
+
+class MemoryQuotaStore:
+    """In-process ``QuotaStore``.
+
+    Counts whose window has passed are dropped on the next increment, so
+    one-off clients don't accumulate; a heap ordered by when windows end
+    finds them without scanning every count. Counts without a window are
+    kept.
+
+    Args:
+        clock: Current unix time; defaults to ``time.time``
+    """
+
+    def __init__(self, clock: Callable[[], float] = time.time):
+        self._clock = clock
+        self._counts: Dict[str, Tuple[int, float, Optional[int]]] = {}
+        # (window end, key, window start) of each windowed count
+        self._ends: List[Tuple[float, str, float]] = []
+        self._lock = threading.Lock()
+
+    def increment(self, key: str, window: Optional[int]) -> int:
+        now = self._clock()
+        with self._lock:
+            while self._ends and self._ends[0][0] <= now:
+                _, expired, started = heapq.heappop(self._ends)
+                if expired in self._counts and self._counts[expired][1] == started:
+                    del self._counts[expired]
+            if key not in self._counts:
+                self._counts[key] = (0, now, window)
+                if window is not None:
+                    heapq.heappush(self._ends, (now + window, key, now))
+            count, started, window = self._counts[key]
+            self._counts[key] = (count + 1, started, window)
+            return count + 1
+
+
+class FreeTier:
+    """Allows each client ``limit`` unpaid requests.
+
+    Args:
+        limit: Free requests per client (per window)
+        by: ``"ip"`` or ``"address"`` (the ``X-Payer-Address`` header)
+        window: Seconds after which a client's count resets; None for a
+            one-off trial
+        store: Where counts live; a fresh ``MemoryQuotaStore`` by default
+    """
+
+    def __init__(
+        self,
+        limit: int,
+        by: str = "ip",
+        window: Optional[int] = None,
+        store: Optional[QuotaStore] = None,
+    ):
+        if limit < 0:
+            raise ValueError("limit must not be negative")
+        if by not in ("ip", "address"):
+            raise ValueError(f"by must be 'ip' or 'address', not {by!r}")
+        self.limit = limit
+        self.by = by
+        self.window = window
+        self._store = store if store is not None else MemoryQuotaStore()
+
+    def client(self, ip: Optional[str], address: Optional[str]) -> Optional[str]:
+        """The client a request counts against, or None if unidentified."""
+        if self.by == "ip":
+            return ip or None
+        if address and _ADDRESS.match(address.strip()):
+            return address.strip().lower()
+        return None
+
+    def allow(self, ip: Optional[str], address: Optional[str] = None) -> bool:
+        """Count an unpaid request and report whether it is free.
+
+        Unidentified clients never get free requests.
+        """
+        client = self.client(ip, address)
+        if client is None:
+            return False
+        return self._store.increment(f"{self.by}:{client}", self.window) <= self.limit
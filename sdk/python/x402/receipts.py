@@ -0,0 +1,212 @@
+"""Client-side settlement receipts: proof of what you paid for.
+
+Servers that settle a payment answer the paid request with an
+``X-Payment-Response`` header holding the settlement receipt. Give a client
+a ``ReceiptStore`` and it keeps every receipt, together with the resource,
+recipient and URL the payment was for::
+
+    receipts = SqliteReceiptStore("receipts.db")
+    client = X402Client(signer, receipts=receipts)
+    ...
+    for stored in receipts.query(recipient="0x1111...", since=start_of_month):
+        print(stored.resource, stored.receipt.transaction_hash)
+
+``X402Auth`` and ``X402Adapter`` take the same ``receipts`` argument.
+
+Receipts whose payer or nonce don't match the payment sent are not
+stored, and a missing or malformed header never fails the request.
+"""
+
+import sqlite3
+import threading
+import time
+from dataclasses import dataclass
+from typing import Callable, List, Mapping, Optional, Protocol
+
+from x402.protocol import X402_PAYMENT_RESPONSE_HEADER, decode_payment_header, decode_payment_response_header
+from x402.types import SettlementReceipt
+
+
+@dataclass(frozen=True)
+class StoredReceipt:
+    """A settlement receipt and what it paid for."""
+
+    receipt: SettlementReceipt
+    resource: str
+    recipient: str
+    token: Optional[str]
+    url: str
+    paid_at: int
+
+
+class ReceiptStore(Protocol):
+    """Keeps settlement receipts."""
+
+    def save(self, stored: StoredReceipt) -> None:
+        """Keep ``stored``; saving the same receipt twice keeps one copy."""
+        ...
+
+    def query(
+        self,
+        *,
+        resource: Optional[str] = None,
+        recipient: Optional[str] = None,
+        since: Optional[int] = None,
+        until: Optional[int] = None,
+    ) -> List[StoredReceipt]:
+        """Receipts matching every given filter, oldest first.
+
+        Args:
+            resource: Exact resource paid for
+            recipient: Recipient address (case-insensitive)
+            since: Earliest ``paid_at`` (unix time, inclusive)
+            until: Latest ``paid_at`` (unix time, inclusive)
+        """
+        ...
+
+
+class SqliteReceiptStore:
+    """``ReceiptStore`` in a SQLite database.
+
+    Args:
+        path: Database file, created if missing; ``":memory:"`` for a
+            throwaway store
+    """
+
+    def __init__(self, path: str):
+        self._conn = sqlite3.connect(path, check_same_thread=False)
+        self._lock = threading.Lock()
+        with self._lock, self._conn:
+            self._conn.execute(
+                """CREATE TABLE IF NOT EXISTS x402_receipts (
+                    chain_id INTEGER NOT NULL,
+                    transaction_hash TEXT NOT NULL,
+                    nonce INTEGER NOT NULL,
+                    payer TEXT NOT NULL,
+                    amount TEXT NOT NULL,
+                    resource TEXT NOT NULL,
+                    recipient TEXT NOT NULL,
+                    token TEXT,
+                    url TEXT NOT NULL,
+                    paid_at INTEGER NOT NULL,
+                    PRIMARY KEY (chain_id, transaction_hash, nonce)
+                )"""
+            )
+            self._conn.execute("CREATE INDEX IF NOT EXISTS x402_receipts_resource ON x402_receipts (resource)")
+            self._conn.execute(
+                "CREATE INDEX IF NOT EXISTS x402_receipts_recipient ON x402_receipts (lower(recipient))"
+            )
+            self._conn.execute("CREATE INDEX IF NOT EXISTS x402_receipts_paid_at ON x402_receipts (paid_at)")
+
+    def close(self) -> None:
+        self._conn.close()
+
+    def save(self, stored: StoredReceipt) -> None:
+        receipt = stored.receipt
+        with self._lock, self._conn:
+            self._conn.execute(
+                "INSERT OR IGNORE INTO x402_receipts VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                (
+                    receipt.chain_id,
+                    receipt.transaction_hash,
+                    receipt.nonce,
+                    receipt.payer,
+                    str(receipt.amount),
+                    stored.resource,
+                    stored.recipient,
+                    stored.token,
+                    stored.url,
+                    stored.paid_at,
+                ),
+            )
+
+    def query(
+        self,
+        *,
+        resource: Optional[str] = None,
+        recipient: Optional[str] = None,
+        since: Optional[int] = None,
+        until: Optional[int] = None,
+    ) -> List[StoredReceipt]:
+        clauses, params = [], []
+        if resource is not None:
+            clauses.append("resource = ?")
+            params.append(resource)
+        if recipient is not None:
+            clauses.append("lower(recipient) = lower(?)")
+            params.append(recipient)
+        if since is not None:
+            clauses.append("paid_at >= ?")
+            params.append(since)
+        if until is not None:
+            clauses.append("paid_at <= ?")
+            params.append(until)
+        where = f" WHERE {' AND '.join(clauses)}" if clauses else ""
+        with self._lock:
+            rows = self._conn.execute(
+                "SELECT chain_id, transaction_hash, nonce, payer, amount, resource, recipient, token, url, paid_at "
+                f"FROM x402_receipts{where} ORDER BY paid_at, rowid",
+                params,
+            ).fetchall()
+        return [
+            StoredReceipt(
+                receipt=SettlementReceipt(
+                    chain_id=chain_id,
+                    transaction_hash=transaction_hash,
+                    nonce=nonce,
+                    payer=payer,
+                    amount=int(amount),
+                ),
+                resource=row_resource,
+                recipient=row_recipient,
+                token=token,
+                url=url,
+                paid_at=paid_at,
+            )
+            for (
+                chain_id, transaction_hash, nonce, payer, amount, row_resource, row_recipient, token, url, paid_at
+            ) in rows
+        ]
+
+
+def record_receipt(
+    store: ReceiptStore,
+    payment_header: str,
+    response_headers: Mapping[str, str],
+    url: str,
+    clock: Callable[[], float] = time.time,
+) -> Optional[StoredReceipt]:
+    """Save the receipt a paid response carries.
+
+    Shared by X402Client and the httpx/requests hooks.
+
+    Args:
+        store: Where to keep the receipt
+        payment_header: The ``X-Payment`` header the request was paid with
+        response_headers: Headers of the paid response
+        url: URL of the paid request
+        clock: Current unix time; defaults to ``time.time``
+
+    Returns:
+        The stored receipt, or None if the response has no usable one
+    """
+    header = response_headers.get(X402_PAYMENT_RESPONSE_HEADER)
+    if not header:
+        return None
+    try:
+        receipt = decode_payment_response_header(header)
+        payment = decode_payment_header(payment_header).payment
+    except ValueError:
+        return None
+    if receipt.payer.lower() != payment.payer.lower() or receipt.nonce != payment.nonce:
+        return None
+    stored = StoredReceipt(
+        receipt=receipt,
+        resource=payment.resource,
+        recipient=payment.recipient,
+        token=payment.token,
+        url=url,
+        paid_at=int(clock()),
+    )
+    store.save(stored)
+    return stored
@@ -0,0 +1,70 @@
+"""Replay protection: each payment nonce is accepted once.
+
+A signed payment stays valid until it expires, so a server that only
+verifies it would serve a captured ``X-Payment`` header again and again.
+``X402Middleware``, ``RequirePayment`` and ``X402WSGIMiddleware`` claim
+the nonce of every payment they accept in a ``NonceStore`` and refuse it
+the second time.
+
+``MemoryNonceStore`` keeps nonces in process, until their payments
+expire. Servers running several processes need a shared store: anything
+with an atomic insert-if-absent (Redis ``SET NX EXAT``, a SQL primary
+key) can back one.
+"""
+
+import heapq
+import threading
+import time
+from typing import Callable, Dict, List, Protocol, Tuple
+
+from x402.types import PaymentPayload
+
+
+class NonceStore(Protocol):
+    """Storage of the payment nonces already used."""
+
+    def claim(self, chain_id: int, payer: str, nonce: int, expires_at: int) -> bool:
+        """Record the nonce as used; False if it already was.
+
+        Args:
+            chain_id: Chain the payment is on
+            payer: Payer address
+            nonce: Nonce of the payment
+            expires_at: When the payment, and so the record, stops mattering
+        """
+        ...
+
+
+def claim_payment(store: NonceStore, payment: PaymentPayload) -> bool:
+    """``NonceStore.claim`` the nonce of ``payment``."""
+    return store.claim(payment.chain_id, payment.payer, payment.nonce, payment.expires_at)
+
+
+class MemoryNonceStore:
+    """In-process ``NonceStore`` that forgets nonces once their payments expire.
+
+    Args:
+        clock: Current unix time; defaults to ``time.time``
+    """
+
+    def __init__(self, clock: Callable[[], float] = time.time):
+        self._clock = clock
+        self._used: Dict[Tuple[int, str, int], int] = {}
+        self._by_expiry: List[Tuple[int, Tuple[int, str, int]]] = []
+        self._lock = threading.Lock()
+
+    def claim(self, chain_id: int, payer: str, nonce: int, expires_at: int) -> bool:
+        now = self._clock()
+        key = (chain_id, payer.lower(), nonce)
+        with self._lock:
+            while self._by_expiry and self._by_expiry[0][0] < now:
+                _, expired = heapq.heappop(self._by_expiry)
+                del self._used[expired]
+            if key in self._used:
+                return False
+            self._used[key] = expires_at
+            heapq.heappush(self._by_expiry, (expires_at, key))
+            return True
+
+    def __len__(self) -> int:
+        return len(self._used)
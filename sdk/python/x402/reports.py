@@ -0,0 +1,123 @@
+"""Spend reports over the client's payment history.
+
+Build a ``SpendReport`` from the receipts a client kept (see
+``x402.receipts``) and export it for reconciliation::
+
+    report = SpendReport.for_month(receipts, 2024, 5)
+    with open("x402-2024-05.csv", "w", newline="") as f:
+        f.write(report.export_csv())
+
+Amounts are in the token's smallest unit, as decimal strings so large
+values survive spreadsheets and JSON parsers; native-token payments have
+an empty token. Timestamps are ISO 8601 in UTC.
+"""
+
+import calendar
+import csv
+import io
+import json
+from datetime import datetime, timezone
+from typing import Dict, List, Optional, Sequence, Tuple
+
+from x402.receipts import ReceiptStore, StoredReceipt
+
+# Columns of ``SpendReport.export_csv``, and keys of each exported payment
+REPORT_COLUMNS = (
+    "timestamp",
+    "amount",
+    "token",
+    "chain_id",
+    "recipient",
+    "resource",
+    "transaction_hash",
+    "payer",
+    "url",
+)
+
+# Leading characters that make spreadsheets evaluate a cell
+_FORMULA_PREFIXES = ("=", "+", "-", "@")
+
+
+class SpendReport:
+    """Payments made over a period, oldest first.
+
+    Args:
+        payments: Stored receipts to report on
+    """
+
+    def __init__(self, payments: Sequence[StoredReceipt]):
+        self.payments: List[StoredReceipt] = sorted(payments, key=lambda stored: stored.paid_at)
+
+    @classmethod
+    def from_store(
+        cls,
+        store: ReceiptStore,
+        *,
+        since: Optional[int] = None,
+        until: Optional[int] = None,
+        recipient: Optional[str] = None,
+    ) -> "SpendReport":
+        """Report on the receipts in ``store`` paid between ``since`` and
+        ``until`` (unix times, inclusive), optionally to one recipient."""
+        return cls(store.query(since=since, until=until, recipient=recipient))
+
+    @classmethod
+    def for_month(cls, store: ReceiptStore, year: int, month: int) -> "SpendReport":
+        """Report on one calendar month (UTC)."""
+        start = calendar.timegm((year, month, 1, 0, 0, 0))
+        days = calendar.monthrange(year, month)[1]
+        return cls.from_store(store, since=start, until=start + days * 86400 - 1)
+
+    def totals(self) -> Dict[Tuple[int, str], int]:
+        """Amount paid per ``(chain_id, token)``; token is ``""`` for native."""
+        totals: Dict[Tuple[int, str], int] = {}
+        for stored in self.payments:
+            key = (stored.receipt.chain_id, stored.token or "")
+            totals[key] = totals.get(key, 0) + stored.receipt.amount
+        return totals
+
+    def rows(self) -> List[Dict[str, str]]:
+        """One dict per payment, keyed by ``REPORT_COLUMNS``."""
+        return [
+            {
+                "timestamp": _iso(stored.paid_at),
+                "amount": str(stored.receipt.amount),
+                "token": stored.token or "",
+                "chain_id": str(stored.receipt.chain_id),
+                "recipient": stored.recipient,
+                "resource": stored.resource,
+                "transaction_hash": stored.receipt.transaction_hash,
+                "payer": stored.receipt.payer,
+                "url": stored.url,
+            }
+            for stored in self.payments
+        ]
+
+    def export_csv(self) -> str:
+        """The payments as CSV with a header row.
+
+        Resources and URLs come from the servers paid, so any that a
+        spreadsheet would read as a formula are prefixed with ``'``.
+        """
+        out = io.StringIO()
+        writer = csv.DictWriter(out, fieldnames=REPORT_COLUMNS, lineterminator="\n")
+        writer.writeheader()
+        for row in self.rows():
+            writer.writerow({**row, "resource": _cell(row["resource"]), "url": _cell(row["url"])})
+        return out.getvalue()
+
+    def export_json(self) -> str:
+        """The payments and per-token totals as a JSON document."""
+        totals = [
+            {"chain_id": str(chain_id), "token": token, "amount": str(amount)}
+            for (chain_id, token), amount in sorted(self.totals().items())
+        ]
+        return json.dumps({"payments": self.rows(), "totals": totals}, indent=2)
+
+
+def _cell(value: str) -> str:
+    return "'" + value if value.startswith(_FORMULA_PREFIXES) else value
+
+
+def _iso(timestamp: int) -> str:
+    return datetime.fromtimestamp(timestamp, tz=timezone.utc).strftime("%Y-%m-%dT%H:%M:%SZ")
@@ -0,0 +1,89 @@
+"""requests integration.
+
+``X402Adapter`` pays for 402 responses on a ``requests.Session``, the same
+way ``X402Client`` does::
+
+    session = requests.Session()
+    session.mount("https://", X402Adapter(signer, max_amount=1_000_000))
+    response = session.get("https://api.example.com/premium")
+
+The signer is async and is run with ``asyncio.run``, so the session must
+not be used from inside a running event loop. Requests with streaming
+bodies can't be retried and get the 402 back.
+
+Requires ``pip install x402[requests]``.
+"""
+
+import asyncio
+import time
+from typing import Any, Optional, Sequence, Union
+
+from requests import PreparedRequest, Response
+from requests.adapters import HTTPAdapter
+
+from x402.client import BalanceCheck, payment_header_for
+from x402.protocol import X402_PAYMENT_HEADER
+from x402.receipts import ReceiptStore, record_receipt
+from x402.signer.base import Signer
+from x402.types import Network
+
+
+class X402Adapter(HTTPAdapter):
+    """Transport adapter that answers 402 responses with a signed payment.
+    
+    Args:
+        signer: Signer to pay with
+        max_amount: Maximum amount to pay per request. None = no limit
+        receipts: Where to keep settlement receipts of paid requests
+        networks: Networks to pay on, most preferred first. None = any
+        balance_check: Skips options the payer can't cover
+        **kwargs: Passed to ``HTTPAdapter``
+    """
+    
+    def __init__(
+        self,
+        signer: Signer,
+        *,
+        max_amount: Optional[int] = None,
+        receipts: Optional[ReceiptStore] = None,
+        networks: Optional[Sequence[Union[Network, str]]] = None,
+        balance_check: Optional[BalanceCheck] = None,
+        **kwargs: Any,
+    ):
+        super().__init__(**kwargs)
+        self._signer = signer
+        self._max_amount = max_amount
+        self._receipts = receipts
+        self._networks = networks
+        self._balance_check = balance_check
+        self._nonce = int(time.time() * 1000)
+    
+    def send(self, request: PreparedRequest, **kwargs: Any) -> Response:  # type: ignore[override]
+        response = super().send(request, **kwargs)
+        if response.status_code != 402 or _is_stream(request):
+            return response
+        
+        self._nonce += 1
+        payment_header = asyncio.run(payment_header_for(
+            response.headers,
+            self._signer,
+            self._nonce,
+            max_amount=self._max_amount,
+            body=response.content,
+            networks=self._networks,
+            balance_check=self._balance_check,
+        ))
+        if not payment_header:
+            return response
+        
+        retry = request.copy()
+        retry.headers[X402_PAYMENT_HEADER] = payment_header
+        response.close()
+        paid = super().send(retry, **kwargs)
+        if self._receipts is not None:
+            record_receipt(self._receipts, payment_header, paid.headers, retry.url or "")
+        return paid
+
+
+def _is_stream(request: PreparedRequest) -> bool:
+    return request.body is not None and not isinstance(request.body, (bytes, str))
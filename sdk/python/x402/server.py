@@ -0,0 +1,36 @@
+"""Framework-independent helpers for gating endpoints on payment.
+
+The framework integrations (``x402.fastapi``, ``x402.wsgi``) build their 402
+responses with these, so every server answers with the same headers and
+body.
+"""
+
+from typing import Any, Dict, Optional
+
+from x402.types import PaymentRequirements
+from x402.protocol import X402_REQUIREMENTS_HEADER, encode_requirements_header
+
+# Protocol version reported in 402 bodies, matching the Rust core
+X402_VERSION = 1
+
+
+def payment_required_headers(requirements: PaymentRequirements) -> Dict[str, str]:
+    """Headers of a 402 response for ``requirements``."""
+    return {X402_REQUIREMENTS_HEADER: encode_requirements_header(requirements)}
+
+
+def payment_required_body(
+    requirements: PaymentRequirements,
+    error: Optional[str] = None,
+) -> Dict[str, Any]:
+    """JSON body of a 402 response for ``requirements``.
+    
+    Args:
+        requirements: What the client has to pay
+        error: Why a submitted payment was rejected, if one was
+    """
+    body: Dict[str, Any] = {"x402Version": X402_VERSION}
+    if error is not None:
+        body["error"] = error
+    body["accepts"] = [requirements.model_dump(mode="json")]
+    return body
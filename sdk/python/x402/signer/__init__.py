@@ -2,17 +2,21 @@
 
 The signer interface allows pluggable signing backends:
 - LocalSigner: For development (loads key from env/file)
+- NativeSigner: For development, signing in the native bindings
 - AWSKMSSigner: AWS Key Management Service
 - VaultSigner: HashiCorp Vault Transit
 - Custom: Implement the Signer protocol
 """
 
-from x402.signer.base import Signer
+from x402.signer.base import AsyncSigner, Signer
 from x402.signer.local import LocalSigner
+from x402.signer.native import NativeSigner
 from x402.signer.aws_kms import AWSKMSSigner
 
 __all__ = [
+    "AsyncSigner",
     "Signer",
     "LocalSigner",
+    "NativeSigner",
     "AWSKMSSigner",
 ]
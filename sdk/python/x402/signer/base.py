@@ -7,7 +7,7 @@ from x402.types import PaymentPayload
 
 
 @runtime_checkable
-class Signer(Protocol):
+class AsyncSigner(Protocol):
     """Protocol for x402 payment signers.
     
     Implement this interface to use custom signing backends
@@ -37,6 +37,10 @@ class Signer(Protocol):
         ...
 
 
+# Original name of AsyncSigner
+Signer = AsyncSigner
+
+
 class BaseSigner(ABC):
     """Abstract base class for signers."""
     
@@ -0,0 +1,93 @@
+"""Signer backed by the native Rust bindings.
+
+Signs with ``x402_native.sign_payment``, so clients that only need to
+produce signatures don't have to install eth-account.
+
+WARNING: This signer loads private keys into memory.
+Use KMS/HSM signers in production!
+"""
+
+import os
+
+from x402.signer.base import BaseSigner
+from x402.types import PaymentPayload
+
+try:
+    from x402_native import sign_payment as _native_sign, private_key_address as _native_address
+    _HAS_NATIVE_SIGNING = True
+except ImportError:
+    _HAS_NATIVE_SIGNING = False
+
+
+class NativeSigner(BaseSigner):
+    """Local signer using the native bindings.
+    
+    Requires ``x402-native`` built with the ``signing`` feature.
+    Signatures are over ``payload.message_hash()`` itself, which is what
+    the native verifier checks.
+    
+    WARNING: Only use for development/testing!
+    Private keys are loaded into memory.
+    """
+    
+    def __init__(self, private_key: str):
+        """Initialize with a private key.
+        
+        Args:
+            private_key: Private key as 0x-prefixed hex string
+            
+        Raises:
+            ImportError: If native signing is not available
+            ValueError: If the key is invalid
+        """
+        if not _HAS_NATIVE_SIGNING:
+            raise ImportError(
+                "NativeSigner requires x402-native built with the 'signing' feature. "
+                "Rebuild it with: maturin build --features signing"
+            )
+        self._private_key = private_key
+        self._address = _native_address(private_key)
+    
+    @classmethod
+    def from_env(cls, env_var: str = "X402_PRIVATE_KEY") -> "NativeSigner":
+        """Create signer from environment variable.
+        
+        Args:
+            env_var: Name of environment variable containing private key
+            
+        Raises:
+            ValueError: If environment variable is not set
+        """
+        private_key = os.environ.get(env_var)
+        if not private_key:
+            raise ValueError(
+                f"Environment variable {env_var} is not set. "
+                f"Set it to your private key (0x-prefixed hex string)."
+            )
+        return cls(private_key)
+    
+    @staticmethod
+    def is_available() -> bool:
+        """Whether the native bindings were built with signing support."""
+        return _HAS_NATIVE_SIGNING
+    
+    async def sign_payment(self, payload: PaymentPayload) -> bytes:
+        """Sign a payment payload.
+        
+        Args:
+            payload: Payment payload to sign
+            
+        Returns:
+            65-byte signature (r + s + v)
+        """
+        from x402.protocol import to_native_payload
+        
+        return bytes(_native_sign(to_native_payload(payload), self._private_key))
+    
+    async def get_address(self) -> str:
+        """Get the signer's address.
+        
+        Returns:
+            Checksummed Ethereum address
+        """
+        return self._address
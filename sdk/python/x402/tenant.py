@@ -0,0 +1,123 @@
+"""Multi-tenant recipient routing, as in the Rust core's ``tenant`` module.
+
+API marketplaces serve many sellers from one server, each paid at their
+own address and price. A ``RecipientResolver`` maps each request's host
+and path to a ``TenantRoute``; ``RouteTable`` is the static
+implementation. The tenant a caller names in ``X-Tenant`` is forgeable, so
+it only chooses among the routes the host and path already match.
+
+Pass a resolver to ``X402Middleware`` or ``X402WSGIMiddleware``: the
+requirements their ``pricing`` callable returns become a template the
+route overrides the recipient and amount of, and priced paths no tenant
+serves get a 404. Handlers read the tenant with ``get_tenant``.
+
+Example:
+    routes = RouteTable([
+        ("/alice", TenantRoute("alice", "0xA11ce...", 10_000)),
+        ("/bob", TenantRoute("bob", "0xB0b...", 20_000)),
+    ]).route("/", TenantRoute("carol", "0xCa401...", 5_000), host="carol.example")
+    app.add_middleware(X402Middleware, pricing=pricing, resolver=routes)
+"""
+
+from dataclasses import dataclass
+from typing import Iterable, List, Optional, Protocol, Tuple
+
+from x402.types import PaymentRequirements
+
+# Header naming the tenant a request is for
+X402_TENANT_HEADER = "X-Tenant"
+
+
+@dataclass(frozen=True)
+class TenantRoute:
+    """Who gets paid for a request, and how much.
+
+    Attributes:
+        tenant: Tenant name
+        recipient: Address the tenant is paid at
+        amount: Price in the token's smallest unit
+    """
+
+    tenant: str
+    recipient: str
+    amount: int
+
+    def apply(self, template: PaymentRequirements, resource: str) -> PaymentRequirements:
+        """``template`` paying this tenant for ``resource``."""
+        return template.model_copy(update={"recipient": self.recipient, "amount": self.amount, "resource": resource})
+
+
+class RecipientResolver(Protocol):
+    """Runtime mapping of requests to the tenant that gets paid."""
+
+    def resolve(
+        self, resource: str, host: Optional[str], tenant: Optional[str]
+    ) -> Optional[TenantRoute]:
+        """Route for ``resource`` requested from ``host``, or None if no tenant serves it.
+
+        Args:
+            resource: Request path
+            host: ``Host`` header of the request, if any
+            tenant: Tenant the caller named, if any; it may only narrow the
+                routes ``host`` and ``resource`` select
+        """
+        ...
+
+
+class RouteTable:
+    """``RecipientResolver`` over path prefixes; the first matching route wins.
+
+    Prefixes match on path-segment boundaries: ``/api`` matches ``/api``
+    and ``/api/data`` but not ``/apis``. Routes given a host only match
+    requests for it (compared without case or port); the others match any
+    host.
+    """
+
+    def __init__(self, routes: Iterable[Tuple[str, TenantRoute]] = ()):
+        self._routes: List[Tuple[Optional[str], str, TenantRoute]] = []
+        for prefix, route in routes:
+            self.route(prefix, route)
+
+    def route(self, prefix: str, route: TenantRoute, host: Optional[str] = None) -> "RouteTable":
+        """Route paths under ``prefix``, on ``host`` or any host, to ``route``."""
+        self._routes.append((host.lower() if host is not None else None, prefix.rstrip("/"), route))
+        return self
+
+    def resolve(
+        self, resource: str, host: Optional[str], tenant: Optional[str]
+    ) -> Optional[TenantRoute]:
+        host = _without_port(host).lower() if host is not None else None
+        for route_host, prefix, route in self._routes:
+            if route_host is not None and route_host != host:
+                continue
+            if tenant is not None and route.tenant != tenant:
+                continue
+            rest = resource[len(prefix):] if resource.startswith(prefix) else None
+            if rest is not None and (rest == "" or rest.startswith("/")):
+                return route
+        return None
+
+
+def _without_port(host: str) -> str:
+    """``host`` from a ``Host`` header value, without its port."""
+    name, colon, port = host.rpartition(":")
+    if colon and name and port.isdigit():
+        return name
+    return host
+
+
+def tenant_requirements(
+    resolver: RecipientResolver,
+    template: PaymentRequirements,
+    path: str,
+    host: Optional[str],
+    tenant: Optional[str],
+) -> Optional[Tuple[TenantRoute, PaymentRequirements]]:
+    """The route for ``path`` on ``host`` and ``template`` applied to it, or None.
+
+    The requirements keep the template's resource.
+    """
+    route = resolver.resolve(path, host, tenant)
+    if route is None:
+        return None
+    return route, route.apply(template, template.resource)
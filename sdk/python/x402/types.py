@@ -1,34 +1,90 @@
 """Core types for x402 payments."""
 
+import json
+import unicodedata
 from enum import Enum
-from typing import Optional
-from pydantic import BaseModel, Field, ConfigDict
+from typing import Any, Optional
+from pydantic import BaseModel, Field, ConfigDict, field_serializer, field_validator
+from pydantic.alias_generators import to_camel
 from eth_typing import ChecksumAddress
 
 
+def canonical_json(value: Any) -> str:
+    """Canonical JSON for signed messages, matching the Rust core.
+
+    Keys sorted, no whitespace, UTF-8 strings, integer numbers only.
+    """
+    _check_canonical(value)
+    return json.dumps(value, sort_keys=True, separators=(",", ":"), ensure_ascii=False)
+
+
+def _check_canonical(value: Any) -> None:
+    if isinstance(value, float):
+        raise ValueError(f"non-integer number {value} has no canonical form; send it as a string")
+    if isinstance(value, list):
+        for item in value:
+            _check_canonical(item)
+    elif isinstance(value, dict):
+        for item in value.values():
+            _check_canonical(item)
+
+
 class Network(str, Enum):
     """Supported blockchain networks."""
     
     ETHEREUM = "ethereum"
+    SEPOLIA = "sepolia"
     BASE = "base"
     BASE_SEPOLIA = "base_sepolia"
     ARBITRUM = "arbitrum"
+    ARBITRUM_SEPOLIA = "arbitrum_sepolia"
     OPTIMISM = "optimism"
+    OPTIMISM_SEPOLIA = "optimism_sepolia"
     POLYGON = "polygon"
+    POLYGON_AMOY = "polygon_amoy"
+    AVALANCHE = "avalanche"
+    AVALANCHE_FUJI = "avalanche_fuji"
+    BNB = "bnb"
+    BNB_TESTNET = "bnb_testnet"
+    ZKSYNC = "zksync"
+    ZKSYNC_SEPOLIA = "zksync_sepolia"
+    LINEA = "linea"
+    LINEA_SEPOLIA = "linea_sepolia"
+    SCROLL = "scroll"
+    SCROLL_SEPOLIA = "scroll_sepolia"
 
     @property
     def chain_id(self) -> int:
         """Get the chain ID for this network."""
         chain_ids = {
             Network.ETHEREUM: 1,
+            Network.SEPOLIA: 11155111,
             Network.BASE: 8453,
             Network.BASE_SEPOLIA: 84532,
             Network.ARBITRUM: 42161,
+            Network.ARBITRUM_SEPOLIA: 421614,
             Network.OPTIMISM: 10,
+            Network.OPTIMISM_SEPOLIA: 11155420,
             Network.POLYGON: 137,
+            Network.POLYGON_AMOY: 80002,
+            Network.AVALANCHE: 43114,
+            Network.AVALANCHE_FUJI: 43113,
+            Network.BNB: 56,
+            Network.BNB_TESTNET: 97,
+            Network.ZKSYNC: 324,
+            Network.ZKSYNC_SEPOLIA: 300,
+            Network.LINEA: 59144,
+            Network.LINEA_SEPOLIA: 59141,
+            Network.SCROLL: 534352,
+            Network.SCROLL_SEPOLIA: 534351,
         }
         return chain_ids[self]
 
+    @property
+    def is_testnet(self) -> bool:
+        """Whether this is a test network, whose tokens have no value."""
+        return self in _TESTNETS
+
     @classmethod
     def from_chain_id(cls, chain_id: int) -> Optional["Network"]:
         """Get network from chain ID."""
@@ -38,6 +94,31 @@ class Network(str, Enum):
         return None
 
 
+_TESTNETS = frozenset({
+    Network.SEPOLIA,
+    Network.BASE_SEPOLIA,
+    Network.ARBITRUM_SEPOLIA,
+    Network.OPTIMISM_SEPOLIA,
+    Network.POLYGON_AMOY,
+    Network.AVALANCHE_FUJI,
+    Network.BNB_TESTNET,
+    Network.ZKSYNC_SEPOLIA,
+    Network.LINEA_SEPOLIA,
+    Network.SCROLL_SEPOLIA,
+})
+
+
+class Split(BaseModel):
+    """One output of a split payment."""
+
+    recipient: str = Field(..., description="Recipient address")
+    share: int = Field(..., description="Amount paid to this recipient, in smallest unit")
+
+    @field_serializer("share")
+    def _serialize_share(self, share: int) -> str:
+        return str(share)
+
+
 class PaymentRequirements(BaseModel):
     """Payment requirements returned in 402 response."""
     
@@ -48,9 +129,26 @@ class PaymentRequirements(BaseModel):
     description: Optional[str] = Field(None, description="Human-readable description")
     expires_at: Optional[int] = Field(None, description="Payment expiry (unix timestamp)")
     resource: str = Field(..., description="Resource being paid for")
+    scheme: str = Field("exact", description="Payment scheme: 'exact' or 'upto'")
+    mime_type: Optional[str] = Field(None, description="Media type of the paid resource")
+    output_schema: Optional[dict[str, Any]] = Field(None, description="JSON Schema of the paid response")
+    max_timeout_seconds: Optional[int] = Field(None, description="Longest accepted payment validity window (seconds)")
+    extra: dict[str, Any] = Field(default_factory=dict, description="Application metadata, covered by the quote commitment")
+    splits: list[Split] = Field(default_factory=list, description="Revenue split outputs, summing to amount")
 
     model_config = ConfigDict(use_enum_values=True)
 
+    @field_serializer("amount")
+    def _serialize_amount(self, amount: int) -> str:
+        # Decimal string on the wire; JSON numbers lose precision above 2^53
+        return str(amount)
+
+    @field_validator("extra")
+    @classmethod
+    def _validate_extra(cls, extra: dict[str, Any]) -> dict[str, Any]:
+        _check_canonical(extra)
+        return extra
+
 
 class PaymentPayload(BaseModel):
     """Payment payload to be signed."""
@@ -63,22 +161,55 @@ class PaymentPayload(BaseModel):
     resource: str = Field(..., description="Resource being paid for")
     nonce: int = Field(..., description="Nonce for replay protection")
     expires_at: int = Field(..., description="Expiry timestamp")
+    scheme: str = Field("exact", description="Payment scheme: 'exact' or 'upto'")
+    extra: dict[str, Any] = Field(default_factory=dict, description="Application metadata, covered by the signature")
+    invoice_id: Optional[str] = Field(None, description="Idempotency key shared by retries of one purchase")
+    splits: list[Split] = Field(default_factory=list, description="Split outputs committed to by the signature")
+
+    @field_serializer("amount")
+    def _serialize_amount(self, amount: int) -> str:
+        return str(amount)
+
+    @field_validator("extra")
+    @classmethod
+    def _validate_extra(cls, extra: dict[str, Any]) -> dict[str, Any]:
+        _check_canonical(extra)
+        return extra
+
+    @field_validator("invoice_id")
+    @classmethod
+    def _validate_invoice_id(cls, invoice_id: Optional[str]) -> Optional[str]:
+        # A line break would forge lines of the signed message
+        if invoice_id is not None and any(unicodedata.category(c) == "Cc" for c in invoice_id):
+            raise ValueError("invoice ID contains control characters")
+        return invoice_id
 
     def message_hash(self) -> bytes:
         """Create the message hash to be signed."""
         from eth_hash.auto import keccak
+        from eth_utils import to_checksum_address
         
-        # Simplified hashing - matches Rust core
+        # Simplified hashing - matches Rust core, which writes addresses
+        # EIP-55 checksummed
         message = (
             f"x402 Payment\n"
             f"Amount: {self.amount}\n"
-            f"Recipient: {self.recipient}\n"
-            f"Payer: {self.payer}\n"
+            f"Recipient: {to_checksum_address(self.recipient)}\n"
+            f"Payer: {to_checksum_address(self.payer)}\n"
             f"ChainId: {self.chain_id}\n"
             f"Resource: {self.resource}\n"
             f"Nonce: {self.nonce}\n"
             f"Expires: {self.expires_at}"
         )
+        if self.scheme != "exact":
+            message += f"\nScheme: {self.scheme}"
+        if self.extra:
+            message += f"\nExtra: {canonical_json(self.extra)}"
+        if self.invoice_id is not None:
+            message += f"\nInvoice: {self.invoice_id}"
+        if self.splits:
+            outputs = ",".join(f"{to_checksum_address(split.recipient)}={split.share}" for split in self.splits)
+            message += f"\nSplits: {outputs}"
         
         return keccak(message.encode())
 
@@ -90,3 +221,42 @@ class SignedPayment(BaseModel):
     signature: bytes
 
     model_config = ConfigDict(arbitrary_types_allowed=True)
+
+
+class SettlementReceipt(BaseModel):
+    """Proof that a payment was settled, from an ``X-Payment-Response`` header."""
+
+    payer: str = Field(..., description="Payer address")
+    chain_id: int = Field(..., description="Network chain ID")
+    nonce: int = Field(..., description="Nonce of the settled payment")
+    amount: int = Field(..., description="Amount settled, in smallest unit")
+    transaction_hash: str = Field(..., description="Settlement transaction hash")
+
+    # camelCase on the wire, like the Rust core's receipts
+    model_config = ConfigDict(alias_generator=to_camel, populate_by_name=True)
+
+    @field_serializer("amount")
+    def _serialize_amount(self, amount: int) -> str:
+        return str(amount)
+
+
+class SupportedPaymentKind(BaseModel):
+    """A (scheme, network, token) combination a facilitator verifies and settles."""
+
+    x402_version: int = Field(1, description="x402 protocol version")
+    scheme: str = Field(..., description="Payment scheme, e.g. 'exact'")
+    network: str = Field(..., description="Network name, as the facilitator spells it")
+    token: Optional[str] = Field(None, description="Token address (None = native)")
+
+    model_config = ConfigDict(alias_generator=to_camel, populate_by_name=True)
+
+    def matches(self, requirements: PaymentRequirements) -> bool:
+        """Whether payments for ``requirements`` are of this kind."""
+        network = requirements.network.value if isinstance(requirements.network, Network) else requirements.network
+        token = requirements.token
+        return (
+            self.scheme == requirements.scheme
+            # The Rust core spells testnets without underscores ("basesepolia")
+            and self.network.replace("_", "") == str(network).replace("_", "")
+            and (self.token or "").lower() == (token or "").lower()
+        )
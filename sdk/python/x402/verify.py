@@ -1,16 +1,21 @@
 """Signature verification for x402 payments.
 
 This module uses the native Rust implementation via FFI when available,
-falling back to pure Python for compatibility.
+falling back to pure Python for compatibility. The fallback makes the same
+checks for ``exact`` and ``upto`` payments; other schemes need the native
+module.
 """
 
+import asyncio
+import functools
 import time
-from typing import Optional
+from typing import List, Optional, Tuple
 
 from eth_account.messages import encode_defunct
 from eth_account import Account
 
-from x402.types import PaymentRequirements, SignedPayment
+from x402.client import _get_chain_id
+from x402.types import PaymentRequirements, SignedPayment, Split
 from x402.protocol import decode_payment_header
 
 # Try to import native Rust bindings
@@ -43,6 +48,11 @@ class InsufficientAmountError(X402VerificationError):
         super().__init__(f"Insufficient amount: required {required}, got {provided}")
 
 
+class ValidityTooLongError(X402VerificationError):
+    """Payment is valid for longer than the requirements allow."""
+    pass
+
+
 class InvalidSignatureError(X402VerificationError):
     """Signature is invalid."""
     pass
@@ -67,8 +77,10 @@ def verify_payment(
         
     Raises:
         PaymentExpiredError: If payment has expired
+        ValidityTooLongError: If payment outlives max_timeout_seconds
         InsufficientAmountError: If amount is insufficient
-        InvalidSignatureError: If signature is invalid
+        InvalidSignatureError: If signature is invalid, or the payment is for
+            other terms than the requirements
     """
     if _USE_NATIVE:
         # Use Rust implementation for verification
@@ -81,18 +93,29 @@ def verify_payment(
                 token=requirements.token,
                 description=requirements.description,
                 expires_at=requirements.expires_at,
+                scheme=requirements.scheme,
+                max_timeout_seconds=requirements.max_timeout_seconds,
+                extra=requirements.extra,
+                splits=[(split.recipient, split.share) for split in requirements.splits],
             )
             return _native_verify(payment_header, native_req)
         except ValueError as e:
             error_msg = str(e)
             if "expired" in error_msg.lower():
                 raise PaymentExpiredError(error_msg)
+            elif "longer than the allowed" in error_msg:
+                raise ValidityTooLongError(error_msg)
             elif "insufficient" in error_msg.lower():
                 raise InsufficientAmountError(0, 0)  # TODO: parse amounts
             else:
                 raise InvalidSignatureError(error_msg)
     
-    # Fallback: pure Python implementation
+    # Fallback: pure Python implementation of the same checks
+    if requirements.scheme not in ("exact", "upto"):
+        raise InvalidSignatureError(
+            f"verifying {requirements.scheme} payments needs the x402_native module"
+        )
+
     # Decode the payment
     signed_payment = decode_payment_header(payment_header)
     payment = signed_payment.payment
@@ -101,7 +124,18 @@ def verify_payment(
     now = current_time or int(time.time())
     if payment.expires_at < now:
         raise PaymentExpiredError("Payment has expired")
+    max_timeout = requirements.max_timeout_seconds
+    if max_timeout is not None and payment.expires_at - now > max_timeout:
+        raise ValidityTooLongError(
+            f"Payment valid for {payment.expires_at - now}s, longer than the allowed {max_timeout}s"
+        )
     
+    # Check scheme
+    if payment.scheme != requirements.scheme:
+        raise InvalidSignatureError(
+            f"Scheme mismatch: expected {requirements.scheme}, got {payment.scheme}"
+        )
+
     # Check amount
     if payment.amount < requirements.amount:
         raise InsufficientAmountError(requirements.amount, payment.amount)
@@ -111,9 +145,29 @@ def verify_payment(
         raise InvalidSignatureError(
             f"Recipient mismatch: expected {requirements.recipient}, got {payment.recipient}"
         )
+
+    # Check token; None is the native coin
+    if _lower(payment.token) != _lower(requirements.token):
+        raise InvalidSignatureError(
+            f"Token mismatch: expected {requirements.token}, got {payment.token}"
+        )
+
+    # Check resource, ignoring trailing slashes and default ports
+    if _normalize_resource(payment.resource) != _normalize_resource(requirements.resource):
+        raise InvalidSignatureError(
+            f"Resource mismatch: payment is for {payment.resource}, not {requirements.resource}"
+        )
+
+    # Check split outputs: exactly the advertised split, accounting for the whole amount
+    if _splits(payment.splits) != _splits(requirements.splits):
+        raise InvalidSignatureError("Split mismatch: payment splits differ from the requirements")
+    if payment.splits and sum(split.share for split in payment.splits) != payment.amount:
+        raise InvalidSignatureError(
+            f"Split shares do not sum to the payment amount of {payment.amount}"
+        )
     
     # Check network
-    expected_chain_id = requirements.network.chain_id if hasattr(requirements.network, 'chain_id') else _get_chain_id(requirements.network)
+    expected_chain_id = _get_chain_id(requirements.network)
     if payment.chain_id != expected_chain_id:
         raise InvalidSignatureError(
             f"Chain ID mismatch: expected {expected_chain_id}, got {payment.chain_id}"
@@ -139,14 +193,54 @@ def verify_payment(
     return recovered_address
 
 
-def _get_chain_id(network: str) -> int:
-    """Get chain ID from network string."""
-    chain_ids = {
-        "ethereum": 1,
-        "base": 8453,
-        "base_sepolia": 84532,
-        "arbitrum": 42161,
-        "optimism": 10,
-        "polygon": 137,
-    }
-    return chain_ids.get(network, 0)
+async def verify_payment_async(
+    payment_header: str,
+    requirements: PaymentRequirements,
+    current_time: Optional[int] = None,
+) -> str:
+    """Verify a signed payment without blocking the event loop.
+    
+    Runs :func:`verify_payment` on the loop's default executor. The native
+    implementation releases the GIL while verifying, so concurrent
+    verifications run in parallel.
+    
+    Args and errors are as for :func:`verify_payment`.
+    """
+    loop = asyncio.get_running_loop()
+    return await loop.run_in_executor(
+        None, functools.partial(verify_payment, payment_header, requirements, current_time)
+    )
+
+
+def _lower(address: Optional[str]) -> Optional[str]:
+    return address.lower() if address is not None else None
+
+
+def _splits(splits: List[Split]) -> List[Tuple[str, int]]:
+    return [(split.recipient.lower(), split.share) for split in splits]
+
+
+def _normalize_resource(resource: str) -> str:
+    """``resource`` as the Rust core's ``normalize_resource`` compares it.
+
+    Lowercases the scheme and host, drops default ports, the fragment, and
+    trailing slashes on the path; keeps the query string as is.
+    """
+    resource = resource.strip().split("#", 1)[0]
+    resource, question, query = resource.partition("?")
+    scheme, separator, rest = resource.partition("://")
+    if separator:
+        scheme = scheme.lower()
+        slash = rest.find("/")
+        authority, path = (rest, "") if slash < 0 else (rest[:slash], rest[slash:])
+        authority = authority.lower()
+        default_port = {"http": ":80", "https": ":443"}.get(scheme)
+        if default_port is not None and authority.endswith(default_port):
+            authority = authority[: -len(default_port)]
+        origin = f"{scheme}://{authority}"
+    else:
+        origin, path = "", resource
+    path = path.rstrip("/")
+    if not path and not origin and resource.startswith("/"):
+        path = "/"
+    return f"{origin}{path}?{query}" if question else f"{origin}{path}"
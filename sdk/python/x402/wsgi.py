@@ -0,0 +1,164 @@
+"""WSGI middleware for Flask, Django and other WSGI frameworks.
+
+Wraps a WSGI application and requires payment for the paths a pricing
+callable returns requirements for::
+
+    def pricing(path):
+        if path.startswith("/premium"):
+            return PaymentRequirements(..., resource=path)
+        return None
+
+    app.wsgi_app = X402WSGIMiddleware(app.wsgi_app, pricing)       # Flask
+    application = X402WSGIMiddleware(get_wsgi_application(), pricing)  # Django
+
+Unpaid or rejected requests get a 402 carrying the encoded requirements,
+with a paywall page as the body for browsers that ask for HTML (see
+``x402.paywall``). Paid requests reach the application with the verified payer address in
+``environ["x402.payer"]`` (``request.environ`` in Flask, ``request.META``
+in Django). Pass a ``FreeTier`` to let clients make a few unpaid requests
+first, a ``CorsPolicy`` to serve browser clients on other origins, and a
+``RecipientResolver`` to pay a different tenant per path (see
+``x402.tenant``); the tenant is in ``environ["x402.tenant"]``.
+
+A payment whose nonce was already accepted is refused (see
+``x402.replay``); pass a shared ``NonceStore`` when running several worker
+processes.
+"""
+
+import json
+from typing import Any, Callable, Dict, Iterable, Optional
+
+from x402.types import PaymentRequirements
+from x402.cors import CorsPolicy
+from x402.paywall import prefers_html, render_paywall
+from x402.protocol import decode_payment_header
+from x402.quota import FreeTier
+from x402.replay import MemoryNonceStore, NonceStore, claim_payment
+from x402.server import payment_required_body, payment_required_headers
+from x402.tenant import RecipientResolver, tenant_requirements
+from x402.verify import X402VerificationError, verify_payment
+
+# Requirements for a request path, or None if it is free
+Pricing = Callable[[str], Optional[PaymentRequirements]]
+
+# WSGI environ key holding the verified payer
+PAYER_ENVIRON_KEY = "x402.payer"
+
+# WSGI environ key holding the tenant the request was routed to
+TENANT_ENVIRON_KEY = "x402.tenant"
+
+# X-Tenant as it appears in a WSGI environ
+_TENANT_ENVIRON_KEY = "HTTP_X_TENANT"
+
+# X-Payment as it appears in a WSGI environ
+_PAYMENT_ENVIRON_KEY = "HTTP_X_PAYMENT"
+
+# X-Payer-Address as it appears in a WSGI environ
+_PAYER_ADDRESS_ENVIRON_KEY = "HTTP_X_PAYER_ADDRESS"
+
+
+class X402WSGIMiddleware:
+    """WSGI middleware that requires payment for the paths ``pricing`` prices.
+    
+    Args:
+        app: The WSGI application to wrap
+        pricing: Returns the requirements for a request path, or None
+        free_tier: Unpaid requests to allow per client before charging
+        cors: Answers preflight requests and exposes the x402 headers to
+            the origins it allows
+        nonces: Nonces already accepted; a fresh ``MemoryNonceStore`` by
+            default
+        resolver: Picks the tenant paid for each priced request, by host,
+            path and ``X-Tenant``; priced paths no tenant serves get a 404
+    """
+    
+    def __init__(
+        self,
+        app: Callable[..., Iterable[bytes]],
+        pricing: Pricing,
+        free_tier: Optional[FreeTier] = None,
+        cors: Optional[CorsPolicy] = None,
+        nonces: Optional[NonceStore] = None,
+        resolver: Optional[RecipientResolver] = None,
+    ):
+        self._app = app
+        self._pricing = pricing
+        self._free_tier = free_tier
+        self._cors = cors
+        self._nonces = nonces if nonces is not None else MemoryNonceStore()
+        self._resolver = resolver
+    
+    def __call__(self, environ: Dict[str, Any], start_response: Callable[..., Any]) -> Iterable[bytes]:
+        if self._cors is None:
+            return self._handle(environ, start_response)
+        origin = environ.get("HTTP_ORIGIN")
+        if environ.get("REQUEST_METHOD") == "OPTIONS" and environ.get("HTTP_ACCESS_CONTROL_REQUEST_METHOD"):
+            start_response("204 No Content", self._cors.preflight_headers(origin) or [])
+            return []
+        cors_headers = self._cors.response_headers(origin)
+        
+        def start_response_with_cors(status, headers, *exc_info):
+            return start_response(status, [*headers, *cors_headers], *exc_info)
+        
+        return self._handle(environ, start_response_with_cors)
+    
+    def _handle(self, environ: Dict[str, Any], start_response: Callable[..., Any]) -> Iterable[bytes]:
+        path = environ.get("PATH_INFO") or "/"
+        requirements = self._pricing(path)
+        if requirements is None:
+            return self._app(environ, start_response)
+        if self._resolver is not None:
+            host = environ.get("HTTP_HOST")
+            routed = tenant_requirements(
+                self._resolver, requirements, path, host, environ.get(_TENANT_ENVIRON_KEY)
+            )
+            if routed is None:
+                body = json.dumps({"error": "no tenant serves this path"}).encode()
+                headers = [("Content-Type", "application/json"), ("Content-Length", str(len(body)))]
+                start_response("404 Not Found", headers)
+                return [body]
+            route, requirements = routed
+            environ[TENANT_ENVIRON_KEY] = route.tenant
+        
+        header = environ.get(_PAYMENT_ENVIRON_KEY)
+        if not header:
+            if self._free_tier is not None and self._free_tier.allow(
+                environ.get("REMOTE_ADDR"), environ.get(_PAYER_ADDRESS_ENVIRON_KEY)
+            ):
+                return self._app(environ, start_response)
+            return _payment_required(environ, start_response, requirements)
+        try:
+            payer = verify_payment(header, requirements)
+        except X402VerificationError as e:
+            return _payment_required(environ, start_response, requirements, str(e))
+        if not claim_payment(self._nonces, decode_payment_header(header).payment):
+            error = "payment nonce already used"
+            return _payment_required(environ, start_response, requirements, error)
+        environ[PAYER_ENVIRON_KEY] = payer
+        return self._app(environ, start_response)
+
+
+def get_payer(environ: Dict[str, Any]) -> Optional[str]:
+    """The payer verified for the request, if it was paid for."""
+    return environ.get(PAYER_ENVIRON_KEY)
+
+
+def _payment_required(
+    environ: Dict[str, Any],
+    start_response: Callable[..., Any],
+    requirements: PaymentRequirements,
+    error: Optional[str] = None,
+) -> Iterable[bytes]:
+    if prefers_html(environ.get("HTTP_ACCEPT")):
+        content_type = "text/html; charset=utf-8"
+        body = render_paywall(requirements, error).encode()
+    else:
+        content_type = "application/json"
+        body = json.dumps(payment_required_body(requirements, error)).encode()
+    headers = [
+        ("Content-Type", content_type),
+        ("Content-Length", str(len(body))),
+        *payment_required_headers(requirements).items(),
+    ]
+    start_response("402 Payment Required", headers)
+    return [body]